@@ -1,14 +1,26 @@
 import os
+import sqlite3
 
 from sqlalchemy import create_engine
 from sqlalchemy.ext.declarative import declarative_base
 from sqlalchemy.orm import sessionmaker
 
+from app.db_encryption import connect as encrypted_connect
+from app.db_encryption import database_key
+
 # Database URL - using SQLite for local development
 DATABASE_URL = os.getenv('DATABASE_URL', 'sqlite:///./daily_notes.db')
 
-# Create engine
-engine = create_engine(DATABASE_URL, connect_args={'check_same_thread': False} if 'sqlite' in DATABASE_URL else {})
+# Create engine. Desktop databases are SQLCipher-encrypted when the shell passes a key.
+_database_key = database_key()
+if _database_key and DATABASE_URL.startswith('sqlite:///'):
+    _database_path = DATABASE_URL.removeprefix('sqlite:///')
+    engine = create_engine(
+        'sqlite://',
+        creator=lambda: encrypted_connect(_database_path, _database_key, check_same_thread=False),
+    )
+else:
+    engine = create_engine(DATABASE_URL, connect_args={'check_same_thread': False} if 'sqlite' in DATABASE_URL else {})
 
 # Create session
 SessionLocal = sessionmaker(autocommit=False, autoflush=False, bind=engine)
@@ -24,3 +36,22 @@ def get_db():
         yield db
     finally:
         db.close()
+
+
+def snapshot_database(target: str) -> None:
+    """Copy the live database to `target` with SQLite's online backup API.
+
+    Safe while requests are being served. An encrypted database stays encrypted
+    with the same key.
+    """
+    if not DATABASE_URL.startswith('sqlite:///'):
+        raise ValueError('Only SQLite databases can be snapshotted')
+    raw = engine.raw_connection()
+    try:
+        dest = encrypted_connect(target, _database_key) if _database_key else sqlite3.connect(target)
+        try:
+            raw.driver_connection.backup(dest)
+        finally:
+            dest.close()
+    finally:
+        raw.close()
@@ -0,0 +1,118 @@
+"""
+SQLCipher support for the desktop database.
+
+The desktop shell keeps a random key in the OS keychain and passes it to the
+backend as DATABASE_KEY. Without it the database is plain SQLite.
+"""
+
+import os
+import re
+import sqlite3
+import sys
+import types
+from pathlib import Path
+
+KEY_ENV = 'DATABASE_KEY'
+
+# 32 random bytes, hex encoded; used as a raw key so no passphrase derivation runs
+_KEY_PATTERN = re.compile(r'^[0-9a-f]{64}$')
+
+_SQLITE_HEADER = b'SQLite format 3\x00'
+
+
+def database_key() -> str | None:
+    """The key to open the database with, or None for a plaintext database."""
+    key = os.getenv(KEY_ENV)
+    if not key:
+        return None
+    if not _KEY_PATTERN.match(key):
+        raise ValueError(f'{KEY_ENV} must be 64 lowercase hex characters')
+    return key
+
+
+def _cipher():
+    try:
+        from sqlcipher3 import dbapi2
+    except ImportError as e:
+        raise RuntimeError(f'{KEY_ENV} is set but sqlcipher3 is not installed') from e
+    return dbapi2
+
+
+def _key_literal(key: str) -> str:
+    return f'"x\'{key}\'"'
+
+
+def connect(database, key: str, *args, **kwargs):
+    """Open an encrypted database; same arguments as sqlite3.connect."""
+    conn = _cipher().connect(database, *args, **kwargs)
+    conn.execute(f'PRAGMA key = {_key_literal(key)}')
+    return conn
+
+
+def install_sqlite3_shim(key: str) -> None:
+    """Make `import sqlite3` yield SQLCipher with the key applied.
+
+    Migrations and maintenance scripts use sqlite3 directly; they are loaded
+    after this runs, so they open the encrypted database unchanged.
+    """
+    cipher = _cipher()
+    shim = types.ModuleType('sqlite3')
+    shim.__dict__.update({name: getattr(cipher, name) for name in dir(cipher) if not name.startswith('__')})
+    shim.connect = lambda database, *args, **kwargs: connect(database, key, *args, **kwargs)
+    # `from sqlite3 import dbapi2`, as SQLAlchemy does, must see the same module
+    shim.dbapi2 = shim
+    sys.modules['sqlite3'] = shim
+
+
+def is_plaintext(db_path: Path) -> bool:
+    with open(db_path, 'rb') as f:
+        return f.read(len(_SQLITE_HEADER)) == _SQLITE_HEADER
+
+
+def _schema_count(conn) -> int:
+    return conn.execute('SELECT count(*) FROM sqlite_master').fetchone()[0]
+
+
+def encrypt_in_place(db_path: Path, key: str) -> None:
+    """Encrypt a plaintext database with `key`.
+
+    The encrypted copy is written next to it and verified before it replaces
+    the original, so a failure at any point leaves the plaintext file as it was.
+    """
+    if not _KEY_PATTERN.match(key):
+        raise ValueError('Database key must be 64 lowercase hex characters')
+    if not is_plaintext(db_path):
+        raise ValueError(f'{db_path} is not a plaintext SQLite database')
+
+    encrypted_path = db_path.with_name(db_path.name + '.encrypting')
+    encrypted_path.unlink(missing_ok=True)
+
+    plain = sqlite3.connect(str(db_path))
+    try:
+        plain.execute('PRAGMA wal_checkpoint(TRUNCATE)')
+        expected_schema = _schema_count(plain)
+        user_version = plain.execute('PRAGMA user_version').fetchone()[0]
+    finally:
+        plain.close()
+
+    conn = _cipher().connect(str(db_path))
+    try:
+        conn.execute(f'ATTACH DATABASE ? AS encrypted KEY {_key_literal(key)}', (str(encrypted_path),))
+        conn.execute("SELECT sqlcipher_export('encrypted')")
+        conn.execute(f'PRAGMA encrypted.user_version = {int(user_version)}')
+        conn.execute('DETACH DATABASE encrypted')
+    finally:
+        conn.close()
+
+    check = connect(str(encrypted_path), key)
+    try:
+        if check.execute('PRAGMA integrity_check').fetchone()[0] != 'ok':
+            raise RuntimeError('Encrypted copy failed the integrity check')
+        if _schema_count(check) != expected_schema:
+            raise RuntimeError('Encrypted copy is missing tables')
+    finally:
+        check.close()
+
+    os.replace(encrypted_path, db_path)
+    for suffix in ('-wal', '-shm'):
+        db_path.with_name(db_path.name + suffix).unlink(missing_ok=True)
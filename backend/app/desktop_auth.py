@@ -0,0 +1,30 @@
+"""
+The desktop shell's per-launch token, checked on every HTTP and WebSocket request
+"""
+
+import os
+import secrets
+
+DESKTOP_TOKEN_HEADER = 'X-Desktop-Token'
+# Set by the shell on requests it proxies for the webview, which carry the token
+# but aren't the shell's own
+DESKTOP_PROXIED_HEADER = 'X-Desktop-Proxied'
+
+# Liveness only; the shell polls it before it sends anything else
+UNAUTHENTICATED_PATHS = {'/health'}
+
+
+def desktop_token_error(supplied: str | None) -> str | None:
+    """Why a request's desktop token is refused, or None when it may be served.
+
+    When the desktop shell runs the backend it passes a per-launch token in
+    DESKTOP_SHELL_TOKEN, and every request must carry it; nothing else on the
+    machine can reach the backend. Without DESKTOP_SHELL_TOKEN (Docker, dev
+    servers) requests without the header are served.
+    """
+    expected = os.getenv('DESKTOP_SHELL_TOKEN')
+    if supplied is None:
+        return 'Missing desktop token' if expected else None
+    if not (expected and secrets.compare_digest(supplied, expected)):
+        return 'Invalid desktop token'
+    return None
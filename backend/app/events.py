@@ -0,0 +1,56 @@
+"""
+In-process event hub for the desktop shell's event WebSocket.
+
+Routers publish from sync endpoints (run in the threadpool), so publishing is
+thread-safe: each subscriber's queue is fed on its own event loop.
+"""
+
+import asyncio
+import threading
+from typing import Any
+
+# Events buffered per subscriber before the oldest are dropped
+MAX_PENDING = 256
+
+
+class EventHub:
+    def __init__(self):
+        self._lock = threading.Lock()
+        self._subscribers: set[tuple[asyncio.AbstractEventLoop, asyncio.Queue]] = set()
+
+    def subscribe(self) -> asyncio.Queue:
+        """Register a queue on the running loop that receives every published event."""
+        queue: asyncio.Queue = asyncio.Queue(maxsize=MAX_PENDING)
+        with self._lock:
+            self._subscribers.add((asyncio.get_running_loop(), queue))
+        return queue
+
+    def unsubscribe(self, queue: asyncio.Queue) -> None:
+        with self._lock:
+            self._subscribers = {entry for entry in self._subscribers if entry[1] is not queue}
+
+    def publish(self, event_type: str, data: dict[str, Any]) -> None:
+        """Send `{"type": event_type, "data": data}` to every subscriber."""
+        message = {'type': event_type, 'data': data}
+        with self._lock:
+            subscribers = list(self._subscribers)
+        for loop, queue in subscribers:
+            try:
+                loop.call_soon_threadsafe(_put_latest, queue, message)
+            except RuntimeError:
+                # Loop already closed; the subscriber is going away
+                pass
+
+
+def _put_latest(queue: asyncio.Queue, message: dict[str, Any]) -> None:
+    if queue.full():
+        queue.get_nowait()
+    queue.put_nowait(message)
+
+
+hub = EventHub()
+
+
+def entry_updated(entry_id: int, action: str) -> None:
+    """Publish `entry-updated`; `action` is `created`, `updated` or `deleted`."""
+    hub.publish('entry-updated', {'id': entry_id, 'action': action})
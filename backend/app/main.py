@@ -2,18 +2,28 @@ import asyncio
 import logging
 import os
 from contextlib import asynccontextmanager
+from datetime import datetime
 
-from fastapi import FastAPI
+from fastapi import FastAPI, Request
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 
 from app.database import Base, SessionLocal, engine
-from app.models import AppSettings
+from app.desktop_auth import (
+    DESKTOP_PROXIED_HEADER,
+    DESKTOP_TOKEN_HEADER,
+    UNAUTHENTICATED_PATHS,
+    desktop_token_error,
+)
+from app.events import hub
+from app.models import AppSettings, Reminder
 from app.routers import (
     app_settings,
     background_images,
     backup,
     custom_emojis,
     entries,
+    events,
     goals,
     jupyter,
     labels,
@@ -27,6 +37,7 @@ from app.routers import (
     reports,
     search,
     search_history,
+    sync,
     uploads,
 )
 from app.services.docker_bridge import DockerBridge
@@ -136,22 +147,59 @@ async def jupyter_watchdog():
             logger.error(f'Jupyter watchdog error: {e}')
 
 
+async def reminder_events():
+    """Background task that publishes `reminder-due` once for each reminder as it comes due."""
+    announced: set[int] = set()
+    while True:
+        try:
+            await asyncio.sleep(15)
+
+            db = SessionLocal()
+            try:
+                current_time = datetime.utcnow().isoformat()
+                due = (
+                    db.query(Reminder)
+                    .filter(Reminder.is_dismissed == 0)
+                    .filter(Reminder.reminder_datetime <= current_time)
+                    .all()
+                )
+                for reminder in due:
+                    if reminder.id not in announced:
+                        hub.publish(
+                            'reminder-due',
+                            {
+                                'id': reminder.id,
+                                'entryId': reminder.entry_id,
+                                'reminderDatetime': reminder.reminder_datetime,
+                            },
+                        )
+                # Forget dismissed or rescheduled reminders so they can fire again
+                announced = {reminder.id for reminder in due}
+            finally:
+                db.close()
+
+        except Exception as e:
+            logger.error(f'Reminder events error: {e}')
+
+
 @asynccontextmanager
 async def lifespan(app: FastAPI):
     """Lifespan context manager for startup/shutdown tasks."""
     # Startup: Start background tasks
     jupyter_task = None
     mcp_task = None
+    reminder_task = None
 
     if os.getenv('TESTING') != 'true':
         jupyter_task = asyncio.create_task(jupyter_watchdog())
         mcp_task = asyncio.create_task(mcp_watchdog())
-        logger.info('Started Jupyter and MCP watchdog tasks')
+        reminder_task = asyncio.create_task(reminder_events())
+        logger.info('Started Jupyter, MCP and reminder watchdog tasks')
 
     yield
 
     # Shutdown: Cancel background tasks
-    for task, name in [(jupyter_task, 'Jupyter'), (mcp_task, 'MCP')]:
+    for task, name in [(jupyter_task, 'Jupyter'), (mcp_task, 'MCP'), (reminder_task, 'Reminder')]:
         if task:
             task.cancel()
             try:
@@ -177,9 +225,26 @@ app.add_middleware(
     allow_headers=['*'],
 )
 
+
+@app.middleware('http')
+async def check_desktop_token(request: Request, call_next):
+    """Reject requests without the desktop shell's token while the shell runs the backend.
+
+    Only the shell's own requests, not those it proxies for the webview, count
+    as coming from the shell.
+    """
+    supplied = request.headers.get(DESKTOP_TOKEN_HEADER)
+    error = None if request.url.path in UNAUTHENTICATED_PATHS else desktop_token_error(supplied)
+    if error:
+        return JSONResponse(status_code=401, content={'detail': error})
+    request.state.desktop_shell = supplied is not None and DESKTOP_PROXIED_HEADER not in request.headers
+    return await call_next(request)
+
+
 # Include routers
 app.include_router(notes.router, prefix='/api/notes', tags=['notes'])
 app.include_router(entries.router, prefix='/api/entries', tags=['entries'])
+app.include_router(events.router)
 app.include_router(uploads.router, prefix='/api/uploads', tags=['uploads'])
 app.include_router(labels.router, prefix='/api/labels', tags=['labels'])
 app.include_router(lists.router)
@@ -197,6 +262,7 @@ app.include_router(reminders.router)
 app.include_router(llm.router)
 app.include_router(mcp.router)
 app.include_router(jupyter.router)
+app.include_router(sync.router)
 
 
 @app.get('/')
@@ -1,3 +1,4 @@
+import uuid
 from datetime import datetime
 
 from sqlalchemy import Column, DateTime, ForeignKey, Integer, String, Table, Text
@@ -206,6 +207,8 @@ class NoteEntry(Base):
     is_dev_null = Column(Integer, default=0)  # 0 = false, 1 = true (marked as /dev/null - discarded)
     is_pinned = Column(Integer, default=0)  # 0 = false, 1 = true (pinned - auto-copy to next day)
     is_archived = Column(Integer, default=0)  # 0 = false, 1 = true (archived - hidden from views)
+    # Identifies the same entry on every install it is synced to
+    sync_id = Column(String, unique=True, index=True, default=lambda: uuid.uuid4().hex)
     created_at = Column(DateTime, default=datetime.utcnow)
     updated_at = Column(DateTime, default=datetime.utcnow, onupdate=datetime.utcnow)
 
@@ -238,6 +241,15 @@ class Reminder(Base):
     entry = relationship('NoteEntry', back_populates='reminder')
 
 
+class SyncTombstone(Base):
+    """Model for deleted entries, so LAN sync can delete them on other installs too"""
+
+    __tablename__ = 'sync_tombstones'
+
+    sync_id = Column(String, primary_key=True)
+    deleted_at = Column(DateTime, default=datetime.utcnow, index=True)
+
+
 class SearchHistory(Base):
     """Model for search history"""
 
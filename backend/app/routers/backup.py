@@ -5,18 +5,43 @@ import re
 from datetime import datetime
 from html import unescape
 
-from fastapi import APIRouter, Depends, File, HTTPException, UploadFile
+from fastapi import APIRouter, Depends, File, HTTPException, Request, UploadFile
 from fastapi.responses import StreamingResponse
 from sqlalchemy.orm import Session
 
-from app import models
-from app.database import get_db
-from app.storage_paths import get_upload_dir
+from app import models, schemas
+from app.database import get_db, snapshot_database
+from app.storage_paths import get_backup_dir, get_upload_dir
 
 router = APIRouter()
 UPLOAD_DIR = get_upload_dir()
 
 
+@router.post('/snapshot', response_model=schemas.DatabaseSnapshot)
+def snapshot(snapshot_request: schemas.DatabaseSnapshotRequest, request: Request):
+    """Write a consistent copy of the database for the desktop shell's scheduled backups.
+
+    Snapshots only go in the backups directory, under a new name.
+    """
+    if not getattr(request.state, 'desktop_shell', False):
+        raise HTTPException(status_code=403, detail='Only the desktop shell can take snapshots')
+
+    name = snapshot_request.name
+    if not name.endswith('.db') or os.path.basename(name) != name or '\\' in name or name.strip('.') == '':
+        raise HTTPException(status_code=400, detail='Snapshot name must be a file name ending in .db')
+    target = os.path.join(get_backup_dir(), name)
+    if os.path.exists(target):
+        raise HTTPException(status_code=400, detail='Snapshot name is already taken')
+
+    try:
+        snapshot_database(target)
+    except Exception as e:
+        if os.path.exists(target):
+            os.remove(target)
+        raise HTTPException(status_code=500, detail=f'Snapshot failed: {e}') from e
+    return schemas.DatabaseSnapshot(path=target)
+
+
 @router.get('/export')
 async def export_data(db: Session = Depends(get_db)):
     """Export all data as JSON"""
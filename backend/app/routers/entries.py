@@ -4,7 +4,7 @@ import sqlalchemy
 from fastapi import APIRouter, Depends, HTTPException
 from sqlalchemy.orm import Session, joinedload
 
-from app import models, schemas
+from app import events, models, schemas, sync
 from app.database import get_db
 
 router = APIRouter()
@@ -58,6 +58,8 @@ def copy_pinned_entries_to_date(date: str, db: Session):
                 is_important=pinned_entry.is_important,
                 is_completed=0,  # Reset completion status for new day
                 is_pinned=1,  # Keep it pinned
+                # Installs that copy the same entry forward independently agree on its sync ID
+                sync_id=sync.pinned_copy_id(date, pinned_entry.title, pinned_entry.content),
             )
             db.add(new_entry)
             db.flush()  # Flush to assign ID
@@ -128,6 +130,7 @@ def create_entry(date: str, entry: schemas.NoteEntryCreate, db: Session = Depend
     db.add(db_entry)
     db.commit()
     db.refresh(db_entry)
+    events.entry_updated(db_entry.id, 'created')
     return db_entry
 
 
@@ -150,6 +153,7 @@ def update_entry(entry_id: int, entry_update: schemas.NoteEntryUpdate, db: Sessi
     db_entry.updated_at = datetime.utcnow()
     db.commit()
     db.refresh(db_entry)
+    events.entry_updated(db_entry.id, 'updated')
     return db_entry
 
 
@@ -189,6 +193,7 @@ def delete_entry(entry_id: int, db: Session = Depends(get_db)):
     # Now delete this specific entry
     db.delete(db_entry)
     db.commit()
+    events.entry_updated(entry_id, 'deleted')
     return None
 
 
@@ -214,6 +219,7 @@ def move_entry_to_top(entry_id: int, db: Session = Depends(get_db)):
 
     db.commit()
     db.refresh(db_entry)
+    events.entry_updated(db_entry.id, 'updated')
     return db_entry
 
 
@@ -230,6 +236,7 @@ def toggle_pin(entry_id: int, db: Session = Depends(get_db)):
 
     db.commit()
     db.refresh(db_entry)
+    events.entry_updated(db_entry.id, 'updated')
     return db_entry
 
 
@@ -246,6 +253,7 @@ def toggle_archive(entry_id: int, db: Session = Depends(get_db)):
 
     db.commit()
     db.refresh(db_entry)
+    events.entry_updated(db_entry.id, 'updated')
     return db_entry
 
 
@@ -365,6 +373,7 @@ def merge_entries(merge_request: schemas.MergeEntriesRequest, db: Session = Depe
             merged_entry.labels.append(label)
 
     # Delete original entries if requested
+    deleted_ids = [entry.id for entry in entries] if merge_request.delete_originals else []
     if merge_request.delete_originals:
         for entry in entries:
             db.delete(entry)
@@ -372,4 +381,8 @@ def merge_entries(merge_request: schemas.MergeEntriesRequest, db: Session = Depe
     db.commit()
     db.refresh(merged_entry)
 
+    events.entry_updated(merged_entry.id, 'created')
+    for entry_id in deleted_ids:
+        events.entry_updated(entry_id, 'deleted')
+
     return merged_entry
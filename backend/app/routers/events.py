@@ -0,0 +1,29 @@
+"""
+WebSocket the desktop shell listens on for backend events
+"""
+
+from fastapi import APIRouter, WebSocket, WebSocketDisconnect
+
+from ..desktop_auth import DESKTOP_TOKEN_HEADER, desktop_token_error
+from ..events import hub
+
+router = APIRouter(tags=['events'])
+
+
+@router.websocket('/api/events')
+async def events(websocket: WebSocket):
+    """Stream `{"type", "data"}` messages, e.g. `entry-updated` and `reminder-due`"""
+    if desktop_token_error(websocket.headers.get(DESKTOP_TOKEN_HEADER)):
+        await websocket.close(code=1008)
+        return
+
+    await websocket.accept()
+    queue = hub.subscribe()
+    try:
+        while True:
+            await websocket.send_json(await queue.get())
+    except (WebSocketDisconnect, RuntimeError):
+        # Client went away between messages
+        pass
+    finally:
+        hub.unsubscribe(queue)
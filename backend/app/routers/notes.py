@@ -1,6 +1,7 @@
 from datetime import datetime
 
 from fastapi import APIRouter, Depends, HTTPException
+from sqlalchemy import func
 from sqlalchemy.orm import Session
 
 from app import models, schemas
@@ -66,6 +67,13 @@ def get_all_notes(skip: int = 0, limit: int = 100, db: Session = Depends(get_db)
     return result
 
 
+@router.get('/span', response_model=schemas.NoteSpan)
+def get_note_span(db: Session = Depends(get_db)):
+    """Get the dates of the first and last daily notes"""
+    first, last = db.query(func.min(models.DailyNote.date), func.max(models.DailyNote.date)).one()
+    return {'first': first, 'last': last}
+
+
 @router.get('/{date}', response_model=schemas.DailyNote)
 def get_note_by_date(date: str, db: Session = Depends(get_db)):
     """Get a specific daily note by date (YYYY-MM-DD)"""
@@ -0,0 +1,34 @@
+"""
+Change feed the desktop shell exchanges with other installs for LAN sync
+"""
+
+from datetime import datetime
+
+from fastapi import APIRouter, Depends, HTTPException, Request
+from sqlalchemy.orm import Session
+
+from app import schemas, sync
+from app.database import get_db
+
+router = APIRouter(prefix='/api/sync', tags=['sync'])
+
+
+def require_desktop_shell(request: Request):
+    if not getattr(request.state, 'desktop_shell', False):
+        raise HTTPException(status_code=403, detail='Only the desktop shell can sync')
+
+
+@router.get('/changes', response_model=schemas.SyncChanges, dependencies=[Depends(require_desktop_shell)])
+def get_changes(since: datetime | None = None, db: Session = Depends(get_db)):
+    """Entries changed or deleted after `since` (all of them when omitted)"""
+    return sync.changes_since(db, since)
+
+
+@router.post('/apply', response_model=schemas.SyncApplyResult, dependencies=[Depends(require_desktop_shell)])
+def apply_changes(changes: schemas.SyncApplyRequest, db: Session = Depends(get_db)):
+    """Apply changes fetched from another install"""
+    return sync.apply_changes(
+        db,
+        [change.model_dump() for change in changes.entries],
+        [tombstone.model_dump() for tombstone in changes.tombstones],
+    )
@@ -1,13 +1,16 @@
 import io
+import json
 import mimetypes
 import os
+import re
 import uuid
 import zipfile
 from datetime import datetime
 
-from fastapi import APIRouter, File, HTTPException, UploadFile
-from fastapi.responses import FileResponse, StreamingResponse
+from fastapi import APIRouter, File, HTTPException, Request, UploadFile
+from fastapi.responses import FileResponse, JSONResponse, StreamingResponse
 
+from app import schemas
 from app.storage_paths import get_upload_dir
 
 router = APIRouter()
@@ -15,6 +18,11 @@ router = APIRouter()
 # Directory to store uploaded files
 UPLOAD_DIR = get_upload_dir()
 
+# Subdirectory of UPLOAD_DIR holding resumable uploads until they complete
+PARTIAL_DIR_NAME = '.partial'
+
+CONTENT_RANGE = re.compile(r'bytes (\d+)-(\d+)/(\d+)')
+
 
 @router.post('/image')
 async def upload_image(file: UploadFile = File(...)):
@@ -69,6 +77,18 @@ async def upload_file(file: UploadFile = File(...)):
     }
 
 
+@router.get('/files')
+async def list_files():
+    """Name and size of every stored upload, for the desktop shell's media checks"""
+    if not UPLOAD_DIR.exists():
+        return []
+    return [
+        {'filename': path.name, 'size': path.stat().st_size}
+        for path in sorted(UPLOAD_DIR.iterdir())
+        if path.is_file()
+    ]
+
+
 @router.get('/files/{filename}')
 async def get_file(filename: str):
     """Serve an uploaded file"""
@@ -85,8 +105,8 @@ async def download_all_files():
     if not UPLOAD_DIR.exists():
         raise HTTPException(status_code=404, detail='Upload directory not found')
 
-    # Get all files recursively in the upload directory
-    files = list(UPLOAD_DIR.rglob('*'))
+    # Get all files recursively in the upload directory, without unfinished uploads
+    files = [path for path in UPLOAD_DIR.rglob('*') if PARTIAL_DIR_NAME not in path.relative_to(UPLOAD_DIR).parts]
 
     if not files:
         raise HTTPException(status_code=404, detail='No files to download')
@@ -162,3 +182,103 @@ async def restore_files(file: UploadFile = File(...)):
         raise HTTPException(status_code=400, detail='Invalid ZIP file')
     except Exception as e:
         raise HTTPException(status_code=500, detail=f'Failed to restore files: {str(e)}')
+
+
+def _partial_paths(upload_id: str):
+    """Data and metadata files of a resumable upload"""
+    try:
+        uuid.UUID(upload_id)
+    except ValueError:
+        raise HTTPException(status_code=404, detail='Upload not found')
+    partial_dir = UPLOAD_DIR / PARTIAL_DIR_NAME
+    return partial_dir / f'{upload_id}.part', partial_dir / f'{upload_id}.json'
+
+
+def _load_partial(upload_id: str):
+    data_path, meta_path = _partial_paths(upload_id)
+    if not meta_path.exists() or not data_path.exists():
+        raise HTTPException(status_code=404, detail='Upload not found')
+    meta = json.loads(meta_path.read_text())
+    return data_path, meta_path, meta
+
+
+def _status(upload_id: str, data_path, meta: dict) -> dict:
+    return {'id': upload_id, 'offset': data_path.stat().st_size, 'size': meta['size']}
+
+
+@router.post('/resumable', status_code=201, response_model=schemas.ResumableUploadStatus)
+async def create_resumable_upload(upload: schemas.ResumableUploadCreate):
+    """Start an upload sent in chunks with PUT, used by the desktop shell for large recordings"""
+    if not upload.filename:
+        raise HTTPException(status_code=400, detail='No filename provided')
+
+    upload_id = str(uuid.uuid4())
+    data_path, meta_path = _partial_paths(upload_id)
+    data_path.parent.mkdir(parents=True, exist_ok=True)
+    data_path.touch()
+    meta_path.write_text(json.dumps(upload.model_dump()))
+    return {'id': upload_id, 'offset': 0, 'size': upload.size}
+
+
+@router.get('/resumable/{upload_id}', response_model=schemas.ResumableUploadStatus)
+async def get_resumable_upload(upload_id: str):
+    """How much of an upload has arrived, to resume after an interruption"""
+    data_path, _, meta = _load_partial(upload_id)
+    return _status(upload_id, data_path, meta)
+
+
+@router.put('/resumable/{upload_id}')
+async def put_resumable_chunk(upload_id: str, request: Request):
+    """Append the chunk in the body, placed by `Content-Range: bytes <start>-<end>/<size>`.
+
+    A chunk that doesn't start at the current offset is refused with 409 and the
+    offset to continue from. The last chunk moves the file into place and returns
+    what POST /file returns.
+    """
+    data_path, meta_path, meta = _load_partial(upload_id)
+    match = CONTENT_RANGE.fullmatch(request.headers.get('content-range', '').strip())
+    if not match:
+        raise HTTPException(status_code=400, detail='Content-Range must be bytes <start>-<end>/<size>')
+    start, end, size = (int(group) for group in match.groups())
+    if size != meta['size'] or end < start or end >= size:
+        raise HTTPException(status_code=416, detail='Content-Range does not fit the upload')
+
+    offset = data_path.stat().st_size
+    if start != offset:
+        return JSONResponse(
+            status_code=409,
+            content={'detail': f'Upload is at byte {offset}', **_status(upload_id, data_path, meta)},
+        )
+
+    body = await request.body()
+    if len(body) != end - start + 1:
+        raise HTTPException(status_code=400, detail='Body length does not match Content-Range')
+    with open(data_path, 'ab') as f:
+        f.write(body)
+
+    if end + 1 < size:
+        return _status(upload_id, data_path, meta)
+
+    file_extension = os.path.splitext(meta['filename'])[1]
+    unique_filename = f'{uuid.uuid4()}{file_extension}'
+    os.replace(data_path, UPLOAD_DIR / unique_filename)
+    meta_path.unlink(missing_ok=True)
+    content_type = (
+        meta.get('content_type') or mimetypes.guess_type(meta['filename'])[0] or 'application/octet-stream'
+    )
+    return {
+        'url': f'/api/uploads/files/{unique_filename}',
+        'filename': meta['filename'],
+        'content_type': content_type,
+        'size': size,
+    }
+
+
+@router.delete('/resumable/{upload_id}', status_code=204)
+async def delete_resumable_upload(upload_id: str):
+    """Abandon an upload and drop what has arrived"""
+    data_path, meta_path = _partial_paths(upload_id)
+    if not meta_path.exists():
+        raise HTTPException(status_code=404, detail='Upload not found')
+    data_path.unlink(missing_ok=True)
+    meta_path.unlink(missing_ok=True)
@@ -204,6 +204,11 @@ class DailyNote(DailyNoteBase):
         from_attributes = True
 
 
+class NoteSpan(BaseModel):
+    first: str | None = None  # YYYY-MM-DD, None when there are no notes
+    last: str | None = None
+
+
 # Response models
 class DailyNoteWithEntries(DailyNote):
     pass
@@ -705,3 +710,72 @@ class JupyterImportResponse(BaseModel):
 class JupyterImportUrlRequest(BaseModel):
     url: str
     pyproject_url: str | None = None  # Optional URL/path to pyproject.toml for dependencies
+
+
+class DatabaseSnapshotRequest(BaseModel):
+    name: str  # File name of the new snapshot in the backups directory, ending in .db
+
+
+class DatabaseSnapshot(BaseModel):
+    path: str  # Where the snapshot was written
+
+
+# LAN sync schemas (desktop shell only)
+class SyncLabel(BaseModel):
+    name: str
+    color: str | None = None
+
+
+class SyncEntryChange(BaseModel):
+    sync_id: str
+    date: str  # YYYY-MM-DD of the entry's daily note
+    title: str = ''
+    content: str
+    content_type: str = 'rich_text'
+    order_index: int = 0
+    include_in_report: int = 0
+    is_important: int = 0
+    is_completed: int = 0
+    is_dev_null: int = 0
+    is_pinned: int = 0
+    is_archived: int = 0
+    labels: list[SyncLabel] = []
+    created_at: datetime
+    updated_at: datetime
+
+
+class SyncTombstoneChange(BaseModel):
+    sync_id: str
+    deleted_at: datetime
+
+
+class SyncChanges(BaseModel):
+    now: datetime  # Cursor for the next request
+    entries: list[SyncEntryChange]
+    tombstones: list[SyncTombstoneChange]
+    media: list[str]  # Upload file names the entries reference
+
+
+class SyncApplyRequest(BaseModel):
+    entries: list[SyncEntryChange] = []
+    tombstones: list[SyncTombstoneChange] = []
+
+
+class SyncApplyResult(BaseModel):
+    created: int
+    updated: int
+    deleted: int
+    skipped: int
+
+
+# Resumable uploads (desktop shell)
+class ResumableUploadCreate(BaseModel):
+    filename: str
+    size: int = Field(gt=0)  # Total bytes the upload will send
+    content_type: str | None = None
+
+
+class ResumableUploadStatus(BaseModel):
+    id: str
+    offset: int  # Bytes received so far; the next chunk starts here
+    size: int
@@ -30,6 +30,16 @@ def get_upload_dir() -> Path:
     return _resolve(None, 'data/uploads')
 
 
+def get_backup_dir() -> Path:
+    """Directory the desktop shell's database snapshots are written to."""
+    env_value = os.getenv('BACKUPS_DIR')
+    if env_value:
+        path = Path(os.path.expandvars(os.path.expanduser(env_value)))
+        path.mkdir(parents=True, exist_ok=True)
+        return path
+    return _resolve(None, 'data/backups')
+
+
 def get_static_dir() -> Path:
     """Directory used for background images / static exports."""
     env_value = os.getenv('STATIC_FILES_DIR')
@@ -0,0 +1,165 @@
+"""
+Entry change feed for LAN sync between desktop installs.
+
+Entries are matched across installs by `sync_id`. Deleting an entry leaves a
+tombstone so the deletion reaches the other install too. Conflicting edits go
+to the most recent `updated_at`. The desktop shell moves the changes between
+installs; the backend only reads and applies them.
+"""
+
+import re
+import uuid
+from datetime import datetime
+
+from sqlalchemy import event
+from sqlalchemy.orm import Session
+
+from app import events, models
+from app.storage_paths import get_upload_dir
+
+# Namespace for the sync IDs of pinned entries copied forward to a new day
+PINNED_COPY_NAMESPACE = uuid.UUID('5b8f3f0e-8d3c-4a4e-9a53-0c6f7e2d1b47')
+
+UPLOAD_REFERENCE = re.compile(r'/api/uploads/files/([A-Za-z0-9][A-Za-z0-9._-]*)')
+
+ENTRY_FIELDS = (
+    'title',
+    'content',
+    'content_type',
+    'order_index',
+    'include_in_report',
+    'is_important',
+    'is_completed',
+    'is_dev_null',
+    'is_pinned',
+    'is_archived',
+)
+
+
+def pinned_copy_id(date: str, title: str, content: str) -> str:
+    """Sync ID for a pinned entry's copy on `date`; copies are unique per date, title and content."""
+    return uuid.uuid5(PINNED_COPY_NAMESPACE, f'{date}\n{title}\n{content}').hex
+
+
+@event.listens_for(models.NoteEntry, 'after_delete')
+def _record_tombstone(mapper, connection, target):
+    if not target.sync_id:
+        return
+    table = models.SyncTombstone.__table__
+    connection.execute(table.delete().where(table.c.sync_id == target.sync_id))
+    connection.execute(table.insert().values(sync_id=target.sync_id, deleted_at=datetime.utcnow()))
+
+
+def _entry_change(entry: models.NoteEntry) -> dict:
+    change = {field: getattr(entry, field) for field in ENTRY_FIELDS}
+    change.update(
+        {
+            'sync_id': entry.sync_id,
+            'date': entry.daily_note.date,
+            'labels': [{'name': label.name, 'color': label.color} for label in entry.labels],
+            'created_at': entry.created_at,
+            'updated_at': entry.updated_at,
+        }
+    )
+    return change
+
+
+def changes_since(db: Session, since: datetime | None) -> dict:
+    """Entries changed and deleted after `since`, plus the uploads they reference.
+
+    `now` is the cursor to pass as `since` next time.
+    """
+    now = datetime.utcnow()
+    entry_query = db.query(models.NoteEntry).filter(models.NoteEntry.sync_id.isnot(None))
+    tombstone_query = db.query(models.SyncTombstone)
+    if since is not None:
+        entry_query = entry_query.filter(models.NoteEntry.updated_at > since)
+        tombstone_query = tombstone_query.filter(models.SyncTombstone.deleted_at > since)
+
+    entries = [_entry_change(entry) for entry in entry_query.order_by(models.NoteEntry.updated_at).all()]
+    tombstones = [
+        {'sync_id': tombstone.sync_id, 'deleted_at': tombstone.deleted_at} for tombstone in tombstone_query.all()
+    ]
+
+    upload_dir = get_upload_dir()
+    media = sorted(
+        {
+            name
+            for entry in entries
+            for name in UPLOAD_REFERENCE.findall(entry['content'] or '')
+            if (upload_dir / name).is_file()
+        }
+    )
+    return {'now': now, 'entries': entries, 'tombstones': tombstones, 'media': media}
+
+
+def _labels(db: Session, wanted: list[dict]) -> list[models.Label]:
+    labels = []
+    for item in wanted:
+        label = db.query(models.Label).filter(models.Label.name == item['name']).first()
+        if not label:
+            label = models.Label(name=item['name'], color=item.get('color') or '#3b82f6')
+            db.add(label)
+            db.flush()
+        labels.append(label)
+    return labels
+
+
+def _daily_note(db: Session, date: str) -> models.DailyNote:
+    note = db.query(models.DailyNote).filter(models.DailyNote.date == date).first()
+    if not note:
+        note = models.DailyNote(date=date)
+        db.add(note)
+        db.flush()
+    return note
+
+
+def apply_changes(db: Session, entries: list[dict], tombstones: list[dict]) -> dict:
+    """Apply another install's changes; the newer side of each entry wins."""
+    result = {'created': 0, 'updated': 0, 'deleted': 0, 'skipped': 0}
+    published: list[tuple[int, str]] = []
+
+    for change in entries:
+        deleted = db.get(models.SyncTombstone, change['sync_id'])
+        if deleted and deleted.deleted_at >= change['updated_at']:
+            result['skipped'] += 1
+            continue
+        entry = db.query(models.NoteEntry).filter(models.NoteEntry.sync_id == change['sync_id']).first()
+        if entry and entry.updated_at and entry.updated_at >= change['updated_at']:
+            result['skipped'] += 1
+            continue
+
+        action = 'updated' if entry else 'created'
+        if not entry:
+            entry = models.NoteEntry(sync_id=change['sync_id'], created_at=change['created_at'])
+            db.add(entry)
+        entry.daily_note = _daily_note(db, change['date'])
+        for field in ENTRY_FIELDS:
+            setattr(entry, field, change[field])
+        entry.labels = _labels(db, change['labels'])
+        # Keep the other install's timestamp, so the change isn't sent back as a newer one
+        entry.updated_at = change['updated_at']
+        if deleted:
+            db.delete(deleted)
+        db.flush()
+        result[action] += 1
+        published.append((entry.id, action))
+
+    for tombstone in tombstones:
+        entry = db.query(models.NoteEntry).filter(models.NoteEntry.sync_id == tombstone['sync_id']).first()
+        if entry and entry.updated_at and entry.updated_at > tombstone['deleted_at']:
+            # Edited here after the other install deleted it; the edit wins
+            result['skipped'] += 1
+            continue
+        if entry:
+            published.append((entry.id, 'deleted'))
+            db.delete(entry)
+            result['deleted'] += 1
+        elif not db.get(models.SyncTombstone, tombstone['sync_id']):
+            db.add(models.SyncTombstone(sync_id=tombstone['sync_id'], deleted_at=tombstone['deleted_at']))
+        db.flush()
+
+    db.commit()
+    for entry_id, action in published:
+        events.entry_updated(entry_id, action)
+    return result
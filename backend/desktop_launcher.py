@@ -10,6 +10,9 @@ with PyInstaller. It is responsible for:
    a desktop-only SQLite file so Docker deployments remain untouched.
 3. Running database initialization + migrations.
 4. Starting uvicorn bound to the requested loopback host/port.
+
+Run with `--encrypt-database` it instead encrypts the existing database with
+the SQLCipher key in TAURI_DATABASE_KEY and exits.
 """
 
 from __future__ import annotations
@@ -94,14 +97,55 @@ def _prepare_environment() -> tuple[str, str]:
     resolved_static.mkdir(parents=True, exist_ok=True)
     os.environ["STATIC_FILES_DIR"] = str(resolved_static)
 
+    # Database snapshots for the shell's backups are only written here
+    os.environ.setdefault("BACKUPS_DIR", str(data_path / "backups"))
+
+    # Per-launch secret the shell sends with requests it proxies for the webview
+    token = os.getenv("TAURI_BACKEND_TOKEN")
+    if token:
+        os.environ["DESKTOP_SHELL_TOKEN"] = token
+
+    # SQLCipher key from the OS keychain, only passed once the database is encrypted
+    database_key = os.getenv("TAURI_DATABASE_KEY")
+    if database_key:
+        os.environ["DATABASE_KEY"] = database_key
+
     return host, port
 
 
+def _encrypt_database() -> int:
+    """Encrypt the plaintext database with TAURI_DATABASE_KEY, then exit."""
+    from app.db_encryption import encrypt_in_place  # noqa: WPS433
+
+    key = os.getenv("TAURI_DATABASE_KEY")
+    if not key:
+        logging.error("--encrypt-database needs TAURI_DATABASE_KEY")
+        return 1
+    db_path = Path(os.environ["DATABASE_URL"].removeprefix("sqlite:///"))
+    logging.info("Encrypting %s", db_path)
+    try:
+        encrypt_in_place(db_path, key)
+    except Exception as e:
+        logging.error("Database encryption failed: %s", e, exc_info=True)
+        return 1
+    logging.info("Database encrypted")
+    return 0
+
+
 def main() -> None:
     _configure_logging(os.getenv("TAURI_BACKEND_LOG"))
 
     host, port = _prepare_environment()
 
+    if "--encrypt-database" in sys.argv[1:]:
+        sys.exit(_encrypt_database())
+
+    if os.getenv("DATABASE_KEY"):
+        from app.db_encryption import install_sqlite3_shim  # noqa: WPS433
+
+        # Migrations open the database with sqlite3 directly
+        install_sqlite3_shim(os.environ["DATABASE_KEY"])
+
     # Import database-dependent modules only after DATABASE_URL is set
     from app.db_init import ensure_database  # noqa: WPS433
     from migrations import run_migrations  # noqa: WPS433
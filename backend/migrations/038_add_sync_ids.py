@@ -0,0 +1,74 @@
+"""
+Migration 038: Add LAN sync identifiers
+
+Adds a sync_id column to note_entries, filled with a random ID for every
+existing entry, so the same entry can be matched across desktop installs.
+Creates sync_tombstones to remember deleted entries for sync.
+"""
+
+import sqlite3
+import uuid
+
+
+def migrate_up(db_path: str) -> bool:
+    """Add sync_id to note_entries and create sync_tombstones."""
+    conn = sqlite3.connect(db_path)
+    cursor = conn.cursor()
+
+    try:
+        cursor.execute('PRAGMA table_info(note_entries)')
+        columns = {row[1] for row in cursor.fetchall()}
+
+        if 'sync_id' not in columns:
+            cursor.execute("""
+                ALTER TABLE note_entries
+                ADD COLUMN sync_id VARCHAR
+            """)
+            print("Added sync_id column to note_entries")
+
+        cursor.execute('SELECT id FROM note_entries WHERE sync_id IS NULL')
+        missing = [row[0] for row in cursor.fetchall()]
+        cursor.executemany(
+            'UPDATE note_entries SET sync_id = ? WHERE id = ?',
+            [(uuid.uuid4().hex, entry_id) for entry_id in missing],
+        )
+        if missing:
+            print(f"Assigned sync IDs to {len(missing)} entries")
+
+        cursor.execute("""
+            CREATE UNIQUE INDEX IF NOT EXISTS ix_note_entries_sync_id
+            ON note_entries (sync_id)
+        """)
+
+        cursor.execute("""
+            CREATE TABLE IF NOT EXISTS sync_tombstones (
+                sync_id VARCHAR NOT NULL PRIMARY KEY,
+                deleted_at DATETIME
+            )
+        """)
+        cursor.execute("""
+            CREATE INDEX IF NOT EXISTS ix_sync_tombstones_deleted_at
+            ON sync_tombstones (deleted_at)
+        """)
+
+        conn.commit()
+        return True
+    except Exception as e:
+        print(f'Migration failed: {e}')
+        conn.rollback()
+        return False
+    finally:
+        conn.close()
+
+
+def migrate_down(db_path: str) -> None:
+    """Rollback migration."""
+    conn = sqlite3.connect(db_path)
+    try:
+        conn.execute('DROP INDEX IF EXISTS ix_note_entries_sync_id')
+        conn.execute('DROP TABLE IF EXISTS sync_tombstones')
+        conn.commit()
+    finally:
+        conn.close()
+    print('Rollback: sync_id column will remain')
+    print('SQLite does not support DROP COLUMN easily')
@@ -36,12 +36,16 @@ a = Analysis(
         "app",
         "app.main",
         "app.database",
+        "app.db_encryption",
         "app.models",
         "app.schemas",
+        "app.events",
+        "app.sync",
         "app.db_init",
         "app.routers",
         "app.routers.backup",
         "app.routers.entries",
+        "app.routers.events",
         "app.routers.goals",
         "app.routers.labels",
         "app.routers.link_preview",
@@ -54,6 +58,7 @@ a = Analysis(
         "app.routers.search",
         "app.routers.search_history",
         "app.routers.settings",
+        "app.routers.sync",
         "app.routers.uploads",
         "app.routers.app_settings",
         "app.routers.background_images",
@@ -61,6 +66,9 @@ a = Analysis(
         "app.services",
         "app.services.docker_bridge",
         "app.storage_paths",
+        # SQLCipher for encrypted desktop databases
+        "sqlcipher3",
+        "sqlcipher3.dbapi2",
         # Docker SDK for MCP container management
         "docker",
         "docker.api",
@@ -22,6 +22,12 @@ if ! command -v pyinstaller >/dev/null 2>&1; then
   exit 1
 fi
 
+# Encrypted databases are opened through SQLCipher, which must be bundled
+if ! python3 -c "import sqlcipher3" >/dev/null 2>&1; then
+  echo "✗ sqlcipher3 is not installed. Install it with 'pip install sqlcipher3-wheels'." >&2
+  exit 1
+fi
+
 PLATFORM="macos"
 case "$(uname -s)" in
   Linux*) PLATFORM="linux" ;;
@@ -39,6 +45,17 @@ pyinstaller \
   --distpath "${DIST_DIR}" \
   --workpath "${WORK_DIR}"
 
+# The shell checks the binary against this before launching it
+BINARY="${DIST_DIR}/track-the-thing-backend/track-the-thing-backend"
+if [[ "${PLATFORM}" == "windows" ]]; then
+  BINARY="${BINARY}.exe"
+fi
+if command -v sha256sum >/dev/null 2>&1; then
+  sha256sum "${BINARY}" | cut -d' ' -f1 > "${BINARY}.sha256"
+else
+  shasum -a 256 "${BINARY}" | cut -d' ' -f1 > "${BINARY}.sha256"
+fi
+
 echo "✓ Backend sidecar built."
 echo "  Output directory: ${DIST_DIR}/track-the-thing-backend"
 
@@ -1,17 +1,85 @@
 fn main() {
     tauri_build::build();
-    
-    #[cfg(target_os = "macos")]
-    {
+
+    // cfg!(target_os) in a build script is the host; iOS is cross-compiled on macOS
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+
+    if target_os == "macos" || target_os == "ios" {
         // Compile Objective-C bridge for speech recognition
         cc::Build::new()
             .file("src/speech_bridge.m")
             .flag("-fobjc-arc")
             .compile("speech_bridge");
-        
-        // Link required macOS frameworks for A/V functionality
+
         println!("cargo:rustc-link-lib=framework=Speech");
         println!("cargo:rustc-link-lib=framework=AVFoundation");
         println!("cargo:rustc-link-lib=framework=Foundation");
     }
+
+    if target_os == "macos" {
+        // Compile Objective-C services provider ("Add to Track The Thing")
+        cc::Build::new()
+            .file("src/services_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("services_bridge");
+
+        // Compile Objective-C frontmost app/window lookup for activity tracking
+        cc::Build::new()
+            .file("src/activity_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("activity_bridge");
+
+        // Compile Objective-C Touch ID prompt for the app lock
+        cc::Build::new()
+            .file("src/lock_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("lock_bridge");
+
+        // Compile Objective-C EventKit bridge for calendar integration
+        cc::Build::new()
+            .file("src/calendar_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("calendar_bridge");
+
+        // Compile Objective-C CoreSpotlight indexer for system search
+        cc::Build::new()
+            .file("src/spotlight_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("spotlight_bridge");
+
+        // Compile Objective-C display/window listing for capture targets
+        cc::Build::new()
+            .file("src/capture_targets_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("capture_targets_bridge");
+
+        // Compile Objective-C ScreenCaptureKit recorder for screen recordings
+        cc::Build::new()
+            .file("src/screen_recorder_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("screen_recorder_bridge");
+
+        // Compile Objective-C camera/microphone hot-plug watcher
+        cc::Build::new()
+            .file("src/devices_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("devices_bridge");
+
+        // Compile Objective-C dock menu for quick actions
+        cc::Build::new()
+            .file("src/quick_actions_bridge.m")
+            .flag("-fobjc-arc")
+            .compile("quick_actions_bridge");
+        
+        // Link required macOS frameworks for the bridges above
+        println!("cargo:rustc-link-lib=framework=AppKit");
+        println!("cargo:rustc-link-lib=framework=CoreGraphics");
+        println!("cargo:rustc-link-lib=framework=LocalAuthentication");
+        println!("cargo:rustc-link-lib=framework=EventKit");
+        println!("cargo:rustc-link-lib=framework=CoreSpotlight");
+        println!("cargo:rustc-link-lib=framework=UniformTypeIdentifiers");
+        println!("cargo:rustc-link-lib=framework=CoreMedia");
+        // ScreenCaptureKit is macOS 13+; weak so the app still starts on older systems
+        println!("cargo:rustc-link-arg=-Wl,-weak_framework,ScreenCaptureKit");
+    }
 }
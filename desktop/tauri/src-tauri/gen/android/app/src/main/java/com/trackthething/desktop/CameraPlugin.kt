@@ -0,0 +1,56 @@
+package com.trackthething.desktop
+
+import android.app.Activity
+import android.content.Intent
+import android.provider.MediaStore
+import androidx.activity.result.ActivityResult
+import androidx.core.content.FileProvider
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.File
+
+/**
+ * Photo capture through the system camera app, called from
+ * src/mobile_camera.rs. The photo is written to the cache dir, which the
+ * template's FileProvider (`${applicationId}.fileprovider`) exposes, and the
+ * Rust side moves it into the app's media dir.
+ */
+@TauriPlugin
+class CameraPlugin(private val activity: Activity) : Plugin(activity) {
+    private var pending: File? = null
+
+    @Command
+    fun capturePhoto(invoke: Invoke) {
+        val dir = File(activity.cacheDir, "camera").apply { mkdirs() }
+        val file = File(dir, "capture_${System.currentTimeMillis()}.jpg")
+        val uri = FileProvider.getUriForFile(activity, "${activity.packageName}.fileprovider", file)
+        val intent = Intent(MediaStore.ACTION_IMAGE_CAPTURE).apply {
+            putExtra(MediaStore.EXTRA_OUTPUT, uri)
+            addFlags(Intent.FLAG_GRANT_WRITE_URI_PERMISSION)
+        }
+        if (intent.resolveActivity(activity.packageManager) == null) {
+            invoke.reject("No camera app is available")
+            return
+        }
+        pending = file
+        startActivityForResult(invoke, intent, "onPhotoCaptured")
+    }
+
+    @ActivityCallback
+    fun onPhotoCaptured(invoke: Invoke, result: ActivityResult) {
+        val file = pending
+        pending = null
+        if (result.resultCode != Activity.RESULT_OK || file == null || !file.exists() || file.length() == 0L) {
+            file?.delete()
+            invoke.reject("Photo capture was cancelled")
+            return
+        }
+        val response = JSObject()
+        response.put("path", file.absolutePath)
+        invoke.resolve(response)
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::time::sleep;
+
+use crate::idle::IdleMonitor;
+use crate::permissions::{self, ACTIVITY};
+use crate::privacy_guard;
+use crate::settings::SettingsState;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+const LOG_FILE: &str = "activity_log.jsonl";
+const PREFS_FILE: &str = "activity_prefs.json";
+
+// Spans kept in memory and on disk; older ones are dropped
+const MAX_SPANS: usize = 5000;
+
+/// Time spent continuously in one app/window
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySpan {
+    pub app: String,
+    pub title: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ActivityPrefs {
+    enabled: bool,
+}
+
+/// Opt-in sampler of the frontmost application and window title
+pub struct ActivityTracker {
+    enabled: AtomicBool,
+    spans: Mutex<VecDeque<ActivitySpan>>,
+    current: Mutex<Option<ActivitySpan>>,
+    log_path: Option<PathBuf>,
+    prefs_path: Option<PathBuf>,
+}
+
+/// Name of the frontmost app and the title of its window
+#[cfg(target_os = "macos")]
+pub(crate) fn frontmost() -> Option<(String, Option<String>)> {
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    extern "C" {
+        fn activity_frontmost_app_name() -> *mut c_char;
+        fn activity_frontmost_window_title() -> *mut c_char;
+        fn activity_free_string(value: *mut c_char);
+    }
+
+    unsafe fn take(ptr: *mut c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        let value = CStr::from_ptr(ptr).to_string_lossy().to_string();
+        activity_free_string(ptr);
+        Some(value)
+    }
+
+    unsafe {
+        let app = take(activity_frontmost_app_name())?;
+        let title = take(activity_frontmost_window_title());
+        Some((app, title))
+    }
+}
+
+/// Address of the page shown by `browser`, the frontmost app's name, when
+/// it is a browser that can be asked for it
+#[cfg(target_os = "macos")]
+pub(crate) fn browser_url(browser: &str) -> Option<String> {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+
+    extern "C" {
+        fn activity_browser_url(app_name: *const c_char) -> *mut c_char;
+        fn activity_free_string(value: *mut c_char);
+    }
+
+    let name = CString::new(browser).ok()?;
+    unsafe {
+        let ptr = activity_browser_url(name.as_ptr());
+        if ptr.is_null() {
+            return None;
+        }
+        let url = CStr::from_ptr(ptr).to_string_lossy().to_string();
+        activity_free_string(ptr);
+        Some(url)
+    }
+}
+
+/// Browsers elsewhere don't expose their address bar without an extension
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn browser_url(_browser: &str) -> Option<String> {
+    None
+}
+
+/// File stem of the executable behind a window, e.g. `chrome`, and the window's title
+#[cfg(target_os = "windows")]
+pub(crate) fn window_app_and_title(hwnd: isize) -> (String, Option<String>) {
+    use std::path::Path;
+
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetWindowTextW(hwnd: isize, buffer: *mut u16, max_count: i32) -> i32;
+        fn GetWindowThreadProcessId(hwnd: isize, process_id: *mut u32) -> u32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(desired_access: u32, inherit_handle: i32, process_id: u32) -> isize;
+        fn QueryFullProcessImageNameW(process: isize, flags: u32, buffer: *mut u16, size: *mut u32) -> i32;
+        fn CloseHandle(handle: isize) -> i32;
+    }
+
+    unsafe {
+        let mut title_buf = [0u16; 512];
+        let title_len = GetWindowTextW(hwnd, title_buf.as_mut_ptr(), title_buf.len() as i32);
+        let title = (title_len > 0).then(|| String::from_utf16_lossy(&title_buf[..title_len as usize]));
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process == 0 {
+            return ("Unknown".to_string(), title);
+        }
+        let mut path_buf = [0u16; 1024];
+        let mut path_len = path_buf.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, 0, path_buf.as_mut_ptr(), &mut path_len);
+        CloseHandle(process);
+
+        let app = if ok != 0 {
+            let path = String::from_utf16_lossy(&path_buf[..path_len as usize]);
+            Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or(path)
+        } else {
+            "Unknown".to_string()
+        };
+        (app, title)
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn frontmost() -> Option<(String, Option<String>)> {
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> isize;
+    }
+
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd == 0 {
+        return None;
+    }
+    Some(window_app_and_title(hwnd))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn frontmost() -> Option<(String, Option<String>)> {
+    use std::os::raw::c_ulong;
+    use x11_dl::xlib::{Window, XA_WINDOW};
+
+    use crate::x11_session::{property, window_class, window_title};
+
+    crate::x11_session::with_display(|xlib, display| unsafe {
+        let root = (xlib.XDefaultRootWindow)(display);
+        let (bytes, format, count) = property(xlib, display, root, c"_NET_ACTIVE_WINDOW", XA_WINDOW)?;
+        if format != 32 || count == 0 {
+            return None;
+        }
+        let window = std::ptr::read_unaligned(bytes.as_ptr() as *const c_ulong) as Window;
+        if window == 0 {
+            return None;
+        }
+
+        let title = window_title(xlib, display, window);
+        let app = window_class(xlib, display, window).unwrap_or_else(|| "Unknown".to_string());
+        Some((app, title))
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub(crate) fn frontmost() -> Option<(String, Option<String>)> {
+    None
+}
+
+impl ActivityTracker {
+    fn load(app: &AppHandle) -> Self {
+        let data_dir = app.path().app_data_dir().ok();
+        let log_path = data_dir.as_ref().map(|dir| dir.join(LOG_FILE));
+        let prefs_path = app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE));
+
+        let prefs: ActivityPrefs = prefs_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        let mut spans: VecDeque<ActivitySpan> = log_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Trim the on-disk log back to the retention limit
+        if spans.len() > MAX_SPANS {
+            let excess = spans.len() - MAX_SPANS;
+            spans.drain(..excess);
+            if let Some(path) = &log_path {
+                let content: String = spans
+                    .iter()
+                    .filter_map(|span| serde_json::to_string(span).ok())
+                    .map(|line| line + "\n")
+                    .collect();
+                if let Err(e) = fs::write(path, content) {
+                    warn!("Failed to trim activity log: {}", e);
+                }
+            }
+        }
+
+        info!(
+            "Activity tracking {} ({} span(s) loaded)",
+            if prefs.enabled { "enabled" } else { "disabled" },
+            spans.len()
+        );
+
+        Self {
+            enabled: AtomicBool::new(prefs.enabled),
+            spans: Mutex::new(spans),
+            current: Mutex::new(None),
+            log_path,
+            prefs_path,
+        }
+    }
+
+    fn append_to_log(&self, span: &ActivitySpan) {
+        let Some(path) = &self.log_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let result = serde_json::to_string(span)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Failed to append to activity log: {}", e);
+        }
+    }
+
+    /// Move the in-progress span into the finished log
+    fn close_current(&self) {
+        let Some(span) = self.current.lock().ok().and_then(|mut current| current.take()) else {
+            return;
+        };
+        self.append_to_log(&span);
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.push_back(span);
+            while spans.len() > MAX_SPANS {
+                spans.pop_front();
+            }
+        }
+    }
+
+    /// Extend the current span or start a new one. Returns the new span when the
+    /// frontmost app/window changed.
+    fn record(&self, app: String, title: Option<String>, now: DateTime<Utc>) -> Option<ActivitySpan> {
+        {
+            let mut current = self.current.lock().ok()?;
+            if let Some(span) = current.as_mut() {
+                if span.app == app && span.title == title {
+                    span.end = now;
+                    return None;
+                }
+            }
+        }
+
+        self.close_current();
+        let span = ActivitySpan {
+            app,
+            title,
+            start: now,
+            end: now,
+        };
+        *self.current.lock().ok()? = Some(span.clone());
+        Some(span)
+    }
+
+    fn save_prefs(&self, enabled: bool) -> Result<(), String> {
+        let path = self.prefs_path.as_ref().ok_or("Failed to resolve app config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&ActivityPrefs { enabled })
+            .map_err(|e| format!("Failed to serialize activity preferences: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write activity preferences: {}", e))
+    }
+}
+
+/// Start the sampler. Nothing is recorded until tracking is enabled.
+pub fn init_activity_tracker(app: &AppHandle) {
+    app.manage(ActivityTracker::load(app));
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(SAMPLE_INTERVAL).await;
+
+            let tracker = app.state::<ActivityTracker>();
+            if !tracker.enabled.load(Ordering::Relaxed) {
+                continue;
+            }
+            // Revoking the feature permission stops recording without turning tracking off
+            if !permissions::is_enabled(&app, ACTIVITY) {
+                tracker.close_current();
+                continue;
+            }
+            // Paused by the battery saver, like time away
+            if crate::power::saving() {
+                tracker.close_current();
+                continue;
+            }
+
+            // Time away from the keyboard isn't time spent in the frontmost app
+            let user_idle = app
+                .try_state::<IdleMonitor>()
+                .map(|monitor| monitor.is_idle())
+                .unwrap_or(false);
+            if user_idle {
+                tracker.close_current();
+                continue;
+            }
+
+            let guard = app.state::<SettingsState>().current().privacy.guard;
+            let sampled = async_runtime::spawn_blocking(move || {
+                let sample = frontmost();
+                let name = sample.as_ref().map(|(name, _)| name.as_str());
+                let blocked = privacy_guard::blocking_reason(&guard, name);
+                (sample, blocked)
+            })
+            .await;
+            let Ok((sample, blocked)) = sampled else {
+                continue;
+            };
+            // Nothing is recorded while the privacy guard blocks captures
+            let suspended = blocked.is_some();
+            privacy_guard::set_activity_suspended(&app, blocked);
+            if suspended {
+                tracker.close_current();
+                continue;
+            }
+            let Some((name, title)) = sample else {
+                continue;
+            };
+            if let Some(span) = tracker.record(name, title, Utc::now()) {
+                if let Err(e) = app.emit("activity-changed", &span) {
+                    warn!("Failed to emit activity-changed event: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn set_activity_tracking(
+    app: AppHandle,
+    tracker: State<'_, ActivityTracker>,
+    enabled: bool,
+) -> Result<bool, String> {
+    if enabled {
+        permissions::require(&app, ACTIVITY)?;
+    }
+    tracker.save_prefs(enabled)?;
+    tracker.enabled.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        tracker.close_current();
+    }
+    info!("Activity tracking {}", if enabled { "enabled" } else { "disabled" });
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub async fn get_activity_tracking(tracker: State<'_, ActivityTracker>) -> Result<bool, String> {
+    Ok(tracker.enabled.load(Ordering::Relaxed))
+}
+
+/// Spans overlapping `from`..`to` (either bound optional), including the one in progress
+#[tauri::command]
+pub async fn get_activity_log(
+    tracker: State<'_, ActivityTracker>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<ActivitySpan>, String> {
+    let overlaps = |span: &ActivitySpan| {
+        from.map_or(true, |from| span.end >= from) && to.map_or(true, |to| span.start <= to)
+    };
+
+    let spans = tracker.spans.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut result: Vec<ActivitySpan> = spans.iter().filter(|span| overlaps(span)).cloned().collect();
+    drop(spans);
+
+    let current = tracker.current.lock().map_err(|e| format!("Lock error: {}", e))?;
+    if let Some(span) = current.as_ref().filter(|span| overlaps(span)) {
+        result.push(span.clone());
+    }
+    Ok(result)
+}
@@ -0,0 +1,97 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+#import <CoreGraphics/CoreGraphics.h>
+
+// Returned strings are heap-allocated; release them with activity_free_string
+
+static char *copyUTF8(NSString *value) {
+    if (value == nil || value.length == 0) {
+        return NULL;
+    }
+    return strdup([value UTF8String]);
+}
+
+// Localized name of the frontmost application
+char *activity_frontmost_app_name(void) {
+    @autoreleasepool {
+        NSRunningApplication *app = [[NSWorkspace sharedWorkspace] frontmostApplication];
+        return copyUTF8(app.localizedName);
+    }
+}
+
+// Title of the frontmost application's topmost on-screen window.
+// macOS 10.15+ only reports titles once Screen Recording permission is granted.
+char *activity_frontmost_window_title(void) {
+    @autoreleasepool {
+        NSRunningApplication *app = [[NSWorkspace sharedWorkspace] frontmostApplication];
+        if (app == nil) {
+            return NULL;
+        }
+        pid_t pid = app.processIdentifier;
+        
+        CFArrayRef windows = CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements,
+                                                        kCGNullWindowID);
+        if (windows == NULL) {
+            return NULL;
+        }
+        
+        char *title = NULL;
+        NSArray *windowList = CFBridgingRelease(windows);
+        for (NSDictionary *window in windowList) {
+            NSNumber *ownerPid = window[(__bridge NSString *)kCGWindowOwnerPID];
+            NSNumber *layer = window[(__bridge NSString *)kCGWindowLayer];
+            if (ownerPid.intValue != pid || layer.intValue != 0) {
+                continue;
+            }
+            NSString *name = window[(__bridge NSString *)kCGWindowName];
+            if (name.length > 0) {
+                title = copyUTF8(name);
+                break;
+            }
+        }
+        return title;
+    }
+}
+
+// Address of the page in the front window of browser `app_name`, asked over
+// Apple Events. Safari and Chromium-based browsers answer; others (Firefox)
+// aren't scriptable. macOS asks the user to allow automating each browser.
+char *activity_browser_url(const char *app_name) {
+    @autoreleasepool {
+        NSString *name = [NSString stringWithUTF8String:app_name];
+        NSString *query;
+        if ([name hasPrefix:@"Safari"]) {
+            query = @"URL of front document";
+        } else if ([@[@"Google Chrome", @"Chromium", @"Brave Browser", @"Microsoft Edge", @"Arc", @"Vivaldi"]
+                       containsObject:name]) {
+            query = @"URL of active tab of front window";
+        } else {
+            return NULL;
+        }
+        NSString *source = [NSString stringWithFormat:@"tell application \"%@\" to return %@", name, query];
+        NSAppleScript *script = [[NSAppleScript alloc] initWithSource:source];
+        NSDictionary *error = nil;
+        NSAppleEventDescriptor *result = [script executeAndReturnError:&error];
+        if (result == nil) {
+            return NULL;
+        }
+        return copyUTF8(result.stringValue);
+    }
+}
+
+// Whether the screen is being shared or remotely controlled (Screen Sharing,
+// Apple Remote Desktop). Doesn't see sharing inside conferencing apps.
+bool activity_screen_is_shared(void) {
+    @autoreleasepool {
+        CFDictionaryRef session = CGSessionCopyCurrentDictionary();
+        if (session == NULL) {
+            return false;
+        }
+        NSDictionary *info = CFBridgingRelease(session);
+        return [info[@"CGSSessionScreenIsShared"] boolValue];
+    }
+}
+
+void activity_free_string(char *value) {
+    free(value);
+}
@@ -0,0 +1,465 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::http::{Response, StatusCode};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::time::sleep;
+
+use crate::error::{ErrorCode, ShellError};
+use crate::idle::IdleMonitor;
+
+const PREFS_FILE: &str = "app_lock.json";
+
+// How often idle time is compared against the lock timeout
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+const DEFAULT_LOCK_AFTER_SECS: u64 = 600;
+const MIN_LOCK_AFTER_SECS: u64 = 60;
+
+// Wrong passwords allowed before unlocking is refused for a while. Each
+// further one doubles the wait, up to MAX_LOCKOUT, to slow down guessing
+// from a script in the webview.
+const FREE_ATTEMPTS: u32 = 5;
+const FIRST_LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Commands the lock screen needs; every other command is refused while locked
+const ALLOWED_WHILE_LOCKED: &[&str] = &["get_app_lock_status", "lock_app", "unlock_app"];
+
+// Shown by the OS as "<app> is trying to <reason>"
+const UNLOCK_REASON: &str = "unlock your tracked items";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LockPrefs {
+    enabled: bool,
+    lock_after_secs: u64,
+    /// Argon2 PHC string for the fallback password
+    password_hash: Option<String>,
+}
+
+impl Default for LockPrefs {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lock_after_secs: DEFAULT_LOCK_AFTER_SECS,
+            password_hash: None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub lock_after_secs: u64,
+    pub has_password: bool,
+    /// Touch ID / Windows Hello can be used instead of the password
+    pub biometrics_available: bool,
+}
+
+/// Wrong passwords since the last unlock, shared by all `unlock_app` calls
+#[derive(Default)]
+struct FailedAttempts {
+    count: u32,
+    retry_after: Option<Instant>,
+}
+
+impl FailedAttempts {
+    fn record(&mut self) {
+        self.count += 1;
+        if let Some(extra) = self.count.checked_sub(FREE_ATTEMPTS) {
+            let lockout = FIRST_LOCKOUT.saturating_mul(2u32.saturating_pow(extra)).min(MAX_LOCKOUT);
+            self.retry_after = Some(Instant::now() + lockout);
+        }
+    }
+}
+
+/// Lock state machine. The webview only renders the lock screen; locking,
+/// unlocking and credential checks all happen here.
+pub struct AppLock {
+    prefs: Mutex<LockPrefs>,
+    locked: AtomicBool,
+    prompting: AtomicBool,
+    // Held across a password check, so parallel calls can't skip the lockout
+    failed: tokio::sync::Mutex<FailedAttempts>,
+    prefs_path: Option<PathBuf>,
+}
+
+#[cfg(target_os = "macos")]
+fn biometrics_available() -> bool {
+    extern "C" {
+        fn lock_biometrics_available() -> bool;
+    }
+    unsafe { lock_biometrics_available() }
+}
+
+#[cfg(target_os = "macos")]
+fn authenticate_native(reason: &str) -> Result<(), String> {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+
+    extern "C" {
+        fn lock_authenticate(reason: *const c_char, error_out: *mut *mut c_char) -> bool;
+        fn lock_free_string(value: *mut c_char);
+    }
+
+    let reason = CString::new(reason).map_err(|e| format!("Invalid unlock reason: {}", e))?;
+    let mut error: *mut c_char = std::ptr::null_mut();
+    if unsafe { lock_authenticate(reason.as_ptr(), &mut error) } {
+        return Ok(());
+    }
+    let message = if error.is_null() {
+        "Touch ID authentication failed".to_string()
+    } else {
+        unsafe {
+            let message = CStr::from_ptr(error).to_string_lossy().to_string();
+            lock_free_string(error);
+            message
+        }
+    };
+    Err(message)
+}
+
+#[cfg(target_os = "windows")]
+fn biometrics_available() -> bool {
+    use windows::Security::Credentials::UI::{UserConsentVerifier, UserConsentVerifierAvailability};
+
+    UserConsentVerifier::CheckAvailabilityAsync()
+        .and_then(|operation| operation.get())
+        .map(|availability| availability == UserConsentVerifierAvailability::Available)
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn authenticate_native(reason: &str) -> Result<(), String> {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{UserConsentVerificationResult, UserConsentVerifier};
+
+    let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+        .and_then(|operation| operation.get())
+        .map_err(|e| format!("Windows Hello failed: {}", e))?;
+    if result == UserConsentVerificationResult::Verified {
+        Ok(())
+    } else {
+        Err(format!("Windows Hello verification not completed: {:?}", result))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn biometrics_available() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn authenticate_native(_reason: &str) -> Result<(), String> {
+    Err("Biometric unlock is not supported on this platform".to_string())
+}
+
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash password: {}", e))
+}
+
+fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+impl AppLock {
+    fn load(app: &AppHandle) -> Self {
+        let prefs_path = app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE));
+        let prefs: LockPrefs = prefs_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            // An enabled lock also guards the app at launch
+            locked: AtomicBool::new(prefs.enabled),
+            prompting: AtomicBool::new(false),
+            failed: tokio::sync::Mutex::new(FailedAttempts::default()),
+            prefs: Mutex::new(prefs),
+            prefs_path,
+        }
+    }
+
+    fn persist(&self, prefs: &LockPrefs) -> Result<(), String> {
+        let path = self.prefs_path.as_ref().ok_or("Failed to resolve app config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(prefs)
+            .map_err(|e| format!("Failed to serialize app lock preferences: {}", e))?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|e| format!("Failed to write app lock preferences: {}", e))
+    }
+
+    fn status(&self) -> Result<AppLockStatus, String> {
+        let prefs = self.prefs.lock().map_err(|e| format!("Lock error: {}", e))?;
+        Ok(AppLockStatus {
+            enabled: prefs.enabled,
+            locked: self.locked.load(Ordering::SeqCst),
+            lock_after_secs: prefs.lock_after_secs,
+            has_password: prefs.password_hash.is_some(),
+            biometrics_available: biometrics_available(),
+        })
+    }
+}
+
+pub fn is_locked(app: &AppHandle) -> bool {
+    app.try_state::<AppLock>()
+        .map(|lock| lock.locked.load(Ordering::SeqCst))
+        .unwrap_or(false)
+}
+
+/// The error for a command refused by the invoke handler while locked, or
+/// `None` when `command` may run
+pub fn refuse_while_locked(app: &AppHandle, command: &str) -> Option<ShellError> {
+    (is_locked(app) && !ALLOWED_WHILE_LOCKED.contains(&command))
+        .then(|| ShellError::new(ErrorCode::PermissionDenied, "The app is locked"))
+}
+
+/// Answer for `ttt-media` and preview requests while locked
+pub fn locked_response() -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(StatusCode::LOCKED)
+        .body(Cow::Borrowed(&b"The app is locked"[..]))
+        .unwrap_or_default()
+}
+
+fn lock(app: &AppHandle) {
+    let state = app.state::<AppLock>();
+    if state.locked.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    info!("App locked");
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_content_protected(true);
+        let _ = window.hide();
+    }
+    if let Err(e) = app.emit("app-locked", ()) {
+        warn!("Failed to emit app-locked event: {}", e);
+    }
+}
+
+fn unlock(app: &AppHandle) {
+    let state = app.state::<AppLock>();
+    if !state.locked.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    info!("App unlocked");
+    if let Ok(mut failed) = state.failed.try_lock() {
+        *failed = FailedAttempts::default();
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_content_protected(false);
+    }
+    if let Err(e) = app.emit("app-unlocked", ()) {
+        warn!("Failed to emit app-unlocked event: {}", e);
+    }
+    crate::focus_main_window(app);
+}
+
+/// Bring a locked app forward: try Touch ID / Windows Hello first, and show the
+/// webview's password lock screen if that is unavailable or declined.
+pub fn prompt_unlock(app: &AppHandle) {
+    let state = app.state::<AppLock>();
+    if state.prompting.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let native = async_runtime::spawn_blocking(|| {
+            biometrics_available().then(|| authenticate_native(UNLOCK_REASON))
+        })
+        .await
+        .ok()
+        .flatten();
+
+        match native {
+            Some(Ok(())) => unlock(&app),
+            other => {
+                if let Some(Err(e)) = other {
+                    warn!("Native unlock failed: {}", e);
+                }
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.set_content_protected(true);
+                    let _ = window.unminimize();
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        }
+        app.state::<AppLock>().prompting.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Load lock preferences and start locking the app once the user has been
+/// idle for the configured time.
+pub fn init_app_lock(app: &AppHandle) {
+    let state = AppLock::load(app);
+    if state.locked.load(Ordering::SeqCst) {
+        info!("App lock enabled, starting locked");
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.set_content_protected(true);
+        }
+    }
+    app.manage(state);
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(CHECK_INTERVAL).await;
+
+            let state = app.state::<AppLock>();
+            let (enabled, lock_after_secs) = match state.prefs.lock() {
+                Ok(prefs) => (prefs.enabled, prefs.lock_after_secs),
+                Err(_) => continue,
+            };
+            if !enabled || state.locked.load(Ordering::SeqCst) {
+                continue;
+            }
+            let idle_seconds = app
+                .try_state::<IdleMonitor>()
+                .map(|monitor| monitor.idle_seconds())
+                .unwrap_or(0);
+            if idle_seconds >= lock_after_secs {
+                info!("Locking after {}s idle", idle_seconds);
+                lock(&app);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_app_lock_status(state: State<'_, AppLock>) -> Result<AppLockStatus, String> {
+    state.status()
+}
+
+/// Update lock settings. Refused while locked so the lock can't be switched
+/// off from the lock screen. Passing `password` replaces the fallback password.
+#[tauri::command]
+pub async fn configure_app_lock(
+    state: State<'_, AppLock>,
+    enabled: bool,
+    lock_after_secs: Option<u64>,
+    password: Option<String>,
+) -> Result<AppLockStatus, String> {
+    if state.locked.load(Ordering::SeqCst) {
+        return Err("Unlock the app before changing lock settings".to_string());
+    }
+    if let Some(secs) = lock_after_secs {
+        if secs < MIN_LOCK_AFTER_SECS {
+            return Err(format!("Lock timeout must be at least {} seconds", MIN_LOCK_AFTER_SECS));
+        }
+    }
+
+    let password_hash = match password.as_deref() {
+        Some(password) if password.chars().count() < 4 => {
+            return Err("Password must be at least 4 characters".to_string());
+        }
+        Some(password) => Some(hash_password(password)?),
+        None => None,
+    };
+
+    {
+        let mut prefs = state.prefs.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let mut updated = prefs.clone();
+        updated.enabled = enabled;
+        if let Some(secs) = lock_after_secs {
+            updated.lock_after_secs = secs;
+        }
+        if password_hash.is_some() {
+            updated.password_hash = password_hash;
+        }
+        if updated.enabled && updated.password_hash.is_none() && !biometrics_available() {
+            return Err("Set a password to enable the app lock on this device".to_string());
+        }
+        state.persist(&updated)?;
+        *prefs = updated;
+        info!(
+            "App lock {} (after {}s idle)",
+            if prefs.enabled { "enabled" } else { "disabled" },
+            prefs.lock_after_secs
+        );
+    }
+    state.status()
+}
+
+#[tauri::command]
+pub async fn lock_app(app: AppHandle, state: State<'_, AppLock>) -> Result<(), String> {
+    let enabled = state.prefs.lock().map_err(|e| format!("Lock error: {}", e))?.enabled;
+    if !enabled {
+        return Err("App lock is not enabled".to_string());
+    }
+    lock(&app);
+    Ok(())
+}
+
+/// Unlock with the fallback password, or with Touch ID / Windows Hello when no
+/// password is given.
+#[tauri::command]
+pub async fn unlock_app(
+    app: AppHandle,
+    state: State<'_, AppLock>,
+    password: Option<String>,
+) -> Result<(), String> {
+    if !state.locked.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    match password {
+        Some(password) => {
+            let mut failed = state.failed.lock().await;
+            if let Some(wait) = failed.retry_after.and_then(|at| at.checked_duration_since(Instant::now())) {
+                return Err(format!("Too many incorrect passwords, try again in {}s", wait.as_secs() + 1));
+            }
+            let hash = state
+                .prefs
+                .lock()
+                .map_err(|e| format!("Lock error: {}", e))?
+                .password_hash
+                .clone()
+                .ok_or("No unlock password is set")?;
+            let valid = async_runtime::spawn_blocking(move || verify_password(&hash, &password))
+                .await
+                .map_err(|e| format!("Password check failed: {}", e))?;
+            if !valid {
+                failed.record();
+                warn!("Failed unlock attempt ({} since the last unlock)", failed.count);
+                return Err("Incorrect password".to_string());
+            }
+        }
+        None => {
+            if !biometrics_available() {
+                return Err("Biometric unlock is not available, enter the password".to_string());
+            }
+            async_runtime::spawn_blocking(|| authenticate_native(UNLOCK_REASON))
+                .await
+                .map_err(|e| format!("Unlock prompt failed: {}", e))??;
+        }
+    }
+
+    unlock(&app);
+    Ok(())
+}
@@ -0,0 +1,336 @@
+//! Screenshot of the app's own main window for bug reports, so "the layout
+//! is broken" comes with a picture. Unless `redact` is off, text fields,
+//! editors and selects are blurred in the webview while the window is
+//! captured. The latest screenshot goes into the diagnostics bundle while it
+//! is recent.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use image::{ImageFormat, RgbaImage};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Manager, WebviewWindow};
+use tokio::time::sleep;
+
+/// Under the app cache dir; each screenshot replaces the last
+const SCREENSHOT_DIR: &str = "diagnostics";
+const SCREENSHOT_FILE: &str = "app-screenshot.png";
+
+/// Older screenshots are left out of the diagnostics bundle
+const MAX_BUNDLE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Time for the webview to repaint after the redaction style is added
+const REPAINT_DELAY: Duration = Duration::from_millis(150);
+
+const REDACT_SCRIPT: &str = r#"(() => {
+  if (document.getElementById("ttt-screenshot-redaction")) return;
+  const style = document.createElement("style");
+  style.id = "ttt-screenshot-redaction";
+  style.textContent = 'input:not([type="checkbox"]):not([type="radio"]):not([type="button"]):not([type="submit"]),'
+    + ' textarea, select, [contenteditable]:not([contenteditable="false"]) { filter: blur(8px) !important; }';
+  document.head.appendChild(style);
+})();"#;
+
+const UNREDACT_SCRIPT: &str = r#"document.getElementById("ttt-screenshot-redaction")?.remove();"#;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppScreenshot {
+    /// PNG in the app cache dir
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Whether text inputs were blurred
+    pub redacted: bool,
+    pub taken_at: DateTime<Utc>,
+}
+
+fn screenshot_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(SCREENSHOT_DIR).join(SCREENSHOT_FILE))
+        .map_err(|e| format!("Failed to resolve app cache directory: {}", e))
+}
+
+/// The latest screenshot if it was taken within `MAX_BUNDLE_AGE`
+pub(crate) fn recent(app: &AppHandle) -> Option<PathBuf> {
+    let path = screenshot_path(app).ok()?;
+    let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+    (age <= MAX_BUNDLE_AGE).then_some(path)
+}
+
+#[cfg(target_os = "macos")]
+fn window_image(window: &WebviewWindow) -> Result<RgbaImage, String> {
+    use std::ffi::c_void;
+
+    extern "C" {
+        fn capture_targets_copy_own_window_png(window: *mut c_void, length: *mut usize) -> *mut u8;
+        fn capture_targets_free_bytes(bytes: *mut u8);
+    }
+
+    let ns_window = window
+        .ns_window()
+        .map_err(|e| format!("Failed to get the main window: {}", e))?;
+    let png = unsafe {
+        let mut length = 0usize;
+        let bytes = capture_targets_copy_own_window_png(ns_window, &mut length);
+        if bytes.is_null() {
+            return Err("The main window could not be captured".to_string());
+        }
+        let png = std::slice::from_raw_parts(bytes, length).to_vec();
+        capture_targets_free_bytes(bytes);
+        png
+    };
+    image::load_from_memory_with_format(&png, ImageFormat::Png)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("Failed to read the window capture: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+fn window_image(window: &WebviewWindow) -> Result<RgbaImage, String> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Rect {
+        left: i32,
+        top: i32,
+        right: i32,
+        bottom: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct BitmapInfo {
+        size: u32,
+        width: i32,
+        height: i32,
+        planes: u16,
+        bit_count: u16,
+        compression: u32,
+        size_image: u32,
+        x_pels_per_meter: i32,
+        y_pels_per_meter: i32,
+        clr_used: u32,
+        clr_important: u32,
+        colors: [u32; 1],
+    }
+
+    /// Includes the WebView2's composited content, which a plain copy misses
+    const PW_RENDERFULLCONTENT: u32 = 2;
+    const DIB_RGB_COLORS: u32 = 0;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetWindowRect(hwnd: isize, rect: *mut Rect) -> i32;
+        fn GetDC(hwnd: isize) -> isize;
+        fn ReleaseDC(hwnd: isize, hdc: isize) -> i32;
+        fn PrintWindow(hwnd: isize, hdc: isize, flags: u32) -> i32;
+    }
+
+    #[link(name = "gdi32")]
+    extern "system" {
+        fn CreateCompatibleDC(hdc: isize) -> isize;
+        fn CreateCompatibleBitmap(hdc: isize, width: i32, height: i32) -> isize;
+        fn SelectObject(hdc: isize, object: isize) -> isize;
+        fn DeleteObject(object: isize) -> i32;
+        fn DeleteDC(hdc: isize) -> i32;
+        fn GetDIBits(
+            hdc: isize,
+            bitmap: isize,
+            start: u32,
+            lines: u32,
+            bits: *mut c_void,
+            info: *mut BitmapInfo,
+            usage: u32,
+        ) -> i32;
+    }
+
+    let hwnd = window
+        .hwnd()
+        .map_err(|e| format!("Failed to get the main window: {}", e))?
+        .0 as isize;
+    let mut rect = Rect::default();
+    if unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+        return Err("Failed to get the main window's size".to_string());
+    }
+    let (width, height) = ((rect.right - rect.left).max(0), (rect.bottom - rect.top).max(0));
+    if width == 0 || height == 0 {
+        return Err("The main window has no size".to_string());
+    }
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let copied = unsafe {
+        let screen = GetDC(0);
+        let memory = CreateCompatibleDC(screen);
+        let bitmap = CreateCompatibleBitmap(screen, width, height);
+        let previous = SelectObject(memory, bitmap);
+        let printed = PrintWindow(hwnd, memory, PW_RENDERFULLCONTENT) != 0;
+        SelectObject(memory, previous);
+        // A negative height asks for rows top to bottom
+        let mut info = BitmapInfo {
+            size: (std::mem::size_of::<BitmapInfo>() - std::mem::size_of::<u32>()) as u32,
+            width,
+            height: -height,
+            planes: 1,
+            bit_count: 32,
+            ..BitmapInfo::default()
+        };
+        let lines = if printed {
+            GetDIBits(memory, bitmap, 0, height as u32, pixels.as_mut_ptr().cast(), &mut info, DIB_RGB_COLORS)
+        } else {
+            0
+        };
+        DeleteObject(bitmap);
+        DeleteDC(memory);
+        ReleaseDC(0, screen);
+        lines == height
+    };
+    if !copied {
+        return Err("The main window could not be captured".to_string());
+    }
+    // GDI rows are BGRX
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+        pixel[3] = u8::MAX;
+    }
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or_else(|| "The window capture is incomplete".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn window_image(window: &WebviewWindow) -> Result<RgbaImage, String> {
+    use std::os::raw::c_ulong;
+
+    use image::Rgba;
+    use x11_dl::xlib::{Window, XWindowAttributes, ZPixmap, XA_CARDINAL, XA_WINDOW};
+
+    use crate::x11_session::{property, window_title};
+
+    /// Scale the bits of `mask` in `pixel` to 0-255
+    fn channel(pixel: c_ulong, mask: c_ulong) -> u8 {
+        let max = mask >> mask.trailing_zeros();
+        if max == 0 {
+            return 0;
+        }
+        (((pixel & mask) >> mask.trailing_zeros()) * 255 / max) as u8
+    }
+
+    let title = window.title().unwrap_or_default();
+    let pid = std::process::id() as c_ulong;
+    crate::x11_session::with_display(|xlib, display| unsafe {
+        let read_long = |bytes: &[u8], index: usize| {
+            let offset = index * std::mem::size_of::<c_ulong>();
+            std::ptr::read_unaligned(bytes[offset..].as_ptr() as *const c_ulong)
+        };
+        let root = (xlib.XDefaultRootWindow)(display);
+        let (clients, _, count) = property(xlib, display, root, c"_NET_CLIENT_LIST", XA_WINDOW)?;
+        let own = (0..count as usize)
+            .map(|i| read_long(&clients, i) as Window)
+            .find(|&client| {
+                let client_pid = property(xlib, display, client, c"_NET_WM_PID", XA_CARDINAL)
+                    .filter(|(_, format, count)| *format == 32 && *count > 0)
+                    .map(|(bytes, _, _)| read_long(&bytes, 0));
+                client_pid == Some(pid) && window_title(xlib, display, client).as_deref() == Some(title.as_str())
+            })?;
+
+        // Read from the root window, clipped to the screen, so a window that is
+        // partly off-screen can't fail the request
+        let mut attributes: XWindowAttributes = std::mem::zeroed();
+        let mut root_attributes: XWindowAttributes = std::mem::zeroed();
+        if (xlib.XGetWindowAttributes)(display, own, &mut attributes) == 0
+            || (xlib.XGetWindowAttributes)(display, root, &mut root_attributes) == 0
+        {
+            return None;
+        }
+        let (mut x, mut y, mut child) = (0, 0, 0 as Window);
+        (xlib.XTranslateCoordinates)(display, own, root, 0, 0, &mut x, &mut y, &mut child);
+        let (left, top) = (x.max(0), y.max(0));
+        let right = (x + attributes.width).min(root_attributes.width);
+        let bottom = (y + attributes.height).min(root_attributes.height);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+        let image = (xlib.XGetImage)(display, root, left, top, width, height, !0, ZPixmap);
+        if image.is_null() {
+            return None;
+        }
+        let (red, green, blue) = ((*image).red_mask, (*image).green_mask, (*image).blue_mask);
+        let mut rgba = RgbaImage::new(width, height);
+        for (px, py, out) in rgba.enumerate_pixels_mut() {
+            let pixel = (xlib.XGetPixel)(image, px as i32, py as i32);
+            *out = Rgba([channel(pixel, red), channel(pixel, green), channel(pixel, blue), u8::MAX]);
+        }
+        (xlib.XDestroyImage)(image);
+        Some(rgba)
+    })
+    .ok_or_else(|| "Capturing the main window needs an X11 session".to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn window_image(_window: &WebviewWindow) -> Result<RgbaImage, String> {
+    Err("Screenshots of the app are not supported on this platform".to_string())
+}
+
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Screenshot task panicked: {}", e))
+        .and_then(|result| result)
+}
+
+/// Capture the main window as a PNG for a bug report. `redact` (default on)
+/// blurs text inputs first. The screenshot replaces the previous one and is
+/// added to `export_diagnostics` bundles made within the hour.
+#[tauri::command]
+pub async fn capture_app_screenshot(app: AppHandle, redact: Option<bool>) -> Result<AppScreenshot, String> {
+    let window = app.get_webview_window("main").ok_or("The main window is not open")?;
+    if !window.is_visible().unwrap_or(false) || window.is_minimized().unwrap_or(false) {
+        return Err("The main window must be visible to take a screenshot of it".to_string());
+    }
+    let path = screenshot_path(&app)?;
+
+    let redacted = redact.unwrap_or(true);
+    if redacted {
+        window
+            .eval(REDACT_SCRIPT)
+            .map_err(|e| format!("Failed to blur text inputs: {}", e))?;
+        sleep(REPAINT_DELAY).await;
+    }
+    let captured = {
+        let window = window.clone();
+        blocking(move || window_image(&window)).await
+    };
+    if redacted {
+        if let Err(e) = window.eval(UNREDACT_SCRIPT) {
+            warn!("Failed to remove the text input blur: {}", e);
+        }
+    }
+    let image = captured?;
+
+    let (width, height) = image.dimensions();
+    let saved = path.clone();
+    blocking(move || {
+        if let Some(dir) = saved.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        image
+            .save_with_format(&saved, ImageFormat::Png)
+            .map_err(|e| format!("Failed to save {}: {}", saved.display(), e))
+    })
+    .await?;
+    info!("App screenshot ({}x{}, redacted: {}) saved to {}", width, height, redacted, path.display());
+
+    Ok(AppScreenshot {
+        path: path.to_string_lossy().to_string(),
+        width,
+        height,
+        redacted,
+        taken_at: Utc::now(),
+    })
+}
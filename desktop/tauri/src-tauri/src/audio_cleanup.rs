@@ -0,0 +1,69 @@
+//! Cleanup pass over the sound of finished recordings: raw laptop-mic audio
+//! is often too noisy and too quiet to use. ffmpeg cuts low rumble, reduces
+//! steady background noise (fans, hum) with `afftdn` and evens out the
+//! loudness with `loudnorm`. Video is copied untouched. Runs when
+//! `media.cleanAudio` is on, or per recording with the `cleanAudio` capture
+//! option.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tracing::{info, warn};
+
+use crate::health::find_on_path;
+use crate::media_store::probe;
+
+const FILTER: &str = "highpass=f=80,afftdn=nf=-25,loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// loudnorm resamples to 192 kHz unless told otherwise
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// An encoder the container of `path` can hold
+fn audio_codec(path: &Path) -> &'static str {
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+    match extension.as_str() {
+        "wav" => "pcm_s16le",
+        "webm" | "ogg" | "opus" => "libopus",
+        "flac" => "flac",
+        _ => "aac",
+    }
+}
+
+fn run(path: &Path) -> Result<bool, String> {
+    let ffmpeg = find_on_path("ffmpeg").ok_or("ffmpeg was not found on the PATH")?;
+    let Some(audio) = probe(path)?.audio else {
+        return Ok(false);
+    };
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let cleaned = path.with_extension(format!("cleaning.{}", extension));
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(path)
+        .args(["-map", "0", "-c", "copy", "-af", FILTER, "-c:a", audio_codec(path), "-ar"])
+        .arg(audio.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE).to_string())
+        .arg(&cleaned)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&cleaned);
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed: {}", reason.trim()));
+    }
+    fs::rename(&cleaned, path).map_err(|e| {
+        let _ = fs::remove_file(&cleaned);
+        format!("Failed to replace the recording: {}", e)
+    })?;
+    Ok(true)
+}
+
+/// Clean up the audio of `path` in place. Files without sound are left
+/// alone, and so is the original when the pass fails. Blocks.
+pub(crate) fn clean(path: &Path) {
+    match run(path) {
+        Ok(true) => info!(path = ?path, "cleaned up the recording's audio"),
+        Ok(false) => {}
+        Err(e) => warn!(path = ?path, error = %e, "audio cleanup failed; keeping the original"),
+    }
+}
@@ -0,0 +1,186 @@
+//! Native playback of voice memos and other audio in the app's data
+//! directories, for when the webview isn't allowed to load the file itself.
+//! Progress is reported as `audio-playback` events.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rodio::{Decoder, OutputStream, Sink, Source};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, info, instrument, warn};
+
+use crate::media_store::validate_app_path;
+
+/// Time between `audio-playback` position events
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Formats rodio can't decode (Opus, which the recordings use) are converted
+/// to this file in the cache dir first
+const CONVERTED_FILE: &str = "playback.wav";
+
+static PLAYBACK: tokio::sync::Mutex<Option<Playback>> = tokio::sync::Mutex::const_new(None);
+
+struct Playback {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum PlaybackState {
+    Playing,
+    Ended,
+    Stopped,
+}
+
+/// Payload of `audio-playback`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PlaybackEvent<'a> {
+    path: &'a str,
+    state: PlaybackState,
+    position_ms: u64,
+    /// `None` when the format doesn't say
+    duration_ms: Option<u64>,
+}
+
+fn open(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| format!("Failed to decode {}: {}", path.display(), e))
+}
+
+/// Decode `path`, converting it to `converted` with ffmpeg when rodio can't
+fn decode(path: &Path, converted: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let unsupported = match open(path) {
+        Ok(decoder) => return Ok(decoder),
+        Err(e) => e,
+    };
+    debug!(error = %unsupported, "converting with ffmpeg before playback");
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-f", "wav", "-y"])
+        .arg(converted)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{}; converting it needs ffmpeg ({})", unsupported, e))?;
+    if !output.status.success() {
+        return Err(format!("{}: {}", unsupported, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    open(converted)
+}
+
+fn emit(app: &AppHandle, path: &str, state: PlaybackState, position: Duration, duration: Option<Duration>) {
+    let event = PlaybackEvent {
+        path,
+        state,
+        position_ms: position.as_millis() as u64,
+        duration_ms: duration.map(|duration| duration.as_millis() as u64),
+    };
+    if let Err(e) = app.emit("audio-playback", event) {
+        warn!(error = %e, "failed to emit audio-playback");
+    }
+}
+
+/// Play until the end or until `stop` is set. The output stream isn't Send,
+/// so everything happens on this thread. Events carry `name`, the path as
+/// the webview gave it.
+fn play(
+    app: AppHandle,
+    name: String,
+    path: PathBuf,
+    converted: PathBuf,
+    stop: Arc<AtomicBool>,
+    started: tokio::sync::oneshot::Sender<Result<(), String>>,
+) {
+    let opened = OutputStream::try_default()
+        .map_err(|e| format!("No audio output device: {}", e))
+        .and_then(|(stream, handle)| {
+            let source = decode(&path, &converted)?;
+            let sink = Sink::try_new(&handle).map_err(|e| format!("Failed to start playback: {}", e))?;
+            let duration = source.total_duration();
+            sink.append(source);
+            Ok((stream, sink, duration))
+        });
+    let (_stream, sink, duration) = match opened {
+        Ok(opened) => {
+            let _ = started.send(Ok(()));
+            opened
+        }
+        Err(e) => {
+            let _ = started.send(Err(e));
+            return;
+        }
+    };
+
+    while !stop.load(Ordering::Relaxed) && !sink.empty() {
+        emit(&app, &name, PlaybackState::Playing, sink.get_pos(), duration);
+        thread::sleep(POSITION_INTERVAL);
+    }
+    let position = sink.get_pos();
+    sink.stop();
+    let state = if stop.load(Ordering::Relaxed) {
+        PlaybackState::Stopped
+    } else {
+        PlaybackState::Ended
+    };
+    info!(?state, "playback finished");
+    emit(&app, &name, state, duration.filter(|_| state == PlaybackState::Ended).unwrap_or(position), duration);
+}
+
+async fn stop_current(playback: Playback) -> Result<(), String> {
+    playback.stop.store(true, Ordering::Relaxed);
+    tokio::task::spawn_blocking(move || playback.thread.join())
+        .await
+        .map_err(|e| format!("Playback task failed: {}", e))?
+        .map_err(|_| "The playback thread panicked".to_string())
+}
+
+/// Play an audio file from the app's data directories, replacing whatever is
+/// playing. Returns once playback has started.
+#[tauri::command]
+#[instrument(skip(app), err)]
+pub async fn play_audio(app: AppHandle, path: String) -> Result<(), String> {
+    let resolved = validate_app_path(&app, &path)?;
+    let converted = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get app cache directory: {}", e))?;
+    std::fs::create_dir_all(&converted).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    let converted = converted.join(CONVERTED_FILE);
+
+    let mut playback = PLAYBACK.lock().await;
+    if let Some(current) = playback.take() {
+        stop_current(current).await?;
+    }
+
+    let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop = stop.clone();
+        thread::Builder::new()
+            .name("audio-playback".to_string())
+            .spawn(move || play(app, path, resolved, converted, stop, started_tx))
+            .map_err(|e| format!("Failed to start the playback thread: {}", e))?
+    };
+    started_rx
+        .await
+        .map_err(|_| "The playback thread exited".to_string())??;
+
+    *playback = Some(Playback { stop, thread });
+    Ok(())
+}
+
+#[tauri::command]
+#[instrument(err)]
+pub async fn stop_audio_playback() -> Result<(), String> {
+    let playback = PLAYBACK.lock().await.take().ok_or("Nothing is playing")?;
+    stop_current(playback).await
+}
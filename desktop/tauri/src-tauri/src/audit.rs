@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tracing::field::{Field, Visit};
+use tracing::{span, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::diagnostics::SECRET_KEYS;
+use crate::prefs;
+
+const AUDIT_FILE: &str = "command_audit.jsonl";
+
+/// Entries kept, in memory and on disk
+const MAX_ENTRIES: usize = 2000;
+
+/// Called many times a second while in use; they would push everything else out
+const IGNORED_COMMANDS: &[&str] = &["feed_speech_audio"];
+
+/// Longest argument summary and error message kept per entry
+const MAX_SUMMARY_CHARS: usize = 240;
+const MAX_VALUE_CHARS: usize = 48;
+
+/// Module Tauri's IPC spans are created in
+const IPC_TARGET: &str = "tauri::ipc::protocol";
+
+static AUDIT_LOG: Mutex<AuditLog> = Mutex::new(AuditLog {
+    path: None,
+    entries: VecDeque::new(),
+    lines_on_disk: 0,
+});
+
+struct AuditLog {
+    path: Option<PathBuf>,
+    entries: VecDeque<AuditEntry>,
+    lines_on_disk: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub command: String,
+    /// Argument names with shortened values; secrets are redacted
+    pub args: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub status: AuditStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only calls of this command
+    pub command: Option<String>,
+    /// Newest entries first, at most this many
+    pub limit: Option<usize>,
+}
+
+impl AuditLog {
+    fn append_to_disk(&mut self, entry: &AuditEntry) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => self.lines_on_disk += 1,
+            Err(e) => warn!("Failed to write {}: {}", AUDIT_FILE, e),
+        }
+    }
+
+    /// Rewrite the file with just the entries kept in memory
+    fn compact(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let contents: String = self
+            .entries
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect();
+        match prefs::write_atomic(path, &contents) {
+            Ok(()) => self.lines_on_disk = self.entries.len(),
+            Err(e) => warn!("Failed to compact {}: {}", AUDIT_FILE, e),
+        }
+    }
+
+    fn push(&mut self, entry: AuditEntry) {
+        self.append_to_disk(&entry);
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        if self.lines_on_disk > MAX_ENTRIES * 2 {
+            self.compact();
+        }
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max).collect();
+    short.push('…');
+    short
+}
+
+fn summarize_value(key: &str, value: &Value) -> String {
+    let lower = key.to_ascii_lowercase();
+    if SECRET_KEYS.iter().any(|secret| lower.contains(secret)) && !value.is_null() {
+        return "[redacted]".to_string();
+    }
+    match value {
+        Value::String(text) => format!("{:?}", truncate(text, MAX_VALUE_CHARS)),
+        Value::Array(items) => format!("[{} items]", items.len()),
+        Value::Object(map) => format!("{{{}}}", map.keys().cloned().collect::<Vec<_>>().join(", ")),
+        other => other.to_string(),
+    }
+}
+
+/// `key=value` pairs of a command's JSON arguments. Requests sent with
+/// postMessage carry the whole message; the arguments are its `payload`.
+fn summarize_args(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return format!("<{} bytes>", body.len());
+    };
+    let args = match value {
+        Value::Object(mut message) if message.contains_key("cmd") => message.remove("payload").unwrap_or(Value::Null),
+        args => args,
+    };
+    let summary = match &args {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, summarize_value(key, value)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Null => String::new(),
+        Value::Array(items) => format!("<{} bytes>", items.len()),
+        other => summarize_value("", other),
+    };
+    truncate(&summary, MAX_SUMMARY_CHARS)
+}
+
+/// The message of an error response. Tauri records errors as the `Debug` form
+/// of `InvokeError(String("..."))`; successful responses are the JSON sent back.
+fn response_error(response: &str) -> Option<String> {
+    let inner = response.strip_prefix("InvokeError(")?.strip_suffix(')')?;
+    let message = inner
+        .strip_prefix("String(")
+        .and_then(|quoted| quoted.strip_suffix(')'))
+        .and_then(|quoted| serde_json::from_str::<String>(quoted).ok())
+        .unwrap_or_else(|| inner.to_string());
+    Some(truncate(&message, MAX_SUMMARY_CHARS))
+}
+
+/// Picks one string field out of a span's attributes
+struct StrField {
+    name: &'static str,
+    value: Option<String>,
+}
+
+impl StrField {
+    fn read(name: &'static str, record: impl FnOnce(&mut dyn Visit)) -> Option<String> {
+        let mut field = StrField { name, value: None };
+        record(&mut field);
+        field.value
+    }
+}
+
+impl Visit for StrField {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.name {
+            self.value = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.name {
+            self.value = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Raw arguments of an `ipc::request`
+struct RequestBody(String);
+
+/// A command call, from `ipc::request::handle` until its response
+struct PendingCall {
+    command: String,
+    args: String,
+    started: Instant,
+    started_at: DateTime<Utc>,
+}
+
+/// Builds audit entries from the spans Tauri opens for every IPC request:
+/// `ipc::request` (arguments), `ipc::request::handle` (command name, start)
+/// and `ipc::request::response`, created as the result is sent back.
+pub struct AuditLayer;
+
+impl AuditLayer {
+    /// Only Tauri's IPC spans reach this layer
+    pub fn filter() -> Targets {
+        Targets::new().with_target(IPC_TARGET, LevelFilter::TRACE)
+    }
+}
+
+impl<S> Layer<S> for AuditLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        match span.name() {
+            "ipc::request" => {
+                if let Some(body) = StrField::read("request", |visit| attrs.record(visit)) {
+                    span.extensions_mut().insert(RequestBody(body));
+                }
+            }
+            "ipc::request::handle" => {
+                let Some(command) = StrField::read("cmd", |visit| attrs.record(visit)) else {
+                    return;
+                };
+                if IGNORED_COMMANDS.contains(&command.as_str()) {
+                    return;
+                }
+                let args = span
+                    .parent()
+                    .and_then(|request| request.extensions().get::<RequestBody>().map(|body| summarize_args(&body.0)))
+                    .unwrap_or_default();
+                span.extensions_mut().insert(PendingCall {
+                    command,
+                    args,
+                    started: Instant::now(),
+                    started_at: Utc::now(),
+                });
+            }
+            "ipc::request::response" => {
+                // Opened inside `ipc::request::respond`, a child of the handle span
+                let Some(handle) = span.parent().and_then(|respond| respond.parent()) else {
+                    return;
+                };
+                let Some(call) = handle.extensions_mut().remove::<PendingCall>() else {
+                    return;
+                };
+                let error = StrField::read("response", |visit| attrs.record(visit)).and_then(|response| response_error(&response));
+                let entry = AuditEntry {
+                    command: call.command,
+                    args: call.args,
+                    started_at: call.started_at,
+                    duration_ms: call.started.elapsed().as_millis() as u64,
+                    status: if error.is_some() { AuditStatus::Error } else { AuditStatus::Ok },
+                    error,
+                };
+                if let Ok(mut log) = AUDIT_LOG.lock() {
+                    log.push(entry);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Custom-protocol requests record their body after the span is created
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if span.name() != "ipc::request" {
+            return;
+        }
+        if let Some(body) = StrField::read("request", |visit| values.record(visit)) {
+            span.extensions_mut().insert(RequestBody(body));
+        }
+    }
+}
+
+/// Load the audit log from the app's local data dir and keep writing to it.
+/// Calls made before this are kept and written out.
+pub fn init_audit(app: &AppHandle) {
+    let path = match app.path().app_local_data_dir() {
+        Ok(dir) => dir.join(AUDIT_FILE),
+        Err(e) => {
+            warn!("Command audit kept in memory only, app data directory unavailable: {}", e);
+            return;
+        }
+    };
+    let saved: Vec<AuditEntry> = fs::read_to_string(&path)
+        .map(|contents| contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default();
+
+    let Ok(mut log) = AUDIT_LOG.lock() else {
+        return;
+    };
+    let early = std::mem::take(&mut log.entries);
+    log.entries = saved.into_iter().chain(early).collect();
+    while log.entries.len() > MAX_ENTRIES {
+        log.entries.pop_front();
+    }
+    log.path = Some(path);
+    log.compact();
+}
+
+/// Recorded command calls, newest first, optionally limited to a time range
+/// and a command
+#[tauri::command]
+pub async fn get_command_audit(range: Option<AuditRange>) -> Result<Vec<AuditEntry>, String> {
+    let range = range.unwrap_or_default();
+    let log = AUDIT_LOG.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(log
+        .entries
+        .iter()
+        .rev()
+        .filter(|entry| range.since.map_or(true, |since| entry.started_at >= since))
+        .filter(|entry| range.until.map_or(true, |until| entry.started_at <= until))
+        .filter(|entry| range.command.as_ref().map_or(true, |command| &entry.command == command))
+        .take(range.limit.unwrap_or(usize::MAX))
+        .cloned()
+        .collect())
+}
@@ -0,0 +1,190 @@
+//! Scripting through `trackthething://` links, for launchers such as Raycast,
+//! Alfred or AutoHotkey. Navigation links (`entry/123`) only open things and
+//! go to the webview as `deep-link` events. Action links do something, so the
+//! user is asked first, either for this one link or for every later link of
+//! the same action.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+use crate::cli::{self, CliCommand};
+use crate::deep_link::DeepLinkRoute;
+use crate::locale::{tr, Text};
+
+/// `<route>/<first param>` of every action link
+pub const ACTIONS: &[&str] = &[
+    "timer/start",
+    "timer/stop",
+    "capture/photo",
+    "capture/screenshot",
+    "note/new",
+    "note/template",
+];
+
+/// Actions the webview carries out, as `automation` events
+const WEBVIEW_ACTIONS: &[&str] = &["timer/start", "timer/stop"];
+
+const PREFS_FILE: &str = "automation_prefs.json";
+
+/// Serializes reads and writes of the prefs file
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct AutomationPrefs {
+    /// Actions the user chose "Always Allow" for
+    always_allowed: BTreeSet<String>,
+}
+
+impl AutomationPrefs {
+    fn load(app: &AppHandle) -> Self {
+        let Some(path) = prefs_path(app) else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse automation preferences: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let path = prefs_path(app).ok_or("Failed to resolve app config directory")?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize automation preferences: {}", e))?;
+        crate::prefs::write_atomic(&path, &json)
+    }
+}
+
+fn prefs_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE))
+}
+
+/// Payload of `automation`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AutomationEvent {
+    /// One of `WEBVIEW_ACTIONS`
+    action: String,
+    /// The link's query, e.g. `{ "project": "X" }`
+    params: HashMap<String, String>,
+}
+
+/// The action a link asks for, or `None` for a navigation link
+pub fn action_name(route: &DeepLinkRoute) -> Option<&'static str> {
+    let name = format!("{}/{}", route.route, route.params.first()?);
+    ACTIONS.iter().copied().find(|action| *action == name)
+}
+
+fn run(app: &AppHandle, action: &'static str, route: DeepLinkRoute) {
+    info!("Running automation action {}", action);
+    let command = match action {
+        "capture/photo" => CliCommand::CapturePhoto,
+        "capture/screenshot" => CliCommand::CaptureScreenshot,
+        "note/new" => match route.query.get("text").filter(|text| !text.trim().is_empty()) {
+            Some(text) => CliCommand::Note(text.clone()),
+            None => {
+                warn!("Ignoring note/new link without text");
+                return;
+            }
+        },
+        "note/template" => {
+            let mut vars = route.query;
+            match vars.remove("id") {
+                Some(id) => CliCommand::Template { id, vars },
+                None => {
+                    warn!("Ignoring note/template link without an id");
+                    return;
+                }
+            }
+        }
+        _ => {
+            debug_assert!(WEBVIEW_ACTIONS.contains(&action));
+            let event = AutomationEvent {
+                action: action.to_string(),
+                params: route.query,
+            };
+            if let Err(e) = app.emit("automation", event) {
+                warn!("Failed to emit automation event: {}", e);
+            }
+            return;
+        }
+    };
+    cli::dispatch(app, command);
+}
+
+/// Run an action link, asking first unless the action is always allowed
+pub fn handle(app: &AppHandle, action: &'static str, route: DeepLinkRoute) {
+    let allowed = {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        AutomationPrefs::load(app).always_allowed.contains(action)
+    };
+    if allowed {
+        run(app, action, route);
+        return;
+    }
+
+    let allow_once = tr(Text::AllowOnce).to_string();
+    let always_allow = tr(Text::AlwaysAllow).to_string();
+    let app = app.clone();
+    app.dialog()
+        .message(tr(Text::AutomationRequest).replace("{action}", action))
+        .title(tr(Text::AutomationTitle))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            allow_once.clone(),
+            always_allow.clone(),
+            tr(Text::Deny).to_string(),
+        ))
+        .show_with_result(move |result| {
+            // Only the custom labels grant anything: a plain Yes or No can't
+            // be told apart from the buttons' positions on every platform
+            let always = match result {
+                MessageDialogResult::Custom(label) if label == allow_once => false,
+                MessageDialogResult::Custom(label) if label == always_allow => true,
+                _ => {
+                    info!("Automation action {} denied", action);
+                    return;
+                }
+            };
+            if always {
+                let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+                let mut prefs = AutomationPrefs::load(&app);
+                prefs.always_allowed.insert(action.to_string());
+                if let Err(e) = prefs.save(&app) {
+                    warn!("Failed to save automation preferences: {}", e);
+                }
+            }
+            run(&app, action, route);
+        });
+}
+
+/// Actions that run without asking
+#[tauri::command]
+pub async fn get_automation_permissions(app: AppHandle) -> Result<Vec<String>, String> {
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(AutomationPrefs::load(&app).always_allowed.into_iter().collect())
+}
+
+/// Ask again before running `action`, or before every action when `None`
+#[tauri::command]
+pub async fn revoke_automation_permission(app: AppHandle, action: Option<String>) -> Result<(), String> {
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut prefs = AutomationPrefs::load(&app);
+    match action {
+        Some(action) => {
+            prefs.always_allowed.remove(&action);
+        }
+        None => prefs.always_allowed.clear(),
+    }
+    prefs.save(&app)
+}
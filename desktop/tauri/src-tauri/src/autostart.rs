@@ -0,0 +1,277 @@
+use std::fs;
+use std::path::PathBuf;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// Command-line flag passed by the login item so the app starts in the tray
+pub const HIDDEN_FLAG: &str = "--hidden";
+
+const PREFS_FILE: &str = "autostart_prefs.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub start_hidden: bool,
+}
+
+impl AutostartStatus {
+    fn load(app: &AppHandle) -> Self {
+        let Some(path) = prefs_path(app) else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse autostart preferences: {}", e);
+                Self::default()
+            }),
+            Err(e) => {
+                warn!("Failed to read autostart preferences: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let path = prefs_path(app).ok_or("Failed to resolve app config directory")?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize autostart preferences: {}", e))?;
+        crate::prefs::write_atomic(&path, &json)
+    }
+}
+
+fn prefs_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE))
+}
+
+/// Whether this process was started by the login item with the hidden flag
+pub fn launched_hidden() -> bool {
+    std::env::args().any(|arg| arg == HIDDEN_FLAG)
+}
+
+fn launch_args(start_hidden: bool) -> Result<(PathBuf, Vec<String>), String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to resolve application executable: {}", e))?;
+    let args = if start_hidden {
+        vec![HIDDEN_FLAG.to_string()]
+    } else {
+        Vec::new()
+    };
+    Ok((exe, args))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    fn agent_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let home = dirs::home_dir().ok_or("Cannot resolve home directory")?;
+        Ok(home
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", app.config().identifier)))
+    }
+
+    fn xml_escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    pub fn enable(app: &AppHandle, start_hidden: bool) -> Result<(), String> {
+        let path = agent_path(app)?;
+        let (exe, args) = launch_args(start_hidden)?;
+        let program_arguments = std::iter::once(exe.to_string_lossy().to_string())
+            .chain(args)
+            .map(|arg| format!("        <string>{}</string>", xml_escape(&arg)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+            xml_escape(&app.config().identifier),
+            program_arguments
+        );
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create LaunchAgents directory: {}", e))?;
+        }
+        fs::write(&path, plist).map_err(|e| format!("Failed to write launch agent: {}", e))
+    }
+
+    pub fn disable(app: &AppHandle) -> Result<(), String> {
+        let path = agent_path(app)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove launch agent: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        Ok(agent_path(app)?.exists())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+    fn value_name(app: &AppHandle) -> String {
+        app.package_info().name.clone()
+    }
+
+    pub fn enable(app: &AppHandle, start_hidden: bool) -> Result<(), String> {
+        let (exe, args) = launch_args(start_hidden)?;
+        let mut command = format!("\"{}\"", exe.display());
+        for arg in args {
+            command.push(' ');
+            command.push_str(&arg);
+        }
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(RUN_KEY)
+            .map_err(|e| format!("Failed to open Run registry key: {}", e))?;
+        key.set_value(value_name(app), &command)
+            .map_err(|e| format!("Failed to write Run registry value: {}", e))
+    }
+
+    pub fn disable(app: &AppHandle) -> Result<(), String> {
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(RUN_KEY)
+            .map_err(|e| format!("Failed to open Run registry key: {}", e))?;
+        match key.delete_value(value_name(app)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove Run registry value: {}", e)),
+        }
+    }
+
+    pub fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(RUN_KEY) else {
+            return Ok(false);
+        };
+        Ok(key.get_value::<String, _>(value_name(app)).is_ok())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+
+    fn desktop_entry_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let config = dirs::config_dir().ok_or("Cannot resolve XDG config directory")?;
+        Ok(config
+            .join("autostart")
+            .join(format!("{}.desktop", app.config().identifier)))
+    }
+
+    pub fn enable(app: &AppHandle, start_hidden: bool) -> Result<(), String> {
+        let path = desktop_entry_path(app)?;
+        let (exe, args) = launch_args(start_hidden)?;
+        let exec = std::iter::once(exe.to_string_lossy().to_string())
+            .chain(args)
+            .map(|arg| shell_words::quote(&arg).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nX-GNOME-Autostart-enabled=true\nTerminal=false\n",
+            app.package_info().name,
+            exec
+        );
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create autostart directory: {}", e))?;
+        }
+        fs::write(&path, entry).map_err(|e| format!("Failed to write autostart entry: {}", e))
+    }
+
+    pub fn disable(app: &AppHandle) -> Result<(), String> {
+        let path = desktop_entry_path(app)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove autostart entry: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        Ok(desktop_entry_path(app)?.exists())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+mod platform {
+    use super::*;
+
+    pub fn enable(_app: &AppHandle, _start_hidden: bool) -> Result<(), String> {
+        Err("Launch at login is not supported on this platform".to_string())
+    }
+
+    pub fn disable(_app: &AppHandle) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn is_enabled(_app: &AppHandle) -> Result<bool, String> {
+        Ok(false)
+    }
+}
+
+/// Enable or disable launching at login. `start_hidden` keeps the previous
+/// choice when omitted.
+#[tauri::command]
+pub async fn set_autostart(
+    app: AppHandle,
+    enabled: bool,
+    start_hidden: Option<bool>,
+) -> Result<AutostartStatus, String> {
+    let mut status = AutostartStatus::load(&app);
+    status.enabled = enabled;
+    if let Some(start_hidden) = start_hidden {
+        status.start_hidden = start_hidden;
+    }
+
+    if enabled {
+        platform::enable(&app, status.start_hidden)?;
+    } else {
+        platform::disable(&app)?;
+    }
+    status.save(&app)?;
+
+    info!(
+        "Launch at login {} (start hidden: {})",
+        if enabled { "enabled" } else { "disabled" },
+        status.start_hidden
+    );
+    Ok(status)
+}
+
+/// Report the launch-at-login state. The OS registration is the source of truth
+/// for `enabled`, since users can remove login items outside the app.
+#[tauri::command]
+pub async fn get_autostart(app: AppHandle) -> Result<AutostartStatus, String> {
+    let mut status = AutostartStatus::load(&app);
+    status.enabled = platform::is_enabled(&app)?;
+    Ok(status)
+}
@@ -0,0 +1,411 @@
+use std::error::Error as _;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use log::debug;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Manager};
+use tokio::sync::watch;
+use tokio::time::timeout;
+
+use crate::error::{ErrorCode, ShellError};
+use crate::DesktopConfig;
+
+/// Header the backend checks against the token it was started with
+pub const TOKEN_HEADER: &str = "X-Desktop-Token";
+
+/// Environment variable the token is handed to the backend in
+pub const TOKEN_ENV: &str = "TAURI_BACKEND_TOKEN";
+
+/// Header on every request made for the webview, so the backend doesn't take
+/// it for the shell's own even though it carries the token
+const PROXIED_HEADER: &str = "X-Desktop-Proxied";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Per chunk of a resumable upload, which may be throttled
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long a refused connection is retried, long enough to cover a backend
+/// restart (data dir move, backend update, rollback)
+const RESTART_GRACE: Duration = Duration::from_secs(20);
+
+/// Delay between retries, doubling up to `MAX_RETRY_DELAY`
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How long a webview request waits for a restart the shell started (data
+/// dir move, encryption, restore) before failing
+const RESTART_WAIT: Duration = Duration::from_secs(120);
+
+/// Webview requests that may wait for a restart at once; more fail straight away
+const MAX_WAITING: usize = 64;
+
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+/// Endpoints only the shell itself may call. The backend refuses proxied
+/// requests to them anyway; this fails them before they are sent.
+const SHELL_ONLY_PATHS: &[&str] = &["/api/backup/snapshot"];
+
+/// Webview requests waiting for a restart to finish
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BackendErrorKind {
+    /// Bad method or path; nothing was sent
+    InvalidRequest,
+    /// The backend didn't accept connections within the restart grace
+    /// period, or a restart outlasted `RESTART_WAIT`
+    Unavailable,
+    Timeout,
+    /// The backend answered with a 4xx or 5xx status
+    Http,
+    /// Any other connection or protocol failure
+    Transport,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendError {
+    pub kind: BackendErrorKind,
+    pub status: Option<u16>,
+    pub message: String,
+    /// Response body of an `http` error, e.g. FastAPI's `{"detail": …}`
+    pub body: Option<Value>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendResponse {
+    pub status: u16,
+    /// Parsed JSON, the text of any other response, or `null` when empty
+    pub body: Value,
+}
+
+impl BackendError {
+    pub(crate) fn new(kind: BackendErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: None,
+            message: message.into(),
+            body: None,
+        }
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// What `backend_request` hands the webview. An `http` error keeps its
+/// status and body in `details` as `{ status, body }`.
+impl From<BackendError> for ShellError {
+    fn from(error: BackendError) -> Self {
+        let code = match error.kind {
+            BackendErrorKind::InvalidRequest => ErrorCode::InvalidInput,
+            BackendErrorKind::Unavailable | BackendErrorKind::Transport => ErrorCode::Unavailable,
+            BackendErrorKind::Timeout => ErrorCode::Timeout,
+            BackendErrorKind::Http => match error.status {
+                Some(408) => ErrorCode::Timeout,
+                Some(429 | 502..=504) => ErrorCode::Unavailable,
+                Some(400..=499) => ErrorCode::InvalidInput,
+                _ => ErrorCode::Internal,
+            },
+        };
+        let shell = ShellError::new(code, error.message);
+        match error.status {
+            Some(status) => shell.with_details(json!({ "status": status, "body": error.body })),
+            None => shell,
+        }
+    }
+}
+
+/// A fresh random token for this launch, hex encoded
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn read_body(response: ureq::Response) -> Value {
+    let is_json = response.content_type() == "application/json";
+    let text = response.into_string().unwrap_or_default();
+    if text.is_empty() {
+        return Value::Null;
+    }
+    if is_json {
+        if let Ok(value) = serde_json::from_str(&text) {
+            return value;
+        }
+    }
+    Value::String(text)
+}
+
+fn is_timeout(error: &ureq::Transport) -> bool {
+    error
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .is_some_and(|e| matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock))
+}
+
+/// Only `/`-rooted paths on the backend itself; the host is never the caller's
+fn validate_path(path: &str) -> Result<(), BackendError> {
+    let valid = path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.contains('\\')
+        && !path.split(['/', '?']).any(|segment| segment == "..");
+    if valid {
+        Ok(())
+    } else {
+        Err(BackendError::new(
+            BackendErrorKind::InvalidRequest,
+            format!("Invalid backend path: {:?}", path),
+        ))
+    }
+}
+
+/// Like `validate_path`, and without `.` segments, empty segments,
+/// percent-escapes in the path or a fragment, so the route the webview names
+/// is the one the backend serves
+fn validate_webview_path(path: &str) -> Result<&str, ShellError> {
+    validate_path(path)?;
+    let route = path.split('?').next().unwrap_or_default();
+    let plain = !path.contains('#')
+        && !route.contains('%')
+        && !route.trim_end_matches('/').split('/').skip(1).any(|segment| segment.is_empty() || segment == ".");
+    if plain {
+        Ok(route.trim_end_matches('/'))
+    } else {
+        Err(ShellError::invalid_input(format!("Invalid backend path: {:?}", path)))
+    }
+}
+
+fn send(
+    url: &str,
+    token: &str,
+    method: &str,
+    body: Option<&Value>,
+    proxied: bool,
+) -> Result<BackendResponse, BackendError> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build();
+    let started = Instant::now();
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        let mut request = agent.request(method, url).set(TOKEN_HEADER, token);
+        if proxied {
+            request = request.set(PROXIED_HEADER, "1");
+        }
+        let result = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        match result {
+            Ok(response) => {
+                return Ok(BackendResponse {
+                    status: response.status(),
+                    body: read_body(response),
+                })
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let message = format!("{} {}", status, response.status_text());
+                return Err(BackendError {
+                    kind: BackendErrorKind::Http,
+                    status: Some(status),
+                    message,
+                    body: Some(read_body(response)),
+                });
+            }
+            // Nothing reached the backend, so even a POST is safe to resend
+            Err(ureq::Error::Transport(e)) if e.kind() == ureq::ErrorKind::ConnectionFailed => {
+                if started.elapsed() + delay > RESTART_GRACE {
+                    return Err(BackendError::new(
+                        BackendErrorKind::Unavailable,
+                        format!("Backend is not reachable: {}", e),
+                    ));
+                }
+                debug!("Backend refused {} {}, retrying in {:?}", method, url, delay);
+                thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(ureq::Error::Transport(e)) if is_timeout(&e) => {
+                return Err(BackendError::new(
+                    BackendErrorKind::Timeout,
+                    format!("Backend request timed out: {}", e),
+                ));
+            }
+            Err(ureq::Error::Transport(e)) => {
+                return Err(BackendError::new(
+                    BackendErrorKind::Transport,
+                    format!("Backend request failed: {}", e),
+                ));
+            }
+        }
+    }
+}
+
+/// Send bytes `start..=end` of a `size` byte upload to a resumable upload
+/// path, reading them from `body`. Not retried: the body is consumed, so the
+/// caller asks the backend how far it got instead.
+pub(crate) fn put_chunk(
+    config: &DesktopConfig,
+    path: &str,
+    (start, end, size): (u64, u64, u64),
+    body: impl io::Read,
+) -> Result<BackendResponse, BackendError> {
+    validate_path(path)?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout(UPLOAD_TIMEOUT)
+        .build();
+    let result = agent
+        .put(&format!("{}{}", config.backend_url, path))
+        .set(TOKEN_HEADER, &config.backend_token)
+        .set("Content-Type", "application/octet-stream")
+        .set("Content-Length", &(end + 1 - start).to_string())
+        .set("Content-Range", &format!("bytes {}-{}/{}", start, end, size))
+        .send(body);
+    match result {
+        Ok(response) => Ok(BackendResponse {
+            status: response.status(),
+            body: read_body(response),
+        }),
+        Err(ureq::Error::Status(status, response)) => Err(BackendError {
+            kind: BackendErrorKind::Http,
+            status: Some(status),
+            message: format!("{} {}", status, response.status_text()),
+            body: Some(read_body(response)),
+        }),
+        Err(ureq::Error::Transport(e)) if e.kind() == ureq::ErrorKind::ConnectionFailed => Err(BackendError::new(
+            BackendErrorKind::Unavailable,
+            format!("Backend is not reachable: {}", e),
+        )),
+        Err(ureq::Error::Transport(e)) if is_timeout(&e) => Err(BackendError::new(
+            BackendErrorKind::Timeout,
+            format!("Backend request timed out: {}", e),
+        )),
+        Err(ureq::Error::Transport(e)) => Err(BackendError::new(
+            BackendErrorKind::Transport,
+            format!("Backend request failed: {}", e),
+        )),
+    }
+}
+
+/// Restarts in progress
+fn restarts() -> &'static watch::Sender<usize> {
+    static RESTARTS: OnceLock<watch::Sender<usize>> = OnceLock::new();
+    RESTARTS.get_or_init(|| watch::channel(0).0)
+}
+
+/// Held while the shell stops and restarts the backend, until it answers
+/// again or the restart has failed. Webview requests made meanwhile wait for
+/// it to be dropped instead of failing.
+pub(crate) struct RestartGuard(());
+
+impl Drop for RestartGuard {
+    fn drop(&mut self) {
+        restarts().send_modify(|count| *count = count.saturating_sub(1));
+    }
+}
+
+pub(crate) fn begin_restart() -> RestartGuard {
+    restarts().send_modify(|count| *count += 1);
+    RestartGuard(())
+}
+
+/// Wait until no restart is in progress, up to `RESTART_WAIT`
+async fn wait_for_restart(method: &str, path: &str) -> Result<(), BackendError> {
+    let mut restarts = restarts().subscribe();
+    if *restarts.borrow() == 0 {
+        return Ok(());
+    }
+    if WAITING.fetch_add(1, Ordering::SeqCst) >= MAX_WAITING {
+        WAITING.fetch_sub(1, Ordering::SeqCst);
+        return Err(BackendError::new(
+            BackendErrorKind::Unavailable,
+            "The backend is restarting and too many requests are waiting for it",
+        ));
+    }
+    debug!("Holding {} {} until the backend has restarted", method, path);
+    let finished = timeout(RESTART_WAIT, restarts.wait_for(|count| *count == 0))
+        .await
+        .is_ok_and(|changed| changed.is_ok());
+    WAITING.fetch_sub(1, Ordering::SeqCst);
+    if !finished {
+        return Err(BackendError::new(
+            BackendErrorKind::Unavailable,
+            "The backend is still restarting",
+        ));
+    }
+    Ok(())
+}
+
+/// Blocking backend call authenticated with the shell's token, for the
+/// shell's own use (e.g. backups)
+pub(crate) fn call(
+    config: &DesktopConfig,
+    method: &str,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<BackendResponse, BackendError> {
+    request(config, method, path, body, false)
+}
+
+fn request(
+    config: &DesktopConfig,
+    method: &str,
+    path: &str,
+    body: Option<&Value>,
+    proxied: bool,
+) -> Result<BackendResponse, BackendError> {
+    let method = method.to_ascii_uppercase();
+    if !METHODS.contains(&method.as_str()) {
+        return Err(BackendError::new(
+            BackendErrorKind::InvalidRequest,
+            format!("Unsupported method: {}", method),
+        ));
+    }
+    validate_path(path)?;
+    send(&format!("{}{}", config.backend_url, path), &config.backend_token, &method, body, proxied)
+}
+
+/// Call the backend on the webview's behalf, authenticated with the shell's
+/// token. `path` is relative to the backend root, e.g. `/api/entries/`.
+/// While the shell restarts the backend the request waits and is sent once
+/// it answers again; refused connections are retried as well. Only reads are
+/// sent in demo mode.
+#[tauri::command]
+pub async fn backend_request(
+    app: AppHandle,
+    method: String,
+    path: String,
+    body: Option<Value>,
+) -> Result<BackendResponse, ShellError> {
+    let route = validate_webview_path(&path)?;
+    if SHELL_ONLY_PATHS.contains(&route) {
+        return Err(ShellError::invalid_input(format!("{} is not available to the webview", route)));
+    }
+    wait_for_restart(&method, route).await?;
+    if !crate::demo_mode::allows(&method) {
+        return Err(ShellError::invalid_input(format!(
+            "{} {} is not allowed in read-only demo mode",
+            method, route
+        )));
+    }
+    let config = app.state::<DesktopConfig>().inner().clone();
+    async_runtime::spawn_blocking(move || request(&config, &method, &path, body.as_ref(), true))
+        .await
+        .map_err(|e| ShellError::internal(format!("Backend request task failed: {}", e)))?
+        .map_err(ShellError::from)
+}
@@ -0,0 +1,183 @@
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::{Message, WebSocket};
+
+use crate::backend_client::TOKEN_HEADER;
+use crate::{event_batch, DesktopConfig};
+
+const EVENTS_PATH: &str = "/api/events";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The backend pings every 20 seconds; this much silence means it's gone
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Reconnect delay after a dropped or failed connection, doubling up to `MAX_RECONNECT_DELAY`
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// One item of the `entry-updated` payload
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryUpdated {
+    pub id: i64,
+    pub action: EntryAction,
+}
+
+/// Payload of `reminder-due`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderDue {
+    pub id: i64,
+    pub entry_id: i64,
+    pub reminder_datetime: String,
+}
+
+/// A message from the backend's event socket, `{"type": …, "data": …}`
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", content = "data")]
+enum BackendEvent {
+    #[serde(rename = "entry-updated")]
+    EntryUpdated(EntryUpdated),
+    #[serde(rename = "reminder-due")]
+    ReminderDue(ReminderDue),
+}
+
+/// Health of the backend as last seen by the supervisor. The bridge only
+/// connects while it's healthy, so it doesn't hammer a backend that's down.
+#[derive(Default)]
+pub struct BackendEventsState {
+    healthy: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl BackendEventsState {
+    fn wait_until_healthy(&self) {
+        let Ok(healthy) = self.healthy.lock() else {
+            return;
+        };
+        drop(self.changed.wait_while(healthy, |healthy| !*healthy));
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy.lock().map(|healthy| *healthy).unwrap_or(false)
+    }
+}
+
+/// Called by the supervisor whenever the backend's health changes
+pub fn set_backend_healthy(app: &AppHandle, healthy: bool) {
+    let Some(state) = app.try_state::<BackendEventsState>() else {
+        return;
+    };
+    if let Ok(mut current) = state.healthy.lock() {
+        *current = healthy;
+    }
+    state.changed.notify_all();
+}
+
+fn connect(config: &DesktopConfig) -> Result<WebSocket<TcpStream>, String> {
+    let authority = config
+        .backend_url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Unsupported backend URL: {}", config.backend_url))?;
+    let address = authority
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", authority, e))?
+        .next()
+        .ok_or_else(|| format!("No address for {}", authority))?;
+    let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|e| e.to_string())?;
+
+    let mut request = format!("ws://{}{}", authority, EVENTS_PATH)
+        .into_client_request()
+        .map_err(|e| e.to_string())?;
+    let token = HeaderValue::from_str(&config.backend_token).map_err(|e| e.to_string())?;
+    request.headers_mut().insert(TOKEN_HEADER, token);
+    let (socket, _) = tungstenite::client(request, stream).map_err(|e| e.to_string())?;
+    Ok(socket)
+}
+
+fn forward(app: &AppHandle, text: &str) {
+    let result = match serde_json::from_str::<BackendEvent>(text) {
+        Ok(BackendEvent::EntryUpdated(payload)) => {
+            event_batch::emit(app, "entry-updated", payload);
+            return;
+        }
+        Ok(BackendEvent::ReminderDue(payload)) => app.emit("reminder-due", payload),
+        Err(e) => {
+            debug!("Ignoring backend event: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = result {
+        warn!("Failed to emit backend event: {}", e);
+    }
+}
+
+/// Read until the connection drops
+fn listen(app: &AppHandle, socket: &mut WebSocket<TcpStream>) -> String {
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => forward(app, &text),
+            Ok(Message::Close(_)) => return "closed by backend".to_string(),
+            // Pings are answered by tungstenite itself
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                return "no ping from backend".to_string()
+            }
+            Err(e) => return e.to_string(),
+        }
+    }
+}
+
+/// Keep a WebSocket open to the backend's event endpoint and re-emit its
+/// messages as `entry-updated` and `reminder-due`. Reconnects with backoff
+/// whenever the connection drops, but only while the backend is healthy.
+pub fn init_backend_events(app: &AppHandle) {
+    app.manage(BackendEventsState::default());
+    let app = app.clone();
+    let spawned = thread::Builder::new().name("backend-events".into()).spawn(move || {
+        let state = app.state::<BackendEventsState>();
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            state.wait_until_healthy();
+            let config = app.state::<DesktopConfig>().inner().clone();
+            match connect(&config) {
+                Ok(mut socket) => {
+                    info!("Connected to backend events");
+                    delay = MIN_RECONNECT_DELAY;
+                    let reason = listen(&app, &mut socket);
+                    info!("Backend events disconnected: {}", reason);
+                }
+                Err(e) => {
+                    debug!("Backend events unavailable: {}; retrying in {:?}", e, delay);
+                }
+            }
+            thread::sleep(delay);
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            if !state.is_healthy() {
+                delay = MIN_RECONNECT_DELAY;
+            }
+        }
+    });
+    if let Err(e) = spawned {
+        warn!("Failed to start backend events thread: {}", e);
+    }
+}
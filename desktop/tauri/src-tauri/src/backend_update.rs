@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+
+use crate::settings::SettingsState;
+use crate::updater::UpdateChannel;
+use crate::{event_batch, prefs, BackendProcess, DesktopConfig};
+
+/// Per-platform backend builds for a channel, published next to `latest.json`
+const MANIFEST_ASSET: &str = "backend.json";
+
+/// Under the app's local data dir: `versions/<version>/` holds each extracted
+/// backend and `versions.json` says which one runs
+const BACKEND_DIR: &str = "backend";
+const VERSIONS_FILE: &str = "versions.json";
+
+/// Index `n` upgrades a schema `n` versions.json to `n + 1`
+const VERSIONS_MIGRATIONS: &[prefs::Migration] = &[
+    // 0 -> 1: first layout
+    Ok,
+];
+
+/// Installed versions kept besides the running one, for rollback
+const KEEP_PREVIOUS: usize = 3;
+
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BackendManifest {
+    version: String,
+    /// Oldest app version whose shell can run this backend
+    min_app_version: Option<String>,
+    notes: Option<String>,
+    /// Keyed by `<platform>-<arch>`, e.g. `macos-aarch64`
+    platforms: HashMap<String, BackendArtifact>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct BackendArtifact {
+    /// `.tar.gz` of the PyInstaller output directory
+    url: String,
+    sha256: String,
+}
+
+/// Which downloaded backend runs, in place of the one bundled with the app
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct InstalledVersions {
+    schema_version: u32,
+    current: Option<String>,
+    /// Downloaded and verified; becomes `current` the next time the backend starts
+    staged: Option<String>,
+    /// Earlier versions still on disk, newest first
+    previous: Vec<String>,
+    /// Versions rolled back from; not offered again
+    rejected: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct BackendUpdater {
+    /// The release found by the last check, with the artifact for this platform
+    pending: Mutex<Option<(BackendManifest, BackendArtifact)>>,
+    installing: AtomicBool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendUpdateInfo {
+    pub available: bool,
+    pub channel: UpdateChannel,
+    /// Version of the backend that is running
+    pub current_version: String,
+    /// Version the app bundle ships with
+    pub bundled_version: String,
+    pub version: Option<String>,
+    pub notes: Option<String>,
+    /// Installed and waiting for the backend's next start
+    pub staged: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendVersions {
+    /// Version of the backend that is running
+    pub current: String,
+    pub bundled: String,
+    pub staged: Option<String>,
+    /// Downloaded versions `rollback_backend` can return to, newest first
+    pub previous: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    bytes_done: u64,
+    bytes_total: Option<u64>,
+}
+
+fn backend_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_local_data_dir()
+        .map(|dir| dir.join(BACKEND_DIR))
+        .map_err(|e| format!("App data directory unavailable: {}", e))
+}
+
+fn platform_key(config: &DesktopConfig) -> String {
+    format!("{}-{}", config.platform_dir, std::env::consts::ARCH)
+}
+
+fn bundled_version(app: &AppHandle) -> Version {
+    app.package_info().version.clone()
+}
+
+fn load_versions(dir: &Path) -> InstalledVersions {
+    match prefs::load_versioned(&dir.join(VERSIONS_FILE), VERSIONS_MIGRATIONS, None) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", VERSIONS_FILE, e);
+            InstalledVersions::default()
+        }),
+        Ok(None) => InstalledVersions::default(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", VERSIONS_FILE, e);
+            InstalledVersions::default()
+        }
+    }
+}
+
+/// Written atomically, so the switch to another version is all or nothing
+fn save_versions(dir: &Path, versions: &InstalledVersions) -> Result<(), String> {
+    let versions = InstalledVersions {
+        schema_version: VERSIONS_MIGRATIONS.len() as u32,
+        ..versions.clone()
+    };
+    let json = serde_json::to_string_pretty(&versions).map_err(|e| format!("Failed to serialize versions: {}", e))?;
+    prefs::write_atomic(&dir.join(VERSIONS_FILE), &json)
+}
+
+fn binary_path(dir: &Path, version: &str, config: &DesktopConfig) -> PathBuf {
+    dir.join("versions")
+        .join(version)
+        .join("track-the-thing-backend")
+        .join(config.binary_name)
+}
+
+/// Whether a downloaded backend should run instead of the bundled one. An app
+/// update bundles a newer backend, which then wins.
+fn supersedes_bundled(app: &AppHandle, version: &str) -> bool {
+    Version::parse(version).is_ok_and(|version| version > bundled_version(app))
+}
+
+/// Delete version directories that are neither running, staged nor kept
+fn prune(dir: &Path, versions: &InstalledVersions) {
+    let Ok(entries) = fs::read_dir(dir.join("versions")) else {
+        return;
+    };
+    let keep: Vec<&String> = versions
+        .current
+        .iter()
+        .chain(&versions.staged)
+        .chain(&versions.previous)
+        .collect();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !keep.contains(&&name) {
+            info!("Removing old backend {}", name);
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                warn!("Failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+/// Make a staged backend the current one. Only called while no backend runs,
+/// so an update never replaces the files of a running session.
+fn activate_staged(dir: &Path, versions: &mut InstalledVersions) -> Result<(), String> {
+    let Some(staged) = versions.staged.take() else {
+        return Ok(());
+    };
+    if let Some(current) = versions.current.replace(staged.clone()) {
+        versions.previous.retain(|version| *version != current && *version != staged);
+        versions.previous.insert(0, current);
+        versions.previous.truncate(KEEP_PREVIOUS);
+    }
+    save_versions(dir, versions)?;
+    info!("Backend {} activated", staged);
+    prune(dir, versions);
+    Ok(())
+}
+
+/// The downloaded backend to launch, if there is one newer than the bundled
+/// backend. Activates a staged update first; called by `spawn_backend`.
+pub(crate) fn installed_backend_path(app: &AppHandle, config: &DesktopConfig) -> Option<PathBuf> {
+    let dir = backend_dir(app).ok()?;
+    let mut versions = load_versions(&dir);
+    if let Err(e) = activate_staged(&dir, &mut versions) {
+        warn!("Failed to activate staged backend: {}", e);
+    }
+
+    let current = versions.current?;
+    if !supersedes_bundled(app, &current) {
+        info!("Bundled backend {} is newer than downloaded {}", bundled_version(app), current);
+        return None;
+    }
+    let path = binary_path(&dir, &current, config);
+    if path.exists() {
+        Some(path)
+    } else {
+        warn!("Downloaded backend {} is missing at {}", current, path.display());
+        None
+    }
+}
+
+/// Version of the backend that `installed_backend_path` resolves to
+fn running_version(app: &AppHandle, versions: &InstalledVersions) -> String {
+    versions
+        .current
+        .as_deref()
+        .filter(|version| supersedes_bundled(app, version))
+        .map(str::to_string)
+        .unwrap_or_else(|| bundled_version(app).to_string())
+}
+
+/// Version of the backend that runs (or would run) now
+pub(crate) fn current_backend_version(app: &AppHandle) -> String {
+    match backend_dir(app) {
+        Ok(dir) => running_version(app, &load_versions(&dir)),
+        Err(_) => bundled_version(app).to_string(),
+    }
+}
+
+fn fetch_manifest(url: &str) -> Result<BackendManifest, String> {
+    let value: Value = ureq::get(url)
+        .timeout(Duration::from_secs(30))
+        .call()
+        .map_err(|e| format!("Backend update check failed: {}", e))?
+        .into_json()
+        .map_err(|e| format!("Invalid backend manifest: {}", e))?;
+    serde_json::from_value(value).map_err(|e| format!("Invalid backend manifest: {}", e))
+}
+
+/// Stream `url` to `dest`, returning the SHA-256 of what was written
+fn download(app: &AppHandle, url: &str, dest: &Path) -> Result<String, String> {
+    let response = ureq::get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .call()
+        .map_err(|e| format!("Backend download failed: {}", e))?;
+    let bytes_total = response.header("Content-Length").and_then(|len| len.parse().ok());
+
+    let mut reader = response.into_reader();
+    let mut file = File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut bytes_done = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| format!("Backend download failed: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        file.write_all(&buf[..read])
+            .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+        bytes_done += read as u64;
+        event_batch::emit(app, "backend-update-progress", DownloadProgress { bytes_done, bytes_total });
+    }
+    file.sync_all().map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn unpack(archive: &Path, dest: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    archive.set_preserve_permissions(true);
+    archive.unpack(dest)
+}
+
+/// Download, verify and unpack a release, then stage it
+fn install(
+    app: &AppHandle,
+    config: &DesktopConfig,
+    manifest: &BackendManifest,
+    artifact: &BackendArtifact,
+) -> Result<(), String> {
+    let dir = backend_dir(app)?;
+    let downloads = dir.join("downloads");
+    fs::create_dir_all(&downloads).map_err(|e| format!("Failed to create {}: {}", downloads.display(), e))?;
+    let archive = downloads.join(format!("{}.tar.gz", manifest.version));
+
+    info!("Downloading backend {} from {}", manifest.version, artifact.url);
+    let checksum = download(app, &artifact.url, &archive);
+    let verified = checksum.and_then(|checksum| {
+        if checksum.eq_ignore_ascii_case(artifact.sha256.trim()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Backend {} failed checksum verification (expected {}, got {})",
+                manifest.version, artifact.sha256, checksum
+            ))
+        }
+    });
+    if let Err(e) = verified {
+        let _ = fs::remove_file(&archive);
+        return Err(e);
+    }
+
+    // Unpack beside the final location and rename, so a half-extracted
+    // backend never has its final name
+    let target = dir.join("versions").join(&manifest.version);
+    let partial = dir.join("versions").join(format!("{}.partial", manifest.version));
+    let _ = fs::remove_dir_all(&partial);
+    let unpacked = unpack(&archive, &partial).map_err(|e| format!("Failed to unpack backend: {}", e));
+    let _ = fs::remove_file(&archive);
+    unpacked?;
+    if !partial.join("track-the-thing-backend").join(config.binary_name).exists() {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(format!("Backend {} archive has no {}", manifest.version, config.binary_name));
+    }
+    let _ = fs::remove_dir_all(&target);
+    fs::rename(&partial, &target).map_err(|e| format!("Failed to install backend: {}", e))?;
+
+    let mut versions = load_versions(&dir);
+    versions.staged = Some(manifest.version.clone());
+    versions.rejected.retain(|version| *version != manifest.version);
+    save_versions(&dir, &versions)?;
+    prune(&dir, &versions);
+    Ok(())
+}
+
+fn backend_versions(app: &AppHandle, versions: &InstalledVersions) -> BackendVersions {
+    BackendVersions {
+        current: running_version(app, versions),
+        bundled: bundled_version(app).to_string(),
+        staged: versions.staged.clone(),
+        previous: versions.previous.clone(),
+    }
+}
+
+/// Point `versions.json` at the previous download, or at the bundled backend
+/// when there is none. Drops any staged update and remembers the version left
+/// so it isn't offered again.
+fn roll_back(dir: &Path, versions: &mut InstalledVersions) -> Result<String, String> {
+    let Some(from) = versions.current.take() else {
+        return Err("The bundled backend is running; there is nothing to roll back to".to_string());
+    };
+    versions.staged = None;
+    versions.current = (!versions.previous.is_empty()).then(|| versions.previous.remove(0));
+    if !versions.rejected.contains(&from) {
+        versions.rejected.push(from.clone());
+    }
+    save_versions(dir, versions)?;
+    prune(dir, versions);
+    Ok(from)
+}
+
+pub fn init_backend_updater(app: &AppHandle) {
+    app.manage(BackendUpdater::default());
+}
+
+/// Ask the update channel for a newer backend build for this platform. The
+/// result is remembered for `install_backend_update`.
+#[tauri::command]
+pub async fn check_backend_update(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    updater: State<'_, BackendUpdater>,
+    config: State<'_, DesktopConfig>,
+) -> Result<BackendUpdateInfo, String> {
+    let channel = settings.current().updates.channel;
+    let url = channel.asset_url(MANIFEST_ASSET);
+    let manifest = async_runtime::spawn_blocking(move || fetch_manifest(&url))
+        .await
+        .map_err(|e| format!("Backend update check panicked: {}", e))??;
+
+    let versions = load_versions(&backend_dir(&app)?);
+    let running = running_version(&app, &versions);
+    let newest_installed = versions.staged.clone().unwrap_or_else(|| running.clone());
+
+    let newer = match (Version::parse(&manifest.version), Version::parse(&newest_installed)) {
+        (Ok(offered), Ok(installed)) => offered > installed,
+        _ => return Err(format!("Invalid backend version {:?}", manifest.version)),
+    };
+    let rejected = versions.rejected.contains(&manifest.version);
+    if newer && rejected {
+        info!("Backend {} was rolled back; not offering it again", manifest.version);
+    }
+    let newer = newer && !rejected;
+    let compatible = manifest
+        .min_app_version
+        .as_deref()
+        .map_or(true, |min| Version::parse(min).is_ok_and(|min| bundled_version(&app) >= min));
+    let artifact = manifest.platforms.get(&platform_key(&config)).cloned();
+    if newer && !compatible {
+        info!("Backend {} needs a newer app; skipping", manifest.version);
+    }
+
+    let available = newer && compatible && artifact.is_some();
+    let info = BackendUpdateInfo {
+        available,
+        channel,
+        current_version: running,
+        bundled_version: bundled_version(&app).to_string(),
+        version: available.then(|| manifest.version.clone()),
+        notes: if available { manifest.notes.clone() } else { None },
+        staged: versions.staged,
+    };
+    if let Ok(mut pending) = updater.pending.lock() {
+        *pending = artifact.filter(|_| available).map(|artifact| (manifest, artifact));
+    }
+    Ok(info)
+}
+
+/// Download and verify the backend found by `check_backend_update` and stage
+/// it. It replaces the running backend the next time the backend starts, so
+/// a session is never cut short. Emits `backend-update-progress` while
+/// downloading and `backend-update-staged` with the version when done.
+#[tauri::command]
+pub async fn install_backend_update(
+    app: AppHandle,
+    updater: State<'_, BackendUpdater>,
+    config: State<'_, DesktopConfig>,
+) -> Result<String, String> {
+    let (manifest, artifact) = updater
+        .pending
+        .lock()
+        .map_err(|e| format!("Lock error: {}", e))?
+        .clone()
+        .ok_or_else(|| "No backend update to install; check for updates first".to_string())?;
+
+    if updater.installing.swap(true, Ordering::SeqCst) {
+        return Err("A backend update is already being installed".to_string());
+    }
+    let result = {
+        let (app, config, manifest) = (app.clone(), config.inner().clone(), manifest.clone());
+        async_runtime::spawn_blocking(move || install(&app, &config, &manifest, &artifact))
+            .await
+            .map_err(|e| format!("Backend install panicked: {}", e))
+            .and_then(|result| result)
+    };
+    updater.installing.store(false, Ordering::SeqCst);
+    result?;
+
+    if let Ok(mut pending) = updater.pending.lock() {
+        *pending = None;
+    }
+    info!("Backend {} staged for the next start", manifest.version);
+    if let Err(e) = app.emit("backend-update-staged", &manifest.version) {
+        warn!("Failed to emit backend-update-staged event: {}", e);
+    }
+    Ok(manifest.version)
+}
+
+/// Installed backend versions, for picking a rollback target
+#[tauri::command]
+pub async fn get_backend_versions(app: AppHandle) -> Result<BackendVersions, String> {
+    Ok(backend_versions(&app, &load_versions(&backend_dir(&app)?)))
+}
+
+/// Stop the backend, go back to the previously installed version (or the one
+/// bundled with the app) and start it again. For when a backend update breaks
+/// something; the version rolled back from isn't offered again.
+#[tauri::command]
+pub async fn rollback_backend(app: AppHandle, updater: State<'_, BackendUpdater>) -> Result<BackendVersions, String> {
+    if updater.installing.swap(true, Ordering::SeqCst) {
+        return Err("A backend update is being installed".to_string());
+    }
+    let result = rollback(&app).await;
+    updater.installing.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn rollback(app: &AppHandle) -> Result<BackendVersions, String> {
+    let dir = backend_dir(app)?;
+    let mut versions = load_versions(&dir);
+    if !versions.current.as_deref().is_some_and(|version| supersedes_bundled(app, version)) {
+        return Err("The bundled backend is running; there is nothing to roll back to".to_string());
+    }
+
+    let _restart = crate::backend_client::begin_restart();
+    app.state::<BackendProcess>().terminate();
+    let rolled_back = roll_back(&dir, &mut versions);
+    // Start whichever backend is now current, even if the switch failed
+    crate::start_backend(app)?;
+    let from = rolled_back?;
+    if !crate::wait_for_backend_health(app, BACKEND_START_TIMEOUT).await {
+        return Err("The backend did not come back up after rolling back".to_string());
+    }
+
+    let versions = backend_versions(app, &versions);
+    info!("Rolled back backend {} to {}", from, versions.current);
+    if let Err(e) = app.emit("backend-rolled-back", &versions) {
+        warn!("Failed to emit backend-rolled-back event: {}", e);
+    }
+    Ok(versions)
+}
@@ -0,0 +1,803 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+
+use crate::jobs::{self, CancelToken, Cancellable, JobKind, Priority, Progress};
+use crate::settings::{Settings, SettingsState};
+use crate::webview_state::{self, WebviewState};
+use crate::{backend_client, backend_update, backup_remote, data_dir, db_encryption, BackendProcess, DesktopConfig};
+
+const ARCHIVE_PREFIX: &str = "ttt-backup-";
+/// Media archives exist only until they've been uploaded
+const MEDIA_ARCHIVE_PREFIX: &str = "ttt-media-";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Marks the backup a restore takes of the data it replaces
+const PRE_RESTORE_TAG: &str = "-pre-restore";
+
+/// Default destination, inside the data dir
+const BACKUP_DIR: &str = "backups";
+
+/// Directories listed in the media manifest; the files themselves aren't copied
+const MEDIA_DIRS: &[&str] = &["uploads", "static"];
+
+/// Name of the database inside an archive
+const DATABASE_ENTRY: &str = "ttt_desktop.db";
+const MANIFEST_ENTRY: &str = "manifest.json";
+/// The webview's saved localStorage, see `webview_state`
+const WEBVIEW_STATE_ENTRY: &str = "webview-state.json";
+
+/// SQLite side files archived and restored with the database. A safety backup
+/// is a plain copy of a stopped database, so it may carry uncheckpointed pages
+/// or a hot journal; `-shm` is rebuilt by SQLite.
+const SIDE_SUFFIXES: &[&str] = &["-wal", "-journal"];
+
+/// Suffixes of every file that makes up the live database
+const DATABASE_FILES: &[&str] = &["", "-wal", "-journal", "-shm"];
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Manifests larger than this aren't ours
+const MAX_MANIFEST_BYTES: u64 = 16 * 1024 * 1024;
+
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Wait before retrying a scheduled backup that failed
+const RETRY_DELAY: chrono::Duration = chrono::Duration::minutes(15);
+
+/// Set while a backup or restore runs
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+static LAST_FAILURE: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    /// Taken by `restore_backup`; never pruned
+    pub pre_restore: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+enum RestorePhase {
+    Validating,
+    StoppingBackend,
+    Snapshotting,
+    /// Swapping in the database files
+    Restoring,
+    StartingBackend,
+    /// The restored database didn't start; the previous one is back
+    RollingBack,
+    /// The database is restored; media files were not touched
+    Done,
+}
+
+/// The parts of `manifest.json` a restore checks
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveManifest {
+    schema_version: u32,
+    database_encrypted: bool,
+    #[serde(default)]
+    media: Vec<ManifestMedia>,
+}
+
+/// A media file listed in `manifest.json`; only its path is needed to tell
+/// whether it's still on disk after a restore
+#[derive(Deserialize, Debug)]
+struct ManifestMedia {
+    path: String,
+}
+
+/// One file under `uploads/` or `static/`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MediaFile {
+    /// Relative to the data dir, `/`-separated
+    path: String,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+fn backup_dir(app: &AppHandle, settings: &Settings) -> PathBuf {
+    settings
+        .backup
+        .destination
+        .clone()
+        .unwrap_or_else(|| data_dir::current_data_dir(app).join(BACKUP_DIR))
+}
+
+/// Creation time and whether it's a pre-restore backup, from an archive name
+fn parse_name(file_name: &str) -> Option<(DateTime<Utc>, bool)> {
+    let stamp = file_name.strip_prefix(ARCHIVE_PREFIX)?.strip_suffix(ARCHIVE_SUFFIX)?;
+    let (stamp, pre_restore) = match stamp.strip_suffix(PRE_RESTORE_TAG) {
+        Some(stamp) => (stamp, true),
+        None => (stamp, false),
+    };
+    let time = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok()?;
+    Some((time.and_utc(), pre_restore))
+}
+
+/// Archives in `dir`, newest first. Anything not named like a backup is ignored.
+fn list(dir: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<BackupInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let (created_at, pre_restore) = parse_name(&file_name)?;
+            let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+            Some(BackupInfo {
+                path: entry.path().to_string_lossy().to_string(),
+                file_name,
+                created_at,
+                size_bytes: meta.len(),
+                pre_restore,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    backups
+}
+
+/// Delete all but the newest `keep` scheduled or manual archives
+fn prune(dir: &Path, keep: usize) {
+    let regular = list(dir).into_iter().filter(|backup| !backup.pre_restore);
+    for old in regular.skip(keep) {
+        match fs::remove_file(&old.path) {
+            Ok(()) => info!("Pruned backup {}", old.file_name),
+            Err(e) => warn!("Failed to prune backup {}: {}", old.file_name, e),
+        }
+    }
+}
+
+fn collect_media(root: &Path, dir: &Path, files: &mut Vec<MediaFile>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.is_dir() {
+            collect_media(root, &path, files);
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        files.push(MediaFile {
+            path: relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            size: meta.len(),
+            modified: meta.modified().ok().map(DateTime::<Utc>::from),
+        });
+    }
+}
+
+fn emit_restore_progress(app: &AppHandle, phase: RestorePhase) {
+    if let Err(e) = app.emit("backup-restore-progress", phase) {
+        warn!("Failed to emit backup-restore-progress event: {}", e);
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The saved webview state as an archive entry, if there is one
+fn webview_state_file(app: &AppHandle) -> Option<(PathBuf, String)> {
+    let path = webview_state::state_path(&data_dir::current_data_dir(app));
+    path.exists().then(|| (path, WEBVIEW_STATE_ENTRY.to_string()))
+}
+
+/// A fresh archive name in `dir`
+fn new_archive(dir: &Path, pre_restore: bool) -> Result<(DateTime<Utc>, String, PathBuf), String> {
+    let created_at = Utc::now();
+    let tag = if pre_restore { PRE_RESTORE_TAG } else { "" };
+    let file_name = format!(
+        "{}{}{}{}",
+        ARCHIVE_PREFIX,
+        created_at.format(STAMP_FORMAT),
+        tag,
+        ARCHIVE_SUFFIX
+    );
+    let dest = dir.join(&file_name);
+    if dest.exists() {
+        return Err("A backup was made less than a second ago".to_string());
+    }
+    Ok((created_at, file_name, dest))
+}
+
+fn manifest(app: &AppHandle, created_at: DateTime<Utc>, pre_restore: bool) -> Result<Vec<u8>, String> {
+    let data_dir = data_dir::current_data_dir(app);
+    let mut media = Vec::new();
+    for name in MEDIA_DIRS {
+        collect_media(&data_dir, &data_dir.join(name), &mut media);
+    }
+    let manifest = json!({
+        "schemaVersion": MANIFEST_VERSION,
+        "createdAt": created_at,
+        "appVersion": app.package_info().version.to_string(),
+        "backendVersion": backend_update::current_backend_version(app),
+        "databaseEncrypted": db_encryption::is_encrypted(app),
+        "preRestore": pre_restore,
+        "media": media,
+    });
+    serde_json::to_vec_pretty(&manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))
+}
+
+/// Write `files` (source, name in the archive) and the manifest to `dest`
+/// through a `.partial` file, so an interrupted backup never has its final name
+fn write_archive(dest: &Path, files: &[(PathBuf, String)], manifest: &[u8], cancel: &CancelToken) -> io::Result<()> {
+    let partial = with_suffix(dest, ".partial");
+    let written = (|| {
+        let encoder = GzEncoder::new(Cancellable::new(File::create(&partial)?, cancel), Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        for (path, name) in files {
+            archive.append_path_with_name(path, name)?;
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp().max(0) as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, MANIFEST_ENTRY, manifest)?;
+
+        archive.into_inner()?.finish()?.into_inner().sync_all()?;
+        fs::rename(&partial, dest)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+/// Archive every file under `uploads/` and `static/` for an off-site copy,
+/// through a `.partial` file like `write_archive`
+fn write_media_archive(data_dir: &Path, dest: &Path, cancel: &CancelToken) -> io::Result<()> {
+    let mut media = Vec::new();
+    for name in MEDIA_DIRS {
+        collect_media(data_dir, &data_dir.join(name), &mut media);
+    }
+    let partial = with_suffix(dest, ".partial");
+    let written = (|| {
+        let encoder = GzEncoder::new(Cancellable::new(File::create(&partial)?, cancel), Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        for file in &media {
+            archive.append_path_with_name(data_dir.join(&file.path), &file.path)?;
+        }
+        archive.into_inner()?.finish()?.into_inner().sync_all()?;
+        fs::rename(&partial, dest)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+fn emit_upload_failed(app: &AppHandle, error: &str) {
+    warn!("Remote backup failed: {}", error);
+    if let Err(e) = app.emit("backup-upload-failed", error) {
+        warn!("Failed to emit backup-upload-failed event: {}", e);
+    }
+}
+
+/// Mark a new archive for the remote target, with the media when
+/// `backup.remote.includeMedia` is on, and queue an `upload` job. A failed
+/// upload doesn't fail the backup; it's retried after the next one.
+fn queue_upload(
+    app: &AppHandle,
+    settings: &Settings,
+    dir: &Path,
+    archive: &Path,
+    created_at: DateTime<Utc>,
+    cancel: &CancelToken,
+) {
+    let remote = &settings.backup.remote;
+    if remote.target.is_none() {
+        return;
+    }
+    let mut files = vec![(archive.to_path_buf(), false)];
+    if remote.include_media {
+        let media = dir.join(format!(
+            "{}{}{}",
+            MEDIA_ARCHIVE_PREFIX,
+            created_at.format(STAMP_FORMAT),
+            ARCHIVE_SUFFIX
+        ));
+        match write_media_archive(&data_dir::current_data_dir(app), &media, cancel) {
+            Ok(()) => files.push((media, true)),
+            Err(_) if cancel.is_cancelled() => info!("Media archive cancelled; only the database is uploaded"),
+            Err(e) => emit_upload_failed(app, &format!("Failed to archive media for upload: {}", e)),
+        }
+    }
+
+    if let Err(e) = backup_remote::mark(&files) {
+        emit_upload_failed(app, &e);
+        return;
+    }
+    if let Err(e) = jobs::enqueue(app, JobKind::Upload, Priority::Low) {
+        warn!("Failed to queue the remote backup: {}", e);
+    }
+}
+
+/// Upload the archives pending for the remote target; run as an `upload` job
+pub(crate) async fn upload_pending(app: &AppHandle, cancel: CancelToken, progress: Progress) -> Result<usize, String> {
+    let settings = app.state::<SettingsState>().current();
+    let dir = backup_dir(app, &settings);
+    let result = {
+        let cancel = cancel.clone();
+        blocking(move || backup_remote::upload(&settings.backup.remote, &dir, &cancel, &progress)).await
+    };
+    match &result {
+        Ok(uploaded) => {
+            info!("Uploaded {} file(s) to the remote backup target", uploaded);
+            if let Err(e) = app.emit("backup-uploaded", uploaded) {
+                warn!("Failed to emit backup-uploaded event: {}", e);
+            }
+        }
+        Err(_) if cancel.is_cancelled() => info!("Remote backup cancelled; it resumes with the next upload"),
+        Err(e) => emit_upload_failed(app, e),
+    }
+    result
+}
+
+/// Snapshot the database through the backend, archive it with the media
+/// manifest, prune old archives and queue the upload of the new one
+fn run_backup(app: &AppHandle, cancel: &CancelToken) -> Result<BackupInfo, String> {
+    let settings = app.state::<SettingsState>().current();
+    let dir = backup_dir(app, &settings);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let (created_at, file_name, dest) = new_archive(&dir, false)?;
+
+    // The backend only writes snapshots to the backups folder of its data dir
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let response = backend_client::call(
+        &config,
+        "POST",
+        "/api/backup/snapshot",
+        Some(&json!({ "name": format!(".{}.db", file_name) })),
+    )
+    .map_err(|e| format!("Database snapshot failed: {}", e))?;
+    let snapshot = response
+        .body
+        .get("path")
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .ok_or("Database snapshot failed: the backend didn't say where it wrote it")?;
+    if let Err(e) = cancel.check() {
+        let _ = fs::remove_file(&snapshot);
+        return Err(e);
+    }
+
+    let mut files = vec![(snapshot.clone(), DATABASE_ENTRY.to_string())];
+    files.extend(webview_state_file(app));
+    let written = manifest(app, created_at, false).and_then(|manifest| {
+        write_archive(&dest, &files, &manifest, cancel).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+    });
+    let _ = fs::remove_file(&snapshot);
+    written?;
+
+    info!("Backup written to {}", dest.display());
+    prune(&dir, settings.backup.keep);
+    let info = info_for(&dest, file_name, created_at, false)?;
+    queue_upload(app, &settings, &dir, &dest, created_at, cancel);
+    Ok(info)
+}
+
+fn info_for(path: &Path, file_name: String, created_at: DateTime<Utc>, pre_restore: bool) -> Result<BackupInfo, String> {
+    let size_bytes = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    Ok(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        file_name,
+        created_at,
+        size_bytes,
+        pre_restore,
+    })
+}
+
+/// Copy the stopped backend's database files into a regular archive before
+/// they're replaced. It's never pruned, so a restore can always be undone.
+/// `None` when there's no database to save.
+fn safety_backup(app: &AppHandle, database: &Path) -> Result<Option<BackupInfo>, String> {
+    if !database.exists() {
+        return Ok(None);
+    }
+    let settings = app.state::<SettingsState>().current();
+    let dir = backup_dir(app, &settings);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let (created_at, file_name, dest) = new_archive(&dir, true)?;
+
+    let mut files = vec![(database.to_path_buf(), DATABASE_ENTRY.to_string())];
+    for suffix in SIDE_SUFFIXES {
+        let side = with_suffix(database, suffix);
+        if side.exists() {
+            files.push((side, format!("{}{}", DATABASE_ENTRY, suffix)));
+        }
+    }
+    files.extend(webview_state_file(app));
+    let manifest = manifest(app, created_at, true)?;
+    write_archive(&dest, &files, &manifest, &CancelToken::default())
+        .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+
+    info!("Pre-restore backup written to {}", dest.display());
+    info_for(&dest, file_name, created_at, true).map(Some)
+}
+
+/// What `stage_restore` unpacked and read from an archive
+struct StagedRestore {
+    /// Staged database files and their final paths
+    files: Vec<(PathBuf, PathBuf)>,
+    webview: Option<WebviewState>,
+    /// Media paths in the manifest, relative to the data dir
+    media: Vec<String>,
+}
+
+/// Unpack the database files from `archive` next to `database`, as
+/// `<name>.restoring`, and check they can be opened. Returns the staged files
+/// with their final paths, the webview state saved with them and the media
+/// the manifest lists.
+fn stage_restore(archive: &Path, database: &Path) -> Result<StagedRestore, String> {
+    let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut webview = None;
+    let mut media = Vec::new();
+    let result = (|| {
+        let file = File::open(archive).map_err(|e| format!("Cannot open {}: {}", archive.display(), e))?;
+        let mut entries = tar::Archive::new(GzDecoder::new(file));
+        let mut manifest = None;
+        for entry in entries.entries().map_err(|e| format!("Not a backup archive: {}", e))? {
+            let mut entry = entry.map_err(|e| format!("Corrupt backup archive: {}", e))?;
+            let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+            if name == MANIFEST_ENTRY {
+                let mut text = String::new();
+                entry
+                    .by_ref()
+                    .take(MAX_MANIFEST_BYTES)
+                    .read_to_string(&mut text)
+                    .map_err(|e| format!("Corrupt backup manifest: {}", e))?;
+                let parsed: ArchiveManifest =
+                    serde_json::from_str(&text).map_err(|e| format!("Corrupt backup manifest: {}", e))?;
+                manifest = Some(parsed);
+                continue;
+            }
+            if name == WEBVIEW_STATE_ENTRY {
+                let mut text = String::new();
+                entry
+                    .by_ref()
+                    .take(webview_state::MAX_STATE_BYTES)
+                    .read_to_string(&mut text)
+                    .map_err(|e| format!("Corrupt backup archive: {}", e))?;
+                // Drafts aren't worth failing a restore over
+                webview = webview_state::parse(&text).map_err(|e| warn!("{}", e)).ok();
+                continue;
+            }
+            let Some(suffix) = name.strip_prefix(DATABASE_ENTRY) else {
+                continue;
+            };
+            if !suffix.is_empty() && !SIDE_SUFFIXES.contains(&suffix) {
+                continue;
+            }
+            let target = with_suffix(database, suffix);
+            let stage = with_suffix(&target, ".restoring");
+            let mut out = File::create(&stage).map_err(|e| format!("Cannot write {}: {}", stage.display(), e))?;
+            staged.push((stage.clone(), target));
+            io::copy(&mut entry, &mut out)
+                .and_then(|_| out.sync_all())
+                .map_err(|e| format!("Failed to unpack the database: {}", e))?;
+        }
+
+        let manifest: ArchiveManifest = manifest.ok_or("The archive has no manifest.json; it isn't a backup")?;
+        media = manifest.media.iter().map(|file| file.path.clone()).collect();
+        if manifest.schema_version > MANIFEST_VERSION {
+            return Err("The backup was made by a newer version of the app".to_string());
+        }
+        let stage = with_suffix(database, ".restoring");
+        if !staged.iter().any(|(staged, _)| *staged == stage) {
+            return Err("The archive has no database".to_string());
+        }
+        match db_encryption::is_plaintext(&stage) {
+            Ok(Some(true)) if manifest.database_encrypted => {
+                Err("The archive's database doesn't match its manifest".to_string())
+            }
+            Ok(Some(true)) => Ok(()),
+            Ok(Some(false)) if !manifest.database_encrypted => {
+                Err("The archive's database is not a SQLite database".to_string())
+            }
+            Ok(Some(false)) if !db_encryption::has_key()? => {
+                Err("The backup is encrypted and the keychain has no database key".to_string())
+            }
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Failed to read the unpacked database: {}", e)),
+        }
+    })();
+    match result {
+        Ok(()) => Ok(StagedRestore {
+            files: staged,
+            webview,
+            media,
+        }),
+        Err(e) => {
+            discard(&staged);
+            Err(e)
+        }
+    }
+}
+
+fn discard(staged: &[(PathBuf, PathBuf)]) {
+    for (stage, _) in staged {
+        let _ = fs::remove_file(stage);
+    }
+}
+
+/// Move the current database files aside as `<name>.pre-restore` and the
+/// staged ones into place. Returns the files moved aside; on failure
+/// everything is put back.
+fn swap_in(database: &Path, staged: &[(PathBuf, PathBuf)]) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut aside = Vec::new();
+    let mut placed = Vec::new();
+    let result = (|| {
+        for suffix in DATABASE_FILES {
+            let current = with_suffix(database, suffix);
+            if current.exists() {
+                let saved = with_suffix(&current, ".pre-restore");
+                fs::rename(&current, &saved).map_err(|e| format!("Failed to move {} aside: {}", current.display(), e))?;
+                aside.push((saved, current));
+            }
+        }
+        for (stage, target) in staged {
+            fs::rename(stage, target).map_err(|e| format!("Failed to restore {}: {}", target.display(), e))?;
+            placed.push(target.clone());
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => Ok(aside),
+        Err(e) => {
+            for target in &placed {
+                let _ = fs::remove_file(target);
+            }
+            put_back(&aside);
+            discard(staged);
+            Err(e)
+        }
+    }
+}
+
+/// Return the files `swap_in` moved aside
+fn put_back(aside: &[(PathBuf, PathBuf)]) {
+    for (saved, original) in aside {
+        if let Err(e) = fs::rename(saved, original) {
+            warn!("Failed to put {} back: {}", original.display(), e);
+        }
+    }
+}
+
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Backup task panicked: {}", e))
+        .and_then(|result| result)
+}
+
+/// Run a backup unless one is already being written
+pub(crate) async fn backup(app: &AppHandle, cancel: CancelToken) -> Result<BackupInfo, String> {
+    crate::demo_mode::refuse("Backing up")?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A backup or restore is already running".to_string());
+    }
+    let result = {
+        let app = app.clone();
+        let cancel = cancel.clone();
+        blocking(move || run_backup(&app, &cancel)).await
+    };
+    RUNNING.store(false, Ordering::SeqCst);
+
+    // A failure holds off the schedule for `RETRY_DELAY`
+    if let Ok(mut failure) = LAST_FAILURE.lock() {
+        *failure = result.as_ref().err().filter(|_| !cancel.is_cancelled()).map(|_| Utc::now());
+    }
+    match &result {
+        Ok(backup) => {
+            if let Err(e) = app.emit("backup-completed", backup) {
+                warn!("Failed to emit backup-completed event: {}", e);
+            }
+        }
+        Err(_) if cancel.is_cancelled() => info!("Backup cancelled"),
+        Err(e) => {
+            if let Err(e) = app.emit("backup-failed", e) {
+                warn!("Failed to emit backup-failed event: {}", e);
+            }
+        }
+    }
+    result
+}
+
+async fn restore(app: &AppHandle, archive: PathBuf) -> Result<Option<BackupInfo>, String> {
+    let database = db_encryption::database_path(app);
+
+    emit_restore_progress(app, RestorePhase::Validating);
+    let StagedRestore {
+        files: staged,
+        webview,
+        media,
+    } = {
+        let database = database.clone();
+        blocking(move || stage_restore(&archive, &database)).await?
+    };
+
+    emit_restore_progress(app, RestorePhase::StoppingBackend);
+    let _restart = backend_client::begin_restart();
+    app.state::<BackendProcess>().terminate();
+
+    emit_restore_progress(app, RestorePhase::Snapshotting);
+    let swapped = {
+        let (app, database, staged) = (app.clone(), database.clone(), staged.clone());
+        blocking(move || {
+            let safety = safety_backup(&app, &database);
+            if safety.is_err() {
+                discard(&staged);
+            }
+            let safety = safety?;
+            emit_restore_progress(&app, RestorePhase::Restoring);
+            swap_in(&database, &staged).map(|aside| (safety, aside))
+        })
+        .await
+    };
+    let (safety, aside) = match swapped {
+        Ok(swapped) => swapped,
+        Err(e) => {
+            // Nothing was replaced, so bring the old backend back up
+            if let Err(restart) = crate::start_backend(app) {
+                warn!("{}", restart);
+            }
+            return Err(e);
+        }
+    };
+
+    emit_restore_progress(app, RestorePhase::StartingBackend);
+    let started = match crate::start_backend(app) {
+        Ok(()) => crate::wait_for_backend_health(app, BACKEND_START_TIMEOUT).await,
+        Err(e) => {
+            warn!("{}", e);
+            false
+        }
+    };
+    if !started {
+        emit_restore_progress(app, RestorePhase::RollingBack);
+        app.state::<BackendProcess>().terminate();
+        // Including any side files the backend created for the restored database
+        for suffix in DATABASE_FILES {
+            let _ = fs::remove_file(with_suffix(&database, suffix));
+        }
+        put_back(&aside);
+        crate::start_backend(app)?;
+        return Err("The backend did not start on the restored database; the previous data was put back".to_string());
+    }
+
+    for (saved, _) in &aside {
+        if let Err(e) = fs::remove_file(saved) {
+            warn!("Failed to remove {}: {}", saved.display(), e);
+        }
+    }
+    info!("Database restored from backup");
+    // Drafts cached for the replaced data would otherwise be shown over it
+    webview_state::replace(app, webview.map(|state| state.items));
+    // Archives hold only the media manifest, so files the restored entries
+    // point at may be gone; say which rather than leave broken attachments
+    let data_dir = data_dir::current_data_dir(app);
+    let missing: Vec<String> = media.into_iter().filter(|path| !data_dir.join(path).exists()).collect();
+    if !missing.is_empty() {
+        warn!("{} media file(s) listed in the backup are missing", missing.len());
+        if let Err(e) = app.emit("backup-restore-missing-media", &missing) {
+            warn!("Failed to emit backup-restore-missing-media event: {}", e);
+        }
+    }
+    emit_restore_progress(app, RestorePhase::Done);
+    Ok(safety)
+}
+
+/// Queue a backup whenever the newest scheduled or manual one is older than
+/// `backup.intervalHours`
+pub fn init_backups(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(TICK).await;
+            let settings = app.state::<SettingsState>().current();
+            if !settings.backup.enabled || RUNNING.load(Ordering::SeqCst) {
+                continue;
+            }
+            let now = Utc::now();
+            let interval = chrono::Duration::hours(settings.backup.interval_hours as i64);
+            let latest = list(&backup_dir(&app, &settings))
+                .into_iter()
+                .find(|backup| !backup.pre_restore)
+                .map(|backup| backup.created_at);
+            let failed = LAST_FAILURE.lock().ok().and_then(|failure| *failure);
+            let due = latest.map_or(true, |latest| now - latest >= interval);
+            let retry_wait = failed.is_some_and(|failed| now - failed < RETRY_DELAY);
+            if !due || retry_wait {
+                continue;
+            }
+
+            // A backup already waiting in the queue is reused
+            if let Err(e) = jobs::enqueue(&app, JobKind::Backup, Priority::Low) {
+                warn!("Failed to queue the scheduled backup: {}", e);
+            }
+        }
+    });
+}
+
+/// Back up the database and media manifest now, then prune old backups
+#[tauri::command]
+pub async fn run_backup_now(app: AppHandle) -> Result<BackupInfo, String> {
+    backup(&app, CancelToken::default()).await
+}
+
+/// Queue `run_backup_now` as a high-priority job that `cancel_job` can stop.
+/// The upload is a job of its own. Returns the job id.
+#[tauri::command]
+pub async fn start_backup(app: AppHandle) -> Result<jobs::JobId, String> {
+    jobs::enqueue(&app, JobKind::Backup, Priority::High)
+}
+
+/// Replace the database with the one in a backup archive. Only the database
+/// (and the webview state) is restored: archives don't hold media files, so
+/// `uploads/` and `static/` are left as they are, and media the archive's
+/// manifest lists but that is missing on disk is reported as a
+/// `backup-restore-missing-media` event. The current database is saved as a
+/// new backup first, which is returned, and is put back if the backend doesn't
+/// start on the restored one. Progress is reported as `backup-restore-progress`
+/// events.
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, path: String) -> Result<Option<BackupInfo>, String> {
+    crate::demo_mode::refuse("Restoring a backup")?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A backup or restore is already running".to_string());
+    }
+    info!("Restoring backup {}", path);
+    let result = restore(&app, PathBuf::from(path)).await;
+    RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Backups in the configured destination, newest first
+#[tauri::command]
+pub async fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let dir = backup_dir(&app, &app.state::<SettingsState>().current());
+    async_runtime::spawn_blocking(move || list(&dir))
+        .await
+        .map_err(|e| format!("Listing backups panicked: {}", e))
+}
@@ -0,0 +1,569 @@
+//! Off-site copies of backups, on a WebDAV server or in an S3-compatible
+//! bucket. Every file is recorded as pending before it's sent and only
+//! forgotten once the copy on the remote has been checked, so a failed or
+//! interrupted upload is picked up again after the next backup. Large files
+//! go to S3 as a multipart upload that continues from the last part sent.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::jobs::{CancelToken, Cancellable, Progress};
+use crate::prefs;
+use crate::settings::RemoteBackupSettings;
+
+const KEYCHAIN_SERVICE: &str = "com.trackthething.desktop";
+const KEYCHAIN_ACCOUNT: &str = "remote-backup";
+
+/// Written next to a file until its upload has been verified
+const STATE_SUFFIX: &str = ".upload.json";
+
+/// Files larger than this go to S3 in parts of this size; S3's minimum is 5 MiB
+const PART_SIZE: u64 = 16 * 1024 * 1024;
+
+const DEFAULT_REGION: &str = "us-east-1";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Per request, so per part for multipart uploads
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Longest error body quoted from the remote
+const MAX_ERROR_CHARS: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteBackupTarget {
+    #[serde(rename = "webdav")]
+    WebDav,
+    #[serde(rename = "s3")]
+    S3,
+}
+
+/// WebDAV user name and password, or S3 access key ID and secret key
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Credentials {
+    username: String,
+    secret: String,
+}
+
+/// A file waiting to be uploaded, and how far an S3 multipart upload of it got
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+struct UploadState {
+    /// Remove the local file once the remote copy is verified
+    delete_after: bool,
+    key: String,
+    /// Object URL the upload below was started for
+    url: String,
+    size: u64,
+    sha256: String,
+    upload_id: Option<String>,
+    /// ETags of the parts sent so far, in order
+    parts: Vec<String>,
+}
+
+struct Remote {
+    target: RemoteBackupTarget,
+    settings: RemoteBackupSettings,
+    credentials: Credentials,
+    agent: ureq::Agent,
+    cancel: CancelToken,
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+fn load_credentials() -> Result<Option<Credentials>, String> {
+    match keychain_entry()?.get_password() {
+        Ok(text) => serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| format!("Corrupt remote backup credentials in the keychain: {}", e)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read remote backup credentials from the keychain: {}", e)),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters, and `/` when
+/// `keep_slash` is set, as SigV4 requires
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Text of the first `<tag>` element in an S3 XML response
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].to_string())
+}
+
+fn remote_error(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let text = response.into_string().unwrap_or_default();
+            let message = xml_value(&text, "Message").unwrap_or(text);
+            let message: String = message.chars().take(MAX_ERROR_CHARS).collect();
+            if message.trim().is_empty() {
+                format!("Remote backup target answered {}", status)
+            } else {
+                format!("Remote backup target answered {}: {}", status, message.trim())
+            }
+        }
+        ureq::Error::Transport(e) => format!("Remote backup target is not reachable: {}", e),
+    }
+}
+
+fn read_text(response: ureq::Response) -> Result<String, String> {
+    response
+        .into_string()
+        .map_err(|e| format!("Failed to read the remote's response: {}", e))
+}
+
+/// Size and SHA-256 of a file
+fn hash_file(path: &Path) -> Result<(u64, String), String> {
+    let mut file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok((size, to_hex(&hasher.finalize())))
+}
+
+fn state_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(STATE_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn save_state(path: &Path, state: &UploadState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| format!("Failed to serialize upload state: {}", e))?;
+    prefs::write_atomic(path, &json)
+}
+
+/// Files in `dir` with an upload pending, oldest first
+fn pending(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(STATE_SUFFIX).map(|file| dir.join(file))
+        })
+        .collect();
+    // Archive names start with their UTC time
+    files.sort();
+    files
+}
+
+impl Remote {
+    /// `None` when no target is configured
+    fn new(settings: &RemoteBackupSettings, cancel: &CancelToken) -> Result<Option<Self>, String> {
+        let Some(target) = settings.target else {
+            return Ok(None);
+        };
+        let credentials = load_credentials()?.ok_or("No remote backup credentials in the keychain")?;
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build();
+        Ok(Some(Self {
+            target,
+            settings: settings.clone(),
+            credentials,
+            agent,
+            cancel: cancel.clone(),
+        }))
+    }
+
+    fn endpoint(&self) -> &str {
+        self.settings.url.as_deref().unwrap_or_default().trim_end_matches('/')
+    }
+
+    fn bucket(&self) -> &str {
+        self.settings.bucket.as_deref().unwrap_or_default()
+    }
+
+    /// Name on the remote, relative to the collection or bucket
+    fn key(&self, path: &Path) -> String {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        format!("{}{}", self.settings.prefix.as_deref().unwrap_or_default(), name)
+    }
+
+    fn url(&self, key: &str) -> String {
+        match self.target {
+            RemoteBackupTarget::WebDav => format!("{}/{}", self.endpoint(), uri_encode(key, true)),
+            RemoteBackupTarget::S3 => format!("{}/{}/{}", self.endpoint(), self.bucket(), uri_encode(key, true)),
+        }
+    }
+
+    fn webdav(&self, method: &str, url: &str) -> ureq::Request {
+        let login = format!("{}:{}", self.credentials.username, self.credentials.secret);
+        self.agent
+            .request(method, url)
+            .set("Authorization", &format!("Basic {}", BASE64.encode(login)))
+    }
+
+    /// Send a SigV4-signed request for `key`, path-style. `headers` are
+    /// signed along with the host, date and payload hash.
+    fn s3(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<ureq::Response, ureq::Error> {
+        let (scheme, host) = self.endpoint().split_once("://").unwrap_or(("https", self.endpoint()));
+        let region = self.settings.region.as_deref().unwrap_or(DEFAULT_REGION);
+        let path = format!("/{}/{}", self.bucket(), uri_encode(key, true));
+
+        let mut query: Vec<String> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", uri_encode(name, false), uri_encode(value, false)))
+            .collect();
+        query.sort();
+        let query = query.join("&");
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let day = now.format("%Y%m%d").to_string();
+        let payload_hash = to_hex(&Sha256::digest(body));
+
+        let mut signed: Vec<(String, String)> = vec![
+            ("host".to_string(), host.to_string()),
+            ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        signed.extend(headers.iter().map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string())));
+        signed.sort();
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_names = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_request = [method, &path, &query, &canonical_headers, &signed_names, &payload_hash].join("\n");
+
+        let scope = format!("{}/{}/s3/aws4_request", day, region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            to_hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = hmac(format!("AWS4{}", self.credentials.secret).as_bytes(), &day);
+        for part in [region, "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part);
+        }
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.username,
+            scope,
+            signed_names,
+            to_hex(&hmac(&signing_key, &string_to_sign))
+        );
+
+        let url = if query.is_empty() {
+            format!("{}://{}{}", scheme, host, path)
+        } else {
+            format!("{}://{}{}?{}", scheme, host, path, query)
+        };
+        let mut request = self
+            .agent
+            .request(method, &url)
+            .set("Host", host)
+            .set("Authorization", &authorization)
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("x-amz-date", &amz_date);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        request.send_bytes(body)
+    }
+
+    /// Upload a pending file and verify the remote copy. Returns false when
+    /// the file has gone (pruned before it could be sent).
+    fn upload_pending(&self, path: &Path) -> Result<bool, String> {
+        let state_path = state_path(path);
+        let mut state: UploadState = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        if !path.exists() {
+            self.abort(&state);
+            let _ = fs::remove_file(&state_path);
+            return Ok(false);
+        }
+
+        let key = self.key(path);
+        let url = self.url(&key);
+        let (size, sha256) = hash_file(path)?;
+        if state.url != url || state.size != size || state.sha256 != sha256 {
+            // A different file or target than the interrupted upload was for
+            self.abort(&state);
+            state = UploadState {
+                delete_after: state.delete_after,
+                key: key.clone(),
+                url,
+                size,
+                sha256,
+                ..UploadState::default()
+            };
+            save_state(&state_path, &state)?;
+        }
+
+        match self.target {
+            RemoteBackupTarget::WebDav => self.put_webdav(path, &key, &state)?,
+            RemoteBackupTarget::S3 => self.put_s3(path, &key, &mut state, &state_path)?,
+        }
+        self.verify(&key, &state)?;
+        info!("Uploaded {} to the remote backup target", key);
+
+        if state.delete_after {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+        let _ = fs::remove_file(&state_path);
+        Ok(true)
+    }
+
+    /// Drop an unfinished S3 multipart upload so its parts aren't billed
+    fn abort(&self, state: &UploadState) {
+        let Some(upload_id) = &state.upload_id else {
+            return;
+        };
+        // Not when it was started on a target that's no longer configured
+        if self.target != RemoteBackupTarget::S3 || state.url != self.url(&state.key) {
+            return;
+        }
+        if let Err(e) = self.s3("DELETE", &state.key, &[("uploadId", upload_id)], &[], &[]) {
+            warn!("Failed to abort the upload of {}: {}", state.key, remote_error(e));
+        }
+    }
+
+    /// Create the collections above `key`. 405 means one already exists.
+    fn make_collections(&self, key: &str) -> Result<(), String> {
+        let Some((dirs, _)) = key.rsplit_once('/') else {
+            return Ok(());
+        };
+        let mut url = self.endpoint().to_string();
+        for segment in dirs.split('/').filter(|segment| !segment.is_empty()) {
+            url = format!("{}/{}", url, uri_encode(segment, false));
+            match self.webdav("MKCOL", &format!("{}/", url)).call() {
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(e) => return Err(remote_error(e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// PUT to a `.partial` name and MOVE into place, so a broken transfer
+    /// never leaves a truncated file under the final name. WebDAV has no
+    /// standard way to resume, so a retry sends the whole file again.
+    fn put_webdav(&self, path: &Path, key: &str, state: &UploadState) -> Result<(), String> {
+        self.make_collections(key)?;
+        let partial = format!("{}.partial", state.url);
+        let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+        self.webdav("PUT", &partial)
+            .set("Content-Type", "application/octet-stream")
+            .set("Content-Length", &state.size.to_string())
+            .send(Cancellable::new(file, &self.cancel))
+            .map_err(remote_error)?;
+        self.webdav("MOVE", &partial)
+            .set("Destination", &state.url)
+            .set("Overwrite", "T")
+            .call()
+            .map_err(remote_error)?;
+        Ok(())
+    }
+
+    /// A single PUT for small files, otherwise a multipart upload that picks
+    /// up after the last part recorded in `state`
+    fn put_s3(&self, path: &Path, key: &str, state: &mut UploadState, state_path: &Path) -> Result<(), String> {
+        let mut file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+        let read_error = |e: io::Error| format!("Failed to read {}: {}", path.display(), e);
+        let metadata = [("x-amz-meta-sha256", state.sha256.as_str())];
+        if state.size <= PART_SIZE {
+            let mut body = Vec::new();
+            file.read_to_end(&mut body).map_err(read_error)?;
+            self.s3("PUT", key, &[], &metadata, &body).map_err(remote_error)?;
+            return Ok(());
+        }
+
+        let upload_id = match &state.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let response = self
+                    .s3("POST", key, &[("uploads", "")], &metadata, &[])
+                    .map_err(remote_error)?;
+                let upload_id = xml_value(&read_text(response)?, "UploadId")
+                    .ok_or("The remote didn't start a multipart upload")?;
+                state.upload_id = Some(upload_id.clone());
+                state.parts.clear();
+                save_state(state_path, state)?;
+                upload_id
+            }
+        };
+
+        let total = state.size.div_ceil(PART_SIZE);
+        file.seek(SeekFrom::Start(state.parts.len() as u64 * PART_SIZE))
+            .map_err(read_error)?;
+        for number in state.parts.len() as u64 + 1..=total {
+            // The parts sent so far are kept, so the next backup resumes here
+            self.cancel.check()?;
+            let mut part = Vec::with_capacity(PART_SIZE as usize);
+            file.by_ref().take(PART_SIZE).read_to_end(&mut part).map_err(read_error)?;
+            let number = number.to_string();
+            let query = [("partNumber", number.as_str()), ("uploadId", upload_id.as_str())];
+            let response = match self.s3("PUT", key, &query, &[], &part) {
+                Ok(response) => response,
+                Err(ureq::Error::Status(404, _)) => {
+                    // The remote expired the upload; start over next time
+                    state.upload_id = None;
+                    state.parts.clear();
+                    save_state(state_path, state)?;
+                    return Err("The interrupted upload expired on the remote; it restarts with the next backup".to_string());
+                }
+                Err(e) => return Err(remote_error(e)),
+            };
+            let etag = response.header("ETag").ok_or("The remote returned no ETag for a part")?;
+            state.parts.push(etag.to_string());
+            save_state(state_path, state)?;
+        }
+
+        let parts: String = state
+            .parts
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", i + 1, etag))
+            .collect();
+        let body = format!("<CompleteMultipartUpload>{}</CompleteMultipartUpload>", parts);
+        let response = self
+            .s3("POST", key, &[("uploadId", &upload_id)], &[], body.as_bytes())
+            .map_err(remote_error)?;
+        // S3 can report a failed completion in a 200 response
+        let text = read_text(response)?;
+        if text.contains("<Error>") {
+            let message = xml_value(&text, "Message").unwrap_or(text);
+            return Err(format!("Completing the upload failed: {}", message));
+        }
+        Ok(())
+    }
+
+    /// Check the remote copy has the local file's size and SHA-256. S3 keeps
+    /// the hash sent as object metadata (and checked every part's payload
+    /// hash on receipt); from WebDAV the file is read back.
+    fn verify(&self, key: &str, state: &UploadState) -> Result<(), String> {
+        let mismatch = || format!("The remote copy of {} doesn't match the local file", key);
+        match self.target {
+            RemoteBackupTarget::S3 => {
+                let response = self.s3("HEAD", key, &[], &[], &[]).map_err(remote_error)?;
+                let size = response.header("Content-Length").and_then(|len| len.parse::<u64>().ok());
+                let sha256 = response.header("x-amz-meta-sha256");
+                if size != Some(state.size) || sha256 != Some(state.sha256.as_str()) {
+                    return Err(mismatch());
+                }
+            }
+            RemoteBackupTarget::WebDav => {
+                let response = self.webdav("GET", &state.url).call().map_err(remote_error)?;
+                let mut hasher = Sha256::new();
+                let size = io::copy(&mut response.into_reader(), &mut hasher)
+                    .map_err(|e| format!("Failed to read back {}: {}", key, e))?;
+                if size != state.size || to_hex(&hasher.finalize()) != state.sha256 {
+                    return Err(mismatch());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mark `files` for upload. Files marked with `true` are deleted locally
+/// once their upload is verified.
+pub(crate) fn mark(files: &[(PathBuf, bool)]) -> Result<(), String> {
+    for (path, delete_after) in files {
+        let state = UploadState {
+            delete_after: *delete_after,
+            ..UploadState::default()
+        };
+        save_state(&state_path(path), &state)?;
+    }
+    Ok(())
+}
+
+/// Upload and verify everything pending in `dir`, including files left over
+/// from earlier failed runs. Returns how many were uploaded; does nothing
+/// without a configured target. A cancelled upload stays pending.
+pub(crate) fn upload(
+    settings: &RemoteBackupSettings,
+    dir: &Path,
+    cancel: &CancelToken,
+    progress: &Progress,
+) -> Result<usize, String> {
+    let Some(remote) = Remote::new(settings, cancel)? else {
+        return Ok(0);
+    };
+    let pending = pending(dir);
+    let total = pending.len() as u64;
+    let mut uploaded = 0;
+    let mut first_error = None;
+    for (done, path) in pending.into_iter().enumerate() {
+        cancel.check()?;
+        progress.report(done as u64, total);
+        match remote.upload_pending(&path) {
+            Ok(true) => uploaded += 1,
+            Ok(false) => {}
+            Err(e) => {
+                warn!("Failed to upload {}: {}", path.display(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    progress.report(total, total);
+    first_error.map_or(Ok(uploaded), Err)
+}
+
+/// Save the WebDAV user name and password, or the S3 access key ID and secret
+/// key, in the keychain
+#[tauri::command]
+pub fn set_remote_backup_credentials(username: String, secret: String) -> Result<(), String> {
+    if username.trim().is_empty() || secret.is_empty() {
+        return Err("Both the user name (or access key ID) and the secret are required".to_string());
+    }
+    let json = serde_json::to_string(&Credentials { username, secret })
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+    keychain_entry()?
+        .set_password(&json)
+        .map_err(|e| format!("Failed to save remote backup credentials in the keychain: {}", e))
+}
+
+/// Remove the remote backup credentials from the keychain
+#[tauri::command]
+pub fn clear_remote_backup_credentials() -> Result<(), String> {
+    match keychain_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to remove remote backup credentials from the keychain: {}", e)),
+    }
+}
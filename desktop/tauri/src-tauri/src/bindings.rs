@@ -0,0 +1,67 @@
+//! Typed TypeScript bindings for the shell's commands and event payloads,
+//! generated with tauri-specta so the frontend's command names, arguments and
+//! payload shapes can't drift from the Rust side. Debug builds on macOS (where
+//! every listed command exists) rewrite the bindings file at startup, so
+//! `npm run tauri:dev` keeps it current; commit the result with the change
+//! that caused it. Other builds leave the file alone, as it would lose the
+//! macOS-only commands.
+
+use std::fs;
+use std::path::Path;
+
+use log::{info, warn};
+use specta_typescript::{BigIntExportBehavior, Typescript};
+use tauri_specta::Builder;
+
+/// Relative to the repo root
+const BINDINGS_FILE: &str = "frontend/src/bindings/desktop.ts";
+
+/// Commands and event payloads covered by the bindings. A command listed here
+/// needs `#[specta::specta]`; a payload needs `specta::Type`.
+fn builder() -> Builder<tauri::Wry> {
+    Builder::<tauri::Wry>::new()
+        .typ::<crate::BackendHealthEvent>()
+        .typ::<crate::speech::SpeechTranscription>()
+        .typ::<crate::media::MediaDevices>()
+        .typ::<crate::mic_test::MicLevel>()
+        .commands(tauri_specta::collect_commands![
+            crate::media::capture_photo,
+            crate::media::capture_photo_burst,
+            crate::media::list_cameras,
+            crate::media::list_microphones,
+            crate::mic_test::start_mic_test,
+            crate::media::start_video_recording,
+            crate::media::stop_video_recording,
+            crate::media::request_camera_permission,
+            crate::media::request_microphone_permission,
+            crate::capture::list_capture_sources,
+            crate::capture::capture_from_source,
+            crate::capture::start_capture_recording,
+            crate::capture::stop_capture_recording,
+            crate::capture::list_media,
+            crate::speech::request_speech_authorization,
+            crate::speech::start_speech_recognition,
+            crate::speech::feed_speech_audio,
+            crate::speech::stop_speech_recognition,
+            crate::speech::is_speech_available,
+        ])
+}
+
+/// Write the bindings under `repo_root`
+pub fn export(repo_root: &Path) {
+    let path = repo_root.join(BINDINGS_FILE);
+    if let Some(dir) = path.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            warn!("Failed to create {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    // Durations and sizes are u64 but stay well below 2^53
+    let language = Typescript::default()
+        .bigint(BigIntExportBehavior::Number)
+        .header("// Generated by the desktop shell (src-tauri/src/bindings.rs); do not edit.");
+    match builder().export(language, &path) {
+        Ok(()) => info!("Wrote TypeScript bindings to {}", path.display()),
+        Err(e) => warn!("Failed to write TypeScript bindings: {}", e),
+    }
+}
@@ -0,0 +1,143 @@
+#import <Foundation/Foundation.h>
+#import <EventKit/EventKit.h>
+
+// Returned strings (identifiers and error messages) are heap-allocated;
+// release them with calendar_free_string
+
+static EKEventStore *sharedStore(void) {
+    static EKEventStore *store = nil;
+    static dispatch_once_t once;
+    dispatch_once(&once, ^{
+        store = [[EKEventStore alloc] init];
+    });
+    return store;
+}
+
+static void setError(char **error_out, NSError *error, NSString *fallback) {
+    if (error_out != NULL) {
+        *error_out = strdup((error.localizedDescription ?: fallback).UTF8String);
+    }
+}
+
+static NSString *optionalString(const char *value) {
+    return value != NULL ? [NSString stringWithUTF8String:value] : nil;
+}
+
+// 0 = not determined, 1 = restricted, 2 = denied, 3 = full access, 4 = write-only
+int calendar_authorization_status(void) {
+    EKAuthorizationStatus status = [EKEventStore authorizationStatusForEntityType:EKEntityTypeEvent];
+    switch (status) {
+        case EKAuthorizationStatusNotDetermined:
+            return 0;
+        case EKAuthorizationStatusRestricted:
+            return 1;
+        case EKAuthorizationStatusDenied:
+            return 2;
+        default:
+            break;
+    }
+    if (@available(macOS 14.0, *)) {
+        if (status == EKAuthorizationStatusWriteOnly) {
+            return 4;
+        }
+    }
+    return 3;
+}
+
+// Show the system permission prompt. Blocks until the user answers, so it
+// must not be called on the main thread.
+bool calendar_request_access(char **error_out) {
+    @autoreleasepool {
+        __block BOOL granted = NO;
+        __block NSError *failure = nil;
+        dispatch_semaphore_t done = dispatch_semaphore_create(0);
+        
+        void (^completion)(BOOL, NSError *) = ^(BOOL success, NSError *error) {
+            granted = success;
+            failure = error;
+            dispatch_semaphore_signal(done);
+        };
+        
+        // Updating events we created earlier requires reading them back, so ask for full access
+        if (@available(macOS 14.0, *)) {
+            [sharedStore() requestFullAccessToEventsWithCompletion:completion];
+        } else {
+            [sharedStore() requestAccessToEntityType:EKEntityTypeEvent completion:completion];
+        }
+        
+        dispatch_semaphore_wait(done, DISPATCH_TIME_FOREVER);
+        
+        if (!granted) {
+            setError(error_out, failure, @"Calendar access was not granted");
+        }
+        return granted;
+    }
+}
+
+// Create an event, or update it when event_id refers to one that still exists.
+// Returns the event identifier, or NULL on failure.
+char *calendar_save_event(const char *event_id,
+                          const char *title,
+                          const char *notes,
+                          const char *url,
+                          double start_timestamp,
+                          double end_timestamp,
+                          bool all_day,
+                          int reminder_minutes,
+                          char **error_out) {
+    @autoreleasepool {
+        EKEventStore *store = sharedStore();
+        EKEvent *event = nil;
+        if (event_id != NULL) {
+            event = [store eventWithIdentifier:[NSString stringWithUTF8String:event_id]];
+        }
+        if (event == nil) {
+            event = [EKEvent eventWithEventStore:store];
+            event.calendar = store.defaultCalendarForNewEvents;
+        }
+        if (event.calendar == nil) {
+            setError(error_out, nil, @"No default calendar is configured");
+            return NULL;
+        }
+        
+        event.title = [NSString stringWithUTF8String:title];
+        event.notes = optionalString(notes);
+        NSString *urlString = optionalString(url);
+        event.URL = urlString != nil ? [NSURL URLWithString:urlString] : nil;
+        event.allDay = all_day;
+        event.startDate = [NSDate dateWithTimeIntervalSince1970:start_timestamp];
+        event.endDate = [NSDate dateWithTimeIntervalSince1970:end_timestamp];
+        event.alarms = reminder_minutes >= 0
+            ? @[[EKAlarm alarmWithRelativeOffset:-60.0 * reminder_minutes]]
+            : nil;
+        
+        NSError *error = nil;
+        if (![store saveEvent:event span:EKSpanThisEvent commit:YES error:&error]) {
+            setError(error_out, error, @"Failed to save calendar event");
+            return NULL;
+        }
+        return strdup(event.eventIdentifier.UTF8String);
+    }
+}
+
+// Remove an event. Events the user already deleted count as removed.
+bool calendar_remove_event(const char *event_id, char **error_out) {
+    @autoreleasepool {
+        EKEventStore *store = sharedStore();
+        EKEvent *event = [store eventWithIdentifier:[NSString stringWithUTF8String:event_id]];
+        if (event == nil) {
+            return true;
+        }
+        
+        NSError *error = nil;
+        if (![store removeEvent:event span:EKSpanThisEvent commit:YES error:&error]) {
+            setError(error_out, error, @"Failed to remove calendar event");
+            return false;
+        }
+        return true;
+    }
+}
+
+void calendar_free_string(char *value) {
+    free(value);
+}
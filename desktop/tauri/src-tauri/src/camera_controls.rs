@@ -0,0 +1,441 @@
+//! Manual camera controls (exposure, white balance, focus, zoom, brightness
+//! and gain) for when the camera's own choice ruins a shot, e.g. a white
+//! document photographed under a desk lamp.
+//!
+//! On macOS the controls go through nokhwa's AVFoundation backend. The
+//! camera is only held while it's used, so values set here are kept for the
+//! session and applied whenever a photo or the preview opens that camera; a
+//! running preview picks them up on its next frame. On Linux they're V4L2
+//! controls set with `v4l2-ctl`, which the device keeps until it's
+//! unplugged.
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle};
+use tracing::{info, instrument};
+
+use crate::permissions::{self, CAMERA};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CameraControl {
+    Exposure,
+    WhiteBalance,
+    Focus,
+    Zoom,
+    Brightness,
+    Gain,
+}
+
+impl CameraControl {
+    const ALL: [CameraControl; 6] = [
+        CameraControl::Exposure,
+        CameraControl::WhiteBalance,
+        CameraControl::Focus,
+        CameraControl::Zoom,
+        CameraControl::Brightness,
+        CameraControl::Gain,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            CameraControl::Exposure => "exposure",
+            CameraControl::WhiteBalance => "white balance",
+            CameraControl::Focus => "focus",
+            CameraControl::Zoom => "zoom",
+            CameraControl::Brightness => "brightness",
+            CameraControl::Gain => "gain",
+        }
+    }
+}
+
+/// A control a camera supports, in the camera's own units
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraControlInfo {
+    pub control: CameraControl,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+    pub default: f64,
+    pub value: f64,
+    /// Whether the camera adjusts it itself; `None` for controls without an
+    /// automatic mode
+    pub automatic: Option<bool>,
+}
+
+#[cfg(target_os = "macos")]
+mod avfoundation {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use nokhwa::pixel_format::RgbFormat;
+    use nokhwa::utils::{
+        CameraIndex, ControlValueDescription, ControlValueSetter, KnownCameraControl, RequestedFormat,
+        RequestedFormatType,
+    };
+    use nokhwa::Camera;
+    use tracing::warn;
+
+    use super::{CameraControl, CameraControlInfo};
+
+    /// Values set with `set_camera_control`, by camera index. `None` hands a
+    /// control of the preview's camera back until the preview applies it.
+    static OVERRIDES: Mutex<Vec<(u32, CameraControl, Option<f64>)>> = Mutex::new(Vec::new());
+
+    /// Bumped on every change so a running preview knows to apply them
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+    /// The camera the preview holds and its controls, which can't be read
+    /// by opening it a second time
+    static LIVE: Mutex<Option<(u32, Vec<CameraControlInfo>)>> = Mutex::new(None);
+
+    fn known(control: CameraControl) -> KnownCameraControl {
+        match control {
+            CameraControl::Exposure => KnownCameraControl::Exposure,
+            CameraControl::WhiteBalance => KnownCameraControl::WhiteBalance,
+            CameraControl::Focus => KnownCameraControl::Focus,
+            CameraControl::Zoom => KnownCameraControl::Zoom,
+            CameraControl::Brightness => KnownCameraControl::Brightness,
+            CameraControl::Gain => KnownCameraControl::Gain,
+        }
+    }
+
+    /// Min, max, step, default and current value of a numeric control
+    fn range(description: &ControlValueDescription) -> Option<(f64, f64, f64, f64, f64)> {
+        match description {
+            ControlValueDescription::IntegerRange { min, max, value, step, default } => {
+                Some((*min as f64, *max as f64, *step as f64, *default as f64, *value as f64))
+            }
+            ControlValueDescription::FloatRange { min, max, value, step, default } => {
+                Some((*min, *max, *step, *default, *value))
+            }
+            _ => None,
+        }
+    }
+
+    fn overrides(index: u32) -> Vec<(CameraControl, Option<f64>)> {
+        OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(camera, _, _)| *camera == index)
+            .map(|&(_, control, value)| (control, value))
+            .collect()
+    }
+
+    fn describe(camera: &Camera, index: u32) -> Result<Vec<CameraControlInfo>, String> {
+        let controls = camera
+            .camera_controls()
+            .map_err(|e| format!("Failed to read the camera's controls: {}", e))?;
+        let overrides = overrides(index);
+        Ok(CameraControl::ALL
+            .iter()
+            .filter_map(|&control| {
+                let found = controls.iter().find(|found| found.control() == known(control))?;
+                let (min, max, step, default, value) = range(found.description())?;
+                let set = overrides.iter().find(|(c, _)| *c == control).and_then(|(_, value)| *value);
+                // AVFoundation has no separate switch; a value set here is manual
+                let automatic = matches!(
+                    control,
+                    CameraControl::Exposure | CameraControl::WhiteBalance | CameraControl::Focus
+                )
+                .then_some(set.is_none());
+                Some(CameraControlInfo {
+                    control,
+                    min,
+                    max,
+                    step,
+                    default,
+                    value: set.unwrap_or(value),
+                    automatic,
+                })
+            })
+            .collect())
+    }
+
+    /// Set the values set for camera `index` on it once it's open. Controls
+    /// never set are left to the camera.
+    pub fn apply(camera: &mut Camera, index: u32) {
+        let overrides = overrides(index);
+        if overrides.is_empty() {
+            return;
+        }
+        let Ok(controls) = camera.camera_controls() else {
+            return;
+        };
+        for (control, set) in overrides {
+            let Some(found) = controls.iter().find(|found| found.control() == known(control)) else {
+                continue;
+            };
+            let setter = match found.description() {
+                ControlValueDescription::IntegerRange { default, .. } => {
+                    ControlValueSetter::Integer(set.map_or(*default, |value| value.round() as i64))
+                }
+                ControlValueDescription::FloatRange { default, .. } => {
+                    ControlValueSetter::Float(set.unwrap_or(*default))
+                }
+                _ => continue,
+            };
+            if let Err(e) = camera.set_camera_control(known(control), setter) {
+                warn!(?control, error = %e, "failed to set camera control");
+            }
+        }
+    }
+
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::SeqCst)
+    }
+
+    /// `apply` for the preview's camera, keeping its controls for
+    /// `get_camera_controls`
+    pub fn apply_live(camera: &mut Camera, index: u32) {
+        apply(camera, index);
+        OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|&(camera, _, value)| camera != index || value.is_some());
+        let controls = describe(camera, index).unwrap_or_default();
+        *LIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some((index, controls));
+    }
+
+    pub fn end_live() {
+        *LIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    pub fn read(index: u32) -> Result<Vec<CameraControlInfo>, String> {
+        let live = LIVE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some((_, controls)) = live.filter(|(live, _)| *live == index) {
+            return Ok(controls);
+        }
+        let requested = RequestedFormat::new::<RgbFormat>(RequestedFormatType::None);
+        let camera = Camera::new(CameraIndex::Index(index), requested)
+            .map_err(|e| format!("Failed to open the camera: {}", e))?;
+        describe(&camera, index)
+    }
+
+    pub fn write(index: u32, info: &CameraControlInfo, value: Option<f64>) -> Result<(), String> {
+        let live = LIVE.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|(live, _)| *live);
+        let mut overrides = OVERRIDES.lock().unwrap_or_else(|e| e.into_inner());
+        overrides.retain(|&(camera, control, _)| camera != index || control != info.control);
+        // A camera opened later starts out automatic anyway
+        if value.is_some() || live == Some(index) {
+            overrides.push((index, info.control, value));
+        }
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) use avfoundation::{apply, apply_live, end_live, generation};
+
+#[cfg(target_os = "linux")]
+mod v4l2 {
+    use std::process::{Command, Stdio};
+
+    use crate::health::find_on_path;
+
+    use super::{CameraControl, CameraControlInfo};
+
+    /// A control as `v4l2-ctl --list-ctrls` prints it
+    #[derive(Default)]
+    struct Control {
+        name: String,
+        min: f64,
+        max: f64,
+        step: f64,
+        default: f64,
+        value: f64,
+    }
+
+    /// V4L2 names of a control and of its automatic switch (newest kernel
+    /// names first), with the switch's manual value
+    fn names(control: CameraControl) -> (&'static [&'static str], &'static [&'static str], i64) {
+        match control {
+            CameraControl::Exposure => (
+                &["exposure_time_absolute", "exposure_absolute"],
+                &["auto_exposure", "exposure_auto"],
+                1,
+            ),
+            CameraControl::WhiteBalance => (
+                &["white_balance_temperature"],
+                &["white_balance_automatic", "white_balance_temperature_auto"],
+                0,
+            ),
+            CameraControl::Focus => (&["focus_absolute"], &["focus_automatic_continuous", "focus_auto"], 0),
+            CameraControl::Zoom => (&["zoom_absolute"], &[], 0),
+            CameraControl::Brightness => (&["brightness"], &[], 0),
+            CameraControl::Gain => (&["gain"], &["gain_automatic"], 0),
+        }
+    }
+
+    fn device(index: u32) -> String {
+        format!("/dev/video{}", index)
+    }
+
+    fn v4l2_ctl(index: u32, args: &[&str]) -> Result<String, String> {
+        let tool = find_on_path("v4l2-ctl").ok_or("v4l2-ctl was not found on the PATH; install v4l-utils")?;
+        let output = Command::new(tool)
+            .arg("-d")
+            .arg(device(index))
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to start v4l2-ctl: {}", e))?;
+        if !output.status.success() {
+            let reason = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("v4l2-ctl failed on {}: {}", device(index), reason.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Parse lines like
+    /// `brightness 0x00980900 (int) : min=-64 max=64 step=1 default=0 value=0`
+    fn list(index: u32) -> Result<Vec<Control>, String> {
+        let output = v4l2_ctl(index, &["--list-ctrls"])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (head, fields) = line.split_once(" : ")?;
+                let mut control = Control {
+                    name: head.split_whitespace().next()?.to_string(),
+                    // Booleans print no range
+                    max: 1.0,
+                    step: 1.0,
+                    ..Control::default()
+                };
+                for (key, value) in fields.split_whitespace().filter_map(|field| field.split_once('=')) {
+                    let Ok(value) = value.parse::<f64>() else {
+                        continue;
+                    };
+                    match key {
+                        "min" => control.min = value,
+                        "max" => control.max = value,
+                        "step" => control.step = value,
+                        "default" => control.default = value,
+                        "value" => control.value = value,
+                        _ => {}
+                    }
+                }
+                Some(control)
+            })
+            .collect())
+    }
+
+    fn find<'a>(controls: &'a [Control], names: &[&str]) -> Option<&'a Control> {
+        names.iter().find_map(|name| controls.iter().find(|control| control.name == *name))
+    }
+
+    pub fn read(index: u32) -> Result<Vec<CameraControlInfo>, String> {
+        let controls = list(index)?;
+        Ok(CameraControl::ALL
+            .iter()
+            .filter_map(|&control| {
+                let (value_names, auto_names, manual) = names(control);
+                let found = find(&controls, value_names)?;
+                let automatic = find(&controls, auto_names).map(|auto| auto.value != manual as f64);
+                Some(CameraControlInfo {
+                    control,
+                    min: found.min,
+                    max: found.max,
+                    step: found.step,
+                    default: found.default,
+                    value: found.value,
+                    automatic,
+                })
+            })
+            .collect())
+    }
+
+    pub fn write(index: u32, info: &CameraControlInfo, value: Option<f64>) -> Result<(), String> {
+        let controls = list(index)?;
+        let (value_names, auto_names, manual) = names(info.control);
+        let target = find(&controls, value_names).ok_or("The camera no longer has this control")?;
+        let auto = find(&controls, auto_names);
+        // The value is ignored while the automatic switch is on, so the
+        // switch goes first, in its own call
+        match (value, auto) {
+            (Some(value), auto) => {
+                if let Some(auto) = auto {
+                    v4l2_ctl(index, &[&format!("--set-ctrl={}={}", auto.name, manual)])?;
+                }
+                v4l2_ctl(index, &[&format!("--set-ctrl={}={}", target.name, value.round() as i64)])?;
+            }
+            (None, Some(auto)) => {
+                v4l2_ctl(index, &[&format!("--set-ctrl={}={}", auto.name, auto.default as i64)])?;
+            }
+            (None, None) => {
+                v4l2_ctl(index, &[&format!("--set-ctrl={}={}", target.name, target.default as i64)])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+use avfoundation::{read, write};
+#[cfg(target_os = "linux")]
+use v4l2::{read, write};
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn read(_index: u32) -> Result<Vec<CameraControlInfo>, String> {
+    Err("Camera controls are only available on macOS and Linux".to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn write(_index: u32, _info: &CameraControlInfo, _value: Option<f64>) -> Result<(), String> {
+    Err("Camera controls are only available on macOS and Linux".to_string())
+}
+
+/// The controls camera `camera_id` lets the app adjust, with their ranges
+/// and current values. `camera_id` is the camera's index in
+/// `list_cameras()` on macOS and `n` of `/dev/video<n>` on Linux. Controls
+/// the camera doesn't have are left out.
+#[tauri::command]
+#[instrument(skip(app), err)]
+pub async fn get_camera_controls(app: AppHandle, camera_id: u32) -> Result<Vec<CameraControlInfo>, String> {
+    permissions::require(&app, CAMERA)?;
+    async_runtime::spawn_blocking(move || read(camera_id))
+        .await
+        .map_err(|e| format!("Camera controls task failed: {}", e))?
+}
+
+/// Set a control of camera `camera_id` to `value`, in the range
+/// `get_camera_controls` reports. `null` hands it back to the camera: its
+/// automatic mode where there is one, the default value otherwise. Returns
+/// the control as it is now.
+#[tauri::command]
+#[instrument(skip(app), err)]
+pub async fn set_camera_control(
+    app: AppHandle,
+    camera_id: u32,
+    control: CameraControl,
+    value: Option<f64>,
+) -> Result<CameraControlInfo, String> {
+    permissions::require(&app, CAMERA)?;
+    async_runtime::spawn_blocking(move || {
+        let info = read(camera_id)?
+            .into_iter()
+            .find(|info| info.control == control)
+            .ok_or_else(|| format!("This camera doesn't let the app adjust its {}", control.label()))?;
+        if let Some(value) = value {
+            if !value.is_finite() || value < info.min || value > info.max {
+                return Err(format!(
+                    "The camera's {} goes from {} to {}",
+                    control.label(),
+                    info.min,
+                    info.max
+                ));
+            }
+        }
+        write(camera_id, &info, value)?;
+        info!(camera_id, ?control, ?value, "camera control set");
+        Ok(CameraControlInfo {
+            value: value.unwrap_or(info.default),
+            automatic: info.automatic.map(|_| value.is_none()),
+            ..info
+        })
+    })
+    .await
+    .map_err(|e| format!("Camera controls task failed: {}", e))?
+}
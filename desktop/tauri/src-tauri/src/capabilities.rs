@@ -0,0 +1,126 @@
+//! What this shell can do, for a webview that may be newer or older than it
+//! (the backend can update the frontend on its own). The webview asks once at
+//! startup and adapts: it hides features the shell lacks and reads each event
+//! according to the payload schema version listed here.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+/// Version of the capabilities document itself
+const CAPABILITIES_VERSION: u32 = 1;
+
+/// Payload schema version of every event the shell emits. Bump an event's
+/// version whenever its payload changes shape; events stay at 1 until then.
+/// Several payloads are bare arrays or values, so the version can't travel
+/// inside the payload itself.
+const EVENT_SCHEMAS: &[(&str, u32)] = &[
+    ("activity-changed", 1),
+    ("app-locked", 1),
+    ("app-unlocked", 1),
+    ("audio-playback", 1),
+    ("automation", 1),
+    ("backend-health", 1),
+    ("backend-rolled-back", 1),
+    ("backend-update-progress", 1),
+    ("backend-update-staged", 1),
+    ("backup-completed", 1),
+    ("backup-failed", 1),
+    ("backup-restore-progress", 1),
+    ("backup-upload-failed", 1),
+    ("backup-uploaded", 1),
+    ("capture-recording-stopped", 1),
+    ("capture-segment-finished", 1),
+    ("capture-skipped", 1),
+    ("data-dir-progress", 1),
+    ("data-export-progress", 1),
+    ("database-encryption-progress", 1),
+    ("deep-link", 1),
+    ("demo-mode-changed", 1),
+    ("dictation", 1),
+    ("email-ingested", 1),
+    // 2: an array of changes per batch instead of one change per event
+    ("entry-updated", 2),
+    ("external-share", 1),
+    ("feature-permission-denied", 1),
+    ("focus-changed", 1),
+    ("focus-tick", 1),
+    ("hotkey", 2),
+    ("job-cancelled", 1),
+    ("job-finished", 1),
+    ("job-progress", 1),
+    ("locale-changed", 1),
+    ("media-devices-changed", 1),
+    ("media-key", 1),
+    ("mic-test-level", 1),
+    ("network-status", 1),
+    ("notification-suppressed", 1),
+    ("open-file", 1),
+    ("power-profile-changed", 1),
+    ("quick-action", 1),
+    ("reminder-due", 1),
+    ("settings-changed", 1),
+    ("speech-transcription", 1),
+    ("startup-repairs", 1),
+    ("sync-completed", 1),
+    ("sync-peers-changed", 1),
+    ("timer-changed", 1),
+    ("timer-tick", 1),
+    ("transcript-ready", 1),
+    ("user-active", 1),
+    ("user-idle", 1),
+    ("watch-folder-ingested", 1),
+    ("webview-state-reset", 1),
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellCapabilities {
+    pub capabilities_version: u32,
+    /// The shell's version, e.g. `0.10.0`
+    pub shell_version: String,
+    pub platform: &'static str,
+    /// Feature name to whether it works in this build on this machine
+    pub features: BTreeMap<&'static str, bool>,
+    /// Event name to payload schema version
+    pub events: BTreeMap<&'static str, u32>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+async fn speech_available() -> bool {
+    crate::speech::is_speech_available().await.unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+async fn speech_available() -> bool {
+    false
+}
+
+/// Shell version, available features and event schema versions
+#[tauri::command]
+pub async fn get_shell_capabilities(app: AppHandle) -> Result<ShellCapabilities, String> {
+    let macos = cfg!(target_os = "macos");
+    let speech = speech_available().await;
+    let features = BTreeMap::from([
+        ("speech", speech),
+        ("camera", cfg!(any(target_os = "macos", target_os = "android"))),
+        ("cameraPreview", macos),
+        ("tray", true),
+        ("hotkeys", cfg!(desktop)),
+        ("pushToTalk", macos && speech),
+        ("updater", crate::updater::updates_available()),
+        ("quickActions", cfg!(any(target_os = "macos", target_os = "windows"))),
+        ("automation", true),
+        ("focusSessions", true),
+        // Needs a way to turn Do Not Disturb on, see dnd::set_dnd
+        ("focusDnd", cfg!(any(target_os = "macos", target_os = "linux"))),
+    ]);
+    Ok(ShellCapabilities {
+        capabilities_version: CAPABILITIES_VERSION,
+        shell_version: app.package_info().version.to_string(),
+        platform: std::env::consts::OS,
+        features,
+        events: EVENT_SCHEMAS.iter().copied().collect(),
+    })
+}
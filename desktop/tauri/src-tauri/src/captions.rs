@@ -0,0 +1,162 @@
+//! Videos with the transcript burned in, for sharing recorded updates with
+//! people who won't have the app. ffmpeg's `subtitles` filter (which needs an
+//! ffmpeg built with libass) renders a WebVTT file onto every frame and the
+//! video is re-encoded as H.264 with AAC audio.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use chrono::Utc;
+use log::info;
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle};
+
+use crate::health::find_on_path;
+use crate::jobs::{self, CancelToken, JobId, JobKind, Priority, Progress};
+use crate::media_store::{get_media_dir, probe, validate_app_path};
+
+/// Where caption files are kept for the export, under the app data dir
+const CAPTIONS_DIR: &str = "captions";
+
+/// Containers H.264 and AAC can go in
+const EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "mkv"];
+
+/// Result of an `export_video_with_captions` job
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptionedVideo {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// Seconds of `out_time=HH:MM:SS.micros` from ffmpeg's `-progress` output
+fn parse_out_time(value: &str) -> Option<f64> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+fn render(video: &Path, vtt: &Path, dest: &Path, cancel: &CancelToken, progress: &Progress) -> Result<(), String> {
+    let ffmpeg = find_on_path("ffmpeg").ok_or("ffmpeg was not found on the PATH")?;
+    let duration_ms = probe(video)?.duration_secs.map_or(0, |secs| (secs * 1000.0) as u64);
+    let (Some(dir), Some(name)) = (vtt.parent(), vtt.file_name()) else {
+        return Err(format!("Invalid caption file: {}", vtt.display()));
+    };
+
+    // Run next to the captions so the filter gets a bare file name; the
+    // filtergraph would need paths with `:` or `\` escaped twice
+    let mut child = Command::new(ffmpeg)
+        .current_dir(dir)
+        .args(["-hide_banner", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-y", "-i"])
+        .arg(video)
+        .arg("-vf")
+        .arg(format!("subtitles={}", name.to_string_lossy()))
+        .args(["-c:v", "libx264", "-preset", "medium", "-crf", "20", "-pix_fmt", "yuv420p"])
+        .args(["-c:a", "aac", "-b:a", "160k", "-movflags", "+faststart"])
+        .arg(dest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or("ffmpeg has no output")?;
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(jobs::CANCELLED.to_string());
+        }
+        if let Some(secs) = line.strip_prefix("out_time=").and_then(parse_out_time) {
+            progress.report(((secs * 1000.0) as u64).min(duration_ms), duration_ms);
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr);
+        if reason.contains("No such filter: 'subtitles'") {
+            return Err("This ffmpeg can't burn in captions; it was built without libass".to_string());
+        }
+        return Err(format!("Burning in the captions failed: {}", reason.trim()));
+    }
+    progress.report(duration_ms, duration_ms);
+    Ok(())
+}
+
+/// Burn `vtt` into `video` as a job, writing to a partial file renamed to
+/// `dest` once ffmpeg is done
+pub(crate) async fn export(
+    video: PathBuf,
+    vtt: PathBuf,
+    dest: PathBuf,
+    cancel: CancelToken,
+    progress: Progress,
+) -> Result<CaptionedVideo, String> {
+    async_runtime::spawn_blocking(move || {
+        let extension = dest.extension().unwrap_or_default().to_string_lossy().to_string();
+        let partial = dest.with_extension(format!("partial.{}", extension));
+        let result = render(&video, &vtt, &partial, &cancel, &progress)
+            .and_then(|()| fs::rename(&partial, &dest).map_err(|e| format!("Failed to save {}: {}", dest.display(), e)));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        info!("Exported {} with captions to {}", video.display(), dest.display());
+        Ok(CaptionedVideo {
+            path: dest.to_string_lossy().to_string(),
+            size_bytes: fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0),
+        })
+    })
+    .await
+    .map_err(|e| format!("Caption export task failed: {}", e))?
+}
+
+/// Render the transcript into a video for sharing. `video` is a recording in
+/// the app's data directories; `vtt` is either WebVTT text or the path of a
+/// `.vtt` file there. `dest` is an absolute `.mp4`, `.m4v`, `.mov` or `.mkv`
+/// path. Queues a high-priority job and returns its id; the job's result is
+/// `{ path, sizeBytes }`.
+#[tauri::command]
+pub async fn export_video_with_captions(app: AppHandle, video: String, vtt: String, dest: String) -> Result<JobId, String> {
+    let video = validate_app_path(&app, &video)?;
+    let dest = PathBuf::from(dest);
+    if !dest.is_absolute() {
+        return Err("Export path must be absolute".to_string());
+    }
+    let extension = dest.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+    if !EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!("Videos with captions are saved as {}", EXTENSIONS.join(", ")));
+    }
+    if let Some(parent) = dest.parent().filter(|parent| !parent.is_dir()) {
+        return Err(format!("{} doesn't exist", parent.display()));
+    }
+
+    let captions = if vtt.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        vtt
+    } else {
+        let path = validate_app_path(&app, &vtt)?;
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+    };
+    if !captions.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        return Err("Captions must be WebVTT, starting with WEBVTT".to_string());
+    }
+    // A copy under a plain name, which the job keeps using after a restart
+    let copy = get_media_dir(&app, CAPTIONS_DIR)?.join(format!("captions_{}.vtt", Utc::now().timestamp_millis()));
+    fs::write(&copy, captions).map_err(|e| format!("Failed to save the captions: {}", e))?;
+    jobs::enqueue(
+        &app,
+        JobKind::Captions {
+            video,
+            vtt: copy,
+            dest,
+        },
+        Priority::High,
+    )
+}
@@ -0,0 +1,530 @@
+//! Capture sources: anything that produces media files for a note, such as
+//! the camera, the screen, the microphone or a file the user imports. Each
+//! source implements `CaptureSource` and is registered in the
+//! `CaptureManager`, which owns it along with its state (e.g. a running
+//! recording). The frontend discovers them with `list_capture_sources`, and
+//! the platform-specific sources live next to their native code (`media.rs`
+//! on macOS).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+use tracing::{info, instrument, warn, Span};
+
+use crate::error::ShellError;
+use crate::media_store::{self, get_capture_dir};
+use crate::permissions::{self, CAMERA, MICROPHONE, SCREEN};
+use crate::settings::SettingsState;
+use crate::{audio_cleanup, capture_names, media_manifest, privacy_guard, telemetry, transcription};
+
+/// How often a split recording is checked for closed segments
+const SEGMENT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Running recordings whose files get the audio cleanup pass, by source id
+static CLEAN_AUDIO: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureKind {
+    Photo,
+    Screenshot,
+    Video,
+    Audio,
+    /// A recording of the screen or one window
+    Screencast,
+    File,
+}
+
+impl CaptureKind {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CaptureKind::Photo => "photo",
+            CaptureKind::Screenshot => "screenshot",
+            CaptureKind::Video => "video",
+            CaptureKind::Audio => "audio",
+            CaptureKind::Screencast => "screencast",
+            CaptureKind::File => "file",
+        }
+    }
+
+    /// Feature permissions a capture of this kind needs
+    fn permissions(self) -> &'static [&'static str] {
+        match self {
+            CaptureKind::Photo => &[CAMERA],
+            CaptureKind::Screenshot => &[SCREEN],
+            CaptureKind::Video => &[CAMERA, MICROPHONE],
+            CaptureKind::Audio => &[MICROPHONE],
+            CaptureKind::Screencast => &[SCREEN],
+            CaptureKind::File => &[],
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureMode {
+    /// Takes its files in one go (`capture_from_source`)
+    Snapshot,
+    /// Runs until stopped (`start_capture_recording` / `stop_capture_recording`)
+    Recording,
+}
+
+/// Options for a snapshot or recording; each source uses the ones that apply
+/// to it
+#[derive(Deserialize, Default, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CaptureOptions {
+    /// Camera to take photos with: its index in `list_cameras` on macOS, `n`
+    /// of `/dev/video<n>` on Linux (default: the first)
+    pub camera: Option<u32>,
+    /// Number of photos in a burst
+    pub count: Option<u32>,
+    /// Time between the photos of a burst
+    pub interval_ms: Option<u64>,
+    /// The file to import
+    pub path: Option<PathBuf>,
+    /// Display to record, an id from `list_capture_targets` (default: the main one)
+    pub display: Option<u64>,
+    /// Window to record instead of a display, an id from `list_capture_targets`
+    pub window: Option<u64>,
+    /// Clean up the sound of the finished recording; `None` follows
+    /// `media.cleanAudio`
+    pub clean_audio: Option<bool>,
+    /// Entry the capture is for: its files go to an `entry_<id>` subfolder
+    /// and `{entry}` in file names is filled in
+    pub entry_id: Option<i64>,
+}
+
+pub trait CaptureSource: Send + Sync {
+    /// Stable identifier used by the commands, e.g. `camera`
+    fn id(&self) -> &'static str;
+    fn label(&self) -> &'static str;
+    fn kind(&self) -> CaptureKind;
+    fn mode(&self) -> CaptureMode;
+    /// Subdirectory of the app data dir the files go to
+    fn media_subdir(&self) -> &'static str;
+
+    /// Whether the device or tool it needs is present. May block.
+    fn available(&self) -> bool {
+        true
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording_elapsed().is_some()
+    }
+
+    /// How long the running recording has been going
+    fn recording_elapsed(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Take a snapshot into `dir`. Called on a blocking thread.
+    fn capture(&self, _dir: &Path, _options: &CaptureOptions) -> Result<Vec<PathBuf>, String> {
+        Err(format!("{} only records", self.label()))
+    }
+
+    /// Start recording into a new file in `dir` and return its path. Called
+    /// on a blocking thread.
+    fn start_recording(&self, _dir: &Path, _options: &CaptureOptions) -> Result<PathBuf, String> {
+        Err(format!("{} doesn't record", self.label()))
+    }
+
+    /// Finish the recording, returning its file and how long it ran
+    fn stop_recording(&self) -> Result<(PathBuf, Duration), String> {
+        Err(format!("{} doesn't record", self.label()))
+    }
+
+    /// Segments of a split recording that were closed since the last call,
+    /// not counting the file `stop_recording` returns. May block.
+    fn take_finished_segments(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+/// Entry of `list_capture_sources`
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSourceInfo {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub kind: CaptureKind,
+    pub mode: CaptureMode,
+    pub available: bool,
+    pub recording: bool,
+}
+
+/// Entry of `list_media`
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFile {
+    pub path: String,
+    /// Media folder it is in, e.g. `photos`
+    pub folder: String,
+    pub size_bytes: u64,
+}
+
+/// The capture sources of this platform, in the order they are listed
+pub struct CaptureManager {
+    sources: Vec<Arc<dyn CaptureSource>>,
+}
+
+impl Default for CaptureManager {
+    fn default() -> Self {
+        let mut manager = Self { sources: Vec::new() };
+        #[cfg(target_os = "macos")]
+        crate::media::register_sources(&mut manager);
+        #[cfg(target_os = "linux")]
+        crate::linux_camera::register_sources(&mut manager);
+        manager.register(FileImport);
+        manager
+    }
+}
+
+impl CaptureManager {
+    pub fn register(&mut self, source: impl CaptureSource + 'static) {
+        debug_assert!(self.sources.iter().all(|existing| existing.id() != source.id()));
+        self.sources.push(Arc::new(source));
+    }
+
+    /// The longest running recording, for the tray
+    pub(crate) fn recording(&self) -> Option<Duration> {
+        self.sources.iter().filter_map(|source| source.recording_elapsed()).max()
+    }
+
+    /// Ids of the sources recording right now
+    pub(crate) fn recording_ids(&self) -> Vec<&'static str> {
+        self.sources
+            .iter()
+            .filter(|source| source.is_recording())
+            .map(|source| source.id())
+            .collect()
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<dyn CaptureSource>, ShellError> {
+        self.sources
+            .iter()
+            .find(|source| source.id() == id)
+            .cloned()
+            .ok_or_else(|| ShellError::unsupported(format!("Capture source {} isn't available on this platform", id)))
+    }
+}
+
+/// Copies a file the user picked into `imports/`, keeping its name
+struct FileImport;
+
+impl CaptureSource for FileImport {
+    fn id(&self) -> &'static str {
+        "file"
+    }
+
+    fn label(&self) -> &'static str {
+        "Import file"
+    }
+
+    fn kind(&self) -> CaptureKind {
+        CaptureKind::File
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Snapshot
+    }
+
+    fn media_subdir(&self) -> &'static str {
+        "imports"
+    }
+
+    fn capture(&self, dir: &Path, options: &CaptureOptions) -> Result<Vec<PathBuf>, String> {
+        let source = options.path.as_ref().ok_or("Importing needs the file's path")?;
+        if !source.is_file() {
+            return Err(format!("Not a file: {}", source.display()));
+        }
+        let stem = source
+            .file_stem()
+            .ok_or_else(|| format!("Not a file: {}", source.display()))?
+            .to_string_lossy();
+        let extension = source
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        // Importing the same name twice keeps both, as `name-2.ext`
+        let stem = capture_names::unique_stem(dir, &stem, &[&extension])?;
+        let dest = dir.join(format!("{}{}", stem, extension));
+        fs::copy(source, &dest).map_err(|e| format!("Failed to import {}: {}", source.display(), e))?;
+        info!(path = ?dest, "file imported");
+        Ok(vec![dest])
+    }
+}
+
+fn paths_to_strings(paths: Vec<PathBuf>) -> Vec<String> {
+    paths.into_iter().map(|path| path.to_string_lossy().to_string()).collect()
+}
+
+/// Permissions, then the privacy guard (except for imports), then the folder
+/// the files go in
+async fn prepare(app: &AppHandle, source: &dyn CaptureSource, entry_id: Option<i64>) -> Result<PathBuf, ShellError> {
+    for feature in source.kind().permissions() {
+        permissions::require(app, feature).map_err(ShellError::from)?;
+    }
+    if source.kind() != CaptureKind::File {
+        privacy_guard::check(app, source.kind().name())
+            .await
+            .map_err(ShellError::unavailable)?;
+    }
+    if let Some(id) = entry_id.filter(|id| *id <= 0) {
+        return Err(ShellError::invalid_input(format!("Invalid entry id {}", id)));
+    }
+    get_capture_dir(app, source.media_subdir(), entry_id).map_err(ShellError::internal)
+}
+
+/// A source that failed: an import was given a bad path, anything else means
+/// the device or session wasn't usable right now
+fn source_error(kind: CaptureKind, message: String) -> ShellError {
+    match kind {
+        CaptureKind::File => ShellError::invalid_input(message),
+        _ => ShellError::unavailable(message),
+    }
+}
+
+fn task_error(e: tokio::task::JoinError) -> ShellError {
+    ShellError::internal(format!("Capture task panicked: {}", e))
+}
+
+/// Take a snapshot from `id` on a blocking thread, returning the new files
+pub async fn capture(app: &AppHandle, id: &str, options: CaptureOptions) -> Result<Vec<String>, ShellError> {
+    let source = app.state::<CaptureManager>().get(id)?;
+    if source.mode() != CaptureMode::Snapshot {
+        return Err(ShellError::invalid_input(format!(
+            "{} records; start and stop it instead",
+            source.label()
+        )));
+    }
+    let dir = prepare(app, source.as_ref(), options.entry_id).await?;
+
+    let span = Span::current();
+    let kind = source.kind();
+    let paths = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        source.capture(&dir, &options)
+    })
+    .await
+    .map_err(task_error)?
+    .map_err(|e| source_error(kind, e))?;
+    telemetry::record(app, &format!("{}_capture", kind.name()), None);
+    media_manifest::track(app, paths.clone());
+
+    Ok(paths_to_strings(paths))
+}
+
+pub async fn start_recording(app: &AppHandle, id: &str, options: CaptureOptions) -> Result<String, ShellError> {
+    let source = app.state::<CaptureManager>().get(id)?;
+    if source.mode() != CaptureMode::Recording {
+        return Err(ShellError::invalid_input(format!("{} doesn't record", source.label())));
+    }
+    let dir = prepare(app, source.as_ref(), options.entry_id).await?;
+    let clean_audio = options
+        .clean_audio
+        .unwrap_or_else(|| app.state::<SettingsState>().current().media.clean_audio);
+
+    let span = Span::current();
+    let started = source.clone();
+    // Some recorders wait for the system to start capturing
+    let path = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        started.start_recording(&dir, &options)
+    })
+    .await
+    .map_err(task_error)?
+    .map_err(ShellError::unavailable)?;
+    set_clean_audio(source.id(), clean_audio);
+    watch_segments(app, source);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Payload of `capture-recording-stopped` and `capture-segment-finished`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RecordingStopped {
+    source: &'static str,
+    path: String,
+}
+
+fn emit_segments(app: &AppHandle, source: &'static str, segments: Vec<PathBuf>) {
+    media_manifest::track(app, segments.clone());
+    for path in segments {
+        info!(source, path = ?path, "recording segment finished");
+        let event = RecordingStopped {
+            source,
+            path: path.to_string_lossy().to_string(),
+        };
+        if let Err(e) = app.emit("capture-segment-finished", event) {
+            warn!(error = %e, "failed to emit capture-segment-finished");
+        }
+    }
+}
+
+/// Remember whether the recording `id` just started gets the audio cleanup
+fn set_clean_audio(id: &'static str, clean: bool) {
+    let mut ids = CLEAN_AUDIO.lock().unwrap_or_else(|e| e.into_inner());
+    ids.retain(|running| *running != id);
+    if clean {
+        ids.push(id);
+    }
+}
+
+/// Run the audio cleanup over finished files of the recording `id`, if it
+/// was started with it. Blocks.
+fn clean_files(id: &'static str, paths: &[PathBuf]) {
+    if !CLEAN_AUDIO.lock().unwrap_or_else(|e| e.into_inner()).contains(&id) {
+        return;
+    }
+    for path in paths {
+        audio_cleanup::clean(path);
+    }
+}
+
+/// Announce each segment of a split recording as it closes, until the
+/// recording stops
+fn watch_segments(app: &AppHandle, source: Arc<dyn CaptureSource>) {
+    let kind = source.kind();
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(SEGMENT_POLL_INTERVAL).await;
+            let polled = source.clone();
+            let Ok((recording, segments)) = tokio::task::spawn_blocking(move || {
+                let recording = polled.is_recording();
+                let segments = polled.take_finished_segments();
+                clean_files(polled.id(), &segments);
+                (recording, segments)
+            })
+            .await
+            else {
+                break;
+            };
+            transcription::queue_recordings(&app, kind, &segments);
+            emit_segments(&app, source.id(), segments);
+            if !recording {
+                break;
+            }
+        }
+    });
+}
+
+/// Stop the recording of `id` and return the finished file. Emits
+/// `capture-recording-stopped`, since the recording indicator can stop it too.
+pub async fn stop_recording(app: &AppHandle, id: &str) -> Result<String, ShellError> {
+    let source = app.state::<CaptureManager>().get(id)?;
+    if source.mode() != CaptureMode::Recording {
+        return Err(ShellError::invalid_input(format!("{} doesn't record", source.label())));
+    }
+    let span = Span::current();
+    let kind = source.kind();
+    let source_id = source.id();
+    // Waits for the recorder to finalize the file
+    let ((path, duration), segments) = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        // Not recording (any more) counts as unavailable, like a missing device
+        let stopped = source.stop_recording().map_err(ShellError::unavailable)?;
+        let segments = source.take_finished_segments();
+        clean_files(source.id(), &segments);
+        clean_files(source.id(), std::slice::from_ref(&stopped.0));
+        Ok::<_, ShellError>((stopped, segments))
+    })
+    .await
+    .map_err(task_error)??;
+    set_clean_audio(source_id, false);
+    telemetry::record(app, &format!("{}_recording", kind.name()), Some(duration));
+    transcription::queue_recordings(app, kind, &segments);
+    transcription::queue_recordings(app, kind, std::slice::from_ref(&path));
+    emit_segments(app, source_id, segments);
+    media_manifest::track(app, vec![path.clone()]);
+
+    let path = path.to_string_lossy().to_string();
+    let event = RecordingStopped {
+        source: source_id,
+        path: path.clone(),
+    };
+    if let Err(e) = app.emit("capture-recording-stopped", event) {
+        warn!(error = %e, "failed to emit capture-recording-stopped");
+    }
+    Ok(path)
+}
+
+/// Every capture source of this platform, whether it can be used right now
+/// and whether it is recording
+#[tauri::command]
+#[specta::specta]
+pub async fn list_capture_sources(app: AppHandle) -> Result<Vec<CaptureSourceInfo>, ShellError> {
+    let sources = app.state::<CaptureManager>().sources.clone();
+    // Availability checks query devices and the PATH
+    tokio::task::spawn_blocking(move || {
+        sources
+            .iter()
+            .map(|source| CaptureSourceInfo {
+                id: source.id(),
+                label: source.label(),
+                kind: source.kind(),
+                mode: source.mode(),
+                available: source.available(),
+                recording: source.is_recording(),
+            })
+            .collect()
+    })
+    .await
+    .map_err(task_error)
+}
+
+/// Take a snapshot with a source, returning the paths of the new files
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn capture_from_source(app: AppHandle, source: String, options: Option<CaptureOptions>) -> Result<Vec<String>, ShellError> {
+    capture(&app, &source, options.unwrap_or_default()).await
+}
+
+/// Start a recording source, returning the file it records to
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn start_capture_recording(app: AppHandle, source: String, options: Option<CaptureOptions>) -> Result<String, ShellError> {
+    start_recording(&app, &source, options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn stop_capture_recording(app: AppHandle, source: String) -> Result<String, ShellError> {
+    stop_recording(&app, &source).await
+}
+
+/// Files captured for entry `entry_id` (with `options.entryId`) and their
+/// transcripts, by folder and then name
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn list_media(app: AppHandle, entry_id: i64) -> Result<Vec<MediaFile>, ShellError> {
+    let mut folders: Vec<&'static str> = app
+        .state::<CaptureManager>()
+        .sources
+        .iter()
+        .map(|source| source.media_subdir())
+        .collect();
+    folders.push(transcription::TRANSCRIPTS_DIR);
+    folders.sort_unstable();
+    folders.dedup();
+
+    let files = media_store::entry_files(&app, &folders, entry_id).map_err(ShellError::internal)?;
+    Ok(files
+        .into_iter()
+        .map(|(folder, path)| MediaFile {
+            size_bytes: fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0),
+            path: path.to_string_lossy().to_string(),
+            folder,
+        })
+        .collect())
+}
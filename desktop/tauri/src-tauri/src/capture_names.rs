@@ -0,0 +1,153 @@
+//! File names of new captures, from the `media.fileNameTemplate` setting, so
+//! exported media carry the names the tools they are fed into expect. A
+//! template names the file without its extension, which the source adds.
+//! It may use these tokens:
+//!
+//! - `{date}`: local date, e.g. `2026-10-16`
+//! - `{time}`: local time, e.g. `153012`
+//! - `{timestamp}`: Unix time in seconds
+//! - `{entry}`: the entry in `options.entryId`, or nothing
+//! - `{type}`: `photo`, `screenshot`, `video`, `audio` or `screencast`
+//! - `{seq}`: the photo's number in a burst, 1 otherwise
+//!
+//! Everything else must be letters, digits, `-`, `_`, `.` or spaces, so a
+//! name is valid on every filesystem and never needs quoting in ffmpeg's
+//! segment lists. A template must use `{time}` or `{timestamp}` so each
+//! capture gets a name of its own; one that is already taken in the folder
+//! anyway gets `-2`, `-3` and so on, never overwriting an earlier capture.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::RwLock;
+
+use chrono::Local;
+
+use crate::capture::{CaptureKind, CaptureOptions};
+
+/// The names captures had before templates: `photo_1760620212`
+pub const DEFAULT_TEMPLATE: &str = "{type}_{timestamp}";
+
+const TOKENS: &[&str] = &["date", "time", "timestamp", "entry", "type", "seq"];
+
+/// Tokens that change from one capture to the next; a template needs one
+const UNIQUE_TOKENS: &[&str] = &["time", "timestamp"];
+
+const MAX_TEMPLATE_CHARS: usize = 100;
+
+/// Highest `-<n>` tried before giving up on a free name
+const MAX_SUFFIX: u32 = 10_000;
+
+/// Trimmed from both ends of a name, e.g. what `{entry}` leaves without an entry
+const SEPARATORS: [char; 4] = ['-', '_', '.', ' '];
+
+/// `media.fileNameTemplate`; `None` uses `DEFAULT_TEMPLATE`
+static TEMPLATE: RwLock<Option<String>> = RwLock::new(None);
+
+pub fn set_template(template: Option<String>) {
+    *TEMPLATE.write().unwrap_or_else(|e| e.into_inner()) = template;
+}
+
+fn check_literal(text: &str) -> Result<(), String> {
+    match text
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ')))
+    {
+        Some(c) => Err(format!("{:?} isn't allowed in file names", c)),
+        None => Ok(()),
+    }
+}
+
+/// A template must only use known tokens and characters that are safe in a
+/// file name, and `{time}` or `{timestamp}` so captures don't all get one name
+pub fn validate(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("The file name template must not be empty".to_string());
+    }
+    if template.chars().count() > MAX_TEMPLATE_CHARS {
+        return Err(format!("The file name template is longer than {} characters", MAX_TEMPLATE_CHARS));
+    }
+    if template.starts_with('.') {
+        return Err("The file name template must not start with a dot".to_string());
+    }
+    let mut unique = false;
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        check_literal(&rest[..start])?;
+        let end = rest[start..]
+            .find('}')
+            .filter(|_| rest[start..].starts_with('{'))
+            .ok_or("The file name template has an unmatched brace")?;
+        let token = &rest[start + 1..start + end];
+        if !TOKENS.contains(&token) {
+            return Err(format!("Unknown file name token {{{}}}", token));
+        }
+        unique |= UNIQUE_TOKENS.contains(&token);
+        rest = &rest[start + end + 1..];
+    }
+    check_literal(rest)?;
+    if !unique {
+        return Err("The file name template needs {time} or {timestamp}".to_string());
+    }
+    Ok(())
+}
+
+/// `stem`, or the first of `stem-2`, `stem-3`... that names no file in `dir`
+/// with any of `suffixes` (e.g. `.jpg`) yet
+pub(crate) fn unique_stem(dir: &Path, stem: &str, suffixes: &[&str]) -> Result<String, String> {
+    let free = |candidate: &str| {
+        suffixes
+            .iter()
+            .all(|suffix| !dir.join(format!("{}{}", candidate, suffix)).exists())
+    };
+    if free(stem) {
+        return Ok(stem.to_string());
+    }
+    (2..=MAX_SUFFIX)
+        .map(|n| format!("{}-{}", stem, n))
+        .find(|candidate| free(candidate))
+        .ok_or_else(|| format!("No free file name for {} in {}", stem, dir.display()))
+}
+
+/// Name of a new capture of `kind` in `dir`, without its extension, that no
+/// file there has with any of `suffixes` yet. `seq` is the photo's number in
+/// a burst; when the template has no `{seq}`, `_<seq>` is appended so the
+/// photos of a burst don't share a name.
+pub(crate) fn stem(
+    dir: &Path,
+    suffixes: &[&str],
+    kind: CaptureKind,
+    options: &CaptureOptions,
+    seq: Option<u32>,
+) -> Result<String, String> {
+    let template = TEMPLATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+    let now = Local::now();
+    let mut name = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        name.push_str(&rest[..start]);
+        let _ = match &rest[start + 1..start + end] {
+            "date" => write!(name, "{}", now.format("%Y-%m-%d")),
+            "time" => write!(name, "{}", now.format("%H%M%S")),
+            "timestamp" => write!(name, "{}", now.timestamp()),
+            "entry" => options.entry_id.map_or(Ok(()), |entry| write!(name, "{}", entry)),
+            "type" => write!(name, "{}", kind.name()),
+            "seq" => write!(name, "{}", seq.unwrap_or(1)),
+            _ => Ok(()),
+        };
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    if let Some(seq) = seq.filter(|_| !template.contains("{seq}")) {
+        let _ = write!(name, "_{}", seq);
+    }
+
+    let name = name.trim_matches(SEPARATORS);
+    unique_stem(dir, if name.is_empty() { kind.name() } else { name }, suffixes)
+}
@@ -0,0 +1,236 @@
+//! Displays and windows that screenshots and screen recordings can target,
+//! for a picker in the webview. macOS lists them through CoreGraphics with
+//! thumbnails; Windows and X11 list them without thumbnails.
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TargetKind {
+    Display,
+    Window,
+}
+
+/// Entry of `list_capture_targets`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureTarget {
+    pub kind: TargetKind,
+    /// CGDirectDisplayID / CGWindowID on macOS, HMONITOR / HWND on Windows,
+    /// the X window on Linux
+    pub id: u64,
+    /// Display name, or the window title (the app name when it has none)
+    pub title: String,
+    /// App owning the window
+    #[serde(default)]
+    pub app: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Small PNG as a `data:` URL
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+#[cfg(target_os = "macos")]
+fn targets(thumbnails: bool) -> Result<Vec<CaptureTarget>, String> {
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    extern "C" {
+        fn capture_targets_copy_json(thumbnails: bool) -> *mut c_char;
+        fn capture_targets_free_string(value: *mut c_char);
+    }
+
+    let json = unsafe {
+        let raw = capture_targets_copy_json(thumbnails);
+        if raw.is_null() {
+            return Err("Failed to list displays and windows".to_string());
+        }
+        let json = CStr::from_ptr(raw).to_string_lossy().to_string();
+        capture_targets_free_string(raw);
+        json
+    };
+    serde_json::from_str(&json).map_err(|e| format!("Failed to read capture targets: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+fn targets(_thumbnails: bool) -> Result<Vec<CaptureTarget>, String> {
+    #[repr(C)]
+    #[derive(Default)]
+    struct Rect {
+        left: i32,
+        top: i32,
+        right: i32,
+        bottom: i32,
+    }
+
+    #[repr(C)]
+    struct MonitorInfoEx {
+        size: u32,
+        monitor: Rect,
+        work: Rect,
+        flags: u32,
+        device: [u16; 32],
+    }
+
+    const GWL_EXSTYLE: i32 = -20;
+    const GW_OWNER: u32 = 4;
+    const WS_EX_TOOLWINDOW: u32 = 0x80;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn EnumDisplayMonitors(
+            hdc: isize,
+            clip: *const Rect,
+            callback: extern "system" fn(isize, isize, *mut Rect, isize) -> i32,
+            data: isize,
+        ) -> i32;
+        fn GetMonitorInfoW(monitor: isize, info: *mut MonitorInfoEx) -> i32;
+        fn EnumWindows(callback: extern "system" fn(isize, isize) -> i32, data: isize) -> i32;
+        fn IsWindowVisible(hwnd: isize) -> i32;
+        fn GetWindow(hwnd: isize, command: u32) -> isize;
+        fn GetWindowLongW(hwnd: isize, index: i32) -> i32;
+        fn GetWindowRect(hwnd: isize, rect: *mut Rect) -> i32;
+        fn GetWindowTextLengthW(hwnd: isize) -> i32;
+    }
+
+    extern "system" fn collect_monitor(monitor: isize, _hdc: isize, _rect: *mut Rect, data: isize) -> i32 {
+        unsafe { (*(data as *mut Vec<isize>)).push(monitor) };
+        1
+    }
+
+    extern "system" fn collect_window(hwnd: isize, data: isize) -> i32 {
+        unsafe { (*(data as *mut Vec<isize>)).push(hwnd) };
+        1
+    }
+
+    let mut monitors: Vec<isize> = Vec::new();
+    let mut windows: Vec<isize> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(0, std::ptr::null(), collect_monitor, &mut monitors as *mut _ as isize);
+        EnumWindows(collect_window, &mut windows as *mut _ as isize);
+    }
+
+    let mut targets = Vec::new();
+    for monitor in monitors {
+        let mut info = MonitorInfoEx {
+            size: std::mem::size_of::<MonitorInfoEx>() as u32,
+            monitor: Rect::default(),
+            work: Rect::default(),
+            flags: 0,
+            device: [0; 32],
+        };
+        if unsafe { GetMonitorInfoW(monitor, &mut info) } == 0 {
+            continue;
+        }
+        let name_len = info.device.iter().position(|c| *c == 0).unwrap_or(info.device.len());
+        targets.push(CaptureTarget {
+            kind: TargetKind::Display,
+            id: monitor as u64,
+            title: String::from_utf16_lossy(&info.device[..name_len]),
+            app: None,
+            width: (info.monitor.right - info.monitor.left).max(0) as u32,
+            height: (info.monitor.bottom - info.monitor.top).max(0) as u32,
+            thumbnail: None,
+        });
+    }
+
+    for hwnd in windows {
+        // Top-level app windows only: visible, titled, unowned and not tool palettes
+        let listed = unsafe {
+            IsWindowVisible(hwnd) != 0
+                && GetWindowTextLengthW(hwnd) > 0
+                && GetWindow(hwnd, GW_OWNER) == 0
+                && GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW == 0
+        };
+        let mut rect = Rect::default();
+        if !listed || unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+            continue;
+        }
+        let (app, title) = crate::activity::window_app_and_title(hwnd);
+        targets.push(CaptureTarget {
+            kind: TargetKind::Window,
+            id: hwnd as u64,
+            title: title.unwrap_or_else(|| app.clone()),
+            app: Some(app),
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+            thumbnail: None,
+        });
+    }
+    Ok(targets)
+}
+
+#[cfg(target_os = "linux")]
+fn targets(_thumbnails: bool) -> Result<Vec<CaptureTarget>, String> {
+    use std::os::raw::c_ulong;
+    use x11_dl::xlib::{Window, XWindowAttributes, XA_WINDOW};
+
+    use crate::x11_session::{property, window_class, window_title};
+
+    crate::x11_session::with_display(|xlib, display| unsafe {
+        let size = |window: Window| {
+            let mut attributes: XWindowAttributes = std::mem::zeroed();
+            ((xlib.XGetWindowAttributes)(display, window, &mut attributes) != 0)
+                .then(|| (attributes.width.max(0) as u32, attributes.height.max(0) as u32))
+        };
+
+        // One X screen spans all monitors
+        let root = (xlib.XDefaultRootWindow)(display);
+        let (width, height) = size(root)?;
+        let mut targets = vec![CaptureTarget {
+            kind: TargetKind::Display,
+            id: root as u64,
+            title: "Screen".to_string(),
+            app: None,
+            width,
+            height,
+            thumbnail: None,
+        }];
+
+        // Managed windows as listed by an EWMH window manager
+        if let Some((bytes, 32, count)) = property(xlib, display, root, c"_NET_CLIENT_LIST", XA_WINDOW) {
+            for i in 0..count as usize {
+                let offset = i * std::mem::size_of::<c_ulong>();
+                let window = std::ptr::read_unaligned(bytes[offset..].as_ptr() as *const c_ulong) as Window;
+                let Some((width, height)) = size(window) else {
+                    continue;
+                };
+                let app = window_class(xlib, display, window);
+                let Some(title) = window_title(xlib, display, window).or_else(|| app.clone()) else {
+                    continue;
+                };
+                targets.push(CaptureTarget {
+                    kind: TargetKind::Window,
+                    id: window as u64,
+                    title,
+                    app,
+                    width,
+                    height,
+                    thumbnail: None,
+                });
+            }
+        }
+        Some(targets)
+    })
+    .ok_or_else(|| "Listing windows needs an X11 session".to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn targets(_thumbnails: bool) -> Result<Vec<CaptureTarget>, String> {
+    Err("Screen capture targets are not available on this platform".to_string())
+}
+
+/// Displays, then visible windows, that a screenshot or screen recording can
+/// target. `thumbnails` (default on) adds a preview image where supported.
+#[tauri::command]
+#[instrument(err)]
+pub async fn list_capture_targets(thumbnails: Option<bool>) -> Result<Vec<CaptureTarget>, String> {
+    let thumbnails = thumbnails.unwrap_or(true);
+    let targets = tokio::task::spawn_blocking(move || targets(thumbnails))
+        .await
+        .map_err(|e| format!("Capture target task panicked: {}", e))??;
+    debug!(count = targets.len(), "listed capture targets");
+    Ok(targets)
+}
@@ -0,0 +1,180 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+#import <CoreGraphics/CoreGraphics.h>
+#import <unistd.h>
+
+// Thumbnails are scaled down to at most this width
+static const CGFloat THUMBNAIL_WIDTH = 320.0;
+
+// Windows smaller than this are palettes, status items and the like
+static const CGFloat MIN_WINDOW_SIZE = 50.0;
+
+static NSString *thumbnailDataURL(CGImageRef image) {
+    size_t width = CGImageGetWidth(image);
+    size_t height = CGImageGetHeight(image);
+    if (width == 0 || height == 0) {
+        return nil;
+    }
+    CGFloat scale = MIN(1.0, THUMBNAIL_WIDTH / (CGFloat)width);
+    size_t scaledWidth = MAX((size_t)1, (size_t)(width * scale));
+    size_t scaledHeight = MAX((size_t)1, (size_t)(height * scale));
+
+    CGColorSpaceRef space = CGColorSpaceCreateDeviceRGB();
+    CGContextRef context = CGBitmapContextCreate(NULL, scaledWidth, scaledHeight, 8, 0, space,
+                                                 kCGImageAlphaPremultipliedLast);
+    CGColorSpaceRelease(space);
+    if (context == NULL) {
+        return nil;
+    }
+    CGContextSetInterpolationQuality(context, kCGInterpolationMedium);
+    CGContextDrawImage(context, CGRectMake(0, 0, scaledWidth, scaledHeight), image);
+    CGImageRef scaled = CGBitmapContextCreateImage(context);
+    CGContextRelease(context);
+    if (scaled == NULL) {
+        return nil;
+    }
+
+    NSBitmapImageRep *rep = [[NSBitmapImageRep alloc] initWithCGImage:scaled];
+    CGImageRelease(scaled);
+    NSData *png = [rep representationUsingType:NSBitmapImageFileTypePNG properties:@{}];
+    if (png == nil) {
+        return nil;
+    }
+    return [@"data:image/png;base64," stringByAppendingString:[png base64EncodedStringWithOptions:0]];
+}
+
+static NSString *displayName(CGDirectDisplayID display) {
+    for (NSScreen *screen in [NSScreen screens]) {
+        NSNumber *number = screen.deviceDescription[@"NSScreenNumber"];
+        if (number.unsignedIntValue == display) {
+            return screen.localizedName;
+        }
+    }
+    return [NSString stringWithFormat:@"Display %u", display];
+}
+
+#pragma clang diagnostic push
+#pragma clang diagnostic ignored "-Wdeprecated-declarations"
+
+static void addDisplays(NSMutableArray *targets, bool thumbnails) {
+    uint32_t count = 0;
+    if (CGGetActiveDisplayList(0, NULL, &count) != kCGErrorSuccess || count == 0) {
+        return;
+    }
+    CGDirectDisplayID displays[count];
+    if (CGGetActiveDisplayList(count, displays, &count) != kCGErrorSuccess) {
+        return;
+    }
+    for (uint32_t i = 0; i < count; i++) {
+        CGDirectDisplayID display = displays[i];
+        NSMutableDictionary *target = [@{
+            @"kind": @"display",
+            @"id": @(display),
+            @"title": displayName(display),
+            @"width": @(CGDisplayPixelsWide(display)),
+            @"height": @(CGDisplayPixelsHigh(display)),
+        } mutableCopy];
+        if (thumbnails) {
+            CGImageRef image = CGDisplayCreateImage(display);
+            if (image != NULL) {
+                target[@"thumbnail"] = thumbnailDataURL(image);
+                CGImageRelease(image);
+            }
+        }
+        [targets addObject:target];
+    }
+}
+
+static void addWindows(NSMutableArray *targets, bool thumbnails) {
+    CFArrayRef windows = CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements,
+                                                    kCGNullWindowID);
+    if (windows == NULL) {
+        return;
+    }
+    NSArray *windowList = CFBridgingRelease(windows);
+    pid_t ownPid = getpid();
+    for (NSDictionary *window in windowList) {
+        NSNumber *ownerPid = window[(__bridge NSString *)kCGWindowOwnerPID];
+        NSNumber *layer = window[(__bridge NSString *)kCGWindowLayer];
+        if (layer.intValue != 0 || ownerPid.intValue == ownPid) {
+            continue;
+        }
+        CGRect bounds;
+        NSDictionary *boundsInfo = window[(__bridge NSString *)kCGWindowBounds];
+        if (!CGRectMakeWithDictionaryRepresentation((__bridge CFDictionaryRef)boundsInfo, &bounds)
+            || bounds.size.width < MIN_WINDOW_SIZE || bounds.size.height < MIN_WINDOW_SIZE) {
+            continue;
+        }
+
+        CGWindowID windowId = [window[(__bridge NSString *)kCGWindowNumber] unsignedIntValue];
+        NSString *owner = window[(__bridge NSString *)kCGWindowOwnerName] ?: @"";
+        // Titles are only reported once Screen Recording permission is granted
+        NSString *name = window[(__bridge NSString *)kCGWindowName];
+        NSMutableDictionary *target = [@{
+            @"kind": @"window",
+            @"id": @(windowId),
+            @"title": name.length > 0 ? name : owner,
+            @"app": owner,
+            @"width": @((uint32_t)bounds.size.width),
+            @"height": @((uint32_t)bounds.size.height),
+        } mutableCopy];
+        if (thumbnails) {
+            CGImageRef image = CGWindowListCreateImage(CGRectNull, kCGWindowListOptionIncludingWindow, windowId,
+                                                       kCGWindowImageBoundsIgnoreFraming | kCGWindowImageNominalResolution);
+            if (image != NULL) {
+                target[@"thumbnail"] = thumbnailDataURL(image);
+                CGImageRelease(image);
+            }
+        }
+        [targets addObject:target];
+    }
+}
+
+// One of this app's own windows as PNG at full resolution, for bug reports.
+// Release with capture_targets_free_bytes.
+uint8_t *capture_targets_copy_own_window_png(void *nsWindow, size_t *length) {
+    @autoreleasepool {
+        NSWindow *window = (__bridge NSWindow *)nsWindow;
+        CGImageRef image = CGWindowListCreateImage(CGRectNull, kCGWindowListOptionIncludingWindow,
+                                                   (CGWindowID)window.windowNumber,
+                                                   kCGWindowImageBoundsIgnoreFraming | kCGWindowImageBestResolution);
+        if (image == NULL) {
+            return NULL;
+        }
+        NSBitmapImageRep *rep = [[NSBitmapImageRep alloc] initWithCGImage:image];
+        CGImageRelease(image);
+        NSData *png = [rep representationUsingType:NSBitmapImageFileTypePNG properties:@{}];
+        if (png == nil || png.length == 0) {
+            return NULL;
+        }
+        uint8_t *bytes = malloc(png.length);
+        memcpy(bytes, png.bytes, png.length);
+        *length = png.length;
+        return bytes;
+    }
+}
+
+#pragma clang diagnostic pop
+
+// Displays and on-screen windows as a JSON array. Release with capture_targets_free_string.
+char *capture_targets_copy_json(bool thumbnails) {
+    @autoreleasepool {
+        NSMutableArray *targets = [NSMutableArray array];
+        addDisplays(targets, thumbnails);
+        addWindows(targets, thumbnails);
+        NSData *json = [NSJSONSerialization dataWithJSONObject:targets options:0 error:nil];
+        if (json == nil) {
+            return NULL;
+        }
+        NSString *string = [[NSString alloc] initWithData:json encoding:NSUTF8StringEncoding];
+        return strdup(string.UTF8String);
+    }
+}
+
+void capture_targets_free_string(char *value) {
+    free(value);
+}
+
+void capture_targets_free_bytes(uint8_t *bytes) {
+    free(bytes);
+}
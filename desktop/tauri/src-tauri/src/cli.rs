@@ -0,0 +1,180 @@
+//! Quick actions from the command line:
+//!
+//! - `track-the-thing capture photo` / `capture screenshot`
+//! - `track-the-thing note "text"`
+//! - `track-the-thing template <id> [name=value ...]`
+//! - `track-the-thing export --format csv [--output <path>]`
+//!
+//! A running instance receives them through the single-instance plugin and
+//! reports the result as a notification. Otherwise the app starts without a
+//! window, runs the command once the backend is up, prints the result and
+//! exits.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Local;
+use log::{info, warn};
+use serde_json::json;
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::capture::{self, CaptureOptions};
+use crate::entry_templates;
+use crate::export::{self, ExportFormat};
+use crate::{backend_client, notifications, BackendProcess, DesktopConfig};
+
+pub const USAGE: &str = "\
+Usage:
+  track-the-thing capture <photo|screenshot>
+  track-the-thing note <text>
+  track-the-thing template <id> [name=value ...]
+  track-the-thing export --format <csv|json|ics> [--output <path>]";
+
+/// How long a launch that only runs a command waits for the backend
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub enum CliCommand {
+    CapturePhoto,
+    CaptureScreenshot,
+    Note(String),
+    /// Create an entry from a template with these variables
+    Template { id: String, vars: HashMap<String, String> },
+    Export { format: ExportFormat, output: PathBuf },
+}
+
+/// The command in `args` (including the executable), with relative paths
+/// resolved against `cwd`. `None` when the first argument isn't a command,
+/// e.g. a launch with flags, an archive or a deep link.
+pub fn parse(args: &[String], cwd: &Path) -> Option<Result<CliCommand, String>> {
+    let command = args.get(1)?;
+    let rest = &args[2..];
+    Some(match command.as_str() {
+        "capture" => match rest {
+            [what] if what == "photo" => Ok(CliCommand::CapturePhoto),
+            [what] if what == "screenshot" => Ok(CliCommand::CaptureScreenshot),
+            _ => Err("capture takes one argument: photo or screenshot".to_string()),
+        },
+        "note" => {
+            let text = rest.join(" ");
+            if text.trim().is_empty() {
+                Err("note needs some text".to_string())
+            } else {
+                Ok(CliCommand::Note(text))
+            }
+        }
+        "template" => parse_template(rest),
+        "export" => parse_export(rest, cwd),
+        _ => return None,
+    })
+}
+
+fn parse_template(args: &[String]) -> Result<CliCommand, String> {
+    let (id, vars) = args.split_first().ok_or("template needs a template id")?;
+    let vars = vars
+        .iter()
+        .map(|var| {
+            var.split_once('=')
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .ok_or_else(|| format!("Template variables are name=value, got {:?}", var))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(CliCommand::Template { id: id.clone(), vars })
+}
+
+fn parse_export(args: &[String], cwd: &Path) -> Result<CliCommand, String> {
+    let mut format = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--format" => {
+                format = Some(ExportFormat::from_name(value).ok_or_else(|| format!("Unknown export format: {}", value))?)
+            }
+            "--output" => output = Some(cwd.join(value)),
+            _ => return Err(format!("Unknown export option: {}", arg)),
+        }
+    }
+    let format = format.ok_or("export needs --format")?;
+    let output = output.unwrap_or_else(|| {
+        cwd.join(format!("track-the-thing-{}.{}", Local::now().format("%Y%m%d"), format.extension()))
+    });
+    Ok(CliCommand::Export { format, output })
+}
+
+async fn execute(app: &AppHandle, command: CliCommand) -> Result<String, String> {
+    info!("Running command line action {:?}", command);
+    match command {
+        CliCommand::CapturePhoto => {
+            let paths = capture::capture(app, "camera", CaptureOptions::default()).await?;
+            Ok(format!("Photo saved to {}", paths.join(", ")))
+        }
+        CliCommand::CaptureScreenshot => {
+            let paths = capture::capture(app, "screen", CaptureOptions::default()).await?;
+            Ok(format!("Screenshot saved to {}", paths.join(", ")))
+        }
+        CliCommand::Note(text) => {
+            let config = app.state::<DesktopConfig>().inner().clone();
+            let path = format!("/api/entries/note/{}", Local::now().format("%Y-%m-%d"));
+            let body = json!({ "content": text, "content_type": "markdown" });
+            async_runtime::spawn_blocking(move || backend_client::call(&config, "POST", &path, Some(&body)))
+                .await
+                .map_err(|e| format!("Note task failed: {}", e))?
+                .map_err(|e| e.message)?;
+            Ok("Note added to today".to_string())
+        }
+        CliCommand::Template { id, vars } => {
+            entry_templates::create_from_template(app, &id, vars).await?;
+            Ok(format!("Entry from template {} added to today", id))
+        }
+        CliCommand::Export { format, output } => {
+            let dest = output.to_string_lossy().to_string();
+            let export = export::export_data(app.clone(), format, None, Some(dest))
+                .await?
+                .ok_or("The export was cancelled")?;
+            Ok(format!("Exported {} entries to {}", export.entries, export.path))
+        }
+    }
+}
+
+/// Run a command forwarded from a second launch and report how it went
+pub fn dispatch(app: &AppHandle, command: CliCommand) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let (title, body) = match execute(&app, command).await {
+            Ok(message) => (app.package_info().name.clone(), message),
+            Err(e) => ("Command failed".to_string(), e),
+        };
+        info!("{}: {}", title, body);
+        if let Err(e) = notifications::notify(app, title, body, None).await {
+            warn!("Failed to report command result: {}", e);
+        }
+    });
+}
+
+/// Run the command this process was launched with once the backend is up,
+/// then stop the backend and exit
+pub fn run_once(app: &AppHandle, command: CliCommand) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let result = if crate::wait_for_backend_health(&app, BACKEND_START_TIMEOUT).await {
+            execute(&app, command).await
+        } else {
+            Err("The backend did not start".to_string())
+        };
+        let code = match result {
+            Ok(message) => {
+                println!("{}", message);
+                0
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        };
+        app.state::<BackendProcess>().terminate();
+        app.exit(code);
+    });
+}
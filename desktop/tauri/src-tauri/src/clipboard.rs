@@ -0,0 +1,69 @@
+use log::{info, warn};
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::media_store::get_media_dir;
+use crate::permissions::{self, CLIPBOARD};
+use crate::privacy_guard;
+
+/// Whatever was on the system clipboard, normalized for filing as a tracked entry
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ClipboardContent {
+    Text {
+        text: String,
+    },
+    Image {
+        path: String,
+        width: u32,
+        height: u32,
+    },
+    Empty,
+}
+
+/// Read the clipboard, preferring image data over text. Images are saved as PNG
+/// into the media `clipboard` directory and returned by path.
+#[tauri::command]
+pub async fn capture_clipboard(app: AppHandle) -> Result<ClipboardContent, String> {
+    info!("capture_clipboard command called");
+    permissions::require(&app, CLIPBOARD)?;
+    privacy_guard::check(&app, "clipboard").await?;
+
+    // Copying an image in most apps also puts a text fallback (file name, alt text)
+    // on the clipboard, so check for image data first
+    if let Ok(image) = app.clipboard().read_image() {
+        let (width, height) = (image.width(), image.height());
+        let rgba = image.rgba().to_vec();
+
+        let dir = get_media_dir(&app, "clipboard")?;
+        let file_path = dir.join(format!("clipboard_{}.png", chrono::Utc::now().timestamp_millis()));
+
+        let save_path = file_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let buffer = image::RgbaImage::from_raw(width, height, rgba)
+                .ok_or("Clipboard image has an unexpected size")?;
+            buffer
+                .save_with_format(&save_path, image::ImageFormat::Png)
+                .map_err(|e| format!("Failed to save clipboard image: {}", e))
+        })
+        .await
+        .map_err(|e| format!("Clipboard image task panicked: {}", e))??;
+
+        info!("Clipboard image ({}x{}) saved to {}", width, height, file_path.display());
+        return Ok(ClipboardContent::Image {
+            path: file_path.to_string_lossy().to_string(),
+            width,
+            height,
+        });
+    }
+
+    match app.clipboard().read_text() {
+        Ok(text) if !text.trim().is_empty() => Ok(ClipboardContent::Text { text }),
+        Ok(_) => Ok(ClipboardContent::Empty),
+        Err(e) => {
+            warn!("Clipboard has no readable text or image: {}", e);
+            Ok(ClipboardContent::Empty)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Short shareable clips cut from recordings with ffmpeg: a GIF (with a
+//! palette generated from the clip itself, so screen recordings keep their
+//! colours) or a small MP4 or WebM.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use chrono::Utc;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle};
+
+use crate::health::find_on_path;
+use crate::media_store::{get_media_dir, probe, validate_app_path};
+
+/// Where clips are written, under the app data dir
+const CLIPS_DIR: &str = "clips";
+
+/// GIFs grow fast; longer snippets should be videos
+const MAX_GIF_SECS: f64 = 60.0;
+const MAX_VIDEO_SECS: f64 = 10.0 * 60.0;
+
+/// Default width of a GIF; videos keep the recording's width
+const DEFAULT_GIF_WIDTH: u32 = 640;
+const GIF_FPS: u32 = 15;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ClipFormat {
+    Gif,
+    Mp4,
+    Webm,
+}
+
+impl ClipFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Mp4 => "mp4",
+            ClipFormat::Webm => "webm",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Clip {
+    pub path: String,
+    pub duration_secs: f64,
+    pub size_bytes: u64,
+}
+
+/// ffmpeg arguments after the input for `format`, scaled down to `max_width`
+fn output_args(format: ClipFormat, max_width: Option<u32>) -> Vec<String> {
+    // Never scaled up; video sizes stay even for the encoders
+    let scale = |width: u32, even: bool| {
+        if even {
+            format!("scale='trunc(min({},iw)/2)*2':-2", width)
+        } else {
+            format!("scale='min({},iw)':-1:flags=lanczos", width)
+        }
+    };
+    let mut args: Vec<String> = Vec::new();
+    match format {
+        ClipFormat::Gif => {
+            let width = max_width.unwrap_or(DEFAULT_GIF_WIDTH);
+            let filter = format!(
+                "fps={},{},split[a][b];[a]palettegen=stats_mode=diff[palette];[b][palette]paletteuse=dither=bayer",
+                GIF_FPS,
+                scale(width, false)
+            );
+            args.extend(["-filter_complex".to_string(), filter, "-loop".to_string(), "0".to_string()]);
+        }
+        ClipFormat::Mp4 | ClipFormat::Webm => {
+            if let Some(width) = max_width {
+                args.extend(["-vf".to_string(), scale(width, true)]);
+            }
+            let codecs: &[&str] = match format {
+                ClipFormat::Mp4 => &[
+                    "-c:v", "libx264", "-crf", "23", "-pix_fmt", "yuv420p", "-c:a", "aac", "-movflags", "+faststart",
+                ],
+                _ => &["-c:v", "libvpx-vp9", "-crf", "32", "-b:v", "0", "-c:a", "libopus"],
+            };
+            args.extend(codecs.iter().map(|arg| arg.to_string()));
+        }
+    }
+    args
+}
+
+fn cut(
+    source: &Path,
+    dest: &Path,
+    (start, end): (f64, f64),
+    format: ClipFormat,
+    max_width: Option<u32>,
+) -> Result<f64, String> {
+    let ffmpeg = find_on_path("ffmpeg").ok_or("ffmpeg was not found on the PATH")?;
+    let duration = probe(source)?.duration_secs;
+    if duration.is_some_and(|duration| start >= duration) {
+        return Err("The clip starts after the end of the recording".to_string());
+    }
+    let end = duration.map_or(end, |duration| end.min(duration));
+
+    // Seeking before the input is fast and, when re-encoding, exact
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-ss", &format!("{:.3}", start), "-t"])
+        .arg(format!("{:.3}", end - start))
+        .arg("-i")
+        .arg(source)
+        .args(output_args(format, max_width))
+        .arg(dest)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    if !output.status.success() || !dest.is_file() {
+        let _ = fs::remove_file(dest);
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Cutting the clip failed: {}", reason.trim()));
+    }
+    Ok(end - start)
+}
+
+/// Cut seconds `start` to `end` of a recording in the app's data directories
+/// into a GIF, MP4 or WebM no wider than `max_width` (GIFs default to 640
+/// pixels). GIFs are limited to a minute and videos to ten. Returns the new
+/// file, under `clips/` in the app data dir.
+#[tauri::command]
+pub async fn export_clip(
+    app: AppHandle,
+    path: String,
+    start: f64,
+    end: f64,
+    format: ClipFormat,
+    max_width: Option<u32>,
+) -> Result<Clip, String> {
+    let source = validate_app_path(&app, &path)?;
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || end <= start {
+        return Err("The clip must start at 0 seconds or later and end after it starts".to_string());
+    }
+    let max_secs = if format == ClipFormat::Gif { MAX_GIF_SECS } else { MAX_VIDEO_SECS };
+    if end - start > max_secs {
+        return Err(format!("Clips in this format can be at most {} seconds long", max_secs));
+    }
+    if max_width.is_some_and(|width| width < 16) {
+        return Err("The clip must be at least 16 pixels wide".to_string());
+    }
+
+    let dest = get_media_dir(&app, CLIPS_DIR)?.join(format!(
+        "clip_{}.{}",
+        Utc::now().timestamp_millis(),
+        format.extension()
+    ));
+    let written = dest.clone();
+    let duration_secs = async_runtime::spawn_blocking(move || cut(&source, &written, (start, end), format, max_width))
+        .await
+        .map_err(|e| format!("Clip task failed: {}", e))??;
+    info!("Cut a {:.1} s {:?} clip from {} to {}", duration_secs, format, path, dest.display());
+    Ok(Clip {
+        path: dest.to_string_lossy().to_string(),
+        duration_secs,
+        size_bytes: fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0),
+    })
+}
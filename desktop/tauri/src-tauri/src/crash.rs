@@ -0,0 +1,355 @@
+use std::backtrace::Backtrace;
+use std::env;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+use crash_handler::{CrashEventResult, CrashHandler};
+use log::{error, info, warn};
+use minidumper::{Client, LoopAction, MinidumpBinary, Server, ServerHandler, SocketName};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// Crash reports are written here under the data dir
+pub const CRASH_DIR: &str = "crashes";
+
+/// Present while the app runs; left behind when it doesn't exit cleanly
+const SESSION_FILE: &str = "session.json";
+
+/// Launches the executable as the out-of-process minidump writer instead of the app
+const MONITOR_FLAG: &str = "--crash-monitor";
+
+/// Message telling the monitor where to write dumps
+const MESSAGE_CRASH_DIR: u32 = 1;
+
+const MONITOR_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Where panic reports go; unset until settings have been read
+static CURRENT_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Connection to the monitor process and the installed signal/exception
+/// handler, kept for the life of the app
+static NATIVE: OnceLock<(Arc<Client>, CrashHandler)> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SessionMarker {
+    pid: u32,
+    app_version: String,
+    started_at: DateTime<Local>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CrashKind {
+    /// Rust panic, with message and backtrace as text
+    Panic,
+    /// Native crash captured as a minidump
+    Minidump,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub name: String,
+    pub kind: CrashKind,
+    pub created_at: Option<DateTime<Local>>,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashStatus {
+    /// The last run didn't exit cleanly (crash, force quit or power loss)
+    pub previous_session_crashed: bool,
+    /// When that session started
+    pub previous_session_started: Option<DateTime<Local>>,
+    /// Reports written since the previous session started
+    pub reports: Vec<CrashReport>,
+}
+
+/// What was found at startup, kept for `get_crash_status`
+pub struct CrashState {
+    status: Mutex<CrashStatus>,
+}
+
+/// The directory crash reports are currently written to
+pub fn crash_dir() -> Option<PathBuf> {
+    CURRENT_DIR.read().ok().and_then(|dir| dir.clone())
+}
+
+fn timestamp() -> String {
+    Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// Write a report for every panic in addition to the default output
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::force_capture();
+        let thread = thread::current();
+        let report = format!(
+            "Track the Thing {} panicked on thread '{}' ({} {})\n\n{}\n\nBacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            thread.name().unwrap_or("<unnamed>"),
+            env::consts::OS,
+            env::consts::ARCH,
+            info,
+            backtrace
+        );
+        error!("{}", info);
+
+        if let Some(dir) = crash_dir() {
+            let path = dir.join(format!("panic-{}.txt", timestamp()));
+            let _ = fs::create_dir_all(&dir);
+            if fs::write(&path, report).is_ok() {
+                error!("Panic report written to {}", path.display());
+            }
+        }
+        previous(info);
+    }));
+}
+
+struct MonitorHandler {
+    dir: Mutex<PathBuf>,
+}
+
+impl ServerHandler for MonitorHandler {
+    fn create_minidump_file(&self) -> Result<(fs::File, PathBuf), std::io::Error> {
+        let dir = self.dir.lock().map(|dir| dir.clone()).unwrap_or_else(|_| env::temp_dir());
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("minidump-{}.dmp", timestamp()));
+        Ok((fs::File::create(&path)?, path))
+    }
+
+    fn on_minidump_created(&self, result: Result<MinidumpBinary, minidumper::Error>) -> LoopAction {
+        match result {
+            Ok(dump) => eprintln!("Crash minidump written to {}", dump.path.display()),
+            Err(e) => eprintln!("Failed to write crash minidump: {}", e),
+        }
+        // The app is gone
+        LoopAction::Exit
+    }
+
+    fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+        if kind == MESSAGE_CRASH_DIR {
+            if let Ok(mut dir) = self.dir.lock() {
+                *dir = PathBuf::from(String::from_utf8_lossy(&buffer).to_string());
+            }
+        }
+    }
+
+    fn on_client_disconnected(&self, num_clients: usize) -> LoopAction {
+        if num_clients == 0 {
+            LoopAction::Exit
+        } else {
+            LoopAction::Continue
+        }
+    }
+}
+
+/// When launched as the crash monitor, serve minidump requests until the app
+/// exits and return true; the caller must not start the app then
+pub fn run_monitor_if_requested() -> bool {
+    let args: Vec<String> = env::args().collect();
+    let Some(socket) = args
+        .iter()
+        .position(|arg| arg == MONITOR_FLAG)
+        .and_then(|index| args.get(index + 1))
+    else {
+        return false;
+    };
+
+    let socket = PathBuf::from(socket);
+    let result = Server::with_name(SocketName::Path(&socket)).and_then(|mut server| {
+        let handler = MonitorHandler {
+            dir: Mutex::new(env::temp_dir()),
+        };
+        server.run(Box::new(handler), &AtomicBool::new(false), None)
+    });
+    if let Err(e) = result {
+        eprintln!("Crash monitor failed: {}", e);
+    }
+    let _ = fs::remove_file(&socket);
+    true
+}
+
+fn tell_monitor(client: &Client, dir: &Path) {
+    if let Err(e) = client.send_message(MESSAGE_CRASH_DIR, dir.to_string_lossy().as_bytes()) {
+        warn!("Failed to tell the crash monitor about {}: {}", dir.display(), e);
+    }
+}
+
+/// Start the monitor process and route native crashes to it
+fn attach_native_handler(dir: PathBuf) -> Result<(), String> {
+    let socket = env::temp_dir().join(format!("ttt-crash-{}.sock", std::process::id()));
+    let _ = fs::remove_file(&socket);
+    let exe = env::current_exe().map_err(|e| format!("Failed to resolve executable: {}", e))?;
+    let mut monitor = Command::new(exe)
+        .arg(MONITOR_FLAG)
+        .arg(&socket)
+        .spawn()
+        .map_err(|e| format!("Failed to start crash monitor: {}", e))?;
+
+    let started = SystemTime::now();
+    let client = loop {
+        match Client::with_name(SocketName::Path(&socket)) {
+            Ok(client) => break Arc::new(client),
+            Err(e) if started.elapsed().unwrap_or_default() > MONITOR_CONNECT_TIMEOUT => {
+                let _ = monitor.kill();
+                let _ = monitor.wait();
+                return Err(format!("Failed to connect to crash monitor: {}", e));
+            }
+            Err(_) => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+    tell_monitor(&client, &dir);
+
+    let for_crash = Arc::clone(&client);
+    // SAFETY: the closure runs in a signal/exception context; it only sends
+    // the crash context over the already-open connection
+    let handler = CrashHandler::attach(unsafe {
+        crash_handler::make_crash_event(move |context: &crash_handler::CrashContext| {
+            CrashEventResult::Handled(for_crash.request_dump(context).is_ok())
+        })
+    })
+    .map_err(|e| format!("Failed to install crash handler: {}", e))?;
+
+    // Only the monitor may inspect this process
+    #[cfg(target_os = "linux")]
+    handler.set_ptracer(Some(monitor.id()));
+    // Reap it when it exits after the app disconnects
+    thread::spawn(move || monitor.wait());
+
+    let _ = NATIVE.set((client, handler));
+    Ok(())
+}
+
+/// Crash reports in `dir` written after `since`, newest first
+pub(crate) fn list_reports(dir: &Path, since: Option<SystemTime>) -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let kind = if name.starts_with("panic-") {
+                CrashKind::Panic
+            } else if name.ends_with(".dmp") {
+                CrashKind::Minidump
+            } else {
+                return None;
+            };
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().ok();
+            if since.is_some_and(|since| modified.is_some_and(|modified| modified < since)) {
+                return None;
+            }
+            Some(CrashReport {
+                name,
+                kind,
+                created_at: modified.map(DateTime::<Local>::from),
+                bytes: meta.len(),
+            })
+        })
+        .collect();
+    reports.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+    reports
+}
+
+/// Check whether the previous run crashed, start a new session marker and
+/// install the native crash handler. `dir` is `<data dir>/crashes`.
+pub fn init_crash_reporting(app: &AppHandle, dir: PathBuf) {
+    if let Err(e) = fs::create_dir_all(&dir) {
+        warn!("Failed to create crash report directory {}: {}", dir.display(), e);
+    }
+    set_crash_dir(dir.clone());
+
+    let marker_path = dir.join(SESSION_FILE);
+    let previous: Option<SessionMarker> = fs::read_to_string(&marker_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let mut status = CrashStatus::default();
+    if let Some(previous) = previous {
+        warn!(
+            "Previous session (pid {}, started {}) did not exit cleanly",
+            previous.pid, previous.started_at
+        );
+        status.previous_session_crashed = true;
+        status.previous_session_started = Some(previous.started_at);
+        status.reports = list_reports(&dir, Some(SystemTime::from(previous.started_at)));
+    }
+
+    let marker = SessionMarker {
+        pid: std::process::id(),
+        app_version: app.package_info().version.to_string(),
+        started_at: Local::now(),
+    };
+    match serde_json::to_string_pretty(&marker) {
+        Ok(json) => {
+            if let Err(e) = crate::prefs::write_atomic(&marker_path, &json) {
+                warn!("Failed to write session marker: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize session marker: {}", e),
+    }
+    app.manage(CrashState {
+        status: Mutex::new(status),
+    });
+
+    // Connecting to the monitor can take a moment; don't hold up startup
+    thread::spawn(move || match attach_native_handler(dir) {
+        Ok(()) => info!("Native crash reporting enabled"),
+        Err(e) => warn!("Native crash reporting unavailable: {}", e),
+    });
+}
+
+/// Point crash reports at a new directory (after the data dir moved)
+pub fn set_crash_dir(dir: PathBuf) {
+    if let Some((client, _)) = NATIVE.get() {
+        tell_monitor(client, &dir);
+    }
+    if let Ok(mut current) = CURRENT_DIR.write() {
+        *current = Some(dir);
+    }
+}
+
+/// Mark the session as cleanly ended
+pub fn end_session() {
+    if let Some(dir) = crash_dir() {
+        let _ = fs::remove_file(dir.join(SESSION_FILE));
+    }
+}
+
+/// Whether the previous run crashed, and the reports it left
+#[tauri::command]
+pub async fn get_crash_status(state: tauri::State<'_, CrashState>) -> Result<CrashStatus, String> {
+    state
+        .status
+        .lock()
+        .map(|status| status.clone())
+        .map_err(|e| format!("Lock error: {}", e))
+}
+
+/// Delete all crash reports and forget that the previous session crashed
+#[tauri::command]
+pub async fn clear_crash_reports(state: tauri::State<'_, CrashState>) -> Result<usize, String> {
+    let dir = crash_dir().ok_or_else(|| "Crash reporting is not set up".to_string())?;
+    let reports = list_reports(&dir, None);
+    for report in &reports {
+        if let Err(e) = fs::remove_file(dir.join(&report.name)) {
+            warn!("Failed to delete {}: {}", report.name, e);
+        }
+    }
+    if let Ok(mut status) = state.status.lock() {
+        *status = CrashStatus::default();
+    }
+    Ok(reports.len())
+}
@@ -0,0 +1,272 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Manager, State};
+
+use crate::install_config;
+use crate::settings::{self, ConfigSource, SettingsState};
+use crate::{event_batch, BackendProcess};
+
+/// Everything the backend keeps under the data dir (see `load_production_env`).
+/// SQLite's side files must travel with the database.
+const DATA_ENTRIES: &[&str] = &[
+    "ttt_desktop.db",
+    "ttt_desktop.db-wal",
+    "ttt_desktop.db-shm",
+    "ttt_desktop.db-journal",
+    "uploads",
+    "static",
+    "logs",
+    "crashes",
+    "backups",
+    crate::startup_repairs::RECOVERED_DIR,
+    crate::webview_state::STATE_FILE,
+    crate::timer::STATE_FILE,
+];
+
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Set while a migration runs so a second one can't interleave with it
+#[derive(Default)]
+pub struct DataDirMigration {
+    running: AtomicBool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+enum MigrationPhase {
+    StoppingBackend,
+    Moving,
+    StartingBackend,
+    Done,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MigrationProgress {
+    phase: MigrationPhase,
+    bytes_done: u64,
+    bytes_total: u64,
+    /// Entry currently being moved
+    item: Option<String>,
+}
+
+fn emit_progress(app: &AppHandle, phase: MigrationPhase, bytes_done: u64, bytes_total: u64, item: Option<&str>) {
+    let progress = MigrationProgress {
+        phase,
+        bytes_done,
+        bytes_total,
+        item: item.map(str::to_string),
+    };
+    // Byte counts while moving are coalesced; phase changes always go out
+    match phase {
+        MigrationPhase::Moving => event_batch::emit(app, "data-dir-progress", progress),
+        _ => event_batch::emit_now(app, "data-dir-progress", progress),
+    }
+}
+
+/// The directory the running backend was pointed at
+pub(crate) fn current_data_dir(app: &AppHandle) -> PathBuf {
+    env::var_os("TAURI_DESKTOP_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| app.state::<SettingsState>().current().data_dir())
+}
+
+fn entry_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| entry_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+fn validate_target(current: &Path, target: &Path) -> Result<(), String> {
+    if !target.is_absolute() {
+        return Err("Data directory must be an absolute path".to_string());
+    }
+    fs::create_dir_all(target).map_err(|e| format!("Cannot create {}: {}", target.display(), e))?;
+    let target = target
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve {}: {}", target.display(), e))?;
+    let current = current.canonicalize().unwrap_or_else(|_| current.to_path_buf());
+
+    if target == current {
+        return Err("That is already the data directory".to_string());
+    }
+    if target.starts_with(&current) || current.starts_with(&target) {
+        return Err("The new data directory can't be inside the current one or contain it".to_string());
+    }
+    if let Some(existing) = DATA_ENTRIES.iter().find(|name| target.join(name).exists()) {
+        return Err(format!("{} already contains {}", target.display(), existing));
+    }
+
+    let probe = target.join(".ttt-write-test");
+    fs::write(&probe, b"").map_err(|e| format!("{} is not writable: {}", target.display(), e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+fn copy_recursive(src: &Path, dst: &Path, copied: &mut dyn FnMut(u64)) -> io::Result<()> {
+    if fs::symlink_metadata(src)?.is_dir() {
+        fs::create_dir_all(dst)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dst.join(entry.file_name()), copied)?;
+        }
+    } else {
+        copied(fs::copy(src, dst)?);
+    }
+    Ok(())
+}
+
+/// Rename when both dirs are on the same volume, otherwise copy then delete
+fn move_entry(src: &Path, dst: &Path, copied: &mut dyn FnMut(u64)) -> io::Result<()> {
+    if fs::rename(src, dst).is_ok() {
+        copied(entry_size(dst));
+        return Ok(());
+    }
+    if let Err(e) = copy_recursive(src, dst, copied) {
+        // Leave the source intact and drop the partial copy
+        let _ = if dst.is_dir() { fs::remove_dir_all(dst) } else { fs::remove_file(dst) };
+        return Err(e);
+    }
+    if src.is_dir() {
+        fs::remove_dir_all(src)
+    } else {
+        fs::remove_file(src)
+    }
+}
+
+/// Move the data entries, putting already-moved ones back if any move fails
+fn move_data(app: &AppHandle, from: &Path, to: &Path) -> Result<(), String> {
+    let entries: Vec<&str> = DATA_ENTRIES.iter().copied().filter(|name| from.join(name).exists()).collect();
+    let total: u64 = entries.iter().map(|name| entry_size(&from.join(name))).sum();
+    let mut done = 0u64;
+    let mut moved = Vec::new();
+
+    for name in entries {
+        info!("Moving {} to {}", name, to.display());
+        emit_progress(app, MigrationPhase::Moving, done, total, Some(name));
+        let result = move_entry(&from.join(name), &to.join(name), &mut |bytes| {
+            done += bytes;
+            emit_progress(app, MigrationPhase::Moving, done, total, Some(name));
+        });
+        if let Err(e) = result {
+            warn!("Failed to move {}: {}, rolling back", name, e);
+            for name in moved.iter().rev() {
+                if let Err(e) = move_entry(&to.join(name), &from.join(name), &mut |_| {}) {
+                    warn!("Failed to move {} back: {}", name, e);
+                }
+            }
+            return Err(format!("Failed to move {}: {}", name, e));
+        }
+        moved.push(name);
+    }
+    emit_progress(app, MigrationPhase::Moving, total, total, None);
+    Ok(())
+}
+
+async fn migrate(app: &AppHandle, from: PathBuf, to: PathBuf) -> Result<(), String> {
+    emit_progress(app, MigrationPhase::StoppingBackend, 0, 0, None);
+    let _restart = crate::backend_client::begin_restart();
+    app.state::<BackendProcess>().terminate();
+    // The shell's own log is open in logs/; hold its lines until the move is done
+    crate::logs::set_log_dir(None);
+
+    let moved = {
+        let (app, from, to) = (app.clone(), from.clone(), to.clone());
+        async_runtime::spawn_blocking(move || move_data(&app, &from, &to))
+            .await
+            .map_err(|e| format!("Data move panicked: {}", e))
+            .and_then(|result| result)
+    };
+    if let Err(e) = moved {
+        // Everything is back where it was, so bring the old backend back up
+        crate::logs::set_log_dir(Some(from.join("logs")));
+        if let Err(restart) = crate::start_backend(app) {
+            warn!("{}", restart);
+        }
+        return Err(e);
+    }
+
+    let state = app.state::<SettingsState>();
+    let updated = settings::update(app, &state, |file| file.media.data_dir = Some(to.clone()))?;
+    crate::load_production_env(&updated);
+    crate::crash::set_crash_dir(to.join(crate::crash::CRASH_DIR));
+    crate::logs::set_log_dir(Some(to.join("logs")));
+
+    emit_progress(app, MigrationPhase::StartingBackend, 0, 0, None);
+    crate::start_backend(app)?;
+    if !crate::wait_for_backend_health(app, BACKEND_START_TIMEOUT).await {
+        return Err("The backend did not come back up after moving the data directory".to_string());
+    }
+
+    info!("Data directory moved to {}", to.display());
+    emit_progress(app, MigrationPhase::Done, 0, 0, None);
+    Ok(())
+}
+
+pub fn init_data_dir(app: &AppHandle) {
+    app.manage(DataDirMigration::default());
+}
+
+/// Move the database, uploads and static files to `path` and restart the
+/// backend there. Progress is reported as `data-dir-progress` events.
+#[tauri::command]
+pub async fn set_data_dir(
+    app: AppHandle,
+    migration: State<'_, DataDirMigration>,
+    path: String,
+) -> Result<String, String> {
+    crate::demo_mode::refuse("Moving the data directory")?;
+    // The saved location would be shadowed at the next launch, or for the
+    // rest of this one
+    match app.state::<SettingsState>().override_source("media.dataDir") {
+        Some(ConfigSource::ConfigFile) => {
+            return Err(format!("The data directory is set in {}; change it there", install_config::CONFIG_FILE));
+        }
+        Some(ConfigSource::LaunchArgs) => {
+            return Err("The data directory is set by a launch flag; change it there".to_string());
+        }
+        Some(ConfigSource::PerUser) => {
+            return Err(
+                "Another user holds the configured data directory, so this session uses a per-user one until they quit"
+                    .to_string(),
+            );
+        }
+        _ => {}
+    }
+    let from = current_data_dir(&app);
+    let to = PathBuf::from(&path);
+    {
+        let (from, to) = (from.clone(), to.clone());
+        async_runtime::spawn_blocking(move || validate_target(&from, &to))
+            .await
+            .map_err(|e| format!("Validation panicked: {}", e))??;
+    }
+
+    if migration.running.swap(true, Ordering::SeqCst) {
+        return Err("A data directory move is already in progress".to_string());
+    }
+    info!("Moving data directory from {} to {}", from.display(), to.display());
+    let result = migrate(&app, from, to.clone()).await;
+    migration.running.store(false, Ordering::SeqCst);
+
+    result.map(|()| to.to_string_lossy().to_string())
+}
+
+/// The directory holding the database, uploads and static files
+#[tauri::command]
+pub async fn get_data_dir(app: AppHandle) -> Result<String, String> {
+    Ok(current_data_dir(&app).to_string_lossy().to_string())
+}
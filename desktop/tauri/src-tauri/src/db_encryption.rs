@@ -0,0 +1,226 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+
+use crate::{data_dir, BackendProcess, DesktopConfig};
+
+/// Keychain item holding the SQLCipher key (macOS Keychain, Windows Credential
+/// Manager, Secret Service on Linux)
+const KEYCHAIN_SERVICE: &str = "com.trackthething.desktop";
+const KEYCHAIN_ACCOUNT: &str = "database-key";
+
+/// Environment variable the key is handed to the backend in
+const KEY_ENV: &str = "TAURI_DATABASE_KEY";
+
+/// Makes the launcher encrypt the plaintext database and exit
+const ENCRYPT_ARG: &str = "--encrypt-database";
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Set while `encrypt_database` runs
+static ENCRYPTING: AtomicBool = AtomicBool::new(false);
+
+/// Set while the launcher runs in encryption mode, the only time the key is
+/// passed for a plaintext database
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+enum EncryptionPhase {
+    StoppingBackend,
+    Encrypting,
+    StartingBackend,
+    Done,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseEncryptionStatus {
+    /// The database file is SQLCipher-encrypted
+    pub encrypted: bool,
+    pub key_in_keychain: bool,
+    pub database_path: String,
+}
+
+fn emit_progress(app: &AppHandle, phase: EncryptionPhase) {
+    if let Err(e) = app.emit("database-encryption-progress", phase) {
+        warn!("Failed to emit database-encryption-progress event: {}", e);
+    }
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+fn load_key() -> Result<Option<String>, String> {
+    match keychain_entry()?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read the database key from the keychain: {}", e)),
+    }
+}
+
+/// Whether the keychain holds a database key
+pub(crate) fn has_key() -> Result<bool, String> {
+    load_key().map(|key| key.is_some())
+}
+
+/// 32 random bytes, hex encoded. The backend uses it as a raw SQLCipher key.
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn database_path(app: &AppHandle) -> PathBuf {
+    env::var_os("TAURI_DATABASE_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data_dir::current_data_dir(app).join("ttt_desktop.db"))
+}
+
+/// `None` when there's no database yet
+pub(crate) fn is_plaintext(path: &Path) -> io::Result<Option<bool>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(Some(&header == SQLITE_HEADER)),
+        // Empty or truncated: SQLite treats it as a new database
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(Some(true)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the database file is SQLCipher-encrypted
+pub(crate) fn is_encrypted(app: &AppHandle) -> bool {
+    matches!(is_plaintext(&database_path(app)), Ok(Some(false)))
+}
+
+/// Environment for a backend spawn: the key, if there is one and the database
+/// is (or, while encrypting, is about to be) encrypted. A key left over from an
+/// interrupted encryption isn't passed for a database that is still plaintext.
+pub(crate) fn backend_env(app: &AppHandle) -> Vec<(&'static str, String)> {
+    // The demo database is never encrypted, and mustn't see the real key
+    if crate::demo_mode::is_active() {
+        return Vec::new();
+    }
+    let key = match load_key() {
+        Ok(Some(key)) => key,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("{}", e);
+            return Vec::new();
+        }
+    };
+    let path = database_path(app);
+    match is_plaintext(&path) {
+        Ok(Some(true)) if !EXPORTING.load(Ordering::SeqCst) => {
+            warn!("A database key is stored but {} is not encrypted; starting without it", path.display());
+            Vec::new()
+        }
+        _ => vec![(KEY_ENV, key)],
+    }
+}
+
+/// Run the backend launcher in encryption mode and wait for it
+fn run_encryption(app: &AppHandle) -> Result<(), String> {
+    let config = app.state::<DesktopConfig>();
+    EXPORTING.store(true, Ordering::SeqCst);
+    let spawned = crate::spawn_backend(app, &config, &[ENCRYPT_ARG]);
+    EXPORTING.store(false, Ordering::SeqCst);
+    let mut child = spawned.map_err(|e| format!("Failed to start encryption: {}", e))?;
+    let status = child.wait().map_err(|e| format!("Failed to wait for encryption: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Database encryption failed ({}); see backend.log", status))
+    }
+}
+
+async fn encrypt(app: &AppHandle) -> Result<(), String> {
+    let path = database_path(app);
+    match is_plaintext(&path) {
+        Ok(Some(true)) => {}
+        Ok(Some(false)) => return Err("The database is already encrypted".to_string()),
+        Ok(None) => return Err(format!("No database at {}", path.display())),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+
+    // Saved before anything is touched, so the data is never encrypted with a
+    // key the keychain doesn't have. A key from an earlier attempt is reused.
+    if load_key()?.is_none() {
+        keychain_entry()?
+            .set_password(&generate_key())
+            .map_err(|e| format!("Failed to save the database key to the keychain: {}", e))?;
+    }
+
+    emit_progress(app, EncryptionPhase::StoppingBackend);
+    let _restart = crate::backend_client::begin_restart();
+    app.state::<BackendProcess>().terminate();
+
+    emit_progress(app, EncryptionPhase::Encrypting);
+    let encrypted = {
+        let app = app.clone();
+        async_runtime::spawn_blocking(move || run_encryption(&app))
+            .await
+            .map_err(|e| format!("Encryption task panicked: {}", e))
+            .and_then(|result| result)
+    };
+
+    // Restart either way: a failed run leaves the plaintext database untouched
+    emit_progress(app, EncryptionPhase::StartingBackend);
+    crate::start_backend(app)?;
+    encrypted?;
+    if !crate::wait_for_backend_health(app, BACKEND_START_TIMEOUT).await {
+        return Err("The backend did not come back up after encrypting the database".to_string());
+    }
+
+    info!("Database encrypted at {}", path.display());
+    emit_progress(app, EncryptionPhase::Done);
+    Ok(())
+}
+
+/// Whether the database is encrypted and its key is in the keychain
+#[tauri::command]
+pub async fn get_database_encryption(app: AppHandle) -> Result<DatabaseEncryptionStatus, String> {
+    let path = database_path(&app);
+    let (encrypted, key_in_keychain) = {
+        let path = path.clone();
+        async_runtime::spawn_blocking(move || (is_plaintext(&path), load_key()))
+            .await
+            .map_err(|e| format!("Encryption check panicked: {}", e))?
+    };
+    Ok(DatabaseEncryptionStatus {
+        encrypted: encrypted.map_err(|e| format!("Failed to read {}: {}", path.display(), e))? == Some(false),
+        key_in_keychain: key_in_keychain?.is_some(),
+        database_path: path.to_string_lossy().to_string(),
+    })
+}
+
+/// Encrypt the existing plaintext database with a new key kept in the OS
+/// keychain. The backend is stopped meanwhile; progress is reported as
+/// `database-encryption-progress` events.
+#[tauri::command]
+pub async fn encrypt_database(app: AppHandle) -> Result<(), String> {
+    crate::demo_mode::refuse("Encrypting the database")?;
+    if ENCRYPTING.swap(true, Ordering::SeqCst) {
+        return Err("The database is already being encrypted".to_string());
+    }
+    info!("Encrypting the database");
+    let result = encrypt(&app).await;
+    ENCRYPTING.store(false, Ordering::SeqCst);
+    result
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::focus_main_window;
+
+pub const SCHEME: &str = "trackthething";
+
+/// A `trackthething://<route>/<params...>?<query>` link split into parts,
+/// e.g. `trackthething://entry/123` -> route `entry`, params `["123"]`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkRoute {
+    pub url: String,
+    pub route: String,
+    pub params: Vec<String>,
+    pub query: HashMap<String, String>,
+}
+
+/// Links that arrived before the webview was listening (e.g. the link that launched the app)
+#[derive(Default)]
+pub struct PendingDeepLinks(Mutex<Vec<DeepLinkRoute>>);
+
+pub fn parse_deep_link(url: &Url) -> Option<DeepLinkRoute> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+
+    let route = url.host_str().filter(|host| !host.is_empty())?.to_ascii_lowercase();
+    let params = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let query = url.query_pairs().into_owned().collect();
+
+    Some(DeepLinkRoute {
+        url: url.to_string(),
+        route,
+        params,
+        query,
+    })
+}
+
+/// Route links to the webview as `deep-link` events. Jump list entries come
+/// through here too and are re-emitted as `quick-action` events; action links
+/// go to `automation`, and `mailto:` links (macOS) become entries.
+pub fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        if url.scheme() == crate::email_ingest::MAILTO_SCHEME {
+            crate::email_ingest::handle_opened(app, vec![crate::email_ingest::EmailSource::Mailto(url)]);
+            continue;
+        }
+        match parse_deep_link(&url) {
+            Some(route) if route.route == crate::quick_actions::ROUTE => match route.params.first() {
+                Some(id) => crate::quick_actions::dispatch(app, id),
+                None => warn!("Ignoring quick action link without an id: {}", url),
+            },
+            Some(route) => match crate::automation::action_name(&route) {
+                Some(action) => crate::automation::handle(app, action, route),
+                None => {
+                    info!("Routing deep link {} -> {}", route.url, route.route);
+                    focus_main_window(app);
+                    if let Err(e) = app.emit("deep-link", route) {
+                        warn!("Failed to emit deep-link event: {}", e);
+                    }
+                }
+            },
+            None => warn!("Ignoring unsupported deep link: {}", url),
+        }
+    }
+}
+
+/// Register the URL scheme handler and capture the link the app was launched with
+pub fn init_deep_links(app: &AppHandle) {
+    app.manage(PendingDeepLinks::default());
+
+    // Installed bundles register the scheme at install time; dev builds and
+    // unregistered AppImages need it registered at runtime
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    if let Err(e) = app.deep_link().register_all() {
+        warn!("Failed to register deep link schemes: {}", e);
+    }
+
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            let (mailto, urls): (Vec<Url>, Vec<Url>) = urls
+                .into_iter()
+                .partition(|url| url.scheme() == crate::email_ingest::MAILTO_SCHEME);
+            handle_urls(app, mailto);
+            let (actions, routes): (Vec<DeepLinkRoute>, Vec<DeepLinkRoute>) = urls
+                .iter()
+                .filter_map(parse_deep_link)
+                .partition(|route| crate::automation::action_name(route).is_some());
+            info!("App launched with {} deep link(s)", routes.len() + actions.len());
+            // Actions don't wait for the webview
+            for route in actions {
+                if let Some(action) = crate::automation::action_name(&route) {
+                    crate::automation::handle(app, action, route);
+                }
+            }
+            if let Ok(mut pending) = app.state::<PendingDeepLinks>().0.lock() {
+                pending.extend(routes);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read launch deep links: {}", e),
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        handle_urls(&handle, event.urls());
+    });
+}
+
+/// Return (and clear) deep links received before the webview subscribed to `deep-link` events
+#[tauri::command]
+pub async fn take_pending_deep_links(
+    pending: State<'_, PendingDeepLinks>,
+) -> Result<Vec<DeepLinkRoute>, String> {
+    let mut pending = pending.0.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(std::mem::take(&mut *pending))
+}
@@ -0,0 +1,202 @@
+//! Read-only demo mode: the backend runs against a throwaway copy of a demo
+//! database in the cache dir instead of the user's, and `backend_request`
+//! refuses anything but reads. Started with `--demo` or `start_demo_mode`;
+//! the copy is deleted when the mode ends or the app quits.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{BackendMode, BackendProcess, DesktopConfig};
+
+/// Command-line flag that launches straight into demo mode
+pub const DEMO_FLAG: &str = "--demo";
+
+/// Optional demo database, bundled with `bundle.resources`
+const DEMO_DATABASE_RESOURCE: &str = "demo/ttt_demo.db";
+
+/// Directory of the throwaway copy, under the app cache dir
+const DEMO_DIR: &str = "demo";
+
+/// Backend environment pointed at the throwaway copy (see `load_production_env`)
+const DEMO_ENV: &[&str] = &["TAURI_DATABASE_PATH", "TAURI_UPLOADS_DIR", "TAURI_STATIC_DIR", "TAURI_BACKEND_LOG"];
+
+/// Methods the webview may still send while read-only
+const READ_METHODS: &[&str] = &["GET", "HEAD", "OPTIONS"];
+
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// `DEMO_ENV` as it was before the demo, restored when it ends
+static SAVED_ENV: Mutex<Vec<(&'static str, Option<OsString>)>> = Mutex::new(Vec::new());
+
+/// Payload of `demo-mode-changed` and result of `get_demo_mode`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DemoStatus {
+    pub active: bool,
+    /// The throwaway database while active
+    pub database_path: Option<String>,
+}
+
+pub fn requested() -> bool {
+    env::args().any(|arg| arg == DEMO_FLAG)
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Whether the webview may send `method` to the backend
+pub fn allows(method: &str) -> bool {
+    !is_active() || READ_METHODS.iter().any(|read| read.eq_ignore_ascii_case(method))
+}
+
+/// Refuse `what` while the demo runs, for actions on the user's real data
+pub fn refuse(what: &str) -> Result<(), String> {
+    if is_active() {
+        return Err(format!("{} is not available in demo mode", what));
+    }
+    Ok(())
+}
+
+fn demo_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(DEMO_DIR))
+        .map_err(|e| format!("Failed to resolve app cache directory: {}", e))
+}
+
+fn status() -> DemoStatus {
+    let active = is_active();
+    DemoStatus {
+        active,
+        database_path: env::var("TAURI_DATABASE_PATH").ok().filter(|_| active),
+    }
+}
+
+/// Create a fresh throwaway copy of the demo database and point the backend
+/// environment at it. Call with the backend stopped; the next spawn uses it.
+/// Without a bundled demo database the backend starts on an empty one.
+pub fn enter(app: &AppHandle) -> Result<(), String> {
+    if is_active() {
+        return Err("Demo mode is already on".to_string());
+    }
+    let dir = demo_dir(app)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear {}: {}", dir.display(), e))?;
+    }
+    fs::create_dir_all(dir.join("logs")).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let database = dir.join("ttt_desktop.db");
+    let template = app
+        .path()
+        .resolve(DEMO_DATABASE_RESOURCE, BaseDirectory::Resource)
+        .ok()
+        .filter(|path| path.is_file());
+    match template {
+        Some(template) => {
+            fs::copy(&template, &database).map_err(|e| format!("Failed to copy the demo database: {}", e))?;
+            info!("Demo database copied from {}", template.display());
+        }
+        None => info!("No demo database bundled; the demo starts empty"),
+    }
+
+    let mut saved = SAVED_ENV.lock().map_err(|e| format!("Lock error: {}", e))?;
+    *saved = DEMO_ENV.iter().map(|key| (*key, env::var_os(key))).collect();
+    env::set_var("TAURI_DATABASE_PATH", &database);
+    env::set_var("TAURI_UPLOADS_DIR", dir.join("uploads"));
+    env::set_var("TAURI_STATIC_DIR", dir.join("static"));
+    env::set_var("TAURI_BACKEND_LOG", dir.join("logs").join("backend.log"));
+    ACTIVE.store(true, Ordering::SeqCst);
+    info!("Demo mode on, using {}", dir.display());
+    Ok(())
+}
+
+/// Point the backend environment back at the user's data and delete the
+/// throwaway copy. Call with the backend stopped.
+fn leave(app: &AppHandle) -> Result<(), String> {
+    let mut saved = SAVED_ENV.lock().map_err(|e| format!("Lock error: {}", e))?;
+    for (key, value) in saved.drain(..) {
+        match value {
+            Some(value) => env::set_var(key, value),
+            None => env::remove_var(key),
+        }
+    }
+    ACTIVE.store(false, Ordering::SeqCst);
+    let dir = demo_dir(app)?;
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        warn!("Failed to delete the demo data at {}: {}", dir.display(), e);
+    }
+    info!("Demo mode off");
+    Ok(())
+}
+
+/// Delete the throwaway copy when the app quits in demo mode
+pub fn discard(app: &AppHandle) {
+    if !is_active() {
+        return;
+    }
+    // The backend must let go of the database first
+    app.state::<BackendProcess>().terminate();
+    if let Ok(dir) = demo_dir(app) {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            warn!("Failed to delete the demo data at {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Restart the backend on the environment `switch` sets up
+async fn restart_backend(app: &AppHandle, switch: fn(&AppHandle) -> Result<(), String>) -> Result<DemoStatus, String> {
+    if app.state::<DesktopConfig>().backend_mode != BackendMode::Local {
+        return Err("Demo mode needs the backend run by this app".to_string());
+    }
+    let _restart = crate::backend_client::begin_restart();
+    app.state::<BackendProcess>().terminate();
+    let switched = switch(app);
+    crate::start_backend(app)?;
+    switched?;
+    if !crate::wait_for_backend_health(app, BACKEND_START_TIMEOUT).await {
+        return Err("The backend did not come back up".to_string());
+    }
+
+    let status = status();
+    if let Err(e) = app.emit("demo-mode-changed", &status) {
+        warn!("Failed to emit demo-mode-changed: {}", e);
+    }
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn get_demo_mode() -> Result<DemoStatus, String> {
+    Ok(status())
+}
+
+/// Restart the backend on a fresh copy of the demo database and make the
+/// session read-only
+#[tauri::command]
+pub async fn start_demo_mode(app: AppHandle) -> Result<DemoStatus, String> {
+    if is_active() {
+        return Err("Demo mode is already on".to_string());
+    }
+    restart_backend(&app, enter).await
+}
+
+/// Restart the backend on the user's data and delete the demo copy
+#[tauri::command]
+pub async fn stop_demo_mode(app: AppHandle) -> Result<DemoStatus, String> {
+    if !is_active() {
+        return Err("Demo mode is not on".to_string());
+    }
+    restart_backend(&app, leave).await
+}
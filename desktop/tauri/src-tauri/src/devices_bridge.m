@@ -0,0 +1,56 @@
+#import <Foundation/Foundation.h>
+#import <AVFoundation/AVFoundation.h>
+
+typedef void (*DevicesChangedCallback)(void);
+static DevicesChangedCallback changedCallback = NULL;
+
+static id connectedObserver = nil;
+static id disconnectedObserver = nil;
+
+// Call callback whenever a camera or microphone is plugged in or removed
+void devices_watch(DevicesChangedCallback callback) {
+    changedCallback = callback;
+    dispatch_async(dispatch_get_main_queue(), ^{
+        if (connectedObserver != nil) {
+            return;
+        }
+        NSNotificationCenter *center = [NSNotificationCenter defaultCenter];
+        void (^changed)(NSNotification *) = ^(NSNotification *note) {
+            AVCaptureDevice *device = note.object;
+            if (![device hasMediaType:AVMediaTypeVideo] && ![device hasMediaType:AVMediaTypeAudio]) {
+                return;
+            }
+            if (changedCallback != NULL) {
+                changedCallback();
+            }
+        };
+        connectedObserver = [center addObserverForName:AVCaptureDeviceWasConnectedNotification
+                                                object:nil
+                                                 queue:[NSOperationQueue mainQueue]
+                                            usingBlock:changed];
+        disconnectedObserver = [center addObserverForName:AVCaptureDeviceWasDisconnectedNotification
+                                                   object:nil
+                                                    queue:[NSOperationQueue mainQueue]
+                                               usingBlock:changed];
+    });
+}
+
+// Names of the audio input devices, one per line. Release with devices_free_string.
+char *devices_copy_microphones(void) {
+    @autoreleasepool {
+#pragma clang diagnostic push
+#pragma clang diagnostic ignored "-Wdeprecated-declarations"
+        // The discovery session's microphone types differ between macOS releases
+        NSArray<AVCaptureDevice *> *devices = [AVCaptureDevice devicesWithMediaType:AVMediaTypeAudio];
+#pragma clang diagnostic pop
+        NSMutableArray<NSString *> *names = [NSMutableArray arrayWithCapacity:devices.count];
+        for (AVCaptureDevice *device in devices) {
+            [names addObject:device.localizedName];
+        }
+        return strdup([names componentsJoinedByString:@"\n"].UTF8String);
+    }
+}
+
+void devices_free_string(char *value) {
+    free(value);
+}
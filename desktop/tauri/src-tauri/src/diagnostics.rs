@@ -0,0 +1,365 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::crash::{self, CrashKind};
+use crate::logs;
+use crate::settings::{self, SettingsState};
+use crate::telemetry;
+
+/// Only the end of each log goes in; that's where the failure is
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+
+const MAX_CRASH_REPORTS: usize = 5;
+
+/// Preference files from the config dir that help reproduce a problem. The app
+/// lock file holds a passcode hash and is never included.
+const CONFIG_FILES: &[&str] = &["settings.json", "window_prefs.json", "autostart_prefs.json"];
+
+/// JSON keys whose values are replaced, matched case-insensitively as substrings
+pub(crate) const SECRET_KEYS: &[&str] = &["password", "passcode", "secret", "token", "apikey", "api_key", "credential", "auth"];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsExport {
+    /// The zip that was written
+    pub path: String,
+    /// Entries in the zip
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// Replaces the home directory (and with it the user name) in everything that
+/// goes into the bundle
+struct Redactor {
+    home: Option<String>,
+}
+
+impl Redactor {
+    fn new() -> Self {
+        Self {
+            home: dirs::home_dir()
+                .map(|home| home.to_string_lossy().to_string())
+                .filter(|home| home.len() > 1),
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        match &self.home {
+            Some(home) => text.replace(home.as_str(), "~"),
+            None => text.to_string(),
+        }
+    }
+
+    fn json(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let lower = key.to_ascii_lowercase();
+                        if SECRET_KEYS.iter().any(|secret| lower.contains(secret)) && !value.is_null() {
+                            (key, Value::String("[redacted]".into()))
+                        } else {
+                            (key, self.json(value))
+                        }
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.into_iter().map(|item| self.json(item)).collect()),
+            Value::String(text) => Value::String(self.text(&text)),
+            other => other,
+        }
+    }
+}
+
+struct Bundle {
+    zip: ZipWriter<File>,
+    files: Vec<String>,
+    redactor: Redactor,
+}
+
+impl Bundle {
+    fn add(&mut self, name: &str, contents: &[u8]) -> Result<(), String> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip
+            .start_file(name, options)
+            .and_then(|()| self.zip.write_all(contents).map_err(Into::into))
+            .map_err(|e| format!("Failed to add {} to the diagnostics bundle: {}", name, e))?;
+        self.files.push(name.to_string());
+        Ok(())
+    }
+
+    fn add_json(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let value = self.redactor.json(value);
+        let json = serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+        self.add(name, json.as_bytes())
+    }
+
+    /// The last `MAX_LOG_BYTES` of a text file
+    fn add_tail(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        let mut contents = Vec::new();
+        let read = File::open(path).and_then(|mut file| {
+            let len = file.metadata()?.len();
+            file.seek(SeekFrom::Start(len.saturating_sub(MAX_LOG_BYTES)))?;
+            file.read_to_end(&mut contents)
+        });
+        if let Err(e) = read {
+            warn!("Skipping {} in diagnostics: {}", path.display(), e);
+            return Ok(());
+        }
+        let text = self.redactor.text(&String::from_utf8_lossy(&contents));
+        self.add(name, text.as_bytes())
+    }
+
+    /// The most recent panic reports (as text) and minidumps (as is)
+    fn add_crash_reports(&mut self, dir: &Path) -> Result<(), String> {
+        for report in crash::list_reports(dir, None).into_iter().take(MAX_CRASH_REPORTS) {
+            let name = format!("{}/{}", crash::CRASH_DIR, report.name);
+            let path = dir.join(&report.name);
+            match report.kind {
+                CrashKind::Panic => self.add_tail(&name, &path)?,
+                CrashKind::Minidump => match fs::read(&path) {
+                    Ok(dump) => self.add(&name, &dump)?,
+                    Err(e) => warn!("Skipping {} in diagnostics: {}", path.display(), e),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    fn sysctl(name: &str) -> Option<String> {
+        let output = Command::new("sysctl").args(["-n", name]).output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    pub fn os_version() -> Option<String> {
+        let output = Command::new("sw_vers").arg("-productVersion").output().ok()?;
+        Some(format!("macOS {}", String::from_utf8_lossy(&output.stdout).trim()))
+    }
+
+    pub fn hardware() -> Vec<(&'static str, String)> {
+        [
+            ("model", sysctl("hw.model")),
+            ("cpu", sysctl("machdep.cpu.brand_string")),
+            ("memoryBytes", sysctl("hw.memsize")),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    /// `key` from a `key: value` line in a /proc file
+    fn proc_field(path: &str, key: &str) -> Option<String> {
+        fs::read_to_string(path).ok()?.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            (name.trim() == key).then(|| value.trim().to_string())
+        })
+    }
+
+    pub fn os_version() -> Option<String> {
+        let release = fs::read_to_string("/etc/os-release").ok()?;
+        let name = release
+            .lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"').to_string())?;
+        let kernel = fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+        Some(format!("{} (kernel {})", name, kernel.trim()))
+    }
+
+    pub fn hardware() -> Vec<(&'static str, String)> {
+        [
+            ("cpu", proc_field("/proc/cpuinfo", "model name")),
+            ("memory", proc_field("/proc/meminfo", "MemTotal")),
+            ("session", std::env::var("XDG_SESSION_TYPE").ok()),
+            ("desktop", std::env::var("XDG_CURRENT_DESKTOP").ok()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::process::Command;
+
+    pub fn os_version() -> Option<String> {
+        let output = Command::new("cmd").args(["/C", "ver"]).output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn hardware() -> Vec<(&'static str, String)> {
+        [
+            ("cpu", std::env::var("PROCESSOR_IDENTIFIER").ok()),
+            ("cpuArch", std::env::var("PROCESSOR_ARCHITECTURE").ok()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+fn system_info(app: &AppHandle) -> Value {
+    let monitors: Vec<Value> = app
+        .get_webview_window("main")
+        .and_then(|window| window.available_monitors().ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|monitor| {
+            json!({
+                "name": monitor.name(),
+                "width": monitor.size().width,
+                "height": monitor.size().height,
+                "scaleFactor": monitor.scale_factor(),
+            })
+        })
+        .collect();
+    let hardware: serde_json::Map<String, Value> = platform::hardware()
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value)))
+        .collect();
+
+    json!({
+        "appVersion": app.package_info().version.to_string(),
+        "tauriVersion": tauri::VERSION,
+        "build": if cfg!(debug_assertions) { "debug" } else { "release" },
+        "os": env::consts::OS,
+        "osVersion": platform::os_version(),
+        "arch": env::consts::ARCH,
+        "cpus": std::thread::available_parallelism().map(|count| count.get()).ok(),
+        "hardware": hardware,
+        "monitors": monitors,
+        "systemLocale": crate::locale::system_locale(),
+        "exportedAt": Local::now().to_rfc3339(),
+    })
+}
+
+fn write_bundle(app: &AppHandle, path: &Path, include_crash_reports: bool) -> Result<Vec<String>, String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut bundle = Bundle {
+        zip: ZipWriter::new(file),
+        files: Vec::new(),
+        redactor: Redactor::new(),
+    };
+
+    bundle.add_json("system.json", system_info(app))?;
+
+    let effective = settings::effective_config(&app.state::<SettingsState>());
+    let effective = serde_json::to_value(effective).map_err(|e| format!("Failed to serialize config: {}", e))?;
+    bundle.add_json("effective_config.json", effective)?;
+
+    if let Ok(config_dir) = app.path().app_config_dir() {
+        for name in CONFIG_FILES {
+            let Ok(content) = fs::read_to_string(config_dir.join(name)) else {
+                continue;
+            };
+            match serde_json::from_str::<Value>(&content) {
+                Ok(value) => bundle.add_json(&format!("config/{}", name), value)?,
+                Err(e) => {
+                    warn!("{} is not valid JSON ({}), adding it as text", name, e);
+                    let text = bundle.redactor.text(&content);
+                    bundle.add(&format!("config/{}", name), text.as_bytes())?;
+                }
+            }
+        }
+    }
+    if let Ok(backend_dir) = app.path().app_local_data_dir().map(|dir| dir.join("backend")) {
+        if let Some(value) = fs::read_to_string(backend_dir.join("versions.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+        {
+            bundle.add_json("config/backend_versions.json", value)?;
+        }
+    }
+
+    for file in logs::list_log_files() {
+        bundle.add_tail(&format!("logs/{}", file.name), Path::new(&file.path))?;
+    }
+    if include_crash_reports {
+        if let Some(dir) = crash::crash_dir() {
+            bundle.add_crash_reports(&dir)?;
+        }
+    }
+    if let Some(screenshot) = crate::app_screenshot::recent(app) {
+        match fs::read(&screenshot) {
+            Ok(png) => bundle.add("screenshot.png", &png)?,
+            Err(e) => warn!("Failed to read {}: {}", screenshot.display(), e),
+        }
+    }
+
+    let files = bundle.files;
+    bundle
+        .zip
+        .finish()
+        .map_err(|e| format!("Failed to finish {}: {}", path.display(), e))?;
+    Ok(files)
+}
+
+/// Zip logs, the effective config, preference files, recent crash reports
+/// (unless `include_crash_reports` is false), the last `capture_app_screenshot`
+/// if it is under an hour old, and OS details for a bug report.
+/// Secrets are redacted and the home directory is shown as `~`. `path` may be
+/// a directory, in which case a timestamped file name is used.
+#[tauri::command]
+pub async fn export_diagnostics(
+    app: AppHandle,
+    path: String,
+    include_crash_reports: Option<bool>,
+) -> Result<DiagnosticsExport, String> {
+    let mut target = PathBuf::from(&path);
+    if !target.is_absolute() {
+        return Err("Diagnostics path must be absolute".to_string());
+    }
+    if target.is_dir() {
+        target = target.join(format!(
+            "track-the-thing-diagnostics-{}.zip",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+    }
+
+    let partial = target.with_extension("zip.partial");
+    let files = {
+        let (app, partial) = (app.clone(), partial.clone());
+        let include_crash_reports = include_crash_reports.unwrap_or(true);
+        async_runtime::spawn_blocking(move || write_bundle(&app, &partial, include_crash_reports))
+            .await
+            .map_err(|e| format!("Diagnostics export panicked: {}", e))
+            .and_then(|result| result)
+    };
+    let files = match files {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    fs::rename(&partial, &target).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+
+    let bytes = fs::metadata(&target).map(|meta| meta.len()).unwrap_or(0);
+    info!("Exported diagnostics ({} files, {} bytes) to {}", files.len(), bytes, target.display());
+    telemetry::record(&app, "diagnostics_export", None);
+    Ok(DiagnosticsExport {
+        path: target.to_string_lossy().to_string(),
+        files,
+        bytes,
+    })
+}
@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DndStatus {
+    /// `None` when the platform doesn't expose Focus / Do Not Disturb state
+    pub active: Option<bool>,
+    /// Notifications shown only as a badge because DND was on
+    pub suppressed_count: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SuppressedNotificationEvent {
+    id: String,
+    title: String,
+}
+
+/// Count of notifications downgraded to a badge while DND was on
+#[derive(Default)]
+pub struct DndState {
+    suppressed: AtomicI64,
+}
+
+#[cfg(target_os = "macos")]
+pub fn dnd_active() -> Option<bool> {
+    // There is no public Focus API without the communication-notifications
+    // entitlement; the Focus store records an assertion while a Focus is on
+    let path = dirs::home_dir()?.join("Library/DoNotDisturb/DB/Assertions.json");
+    let content = std::fs::read_to_string(path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let records = json
+        .get("data")?
+        .as_array()?
+        .iter()
+        .filter_map(|store| store.get("storeAssertionRecords")?.as_array())
+        .map(|records| records.len())
+        .sum::<usize>();
+    Some(records > 0)
+}
+
+#[cfg(target_os = "windows")]
+pub fn dnd_active() -> Option<bool> {
+    // QUERY_USER_NOTIFICATION_STATE::QUNS_ACCEPTS_NOTIFICATIONS
+    const ACCEPTS_NOTIFICATIONS: i32 = 5;
+
+    #[link(name = "shell32")]
+    extern "system" {
+        fn SHQueryUserNotificationState(state: *mut i32) -> i32;
+    }
+
+    let mut state = 0;
+    // Busy, full-screen, presentation mode and Focus assist all count as DND
+    (unsafe { SHQueryUserNotificationState(&mut state) } == 0).then_some(state != ACCEPTS_NOTIFICATIONS)
+}
+
+#[cfg(target_os = "linux")]
+pub fn dnd_active() -> Option<bool> {
+    // GNOME's "Do Not Disturb" toggle turns off notification banners
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "false" => Some(true),
+        "true" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn dnd_active() -> Option<bool> {
+    None
+}
+
+/// Shortcuts the user creates with a "Set Focus" action, run to turn
+/// Do Not Disturb on and off (there is no public API for it)
+#[cfg(target_os = "macos")]
+pub const DND_SHORTCUTS: (&str, &str) = ("Track the Thing Focus On", "Track the Thing Focus Off");
+
+/// Turn Do Not Disturb on or off. Blocks while the system tool runs.
+#[cfg(target_os = "macos")]
+pub fn set_dnd(active: bool) -> Result<(), String> {
+    let name = if active { DND_SHORTCUTS.0 } else { DND_SHORTCUTS.1 };
+    let output = std::process::Command::new("shortcuts")
+        .args(["run", name])
+        .output()
+        .map_err(|e| format!("Failed to run shortcuts: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "The shortcut \"{}\" failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_dnd(active: bool) -> Result<(), String> {
+    let status = std::process::Command::new("gsettings")
+        .args(["set", "org.gnome.desktop.notifications", "show-banners", if active { "false" } else { "true" }])
+        .status()
+        .map_err(|e| format!("Failed to run gsettings: {}", e))?;
+    if !status.success() {
+        return Err("gsettings could not change the Do Not Disturb setting".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn set_dnd(_active: bool) -> Result<(), String> {
+    Err("Do Not Disturb can't be changed on this platform".to_string())
+}
+
+fn set_badge(app: &AppHandle, count: i64) {
+    if let Some(window) = app.get_webview_window("main") {
+        let badge = (count > 0).then_some(count);
+        if let Err(e) = window.set_badge_count(badge) {
+            warn!("Failed to update badge: {}", e);
+        }
+    }
+}
+
+/// Record a notification that wasn't shown because DND is on: bump the dock /
+/// taskbar badge and let the webview list it as `notification-suppressed`
+pub fn suppress_notification(app: &AppHandle, id: &str, title: &str) {
+    let state = app.state::<DndState>();
+    let count = state.suppressed.fetch_add(1, Ordering::SeqCst) + 1;
+    info!("Do Not Disturb is on, notification {} shown as badge ({})", id, count);
+    set_badge(app, count);
+
+    let event = SuppressedNotificationEvent {
+        id: id.to_string(),
+        title: title.to_string(),
+    };
+    if let Err(e) = app.emit("notification-suppressed", event) {
+        warn!("Failed to emit notification-suppressed event: {}", e);
+    }
+}
+
+/// Reset the badge once the user has seen the app
+pub fn clear_badge(app: &AppHandle) {
+    let Some(state) = app.try_state::<DndState>() else {
+        return;
+    };
+    if state.suppressed.swap(0, Ordering::SeqCst) > 0 {
+        set_badge(app, 0);
+    }
+}
+
+pub fn init_dnd(app: &AppHandle) {
+    app.manage(DndState::default());
+}
+
+#[tauri::command]
+pub async fn get_dnd_status(state: State<'_, DndState>) -> Result<DndStatus, String> {
+    let active = tokio::task::spawn_blocking(dnd_active)
+        .await
+        .map_err(|e| format!("DND check panicked: {}", e))?;
+    Ok(DndStatus {
+        active,
+        suppressed_count: state.suppressed.load(Ordering::SeqCst),
+    })
+}
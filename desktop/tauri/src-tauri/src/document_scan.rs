@@ -0,0 +1,248 @@
+//! Document scans from the camera: a photo is taken through the `camera`
+//! capture source, the page is found as the largest four-cornered outline in
+//! its edges, straightened with a perspective warp and evened out so the
+//! paper comes out white. Scans are saved under `scans/` as PNG or as a
+//! one-page PDF.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgb, RgbImage};
+use imageproc::contours::find_contours;
+use imageproc::distance_transform::Norm;
+use imageproc::edges::canny;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::geometric_transformations::{warp_into, Interpolation, Projection};
+use imageproc::geometry::{approximate_polygon_dp, arc_length};
+use imageproc::morphology::dilate;
+use imageproc::point::Point;
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle};
+use tracing::{info, instrument};
+
+use crate::capture::{self, CaptureOptions};
+use crate::media_manifest;
+use crate::media_store::get_media_dir;
+use crate::pdf::{self, PdfPage};
+
+/// Where scans are written, under the app data dir
+const SCANS_DIR: &str = "scans";
+
+/// Longest side the page is looked for at; edges don't need full resolution
+const DETECT_SIZE: u32 = 640;
+
+/// Smallest share of the photo an outline must cover to be taken for the page
+const MIN_PAGE_AREA: f64 = 0.2;
+
+/// Share of the darkest and brightest pixels clipped when evening out
+const INK_PERCENTILE: f64 = 0.02;
+const PAPER_PERCENTILE: f64 = 0.90;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanFormat {
+    #[default]
+    Png,
+    Pdf,
+}
+
+/// Result of `scan_document`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedDocument {
+    pub path: String,
+    /// The photo the scan was made from, kept in `photos/`
+    pub photo: String,
+    /// Whether the page's outline was found; otherwise the whole photo was
+    /// evened out as it is
+    pub page_found: bool,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Area of a polygon, from the shoelace formula
+fn area(corners: &[(f64, f64)]) -> f64 {
+    let sum: f64 = corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum();
+    sum.abs() / 2.0
+}
+
+fn is_convex(corners: &[(f64, f64)]) -> bool {
+    let turns: Vec<f64> = (0..corners.len())
+        .map(|i| {
+            let (a, b, c) = (corners[i], corners[(i + 1) % corners.len()], corners[(i + 2) % corners.len()]);
+            (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+        })
+        .collect();
+    turns.iter().all(|turn| *turn > 0.0) || turns.iter().all(|turn| *turn < 0.0)
+}
+
+/// Top-left, top-right, bottom-right and bottom-left, in that order
+fn order_corners(corners: &[(f64, f64)]) -> [(f64, f64); 4] {
+    let by = |key: fn(&(f64, f64)) -> f64, largest: bool| {
+        let compare = |a: &&(f64, f64), b: &&(f64, f64)| key(a).total_cmp(&key(b));
+        let found = if largest {
+            corners.iter().max_by(compare)
+        } else {
+            corners.iter().min_by(compare)
+        };
+        found.copied().unwrap_or_default()
+    };
+    [
+        by(|p| p.0 + p.1, false),
+        by(|p| p.0 - p.1, true),
+        by(|p| p.0 + p.1, true),
+        by(|p| p.1 - p.0, true),
+    ]
+}
+
+/// Corners of the page in `photo`, if there's an outline large enough
+fn find_page(photo: &RgbImage) -> Option<[(f64, f64); 4]> {
+    let scale = DETECT_SIZE as f64 / photo.width().max(photo.height()) as f64;
+    let small = if scale < 1.0 {
+        let (width, height) = (photo.width() as f64 * scale, photo.height() as f64 * scale);
+        imageops::resize(photo, width as u32, height as u32, FilterType::Triangle)
+    } else {
+        photo.clone()
+    };
+    let scale = small.width() as f64 / photo.width() as f64;
+    let gray = gaussian_blur_f32(&imageops::grayscale(&small), 2.0);
+    // Thickened so a page edge with a gap still closes into one outline
+    let edges = dilate(&canny(&gray, 30.0, 90.0), Norm::LInf, 1);
+    let min_area = (small.width() * small.height()) as f64 * MIN_PAGE_AREA;
+
+    find_contours::<i32>(&edges)
+        .into_iter()
+        .filter(|contour| contour.points.len() >= 4)
+        .filter_map(|contour| {
+            let epsilon = arc_length(&contour.points, true) * 0.02;
+            let mut polygon: Vec<Point<i32>> = approximate_polygon_dp(&contour.points, epsilon, true);
+            if polygon.len() > 1 && polygon.first() == polygon.last() {
+                polygon.pop();
+            }
+            let corners: Vec<(f64, f64)> = polygon.iter().map(|point| (point.x as f64, point.y as f64)).collect();
+            (corners.len() == 4 && is_convex(&corners)).then_some(corners)
+        })
+        .map(|corners| (area(&corners), corners))
+        .filter(|(area, _)| *area >= min_area)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, corners)| order_corners(&corners).map(|(x, y)| (x / scale, y / scale)))
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// The page cut out of `photo` and warped into a rectangle
+fn straighten(photo: &RgbImage, [top_left, top_right, bottom_right, bottom_left]: [(f64, f64); 4]) -> Option<RgbImage> {
+    let width = distance(top_left, top_right).max(distance(bottom_left, bottom_right)).round() as u32;
+    let height = distance(top_left, bottom_left).max(distance(top_right, bottom_right)).round() as u32;
+    if width < 16 || height < 16 {
+        return None;
+    }
+    let to_f32 = |(x, y): (f64, f64)| (x as f32, y as f32);
+    let (right, bottom) = (width as f32 - 1.0, height as f32 - 1.0);
+    let projection = Projection::from_control_points(
+        [top_left, top_right, bottom_right, bottom_left].map(to_f32),
+        [(0.0, 0.0), (right, 0.0), (right, bottom), (0.0, bottom)],
+    )?;
+    let mut page = RgbImage::new(width, height);
+    warp_into(photo, &projection, Interpolation::Bilinear, Rgb([255, 255, 255]), &mut page);
+    Some(page)
+}
+
+/// Stretch the levels so the paper is white and the ink dark, whatever the
+/// lighting was
+fn even_out(page: &mut RgbImage) {
+    let mut histogram = [0u64; 256];
+    for pixel in page.pixels() {
+        let [r, g, b] = pixel.0;
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        histogram[luma as usize] += 1;
+    }
+    let total = (page.width() * page.height()) as f64;
+    let level = |share: f64| {
+        let mut seen = 0u64;
+        histogram
+            .iter()
+            .position(|count| {
+                seen += count;
+                seen as f64 >= total * share
+            })
+            .unwrap_or(255) as f64
+    };
+    let (ink, paper) = (level(INK_PERCENTILE), level(PAPER_PERCENTILE));
+    if paper - ink < 16.0 {
+        return;
+    }
+    for pixel in page.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = ((*channel as f64 - ink) * 255.0 / (paper - ink)).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Turn the photo at `photo` into a scan at `dest`
+fn scan(photo: &Path, dest: &Path, format: ScanFormat) -> Result<(bool, u32, u32), String> {
+    let photo = image::open(photo)
+        .map_err(|e| format!("Failed to read {}: {}", photo.display(), e))?
+        .into_rgb8();
+    let straightened = find_page(&photo).and_then(|corners| straighten(&photo, corners));
+    let page_found = straightened.is_some();
+    let mut page = straightened.unwrap_or(photo);
+    even_out(&mut page);
+    match format {
+        ScanFormat::Png => page
+            .save_with_format(dest, ImageFormat::Png)
+            .map_err(|e| format!("Failed to save {}: {}", dest.display(), e))?,
+        ScanFormat::Pdf => pdf::write(dest, &[PdfPage::new(&page)?])?,
+    }
+    Ok((page_found, page.width(), page.height()))
+}
+
+/// Take a photo of a document with camera `camera_id` (the first by
+/// default), straighten the page and even out its lighting, and save it
+/// under `scans/` as a PNG or, with `format: "pdf"`, a one-page PDF. When
+/// no page outline is found the whole photo is used and `pageFound` is
+/// false.
+#[tauri::command]
+#[instrument(skip(app), err)]
+pub async fn scan_document(
+    app: AppHandle,
+    camera_id: Option<u32>,
+    format: Option<ScanFormat>,
+) -> Result<ScannedDocument, String> {
+    let options = CaptureOptions {
+        camera: camera_id,
+        ..CaptureOptions::default()
+    };
+    let photo = capture::capture(&app, "camera", options)
+        .await?
+        .pop()
+        .ok_or("No photo was taken")?;
+
+    let format = format.unwrap_or_default();
+    let extension = match format {
+        ScanFormat::Png => "png",
+        ScanFormat::Pdf => "pdf",
+    };
+    let dest = get_media_dir(&app, SCANS_DIR)?.join(format!("scan_{}.{}", Utc::now().timestamp_millis(), extension));
+    let (source, written) = (PathBuf::from(&photo), dest.clone());
+    let (page_found, width, height) = async_runtime::spawn_blocking(move || scan(&source, &written, format))
+        .await
+        .map_err(|e| format!("Scan task failed: {}", e))??;
+    info!(path = ?dest, page_found, width, height, "document scanned");
+    media_manifest::track(&app, vec![dest.clone()]);
+    Ok(ScannedDocument {
+        path: dest.to_string_lossy().to_string(),
+        photo,
+        page_found,
+        width,
+        height,
+    })
+}
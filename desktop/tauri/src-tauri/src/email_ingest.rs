@@ -0,0 +1,266 @@
+//! Emails turned into entries: a `.eml` file opened with the app or passed to
+//! `ingest_eml`, or a `mailto:` link when the app is picked as the mail
+//! handler. The subject becomes the entry's title and the text body its
+//! content; attachments are saved to the media `email/` directory and
+//! announced with the entry as `email-ingested`, so the webview can attach
+//! them.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Local;
+use log::{info, warn};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, Url};
+
+use crate::media_store::get_media_dir;
+use crate::{backend_client, notifications, DesktopConfig};
+
+/// Extension registered in tauri.conf.json `bundle.fileAssociations`
+pub const EML_EXTENSION: &str = "eml";
+
+pub const MAILTO_SCHEME: &str = "mailto";
+
+/// Larger files are refused rather than read into memory
+const MAX_EML_BYTES: u64 = 50 * 1024 * 1024;
+
+/// How long an email opened at launch waits for the backend
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An email or `mailto:` link handed to the app by the OS
+#[derive(Debug, Clone)]
+pub enum EmailSource {
+    File(PathBuf),
+    Mailto(Url),
+}
+
+/// Result of `ingest_eml` and payload of `email-ingested`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestedEmail {
+    pub subject: String,
+    /// `Name <address>`, or just the address
+    pub from: Option<String>,
+    /// RFC 3339
+    pub date: Option<String>,
+    pub message_id: Option<String>,
+    pub body: String,
+    /// Saved copies of the attachments in the media `email/` directory
+    pub attachments: Vec<String>,
+    /// The entry created on today's note
+    pub entry_id: Option<i64>,
+}
+
+pub fn is_eml_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(EML_EXTENSION))
+}
+
+/// Pick `.eml` files and `mailto:` links out of a process argument list
+/// (Windows/Linux pass them as arguments). The first argument is the binary.
+pub fn sources_from_args<I: IntoIterator<Item = String>>(args: I) -> Vec<EmailSource> {
+    args.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .filter_map(|arg| {
+            if arg.get(..MAILTO_SCHEME.len() + 1).is_some_and(|prefix| prefix.eq_ignore_ascii_case("mailto:")) {
+                return Url::parse(&arg).ok().map(EmailSource::Mailto);
+            }
+            let path = PathBuf::from(arg);
+            (is_eml_path(&path) && path.is_file()).then_some(EmailSource::File(path))
+        })
+        .collect()
+}
+
+/// A file name for an attachment that can't escape `dir` or overwrite a
+/// sibling
+fn attachment_name(name: Option<&str>, index: usize, taken: &mut HashSet<String>) -> String {
+    let cleaned: String = name
+        .and_then(|name| Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().chars().filter(|c| !c.is_control()).collect())
+        .filter(|name: &String| !name.trim().is_empty() && !name.starts_with('.'))
+        .unwrap_or_else(|| format!("attachment_{}", index + 1));
+    let mut candidate = cleaned.clone();
+    let mut copy = 1;
+    while !taken.insert(candidate.to_lowercase()) {
+        copy += 1;
+        candidate = format!("{}_{}", copy, cleaned);
+    }
+    candidate
+}
+
+/// Parse an `.eml` file, saving its attachments to a new directory under
+/// the media `email/` directory
+fn parse_eml(app: &AppHandle, path: &Path) -> Result<IngestedEmail, String> {
+    let size = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+    if size > MAX_EML_BYTES {
+        return Err(format!("{} is larger than {} MB", path.display(), MAX_EML_BYTES / 1024 / 1024));
+    }
+    let raw = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let message = MessageParser::default()
+        .parse(&raw[..])
+        .ok_or_else(|| format!("{} is not an email message", path.display()))?;
+
+    let from = message.from().and_then(|from| from.first()).and_then(|sender| {
+        match (sender.name(), sender.address()) {
+            (Some(name), Some(address)) => Some(format!("{} <{}>", name, address)),
+            (name, address) => address.or(name).map(str::to_string),
+        }
+    });
+
+    let mut attachments = Vec::new();
+    if message.attachment_count() > 0 {
+        let dir = get_media_dir(app, "email")?.join(format!("email_{}", chrono::Utc::now().timestamp_millis()));
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut taken = HashSet::new();
+        for (index, part) in message.attachments().enumerate() {
+            let dest = dir.join(attachment_name(part.attachment_name(), index, &mut taken));
+            fs::write(&dest, part.contents()).map_err(|e| format!("Failed to save {}: {}", dest.display(), e))?;
+            attachments.push(dest.to_string_lossy().to_string());
+        }
+    }
+
+    Ok(IngestedEmail {
+        subject: message.subject().unwrap_or_default().trim().to_string(),
+        from,
+        date: message.date().map(|date| date.to_rfc3339()),
+        message_id: message.message_id().map(str::to_string),
+        // HTML-only messages are converted to text
+        body: message.body_text(0).map(|body| body.trim().to_string()).unwrap_or_default(),
+        attachments,
+        entry_id: None,
+    })
+}
+
+/// The subject and body of a `mailto:` link
+fn parse_mailto(url: &Url) -> IngestedEmail {
+    let mut subject = String::new();
+    let mut body = String::new();
+    for (key, value) in url.query_pairs() {
+        match key.to_ascii_lowercase().as_str() {
+            "subject" => subject = value.trim().to_string(),
+            "body" => body = value.trim().to_string(),
+            _ => {}
+        }
+    }
+    IngestedEmail {
+        subject,
+        from: None,
+        date: None,
+        message_id: None,
+        body,
+        attachments: Vec::new(),
+        entry_id: None,
+    }
+}
+
+/// Markdown content of the entry: the sender and date, the body, then the
+/// attachments by name
+fn entry_content(email: &IngestedEmail) -> String {
+    let mut content = String::new();
+    if let Some(from) = &email.from {
+        content.push_str(&format!("**From:** {}  \n", from));
+    }
+    if let Some(date) = &email.date {
+        content.push_str(&format!("**Date:** {}  \n", date));
+    }
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content.push_str(&email.body);
+    if !email.attachments.is_empty() {
+        content.push_str("\n\n**Attachments:**\n");
+        for path in &email.attachments {
+            let name = Path::new(path).file_name().unwrap_or_default().to_string_lossy();
+            content.push_str(&format!("- {}\n", name));
+        }
+    }
+    content
+}
+
+/// Create today's entry for `email` and announce it
+async fn forward(app: &AppHandle, mut email: IngestedEmail) -> Result<IngestedEmail, String> {
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let path = format!("/api/entries/note/{}", Local::now().format("%Y-%m-%d"));
+    let title = if email.subject.is_empty() {
+        "(no subject)".to_string()
+    } else {
+        email.subject.clone()
+    };
+    let body = json!({ "title": title, "content": entry_content(&email), "content_type": "markdown" });
+    let response = async_runtime::spawn_blocking(move || backend_client::call(&config, "POST", &path, Some(&body)))
+        .await
+        .map_err(|e| format!("Email task failed: {}", e))?
+        .map_err(|e| e.message)?;
+    email.entry_id = response.body.get("id").and_then(|id| id.as_i64());
+    info!("Email {:?} added as entry {:?}", email.subject, email.entry_id);
+
+    if let Err(e) = app.emit("email-ingested", &email) {
+        warn!("Failed to emit email-ingested: {}", e);
+    }
+    Ok(email)
+}
+
+async fn ingest(app: &AppHandle, source: EmailSource) -> Result<IngestedEmail, String> {
+    let email = match source {
+        EmailSource::File(path) => {
+            let app = app.clone();
+            async_runtime::spawn_blocking(move || parse_eml(&app, &path))
+                .await
+                .map_err(|e| format!("Email task failed: {}", e))??
+        }
+        EmailSource::Mailto(url) => parse_mailto(&url),
+    };
+    if email.subject.is_empty() && email.body.is_empty() && email.attachments.is_empty() {
+        return Err("The email is empty".to_string());
+    }
+    forward(app, email).await
+}
+
+/// Ingest emails the OS opened the app with, once the backend is up, and
+/// report each as a notification
+pub fn handle_opened(app: &AppHandle, sources: Vec<EmailSource>) {
+    if sources.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let ready = crate::wait_for_backend_health(&app, BACKEND_START_TIMEOUT).await;
+        for source in sources {
+            let result = if ready {
+                ingest(&app, source).await
+            } else {
+                Err("The backend did not start".to_string())
+            };
+            let (title, body) = match result {
+                Ok(email) => ("Email added".to_string(), email.subject),
+                Err(e) => ("Email not added".to_string(), e),
+            };
+            info!("{}: {}", title, body);
+            if let Err(e) = notifications::notify(app.clone(), title, body, None).await {
+                warn!("Failed to report email ingestion: {}", e);
+            }
+        }
+    });
+}
+
+/// Ingest the emails passed on the command line of the first launch
+pub fn init_email_ingest(app: &AppHandle) {
+    handle_opened(app, sources_from_args(std::env::args()));
+}
+
+/// Turn an `.eml` file into an entry on today's note, saving its
+/// attachments to the media `email/` directory
+#[tauri::command]
+pub async fn ingest_eml(app: AppHandle, path: String) -> Result<IngestedEmail, String> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    ingest(&app, EmailSource::File(path)).await
+}
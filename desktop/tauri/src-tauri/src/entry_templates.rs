@@ -0,0 +1,234 @@
+//! Entry templates, expanded and posted to the backend by the shell so an
+//! entry can be created from a shortcut, the command line or an automation
+//! link without the window. Templates live in `entry_templates.json` in the
+//! config dir. `{{name}}` placeholders are filled from the caller's
+//! variables first, then from the built-ins: `date`, `time`, `datetime`,
+//! `weekday`, `clipboard`, `app` and `window` (the frontmost app and its
+//! window title).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::Local;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::permissions::{self, ACTIVITY, CLIPBOARD};
+use crate::{backend_client, DesktopConfig};
+
+const TEMPLATES_FILE: &str = "entry_templates.json";
+
+/// Serializes reads and writes of the templates file
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryTemplate {
+    /// Used by commands, links and the shortcut, e.g. `standup`
+    pub id: String,
+    /// Entry title; may contain placeholders
+    #[serde(default)]
+    pub title: String,
+    pub content: String,
+    /// `markdown` or `rich_text`, as the backend stores entries
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+}
+
+fn default_content_type() -> String {
+    "markdown".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct TemplateFile {
+    templates: Vec<EntryTemplate>,
+}
+
+impl TemplateFile {
+    fn load(app: &AppHandle) -> Self {
+        let Some(path) = templates_path(app) else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse entry templates: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let path = templates_path(app).ok_or("Failed to resolve app config directory")?;
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize entry templates: {}", e))?;
+        crate::prefs::write_atomic(&path, &json)
+    }
+}
+
+fn templates_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(TEMPLATES_FILE))
+}
+
+/// Result of `create_entry_from_template`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateEntry {
+    /// The entry created on today's note
+    pub entry_id: Option<i64>,
+    pub title: String,
+    pub content: String,
+}
+
+/// Names of the `{{name}}` placeholders in `text`, in order
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + end].trim());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+
+fn expand(text: &str, values: &HashMap<String, String>) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + end].trim();
+        expanded.push_str(values.get(name).map(String::as_str).unwrap_or_default());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Values for the built-in placeholders `template` uses and `vars` doesn't
+/// set. The clipboard needs the `clipboard` feature permission and the
+/// frontmost app needs `activity`.
+async fn builtins(app: &AppHandle, template: &EntryTemplate, vars: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+    let mut values = HashMap::new();
+    let now = Local::now();
+    let wanted: Vec<&str> = placeholders(&template.title)
+        .into_iter()
+        .chain(placeholders(&template.content))
+        .filter(|name| !vars.contains_key(*name))
+        .collect();
+    let mut frontmost = None;
+    for name in wanted {
+        let value = match name {
+            "date" => now.format("%Y-%m-%d").to_string(),
+            "time" => now.format("%H:%M").to_string(),
+            "datetime" => now.format("%Y-%m-%d %H:%M").to_string(),
+            "weekday" => now.format("%A").to_string(),
+            "clipboard" => {
+                permissions::require(app, CLIPBOARD)?;
+                app.clipboard().read_text().unwrap_or_default()
+            }
+            "app" | "window" => {
+                permissions::require(app, ACTIVITY)?;
+                if frontmost.is_none() {
+                    frontmost = Some(
+                        async_runtime::spawn_blocking(crate::activity::frontmost)
+                            .await
+                            .map_err(|e| format!("Frontmost app task failed: {}", e))?,
+                    );
+                }
+                let (app_name, window) = frontmost.clone().flatten().unwrap_or_default();
+                if name == "app" {
+                    app_name
+                } else {
+                    window.unwrap_or_default()
+                }
+            }
+            _ => return Err(format!("Template placeholder {{{{{}}}}} has no value", name)),
+        };
+        values.insert(name.to_string(), value);
+    }
+    Ok(values)
+}
+
+/// Expand template `id` with `vars` and add the result to today's note
+pub async fn create_from_template(app: &AppHandle, id: &str, vars: HashMap<String, String>) -> Result<TemplateEntry, String> {
+    let template = {
+        let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+        TemplateFile::load(app).templates.into_iter().find(|template| template.id == id)
+    }
+    .ok_or_else(|| format!("No entry template {:?}", id))?;
+
+    let mut values = builtins(app, &template, &vars).await?;
+    values.extend(vars);
+    let title = expand(&template.title, &values).trim().to_string();
+    let content = expand(&template.content, &values);
+
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let path = format!("/api/entries/note/{}", Local::now().format("%Y-%m-%d"));
+    let body = json!({ "title": title, "content": content, "content_type": template.content_type });
+    let response = async_runtime::spawn_blocking(move || backend_client::call(&config, "POST", &path, Some(&body)))
+        .await
+        .map_err(|e| format!("Template task failed: {}", e))?
+        .map_err(|e| e.message)?;
+    let entry_id = response.body.get("id").and_then(|id| id.as_i64());
+    info!("Created entry {:?} from template {}", entry_id, id);
+    Ok(TemplateEntry { entry_id, title, content })
+}
+
+#[tauri::command]
+pub async fn list_entry_templates(app: AppHandle) -> Result<Vec<EntryTemplate>, String> {
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(TemplateFile::load(&app).templates)
+}
+
+/// Add a template, or replace the one with the same id
+#[tauri::command]
+pub async fn save_entry_template(app: AppHandle, template: EntryTemplate) -> Result<(), String> {
+    let id = &template.id;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')) {
+        return Err(format!("Template id {:?} must be letters, digits, - and _", template.id));
+    }
+    if template.content.trim().is_empty() {
+        return Err("A template needs some content".to_string());
+    }
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut file = TemplateFile::load(&app);
+    match file.templates.iter_mut().find(|existing| existing.id == template.id) {
+        Some(existing) => *existing = template,
+        None => file.templates.push(template),
+    }
+    file.save(&app)
+}
+
+#[tauri::command]
+pub async fn delete_entry_template(app: AppHandle, id: String) -> Result<(), String> {
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut file = TemplateFile::load(&app);
+    let count = file.templates.len();
+    file.templates.retain(|template| template.id != id);
+    if file.templates.len() == count {
+        return Err(format!("No entry template {:?}", id));
+    }
+    file.save(&app)
+}
+
+/// Expand a template's placeholders and add the entry to today's note
+#[tauri::command]
+pub async fn create_entry_from_template(
+    app: AppHandle,
+    template_id: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<TemplateEntry, String> {
+    create_from_template(&app, &template_id, vars.unwrap_or_default()).await
+}
@@ -0,0 +1,211 @@
+//! `ShellError`, the typed error of the media, speech, capture and backend
+//! commands. It serializes as `{ code, message, details, recoverable }`, so
+//! the webview can branch on `code` instead of parsing the message. Helpers
+//! that still return `Result<_, String>` convert with `?`; the
+//! `permission-denied:` messages of `permissions::require` become
+//! `permissionDenied`, anything else `internal`, so commands map the failures
+//! they know about themselves.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::permissions::DENIED_PREFIX;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// An argument was malformed or out of range; nothing was done
+    InvalidInput,
+    /// A feature permission is off (see `permissions`) or the OS denied access
+    PermissionDenied,
+    /// A device, session or the backend isn't there right now
+    Unavailable,
+    /// Not supported on this platform or in this build
+    Unsupported,
+    Timeout,
+    /// Anything else; the message says what failed
+    Internal,
+}
+
+impl ErrorCode {
+    /// Whether trying again can succeed, e.g. once a permission is granted or
+    /// a device is plugged in
+    pub fn recoverable(self) -> bool {
+        matches!(self, Self::PermissionDenied | Self::Unavailable | Self::Timeout)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellError {
+    pub code: ErrorCode,
+    /// For people; may change between versions
+    pub message: String,
+    /// For code, e.g. the feature of a `permissionDenied` error or the status
+    /// and body of a backend response
+    pub details: Option<Value>,
+    pub recoverable: bool,
+}
+
+impl ShellError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            recoverable: code.recoverable(),
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Into<Value>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Unavailable, message)
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Unsupported, message)
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Timeout, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ShellError {}
+
+impl From<String> for ShellError {
+    fn from(message: String) -> Self {
+        match message.strip_prefix(DENIED_PREFIX) {
+            Some(feature) => Self::new(
+                ErrorCode::PermissionDenied,
+                format!("The {} permission is turned off", feature),
+            )
+            .with_details(feature),
+            None => Self::internal(message),
+        }
+    }
+}
+
+impl From<&str> for ShellError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+/// For callers that still return `Result<_, String>`. A permission error
+/// keeps its `permission-denied:` form.
+impl From<ShellError> for String {
+    fn from(error: ShellError) -> Self {
+        match (error.code, error.details) {
+            (ErrorCode::PermissionDenied, Some(Value::String(feature))) => format!("{}{}", DENIED_PREFIX, feature),
+            _ => error.message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const CODES: [(ErrorCode, &str, bool); 6] = [
+        (ErrorCode::InvalidInput, "invalidInput", false),
+        (ErrorCode::PermissionDenied, "permissionDenied", true),
+        (ErrorCode::Unavailable, "unavailable", true),
+        (ErrorCode::Unsupported, "unsupported", false),
+        (ErrorCode::Timeout, "timeout", true),
+        (ErrorCode::Internal, "internal", false),
+    ];
+
+    #[test]
+    fn recoverable_codes() {
+        for (code, _, recoverable) in CODES {
+            assert_eq!(code.recoverable(), recoverable, "{:?}", code);
+            assert_eq!(ShellError::new(code, "failed").recoverable, recoverable, "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn codes_serialize_in_camel_case() {
+        for (code, name, _) in CODES {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(name));
+        }
+    }
+
+    #[test]
+    fn serializes_code_message_details_and_recoverable() {
+        let error = ShellError::unavailable("No camera");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({ "code": "unavailable", "message": "No camera", "details": null, "recoverable": true })
+        );
+
+        let error = ShellError::invalid_input("Bad request").with_details(json!({ "status": 422 }));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "invalidInput",
+                "message": "Bad request",
+                "details": { "status": 422 },
+                "recoverable": false,
+            })
+        );
+    }
+
+    #[test]
+    fn permission_messages_become_permission_denied() {
+        let error = ShellError::from(format!("{}camera", DENIED_PREFIX));
+        assert_eq!(error.code, ErrorCode::PermissionDenied);
+        assert_eq!(error.details, Some(json!("camera")));
+        assert!(error.recoverable);
+        assert_eq!(error.message, "The camera permission is turned off");
+    }
+
+    #[test]
+    fn other_messages_become_internal() {
+        for message in ["Failed to query cameras", "", "camera permission-denied:"] {
+            let error = ShellError::from(message);
+            assert_eq!(error, ShellError::internal(message));
+            assert_eq!(error.details, None);
+            assert!(!error.recoverable);
+        }
+    }
+
+    #[test]
+    fn converts_back_to_the_string_it_came_from() {
+        for message in [format!("{}microphone", DENIED_PREFIX), "Not currently recording".to_string()] {
+            assert_eq!(String::from(ShellError::from(message.clone())), message);
+        }
+    }
+
+    #[test]
+    fn converts_to_the_message_without_permission_details() {
+        assert_eq!(String::from(ShellError::new(ErrorCode::Timeout, "Timed out")), "Timed out");
+        let denied = ShellError::new(ErrorCode::PermissionDenied, "Access denied by the OS");
+        assert_eq!(String::from(denied), "Access denied by the OS");
+        let unexpected = ShellError::new(ErrorCode::PermissionDenied, "Denied").with_details(json!({ "feature": 1 }));
+        assert_eq!(String::from(unexpected), "Denied");
+    }
+}
@@ -0,0 +1,176 @@
+//! Rate limits for events the webview can get many of in a short time:
+//! transcription partials, download, move and job progress, and entry changes
+//! during a sync. Each listed event is sent at most once per interval, either
+//! as the newest payload or as an array of everything since the last send.
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{async_runtime, AppHandle, Emitter};
+use tokio::time::sleep;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Only the newest payload of an interval is sent
+    Coalesce,
+    /// Every payload is sent, as an array per interval
+    Batch,
+}
+
+struct Channel {
+    event: &'static str,
+    interval: Duration,
+    mode: Mode,
+}
+
+/// Events not listed here are emitted as they come
+const CHANNELS: &[Channel] = &[
+    Channel {
+        event: "speech-transcription",
+        interval: Duration::from_millis(100),
+        mode: Mode::Coalesce,
+    },
+    Channel {
+        event: "backend-update-progress",
+        interval: Duration::from_millis(250),
+        mode: Mode::Coalesce,
+    },
+    Channel {
+        event: "data-dir-progress",
+        interval: Duration::from_millis(250),
+        mode: Mode::Coalesce,
+    },
+    Channel {
+        event: "data-export-progress",
+        interval: Duration::from_millis(250),
+        mode: Mode::Coalesce,
+    },
+    Channel {
+        event: "job-progress",
+        interval: Duration::from_millis(250),
+        mode: Mode::Batch,
+    },
+    Channel {
+        event: "entry-updated",
+        interval: Duration::from_millis(250),
+        mode: Mode::Batch,
+    },
+];
+
+#[derive(Default)]
+struct Pending {
+    last_sent: Option<Instant>,
+    payloads: Vec<Value>,
+    /// A send is scheduled for the end of the interval
+    scheduled: bool,
+}
+
+fn pending() -> &'static Mutex<HashMap<&'static str, Pending>> {
+    static PENDING: OnceLock<Mutex<HashMap<&'static str, Pending>>> = OnceLock::new();
+    PENDING.get_or_init(Mutex::default)
+}
+
+fn channel(event: &str) -> Option<&'static Channel> {
+    CHANNELS.iter().find(|channel| channel.event == event)
+}
+
+fn send(app: &AppHandle, channel: &Channel, payloads: Vec<Value>) {
+    let result = match channel.mode {
+        Mode::Coalesce => match payloads.into_iter().last() {
+            Some(payload) => app.emit(channel.event, payload),
+            None => return,
+        },
+        Mode::Batch if payloads.is_empty() => return,
+        Mode::Batch => app.emit(channel.event, payloads),
+    };
+    if let Err(e) = result {
+        warn!("Failed to emit {} event: {}", channel.event, e);
+    }
+}
+
+/// Send what's pending for `channel` at the end of its interval
+fn flush(app: &AppHandle, channel: &'static Channel) {
+    let payloads = {
+        let mut pending = pending().lock().unwrap_or_else(|e| e.into_inner());
+        let state = pending.entry(channel.event).or_default();
+        state.scheduled = false;
+        state.last_sent = Some(Instant::now());
+        mem::take(&mut state.payloads)
+    };
+    send(app, channel, payloads);
+}
+
+fn to_value<S: Serialize>(event: &str, payload: S) -> Option<Value> {
+    serde_json::to_value(payload)
+        .map_err(|e| warn!("Failed to serialize {} event: {}", event, e))
+        .ok()
+}
+
+/// Emit `event` within its channel's limit: straight away if the interval has
+/// passed since the last send, otherwise at the end of it
+pub(crate) fn emit<S: Serialize + Clone>(app: &AppHandle, event: &'static str, payload: S) {
+    let Some(channel) = channel(event) else {
+        if let Err(e) = app.emit(event, payload) {
+            warn!("Failed to emit {} event: {}", event, e);
+        }
+        return;
+    };
+    let Some(payload) = to_value(event, payload) else {
+        return;
+    };
+
+    let now = Instant::now();
+    let mut pending = pending().lock().unwrap_or_else(|e| e.into_inner());
+    let state = pending.entry(event).or_default();
+    if channel.mode == Mode::Coalesce {
+        state.payloads.clear();
+    }
+    state.payloads.push(payload);
+    if state.scheduled {
+        return;
+    }
+    let due = state.last_sent.map_or(now, |last| last + channel.interval);
+    if due <= now {
+        state.last_sent = Some(now);
+        let payloads = mem::take(&mut state.payloads);
+        drop(pending);
+        send(app, channel, payloads);
+        return;
+    }
+    state.scheduled = true;
+    drop(pending);
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        sleep(due - now).await;
+        flush(&app, channel);
+    });
+}
+
+/// Emit `event` now, for payloads that must not be coalesced away, such as
+/// a final transcript or a phase change. Batched payloads still pending are
+/// sent first; coalesced ones are superseded.
+pub(crate) fn emit_now<S: Serialize + Clone>(app: &AppHandle, event: &'static str, payload: S) {
+    if let Some(channel) = channel(event) {
+        let earlier = {
+            let mut pending = pending().lock().unwrap_or_else(|e| e.into_inner());
+            let state = pending.entry(event).or_default();
+            state.last_sent = Some(Instant::now());
+            mem::take(&mut state.payloads)
+        };
+        if channel.mode == Mode::Batch {
+            let mut payloads = earlier;
+            payloads.extend(to_value(event, payload));
+            send(app, channel, payloads);
+            return;
+        }
+    }
+    if let Err(e) = app.emit(event, payload) {
+        warn!("Failed to emit {} event: {}", event, e);
+    }
+}
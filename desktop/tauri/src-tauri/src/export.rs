@@ -0,0 +1,541 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::jobs::{self, CancelToken, JobKind, Priority, Progress};
+use crate::{backend_client, event_batch, DesktopConfig};
+
+/// ICS lines are folded at this many octets (RFC 5545 §3.1)
+const ICS_LINE_OCTETS: usize = 75;
+
+const CSV_COLUMNS: &[&str] = &[
+    "date",
+    "id",
+    "title",
+    "content",
+    "contentType",
+    "labels",
+    "important",
+    "completed",
+    "pinned",
+    "archived",
+    "reminder",
+    "createdAt",
+    "updatedAt",
+];
+
+/// Set while an export runs
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ics,
+}
+
+impl ExportFormat {
+    /// `csv`, `json` or `ics`, as on the command line
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Ics]
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(name))
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ics => "ics",
+        }
+    }
+
+    fn filter_name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Ics => "iCalendar",
+        }
+    }
+}
+
+/// Inclusive date range; an open end means the first or last note
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataExport {
+    pub path: String,
+    pub notes: u64,
+    pub entries: u64,
+    pub size_bytes: u64,
+}
+
+/// Payload of `data-export-progress`, sent after each month is written
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ExportProgress {
+    months_done: u32,
+    months_total: u32,
+    entries_written: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct NoteSpan {
+    first: Option<NaiveDate>,
+    last: Option<NaiveDate>,
+}
+
+/// Writes notes one at a time, so memory use doesn't grow with the export
+struct Exporter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    notes: u64,
+    entries: u64,
+    stamp: String,
+}
+
+fn text(value: &Value, key: &str) -> String {
+    match &value[key] {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn flag(value: &Value, key: &str) -> bool {
+    value[key].as_bool().unwrap_or(false)
+}
+
+fn label_names(value: &Value) -> Vec<String> {
+    value["labels"]
+        .as_array()
+        .map(|labels| labels.iter().map(|label| text(label, "name")).collect())
+        .unwrap_or_default()
+}
+
+/// Active (not dismissed) reminder time, as stored by the backend
+fn reminder(entry: &Value) -> Option<String> {
+    let reminder = entry["reminder"].as_object()?;
+    if reminder.get("is_dismissed").and_then(Value::as_bool).unwrap_or(false) {
+        return None;
+    }
+    reminder.get("reminder_datetime")?.as_str().map(str::to_string)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Rich-text entries are HTML; calendars want plain text
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag.trim_start_matches('/').split_whitespace().next().unwrap_or_default();
+                if matches!(name.to_ascii_lowercase().as_str(), "br" | "br/" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3")
+                    && !text.ends_with('\n')
+                    && !text.is_empty()
+                {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => text.push(c),
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn ics_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// `2025-11-15T09:30:00` (UTC, as the backend stores it) or an RFC 3339 time
+fn ics_time(value: &str) -> Option<String> {
+    let utc = DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").map(|time| time.and_utc()))
+        .ok()?;
+    Some(utc.format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+impl<W: Write> Exporter<W> {
+    fn new(out: W, format: ExportFormat) -> Self {
+        Self {
+            out,
+            format,
+            notes: 0,
+            entries: 0,
+            stamp: Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        }
+    }
+
+    /// Write a content line, folded so no line exceeds 75 octets
+    fn ics_line(&mut self, line: &str) -> io::Result<()> {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > ICS_LINE_OCTETS {
+                self.out.write_all(b"\r\n ")?;
+                width = 1;
+            }
+            write!(self.out, "{}", c)?;
+            width += c.len_utf8();
+        }
+        self.out.write_all(b"\r\n")
+    }
+
+    fn begin(&mut self, range: ExportRange) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => writeln!(self.out, "{}", CSV_COLUMNS.join(",")),
+            ExportFormat::Json => write!(
+                self.out,
+                "{{\"exportedAt\":{},\"from\":{},\"to\":{},\"notes\":[",
+                Value::from(Utc::now().to_rfc3339()),
+                serde_json::to_value(range.from).unwrap_or_default(),
+                serde_json::to_value(range.to).unwrap_or_default(),
+            ),
+            ExportFormat::Ics => {
+                self.ics_line("BEGIN:VCALENDAR")?;
+                self.ics_line("VERSION:2.0")?;
+                self.ics_line("PRODID:-//Track the Thing//Desktop//EN")?;
+                self.ics_line("CALSCALE:GREGORIAN")
+            }
+        }
+    }
+
+    fn note(&mut self, note: &Value) -> io::Result<()> {
+        let date = text(note, "date");
+        let entries = note["entries"].as_array().map(Vec::as_slice).unwrap_or_default();
+        match self.format {
+            ExportFormat::Csv => {
+                for entry in entries {
+                    let row = [
+                        date.clone(),
+                        text(entry, "id"),
+                        text(entry, "title"),
+                        text(entry, "content"),
+                        text(entry, "content_type"),
+                        label_names(entry).join(";"),
+                        flag(entry, "is_important").to_string(),
+                        flag(entry, "is_completed").to_string(),
+                        flag(entry, "is_pinned").to_string(),
+                        flag(entry, "is_archived").to_string(),
+                        reminder(entry).unwrap_or_default(),
+                        text(entry, "created_at"),
+                        text(entry, "updated_at"),
+                    ];
+                    let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                    writeln!(self.out, "{}", row.join(","))?;
+                }
+            }
+            ExportFormat::Json => {
+                if self.notes > 0 {
+                    self.out.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut self.out, note)?;
+            }
+            ExportFormat::Ics => {
+                let day = date.replace('-', "");
+                for entry in entries {
+                    self.ics_entry(&day, entry)?;
+                }
+            }
+        }
+        self.notes += 1;
+        self.entries += entries.len() as u64;
+        Ok(())
+    }
+
+    /// A VJOURNAL on the note's day, and a VEVENT for an active reminder
+    fn ics_entry(&mut self, day: &str, entry: &Value) -> io::Result<()> {
+        let id = text(entry, "id");
+        let title = text(entry, "title");
+        let content = text(entry, "content");
+        let description = if text(entry, "content_type") == "rich_text" {
+            html_to_text(&content)
+        } else {
+            content
+        };
+        let summary = if title.is_empty() {
+            description.lines().next().unwrap_or_default().to_string()
+        } else {
+            title
+        };
+        let labels = label_names(entry);
+        let stamp = self.stamp.clone();
+
+        self.ics_line("BEGIN:VJOURNAL")?;
+        self.ics_line(&format!("UID:entry-{}@trackthething", id))?;
+        self.ics_line(&format!("DTSTAMP:{}", stamp))?;
+        self.ics_line(&format!("DTSTART;VALUE=DATE:{}", day))?;
+        self.ics_line(&format!("SUMMARY:{}", ics_escape(&summary)))?;
+        if !description.is_empty() {
+            self.ics_line(&format!("DESCRIPTION:{}", ics_escape(&description)))?;
+        }
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels.iter().map(|label| ics_escape(label)).collect();
+            self.ics_line(&format!("CATEGORIES:{}", labels.join(",")))?;
+        }
+        if let Some(created) = ics_time(&text(entry, "created_at")) {
+            self.ics_line(&format!("CREATED:{}", created))?;
+        }
+        if let Some(modified) = ics_time(&text(entry, "updated_at")) {
+            self.ics_line(&format!("LAST-MODIFIED:{}", modified))?;
+        }
+        self.ics_line("END:VJOURNAL")?;
+
+        if let Some(due) = reminder(entry).as_deref().and_then(ics_time) {
+            self.ics_line("BEGIN:VEVENT")?;
+            self.ics_line(&format!("UID:reminder-{}@trackthething", id))?;
+            self.ics_line(&format!("DTSTAMP:{}", stamp))?;
+            self.ics_line(&format!("DTSTART:{}", due))?;
+            self.ics_line(&format!("SUMMARY:{}", ics_escape(&summary)))?;
+            self.ics_line("BEGIN:VALARM")?;
+            self.ics_line("ACTION:DISPLAY")?;
+            self.ics_line(&format!("DESCRIPTION:{}", ics_escape(&summary)))?;
+            self.ics_line("TRIGGER:PT0S")?;
+            self.ics_line("END:VALARM")?;
+            self.ics_line("END:VEVENT")?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        match self.format {
+            ExportFormat::Csv => {}
+            ExportFormat::Json => self.out.write_all(b"]}")?,
+            ExportFormat::Ics => self.ics_line("END:VCALENDAR")?,
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn get(config: &DesktopConfig, path: &str) -> Result<Value, String> {
+    backend_client::call(config, "GET", path, None)
+        .map(|response| response.body)
+        .map_err(|e| format!("Failed to fetch {}: {}", path, e))
+}
+
+/// First day of every month from `from` through `to`
+fn months(from: NaiveDate, to: NaiveDate) -> Vec<(i32, u32)> {
+    let mut months = Vec::new();
+    let (mut year, mut month) = (from.year(), from.month());
+    while (year, month) <= (to.year(), to.month()) {
+        months.push((year, month));
+        (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    }
+    months
+}
+
+/// Fill open ends of `range` from the first and last notes. `None` when there
+/// is nothing to export.
+fn resolve_range(config: &DesktopConfig, range: ExportRange) -> Result<Option<(NaiveDate, NaiveDate)>, String> {
+    if let (Some(from), Some(to)) = (range.from, range.to) {
+        return Ok((from <= to).then_some((from, to)));
+    }
+    let span: NoteSpan =
+        serde_json::from_value(get(config, "/api/notes/span")?).map_err(|e| format!("Unexpected note span: {}", e))?;
+    let (Some(first), Some(last)) = (span.first, span.last) else {
+        return Ok(None);
+    };
+    let from = range.from.unwrap_or(first);
+    let to = range.to.unwrap_or(last);
+    Ok((from <= to).then_some((from, to)))
+}
+
+/// Fetch the range a month at a time through the backend and stream it to
+/// `partial`, stopping between months once `cancel` is set
+fn write_export(
+    app: &AppHandle,
+    format: ExportFormat,
+    range: ExportRange,
+    partial: &Path,
+    cancel: &CancelToken,
+    progress: &Progress,
+) -> Result<(u64, u64), String> {
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let resolved = resolve_range(&config, range)?;
+    let file = File::create(partial).map_err(|e| format!("Cannot write {}: {}", partial.display(), e))?;
+    let mut exporter = Exporter::new(BufWriter::new(file), format);
+    let write_error = |e: io::Error| format!("Failed to write {}: {}", partial.display(), e);
+    exporter.begin(range).map_err(write_error)?;
+
+    if let Some((from, to)) = resolved {
+        let months = months(from, to);
+        let months_total = months.len() as u32;
+        for (done, (year, month)) in months.into_iter().enumerate() {
+            cancel.check()?;
+            let notes = get(&config, &format!("/api/notes/month/{}/{}", year, month))?;
+            for note in notes.as_array().map(Vec::as_slice).unwrap_or_default() {
+                let in_range = NaiveDate::parse_from_str(&text(note, "date"), "%Y-%m-%d")
+                    .map(|date| from <= date && date <= to)
+                    .unwrap_or(false);
+                if in_range {
+                    exporter.note(note).map_err(write_error)?;
+                }
+            }
+            let export_progress = ExportProgress {
+                months_done: done as u32 + 1,
+                months_total,
+                entries_written: exporter.entries,
+            };
+            progress.report(done as u64 + 1, months_total as u64);
+            event_batch::emit(app, "data-export-progress", export_progress);
+        }
+    }
+
+    let (notes, entries) = (exporter.notes, exporter.entries);
+    let out = exporter.finish().map_err(write_error)?;
+    out.into_inner()
+        .map_err(|e| write_error(e.into_error()))?
+        .sync_all()
+        .map_err(write_error)?;
+    Ok((notes, entries))
+}
+
+/// Ask where to save, `None` if the dialog is cancelled
+fn choose_destination(app: &AppHandle, format: ExportFormat) -> Result<Option<PathBuf>, String> {
+    let file_name = format!(
+        "track-the-thing-{}.{}",
+        Local::now().format("%Y%m%d"),
+        format.extension()
+    );
+    let chosen = app
+        .dialog()
+        .file()
+        .set_title("Export data")
+        .set_file_name(file_name)
+        .add_filter(format.filter_name(), &[format.extension()])
+        .blocking_save_file();
+    match chosen {
+        Some(path) => path.into_path().map(Some).map_err(|e| format!("Unsupported destination: {}", e)),
+        None => Ok(None),
+    }
+}
+
+fn export(
+    app: &AppHandle,
+    format: ExportFormat,
+    range: ExportRange,
+    dest: Option<String>,
+    cancel: &CancelToken,
+    progress: &Progress,
+) -> Result<Option<DataExport>, String> {
+    let dest = match dest {
+        Some(dest) => PathBuf::from(dest),
+        None => match choose_destination(app, format)? {
+            Some(dest) => dest,
+            None => return Ok(None),
+        },
+    };
+    if !dest.is_absolute() {
+        return Err("Export path must be absolute".to_string());
+    }
+
+    let partial = dest.with_extension(format!("{}.partial", format.extension()));
+    let written = write_export(app, format, range, &partial, cancel, progress)
+        .and_then(|counts| fs::rename(&partial, &dest).map(|()| counts).map_err(|e| e.to_string()));
+    let (notes, entries) = match written {
+        Ok(counts) => counts,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    info!("Exported {} entries to {}", entries, dest.display());
+    Ok(Some(DataExport {
+        path: dest.to_string_lossy().to_string(),
+        notes,
+        entries,
+        size_bytes: fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0),
+    }))
+}
+
+/// Run an export unless one is already running
+pub(crate) async fn run_export(
+    app: AppHandle,
+    format: ExportFormat,
+    range: Option<ExportRange>,
+    dest: Option<String>,
+    cancel: CancelToken,
+    progress: Progress,
+) -> Result<Option<DataExport>, String> {
+    if EXPORTING.swap(true, Ordering::SeqCst) {
+        return Err("An export is already running".to_string());
+    }
+    let range = range.unwrap_or_default();
+    let result = async_runtime::spawn_blocking(move || export(&app, format, range, dest, &cancel, &progress))
+        .await
+        .map_err(|e| format!("Export task panicked: {}", e))
+        .and_then(|result| result);
+    EXPORTING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Export notes and entries in `range` to a CSV, JSON or ICS file. Data is
+/// fetched from the backend a month at a time and streamed to disk, with
+/// `data-export-progress` events along the way. Without `dest` a save dialog
+/// is shown; cancelling it returns `null`.
+#[tauri::command]
+pub async fn export_data(
+    app: AppHandle,
+    format: ExportFormat,
+    range: Option<ExportRange>,
+    dest: Option<String>,
+) -> Result<Option<DataExport>, String> {
+    run_export(app, format, range, dest, CancelToken::default(), Progress::default()).await
+}
+
+/// Queue `export_data` as a high-priority job that `cancel_job` can stop; the
+/// partial file is removed then. Returns the job id.
+#[tauri::command]
+pub async fn start_export(
+    app: AppHandle,
+    format: ExportFormat,
+    range: Option<ExportRange>,
+    dest: Option<String>,
+) -> Result<jobs::JobId, String> {
+    jobs::enqueue(&app, JobKind::Export { format, range, dest }, Priority::High)
+}
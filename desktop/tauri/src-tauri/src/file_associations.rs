@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::focus_main_window;
+
+/// Extensions registered in tauri.conf.json `bundle.fileAssociations`
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["tttbackup", "ttt"];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFileEvent {
+    pub path: String,
+}
+
+/// Archives opened before the webview was ready to import them. Once the
+/// webview has taken the pending list, later files are delivered as events only.
+#[derive(Default)]
+pub struct PendingOpenFiles {
+    paths: Mutex<Vec<PathBuf>>,
+    delivered: AtomicBool,
+}
+
+pub fn is_archive_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ARCHIVE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        .unwrap_or(false)
+}
+
+/// Pick archive paths out of a process argument list (Windows/Linux pass the
+/// double-clicked file as an argument). The first argument is the binary.
+pub fn archive_paths_from_args<I: IntoIterator<Item = String>>(args: I) -> Vec<PathBuf> {
+    args.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .filter(|path| is_archive_path(path) && path.is_file())
+        .collect()
+}
+
+/// Route opened archives to the import flow via `open-file` events
+pub fn handle_open_files(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+
+    let pending = app.state::<PendingOpenFiles>();
+    if !pending.delivered.load(Ordering::SeqCst) {
+        info!("Queueing {} archive(s) until the webview is ready", paths.len());
+        if let Ok(mut queued) = pending.paths.lock() {
+            queued.extend(paths);
+        }
+        return;
+    }
+
+    focus_main_window(app);
+    for path in paths {
+        info!("Opening archive {}", path.display());
+        let event = OpenFileEvent {
+            path: path.to_string_lossy().to_string(),
+        };
+        if let Err(e) = app.emit("open-file", event) {
+            warn!("Failed to emit open-file event: {}", e);
+        }
+    }
+}
+
+/// Capture any archive passed on the command line of the first launch
+pub fn init_file_associations(app: &AppHandle) {
+    app.manage(PendingOpenFiles::default());
+    handle_open_files(app, archive_paths_from_args(std::env::args()));
+}
+
+/// Return (and clear) archives opened before the webview subscribed to
+/// `open-file` events; afterwards they are emitted directly.
+#[tauri::command]
+pub async fn take_pending_open_files(
+    pending: State<'_, PendingOpenFiles>,
+) -> Result<Vec<OpenFileEvent>, String> {
+    let mut queued = pending.paths.lock().map_err(|e| format!("Lock error: {}", e))?;
+    pending.delivered.store(true, Ordering::SeqCst);
+    Ok(std::mem::take(&mut *queued)
+        .into_iter()
+        .map(|path| OpenFileEvent {
+            path: path.to_string_lossy().to_string(),
+        })
+        .collect())
+}
@@ -0,0 +1,312 @@
+//! Focus sessions (Pomodoro): focus phases alternating with breaks, with a
+//! long break after every `focus.longBreakEvery` focus phases. The shell runs
+//! them, so a session keeps going while the window is closed to the tray.
+//! The tray shows the countdown, each transition is announced with a
+//! notification, and with `focus.toggleDnd` Do Not Disturb is on during
+//! focus phases.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+
+use crate::locale::{tr, Text};
+use crate::settings::{FocusSettings, SettingsState};
+use crate::{dnd, notifications, telemetry, tray};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+static SESSION: Mutex<Option<FocusSession>> = Mutex::new(None);
+
+/// Bumped whenever a session starts or stops, which ends the previous
+/// session's task
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FocusPhase {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+impl FocusPhase {
+    fn label(self) -> &'static str {
+        match self {
+            FocusPhase::Focus => tr(Text::FocusTime),
+            FocusPhase::ShortBreak => tr(Text::ShortBreak),
+            FocusPhase::LongBreak => tr(Text::LongBreak),
+        }
+    }
+}
+
+struct FocusSession {
+    settings: FocusSettings,
+    phase: FocusPhase,
+    phase_ends_at: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+    /// Focus phases finished in this session
+    completed: u32,
+    /// Do Not Disturb was turned on by the session and must be turned off again
+    dnd_on: bool,
+}
+
+impl FocusSession {
+    fn minutes(&self, phase: FocusPhase) -> u64 {
+        match phase {
+            FocusPhase::Focus => self.settings.work_minutes,
+            FocusPhase::ShortBreak => self.settings.short_break_minutes,
+            FocusPhase::LongBreak => self.settings.long_break_minutes,
+        }
+    }
+
+    fn next_phase(&self) -> FocusPhase {
+        match self.phase {
+            FocusPhase::Focus if (self.completed + 1) % self.settings.long_break_every == 0 => FocusPhase::LongBreak,
+            FocusPhase::Focus => FocusPhase::ShortBreak,
+            FocusPhase::ShortBreak | FocusPhase::LongBreak => FocusPhase::Focus,
+        }
+    }
+
+    fn enter(&mut self, phase: FocusPhase, now: DateTime<Utc>) {
+        if self.phase == FocusPhase::Focus && phase != FocusPhase::Focus {
+            self.completed += 1;
+        }
+        self.phase = phase;
+        self.phase_ends_at = now + chrono::Duration::minutes(self.minutes(phase) as i64);
+    }
+
+    fn status(&self) -> FocusStatus {
+        FocusStatus {
+            phase: self.phase,
+            phase_ends_at: self.phase_ends_at,
+            remaining_secs: (self.phase_ends_at - Utc::now()).num_seconds().max(0) as u64,
+            completed_focus_phases: self.completed,
+        }
+    }
+}
+
+/// Returned by the focus commands and the payload of `focus-tick` and
+/// `focus-changed` (`null` once the session stops)
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusStatus {
+    pub phase: FocusPhase,
+    pub phase_ends_at: DateTime<Utc>,
+    pub remaining_secs: u64,
+    pub completed_focus_phases: u32,
+}
+
+fn emit_changed(app: &AppHandle, status: Option<&FocusStatus>) {
+    if let Err(e) = app.emit("focus-changed", status) {
+        warn!("Failed to emit focus-changed event: {}", e);
+    }
+}
+
+/// The phase and the time left, for the tray
+pub(crate) fn tray_status() -> Option<String> {
+    let status = SESSION.lock().ok()?.as_ref().map(FocusSession::status)?;
+    Some(format!("{} {}", status.phase.label(), tray::format_duration(status.remaining_secs)))
+}
+
+async fn set_dnd(active: bool) -> bool {
+    let result = async_runtime::spawn_blocking(move || {
+        // Leave a Do Not Disturb the user turned on alone
+        if active && dnd::dnd_active() == Some(true) {
+            return Ok(false);
+        }
+        dnd::set_dnd(active).map(|()| true)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|result| result);
+    match result {
+        Ok(changed) => changed,
+        Err(e) => {
+            warn!("Failed to turn Do Not Disturb {}: {}", if active { "on" } else { "off" }, e);
+            false
+        }
+    }
+}
+
+/// Announce the phase just entered, and turn Do Not Disturb off before a
+/// break so the notification gets through, or on after it for focus
+async fn announce(app: &AppHandle, phase: FocusPhase, minutes: u64, toggle_dnd: bool) {
+    if phase != FocusPhase::Focus {
+        let was_on = SESSION.lock().ok().and_then(|mut session| {
+            session.as_mut().map(|session| std::mem::replace(&mut session.dnd_on, false))
+        });
+        if was_on == Some(true) {
+            set_dnd(false).await;
+        }
+    }
+
+    let body = tr(Text::PhaseMinutes).replace("{minutes}", &minutes.to_string());
+    if let Err(e) = notifications::notify(app.clone(), phase.label().to_string(), body, None).await {
+        warn!("Failed to announce focus phase: {}", e);
+    }
+
+    if phase == FocusPhase::Focus && toggle_dnd && set_dnd(true).await {
+        let stopped = match SESSION.lock() {
+            Ok(mut session) => match session.as_mut() {
+                Some(session) => {
+                    session.dnd_on = true;
+                    false
+                }
+                None => true,
+            },
+            Err(_) => true,
+        };
+        // Stopped while Do Not Disturb was being turned on
+        if stopped {
+            set_dnd(false).await;
+        }
+    }
+}
+
+/// Move to the next phase and announce it
+async fn advance(app: &AppHandle) {
+    let entered = SESSION.lock().ok().and_then(|mut session| {
+        let session = session.as_mut()?;
+        let phase = session.next_phase();
+        session.enter(phase, Utc::now());
+        Some((session.status(), session.minutes(phase), session.settings.toggle_dnd))
+    });
+    let Some((status, minutes, toggle_dnd)) = entered else {
+        return;
+    };
+    info!("Focus session entered {:?} ({} min)", status.phase, minutes);
+    emit_changed(app, Some(&status));
+    announce(app, status.phase, minutes, toggle_dnd).await;
+}
+
+/// Count down the session started as `generation` until it is stopped or
+/// replaced
+fn run(app: AppHandle, generation: u64) {
+    async_runtime::spawn(async move {
+        loop {
+            sleep(TICK_INTERVAL).await;
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let due = SESSION
+                .lock()
+                .ok()
+                .and_then(|session| session.as_ref().map(|session| session.phase_ends_at <= Utc::now()));
+            match due {
+                Some(true) => advance(&app).await,
+                Some(false) => {}
+                None => return,
+            }
+            let status = SESSION.lock().ok().and_then(|session| session.as_ref().map(FocusSession::status));
+            if let Some(status) = status {
+                let _ = app.emit("focus-tick", &status);
+            }
+        }
+    });
+}
+
+/// Start a session with a focus phase. `work_minutes` and `break_minutes`
+/// override `focus.workMinutes` and `focus.shortBreakMinutes` for this
+/// session. A running session is replaced.
+#[tauri::command]
+pub async fn start_focus_session(
+    app: AppHandle,
+    work_minutes: Option<u64>,
+    break_minutes: Option<u64>,
+) -> Result<FocusStatus, String> {
+    let mut settings = app.state::<SettingsState>().current().focus;
+    if let Some(minutes) = work_minutes {
+        if !(1..=240).contains(&minutes) {
+            return Err("Focus phases must be between 1 and 240 minutes".to_string());
+        }
+        settings.work_minutes = minutes;
+    }
+    if let Some(minutes) = break_minutes {
+        if !(1..=120).contains(&minutes) {
+            return Err("Breaks must be between 1 and 120 minutes".to_string());
+        }
+        settings.short_break_minutes = minutes;
+    }
+    stop(&app).await;
+
+    let now = Utc::now();
+    let toggle_dnd = settings.toggle_dnd;
+    let mut session = FocusSession {
+        settings,
+        phase: FocusPhase::Focus,
+        phase_ends_at: now,
+        started_at: now,
+        completed: 0,
+        dnd_on: false,
+    };
+    session.enter(FocusPhase::Focus, now);
+    let minutes = session.minutes(FocusPhase::Focus);
+    let status = session.status();
+    let generation = {
+        let mut guard = SESSION.lock().map_err(|e| format!("Lock error: {}", e))?;
+        *guard = Some(session);
+        GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+    };
+
+    info!("Focus session started ({} min)", minutes);
+    emit_changed(&app, Some(&status));
+    run(app.clone(), generation);
+    announce(&app, FocusPhase::Focus, minutes, toggle_dnd).await;
+    Ok(status)
+}
+
+/// End the running session, if any, and undo what it changed
+async fn stop(app: &AppHandle) -> bool {
+    let session = {
+        let Ok(mut guard) = SESSION.lock() else {
+            return false;
+        };
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        guard.take()
+    };
+    let Some(session) = session else {
+        return false;
+    };
+    if session.dnd_on {
+        set_dnd(false).await;
+    }
+    telemetry::record(app, "focus_session", (Utc::now() - session.started_at).to_std().ok());
+    true
+}
+
+#[tauri::command]
+pub async fn stop_focus_session(app: AppHandle) -> Result<(), String> {
+    if !stop(&app).await {
+        return Err("No focus session is running".to_string());
+    }
+    info!("Focus session stopped");
+    emit_changed(&app, None);
+    Ok(())
+}
+
+/// End the current phase early and go on with the next one
+#[tauri::command]
+pub async fn skip_focus_phase(app: AppHandle) -> Result<FocusStatus, String> {
+    if SESSION.lock().map_err(|e| format!("Lock error: {}", e))?.is_none() {
+        return Err("No focus session is running".to_string());
+    }
+    advance(&app).await;
+    let guard = SESSION.lock().map_err(|e| format!("Lock error: {}", e))?;
+    guard
+        .as_ref()
+        .map(FocusSession::status)
+        .ok_or_else(|| "No focus session is running".to_string())
+}
+
+/// The running session, if any
+#[tauri::command]
+pub async fn get_focus_session() -> Result<Option<FocusStatus>, String> {
+    let guard = SESSION.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(guard.as_ref().map(FocusSession::status))
+}
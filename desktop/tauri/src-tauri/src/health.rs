@@ -0,0 +1,187 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::{backend_is_ready, backend_update, data_dir, BackendProcess, DesktopConfig};
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemHealth {
+    pub backend: BackendHealth,
+    pub disk: DiskHealth,
+    pub camera: CameraHealth,
+    pub ffmpeg: ToolHealth,
+    /// Speech recognition is authorized and the recognizer is ready
+    pub speech_available: bool,
+    pub permissions: Permissions,
+    /// Files waiting to be uploaded to the backend. `None`: captures are
+    /// uploaded directly, there is no upload queue yet.
+    pub pending_uploads: Option<usize>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendHealth {
+    /// The sidecar process is alive
+    pub running: bool,
+    /// The health endpoint answered
+    pub healthy: bool,
+    pub version: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealth {
+    pub data_dir: String,
+    pub free_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraHealth {
+    /// Capture is implemented on this platform
+    pub supported: bool,
+    pub available: bool,
+    pub devices: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolHealth {
+    pub available: bool,
+    pub path: Option<String>,
+}
+
+// Only macOS reports anything but `NotRequired`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    Restricted,
+    NotDetermined,
+    /// The platform doesn't gate this behind a permission
+    NotRequired,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Permissions {
+    pub camera: PermissionState,
+    pub microphone: PermissionState,
+    pub speech: PermissionState,
+}
+
+#[cfg(target_os = "macos")]
+impl PermissionState {
+    /// Decode the state reported by the speech bridge
+    fn from_code(code: i32) -> Self {
+        match code {
+            3 => PermissionState::Granted,
+            2 => PermissionState::Restricted,
+            1 => PermissionState::Denied,
+            _ => PermissionState::NotDetermined,
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn permissions() -> Permissions {
+    let [camera, microphone, speech] = crate::speech::permission_states().map(PermissionState::from_code);
+    Permissions { camera, microphone, speech }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+fn permissions() -> Permissions {
+    Permissions {
+        camera: PermissionState::NotRequired,
+        microphone: PermissionState::NotRequired,
+        speech: PermissionState::NotRequired,
+    }
+}
+
+#[cfg(target_os = "macos")]
+async fn camera() -> CameraHealth {
+    let devices = crate::media::list_cameras().await.unwrap_or_default();
+    CameraHealth {
+        supported: true,
+        available: !devices.is_empty(),
+        devices,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+async fn camera() -> CameraHealth {
+    CameraHealth {
+        supported: false,
+        available: false,
+        devices: Vec::new(),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+async fn speech_available() -> bool {
+    crate::speech::is_speech_available().await.unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+async fn speech_available() -> bool {
+    false
+}
+
+/// `name` on the PATH the app runs with, which is what video recording spawns
+pub(crate) fn find_on_path(name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+}
+
+/// Free space on the volume holding `dir`; the dir itself may not exist yet
+fn disk(dir: &Path) -> DiskHealth {
+    let existing = dir.ancestors().find(|path| path.exists()).unwrap_or(dir);
+    DiskHealth {
+        data_dir: dir.to_string_lossy().to_string(),
+        free_bytes: fs2::available_space(existing).ok(),
+        total_bytes: fs2::total_space(existing).ok(),
+    }
+}
+
+/// Everything a status page needs in one call: backend, disk, capture devices,
+/// tools and permissions
+#[tauri::command]
+pub async fn get_system_health(app: AppHandle) -> Result<SystemHealth, String> {
+    let url = app.state::<DesktopConfig>().health_url.clone();
+    let data_dir = data_dir::current_data_dir(&app);
+
+    let healthy = backend_is_ready(&url).await;
+    let (disk, ffmpeg) = async_runtime::spawn_blocking(move || (disk(&data_dir), find_on_path("ffmpeg")))
+        .await
+        .map_err(|e| format!("Health check task failed: {}", e))?;
+
+    Ok(SystemHealth {
+        backend: BackendHealth {
+            running: app.state::<BackendProcess>().is_running(),
+            healthy,
+            version: backend_update::current_backend_version(&app),
+            url,
+        },
+        disk,
+        camera: camera().await,
+        ffmpeg: ToolHealth {
+            available: ffmpeg.is_some(),
+            path: ffmpeg.map(|path| path.to_string_lossy().to_string()),
+        },
+        speech_available: speech_available().await,
+        permissions: permissions(),
+        pending_uploads: None,
+        checked_at: Utc::now(),
+    })
+}
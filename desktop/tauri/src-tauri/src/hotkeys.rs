@@ -0,0 +1,346 @@
+//! System-wide keyboard shortcuts. Each action takes one accelerator such as
+//! `CommandOrControl+Shift+Space`, stored in the `hotkeys` settings section
+//! and registered with the OS through the global-shortcut plugin. Changing
+//! a binding rebinds it at once. The OS refuses a shortcut another app
+//! already holds; that is reported as a conflict instead of failing
+//! silently.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::cli::{self, CliCommand};
+use crate::permissions::{self, ACTIVITY};
+use crate::settings::{self, HotkeySettings, SettingsState};
+use crate::{activity, focus_main_window, privacy_guard};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum HotkeyAction {
+    /// Show and focus the window, or hide it when it is in front
+    ToggleWindow,
+    /// Bring the window forward for a new entry
+    QuickCapture,
+    /// Start a recording, or stop the running one
+    ToggleRecording,
+    /// Dictate while the keys are held (macOS)
+    PushToTalk,
+    /// Add an entry from the template in `hotkeys.templateId`
+    TemplateEntry,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 5] = [
+        HotkeyAction::ToggleWindow,
+        HotkeyAction::QuickCapture,
+        HotkeyAction::ToggleRecording,
+        HotkeyAction::PushToTalk,
+        HotkeyAction::TemplateEntry,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleWindow => "toggle-window",
+            HotkeyAction::QuickCapture => "quick-capture",
+            HotkeyAction::ToggleRecording => "toggle-recording",
+            HotkeyAction::PushToTalk => "push-to-talk",
+            HotkeyAction::TemplateEntry => "template-entry",
+        }
+    }
+
+    fn binding(self, hotkeys: &HotkeySettings) -> Option<&str> {
+        match self {
+            HotkeyAction::ToggleWindow => hotkeys.toggle_window.as_deref(),
+            HotkeyAction::QuickCapture => hotkeys.quick_capture.as_deref(),
+            HotkeyAction::ToggleRecording => hotkeys.toggle_recording.as_deref(),
+            HotkeyAction::PushToTalk => hotkeys.push_to_talk.as_deref(),
+            HotkeyAction::TemplateEntry => hotkeys.template_entry.as_deref(),
+        }
+    }
+
+    fn binding_mut(self, hotkeys: &mut HotkeySettings) -> &mut Option<String> {
+        match self {
+            HotkeyAction::ToggleWindow => &mut hotkeys.toggle_window,
+            HotkeyAction::QuickCapture => &mut hotkeys.quick_capture,
+            HotkeyAction::ToggleRecording => &mut hotkeys.toggle_recording,
+            HotkeyAction::PushToTalk => &mut hotkeys.push_to_talk,
+            HotkeyAction::TemplateEntry => &mut hotkeys.template_entry,
+        }
+    }
+}
+
+/// Payload of `hotkey`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HotkeyEvent {
+    action: HotkeyAction,
+    /// `pressed` or `released`
+    state: &'static str,
+    /// What was in front when `quick-capture` was pressed, with
+    /// `hotkeys.captureContext` on
+    context: Option<CaptureContext>,
+}
+
+/// The app the user was in when they reached for `quick-capture`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CaptureContext {
+    app: String,
+    /// Title of its front window
+    title: Option<String>,
+    /// Address of the page, when the app is a browser that can be asked (macOS)
+    url: Option<String>,
+}
+
+/// Entry of `list_hotkeys`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyBinding {
+    pub action: HotkeyAction,
+    pub accelerator: Option<String>,
+    /// Held by this app right now
+    pub registered: bool,
+    /// Why the OS refused the accelerator, usually another app holding it
+    pub conflict: Option<String>,
+}
+
+#[derive(Default)]
+struct Registered {
+    shortcuts: BTreeMap<HotkeyAction, Shortcut>,
+    conflicts: BTreeMap<HotkeyAction, String>,
+}
+
+/// Shortcuts registered with the OS. Managed once the window exists, so a
+/// headless app never grabs keys.
+#[derive(Default)]
+pub struct HotkeyState(Mutex<Registered>);
+
+fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse()
+        .map_err(|e| format!("Invalid shortcut {:?}: {}", accelerator, e))
+}
+
+/// Every binding must parse, and no two actions may share one
+pub fn validate(hotkeys: &HotkeySettings) -> Result<(), String> {
+    let mut seen: Vec<(HotkeyAction, Shortcut)> = Vec::new();
+    for action in HotkeyAction::ALL {
+        let Some(accelerator) = action.binding(hotkeys) else {
+            continue;
+        };
+        let shortcut = parse(accelerator)?;
+        if let Some((other, _)) = seen.iter().find(|(_, existing)| *existing == shortcut) {
+            return Err(format!("{} is already used by {}", accelerator, other.name()));
+        }
+        seen.push((action, shortcut));
+    }
+    Ok(())
+}
+
+/// Bring the OS registrations in line with `hotkeys`. Unchanged bindings
+/// are left registered. A refused binding is remembered as a conflict.
+pub fn apply(app: &AppHandle, hotkeys: &HotkeySettings) {
+    let Some(state) = app.try_state::<HotkeyState>() else {
+        return;
+    };
+    let Ok(mut registered) = state.0.lock() else {
+        return;
+    };
+    let global = app.global_shortcut();
+    for action in HotkeyAction::ALL {
+        let wanted = action.binding(hotkeys).and_then(|accelerator| parse(accelerator).ok());
+        if wanted.is_some() && registered.shortcuts.get(&action) == wanted.as_ref() {
+            continue;
+        }
+        if let Some(old) = registered.shortcuts.remove(&action) {
+            if let Err(e) = global.unregister(old) {
+                warn!("Failed to unregister {} shortcut: {}", action.name(), e);
+            }
+        }
+        registered.conflicts.remove(&action);
+        let Some(shortcut) = wanted else {
+            continue;
+        };
+        match global.register(shortcut) {
+            Ok(()) => {
+                info!("Registered {} shortcut", action.name());
+                registered.shortcuts.insert(action, shortcut);
+            }
+            Err(e) => {
+                warn!("Couldn't register {} shortcut: {}", action.name(), e);
+                registered.conflicts.insert(action, e.to_string());
+            }
+        }
+    }
+}
+
+/// Add an entry from the shortcut's template in the background; the result
+/// is shown as a notification
+fn template_entry(app: &AppHandle) {
+    match app.state::<SettingsState>().current().hotkeys.template_id {
+        Some(id) => cli::dispatch(
+            app,
+            CliCommand::Template {
+                id,
+                vars: Default::default(),
+            },
+        ),
+        None => warn!("The template-entry shortcut has no template; set hotkeys.templateId"),
+    }
+}
+
+fn emit(app: &AppHandle, action: HotkeyAction, pressed: bool, context: Option<CaptureContext>) {
+    let event = HotkeyEvent {
+        action,
+        state: if pressed { "pressed" } else { "released" },
+        context,
+    };
+    if let Err(e) = app.emit("hotkey", event) {
+        warn!("Failed to emit hotkey event: {}", e);
+    }
+}
+
+/// Bring the window forward for a new entry. With `hotkeys.captureContext`
+/// on, the frontmost app is read first, before the window takes its place,
+/// and the browser is asked for its URL in the background; `hotkey` is
+/// emitted once that is known. Nothing is read while the window itself is in
+/// front or the privacy guard blocks captures.
+fn quick_capture(app: &AppHandle) {
+    let settings = app.state::<SettingsState>().current();
+    let in_front = app
+        .get_webview_window("main")
+        .is_some_and(|window| window.is_focused().unwrap_or(false));
+    let wanted = settings.hotkeys.capture_context && permissions::is_enabled(app, ACTIVITY) && !in_front;
+    let frontmost = if wanted { activity::frontmost() } else { None };
+    focus_main_window(app);
+
+    let app = app.clone();
+    let guard = settings.privacy.guard;
+    async_runtime::spawn(async move {
+        let context = async_runtime::spawn_blocking(move || {
+            let (name, title) = frontmost?;
+            if privacy_guard::blocking_reason(&guard, Some(&name)).is_some() {
+                return None;
+            }
+            let url = activity::browser_url(&name);
+            Some(CaptureContext { app: name, title, url })
+        })
+        .await
+        .ok()
+        .flatten();
+        emit(&app, HotkeyAction::QuickCapture, true, context);
+    });
+}
+
+fn toggle_window(app: &AppHandle) {
+    match app.get_webview_window("main") {
+        Some(window) if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) => {
+            let _ = window.hide();
+        }
+        _ => focus_main_window(app),
+    }
+}
+
+/// Called by the plugin for every press and release of a registered shortcut
+pub fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    let action = app.try_state::<HotkeyState>().and_then(|state| {
+        let registered = state.0.lock().ok()?;
+        registered
+            .shortcuts
+            .iter()
+            .find(|(_, registered)| *registered == shortcut)
+            .map(|(action, _)| *action)
+    });
+    let Some(action) = action else {
+        return;
+    };
+    let pressed = event.state() == ShortcutState::Pressed;
+    match action {
+        HotkeyAction::ToggleWindow if pressed => toggle_window(app),
+        HotkeyAction::QuickCapture if pressed => {
+            // Emits `hotkey` itself, once the context is known
+            quick_capture(app);
+            return;
+        }
+        HotkeyAction::TemplateEntry if pressed => template_entry(app),
+        #[cfg(target_os = "macos")]
+        HotkeyAction::PushToTalk if pressed => crate::speech::push_to_talk_pressed(app),
+        #[cfg(target_os = "macos")]
+        HotkeyAction::PushToTalk => crate::speech::push_to_talk_released(app),
+        _ => {}
+    }
+    emit(app, action, pressed, None);
+}
+
+/// Register the shortcuts from the settings
+pub fn init_hotkeys(app: &AppHandle) {
+    app.manage(HotkeyState::default());
+    apply(app, &app.state::<SettingsState>().current().hotkeys);
+}
+
+/// Bind `action` to `accelerator`, or unbind it with `null`, and save it.
+/// Fails without changing anything when the shortcut is invalid, taken by
+/// another action or refused by the OS.
+#[tauri::command]
+pub async fn set_hotkey(
+    app: AppHandle,
+    state: State<'_, SettingsState>,
+    action: HotkeyAction,
+    accelerator: Option<String>,
+) -> Result<HotkeyBinding, String> {
+    let accelerator = accelerator.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+    let mut updated = state.current();
+    *action.binding_mut(&mut updated.hotkeys) = accelerator.clone();
+    validate(&updated.hotkeys)?;
+
+    apply(&app, &updated.hotkeys);
+    let conflict = app
+        .try_state::<HotkeyState>()
+        .and_then(|hotkeys| hotkeys.0.lock().ok()?.conflicts.get(&action).cloned());
+    if let Some(conflict) = conflict {
+        // Put the previous binding back
+        apply(&app, &state.current().hotkeys);
+        return Err(format!(
+            "{} is taken by another app or the system: {}",
+            accelerator.unwrap_or_default(),
+            conflict
+        ));
+    }
+
+    settings::update(&app, &state, |file| *action.binding_mut(&mut file.hotkeys) = accelerator.clone())?;
+    info!("Shortcut for {} set to {:?}", action.name(), accelerator);
+    let registered = accelerator.is_some() && app.try_state::<HotkeyState>().is_some();
+    Ok(HotkeyBinding {
+        action,
+        accelerator,
+        registered,
+        conflict: None,
+    })
+}
+
+/// Every action with its binding and whether the OS accepted it
+#[tauri::command]
+pub async fn list_hotkeys(app: AppHandle, state: State<'_, SettingsState>) -> Result<Vec<HotkeyBinding>, String> {
+    let hotkeys = state.current().hotkeys;
+    let (shortcuts, conflicts) = match app.try_state::<HotkeyState>() {
+        Some(hotkeys) => {
+            let registered = hotkeys.0.lock().map_err(|e| format!("Lock error: {}", e))?;
+            (registered.shortcuts.clone(), registered.conflicts.clone())
+        }
+        // Headless
+        None => Default::default(),
+    };
+    Ok(HotkeyAction::ALL
+        .into_iter()
+        .map(|action| HotkeyBinding {
+            action,
+            accelerator: action.binding(&hotkeys).map(str::to_string),
+            registered: shortcuts.contains_key(&action),
+            conflict: conflicts.get(&action).cloned(),
+        })
+        .collect())
+}
@@ -0,0 +1,178 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::time::sleep;
+
+// How often system idle time is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+// Thresholds below this would flap between idle and active during normal use
+pub const MIN_THRESHOLD_SECS: u64 = 30;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleStatus {
+    pub idle: bool,
+    /// Seconds since the last keyboard/mouse input, when the platform can report it
+    pub idle_seconds: Option<u64>,
+    pub threshold_secs: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IdleEvent {
+    /// For `user-idle`: seconds without input. For `user-active`: how long the user was away.
+    idle_seconds: u64,
+}
+
+pub struct IdleMonitor {
+    threshold_secs: AtomicU64,
+    idle: AtomicBool,
+    last_idle_seconds: AtomicU64,
+}
+
+impl IdleMonitor {
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    /// Seconds without input as of the last sample
+    pub fn idle_seconds(&self) -> u64 {
+        self.last_idle_seconds.load(Ordering::Relaxed)
+    }
+
+    pub fn set_threshold_secs(&self, seconds: u64) {
+        self.threshold_secs.store(seconds.max(MIN_THRESHOLD_SECS), Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn system_idle_seconds() -> Option<u64> {
+    // CGEventSourceStateID::CombinedSessionState and kCGAnyInputEventType
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT_TYPE: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    }
+
+    let seconds =
+        unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT_TYPE) };
+    (seconds.is_finite() && seconds >= 0.0).then_some(seconds as u64)
+}
+
+#[cfg(target_os = "windows")]
+fn system_idle_seconds() -> Option<u64> {
+    #[repr(C)]
+    struct LastInputInfo {
+        cb_size: u32,
+        dw_time: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(plii: *mut LastInputInfo) -> i32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    let mut info = LastInputInfo {
+        cb_size: std::mem::size_of::<LastInputInfo>() as u32,
+        dw_time: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    // Both tick counts wrap after ~49 days, wrapping_sub keeps the difference correct
+    let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dw_time);
+    Some(u64::from(idle_ms) / 1000)
+}
+
+#[cfg(target_os = "linux")]
+fn system_idle_seconds() -> Option<u64> {
+    let xss = crate::x11_session::xss()?;
+    crate::x11_session::with_display(|xlib, display| unsafe {
+        let root = (xlib.XDefaultRootWindow)(display);
+        let mut info: x11_dl::xss::XScreenSaverInfo = std::mem::zeroed();
+        let status = (xss.XScreenSaverQueryInfo)(display, root, &mut info);
+        (status != 0).then_some(info.idle as u64 / 1000)
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn system_idle_seconds() -> Option<u64> {
+    None
+}
+
+/// Start sampling system idle time, emitting `user-idle` once input has been
+/// absent for the threshold and `user-active` when input resumes.
+pub fn init_idle_monitor(app: &AppHandle, threshold: Duration) {
+    let threshold_secs = threshold.as_secs().max(MIN_THRESHOLD_SECS);
+    info!("Idle monitor threshold: {}s", threshold_secs);
+    app.manage(IdleMonitor {
+        threshold_secs: AtomicU64::new(threshold_secs),
+        idle: AtomicBool::new(false),
+        last_idle_seconds: AtomicU64::new(0),
+    });
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(SAMPLE_INTERVAL).await;
+
+            let Some(idle_seconds) = system_idle_seconds() else {
+                continue;
+            };
+            let monitor = app.state::<IdleMonitor>();
+            let threshold_secs = monitor.threshold_secs.load(Ordering::Relaxed);
+            let was_idle = monitor.idle.load(Ordering::Relaxed);
+
+            if !was_idle && idle_seconds >= threshold_secs {
+                info!("User idle for {}s", idle_seconds);
+                monitor.idle.store(true, Ordering::Relaxed);
+                if let Err(e) = app.emit("user-idle", IdleEvent { idle_seconds }) {
+                    warn!("Failed to emit user-idle event: {}", e);
+                }
+                crate::timer::on_idle(&app, idle_seconds);
+            } else if was_idle && idle_seconds < threshold_secs {
+                // The last sample before input resumed is the best estimate of time away
+                let away = monitor.last_idle_seconds.load(Ordering::Relaxed);
+                info!("User active again after ~{}s", away);
+                monitor.idle.store(false, Ordering::Relaxed);
+                if let Err(e) = app.emit("user-active", IdleEvent { idle_seconds: away }) {
+                    warn!("Failed to emit user-active event: {}", e);
+                }
+                crate::timer::on_active(&app);
+            }
+            monitor.last_idle_seconds.store(idle_seconds, Ordering::Relaxed);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_idle_status(monitor: State<'_, IdleMonitor>) -> Result<IdleStatus, String> {
+    Ok(IdleStatus {
+        idle: monitor.idle.load(Ordering::Relaxed),
+        idle_seconds: system_idle_seconds(),
+        threshold_secs: monitor.threshold_secs.load(Ordering::Relaxed),
+    })
+}
+
+#[tauri::command]
+pub async fn set_idle_threshold(
+    monitor: State<'_, IdleMonitor>,
+    seconds: u64,
+) -> Result<u64, String> {
+    if seconds < MIN_THRESHOLD_SECS {
+        return Err(format!("Idle threshold must be at least {} seconds", MIN_THRESHOLD_SECS));
+    }
+    monitor.set_threshold_secs(seconds);
+    info!("Idle threshold set to {}s", seconds);
+    Ok(seconds)
+}
@@ -0,0 +1,157 @@
+//! Per-install overrides of the backend address and the data dir, for
+//! packaged builds where `.tourienv` isn't read. They are resolved on top of
+//! `settings.json`, highest first:
+//!
+//! 1. launch flags: `--backend-host=<host>`, `--backend-port=<port>`, `--data-dir=<path>`
+//! 2. `config.toml` in the app config dir
+//! 3. the environment: `TAURI_BACKEND_HOST`, `TAURI_BACKEND_PORT` and
+//!    `TAURI_DESKTOP_DATA_DIR` as the app was launched with (in debug builds,
+//!    every variable of `.tourienv`)
+//! 4. `settings.json`, then the built-in defaults
+//!
+//! ```toml
+//! data_dir = "/Volumes/Data/TrackTheThing"
+//!
+//! [backend]
+//! host = "127.0.0.1"
+//! port = 18800
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use log::warn;
+use serde::Deserialize;
+
+use crate::settings::Settings;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Settings keys these layers can replace; release builds read only these
+/// from the environment
+pub const KEYS: &[&str] = &["backend.host", "backend.port", "media.dataDir"];
+
+const HOST_FLAG: &str = "--backend-host=";
+const PORT_FLAG: &str = "--backend-port=";
+const DATA_DIR_FLAG: &str = "--data-dir=";
+
+/// The environment before `load_production_env` replaces it
+static LAUNCH_ENV: OnceLock<Overrides> = OnceLock::new();
+
+/// One layer of overrides; unset fields leave the layer below alone
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    backend: BackendOverrides,
+    data_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct BackendOverrides {
+    host: Option<String>,
+    port: Option<u16>,
+}
+
+impl Overrides {
+    /// Drop values the settings would reject, naming `origin` in the warning
+    fn validated(mut self, origin: &str) -> Self {
+        if self.backend.host.as_deref().is_some_and(|host| host.trim().is_empty()) {
+            warn!("Ignoring the empty backend host from {}", origin);
+            self.backend.host = None;
+        }
+        if self.backend.port == Some(0) {
+            warn!("Ignoring backend port 0 from {}", origin);
+            self.backend.port = None;
+        }
+        if let Some(dir) = self.data_dir.take() {
+            if dir.is_absolute() {
+                self.data_dir = Some(dir);
+            } else {
+                warn!("Ignoring data dir {} from {}: it must be an absolute path", dir.display(), origin);
+            }
+        }
+        self
+    }
+
+    /// Apply to `settings` and return the keys (as in `settings::KNOBS`) replaced
+    pub fn apply(&self, settings: &mut Settings) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if let Some(host) = &self.backend.host {
+            settings.backend.host = host.trim().to_string();
+            applied.push("backend.host");
+        }
+        if let Some(port) = self.backend.port {
+            settings.backend.port = port;
+            applied.push("backend.port");
+        }
+        if let Some(dir) = &self.data_dir {
+            settings.media.data_dir = Some(dir.clone());
+            applied.push("media.dataDir");
+        }
+        applied
+    }
+}
+
+/// Remember the launch environment; call before anything sets `TAURI_*`
+pub fn init() {
+    LAUNCH_ENV.get_or_init(|| {
+        let port = env::var("TAURI_BACKEND_PORT").ok();
+        let overrides = Overrides {
+            backend: BackendOverrides {
+                host: env::var("TAURI_BACKEND_HOST").ok(),
+                port: port.as_deref().and_then(|port| match port.trim().parse() {
+                    Ok(port) => Some(port),
+                    Err(_) => {
+                        warn!("Ignoring TAURI_BACKEND_PORT={:?}: not a port", port);
+                        None
+                    }
+                }),
+            },
+            data_dir: env::var_os("TAURI_DESKTOP_DATA_DIR").map(PathBuf::from),
+        };
+        overrides.validated("the environment")
+    });
+}
+
+/// Overrides from the environment the app was launched with
+pub fn environment() -> Overrides {
+    LAUNCH_ENV.get().cloned().unwrap_or_default()
+}
+
+/// Overrides from `config.toml` next to `settings.json`. A file that can't be
+/// parsed is ignored as a whole.
+pub fn config_file(config_dir: &Path) -> Overrides {
+    let path = config_dir.join(CONFIG_FILE);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Overrides::default();
+    };
+    match toml::from_str::<Overrides>(&content) {
+        Ok(overrides) => overrides.validated(CONFIG_FILE),
+        Err(e) => {
+            warn!("Ignoring {}: {}", path.display(), e);
+            Overrides::default()
+        }
+    }
+}
+
+/// Overrides from the launch flags. Only the `--flag=value` form is read, so
+/// a path value is never taken for a file to open.
+pub fn launch_args() -> Overrides {
+    let mut overrides = Overrides::default();
+    for arg in env::args().skip(1) {
+        if let Some(host) = arg.strip_prefix(HOST_FLAG) {
+            overrides.backend.host = Some(host.to_string());
+        } else if let Some(port) = arg.strip_prefix(PORT_FLAG) {
+            match port.parse() {
+                Ok(port) => overrides.backend.port = Some(port),
+                Err(_) => warn!("Ignoring {}: not a port", arg),
+            }
+        } else if let Some(dir) = arg.strip_prefix(DATA_DIR_FLAG) {
+            overrides.data_dir = Some(env::current_dir().map(|cwd| cwd.join(dir)).unwrap_or_else(|_| dir.into()));
+        }
+    }
+    overrides.validated("the command line")
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+const LINKS_FILE: &str = "calendar_events.json";
+
+const DEFAULT_DURATION_MINUTES: u32 = 30;
+
+// Which variants are reported depends on the platform
+#[allow(dead_code)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CalendarPermission {
+    NotDetermined,
+    Restricted,
+    Denied,
+    Granted,
+    /// Events can be added but not read back, so updates create duplicates
+    WriteOnly,
+    /// ICS export doesn't need calendar access
+    NotRequired,
+}
+
+/// A tracked item's due date, as a calendar event
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarEventInput {
+    pub item_id: String,
+    pub title: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Link back to the item, e.g. a `trackthething://` deep link
+    #[serde(default)]
+    pub url: Option<String>,
+    pub due: DateTime<Utc>,
+    #[serde(default)]
+    pub all_day: bool,
+    #[serde(default)]
+    pub duration_minutes: Option<u32>,
+    /// Alert this many minutes before the event starts
+    #[serde(default)]
+    pub reminder_minutes: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarEventRef {
+    pub item_id: String,
+    pub event_id: String,
+    /// Path of the exported .ics file on platforms without a calendar API
+    pub ics_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LinkedEvent {
+    event_id: String,
+    /// ICS SEQUENCE, bumped on every update so calendar apps replace the old copy
+    #[serde(default)]
+    sequence: u32,
+}
+
+/// Which calendar event belongs to which tracked item, persisted so later
+/// due-date changes update the same event instead of adding another
+pub struct CalendarLinks {
+    links: Mutex<HashMap<String, LinkedEvent>>,
+    store_path: Option<PathBuf>,
+}
+
+impl CalendarLinks {
+    fn load(app: &AppHandle) -> Self {
+        let store_path = app.path().app_data_dir().ok().map(|dir| dir.join(LINKS_FILE));
+        let links = store_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(links) => Some(links),
+                Err(e) => {
+                    warn!("Failed to parse calendar links: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            links: Mutex::new(links),
+            store_path,
+        }
+    }
+
+    fn persist(&self, links: &HashMap<String, LinkedEvent>) {
+        let Some(path) = &self.store_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let json = match serde_json::to_string_pretty(links) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize calendar links: {}", e);
+                return;
+            }
+        };
+        let tmp_path = path.with_extension("json.tmp");
+        if let Err(e) = fs::write(&tmp_path, json).and_then(|_| fs::rename(&tmp_path, path)) {
+            warn!("Failed to write calendar links: {}", e);
+        }
+    }
+}
+
+/// Start and end of the event. All-day events cover the due date in local time.
+fn event_span(event: &CalendarEventInput) -> (DateTime<Utc>, DateTime<Utc>) {
+    if event.all_day {
+        let date = event.due.with_timezone(&Local).date_naive();
+        let start = local_midnight(date).unwrap_or(event.due);
+        (start, start + Duration::days(1))
+    } else {
+        let minutes = event.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+        (event.due, event.due + Duration::minutes(i64::from(minutes)))
+    }
+}
+
+fn local_midnight(date: NaiveDate) -> Option<DateTime<Utc>> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_int};
+
+    use super::{event_span, CalendarEventInput, CalendarPermission};
+
+    extern "C" {
+        fn calendar_authorization_status() -> c_int;
+        fn calendar_request_access(error_out: *mut *mut c_char) -> bool;
+        fn calendar_save_event(
+            event_id: *const c_char,
+            title: *const c_char,
+            notes: *const c_char,
+            url: *const c_char,
+            start_timestamp: f64,
+            end_timestamp: f64,
+            all_day: bool,
+            reminder_minutes: c_int,
+            error_out: *mut *mut c_char,
+        ) -> *mut c_char;
+        fn calendar_remove_event(event_id: *const c_char, error_out: *mut *mut c_char) -> bool;
+        fn calendar_free_string(value: *mut c_char);
+    }
+
+    fn c_string(value: &str) -> Result<CString, String> {
+        CString::new(value).map_err(|e| format!("Invalid calendar text: {}", e))
+    }
+
+    fn optional_c_string(value: Option<&str>) -> Result<Option<CString>, String> {
+        value.map(c_string).transpose()
+    }
+
+    fn as_ptr(value: &Option<CString>) -> *const c_char {
+        value.as_ref().map_or(std::ptr::null(), |value| value.as_ptr())
+    }
+
+    unsafe fn take_string(ptr: *mut c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        let value = CStr::from_ptr(ptr).to_string_lossy().to_string();
+        calendar_free_string(ptr);
+        Some(value)
+    }
+
+    pub fn permission() -> CalendarPermission {
+        match unsafe { calendar_authorization_status() } {
+            0 => CalendarPermission::NotDetermined,
+            1 => CalendarPermission::Restricted,
+            2 => CalendarPermission::Denied,
+            4 => CalendarPermission::WriteOnly,
+            _ => CalendarPermission::Granted,
+        }
+    }
+
+    pub fn request_permission() -> Result<CalendarPermission, String> {
+        let mut error: *mut c_char = std::ptr::null_mut();
+        if !unsafe { calendar_request_access(&mut error) } {
+            let message = unsafe { take_string(error) };
+            log::warn!(
+                "Calendar access not granted: {}",
+                message.as_deref().unwrap_or("unknown reason")
+            );
+        }
+        Ok(permission())
+    }
+
+    pub fn save_event(existing_id: Option<&str>, event: &CalendarEventInput) -> Result<String, String> {
+        if !matches!(permission(), CalendarPermission::Granted | CalendarPermission::WriteOnly) {
+            return Err("Calendar access has not been granted".to_string());
+        }
+
+        let (start, end) = event_span(event);
+        let event_id = optional_c_string(existing_id)?;
+        let title = c_string(&event.title)?;
+        let notes = optional_c_string(event.notes.as_deref())?;
+        let url = optional_c_string(event.url.as_deref())?;
+        let reminder = event.reminder_minutes.map_or(-1, |minutes| minutes.min(c_int::MAX as u32) as c_int);
+
+        let mut error: *mut c_char = std::ptr::null_mut();
+        unsafe {
+            let saved = calendar_save_event(
+                as_ptr(&event_id),
+                title.as_ptr(),
+                as_ptr(&notes),
+                as_ptr(&url),
+                start.timestamp() as f64,
+                end.timestamp() as f64,
+                event.all_day,
+                reminder,
+                &mut error,
+            );
+            take_string(saved).ok_or_else(|| {
+                take_string(error).unwrap_or_else(|| "Failed to save calendar event".to_string())
+            })
+        }
+    }
+
+    pub fn remove_event(event_id: &str) -> Result<(), String> {
+        let event_id = c_string(event_id)?;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        unsafe {
+            if calendar_remove_event(event_id.as_ptr(), &mut error) {
+                Ok(())
+            } else {
+                Err(take_string(error).unwrap_or_else(|| "Failed to remove calendar event".to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod ics {
+    use chrono::{DateTime, Local, Utc};
+
+    use super::{event_span, CalendarEventInput};
+
+    /// Escape TEXT values per RFC 5545 §3.3.11
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\\n")
+            .replace('\n', "\\n")
+    }
+
+    /// Fold content lines longer than 75 octets without splitting a UTF-8 character
+    fn fold(line: &str) -> String {
+        let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+        let mut width = 0;
+        for ch in line.chars() {
+            if width + ch.len_utf8() > 75 {
+                folded.push_str("\r\n ");
+                width = 1;
+            }
+            folded.push(ch);
+            width += ch.len_utf8();
+        }
+        folded
+    }
+
+    fn utc_stamp(value: DateTime<Utc>) -> String {
+        value.format("%Y%m%dT%H%M%SZ").to_string()
+    }
+
+    fn local_date(value: DateTime<Utc>) -> String {
+        value.with_timezone(&Local).format("%Y%m%d").to_string()
+    }
+
+    pub fn render(uid: &str, sequence: u32, event: &CalendarEventInput) -> String {
+        let (start, end) = event_span(event);
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//Track the Thing//Desktop//EN".to_string(),
+            "METHOD:PUBLISH".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", uid),
+            format!("SEQUENCE:{}", sequence),
+            format!("DTSTAMP:{}", utc_stamp(Utc::now())),
+        ];
+        if event.all_day {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", local_date(start)));
+            lines.push(format!("DTEND;VALUE=DATE:{}", local_date(end)));
+        } else {
+            lines.push(format!("DTSTART:{}", utc_stamp(start)));
+            lines.push(format!("DTEND:{}", utc_stamp(end)));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&event.title)));
+        if let Some(notes) = &event.notes {
+            lines.push(format!("DESCRIPTION:{}", escape(notes)));
+        }
+        if let Some(url) = &event.url {
+            lines.push(format!("URL:{}", url));
+        }
+        if let Some(minutes) = event.reminder_minutes {
+            lines.extend([
+                "BEGIN:VALARM".to_string(),
+                "ACTION:DISPLAY".to_string(),
+                format!("DESCRIPTION:{}", escape(&event.title)),
+                format!("TRIGGER:-PT{}M", minutes),
+                "END:VALARM".to_string(),
+            ]);
+        }
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut content = lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("\r\n");
+        content.push_str("\r\n");
+        content
+    }
+}
+
+pub fn init_calendar(app: &AppHandle) {
+    app.manage(CalendarLinks::load(app));
+}
+
+#[tauri::command]
+pub async fn get_calendar_permission() -> Result<CalendarPermission, String> {
+    #[cfg(target_os = "macos")]
+    return Ok(native::permission());
+
+    #[cfg(not(target_os = "macos"))]
+    Ok(CalendarPermission::NotRequired)
+}
+
+/// Show the system calendar permission prompt (macOS) if it hasn't been answered yet
+#[tauri::command]
+pub async fn request_calendar_permission() -> Result<CalendarPermission, String> {
+    #[cfg(target_os = "macos")]
+    return tokio::task::spawn_blocking(native::request_permission)
+        .await
+        .map_err(|e| format!("Calendar permission task panicked: {}", e))?;
+
+    #[cfg(not(target_os = "macos"))]
+    Ok(CalendarPermission::NotRequired)
+}
+
+/// Create the calendar event for a tracked item, or update it if one was
+/// created before. On macOS this writes to the default calendar through
+/// EventKit; elsewhere an .ics file is exported and opened with the calendar app.
+#[tauri::command]
+pub async fn upsert_calendar_event(
+    #[cfg_attr(target_os = "macos", allow(unused_variables))] app: AppHandle,
+    links: State<'_, CalendarLinks>,
+    event: CalendarEventInput,
+) -> Result<CalendarEventRef, String> {
+    if event.title.trim().is_empty() {
+        return Err("Calendar event needs a title".to_string());
+    }
+    let existing = links
+        .links
+        .lock()
+        .map_err(|e| format!("Lock error: {}", e))?
+        .get(&event.item_id)
+        .cloned();
+
+    #[cfg(target_os = "macos")]
+    let (linked, ics_path) = {
+        let existing_id = existing.map(|linked| linked.event_id);
+        let input = event.clone();
+        let event_id = tokio::task::spawn_blocking(move || native::save_event(existing_id.as_deref(), &input))
+            .await
+            .map_err(|e| format!("Calendar task panicked: {}", e))??;
+        (LinkedEvent { event_id, sequence: 0 }, None)
+    };
+
+    #[cfg(not(target_os = "macos"))]
+    let (linked, ics_path) = {
+        use tauri_plugin_opener::OpenerExt;
+
+        let linked = match existing {
+            Some(linked) => LinkedEvent {
+                sequence: linked.sequence + 1,
+                ..linked
+            },
+            None => LinkedEvent {
+                event_id: format!("{}@trackthething", event.item_id),
+                sequence: 0,
+            },
+        };
+
+        // Item ids come from the webview, keep them from escaping the export dir
+        let file_stem: String = event
+            .item_id
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+            .collect();
+        let path = crate::media_store::get_media_dir(&app, "calendar")?.join(format!("{}.ics", file_stem));
+        fs::write(&path, ics::render(&linked.event_id, linked.sequence, &event))
+            .map_err(|e| format!("Failed to write calendar file: {}", e))?;
+        if let Err(e) = app.opener().open_path(path.to_string_lossy(), None::<&str>) {
+            warn!("Failed to open calendar file: {}", e);
+        }
+        (linked, Some(path.to_string_lossy().to_string()))
+    };
+
+    info!("Calendar event {} saved for item {}", linked.event_id, event.item_id);
+    let mut map = links.links.lock().map_err(|e| format!("Lock error: {}", e))?;
+    map.insert(event.item_id.clone(), linked.clone());
+    links.persist(&map);
+
+    Ok(CalendarEventRef {
+        item_id: event.item_id,
+        event_id: linked.event_id,
+        ics_path,
+    })
+}
+
+/// Forget (and on macOS delete) the calendar event linked to a tracked item
+#[tauri::command]
+pub async fn remove_calendar_event(
+    links: State<'_, CalendarLinks>,
+    item_id: String,
+) -> Result<bool, String> {
+    let linked = links
+        .links
+        .lock()
+        .map_err(|e| format!("Lock error: {}", e))?
+        .get(&item_id)
+        .cloned();
+    let Some(linked) = linked else {
+        return Ok(false);
+    };
+
+    #[cfg(target_os = "macos")]
+    {
+        let event_id = linked.event_id.clone();
+        tokio::task::spawn_blocking(move || native::remove_event(&event_id))
+            .await
+            .map_err(|e| format!("Calendar task panicked: {}", e))??;
+    }
+
+    info!("Calendar event {} removed for item {}", linked.event_id, item_id);
+    let mut map = links.links.lock().map_err(|e| format!("Lock error: {}", e))?;
+    map.remove(&item_id);
+    links.persist(&map);
+    Ok(true)
+}
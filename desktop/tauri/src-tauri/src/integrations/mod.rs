@@ -0,0 +1,3 @@
+//! Integrations with other apps on the user's machine
+
+pub mod calendar;
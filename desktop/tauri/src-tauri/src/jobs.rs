@@ -0,0 +1,482 @@
+//! Background jobs: exports, backups, remote backup uploads, media uploads,
+//! caption exports, watch-folder imports and transcriptions go through one
+//! queue instead of each spawning its own task. Jobs run highest priority
+//! first, at most `MAX_RUNNING` at a time and one per kind. The queue is saved to `jobs.json`, so jobs queued or running when
+//! the app quits run again at the next start, once the backend is up.
+//!
+//! A job reports how far it is with `job-progress` and ends with
+//! `job-finished`, or with `job-cancelled` after `cancel_job(id)`. Work checks
+//! its `CancelToken` between steps (a month of an export, a file of an upload)
+//! and reads and writes through `Cancellable`, so archiving and uploading stop
+//! mid-file. Files half written when a job is cancelled are removed like
+//! after any other failure.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+
+use crate::event_batch;
+use crate::export::{ExportFormat, ExportRange};
+use crate::watch_folders::WatchFolder;
+
+/// Error message of work stopped by `cancel_job`
+pub const CANCELLED: &str = "Cancelled";
+
+const STATE_FILE: &str = "jobs.json";
+
+/// Jobs running at once; jobs of the same kind also wait for each other
+const MAX_RUNNING: usize = 2;
+
+pub type JobId = u64;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Queued and running jobs by id
+static JOBS: Mutex<BTreeMap<JobId, Job>> = Mutex::new(BTreeMap::new());
+
+/// Set once the backend is ready; jobs only queue up until then
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Set by `cancel_job`; the default token is never cancelled
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// `Err(CANCELLED)` once cancelled, for `?` between steps
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A reader or writer that fails with `CANCELLED` once its token is set
+pub struct Cancellable<T> {
+    inner: T,
+    cancel: CancelToken,
+}
+
+impl<T> Cancellable<T> {
+    pub fn new(inner: T, cancel: &CancelToken) -> Self {
+        Self {
+            inner,
+            cancel: cancel.clone(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Other, CANCELLED));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read> Read for Cancellable<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl<T: Write> Write for Cancellable<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// What a job does, as saved in `jobs.json`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JobKind {
+    /// `start_export`
+    Export {
+        format: ExportFormat,
+        range: Option<ExportRange>,
+        dest: Option<String>,
+    },
+    /// `start_backup` and scheduled backups
+    Backup,
+    /// Send the backups pending for the remote target
+    Upload,
+    /// A new file in a watched folder
+    Import { folder: WatchFolder, source: PathBuf },
+    /// `upload_media`
+    MediaUpload { source: PathBuf },
+    /// `export_video_with_captions`
+    Captions { video: PathBuf, vtt: PathBuf, dest: PathBuf },
+    /// A finished recording, or `transcribe_media`
+    Transcription { source: PathBuf },
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Export { .. } => "export",
+            JobKind::Backup => "backup",
+            JobKind::Upload => "upload",
+            JobKind::Import { .. } => "import",
+            JobKind::MediaUpload { .. } => "mediaUpload",
+            JobKind::Captions { .. } => "captions",
+            JobKind::Transcription { .. } => "transcription",
+        }
+    }
+
+    /// Queuing one of these while another waits would only repeat its work
+    fn coalesces(&self) -> bool {
+        matches!(self, JobKind::Backup | JobKind::Upload)
+    }
+
+    /// An export without a destination would open a save dialog out of
+    /// nowhere after a restart
+    fn persists(&self) -> bool {
+        !matches!(self, JobKind::Export { dest: None, .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Priority {
+    /// Scheduled backups and uploads
+    Low,
+    /// Watch-folder imports
+    #[default]
+    Normal,
+    /// Started from the webview
+    High,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub done: u64,
+    /// 0 until known
+    pub total: u64,
+}
+
+struct Job {
+    kind: JobKind,
+    priority: Priority,
+    running: bool,
+    progress: Option<JobProgress>,
+    cancel: CancelToken,
+}
+
+/// An entry of `jobs.json`
+#[derive(Serialize, Deserialize)]
+struct SavedJob {
+    id: JobId,
+    priority: Priority,
+    #[serde(flatten)]
+    kind: JobKind,
+}
+
+/// A queued or running job, from `list_jobs`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: JobId,
+    /// `JobKind::name`, e.g. `export`
+    pub kind: &'static str,
+    pub priority: Priority,
+    pub running: bool,
+    pub progress: Option<JobProgress>,
+}
+
+/// Payload of `job-cancelled`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JobEnded {
+    id: JobId,
+    kind: &'static str,
+}
+
+/// An entry of `job-progress`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ProgressUpdate {
+    id: JobId,
+    kind: &'static str,
+    #[serde(flatten)]
+    progress: JobProgress,
+}
+
+/// Payload of `job-finished`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JobFinished {
+    id: JobId,
+    kind: &'static str,
+    /// What the matching one-shot command would have returned
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+/// Reports how far a job is; the default reports nothing, for work run
+/// outside the queue
+#[derive(Clone, Default)]
+pub struct Progress(Option<(AppHandle, JobId, &'static str)>);
+
+impl Progress {
+    pub fn report(&self, done: u64, total: u64) {
+        let Some((app, id, kind)) = &self.0 else {
+            return;
+        };
+        let progress = JobProgress { done, total };
+        if let Ok(mut jobs) = JOBS.lock() {
+            if let Some(job) = jobs.get_mut(id) {
+                job.progress = Some(progress);
+            }
+        }
+        event_batch::emit(app, "job-progress", ProgressUpdate { id: *id, kind, progress });
+    }
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(STATE_FILE))
+}
+
+fn save(app: &AppHandle, jobs: &BTreeMap<JobId, Job>) {
+    let Some(path) = state_path(app) else {
+        return;
+    };
+    let saved: Vec<SavedJob> = jobs
+        .iter()
+        .filter(|(_, job)| job.kind.persists())
+        .map(|(&id, job)| SavedJob {
+            id,
+            priority: job.priority,
+            kind: job.kind.clone(),
+        })
+        .collect();
+    let result = serde_json::to_string_pretty(&saved)
+        .map_err(|e| format!("Failed to serialize {}: {}", STATE_FILE, e))
+        .and_then(|json| crate::prefs::write_atomic(&path, &json));
+    if let Err(e) = result {
+        warn!("Failed to save the job queue: {}", e);
+    }
+}
+
+/// Load the jobs left from the last run. They wait for `start_queue`.
+pub fn init_jobs(app: &AppHandle) {
+    let saved: Vec<SavedJob> = state_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| {
+            serde_json::from_str(&content)
+                .map_err(|e| warn!("Failed to parse {}: {}", STATE_FILE, e))
+                .ok()
+        })
+        .unwrap_or_default();
+    if saved.is_empty() {
+        return;
+    }
+    let Ok(mut jobs) = JOBS.lock() else {
+        return;
+    };
+    for job in saved {
+        NEXT_ID.fetch_max(job.id + 1, Ordering::SeqCst);
+        jobs.insert(
+            job.id,
+            Job {
+                kind: job.kind,
+                priority: job.priority,
+                running: false,
+                progress: None,
+                cancel: CancelToken::default(),
+            },
+        );
+    }
+    info!("Resuming {} job(s) from the last run", jobs.len());
+}
+
+/// Start running jobs; called once the backend answers
+pub fn start_queue(app: &AppHandle) {
+    STARTED.store(true, Ordering::SeqCst);
+    schedule(app);
+}
+
+/// Queue a job and return its id. A backup or upload already waiting is
+/// reused instead, raised to `priority` if that is higher.
+pub fn enqueue(app: &AppHandle, kind: JobKind, priority: Priority) -> Result<JobId, String> {
+    let mut jobs = JOBS.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let waiting = jobs
+        .iter_mut()
+        .find(|(_, job)| kind.coalesces() && !job.running && job.kind.name() == kind.name());
+    if let Some((&id, job)) = waiting {
+        job.priority = job.priority.max(priority);
+        save(app, &jobs);
+        return Ok(id);
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    info!("Queued job {} ({}, {:?} priority)", id, kind.name(), priority);
+    jobs.insert(
+        id,
+        Job {
+            kind,
+            priority,
+            running: false,
+            progress: None,
+            cancel: CancelToken::default(),
+        },
+    );
+    save(app, &jobs);
+    drop(jobs);
+    schedule(app);
+    Ok(id)
+}
+
+/// Start waiting jobs while there is room. The battery saver holds back all
+/// but `High` priority jobs.
+pub(crate) fn schedule(app: &AppHandle) {
+    if !STARTED.load(Ordering::SeqCst) {
+        return;
+    }
+    let Ok(mut jobs) = JOBS.lock() else {
+        return;
+    };
+    let saving = crate::power::saving();
+    loop {
+        let running: Vec<&'static str> = jobs
+            .values()
+            .filter(|job| job.running)
+            .map(|job| job.kind.name())
+            .collect();
+        if running.len() >= MAX_RUNNING {
+            return;
+        }
+        // Highest priority first, then the oldest
+        let next = jobs
+            .iter_mut()
+            .filter(|(_, job)| !job.running && !running.contains(&job.kind.name()))
+            .filter(|(_, job)| !saving || job.priority == Priority::High)
+            .max_by_key(|(id, job)| (job.priority, Reverse(**id)));
+        let Some((&id, job)) = next else {
+            return;
+        };
+        job.running = true;
+        let (kind, cancel) = (job.kind.clone(), job.cancel.clone());
+        async_runtime::spawn(run_job(app.clone(), id, kind, cancel));
+    }
+}
+
+async fn run(app: &AppHandle, kind: JobKind, cancel: CancelToken, progress: Progress) -> Result<Value, String> {
+    let value = match kind {
+        JobKind::Export { format, range, dest } => {
+            serde_json::to_value(crate::export::run_export(app.clone(), format, range, dest, cancel, progress).await?)
+        }
+        JobKind::Backup => serde_json::to_value(crate::backup::backup(app, cancel).await?),
+        JobKind::Upload => serde_json::to_value(crate::backup::upload_pending(app, cancel, progress).await?),
+        // A single copy, so it isn't interrupted
+        JobKind::Import { folder, source } => {
+            serde_json::to_value(crate::watch_folders::ingest(app, &folder, source).await?)
+        }
+        JobKind::MediaUpload { source } => Ok(crate::media_upload::upload(app, source, cancel, progress).await?),
+        JobKind::Captions { video, vtt, dest } => {
+            serde_json::to_value(crate::captions::export(video, vtt, dest, cancel, progress).await?)
+        }
+        JobKind::Transcription { source } => {
+            serde_json::to_value(crate::transcription::transcribe(app, source, cancel, progress).await?)
+        }
+    };
+    value.map_err(|e| format!("Failed to serialize the job result: {}", e))
+}
+
+async fn run_job(app: AppHandle, id: JobId, kind: JobKind, cancel: CancelToken) {
+    let name = kind.name();
+    info!("Job {} ({}) started", id, name);
+    let progress = Progress(Some((app.clone(), id, name)));
+    let result = run(&app, kind, cancel.clone(), progress).await;
+    if let Ok(mut jobs) = JOBS.lock() {
+        jobs.remove(&id);
+        save(&app, &jobs);
+    }
+
+    // Work that finished before it saw the cancel still counts
+    if result.is_err() && cancel.is_cancelled() {
+        info!("Job {} ({}) cancelled", id, name);
+        if let Err(e) = app.emit("job-cancelled", JobEnded { id, kind: name }) {
+            warn!("Failed to emit job-cancelled: {}", e);
+        }
+    } else {
+        if let Err(e) = &result {
+            warn!("Job {} ({}) failed: {}", id, name, e);
+        }
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        let finished = JobFinished {
+            id,
+            kind: name,
+            result,
+            error,
+        };
+        if let Err(e) = app.emit("job-finished", finished) {
+            warn!("Failed to emit job-finished: {}", e);
+        }
+    }
+    schedule(&app);
+}
+
+/// Ask a job to stop. A running job ends with `job-cancelled` at its next
+/// check; a queued one is dropped at once.
+#[tauri::command]
+pub async fn cancel_job(app: AppHandle, id: JobId) -> Result<(), String> {
+    let mut jobs = JOBS.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let job = jobs.get(&id).ok_or_else(|| format!("No queued or running job {}", id))?;
+    info!("Cancelling job {} ({})", id, job.kind.name());
+    if job.running {
+        job.cancel.cancel();
+        return Ok(());
+    }
+    let kind = job.kind.name();
+    jobs.remove(&id);
+    save(&app, &jobs);
+    if let Err(e) = app.emit("job-cancelled", JobEnded { id, kind }) {
+        warn!("Failed to emit job-cancelled: {}", e);
+    }
+    Ok(())
+}
+
+/// Queued and running jobs, in the order they were queued
+#[tauri::command]
+pub async fn list_jobs() -> Result<Vec<JobInfo>, String> {
+    let jobs = JOBS.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(jobs
+        .iter()
+        .map(|(&id, job)| JobInfo {
+            id,
+            kind: job.kind.name(),
+            priority: job.priority,
+            running: job.running,
+            progress: job.progress,
+        })
+        .collect())
+}
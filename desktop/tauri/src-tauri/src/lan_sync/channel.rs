@@ -0,0 +1,188 @@
+//! Wire format between paired installs. Every request and response body after
+//! pairing is sealed with ChaCha20-Poly1305 under the key the two installs
+//! agreed on, so traffic on the LAN is both private and authenticated.
+
+use std::io::Read;
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+/// Header naming the install a sealed request comes from
+pub const DEVICE_HEADER: &str = "X-Sync-Device";
+
+/// Sealed requests sent longer ago than this (or this far in the future) are
+/// refused, which bounds replays
+pub const MAX_CLOCK_SKEW_SECS: i64 = 5 * 60;
+
+const NONCE_LEN: usize = 12;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest response read from a peer; media files are the big ones
+pub const MAX_RESPONSE_BYTES: u64 = 256 * 1024 * 1024;
+
+pub type ChannelKey = [u8; 32];
+
+/// A sealed request body: the payload and when it was sent
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    pub sent_at: i64,
+    pub body: T,
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.is_ascii() || text.len() % 2 != 0 {
+        return Err("Invalid hex".to_string());
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| format!("Invalid hex: {}", e)))
+        .collect()
+}
+
+fn hmac(key: &[u8], parts: &[&str]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part.as_bytes());
+        mac.update(b"\n");
+    }
+    mac
+}
+
+/// Hex HMAC-SHA256 of `parts` under `key`
+pub fn mac(key: &[u8], parts: &[&str]) -> String {
+    to_hex(&hmac(key, parts).finalize().into_bytes())
+}
+
+/// Constant-time check of a `mac` result
+pub fn verify_mac(key: &[u8], parts: &[&str], expected: &str) -> bool {
+    from_hex(expected).is_ok_and(|expected| hmac(key, parts).verify_slice(&expected).is_ok())
+}
+
+/// The channel key, from the secret both sides derived while pairing
+pub fn channel_key(shared: &[u8]) -> ChannelKey {
+    hmac(shared, &["track-the-thing sync channel"]).finalize().into_bytes().into()
+}
+
+pub fn request_aad(path: &str, from: &str) -> String {
+    format!("request\n{}\n{}", path, from)
+}
+
+pub fn response_aad(path: &str, to: &str) -> String {
+    format!("response\n{}\n{}", path, to)
+}
+
+/// Random nonce followed by the ciphertext
+pub fn seal(key: &ChannelKey, aad: &str, plaintext: &[u8]) -> Vec<u8> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let payload = Payload {
+        msg: plaintext,
+        aad: aad.as_bytes(),
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .expect("ChaCha20-Poly1305 encryption is infallible for in-memory buffers");
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    sealed
+}
+
+pub fn open(key: &ChannelKey, aad: &str, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("Sealed message is too short".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let payload = Payload {
+        msg: ciphertext,
+        aad: aad.as_bytes(),
+    };
+    cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| "Message failed authentication".to_string())
+}
+
+/// Open a sealed request and check it's recent
+pub fn open_request<T: DeserializeOwned>(key: &ChannelKey, path: &str, from: &str, sealed: &[u8]) -> Result<T, String> {
+    let plaintext = open(key, &request_aad(path, from), sealed)?;
+    let envelope: Envelope<T> = serde_json::from_slice(&plaintext).map_err(|e| format!("Malformed request: {}", e))?;
+    if (Utc::now().timestamp() - envelope.sent_at).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err("Request is too old; check both computers' clocks".to_string());
+    }
+    Ok(envelope.body)
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
+
+fn read_response(response: ureq::Response) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read peer response: {}", e))?;
+    if bytes.len() as u64 > MAX_RESPONSE_BYTES {
+        return Err("Peer response is too large".to_string());
+    }
+    Ok(bytes)
+}
+
+fn peer_error(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let text = response.into_string().unwrap_or_default();
+            if text.is_empty() {
+                format!("Peer answered {}", status)
+            } else {
+                text
+            }
+        }
+        ureq::Error::Transport(e) => format!("Peer is not reachable: {}", e),
+    }
+}
+
+/// Unsealed JSON request, used only while pairing
+pub fn post_plain(address: &str, path: &str, body: &Value) -> Result<Value, String> {
+    let response = agent()
+        .post(&format!("http://{}{}", address, path))
+        .send_json(body)
+        .map_err(peer_error)?;
+    serde_json::from_slice(&read_response(response)?).map_err(|e| format!("Malformed peer response: {}", e))
+}
+
+/// Sealed request to a paired peer; returns the opened response body
+pub fn post_sealed(address: &str, device_id: &str, key: &ChannelKey, path: &str, body: &Value) -> Result<Vec<u8>, String> {
+    let envelope = Envelope {
+        sent_at: Utc::now().timestamp(),
+        body,
+    };
+    let plaintext = serde_json::to_vec(&envelope).map_err(|e| format!("Failed to serialize request: {}", e))?;
+    let response = agent()
+        .post(&format!("http://{}{}", address, path))
+        .set(DEVICE_HEADER, device_id)
+        .set("Content-Type", "application/octet-stream")
+        .send_bytes(&seal(key, &request_aad(path, device_id), &plaintext))
+        .map_err(peer_error)?;
+    open(key, &response_aad(path, device_id), &read_response(response)?)
+}
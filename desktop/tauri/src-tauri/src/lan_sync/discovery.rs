@@ -0,0 +1,93 @@
+//! mDNS advertising and browsing for other installs on the local network
+
+use std::net::{IpAddr, SocketAddr};
+use std::thread;
+
+use log::{debug, info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use tauri::{AppHandle, Manager};
+
+use super::{emit_peers_changed, DiscoveredPeer, LanSyncState};
+
+const SERVICE_TYPE: &str = "_trackthething._tcp.local.";
+
+/// Bumped when installs can no longer sync with older ones
+pub const PROTOCOL_VERSION: &str = "1";
+
+/// Prefer IPv4: link-local IPv6 addresses need a scope the URL can't carry
+fn best_address(info: &ServiceInfo) -> Option<SocketAddr> {
+    let addresses = info.get_addresses();
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or_else(|| addresses.iter().find(|address| matches!(address, IpAddr::V6(v6) if !v6.is_loopback())))
+        .map(|address| SocketAddr::new(*address, info.get_port()))
+}
+
+fn resolved(app: &AppHandle, own_id: &str, info: &ServiceInfo) {
+    let Some(id) = info.get_property_val_str("id") else {
+        return;
+    };
+    if id == own_id || info.get_property_val_str("v") != Some(PROTOCOL_VERSION) {
+        return;
+    }
+    let Some(address) = best_address(info) else {
+        return;
+    };
+    let peer = DiscoveredPeer {
+        id: id.to_string(),
+        name: info.get_property_val_str("name").unwrap_or(id).to_string(),
+        address: address.to_string(),
+        fullname: info.get_fullname().to_string(),
+    };
+    debug!("Found sync peer {} at {}", peer.name, peer.address);
+    let state = app.state::<LanSyncState>();
+    if let Ok(mut discovered) = state.discovered.lock() {
+        discovered.insert(peer.id.clone(), peer);
+    }
+    emit_peers_changed(app);
+}
+
+fn removed(app: &AppHandle, fullname: &str) {
+    let state = app.state::<LanSyncState>();
+    if let Ok(mut discovered) = state.discovered.lock() {
+        discovered.retain(|_, peer| peer.fullname != fullname);
+    }
+    emit_peers_changed(app);
+}
+
+/// Advertise this install and watch for others. Shutting the daemon down ends
+/// the browse thread.
+pub fn start(app: &AppHandle, device_id: &str, device_name: &str, port: u16) -> Result<ServiceDaemon, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| format!("mDNS unavailable: {}", e))?;
+    let instance = format!("ttt-{}", device_id);
+    let host = format!("{}.local.", instance);
+    let properties = [("id", device_id), ("name", device_name), ("v", PROTOCOL_VERSION)];
+    let service = ServiceInfo::new(SERVICE_TYPE, &instance, &host, "", port, &properties[..])
+        .map_err(|e| format!("Invalid mDNS service: {}", e))?
+        .enable_addr_auto();
+    daemon
+        .register(service)
+        .map_err(|e| format!("Failed to advertise on the local network: {}", e))?;
+    let events = daemon
+        .browse(SERVICE_TYPE)
+        .map_err(|e| format!("Failed to browse the local network: {}", e))?;
+
+    let app = app.clone();
+    let own_id = device_id.to_string();
+    let spawned = thread::Builder::new().name("lan-sync-discovery".into()).spawn(move || {
+        while let Ok(event) = events.recv() {
+            match event {
+                ServiceEvent::ServiceResolved(info) => resolved(&app, &own_id, &info),
+                ServiceEvent::ServiceRemoved(_, fullname) => removed(&app, &fullname),
+                _ => {}
+            }
+        }
+        debug!("Sync discovery stopped");
+    });
+    if let Err(e) = spawned {
+        warn!("Failed to start sync discovery thread: {}", e);
+    }
+    info!("Advertising LAN sync as {} on port {}", device_name, port);
+    Ok(daemon)
+}
@@ -0,0 +1,782 @@
+//! Opt-in sync between desktop installs on the same local network. Installs
+//! find each other over mDNS, pair once with a six-digit code (SPAKE2, so the
+//! code can't be guessed from captured traffic) and then pull each other's
+//! entry changes over a sealed channel. The backend reads and applies the
+//! changes; see `backend/app/sync.py`.
+
+mod channel;
+mod discovery;
+mod server;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use mdns_sd::ServiceDaemon;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+
+use crate::settings::{SettingsState, SyncSettings};
+use crate::{backend_client, data_dir, prefs, DesktopConfig};
+use channel::ChannelKey;
+
+const PEERS_FILE: &str = "sync_peers.json";
+
+/// Index `n` upgrades a schema `n` sync_peers.json to `n + 1`
+const PEERS_MIGRATIONS: &[prefs::Migration] = &[
+    // 0 -> 1: first versioned layout
+    Ok,
+];
+
+const KEYCHAIN_SERVICE: &str = "com.trackthething.desktop";
+
+/// SPAKE2 identity shared by every install
+const PAIRING_IDENTITY: &[u8] = b"track-the-thing-sync";
+
+/// How long a pairing code stays valid
+const PAIRING_TTL: chrono::Duration = chrono::Duration::minutes(2);
+
+/// Wrong codes accepted before the pairing window closes
+const MAX_PAIRING_ATTEMPTS: u32 = 5;
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Set while changes are pulled from a peer
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedPeer {
+    pub id: String,
+    pub name: String,
+    pub paired_at: DateTime<Utc>,
+    pub last_sync: Option<DateTime<Utc>>,
+    /// The peer's `now` from the last sync, passed back as `since`
+    pub cursor: Option<String>,
+    /// Where the peer was last reached, used while mDNS hasn't found it yet
+    pub last_address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct SyncPeers {
+    schema_version: u32,
+    /// This install's ID, generated on first use
+    device_id: String,
+    peers: Vec<PairedPeer>,
+}
+
+/// An install seen on the network
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredPeer {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    /// mDNS instance name, matched when the service goes away
+    #[serde(skip)]
+    pub fullname: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerStatus {
+    #[serde(flatten)]
+    pub peer: PairedPeer,
+    pub online: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub enabled: bool,
+    pub device_id: String,
+    /// `None` while sync is off
+    pub device_name: Option<String>,
+    pub port: Option<u16>,
+    pub pairing_expires_at: Option<DateTime<Utc>>,
+    pub peers: Vec<PeerStatus>,
+    /// Installs on the network that aren't paired yet
+    pub discovered: Vec<DiscoveredPeer>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingCode {
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    #[serde(default)]
+    pub peer_id: String,
+    pub created: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    pub skipped: u64,
+    /// Upload files fetched from the peer
+    #[serde(default)]
+    pub media: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SyncFailure {
+    peer_id: String,
+    error: String,
+}
+
+struct PendingPeer {
+    id: String,
+    name: String,
+    shared: Vec<u8>,
+}
+
+/// Open after `start_sync_pairing`, until a peer pairs, the code expires or
+/// too many wrong codes were tried
+struct PairingWindow {
+    code: String,
+    expires_at: DateTime<Utc>,
+    attempts: u32,
+    pending: Option<PendingPeer>,
+}
+
+struct Running {
+    daemon: ServiceDaemon,
+    server: Arc<tiny_http::Server>,
+    /// `sync.port` it was started with (0 = any)
+    requested_port: u16,
+    port: u16,
+    name: String,
+}
+
+pub struct LanSyncState {
+    path: PathBuf,
+    peers: Mutex<SyncPeers>,
+    /// Channel keys read from the keychain, by peer ID
+    keys: Mutex<HashMap<String, ChannelKey>>,
+    discovered: Mutex<HashMap<String, DiscoveredPeer>>,
+    pairing: Mutex<Option<PairingWindow>>,
+    running: Mutex<Option<Running>>,
+}
+
+fn keychain_entry(peer_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &format!("sync-peer-{}", peer_id))
+        .map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+fn load_peers(path: &Path) -> SyncPeers {
+    let mut peers = match prefs::load_versioned(path, PEERS_MIGRATIONS, None) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", PEERS_FILE, e);
+            SyncPeers::default()
+        }),
+        Ok(None) => SyncPeers::default(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", PEERS_FILE, e);
+            SyncPeers::default()
+        }
+    };
+    peers.schema_version = PEERS_MIGRATIONS.len() as u32;
+    if peers.device_id.is_empty() {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        peers.device_id = channel::to_hex(&bytes);
+        // Saved straight away so the ID other installs see stays the same
+        let saved = serde_json::to_string_pretty(&peers)
+            .map_err(|e| format!("Failed to serialize {}: {}", PEERS_FILE, e))
+            .and_then(|json| prefs::write_atomic(path, &json));
+        if let Err(e) = saved {
+            warn!("Failed to save sync peers: {}", e);
+        }
+    }
+    peers
+}
+
+/// Device IDs are 16 random bytes, hex encoded
+fn valid_device_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl LanSyncState {
+    fn save(&self, peers: &SyncPeers) {
+        let result = serde_json::to_string_pretty(peers)
+            .map_err(|e| format!("Failed to serialize {}: {}", PEERS_FILE, e))
+            .and_then(|json| prefs::write_atomic(&self.path, &json));
+        if let Err(e) = result {
+            warn!("Failed to save sync peers: {}", e);
+        }
+    }
+
+    fn device_id(&self) -> String {
+        self.peers.lock().map(|peers| peers.device_id.clone()).unwrap_or_default()
+    }
+
+    fn peer(&self, peer_id: &str) -> Option<PairedPeer> {
+        let peers = self.peers.lock().ok()?;
+        peers.peers.iter().find(|peer| peer.id == peer_id).cloned()
+    }
+
+    fn update_peer(&self, peer_id: &str, update: impl FnOnce(&mut PairedPeer)) {
+        let Ok(mut peers) = self.peers.lock() else {
+            return;
+        };
+        if let Some(peer) = peers.peers.iter_mut().find(|peer| peer.id == peer_id) {
+            update(peer);
+        }
+        self.save(&peers);
+    }
+
+    /// Remember a peer and its channel key
+    fn add_peer(&self, id: &str, name: &str, key: ChannelKey, address: Option<String>) -> Result<PairedPeer, String> {
+        keychain_entry(id)?
+            .set_password(&channel::to_hex(&key))
+            .map_err(|e| format!("Failed to store the sync key in the keychain: {}", e))?;
+        if let Ok(mut keys) = self.keys.lock() {
+            keys.insert(id.to_string(), key);
+        }
+        let peer = PairedPeer {
+            id: id.to_string(),
+            name: name.to_string(),
+            paired_at: Utc::now(),
+            last_sync: None,
+            cursor: None,
+            last_address: address,
+        };
+        let mut peers = self.peers.lock().map_err(|_| "Sync peers are unavailable".to_string())?;
+        peers.peers.retain(|existing| existing.id != id);
+        peers.peers.push(peer.clone());
+        self.save(&peers);
+        Ok(peer)
+    }
+
+    /// The channel key shared with a paired peer
+    fn key(&self, peer_id: &str) -> Result<ChannelKey, String> {
+        if let Some(key) = self.keys.lock().ok().and_then(|keys| keys.get(peer_id).copied()) {
+            return Ok(key);
+        }
+        if self.peer(peer_id).is_none() {
+            return Err(format!("{} isn't paired", peer_id));
+        }
+        let hex = keychain_entry(peer_id)?
+            .get_password()
+            .map_err(|e| format!("Failed to read the sync key from the keychain: {}", e))?;
+        let key: ChannelKey = channel::from_hex(&hex)?
+            .try_into()
+            .map_err(|_| "Sync key in the keychain is malformed".to_string())?;
+        if let Ok(mut keys) = self.keys.lock() {
+            keys.insert(peer_id.to_string(), key);
+        }
+        Ok(key)
+    }
+
+    fn address(&self, peer: &PairedPeer) -> Option<String> {
+        self.discovered
+            .lock()
+            .ok()
+            .and_then(|discovered| discovered.get(&peer.id).map(|found| found.address.clone()))
+            .or_else(|| peer.last_address.clone())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.lock().is_ok_and(|running| running.is_some())
+    }
+
+    fn status(&self, enabled: bool) -> SyncStatus {
+        let discovered: HashMap<String, DiscoveredPeer> =
+            self.discovered.lock().map(|discovered| discovered.clone()).unwrap_or_default();
+        let (device_id, paired) = self
+            .peers
+            .lock()
+            .map(|peers| (peers.device_id.clone(), peers.peers.clone()))
+            .unwrap_or_default();
+        let (device_name, port) = self
+            .running
+            .lock()
+            .ok()
+            .and_then(|running| running.as_ref().map(|running| (running.name.clone(), running.port)))
+            .unzip();
+        let pairing_expires_at = self
+            .pairing
+            .lock()
+            .ok()
+            .and_then(|pairing| pairing.as_ref().map(|window| window.expires_at))
+            .filter(|expires_at| *expires_at > Utc::now());
+
+        let mut unpaired: Vec<DiscoveredPeer> = discovered
+            .values()
+            .filter(|found| !paired.iter().any(|peer| peer.id == found.id))
+            .cloned()
+            .collect();
+        unpaired.sort_by(|a, b| a.name.cmp(&b.name));
+        SyncStatus {
+            enabled,
+            device_id,
+            device_name,
+            port,
+            pairing_expires_at,
+            peers: paired
+                .into_iter()
+                .map(|peer| PeerStatus {
+                    online: discovered.contains_key(&peer.id),
+                    peer,
+                })
+                .collect(),
+            discovered: unpaired,
+        }
+    }
+
+    /// First pairing step on the install showing the code: derive the shared
+    /// secret from the other install's SPAKE2 message. Returns our message.
+    fn accept_pairing(&self, peer_id: &str, name: &str, message: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let mut pairing = self.pairing.lock().map_err(|_| "Pairing is unavailable".to_string())?;
+        let window = pairing.as_mut().ok_or("Not pairing; start pairing on this computer first")?;
+        if window.expires_at <= Utc::now() || window.attempts >= MAX_PAIRING_ATTEMPTS {
+            *pairing = None;
+            return Err("The pairing code has expired; start pairing again".to_string());
+        }
+        window.attempts += 1;
+        let (spake, ours) =
+            Spake2::<Ed25519Group>::start_symmetric(&Password::new(&window.code), &Identity::new(PAIRING_IDENTITY));
+        let shared = spake.finish(message).map_err(|e| format!("Invalid pairing message: {:?}", e))?;
+        window.pending = Some(PendingPeer {
+            id: peer_id.to_string(),
+            name: name.to_string(),
+            shared: shared.clone(),
+        });
+        Ok((ours, shared))
+    }
+
+    /// Second pairing step: the other install proves it derived the same
+    /// secret, i.e. knew the code
+    fn confirm_pairing(&self, peer_id: &str, confirm: &str) -> Result<PairedPeer, String> {
+        let own_id = self.device_id();
+        let mut pairing = self.pairing.lock().map_err(|_| "Pairing is unavailable".to_string())?;
+        let pending = pairing
+            .as_mut()
+            .and_then(|window| window.pending.take())
+            .filter(|pending| pending.id == peer_id)
+            .ok_or("No pairing in progress for this computer")?;
+        if !channel::verify_mac(&pending.shared, &["client", peer_id, &own_id], confirm) {
+            return Err("Wrong pairing code".to_string());
+        }
+        *pairing = None;
+        drop(pairing);
+        let peer = self.add_peer(&pending.id, &pending.name, channel::channel_key(&pending.shared), None)?;
+        info!("Paired with {} for LAN sync", peer.name);
+        Ok(peer)
+    }
+}
+
+fn emit_peers_changed(app: &AppHandle) {
+    let Some(state) = app.try_state::<LanSyncState>() else {
+        return;
+    };
+    let enabled = app.state::<SettingsState>().current().sync.enabled;
+    if let Err(e) = app.emit("sync-peers-changed", state.status(enabled)) {
+        warn!("Failed to emit sync-peers-changed event: {}", e);
+    }
+}
+
+/// The settings name, else the computer's
+fn device_name(settings: &SyncSettings) -> String {
+    settings.device_name.clone().unwrap_or_else(|| {
+        let host = gethostname::gethostname().to_string_lossy().to_string();
+        host.strip_suffix(".local").unwrap_or(&host).to_string()
+    })
+}
+
+fn uploads_dir(app: &AppHandle) -> PathBuf {
+    env::var_os("TAURI_UPLOADS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data_dir::current_data_dir(app).join("uploads"))
+}
+
+/// Upload file names as the backend generates them; nothing that could leave
+/// the uploads directory
+fn valid_media_name(name: &str) -> bool {
+    name.len() <= 255
+        && name.chars().next().is_some_and(|first| first.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn stop(state: &LanSyncState) {
+    let Some(running) = state.running.lock().ok().and_then(|mut running| running.take()) else {
+        return;
+    };
+    running.server.unblock();
+    if let Err(e) = running.daemon.shutdown() {
+        warn!("Failed to stop mDNS: {}", e);
+    }
+    if let Ok(mut discovered) = state.discovered.lock() {
+        discovered.clear();
+    }
+    if let Ok(mut pairing) = state.pairing.lock() {
+        *pairing = None;
+    }
+    info!("LAN sync stopped");
+}
+
+fn start(app: &AppHandle, state: &LanSyncState, settings: &SyncSettings) -> Result<(), String> {
+    let name = device_name(settings);
+    let server = tiny_http::Server::http(("0.0.0.0", settings.port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", settings.port, e))?;
+    let server = Arc::new(server);
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|address| address.port())
+        .ok_or("Sync server has no TCP address")?;
+    let daemon = match discovery::start(app, &state.device_id(), &name, port) {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            server.unblock();
+            return Err(e);
+        }
+    };
+    server::spawn(app, server.clone());
+    if let Ok(mut running) = state.running.lock() {
+        *running = Some(Running {
+            daemon,
+            server,
+            requested_port: settings.port,
+            port,
+            name,
+        });
+    }
+    Ok(())
+}
+
+/// Follow the `sync` settings: start or stop advertising and serving, and
+/// restart when the name or port changed. Does nothing before `init_lan_sync`.
+pub fn apply_settings(app: &AppHandle, settings: &SyncSettings) {
+    let Some(state) = app.try_state::<LanSyncState>() else {
+        return;
+    };
+    let unchanged = state.running.lock().is_ok_and(|running| {
+        running
+            .as_ref()
+            .is_some_and(|running| running.requested_port == settings.port && running.name == device_name(settings))
+    });
+    if settings.enabled && unchanged {
+        return;
+    }
+    stop(&state);
+    if settings.enabled {
+        if let Err(e) = start(app, &state, settings) {
+            warn!("LAN sync unavailable: {}", e);
+        }
+    }
+    emit_peers_changed(app);
+}
+
+/// Write a file fetched from a peer into uploads, unless it's already there
+fn fetch_media(app: &AppHandle, address: &str, own_id: &str, key: &ChannelKey, name: &str) -> Result<bool, String> {
+    if !valid_media_name(name) {
+        return Err(format!("Peer sent an invalid upload name: {:?}", name));
+    }
+    let dir = uploads_dir(app);
+    let dest = dir.join(name);
+    if dest.exists() {
+        return Ok(false);
+    }
+    let bytes = channel::post_sealed(address, own_id, key, "/media", &json!({ "name": name }))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let partial = dir.join(format!("{}.partial", name));
+    fs::write(&partial, bytes).map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    fs::rename(&partial, &dest).map_err(|e| format!("Failed to save {}: {}", dest.display(), e))?;
+    Ok(true)
+}
+
+/// Pull a peer's changes since the last sync: fetch the uploads they
+/// reference, then have the backend apply them
+fn pull(app: &AppHandle, peer_id: &str) -> Result<SyncSummary, String> {
+    let state = app.state::<LanSyncState>();
+    if !state.is_running() {
+        return Err("LAN sync is off".to_string());
+    }
+    let peer = state.peer(peer_id).ok_or_else(|| format!("{} isn't paired", peer_id))?;
+    let address = state
+        .address(&peer)
+        .ok_or_else(|| format!("{} isn't on the network", peer.name))?;
+    let key = state.key(peer_id)?;
+    let own_id = state.device_id();
+
+    let body = channel::post_sealed(&address, &own_id, &key, "/changes", &json!({ "since": peer.cursor }))?;
+    let changes: Value = serde_json::from_slice(&body).map_err(|e| format!("Malformed changes from peer: {}", e))?;
+
+    let mut media = 0;
+    for name in changes["media"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+        if fetch_media(app, &address, &own_id, &key, name)? {
+            media += 1;
+        }
+    }
+
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let response = backend_client::call(
+        &config,
+        "POST",
+        "/api/sync/apply",
+        Some(&json!({ "entries": changes["entries"], "tombstones": changes["tombstones"] })),
+    )
+    .map_err(|e| format!("Applying changes failed: {}", e))?;
+    let mut summary: SyncSummary =
+        serde_json::from_value(response.body).map_err(|e| format!("Unexpected apply response: {}", e))?;
+    summary.peer_id = peer_id.to_string();
+    summary.media = media;
+
+    let cursor = changes["now"].as_str().map(str::to_string);
+    state.update_peer(peer_id, |peer| {
+        peer.cursor = cursor.or(peer.cursor.take());
+        peer.last_sync = Some(Utc::now());
+        peer.last_address = Some(address);
+    });
+    info!(
+        "Synced with {}: {} created, {} updated, {} deleted, {} skipped, {} files",
+        peer.name, summary.created, summary.updated, summary.deleted, summary.skipped, summary.media
+    );
+    Ok(summary)
+}
+
+/// Pull from a peer and report the result as `sync-completed` or `sync-failed`
+fn sync_with(app: &AppHandle, peer_id: &str) -> Result<SyncSummary, String> {
+    if SYNCING.swap(true, Ordering::SeqCst) {
+        return Err("A sync is already running".to_string());
+    }
+    let result = pull(app, peer_id);
+    SYNCING.store(false, Ordering::SeqCst);
+
+    let emitted = match &result {
+        Ok(summary) => app.emit("sync-completed", summary),
+        Err(error) => {
+            warn!("Sync with {} failed: {}", peer_id, error);
+            app.emit(
+                "sync-failed",
+                SyncFailure {
+                    peer_id: peer_id.to_string(),
+                    error: error.clone(),
+                },
+            )
+        }
+    };
+    if let Err(e) = emitted {
+        warn!("Failed to emit sync event: {}", e);
+    }
+    emit_peers_changed(app);
+    result
+}
+
+/// Ask a peer to pull from us, so a sync goes both ways
+fn nudge(app: &AppHandle, peer_id: &str) {
+    let state = app.state::<LanSyncState>();
+    let Some(peer) = state.peer(peer_id) else {
+        return;
+    };
+    let Some(address) = state.address(&peer) else {
+        return;
+    };
+    let result = state
+        .key(peer_id)
+        .and_then(|key| channel::post_sealed(&address, &state.device_id(), &key, "/nudge", &json!({})));
+    if let Err(e) = result {
+        warn!("Failed to ask {} to sync: {}", peer.name, e);
+    }
+}
+
+/// Called by the server when a peer asks us to pull from it
+fn nudged(app: &AppHandle, peer_id: &str) {
+    let app = app.clone();
+    let peer_id = peer_id.to_string();
+    thread::spawn(move || {
+        let _ = sync_with(&app, &peer_id);
+    });
+}
+
+/// Manage the sync state, start serving if `sync.enabled` and sync with each
+/// online paired peer every `sync.intervalMinutes`. Call once `DesktopConfig`
+/// is managed.
+pub fn init_lan_sync(app: &AppHandle) {
+    let path = match app.path().app_local_data_dir() {
+        Ok(dir) => dir.join(PEERS_FILE),
+        Err(e) => {
+            warn!("LAN sync disabled, app data directory unavailable: {}", e);
+            return;
+        }
+    };
+    let peers = load_peers(&path);
+    app.manage(LanSyncState {
+        path,
+        peers: Mutex::new(peers),
+        keys: Mutex::new(HashMap::new()),
+        discovered: Mutex::new(HashMap::new()),
+        pairing: Mutex::new(None),
+        running: Mutex::new(None),
+    });
+    apply_settings(app, &app.state::<SettingsState>().current().sync);
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(TICK).await;
+            let settings = app.state::<SettingsState>().current().sync;
+            if !settings.enabled || SYNCING.load(Ordering::SeqCst) {
+                continue;
+            }
+            let interval = chrono::Duration::minutes(settings.interval_minutes as i64);
+            let now = Utc::now();
+            let due: Vec<String> = app
+                .state::<LanSyncState>()
+                .status(true)
+                .peers
+                .into_iter()
+                .filter(|status| status.online && status.peer.last_sync.map_or(true, |last| now - last >= interval))
+                .map(|status| status.peer.id)
+                .collect();
+            for peer_id in due {
+                let app = app.clone();
+                let _ = async_runtime::spawn_blocking(move || sync_with(&app, &peer_id)).await;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_sync_status(app: AppHandle) -> Result<SyncStatus, String> {
+    let enabled = app.state::<SettingsState>().current().sync.enabled;
+    Ok(app.state::<LanSyncState>().status(enabled))
+}
+
+/// Open a two-minute window in which another install can pair with this one
+/// using the returned code
+#[tauri::command]
+pub async fn start_sync_pairing(app: AppHandle) -> Result<PairingCode, String> {
+    let state = app.state::<LanSyncState>();
+    if !state.is_running() {
+        return Err("Turn on LAN sync first".to_string());
+    }
+    let code = format!("{:06}", OsRng.next_u32() % 1_000_000);
+    let expires_at = Utc::now() + PAIRING_TTL;
+    let mut pairing = state.pairing.lock().map_err(|_| "Pairing is unavailable".to_string())?;
+    *pairing = Some(PairingWindow {
+        code: code.clone(),
+        expires_at,
+        attempts: 0,
+        pending: None,
+    });
+    drop(pairing);
+    emit_peers_changed(&app);
+    Ok(PairingCode { code, expires_at })
+}
+
+/// Pair with a discovered install using the code it shows
+#[tauri::command]
+pub async fn pair_sync_peer(app: AppHandle, peer_id: String, code: String) -> Result<PairedPeer, String> {
+    let code = code.trim().to_string();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("The pairing code is six digits".to_string());
+    }
+    let handle = app.clone();
+    let peer = async_runtime::spawn_blocking(move || {
+        let state = handle.state::<LanSyncState>();
+        if !state.is_running() {
+            return Err("Turn on LAN sync first".to_string());
+        }
+        let found = state
+            .discovered
+            .lock()
+            .ok()
+            .and_then(|discovered| discovered.get(&peer_id).cloned())
+            .ok_or("That computer isn't on the network")?;
+        let own_id = state.device_id();
+        let own_name = device_name(&handle.state::<SettingsState>().current().sync);
+
+        let (spake, message) =
+            Spake2::<Ed25519Group>::start_symmetric(&Password::new(&code), &Identity::new(PAIRING_IDENTITY));
+        let reply = channel::post_plain(
+            &found.address,
+            "/pair/start",
+            &json!({ "deviceId": own_id, "name": own_name, "message": channel::to_hex(&message) }),
+        )?;
+        if reply["deviceId"].as_str() != Some(found.id.as_str()) {
+            return Err("A different computer answered".to_string());
+        }
+        let theirs = channel::from_hex(reply["message"].as_str().unwrap_or_default())?;
+        let shared = spake.finish(&theirs).map_err(|e| format!("Invalid pairing message: {:?}", e))?;
+        let confirm = reply["confirm"].as_str().unwrap_or_default();
+        if !channel::verify_mac(&shared, &["server", &own_id, &found.id], confirm) {
+            return Err("Wrong pairing code".to_string());
+        }
+        channel::post_plain(
+            &found.address,
+            "/pair/confirm",
+            &json!({ "deviceId": own_id, "confirm": channel::mac(&shared, &["client", &own_id, &found.id]) }),
+        )?;
+        let name = reply["name"].as_str().unwrap_or(&found.name).to_string();
+        let peer = state.add_peer(&found.id, &name, channel::channel_key(&shared), Some(found.address.clone()))?;
+        info!("Paired with {} for LAN sync", peer.name);
+        Ok(peer)
+    })
+    .await
+    .map_err(|e| format!("Pairing panicked: {}", e))??;
+    emit_peers_changed(&app);
+    Ok(peer)
+}
+
+/// Forget a paired install and its key. It can no longer sync with this one.
+#[tauri::command]
+pub async fn unpair_sync_peer(app: AppHandle, peer_id: String) -> Result<(), String> {
+    let state = app.state::<LanSyncState>();
+    {
+        let mut peers = state.peers.lock().map_err(|_| "Sync peers are unavailable".to_string())?;
+        peers.peers.retain(|peer| peer.id != peer_id);
+        state.save(&peers);
+    }
+    if let Ok(mut keys) = state.keys.lock() {
+        keys.remove(&peer_id);
+    }
+    match keychain_entry(&peer_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => warn!("Failed to remove the sync key from the keychain: {}", e),
+    }
+    emit_peers_changed(&app);
+    Ok(())
+}
+
+/// Sync with one paired peer, or every online one, and ask each to sync back
+#[tauri::command]
+pub async fn sync_now(app: AppHandle, peer_id: Option<String>) -> Result<Vec<SyncSummary>, String> {
+    let targets: Vec<String> = match peer_id {
+        Some(peer_id) => vec![peer_id],
+        None => app
+            .state::<LanSyncState>()
+            .status(true)
+            .peers
+            .into_iter()
+            .filter(|status| status.online)
+            .map(|status| status.peer.id)
+            .collect(),
+    };
+    async_runtime::spawn_blocking(move || {
+        let mut summaries = Vec::new();
+        for peer_id in targets {
+            summaries.push(sync_with(&app, &peer_id)?);
+            nudge(&app, &peer_id);
+        }
+        Ok(summaries)
+    })
+    .await
+    .map_err(|e| format!("Sync panicked: {}", e))?
+}
@@ -0,0 +1,226 @@
+//! HTTP endpoint other installs talk to. Pairing requests are plain JSON;
+//! everything else must be sealed with the key of a paired peer.
+
+use std::fs;
+use std::io::Read;
+use std::sync::Arc;
+use std::thread;
+
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::channel::{self, ChannelKey, DEVICE_HEADER};
+use super::{emit_peers_changed, nudged, uploads_dir, valid_device_id, valid_media_name, LanSyncState};
+use crate::{backend_client, DesktopConfig};
+
+/// Requests are small JSON documents; anything bigger isn't from a peer
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+
+struct Reply {
+    status: u16,
+    body: Vec<u8>,
+    content_type: &'static str,
+}
+
+impl Reply {
+    fn json(body: &Value) -> Self {
+        Self {
+            status: 200,
+            body: body.to_string().into_bytes(),
+            content_type: "application/json",
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: message.into().into_bytes(),
+            content_type: "text/plain; charset=utf-8",
+        }
+    }
+
+    fn sealed(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            body,
+            content_type: "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairStart {
+    device_id: String,
+    name: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairConfirm {
+    device_id: String,
+    confirm: String,
+}
+
+#[derive(Deserialize)]
+struct ChangesRequest {
+    since: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MediaRequest {
+    name: String,
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>, Reply> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_REQUEST_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| Reply::error(400, format!("Failed to read request: {}", e)))?;
+    if body.len() as u64 > MAX_REQUEST_BYTES {
+        return Err(Reply::error(413, "Request is too large"));
+    }
+    Ok(body)
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(body: &[u8]) -> Result<T, Reply> {
+    serde_json::from_slice(body).map_err(|e| Reply::error(400, format!("Malformed request: {}", e)))
+}
+
+fn pair_start(app: &AppHandle, body: &[u8]) -> Result<Reply, Reply> {
+    let request: PairStart = parse(body)?;
+    if !valid_device_id(&request.device_id) {
+        return Err(Reply::error(400, "Invalid device ID"));
+    }
+    let message = channel::from_hex(&request.message).map_err(|e| Reply::error(400, e))?;
+    let name: String = request.name.chars().take(63).collect();
+    let state = app.state::<LanSyncState>();
+    let own_id = state.device_id();
+    let (ours, shared) = state
+        .accept_pairing(&request.device_id, &name, &message)
+        .map_err(|e| Reply::error(403, e))?;
+    let own_name = state
+        .running
+        .lock()
+        .ok()
+        .and_then(|running| running.as_ref().map(|running| running.name.clone()))
+        .unwrap_or_default();
+    Ok(Reply::json(&json!({
+        "deviceId": own_id,
+        "name": own_name,
+        "message": channel::to_hex(&ours),
+        "confirm": channel::mac(&shared, &["server", &request.device_id, &own_id]),
+    })))
+}
+
+fn pair_confirm(app: &AppHandle, body: &[u8]) -> Result<Reply, Reply> {
+    let request: PairConfirm = parse(body)?;
+    app.state::<LanSyncState>()
+        .confirm_pairing(&request.device_id, &request.confirm)
+        .map_err(|e| Reply::error(403, e))?;
+    emit_peers_changed(app);
+    Ok(Reply::json(&json!({})))
+}
+
+/// Only what the backend itself writes: digits, `-`, `:`, `.` and `T`
+fn valid_cursor(since: &str) -> bool {
+    !since.is_empty() && since.len() <= 40 && since.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | ':' | '.' | 'T'))
+}
+
+fn changes(app: &AppHandle, request: ChangesRequest) -> Result<Vec<u8>, Reply> {
+    let path = match request.since {
+        Some(since) if !valid_cursor(&since) => return Err(Reply::error(400, "Invalid sync cursor")),
+        Some(since) => format!("/api/sync/changes?since={}", since),
+        None => "/api/sync/changes".to_string(),
+    };
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let response = backend_client::call(&config, "GET", &path, None)
+        .map_err(|e| Reply::error(502, format!("Reading changes failed: {}", e)))?;
+    Ok(response.body.to_string().into_bytes())
+}
+
+fn media(app: &AppHandle, request: MediaRequest) -> Result<Vec<u8>, Reply> {
+    if !valid_media_name(&request.name) {
+        return Err(Reply::error(400, "Invalid upload name"));
+    }
+    let path = uploads_dir(app).join(&request.name);
+    let size = fs::metadata(&path).map(|meta| meta.len()).map_err(|_| Reply::error(404, "No such upload"))?;
+    // Leave room for the nonce and tag
+    if size > channel::MAX_RESPONSE_BYTES - 64 {
+        return Err(Reply::error(413, "Upload is too large to sync"));
+    }
+    fs::read(&path).map_err(|e| Reply::error(500, format!("Failed to read upload: {}", e)))
+}
+
+/// Check a sealed request from a paired peer and seal the response for it
+fn sealed(app: &AppHandle, path: &str, from: &str, body: &[u8]) -> Result<Reply, Reply> {
+    let key: ChannelKey = app
+        .state::<LanSyncState>()
+        .key(from)
+        .map_err(|_| Reply::error(403, "Not paired with this computer"))?;
+    let payload: Value = channel::open_request(&key, path, from, body).map_err(|e| Reply::error(403, e))?;
+    let plaintext = match path {
+        "/changes" => changes(app, serde_json::from_value(payload).map_err(|e| Reply::error(400, e.to_string()))?)?,
+        "/media" => media(app, serde_json::from_value(payload).map_err(|e| Reply::error(400, e.to_string()))?)?,
+        "/nudge" => {
+            nudged(app, from);
+            b"{}".to_vec()
+        }
+        _ => return Err(Reply::error(404, "Not found")),
+    };
+    Ok(Reply::sealed(channel::seal(&key, &channel::response_aad(path, from), &plaintext)))
+}
+
+fn handle(app: &AppHandle, request: &mut Request) -> Reply {
+    if *request.method() != Method::Post {
+        return Reply::error(405, "Method not allowed");
+    }
+    let path = request.url().to_string();
+    let from = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(DEVICE_HEADER))
+        .map(|header| header.value.as_str().to_string());
+    let result = read_body(request).and_then(|body| match (path.as_str(), from) {
+        ("/pair/start", _) => pair_start(app, &body),
+        ("/pair/confirm", _) => pair_confirm(app, &body),
+        (_, Some(from)) => sealed(app, &path, &from, &body),
+        (_, None) => Err(Reply::error(403, "Not paired with this computer")),
+    });
+    result.unwrap_or_else(|reply| reply)
+}
+
+fn respond(app: &AppHandle, mut request: Request) {
+    let reply = handle(app, &mut request);
+    if reply.status != 200 {
+        debug!("Sync request {} refused: {}", request.url(), String::from_utf8_lossy(&reply.body));
+    }
+    let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], reply.content_type.as_bytes()) {
+        response.add_header(header);
+    }
+    if let Err(e) = request.respond(response) {
+        debug!("Failed to answer sync request: {}", e);
+    }
+}
+
+/// Serve peers until the server is unblocked, one thread per request
+pub fn spawn(app: &AppHandle, server: Arc<Server>) {
+    let app = app.clone();
+    let spawned = thread::Builder::new().name("lan-sync-server".into()).spawn(move || {
+        for request in server.incoming_requests() {
+            let app = app.clone();
+            thread::spawn(move || respond(&app, request));
+        }
+        debug!("Sync server stopped");
+    });
+    if let Err(e) = spawned {
+        warn!("Failed to start sync server thread: {}", e);
+    }
+}
@@ -1,24 +1,130 @@
 use std::{
   env,
-  fs,
+  fs::File,
+  io,
   path::{Path, PathBuf},
   process::{Child, Command},
-  sync::Mutex,
+  sync::{Mutex, OnceLock},
   time::{Duration, Instant},
 };
 
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use dotenvy::from_path;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use shell_words;
-use tauri::{async_runtime, path::BaseDirectory, Manager, WindowEvent};
-use tokio::time::sleep;
+use tauri::{async_runtime, path::BaseDirectory, Emitter, Manager, WindowEvent};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::time::{sleep, timeout};
 
-// A/V modules for native macOS speech recognition and media capture
-#[cfg(target_os = "macos")]
+use error::ShellError;
+
+mod activity;
+mod app_lock;
+mod app_screenshot;
+mod audio_cleanup;
+#[cfg(desktop)]
+mod audio_playback;
+mod audit;
+mod autostart;
+mod automation;
+mod backend_client;
+mod backup;
+mod backup_remote;
+mod camera_controls;
+mod capabilities;
+mod captions;
+mod capture;
+mod capture_names;
+mod capture_targets;
+mod cli;
+mod backend_events;
+mod backend_update;
+mod clipboard;
+mod clips;
+mod crash;
+mod data_dir;
+mod db_encryption;
+mod deep_link;
+mod demo_mode;
+mod diagnostics;
+mod dnd;
+mod document_scan;
+mod email_ingest;
+mod entry_templates;
+mod error;
+mod event_batch;
+mod export;
+mod file_associations;
+mod focus;
+mod health;
+#[cfg(desktop)]
+mod hotkeys;
+mod idle;
+mod install_config;
+mod integrations;
+mod jobs;
+mod lan_sync;
+mod locale;
+mod logs;
+mod media_manifest;
+mod media_store;
+mod media_url;
+mod media_upload;
+mod network;
+mod notifications;
+mod pdf;
+mod permissions;
+mod power;
+mod prefs;
+mod privacy_guard;
+mod now_playing;
+mod quick_actions;
+#[cfg(desktop)]
+mod recording_indicator;
+mod reminders;
+mod remote_backend;
+mod settings;
+mod startup_repairs;
+mod system_search;
+mod telemetry;
+mod timer;
+mod touri_env;
+mod transcription;
+mod tray;
+mod updater;
+mod user_isolation;
+mod watch_folders;
+mod webview_state;
+#[cfg(target_os = "linux")]
+mod x11_session;
+
+// TypeScript bindings are generated by debug builds on macOS
+#[cfg(all(debug_assertions, target_os = "macos"))]
+mod bindings;
+
+// A/V modules for native speech recognition (macOS and iOS) and media capture (macOS)
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 mod speech;
 #[cfg(target_os = "macos")]
 mod media;
+#[cfg(target_os = "macos")]
+mod mic_test;
+#[cfg(target_os = "macos")]
+mod preview;
+#[cfg(target_os = "macos")]
+mod share;
+
+// Camera photos through ffmpeg on Linux
+#[cfg(target_os = "linux")]
+mod linux_camera;
+
+// Photo capture through the system camera app on Android
+#[cfg(target_os = "android")]
+mod mobile_camera;
 
 #[derive(Default)]
 struct BackendProcess {
@@ -30,46 +136,89 @@ impl BackendProcess {
     *self.child.lock().expect("backend lock poisoned") = Some(child);
   }
 
+  /// Whether the sidecar was spawned and hasn't exited
+  fn is_running(&self) -> bool {
+    match self.child.lock().expect("backend lock poisoned").as_mut() {
+      Some(child) => matches!(child.try_wait(), Ok(None)),
+      None => false,
+    }
+  }
+
   fn terminate(&self) {
     if let Some(mut child) = self.child.lock().expect("backend lock poisoned").take() {
       if let Err(err) = child.kill() {
         warn!("Failed to stop backend sidecar: {err}");
       }
+      // Reap it so its file handles (the SQLite database) are released
+      let _ = child.wait();
     }
   }
 }
 
+/// Command-line flags that start the app without a window: the backend,
+/// health monitoring, the tray icon and the reminder scheduler only
+const HEADLESS_FLAGS: &[&str] = &["--headless", "--backend-only"];
+
+/// Readiness probes start this far apart and back off to the maximum
+const READY_MIN_DELAY: Duration = Duration::from_millis(50);
+const READY_MAX_DELAY: Duration = Duration::from_secs(1);
+
+const HEALTH_CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+const HEALTH_REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Index `n` upgrades a schema `n` window_prefs.json to `n + 1`
+const WINDOW_PREFS_MIGRATIONS: &[prefs::Migration] = &[
+  // 0 -> 1: the unversioned layout gains `schemaVersion` and is otherwise unchanged
+  Ok,
+];
+
 #[derive(Serialize, Deserialize, Debug)]
 struct WindowPreferences {
+  #[serde(rename = "schemaVersion", default)]
+  schema_version: u32,
   width: u32,
   height: u32,
   maximized: bool,
 }
 
 impl WindowPreferences {
+  fn new(width: u32, height: u32, maximized: bool) -> Self {
+    Self {
+      schema_version: WINDOW_PREFS_MIGRATIONS.len() as u32,
+      width,
+      height,
+      maximized,
+    }
+  }
+
   fn load(app: &tauri::AppHandle) -> Option<Self> {
     let config_path = app
       .path()
       .app_config_dir()
       .ok()?
       .join("window_prefs.json");
-    
-    if !config_path.exists() {
-      info!("No window preferences file found at {:?}", config_path);
+    if let Some(reason) = prefs::corruption(&config_path) {
+      // Written again from the window's size on the next resize or close
+      startup_repairs::set_aside_corrupted(&config_path, &reason);
       return None;
     }
     
-    match fs::read_to_string(&config_path) {
-      Ok(content) => match serde_json::from_str::<WindowPreferences>(&content) {
+    match prefs::load_versioned(&config_path, WINDOW_PREFS_MIGRATIONS, None) {
+      Ok(Some(value)) => match serde_json::from_value::<WindowPreferences>(value) {
         Ok(prefs) => {
           info!("Loaded window preferences: {}x{}, maximized: {}", prefs.width, prefs.height, prefs.maximized);
           Some(prefs)
         }
         Err(e) => {
           warn!("Failed to parse window preferences: {}", e);
+          startup_repairs::set_aside_corrupted(&config_path, &e.to_string());
           None
         }
       },
+      Ok(None) => {
+        info!("No window preferences file found at {:?}", config_path);
+        None
+      }
       Err(e) => {
         warn!("Failed to read window preferences: {}", e);
         None
@@ -79,17 +228,11 @@ impl WindowPreferences {
   
   fn save(&self, app: &tauri::AppHandle) {
     if let Ok(config_dir) = app.path().app_config_dir() {
-      // Create config directory if it doesn't exist
-      if let Err(e) = fs::create_dir_all(&config_dir) {
-        warn!("Failed to create config directory: {}", e);
-        return;
-      }
-      
       let config_path = config_dir.join("window_prefs.json");
       
       match serde_json::to_string_pretty(self) {
         Ok(json) => {
-          if let Err(e) = fs::write(&config_path, json) {
+          if let Err(e) = prefs::write_atomic(&config_path, &json) {
             warn!("Failed to write window preferences: {}", e);
           } else {
             info!("Saved window preferences: {}x{}, maximized: {}", self.width, self.height, self.maximized);
@@ -103,142 +246,535 @@ impl WindowPreferences {
   }
 }
 
+/// The bundled backend binary, resolved and checked once at startup
+#[derive(Clone, Debug)]
+enum PackagedBackend {
+  /// Not bundled, as in dev builds; the launcher command is used instead
+  Missing,
+  Valid(PathBuf),
+  /// Bundled but unusable; the message is the spawn error
+  Invalid(String),
+}
+
+/// Where the backend comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BackendMode {
+  /// The bundled sidecar or the launcher command, started by the shell
+  Local,
+  /// `backend.remoteUrl`, started and stopped by someone else
+  Remote,
+  /// Mobile without `backend.remoteUrl`: there's nothing to connect to yet
+  Unconfigured,
+}
+
 #[derive(Clone)]
 struct DesktopConfig {
   repo_root: PathBuf,
   platform_dir: &'static str,
   binary_name: &'static str,
+  packaged_backend: PackagedBackend,
+  backend_mode: BackendMode,
+  backend_url: String,
   health_url: String,
+  /// Shared with the backend for this launch; see `backend_client`
+  backend_token: String,
   window_height_ratio: f64,
   window_width: Option<f64>,
   window_maximized: bool,
   splash_min: Duration,
   launcher_command: String,
+  start_hidden: bool,
+  /// Started with one of `HEADLESS_FLAGS`; no webview is ever created
+  headless: bool,
+  idle_threshold: Duration,
+  network_probes: Vec<String>,
 }
 
 impl DesktopConfig {
-  fn from_env(repo_root: PathBuf) -> Self {
+  fn from_settings(repo_root: PathBuf, settings: &settings::Settings) -> Self {
     #[cfg(target_os = "windows")]
     let platform = ("windows", "track-the-thing-backend.exe");
     #[cfg(target_os = "macos")]
     let platform = ("macos", "track-the-thing-backend");
     #[cfg(target_os = "linux")]
     let platform = ("linux", "track-the-thing-backend");
+    // No sidecar on mobile
+    #[cfg(mobile)]
+    let platform = ("mobile", "");
 
-    let backend_host = env::var("TAURI_BACKEND_HOST").unwrap_or_else(|_| "127.0.0.1".into());
-    let backend_port = env::var("TAURI_BACKEND_PORT")
-      .ok()
-      .and_then(|value| value.parse::<u16>().ok())
-      .unwrap_or(18765);
-    let health_url = format!("http://{backend_host}:{backend_port}/health");
-
-    let window_height_ratio = env::var("TAURI_WINDOW_HEIGHT_RATIO")
-      .ok()
-      .and_then(|value| value.parse::<f64>().ok())
-      .map(|ratio| ratio.clamp(0.5, 0.98))
-      .unwrap_or(0.95);
-
-    let window_width = env::var("TAURI_WINDOW_WIDTH")
-      .ok()
-      .and_then(|value| value.parse::<f64>().ok())
-      .filter(|width| *width > 320.0);
-
-    let window_maximized = env::var("TAURI_WINDOW_MAXIMIZED")
-      .map(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
-      .unwrap_or(false);
-
-    let splash_min = env::var("TAURI_SPLASH_MIN_VISIBLE_MS")
-      .ok()
-      .and_then(|value| value.parse::<u64>().ok())
-      .map(Duration::from_millis)
-      .unwrap_or(Duration::from_millis(1200));
-
-    let launcher_command =
-      env::var("PYINSTALLER_ENTRYPOINT").unwrap_or_else(|_| "python3 backend/desktop_launcher.py".into());
+    let backend_mode = match &settings.backend.remote_url {
+      Some(_) => BackendMode::Remote,
+      None if cfg!(mobile) => BackendMode::Unconfigured,
+      None => BackendMode::Local,
+    };
+    let backend_url = settings.backend_url();
+    let health_url = format!("{backend_url}/health");
 
     Self {
       repo_root,
       platform_dir: platform.0,
       binary_name: platform.1,
+      packaged_backend: PackagedBackend::Missing,
+      backend_mode,
+      backend_url,
       health_url,
-      window_height_ratio,
-      window_width,
-      window_maximized,
-      splash_min,
-      launcher_command,
+      backend_token: backend_client::generate_token(),
+      window_height_ratio: settings.window.height_ratio,
+      window_width: settings.window.width,
+      window_maximized: settings.window.maximized,
+      splash_min: Duration::from_millis(settings.window.splash_min_ms),
+      launcher_command: settings.backend.launcher_command.clone(),
+      start_hidden: autostart::launched_hidden(),
+      headless: env::args().any(|arg| HEADLESS_FLAGS.contains(&arg.as_str())),
+      idle_threshold: Duration::from_secs(settings.privacy.idle_threshold_secs),
+      network_probes: settings.privacy.network_probes.clone(),
     }
   }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  tauri::Builder::default()
+  // The same executable doubles as the out-of-process minidump writer
+  if crash::run_monitor_if_requested() {
+    return;
+  }
+  crash::install_panic_hook();
+
+  // Command line actions are checked before anything starts, so a typo
+  // never reaches a running instance
+  let args: Vec<String> = env::args().collect();
+  let cli_command = match cli::parse(&args, &env::current_dir().unwrap_or_default()) {
+    Some(Ok(command)) => Some(command),
+    Some(Err(e)) => {
+      eprintln!("{}\n\n{}", e, cli::USAGE);
+      std::process::exit(2);
+    }
+    None => None,
+  };
+
+  let mut builder = tauri::Builder::default();
+
+  // Single-instance must be the first plugin registered. A second launch (including
+  // one started by the OS for a trackthething:// link) focuses the running window
+  // instead, and its deep link is forwarded to the deep-link plugin.
+  #[cfg(desktop)]
+  {
+    builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+      if let Some(command) = cli::parse(&argv, Path::new(&cwd)) {
+        match command {
+          Ok(command) => cli::dispatch(app, command),
+          Err(e) => warn!("Ignoring command line from second instance: {}", e),
+        }
+        return;
+      }
+      info!("Second instance launched with args {:?}, focusing existing window", argv);
+      focus_main_window(app);
+      email_ingest::handle_opened(app, email_ingest::sources_from_args(argv.clone()));
+      file_associations::handle_open_files(app, file_associations::archive_paths_from_args(argv));
+    }));
+    builder = builder.plugin(
+      tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, shortcut, event| hotkeys::handle(app, shortcut, event))
+        .build(),
+    );
+    builder = builder.register_uri_scheme_protocol(recording_indicator::SCHEME, |_ctx, request| {
+      recording_indicator::handle_request(&request)
+    });
+  }
+
+  // Captured media for <img>/<video>/<audio>, read off the main thread
+  builder = builder.register_asynchronous_uri_scheme_protocol(media_url::SCHEME, |ctx, request, responder| {
+    let app = ctx.app_handle().clone();
+    if app_lock::is_locked(&app) {
+      return responder.respond(app_lock::locked_response());
+    }
+    tauri::async_runtime::spawn_blocking(move || responder.respond(media_url::handle_request(&app, &request)));
+  });
+
+  // Live camera preview frames, fetched by the webview as raw JPEG
+  #[cfg(target_os = "macos")]
+  {
+    builder = builder.register_uri_scheme_protocol(preview::SCHEME, |ctx, request| {
+      if app_lock::is_locked(ctx.app_handle()) {
+        return app_lock::locked_response();
+      }
+      preview::handle_request(&request)
+    });
+  }
+
+  #[cfg(target_os = "android")]
+  {
+    builder = builder.plugin(mobile_camera::init());
+  }
+
+  builder
+    .plugin(tauri_plugin_deep_link::init())
     .manage(BackendProcess::default())
-    .setup(|app| {
-      // Enable logging in both debug and release modes
+    .manage(capture::CaptureManager::default())
+    .setup(move |app| {
+      // Enable logging in both debug and release modes. Everything reaches the
+      // logger so the level from settings can be raised at runtime. The file
+      // target rotates under the data dir's logs/ once settings are read.
       app.handle().plugin(
         tauri_plugin_log::Builder::default()
-          .level(log::LevelFilter::Info)
+          .level(log::LevelFilter::Trace)
+          .clear_targets()
+          .target(tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Stdout))
+          .target(logs::file_target())
           .build(),
       )?;
-      
-      // Enable opening external URLs in system browser
-      app.handle().plugin(tauri_plugin_opener::init())?;
-      
-      // Enable file system access for A/V file handling
-      app.handle().plugin(tauri_plugin_fs::init())?;
+      log::set_max_level(log::LevelFilter::Info);
+      logs::init_tracing();
+      audit::init_audit(app.handle());
 
       let repo_root = resolve_repo_root();
+      #[cfg(all(debug_assertions, target_os = "macos"))]
+      bindings::export(&repo_root);
       
       // In production (release build), skip loading .tourienv from compile-time source directory
-      // and use platform-appropriate production defaults instead
-      if cfg!(debug_assertions) {
+      // and use the saved settings with platform-appropriate defaults instead
+      let touri_loaded = if cfg!(debug_assertions) {
         info!("Running in debug mode - loading .tourienv from source directory");
-        load_touri_env(&repo_root);
+        load_touri_env(&repo_root)
       } else {
-        info!("Running in production mode - using platform defaults (ignoring .tourienv from source)");
-        load_production_env();
+        info!("Running in production mode - using saved settings (ignoring .tourienv from source)");
+        false
+      };
+
+      // Typed settings from the config dir; .tourienv values override them in debug builds
+      let touri_env = touri_loaded.then(|| repo_root.join(".tourienv"));
+      let settings = settings::init_settings(app.handle(), touri_env);
+      // config.toml, launch flags and per-user values outrank .tourienv; the
+      // backend paths are then derived from the resulting data dir as in production
+      let outranked = install_config::KEYS.iter().any(|key| {
+        matches!(
+          app.state::<settings::SettingsState>().override_source(key),
+          Some(
+            settings::ConfigSource::ConfigFile
+              | settings::ConfigSource::LaunchArgs
+              | settings::ConfigSource::PerUser
+          )
+        )
+      });
+      if !touri_loaded || outranked {
+        load_production_env(&settings);
       }
       
-      let config = DesktopConfig::from_env(repo_root.clone());
-      initialize_windows(app, &config);
+      // Logs, panic reports and minidumps go to the data dir from here on
+      let data_dir = data_dir::current_data_dir(app.handle());
+      logs::set_log_dir(Some(data_dir.join("logs")));
+      crash::init_crash_reporting(app.handle(), data_dir.join(crash::CRASH_DIR));
 
-      let child = spawn_backend(&app.handle(), &config)?;
-      app.state::<BackendProcess>().replace(child);
+      // Leftovers of a crash that would trip up the backend are moved aside
+      startup_repairs::run(app.handle());
+      // Jobs left from the last run wait in the queue until the backend is ready
+      jobs::init_jobs(app.handle());
 
-      // Initialize native speech recognition system on macOS
-      #[cfg(target_os = "macos")]
+      // The backend is the slowest part of startup, so it's spawned as soon as
+      // its environment is known and polled while the rest of setup runs. The
+      // window is only shown once setup has finished as well.
+      let mut config = DesktopConfig::from_settings(repo_root.clone(), &settings);
+      match config.backend_mode {
+        BackendMode::Local => {
+          config.packaged_backend = resolve_packaged_backend(app.handle(), &config);
+          if demo_mode::requested() {
+            demo_mode::enter(app.handle())?;
+          }
+          let child = spawn_backend(&app.handle(), &config, &[])?;
+          app.state::<BackendProcess>().replace(child);
+        }
+        BackendMode::Remote => {
+          info!("Using the remote backend at {}", config.backend_url);
+          if demo_mode::requested() {
+            warn!("Ignoring {}: demo mode needs the backend run by this app", demo_mode::DEMO_FLAG);
+          }
+          config.backend_token = remote_backend::load_token(app.handle()).unwrap_or_default();
+        }
+        BackendMode::Unconfigured => {
+          warn!("No backend: set backend.remoteUrl to a backend this device can reach");
+        }
+      }
+      app.manage(config.clone());
+
+      // A launch that only runs a command needs nothing else
+      if let Some(command) = cli_command {
+        cli::run_once(app.handle(), command);
+        return Ok(());
+      }
+
+      let (setup_done, setup_finished) = oneshot::channel();
+      wait_for_backend_ready(app.handle().clone(), config.clone(), setup_finished);
+
+      // Enable opening external URLs in system browser
+      app.handle().plugin(tauri_plugin_opener::init())?;
+      
+      // Enable file system access for A/V file handling
+      app.handle().plugin(tauri_plugin_fs::init())?;
+
+      // Clipboard access for quick capture
+      app.handle().plugin(tauri_plugin_clipboard_manager::init())?;
+
+      // Native save dialog for data exports
+      app.handle().plugin(tauri_plugin_dialog::init())?;
+
+      // Signed self-updates from the stable or beta release feed
+      app.handle().plugin(tauri_plugin_updater::Builder::new().build())?;
+
+      if !config.headless {
+        create_main_window(app.handle())?;
+        initialize_windows(app, &config);
+      }
+      settings::apply_live(app.handle(), &settings);
+      settings::watch_settings(app.handle());
+
+      // Tray icon so the window can be reopened when started hidden at login
+      tray::init_tray(app.handle())?;
+
+      // Camera, microphone, screen, clipboard and activity stay off until enabled
+      permissions::init_permissions(app.handle());
+
+      // Native notifications and the persisted reminder scheduler
+      dnd::init_dnd(app.handle());
+      notifications::init_notifications(app.handle());
+      reminders::init_reminders(app.handle());
+
+      // Everything else serves the webview
+      if config.headless {
+        info!("Running headless ({}); no window will be opened", HEADLESS_FLAGS.join(" / "));
+        let _ = setup_done.send(());
+        return Ok(());
+      }
+
+      // trackthething:// links, including the one the app may have been launched with
+      deep_link::init_deep_links(app.handle());
+
+      // Exported archives double-clicked to launch the app
+      file_associations::init_file_associations(app.handle());
+
+      // Emails opened with the app become entries
+      email_ingest::init_email_ingest(app.handle());
+
+      // Idle detection so running timers can auto-pause
+      idle::init_idle_monitor(app.handle(), config.idle_threshold);
+      timer::init_timer(app.handle());
+      #[cfg(desktop)]
+      hotkeys::init_hotkeys(app.handle());
+      #[cfg(desktop)]
+      recording_indicator::init_recording_indicator(app.handle());
+      app_lock::init_app_lock(app.handle());
+      activity::init_activity_tracker(app.handle());
+      integrations::calendar::init_calendar(app.handle());
+      now_playing::init_now_playing(app.handle());
+      quick_actions::init_quick_actions(app.handle());
+      system_search::init_system_search(app.handle(), config.clone());
+      network::init_network_monitor(app.handle(), config.network_probes.clone());
+      power::init_power(app.handle());
+      telemetry::init_telemetry(app.handle());
+
+      backend_events::init_backend_events(app.handle());
+      data_dir::init_data_dir(app.handle());
+      updater::init_updater(app.handle());
+      backend_update::init_backend_updater(app.handle());
+      backup::init_backups(app.handle());
+      lan_sync::init_lan_sync(app.handle());
+      watch_folders::init_watch_folders(app.handle());
+
+      // Initialize native speech recognition system on macOS and iOS
+      #[cfg(any(target_os = "macos", target_os = "ios"))]
       {
         info!("Initializing native speech recognition system");
         speech::init_speech_system(app.handle().clone());
       }
 
-      wait_for_backend_ready(app.handle().clone(), config);
+      #[cfg(target_os = "macos")]
+      {
+        info!("Registering macOS Services provider");
+        share::init_share_services(app.handle().clone());
+      }
+
+      #[cfg(target_os = "macos")]
+      media::init_device_watch(app.handle());
+
+      let _ = setup_done.send(());
       Ok(())
     })
     // Register native A/V commands for macOS
-    .invoke_handler(tauri::generate_handler![
-      #[cfg(target_os = "macos")]
-      speech::request_speech_authorization,
-      #[cfg(target_os = "macos")]
-      speech::start_speech_recognition,
-      #[cfg(target_os = "macos")]
-      speech::stop_speech_recognition,
-      #[cfg(target_os = "macos")]
-      speech::is_speech_available,
-      #[cfg(target_os = "macos")]
-      media::capture_photo,
-      #[cfg(target_os = "macos")]
-      media::list_cameras,
-      #[cfg(target_os = "macos")]
-      media::start_video_recording,
-      #[cfg(target_os = "macos")]
-      media::stop_video_recording,
-      #[cfg(target_os = "macos")]
-      media::request_camera_permission,
-      #[cfg(target_os = "macos")]
-      media::request_microphone_permission,
-    ])
+    .invoke_handler({
+      let handler = tauri::generate_handler![
+        settings::get_settings,
+        settings::update_settings,
+        settings::get_effective_config,
+        capabilities::get_shell_capabilities,
+        diagnostics::export_diagnostics,
+        app_screenshot::capture_app_screenshot,
+        crash::get_crash_status,
+        crash::clear_crash_reports,
+        startup_repairs::get_startup_repairs,
+        logs::set_log_level,
+        logs::set_log_filter,
+        logs::get_log_files,
+        audit::get_command_audit,
+        health::get_system_health,
+        backend_client::backend_request,
+        demo_mode::get_demo_mode,
+        demo_mode::start_demo_mode,
+        demo_mode::stop_demo_mode,
+        db_encryption::get_database_encryption,
+        db_encryption::encrypt_database,
+        backup::run_backup_now,
+        backup::start_backup,
+        backup::list_backups,
+        backup::restore_backup,
+        backup_remote::set_remote_backup_credentials,
+        backup_remote::clear_remote_backup_credentials,
+        remote_backend::set_remote_backend_token,
+        export::export_data,
+        export::start_export,
+        jobs::cancel_job,
+        jobs::list_jobs,
+        webview_state::get_webview_state,
+        webview_state::save_webview_state,
+        lan_sync::get_sync_status,
+        lan_sync::start_sync_pairing,
+        lan_sync::pair_sync_peer,
+        lan_sync::unpair_sync_peer,
+        lan_sync::sync_now,
+        locale::get_app_locale,
+        locale::set_app_locale,
+        data_dir::get_data_dir,
+        data_dir::set_data_dir,
+        updater::check_for_updates,
+        updater::install_update,
+        updater::restart_to_update,
+        updater::set_update_channel,
+        backend_update::check_backend_update,
+        backend_update::install_backend_update,
+        backend_update::get_backend_versions,
+        backend_update::rollback_backend,
+        autostart::set_autostart,
+        autostart::get_autostart,
+        clipboard::capture_clipboard,
+        deep_link::take_pending_deep_links,
+        automation::get_automation_permissions,
+        permissions::get_feature_permissions,
+        permissions::set_feature_permission,
+        automation::revoke_automation_permission,
+        file_associations::take_pending_open_files,
+        email_ingest::ingest_eml,
+        entry_templates::list_entry_templates,
+        entry_templates::save_entry_template,
+        entry_templates::delete_entry_template,
+        entry_templates::create_entry_from_template,
+        idle::get_idle_status,
+        idle::set_idle_threshold,
+        timer::start_timer,
+        timer::pause_timer,
+        timer::stop_timer,
+        timer::get_timer,
+        focus::start_focus_session,
+        focus::stop_focus_session,
+        focus::skip_focus_phase,
+        focus::get_focus_session,
+        reminders::sync_reminders,
+        #[cfg(desktop)]
+        hotkeys::set_hotkey,
+        #[cfg(desktop)]
+        hotkeys::list_hotkeys,
+        activity::set_activity_tracking,
+        activity::get_activity_tracking,
+        activity::get_activity_log,
+        network::get_network_status,
+        power::get_power_status,
+        power::set_power_profile,
+        telemetry::get_telemetry_preview,
+        telemetry::record_feature_use,
+        app_lock::get_app_lock_status,
+        app_lock::configure_app_lock,
+        app_lock::lock_app,
+        app_lock::unlock_app,
+        media_store::reveal_in_file_manager,
+        media_store::open_with_default_app,
+        media_store::inspect_media,
+        media_url::media_url,
+        media_upload::upload_media,
+        media_manifest::verify_media_integrity,
+        captions::export_video_with_captions,
+        clips::export_clip,
+        camera_controls::get_camera_controls,
+        camera_controls::set_camera_control,
+        document_scan::scan_document,
+        pdf::assemble_pdf,
+        transcription::transcribe_media,
+        capture_targets::list_capture_targets,
+        #[cfg(desktop)]
+        audio_playback::play_audio,
+        #[cfg(desktop)]
+        audio_playback::stop_audio_playback,
+        integrations::calendar::get_calendar_permission,
+        integrations::calendar::request_calendar_permission,
+        integrations::calendar::upsert_calendar_event,
+        integrations::calendar::remove_calendar_event,
+        now_playing::set_now_playing,
+        now_playing::clear_now_playing,
+        quick_actions::set_quick_actions,
+        system_search::get_system_search_indexing,
+        system_search::set_system_search_indexing,
+        system_search::reindex_system_search,
+        dnd::get_dnd_status,
+        notifications::notify,
+        notifications::schedule_notification,
+        notifications::cancel_scheduled_notification,
+        notifications::list_scheduled_notifications,
+        capture::list_capture_sources,
+        capture::capture_from_source,
+        capture::start_capture_recording,
+        capture::stop_capture_recording,
+        capture::list_media,
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        speech::request_speech_authorization,
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        speech::start_speech_recognition,
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        speech::stop_speech_recognition,
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        speech::feed_speech_audio,
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        speech::is_speech_available,
+        #[cfg(target_os = "macos")]
+        media::capture_photo,
+        #[cfg(target_os = "macos")]
+        media::capture_photo_burst,
+        #[cfg(target_os = "macos")]
+        media::list_cameras,
+        #[cfg(target_os = "macos")]
+        media::list_microphones,
+        #[cfg(target_os = "macos")]
+        mic_test::start_mic_test,
+        #[cfg(target_os = "macos")]
+        media::start_video_recording,
+        #[cfg(target_os = "macos")]
+        media::stop_video_recording,
+        #[cfg(target_os = "macos")]
+        media::request_camera_permission,
+        #[cfg(target_os = "macos")]
+        media::request_microphone_permission,
+        #[cfg(target_os = "macos")]
+        preview::start_camera_preview,
+        #[cfg(target_os = "macos")]
+        preview::stop_camera_preview,
+        #[cfg(target_os = "android")]
+        mobile_camera::capture_photo,
+      ];
+      // While the app is locked only the lock screen's commands run
+      move |invoke: tauri::ipc::Invoke| {
+        let app = invoke.message.webview_ref().app_handle();
+        if let Some(error) = app_lock::refuse_while_locked(app, invoke.message.command()) {
+          invoke.resolver.reject(error);
+          return true;
+        }
+        handler(invoke)
+      }
+    })
     .on_window_event(|window, event| {
       if window.label() == "main" {
         match event {
@@ -246,25 +782,20 @@ pub fn run() {
             // Save window size before closing
             if let Ok(size) = window.outer_size() {
               if let Ok(is_maximized) = window.is_maximized() {
-                let prefs = WindowPreferences {
-                  width: size.width,
-                  height: size.height,
-                  maximized: is_maximized,
-                };
+                let prefs = WindowPreferences::new(size.width, size.height, is_maximized);
                 prefs.save(&window.app_handle());
               }
             }
             window.app_handle().state::<BackendProcess>().terminate();
           }
+          WindowEvent::Focused(true) => {
+            dnd::clear_badge(window.app_handle());
+          }
           WindowEvent::Resized(size) => {
             // Save window size when resized (debounced by only saving on meaningful changes)
             if size.width >= 480 && size.height >= 600 {
               if let Ok(is_maximized) = window.is_maximized() {
-                let prefs = WindowPreferences {
-                  width: size.width,
-                  height: size.height,
-                  maximized: is_maximized,
-                };
+                let prefs = WindowPreferences::new(size.width, size.height, is_maximized);
                 prefs.save(&window.app_handle());
               }
             }
@@ -273,8 +804,48 @@ pub fn run() {
         }
       }
     })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|_app, event| {
+      match event {
+        // A session that ends here wasn't a crash
+        tauri::RunEvent::Exit => {
+          crash::end_session();
+          demo_mode::discard(_app);
+        }
+        // macOS delivers double-clicked archives and emails as an Opened event rather than argv
+        #[cfg(target_os = "macos")]
+        tauri::RunEvent::Opened { urls } => {
+          let paths: Vec<PathBuf> = urls.into_iter().filter_map(|url| url.to_file_path().ok()).collect();
+          let emails = paths
+            .iter()
+            .filter(|path| email_ingest::is_eml_path(path))
+            .map(|path| email_ingest::EmailSource::File(path.clone()))
+            .collect();
+          email_ingest::handle_opened(_app, emails);
+          let archives = paths
+            .into_iter()
+            .filter(|path| file_associations::is_archive_path(path))
+            .collect();
+          file_associations::handle_open_files(_app, archives);
+        }
+        _ => {}
+      }
+    });
+}
+
+/// Bring the (single) main window to the front
+fn focus_main_window(app: &tauri::AppHandle) {
+  // A locked app only comes forward through the unlock flow
+  if app_lock::is_locked(app) {
+    app_lock::prompt_unlock(app);
+    return;
+  }
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
 }
 
 fn resolve_repo_root() -> PathBuf {
@@ -292,23 +863,10 @@ fn resolve_repo_root() -> PathBuf {
   repo_root
 }
 
-fn load_production_env() {
+fn load_production_env(settings: &settings::Settings) {
   info!("Setting platform-appropriate production environment variables");
-  
-  #[cfg(target_os = "macos")]
-  let data_dir = dirs::home_dir()
-    .map(|h| h.join("Library/Application Support/TrackTheThingDesktop"))
-    .expect("FATAL: Cannot resolve home directory. Unable to determine data directory location.");
-  
-  #[cfg(target_os = "linux")]
-  let data_dir = dirs::home_dir()
-    .map(|h| h.join(".local/share/track-the-thing-desktop"))
-    .expect("FATAL: Cannot resolve home directory. Unable to determine data directory location.");
-  
-  #[cfg(target_os = "windows")]
-  let data_dir = dirs::data_local_dir()
-    .map(|d| d.join("TrackTheThingDesktop"))
-    .expect("FATAL: Cannot resolve local app data directory. Unable to determine data directory location.");
+
+  let data_dir = settings.data_dir();
 
   // Create the data directory if it doesn't exist
   if let Err(e) = std::fs::create_dir_all(&data_dir) {
@@ -316,34 +874,64 @@ fn load_production_env() {
   }
   
   let data_dir_str = data_dir.to_string_lossy().to_string();
-  env::set_var("TAURI_BACKEND_HOST", "127.0.0.1");
-  env::set_var("TAURI_BACKEND_PORT", "18765");
+  env::set_var("TAURI_BACKEND_HOST", &settings.backend.host);
+  env::set_var("TAURI_BACKEND_PORT", settings.backend.port.to_string());
   env::set_var("TAURI_DESKTOP_DATA_DIR", &data_dir_str);
   env::set_var("TAURI_DATABASE_PATH", format!("{}/ttt_desktop.db", data_dir_str));
   env::set_var("TAURI_UPLOADS_DIR", format!("{}/uploads", data_dir_str));
   env::set_var("TAURI_STATIC_DIR", format!("{}/static", data_dir_str));
   env::set_var("TAURI_BACKEND_LOG", format!("{}/logs/backend.log", data_dir_str));
-  env::set_var("TAURI_WINDOW_HEIGHT_RATIO", "0.95");
-  env::set_var("TAURI_WINDOW_MAXIMIZED", "false");
   
   info!("Set TAURI_DESKTOP_DATA_DIR={}", data_dir_str);
 }
 
-fn load_touri_env(repo_root: &Path) {
+/// Load `.tourienv` into the environment. Returns false if it is missing or
+/// unreadable, in which case the production defaults are used instead.
+/// Problems with its keys and values are logged by line either way.
+fn load_touri_env(repo_root: &Path) -> bool {
   let env_path = repo_root.join(".tourienv");
   if env_path.exists() {
+    touri_env::check(&env_path);
     if let Err(err) = from_path(&env_path) {
       warn!("Failed to load .tourienv: {err}");
-      load_production_env();
+      false
     } else {
       info!("Loaded desktop environment overrides from {}", env_path.display());
+      true
     }
   } else {
     warn!(".tourienv not found at {}", env_path.display());
-    load_production_env();
+    false
   }
 }
 
+/// Size of the monitor the main window opens on. Queried at most once, and
+/// only when the window isn't maximized.
+fn screen_size(window: &tauri::WebviewWindow) -> Option<tauri::PhysicalSize<u32>> {
+  static SCREEN_SIZE: OnceLock<Option<tauri::PhysicalSize<u32>>> = OnceLock::new();
+  *SCREEN_SIZE.get_or_init(|| {
+    let size = window.current_monitor().ok().flatten().map(|monitor| *monitor.size());
+    if let Some(size) = size {
+      info!("Screen size: {}x{}", size.width, size.height);
+    }
+    size
+  })
+}
+
+/// Create the main window from tauri.conf.json. It isn't created
+/// automatically, so a headless launch never starts a webview.
+fn create_main_window(app: &tauri::AppHandle) -> tauri::Result<tauri::WebviewWindow> {
+  let window_config = app
+    .config()
+    .app
+    .windows
+    .iter()
+    .find(|window| window.label == "main")
+    .cloned()
+    .ok_or(tauri::Error::WindowNotFound)?;
+  tauri::WebviewWindowBuilder::from_config(app, &window_config)?.build()
+}
+
 fn initialize_windows(app: &tauri::App, config: &DesktopConfig) {
   if let Some(main_window) = app.get_webview_window("main") {
     let _ = main_window.hide();
@@ -365,10 +953,9 @@ fn initialize_windows(app: &tauri::App, config: &DesktopConfig) {
       }
     }
     
-    if let Ok(Some(monitor)) = main_window.current_monitor() {
-      let screen_size = monitor.size();
-      info!("Screen size: {}x{}", screen_size.width, screen_size.height);
-      
+    // A maximized window is sized when it's shown; the monitor isn't needed
+    let screen_size = if config.window_maximized { None } else { screen_size(&main_window) };
+    if let Some(screen_size) = screen_size {
       // Use saved preferences if available, otherwise calculate from config
       let (width, height) = if let Some(prefs) = saved_prefs {
         info!("Using saved window size: {}x{}", prefs.width, prefs.height);
@@ -400,35 +987,55 @@ fn initialize_windows(app: &tauri::App, config: &DesktopConfig) {
   }
 }
 
-fn spawn_backend(app: &tauri::AppHandle, config: &DesktopConfig) -> Result<Child, std::io::Error> {
-  if let Some(binary_path) = packaged_backend_path(app, config) {
-    info!("Checking for packaged backend at: {}", binary_path.display());
-    if binary_path.exists() {
-      info!("Starting packaged backend at {}", binary_path.display());
-      info!("Environment variables being passed:");
-      for (key, value) in env::vars() {
-        if key.starts_with("TAURI_") {
-          info!("  {}={}", key, value);
-        }
+/// Start the backend process. `extra_args` are passed on to the launcher, e.g.
+/// for one-off maintenance runs.
+fn spawn_backend(app: &tauri::AppHandle, config: &DesktopConfig, extra_args: &[&str]) -> Result<Child, std::io::Error> {
+  // Restarts for a data dir move, restore or update only apply to a local backend
+  if config.backend_mode != BackendMode::Local {
+    return Err(io::Error::new(io::ErrorKind::Unsupported, "The backend isn't run by this app"));
+  }
+  // A backend update downloaded since the last start takes precedence over the bundled one
+  let installed = backend_update::installed_backend_path(app, config).filter(|path| {
+    match validate_backend_binary(path) {
+      Ok(()) => true,
+      Err(reason) => {
+        warn!("Downloaded backend binary invalid: {}: {}", path.display(), reason);
+        false
       }
-      
-      match Command::new(&binary_path)
-        .envs(env::vars())
-        .spawn() {
-        Ok(child) => {
-          info!("Backend process spawned successfully with PID: {}", child.id());
-          return Ok(child);
-        }
-        Err(e) => {
-          warn!("Failed to spawn packaged backend: {}", e);
-          return Err(e);
-        }
+    }
+  });
+  let binary_path = match (installed, &config.packaged_backend) {
+    (Some(path), _) => Some(path),
+    (None, PackagedBackend::Valid(path)) => Some(path.clone()),
+    (None, PackagedBackend::Invalid(message)) => {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, message.clone()));
+    }
+    (None, PackagedBackend::Missing) => None,
+  };
+  if let Some(binary_path) = binary_path {
+    info!("Starting packaged backend at {}", binary_path.display());
+    info!("Environment variables being passed:");
+    for (key, value) in env::vars() {
+      if key.starts_with("TAURI_") {
+        info!("  {}={}", key, value);
       }
-    } else {
-      warn!("Packaged backend not found at: {}", binary_path.display());
     }
-  } else {
-    warn!("Could not resolve packaged backend path");
+
+    return match Command::new(&binary_path)
+      .args(extra_args)
+      .envs(env::vars())
+      .env(backend_client::TOKEN_ENV, &config.backend_token)
+      .envs(db_encryption::backend_env(app))
+      .spawn() {
+      Ok(child) => {
+        info!("Backend process spawned successfully with PID: {}", child.id());
+        Ok(child)
+      }
+      Err(e) => {
+        warn!("Failed to spawn packaged backend: {}", e);
+        Err(e)
+      }
+    };
   }
 
   let fallback = shell_words::split(&config.launcher_command)
@@ -441,22 +1048,60 @@ fn spawn_backend(app: &tauri::AppHandle, config: &DesktopConfig) -> Result<Child
   info!("Launching backend via fallback command: {} {:?}", program, args);
   Command::new(&program)
     .args(args)
+    .args(extra_args)
     .current_dir(&config.repo_root)
     .envs(env::vars())
+    .env(backend_client::TOKEN_ENV, &config.backend_token)
+    .envs(db_encryption::backend_env(app))
     .spawn()
 }
 
-fn wait_for_backend_ready(app_handle: tauri::AppHandle, config: DesktopConfig) {
+/// Spawn the backend for the managed config and track it as the running sidecar
+fn start_backend(app: &tauri::AppHandle) -> Result<(), ShellError> {
+  let config = app.state::<DesktopConfig>();
+  let child = spawn_backend(app, &config, &[]).map_err(|e| {
+    let message = format!("Failed to start backend: {}", e);
+    // A remote backend isn't started by this app
+    if e.kind() == io::ErrorKind::Unsupported {
+      ShellError::unsupported(message)
+    } else {
+      ShellError::unavailable(message)
+    }
+  })?;
+  app.state::<BackendProcess>().replace(child);
+  Ok(())
+}
+
+/// Poll the health endpoint until it answers or `limit` passes
+async fn wait_for_backend_health(app: &tauri::AppHandle, limit: Duration) -> bool {
+  let health_url = app.state::<DesktopConfig>().health_url.clone();
+  let start = Instant::now();
+  let mut delay = READY_MIN_DELAY;
+  while start.elapsed() < limit {
+    if backend_is_ready(&health_url).await {
+      return true;
+    }
+    delay = backoff(delay).await;
+  }
+  false
+}
+
+/// Show the main window once the backend answers and `setup_finished` fires
+fn wait_for_backend_ready(app_handle: tauri::AppHandle, config: DesktopConfig, setup_finished: oneshot::Receiver<()>) {
   async_runtime::spawn(async move {
-    let splash = app_handle.get_webview_window("splashscreen");
-    let main = app_handle.get_webview_window("main");
     let start = Instant::now();
-    loop {
-      if backend_is_ready(&config.health_url) {
-        break;
-      }
-      sleep(Duration::from_millis(250)).await;
+    let mut delay = READY_MIN_DELAY;
+    // Without a backend the window still opens, so one can be configured
+    let has_backend = config.backend_mode != BackendMode::Unconfigured;
+    while has_backend && !backend_is_ready(&config.health_url).await {
+      delay = backoff(delay).await;
+    }
+    // Setup failing drops the sender; the app is exiting then anyway
+    if setup_finished.await.is_err() {
+      return;
     }
+    let splash = app_handle.get_webview_window("splashscreen");
+    let main = app_handle.get_webview_window("main");
 
     let elapsed = start.elapsed();
     if config.splash_min > elapsed {
@@ -475,10 +1120,7 @@ fn wait_for_backend_ready(app_handle: tauri::AppHandle, config: DesktopConfig) {
         let _ = window.set_fullscreen(false);
         
         // Reapply size constraints after backend is ready
-        if let Ok(Some(monitor)) = window.current_monitor() {
-          let screen_size = monitor.size();
-          info!("Monitor screen size: {}x{}", screen_size.width, screen_size.height);
-          
+        if let Some(screen_size) = screen_size(&window) {
           // Use config width if set, otherwise default to 51% of screen + 510px
           let width = config.window_width
             .unwrap_or_else(|| (screen_size.width as f64 * 0.51) + 510.0)
@@ -494,22 +1136,175 @@ fn wait_for_backend_ready(app_handle: tauri::AppHandle, config: DesktopConfig) {
           let _ = window.center();
         }
       }
-      let _ = window.show();
-      let _ = window.set_focus();
+      if config.start_hidden {
+        info!("Launched at login with {}, staying in the tray", autostart::HIDDEN_FLAG);
+      } else if app_lock::is_locked(&app_handle) {
+        app_lock::prompt_unlock(&app_handle);
+      } else {
+        let _ = window.show();
+        let _ = window.set_focus();
+      }
     }
     if let Some(window) = splash {
       let _ = window.close();
     }
-    info!("Backend ready. Main window displayed.");
+    startup_repairs::report(&app_handle);
+    jobs::start_queue(&app_handle);
+    if !has_backend {
+      info!("Main window displayed without a backend.");
+      return;
+    }
+    if config.headless {
+      info!("Backend ready.");
+    } else {
+      info!("Backend ready. Main window displayed.");
+    }
+    backend_events::set_backend_healthy(&app_handle, true);
+
+    monitor_backend_health(app_handle, config.health_url).await;
   });
 }
 
-fn backend_is_ready(url: &str) -> bool {
-  ureq::get(url)
-    .timeout(Duration::from_millis(500))
-    .call()
-    .map(|response| response.status() == 200)
-    .unwrap_or(false)
+/// Payload of `backend-health`
+#[derive(Serialize, Clone, specta::Type)]
+struct BackendHealthEvent {
+  healthy: bool,
+}
+
+/// Keep checking the backend after startup, emitting `backend-health` when it
+/// stops responding or recovers. The interval comes from the live settings.
+async fn monitor_backend_health(app_handle: tauri::AppHandle, health_url: String) {
+  let mut healthy = true;
+  loop {
+    let mut interval = app_handle
+      .state::<settings::SettingsState>()
+      .current()
+      .backend
+      .health_interval_secs;
+    if power::saving() {
+      interval *= power::HEALTH_INTERVAL_FACTOR;
+    }
+    sleep(Duration::from_secs(interval)).await;
+
+    let now_healthy = backend_is_ready(&health_url).await;
+    if now_healthy == healthy {
+      continue;
+    }
+    healthy = now_healthy;
+    if healthy {
+      info!("Backend is responding again");
+    } else {
+      warn!("Backend health check failed at {}", health_url);
+    }
+    backend_events::set_backend_healthy(&app_handle, healthy);
+    if let Err(e) = app_handle.emit("backend-health", BackendHealthEvent { healthy }) {
+      warn!("Failed to emit backend-health event: {}", e);
+    }
+  }
+}
+
+/// `delay` give or take a quarter, so probes from restarts don't line up
+fn jittered(delay: Duration) -> Duration {
+  let spread = OsRng.next_u32() as f64 / u32::MAX as f64;
+  delay.mul_f64(0.75 + spread * 0.5)
+}
+
+/// Sleep for about `delay` and return the next one, doubled up to `READY_MAX_DELAY`
+async fn backoff(delay: Duration) -> Duration {
+  sleep(jittered(delay)).await;
+  (delay * 2).min(READY_MAX_DELAY)
+}
+
+/// `host:port` and path of an `http://` URL
+fn split_http_url(url: &str) -> Option<(String, &str)> {
+  let rest = url.strip_prefix("http://")?;
+  let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+  let has_port = authority
+    .rsplit_once(':')
+    .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+  let address = if has_port { authority.to_string() } else { format!("{authority}:80") };
+  Some((address, path))
+}
+
+/// Whether the backend answers its health endpoint with 200. A plain TCP
+/// connect comes first, so while the backend isn't listening a probe costs a
+/// refused connection; the connect and the request each have a short timeout.
+async fn backend_is_ready(url: &str) -> bool {
+  let Some((address, path)) = split_http_url(url) else {
+    warn!("Health URL {} is not plain http", url);
+    return false;
+  };
+  let Ok(Ok(mut stream)) = timeout(HEALTH_CONNECT_TIMEOUT, TcpStream::connect(&address)).await else {
+    return false;
+  };
+  let request = format!("GET {path} HTTP/1.1\r\nHost: {address}\r\nConnection: close\r\n\r\n");
+  let exchange = async {
+    stream.write_all(request.as_bytes()).await?;
+    // "HTTP/1.1 200" is all that's needed of the response
+    let mut head = [0u8; 12];
+    stream.read_exact(&mut head).await?;
+    Ok::<_, std::io::Error>(head)
+  };
+  match timeout(HEALTH_REQUEST_TIMEOUT, exchange).await {
+    Ok(Ok(head)) => head.starts_with(b"HTTP/1.") && &head[8..] == b" 200",
+    _ => false,
+  }
+}
+
+/// Why a backend binary can't be launched: it must be a non-empty file,
+/// executable on Unix, and match `<binary>.sha256` when one ships beside it
+fn validate_backend_binary(path: &Path) -> Result<(), String> {
+  let meta = std::fs::metadata(path).map_err(|e| format!("cannot be read ({e})"))?;
+  if !meta.is_file() || meta.len() == 0 {
+    return Err("not a non-empty regular file".to_string());
+  }
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    if meta.permissions().mode() & 0o111 == 0 {
+      return Err("not executable".to_string());
+    }
+  }
+
+  let mut checksum_name = path.file_name().unwrap_or_default().to_os_string();
+  checksum_name.push(".sha256");
+  let Ok(expected) = std::fs::read_to_string(path.with_file_name(checksum_name)) else {
+    return Ok(());
+  };
+  let expected = expected.split_whitespace().next().unwrap_or_default();
+  let mut hasher = Sha256::new();
+  File::open(path)
+    .and_then(|mut file| io::copy(&mut file, &mut hasher))
+    .map_err(|e| format!("cannot be read ({e})"))?;
+  let actual: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+  if !actual.eq_ignore_ascii_case(expected) {
+    return Err(format!("checksum mismatch (expected {expected}, found {actual})"));
+  }
+  Ok(())
+}
+
+/// Find and check the bundled backend; `spawn_backend` uses the result for
+/// every start and restart
+fn resolve_packaged_backend(app: &tauri::AppHandle, config: &DesktopConfig) -> PackagedBackend {
+  let Some(path) = packaged_backend_path(app, config) else {
+    warn!("Could not resolve packaged backend path");
+    return PackagedBackend::Missing;
+  };
+  if !path.exists() {
+    warn!("Packaged backend not found at: {}", path.display());
+    return PackagedBackend::Missing;
+  }
+  match validate_backend_binary(&path) {
+    Ok(()) => {
+      info!("Packaged backend at {}", path.display());
+      PackagedBackend::Valid(path)
+    }
+    Err(reason) => {
+      let message = format!("Backend binary invalid: {}: {}", path.display(), reason);
+      warn!("{}", message);
+      PackagedBackend::Invalid(message)
+    }
+  }
 }
 
 fn packaged_backend_path(app: &tauri::AppHandle, config: &DesktopConfig) -> Option<PathBuf> {
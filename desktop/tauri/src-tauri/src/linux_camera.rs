@@ -0,0 +1,99 @@
+//! Camera photos on Linux. nokhwa is only built for macOS, where AVFoundation
+//! is reliable; on Linux its V4L2 backend trips over many webcams' pixel
+//! formats, so frames are grabbed with a one-shot `ffmpeg -f v4l2` instead.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info};
+
+use crate::capture::{CaptureKind, CaptureManager, CaptureMode, CaptureOptions, CaptureSource};
+use crate::capture_names;
+use crate::health::find_on_path;
+
+const DEVICE: &str = "/dev/video0";
+
+/// Longest burst, as on macOS
+const MAX_BURST: u32 = 20;
+
+/// Frames read before the one that is kept, while auto-exposure settles
+const WARMUP_FRAMES: u32 = 10;
+
+/// A V4L2 camera (`/dev/video0` unless `options.camera` says otherwise),
+/// through ffmpeg
+struct V4l2Camera;
+
+fn grab_frame(device: &str, path: &Path) -> Result<(), String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-f", "v4l2", "-i", device])
+        .args(["-vf", &format!("select=gte(n\\,{})", WARMUP_FRAMES), "-frames:v", "1", "-q:v", "2", "-y"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to start ffmpeg: {}. Make sure ffmpeg is installed.", e))?;
+    if !output.status.success() || !path.is_file() {
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to capture from {}: {}", device, reason.trim()));
+    }
+    Ok(())
+}
+
+impl CaptureSource for V4l2Camera {
+    fn id(&self) -> &'static str {
+        "camera"
+    }
+
+    fn label(&self) -> &'static str {
+        "Camera"
+    }
+
+    fn kind(&self) -> CaptureKind {
+        CaptureKind::Photo
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Snapshot
+    }
+
+    fn media_subdir(&self) -> &'static str {
+        "photos"
+    }
+
+    fn available(&self) -> bool {
+        Path::new(DEVICE).exists() && find_on_path("ffmpeg").is_some()
+    }
+
+    fn capture(&self, dir: &Path, options: &CaptureOptions) -> Result<Vec<PathBuf>, String> {
+        let count = options.count.unwrap_or(1);
+        if count == 0 || count > MAX_BURST {
+            return Err(format!("A burst is 1 to {} photos", MAX_BURST));
+        }
+        let interval = Duration::from_millis(options.interval_ms.unwrap_or(200));
+        let device = options.camera.map_or_else(|| DEVICE.to_string(), |camera| format!("/dev/video{}", camera));
+        if !Path::new(&device).exists() {
+            return Err(format!("{} doesn't exist", device));
+        }
+
+        let mut paths = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            if i > 0 {
+                thread::sleep(interval);
+            }
+            let seq = (count > 1).then_some(i + 1);
+            let stem = capture_names::stem(dir, &[".jpg"], CaptureKind::Photo, options, seq)?;
+            let path = dir.join(format!("{}.jpg", stem));
+            debug!(path = ?path, "grabbing frame with ffmpeg");
+            grab_frame(&device, &path)?;
+            paths.push(path);
+        }
+        info!(count, "photos saved through ffmpeg v4l2");
+        Ok(paths)
+    }
+}
+
+/// Register the Linux capture sources
+pub fn register_sources(manager: &mut CaptureManager) {
+    manager.register(V4l2Camera);
+}
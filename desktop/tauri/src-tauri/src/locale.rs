@@ -0,0 +1,220 @@
+use std::env;
+use std::sync::RwLock;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::settings::{self, SettingsState};
+
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// Locale the native strings are currently rendered in
+static CURRENT: RwLock<String> = RwLock::new(String::new());
+
+/// Labels the shell itself shows in native UI (tray, notifications, media
+/// controls, jump list); everything else is translated by the webview
+#[derive(Clone, Copy, Debug)]
+// Which labels are used depends on the platform
+#[allow(dead_code)]
+pub enum Text {
+    ShowApp,
+    Quit,
+    Open,
+    Actions,
+    Recording,
+    Timer,
+    Recent,
+    AutomationTitle,
+    /// `{action}` is replaced with the requested action, e.g. `timer/start`
+    AutomationRequest,
+    AllowOnce,
+    AlwaysAllow,
+    Deny,
+    FocusTime,
+    ShortBreak,
+    LongBreak,
+    /// `{minutes}` is replaced with the length of the phase
+    PhaseMinutes,
+    Reminder,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLocale {
+    /// BCP 47 tag in use, e.g. `de-DE`
+    pub locale: String,
+    /// Whether it was chosen by the user or follows the OS
+    pub follows_system: bool,
+    /// Default language for speech recognition and text-to-speech voices
+    pub speech_locale: String,
+}
+
+/// The OS language, e.g. `fr-CA`
+pub fn system_locale() -> String {
+    sys_locale::get_locale()
+        .and_then(|tag| normalize(&tag).ok())
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
+
+/// Check a BCP 47 language tag and put it in canonical `ll-RR` form.
+/// POSIX-style tags (`de_DE.UTF-8`) are accepted.
+pub fn normalize(tag: &str) -> Result<String, String> {
+    let tag = tag.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let valid_language = (2..=3).contains(&language.len()) && language.chars().all(|ch| ch.is_ascii_alphabetic());
+    let rest: Vec<&str> = subtags.collect();
+    let valid_rest = rest
+        .iter()
+        .all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|ch| ch.is_ascii_alphanumeric()));
+    if !valid_language || !valid_rest {
+        return Err(format!("Invalid locale: {:?}", tag));
+    }
+
+    let mut normalized = language.to_ascii_lowercase();
+    for subtag in rest {
+        normalized.push('-');
+        // Regions are upper case, scripts title case, anything else as given
+        match subtag.len() {
+            2 => normalized.push_str(&subtag.to_ascii_uppercase()),
+            4 => {
+                normalized.push_str(&subtag[..1].to_ascii_uppercase());
+                normalized.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            _ => normalized.push_str(subtag),
+        }
+    }
+    Ok(normalized)
+}
+
+fn language() -> String {
+    let current = CURRENT.read().map(|locale| locale.clone()).unwrap_or_default();
+    current.split('-').next().unwrap_or_default().to_string()
+}
+
+/// `text` in the current app language, falling back to English
+pub fn tr(text: Text) -> &'static str {
+    match (language().as_str(), text) {
+        ("de", Text::ShowApp) => "Track the Thing anzeigen",
+        ("de", Text::Quit) => "Beenden",
+        ("de", Text::Open) => "Öffnen",
+        ("de", Text::Actions) => "Aktionen",
+        ("de", Text::Recording) => "Aufnahme",
+        ("de", Text::Timer) => "Timer",
+        ("de", Text::Recent) => "Zuletzt verwendet",
+        ("de", Text::AutomationTitle) => "Automatisierung erlauben?",
+        ("de", Text::AutomationRequest) => "Eine andere App möchte „{action}“ ausführen.",
+        ("de", Text::AllowOnce) => "Einmal erlauben",
+        ("de", Text::AlwaysAllow) => "Immer erlauben",
+        ("de", Text::Deny) => "Ablehnen",
+        ("de", Text::FocusTime) => "Fokuszeit",
+        ("de", Text::ShortBreak) => "Kurze Pause",
+        ("de", Text::LongBreak) => "Lange Pause",
+        ("de", Text::PhaseMinutes) => "{minutes} Minuten",
+        ("de", Text::Reminder) => "Erinnerung",
+
+        ("es", Text::ShowApp) => "Mostrar Track the Thing",
+        ("es", Text::Quit) => "Salir",
+        ("es", Text::Open) => "Abrir",
+        ("es", Text::Actions) => "Acciones",
+        ("es", Text::Recording) => "Grabación",
+        ("es", Text::Timer) => "Temporizador",
+        ("es", Text::Recent) => "Recientes",
+        ("es", Text::AutomationTitle) => "¿Permitir la automatización?",
+        ("es", Text::AutomationRequest) => "Otra aplicación quiere ejecutar «{action}».",
+        ("es", Text::AllowOnce) => "Permitir una vez",
+        ("es", Text::AlwaysAllow) => "Permitir siempre",
+        ("es", Text::Deny) => "Denegar",
+        ("es", Text::FocusTime) => "Tiempo de concentración",
+        ("es", Text::ShortBreak) => "Descanso corto",
+        ("es", Text::LongBreak) => "Descanso largo",
+        ("es", Text::PhaseMinutes) => "{minutes} minutos",
+        ("es", Text::Reminder) => "Recordatorio",
+
+        ("fr", Text::ShowApp) => "Afficher Track the Thing",
+        ("fr", Text::Quit) => "Quitter",
+        ("fr", Text::Open) => "Ouvrir",
+        ("fr", Text::Actions) => "Actions",
+        ("fr", Text::Recording) => "Enregistrement",
+        ("fr", Text::Timer) => "Minuteur",
+        ("fr", Text::Recent) => "Récents",
+        ("fr", Text::AutomationTitle) => "Autoriser l’automatisation ?",
+        ("fr", Text::AutomationRequest) => "Une autre app veut exécuter « {action} ».",
+        ("fr", Text::AllowOnce) => "Autoriser une fois",
+        ("fr", Text::AlwaysAllow) => "Toujours autoriser",
+        ("fr", Text::Deny) => "Refuser",
+        ("fr", Text::FocusTime) => "Temps de concentration",
+        ("fr", Text::ShortBreak) => "Courte pause",
+        ("fr", Text::LongBreak) => "Longue pause",
+        ("fr", Text::PhaseMinutes) => "{minutes} minutes",
+        ("fr", Text::Reminder) => "Rappel",
+
+        (_, Text::ShowApp) => "Show Track the Thing",
+        (_, Text::Quit) => "Quit",
+        (_, Text::Open) => "Open",
+        (_, Text::Actions) => "Actions",
+        (_, Text::Recording) => "Recording",
+        (_, Text::Timer) => "Timer",
+        (_, Text::Recent) => "Recent",
+        (_, Text::AutomationTitle) => "Allow automation?",
+        (_, Text::AutomationRequest) => "Another app wants to run “{action}”.",
+        (_, Text::AllowOnce) => "Allow Once",
+        (_, Text::AlwaysAllow) => "Always Allow",
+        (_, Text::Deny) => "Deny",
+        (_, Text::FocusTime) => "Focus time",
+        (_, Text::ShortBreak) => "Short break",
+        (_, Text::LongBreak) => "Long break",
+        (_, Text::PhaseMinutes) => "{minutes} minutes",
+        (_, Text::Reminder) => "Reminder",
+    }
+}
+
+/// Switch the native strings to `locale`, export it for the backend and tell
+/// the webview. Does nothing if it is already current.
+pub fn apply(app: &AppHandle, locale: &str) {
+    {
+        let Ok(mut current) = CURRENT.write() else {
+            return;
+        };
+        if *current == locale {
+            return;
+        }
+        *current = locale.to_string();
+    }
+    info!("App locale: {}", locale);
+
+    // Read by the backend at startup; a running backend picks it up on its next launch
+    env::set_var("TAURI_APP_LOCALE", locale);
+
+    crate::tray::refresh_menu(app);
+    if let Err(e) = app.emit("locale-changed", locale) {
+        warn!("Failed to emit locale-changed event: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn get_app_locale(state: tauri::State<'_, SettingsState>) -> Result<AppLocale, String> {
+    let settings = state.current();
+    Ok(AppLocale {
+        locale: settings.app_locale(),
+        follows_system: settings.general.locale.is_none(),
+        speech_locale: settings.speech_locale(),
+    })
+}
+
+/// Set the app language (BCP 47 tag), or follow the OS again with `None`
+#[tauri::command]
+pub async fn set_app_locale(
+    app: AppHandle,
+    state: tauri::State<'_, SettingsState>,
+    tag: Option<String>,
+) -> Result<AppLocale, String> {
+    let locale = tag.as_deref().map(normalize).transpose()?;
+    let updated = settings::update(&app, &state, |file| file.general.locale = locale)?;
+    Ok(AppLocale {
+        locale: updated.app_locale(),
+        follows_system: updated.general.locale.is_none(),
+        speech_locale: updated.speech_locale(),
+    })
+}
@@ -0,0 +1,45 @@
+#import <Foundation/Foundation.h>
+#import <LocalAuthentication/LocalAuthentication.h>
+
+// Whether Touch ID (or another biometric) is enrolled and usable
+bool lock_biometrics_available(void) {
+    @autoreleasepool {
+        LAContext *context = [[LAContext alloc] init];
+        return [context canEvaluatePolicy:LAPolicyDeviceOwnerAuthenticationWithBiometrics error:nil];
+    }
+}
+
+// Prompt for Touch ID, falling back to the account password. Blocks until the
+// user responds, so it must not be called on the main thread.
+// On failure *error_out receives a heap-allocated message the caller frees.
+bool lock_authenticate(const char *reason, char **error_out) {
+    @autoreleasepool {
+        LAContext *context = [[LAContext alloc] init];
+        NSString *localizedReason = [NSString stringWithUTF8String:reason];
+        
+        __block BOOL succeeded = NO;
+        __block NSString *failure = nil;
+        dispatch_semaphore_t done = dispatch_semaphore_create(0);
+        
+        [context evaluatePolicy:LAPolicyDeviceOwnerAuthentication
+                localizedReason:localizedReason
+                          reply:^(BOOL success, NSError *error) {
+            succeeded = success;
+            if (!success && error != nil) {
+                failure = error.localizedDescription;
+            }
+            dispatch_semaphore_signal(done);
+        }];
+        
+        dispatch_semaphore_wait(done, DISPATCH_TIME_FOREVER);
+        
+        if (!succeeded && error_out != NULL) {
+            *error_out = strdup((failure ?: @"Authentication failed").UTF8String);
+        }
+        return succeeded;
+    }
+}
+
+void lock_free_string(char *value) {
+    free(value);
+}
@@ -0,0 +1,398 @@
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Local, NaiveDate};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use tauri_plugin_log::{fern, Target, TargetKind};
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::audit::AuditLayer;
+use crate::settings::{self, SettingsState};
+
+/// The shell's log; the backend writes `backend.log` next to it
+const LOG_NAME: &str = "desktop";
+
+/// A file is rotated when it would grow past this, or at the first write on a new day
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept besides the current one
+const KEEP_ROTATED: usize = 5;
+
+/// Lines logged before the data dir is known (or while it moves) are held
+/// here, up to this size, and written once the file is open again
+const MAX_PENDING_BYTES: usize = 1024 * 1024;
+
+/// Swaps the filter of traced code when `logging.filter` changes
+static TRACE_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+static LOG_FILE: Mutex<LogFile> = Mutex::new(LogFile {
+    dir: None,
+    file: None,
+    size: 0,
+    opened_on: None,
+    pending: Vec::new(),
+});
+
+struct LogFile {
+    dir: Option<PathBuf>,
+    file: Option<File>,
+    size: u64,
+    opened_on: Option<NaiveDate>,
+    pending: Vec<u8>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogSource {
+    Shell,
+    Backend,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFileInfo {
+    pub name: String,
+    pub path: String,
+    pub source: LogSource,
+    pub bytes: u64,
+    pub modified: Option<DateTime<Local>>,
+    /// The file currently written to
+    pub current: bool,
+}
+
+impl LogFile {
+    fn current_path(dir: &Path) -> PathBuf {
+        dir.join(format!("{}.log", LOG_NAME))
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::current_path(dir))?;
+        self.size = file.metadata()?.len();
+        self.opened_on = Some(Local::now().date_naive());
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Rename the current file to `desktop-<time>.log` and drop the oldest
+    /// rotated files beyond `KEEP_ROTATED`
+    fn rotate(&mut self) -> io::Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        self.file = None;
+        let rotated = dir.join(format!("{}-{}.log", LOG_NAME, Local::now().format("%Y%m%d-%H%M%S")));
+        fs::rename(Self::current_path(&dir), rotated)?;
+
+        let mut old: Vec<PathBuf> = fs::read_dir(&dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_rotated(path))
+            .collect();
+        // The timestamp sorts chronologically
+        old.sort();
+        let excess = old.len().saturating_sub(KEEP_ROTATED);
+        for path in old.into_iter().take(excess) {
+            let _ = fs::remove_file(path);
+        }
+        self.open()
+    }
+
+    fn write_line(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.file.is_none() {
+            if self.pending.len() + buf.len() <= MAX_PENDING_BYTES {
+                self.pending.extend_from_slice(buf);
+            }
+            return Ok(());
+        }
+        let new_day = self.opened_on != Some(Local::now().date_naive());
+        if self.size > 0 && (new_day || self.size + buf.len() as u64 > MAX_FILE_BYTES) {
+            self.rotate()?;
+        }
+        if let Some(file) = &mut self.file {
+            file.write_all(buf)?;
+            self.size += buf.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+fn is_rotated(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(&format!("{}-", LOG_NAME)) && name.ends_with(".log"))
+}
+
+/// Feeds the log plugin's formatted lines into the rotating file.
+/// Must not log itself: it runs inside the logger.
+struct RotatingWriter;
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut log = LOG_FILE.lock().map_err(|_| io::Error::other("log file lock poisoned"))?;
+        log.write_line(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut log = LOG_FILE.lock().map_err(|_| io::Error::other("log file lock poisoned"))?;
+        match &mut log.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Log plugin target writing to `<data dir>/logs/desktop.log`
+pub fn file_target() -> Target {
+    let writer: Box<dyn Write + Send> = Box::new(RotatingWriter);
+    Target::new(TargetKind::Dispatch(fern::Dispatch::new().chain(writer)))
+}
+
+/// Fields of a span or event as `key=value` pairs, with the message apart
+#[derive(Default)]
+struct Fields {
+    message: String,
+    pairs: String,
+}
+
+impl Fields {
+    fn push(&mut self, field: &Field, value: fmt::Arguments) {
+        if field.name() == "message" {
+            let _ = self.message.write_fmt(value);
+        } else {
+            if !self.pairs.is_empty() {
+                self.pairs.push(' ');
+            }
+            let _ = write!(self.pairs, "{}={}", field.name(), value);
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format_args!("{:?}", value));
+    }
+}
+
+/// A span's fields, rendered once when it is created or recorded to
+struct SpanFields(String);
+
+/// Hands `tracing` events to the log plugin, prefixed with the spans they
+/// happened in (`speech_session{id=3}: stopped`), so traced code ends up in
+/// the same stdout and file targets as everything else
+struct LogBridge;
+
+impl<S> Layer<S> for LogBridge
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields.pairs));
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(pairs)) = extensions.get_mut::<SpanFields>() {
+            if !pairs.is_empty() && !fields.pairs.is_empty() {
+                pairs.push(' ');
+            }
+            pairs.push_str(&fields.pairs);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut line = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                line.push_str(span.name());
+                let extensions = span.extensions();
+                if let Some(SpanFields(pairs)) = extensions.get::<SpanFields>() {
+                    if !pairs.is_empty() {
+                        let _ = write!(line, "{{{}}}", pairs);
+                    }
+                }
+                line.push_str(": ");
+            }
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        line.push_str(&fields.message);
+        if !fields.pairs.is_empty() {
+            let _ = write!(line, " {}", fields.pairs);
+        }
+
+        let meta = event.metadata();
+        let level = match *meta.level() {
+            tracing::Level::ERROR => log::Level::Error,
+            tracing::Level::WARN => log::Level::Warn,
+            tracing::Level::INFO => log::Level::Info,
+            tracing::Level::DEBUG => log::Level::Debug,
+            tracing::Level::TRACE => log::Level::Trace,
+        };
+        // Straight to the logger: the filter below has already decided, and
+        // `logging.level` shouldn't hide what `logging.filter` lets through
+        log::logger().log(
+            &log::Record::builder()
+                .level(level)
+                .target(meta.target())
+                .module_path(meta.module_path())
+                .file(meta.file())
+                .line(meta.line())
+                .args(format_args!("{}", line))
+                .build(),
+        );
+    }
+}
+
+/// Route `tracing` events through the log plugin, filtered by `info` until
+/// the settings are applied, and feed Tauri's IPC spans to the command audit.
+/// Each has its own filter. Call after the log plugin is registered.
+pub fn init_tracing() {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+    let subscriber = Registry::default()
+        .with(LogBridge.with_filter(filter))
+        .with(AuditLayer.with_filter(AuditLayer::filter()));
+    match tracing::subscriber::set_global_default(subscriber) {
+        Ok(()) => {
+            let _ = TRACE_FILTER.set(handle);
+        }
+        Err(e) => warn!("Failed to install the tracing subscriber: {}", e),
+    }
+}
+
+/// Replace the filter of traced code with `directives` (`RUST_LOG` syntax)
+pub fn set_trace_filter(directives: &str) {
+    let Some(handle) = TRACE_FILTER.get() else {
+        return;
+    };
+    match EnvFilter::try_new(directives) {
+        Ok(filter) => {
+            if let Err(e) = handle.reload(filter) {
+                warn!("Failed to apply log filter: {}", e);
+            }
+        }
+        Err(e) => warn!("Invalid log filter {:?}: {}", directives, e),
+    }
+}
+
+/// Write the shell log to `dir` from now on. `None` closes the file (so the
+/// folder can be moved) and holds new lines until a directory is set again.
+pub fn set_log_dir(dir: Option<PathBuf>) {
+    let Ok(mut log) = LOG_FILE.lock() else {
+        return;
+    };
+    log.file = None;
+    log.dir = dir;
+    if let Err(e) = log.open() {
+        eprintln!("Failed to open log file: {}", e);
+        return;
+    }
+    let pending = std::mem::take(&mut log.pending);
+    if !pending.is_empty() {
+        let _ = log.write_line(&pending);
+    }
+}
+
+/// Directory of the shell and backend logs
+pub fn log_dir() -> Option<PathBuf> {
+    LOG_FILE.lock().ok().and_then(|log| log.dir.clone())
+}
+
+/// The shell's and the backend's log files, newest first
+pub fn list_log_files() -> Vec<LogFileInfo> {
+    let Some(dir) = log_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let current = LogFile::current_path(&dir);
+    let mut files: Vec<LogFileInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_name()?.to_string_lossy().to_string();
+            if !name.ends_with(".log") {
+                return None;
+            }
+            let source = if name.starts_with(LOG_NAME) {
+                LogSource::Shell
+            } else if name.starts_with("backend") {
+                LogSource::Backend
+            } else {
+                return None;
+            };
+            let meta = entry.metadata().ok()?;
+            Some(LogFileInfo {
+                current: path == current,
+                path: path.to_string_lossy().to_string(),
+                name,
+                source,
+                bytes: meta.len(),
+                modified: meta.modified().ok().map(DateTime::<Local>::from),
+            })
+        })
+        .collect();
+    files.sort_by_key(|file| std::cmp::Reverse(file.modified));
+    files
+}
+
+/// Change the shell's log level (`error` to `trace`). Applies immediately and
+/// is saved as `logging.level`.
+#[tauri::command]
+pub async fn set_log_level(app: AppHandle, state: State<'_, SettingsState>, level: String) -> Result<String, String> {
+    let level = level.trim().to_ascii_lowercase();
+    settings::update(&app, &state, |file| file.logging.level = level.clone())?;
+    info!("Log level set to {}", level);
+    Ok(level)
+}
+
+/// Set per-module log directives (`RUST_LOG` syntax, e.g.
+/// `info,app_lib::speech=trace`) for the media and speech code, or clear them
+/// with `None` to follow the log level. Saved as `logging.filter`.
+#[tauri::command]
+pub async fn set_log_filter(
+    app: AppHandle,
+    state: State<'_, SettingsState>,
+    filter: Option<String>,
+) -> Result<Option<String>, String> {
+    let filter = filter.map(|filter| filter.trim().to_string()).filter(|filter| !filter.is_empty());
+    settings::update(&app, &state, |file| file.logging.filter = filter.clone())?;
+    info!("Log filter set to {:?}", filter);
+    Ok(filter)
+}
+
+/// Log files for the diagnostics UI
+#[tauri::command]
+pub async fn get_log_files() -> Result<Vec<LogFileInfo>, String> {
+    Ok(list_log_files())
+}
@@ -1,232 +1,667 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::ExtendedColorType;
 use nokhwa::pixel_format::RgbFormat;
-use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
-use nokhwa::Camera;
-use std::fs;
-use std::path::PathBuf;
+use nokhwa::utils::{CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType};
+use nokhwa::{Buffer, Camera};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::process::{Child, Command};
-use std::sync::Mutex;
-use tauri::{AppHandle, Manager};
-use chrono;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, info, instrument, warn};
 
-// Store the ffmpeg process for video recording
-static VIDEO_RECORDER: Mutex<Option<(Child, PathBuf)>> = Mutex::new(None);
+use crate::error::ShellError;
+use crate::capture::{self, CaptureKind, CaptureManager, CaptureMode, CaptureOptions, CaptureSource};
+use crate::capture_names;
 
-/// Get or create the media directory within app data
-fn get_media_dir(app: &AppHandle, subdir: &str) -> Result<PathBuf, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+const JPEG_QUALITY: u8 = 90;
 
-    let media_dir = app_data_dir.join(subdir);
-    
-    fs::create_dir_all(&media_dir)
-        .map_err(|e| format!("Failed to create {} directory: {}", subdir, e))?;
+/// Longest burst `capture_photo_burst` takes
+const MAX_BURST: u32 = 20;
+
+/// Plugging in a dock or headset fires several notifications in a row; wait
+/// for them to settle before listing devices
+const DEVICE_SETTLE: Duration = Duration::from_millis(500);
 
-    Ok(media_dir)
+extern "C" {
+    fn devices_watch(callback: extern "C" fn());
+    fn devices_copy_microphones() -> *mut c_char;
+    fn devices_free_string(value: *mut c_char);
+    fn screen_recorder_available() -> bool;
+    fn screen_recorder_start(display_id: u32, window_id: u32, path: *const c_char, error: *mut *mut c_char) -> bool;
+    fn screen_recorder_stop(error: *mut *mut c_char) -> bool;
+    fn screen_recorder_free_string(value: *mut c_char);
 }
 
-#[tauri::command]
-pub async fn capture_photo(app: AppHandle) -> Result<String, String> {
-    println!("[Media] capture_photo command called");
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Bumped on every hot-plug notification so only the last one of a burst emits
+static DEVICE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// `media.segmentMinutes`, 0 when recordings aren't split
+static SEGMENT_MINUTES: AtomicU64 = AtomicU64::new(0);
+
+/// Payload of `media-devices-changed`: the connected devices by name
+#[derive(Serialize, Clone, Debug, specta::Type)]
+pub struct MediaDevices {
+    pub cameras: Vec<String>,
+    pub microphones: Vec<String>,
+}
+
+/// Write one frame to `path` as JPEG. MJPEG frames are already JPEG and are
+/// written as they are; anything else is decoded into `rgb`, which is reused
+/// across a burst, and encoded straight into the file.
+fn save_frame(frame: &Buffer, rgb: &mut Vec<u8>, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut out = BufWriter::new(file);
+    if frame.source_frame_format() == FrameFormat::MJPEG {
+        out.write_all(frame.buffer())
+            .map_err(|e| format!("Failed to save image: {}", e))?;
+    } else {
+        let resolution = frame.resolution();
+        rgb.resize((resolution.width() * resolution.height() * 3) as usize, 0);
+        frame
+            .decode_image_to_buffer::<RgbFormat>(rgb)
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+        JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+            .encode(rgb, resolution.width(), resolution.height(), ExtendedColorType::Rgb8)
+            .map_err(|e| format!("Failed to save image: {}", e))?;
+    }
+    out.into_inner()
+        .map_err(|e| format!("Failed to save image: {}", e.error()))?
+        .sync_all()
+        .map_err(|e| format!("Failed to save image: {}", e))
+}
+
+/// Open camera `index`, let auto-exposure settle, then save `count` frames
+/// `interval` apart, named for `options`. Runs on a blocking thread since
+/// Camera is not Send.
+fn capture_frames(
+    photos_dir: &Path,
+    index: u32,
+    count: u32,
+    interval: Duration,
+    options: &CaptureOptions,
+) -> Result<Vec<PathBuf>, String> {
+    debug!(index, "initializing camera");
+    let camera_index = CameraIndex::Index(index);
+    let requested = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+
+    let mut camera = Camera::new(camera_index, requested)
+        .map_err(|e| format!("Failed to initialize camera: {}", e))?;
+
+    // Open camera stream
+    camera
+        .open_stream()
+        .map_err(|e| format!("Failed to open camera stream: {}", e))?;
+
+    crate::camera_controls::apply(&mut camera, index);
+    debug!("camera stream opened, warming up");
+    
+    // Give the camera a moment to adjust (auto-exposure, etc)
+    std::thread::sleep(Duration::from_millis(500));
     
-    let photos_dir = get_media_dir(&app, "photos")?;
-    println!("[Media] Photos directory: {:?}", photos_dir);
-
-    // Run camera operations in a blocking thread since Camera is not Send
-    // and cannot be held across await points. Using spawn_blocking allows
-    // std::thread::sleep without blocking the async runtime.
-    let result = tokio::task::spawn_blocking(move || {
-        // Initialize camera
-        println!("[Media] Initializing camera...");
-        let index = CameraIndex::Index(0); // Use first camera
-        let requested = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
-
-        let mut camera = Camera::new(index, requested)
-            .map_err(|e| {
-                let err_msg = format!("Failed to initialize camera: {}", e);
-                println!("[Media] Error: {}", err_msg);
-                err_msg
-            })?;
-
-        // Open camera stream
-        camera
-            .open_stream()
-            .map_err(|e| format!("Failed to open camera stream: {}", e))?;
-
-        println!("[Media] Camera stream opened, warming up...");
-        
-        // Give the camera a moment to adjust (auto-exposure, etc)
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        
-        // Capture a few frames to let auto-exposure settle
-        for _ in 0..5 {
-            let _ = camera.frame();
-        }
-
-        // Capture frame
-        println!("[Media] Capturing frame...");
+    // Capture a few frames to let auto-exposure settle
+    for _ in 0..5 {
+        let _ = camera.frame();
+    }
+
+    let mut rgb = Vec::new();
+    let mut paths = Vec::new();
+    for n in 0..count {
+        if n > 0 {
+            std::thread::sleep(interval);
+        }
+        debug!(n, "capturing frame");
         let frame = camera
             .frame()
             .map_err(|e| format!("Failed to capture frame: {}", e))?;
 
-        // Convert to image
-        let image = frame.decode_image::<RgbFormat>()
-            .map_err(|e| format!("Failed to decode image: {}", e))?;
+        let seq = (count > 1).then_some(n + 1);
+        let stem = capture_names::stem(photos_dir, &[".jpg"], CaptureKind::Photo, options, seq)?;
+        let file_path = photos_dir.join(format!("{}.jpg", stem));
+        save_frame(&frame, &mut rgb, &file_path)?;
+        info!(path = ?file_path, "photo saved");
+        paths.push(file_path);
+    }
 
-        // Generate filename
-        let filename = format!("photo_{}.jpg", chrono::Utc::now().timestamp());
-        let file_path = photos_dir.join(&filename);
+    // Stop camera
+    camera.stop_stream()
+        .map_err(|e| format!("Failed to stop camera: {}", e))?;
 
-        // Save image
-        image
-            .save(&file_path)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
+    Ok(paths)
+}
+
+/// A camera (the first unless `options.camera` says otherwise), taking single
+/// photos or bursts with nokhwa
+struct CameraSource;
+
+impl CaptureSource for CameraSource {
+    fn id(&self) -> &'static str {
+        "camera"
+    }
+
+    fn label(&self) -> &'static str {
+        "Camera"
+    }
 
-        // Stop camera
-        camera.stop_stream()
-            .map_err(|e| format!("Failed to stop camera: {}", e))?;
+    fn kind(&self) -> CaptureKind {
+        CaptureKind::Photo
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Snapshot
+    }
 
-        println!("[Media] Photo saved to: {:?}", file_path);
-        
-        Ok::<String, String>(file_path.to_string_lossy().to_string())
-    })
-    .await
-    .map_err(|e| format!("Camera task panicked: {}", e))??;
+    fn media_subdir(&self) -> &'static str {
+        "photos"
+    }
+
+    fn available(&self) -> bool {
+        nokhwa::query(nokhwa::utils::ApiBackend::Auto).is_ok_and(|cameras| !cameras.is_empty())
+    }
 
-    Ok(result)
+    fn capture(&self, dir: &Path, options: &CaptureOptions) -> Result<Vec<PathBuf>, String> {
+        let count = options.count.unwrap_or(1);
+        if count == 0 || count > MAX_BURST {
+            return Err(format!("A burst is 1 to {} photos", MAX_BURST));
+        }
+        let interval = Duration::from_millis(options.interval_ms.unwrap_or(200));
+        capture_frames(dir, options.camera.unwrap_or(0), count, interval, options)
+    }
 }
 
-#[tauri::command]
-pub async fn list_cameras() -> Result<Vec<String>, String> {
-    use nokhwa::query;
+/// The main display, through the system `screencapture` tool
+struct ScreenSource;
 
-    let cameras = query(nokhwa::utils::ApiBackend::Auto)
-        .map_err(|e| format!("Failed to query cameras: {}", e))?;
+impl CaptureSource for ScreenSource {
+    fn id(&self) -> &'static str {
+        "screen"
+    }
 
-    let camera_names: Vec<String> = cameras
-        .iter()
-        .map(|info| info.human_name().to_string())
-        .collect();
+    fn label(&self) -> &'static str {
+        "Screen"
+    }
 
-    Ok(camera_names)
+    fn kind(&self) -> CaptureKind {
+        CaptureKind::Screenshot
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Snapshot
+    }
+
+    fn media_subdir(&self) -> &'static str {
+        "screenshots"
+    }
+
+    fn capture(&self, dir: &Path, options: &CaptureOptions) -> Result<Vec<PathBuf>, String> {
+        let stem = capture_names::stem(dir, &[".png"], CaptureKind::Screenshot, options, None)?;
+        let path = dir.join(format!("{}.png", stem));
+        // -x: no shutter sound
+        let status = Command::new("screencapture")
+            .arg("-x")
+            .arg(&path)
+            .status()
+            .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+        if !status.success() || !path.is_file() {
+            return Err("The screenshot was not taken; check the Screen Recording permission".to_string());
+        }
+        info!(path = ?path, "screenshot saved");
+        Ok(vec![path])
+    }
 }
 
-#[tauri::command]
-pub async fn start_video_recording(app: AppHandle) -> Result<String, String> {
-    println!("[Media] start_video_recording command called");
-    
-    // Check if already recording
-    {
-        let guard = VIDEO_RECORDER.lock().map_err(|e| format!("Lock error: {}", e))?;
+/// Split recordings into files of `minutes` from the next recording on
+pub fn set_segment_minutes(minutes: Option<u64>) {
+    SEGMENT_MINUTES.store(minutes.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// ffmpeg's CSV list of the closed segments of a split recording, and how
+/// many of them were handed out already
+struct SegmentList {
+    path: PathBuf,
+    reported: usize,
+}
+
+impl SegmentList {
+    fn read(&self) -> Vec<PathBuf> {
+        let dir = self.path.parent().unwrap_or(Path::new(""));
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            // `<file>,<start>,<end>`; our file names never need quoting
+            .filter_map(|line| line.split(',').next())
+            .filter(|name| !name.is_empty())
+            .map(|name| dir.join(name))
+            .collect()
+    }
+}
+
+/// Records from AVFoundation devices with ffmpeg until stopped
+struct FfmpegSource {
+    id: &'static str,
+    label: &'static str,
+    kind: CaptureKind,
+    media_subdir: &'static str,
+    /// Input and encoder arguments, without the output file
+    args: &'static [&'static str],
+    /// The running ffmpeg process, with its output file and when it started
+    recorder: Mutex<Option<(Child, PathBuf, Instant)>>,
+    /// Set while a split recording runs and until its last segments are taken
+    segments: Mutex<Option<SegmentList>>,
+}
+
+impl CaptureSource for FfmpegSource {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn label(&self) -> &'static str {
+        self.label
+    }
+
+    fn kind(&self) -> CaptureKind {
+        self.kind
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Recording
+    }
+
+    fn media_subdir(&self) -> &'static str {
+        self.media_subdir
+    }
+
+    fn available(&self) -> bool {
+        crate::health::find_on_path("ffmpeg").is_some()
+    }
+
+    fn recording_elapsed(&self) -> Option<Duration> {
+        let recorder = self.recorder.lock().ok()?;
+        recorder.as_ref().map(|(_, _, started)| started.elapsed())
+    }
+
+    fn start_recording(&self, dir: &Path, options: &CaptureOptions) -> Result<PathBuf, String> {
+        let mut guard = self.recorder.lock().map_err(|e| format!("Lock error: {}", e))?;
         if guard.is_some() {
-            return Err("Already recording video".to_string());
+            return Err(format!("{} is already recording", self.label));
         }
+
+        // A split recording's files are `<stem>_000.webm` onwards
+        let suffixes = [".webm", "_000.webm", ".segments.csv"];
+        let stem = capture_names::stem(dir, &suffixes, self.kind, options, None)?;
+        let mut command = Command::new("ffmpeg");
+        command.args(["-f", "avfoundation"]).args(self.args);
+
+        let segment_minutes = SEGMENT_MINUTES.load(Ordering::Relaxed);
+        let (file_path, segment_list) = if segment_minutes > 0 {
+            // Each segment is a complete file, playable while later ones are recorded
+            let list = dir.join(format!("{}.segments.csv", stem));
+            let _ = std::fs::remove_file(&list);
+            command
+                .args(["-f", "segment", "-segment_format", "webm", "-reset_timestamps", "1"])
+                .args(["-segment_time", &(segment_minutes * 60).to_string()])
+                .args(["-segment_list_type", "csv", "-segment_list"])
+                .arg(&list)
+                .arg("-y")
+                .arg(dir.join(format!("{}_%03d.webm", stem)));
+            (dir.join(format!("{}_000.webm", stem)), Some(list))
+        } else {
+            let file_path = dir.join(format!("{}.webm", stem));
+            command.arg("-y").arg(&file_path); // Overwrite output file if exists
+            (file_path, None)
+        };
+        debug!(path = ?file_path, segment_minutes, "starting ffmpeg recording");
+
+        let child = command
+            .spawn()
+            .map_err(|e| format!("Failed to start ffmpeg: {}. Make sure ffmpeg is installed.", e))?;
+
+        info!(pid = child.id(), path = ?file_path, "recording started");
+        if let Ok(mut segments) = self.segments.lock() {
+            *segments = segment_list.map(|path| SegmentList { path, reported: 0 });
+        }
+        *guard = Some((child, file_path.clone(), Instant::now()));
+        Ok(file_path)
     }
-    
-    let videos_dir = get_media_dir(&app, "videos")?;
-    
-    // Generate filename
-    let filename = format!("video_{}.webm", chrono::Utc::now().timestamp());
-    let file_path = videos_dir.join(&filename);
-    
-    println!("[Media] Starting ffmpeg recording to: {:?}", file_path);
-    
-    // Spawn ffmpeg process to record from camera
-    // Using avfoundation on macOS for camera + microphone capture
-    let child = Command::new("ffmpeg")
-        .args([
-            "-f", "avfoundation",
+
+    fn stop_recording(&self) -> Result<(PathBuf, Duration), String> {
+        let (mut child, file_path, started) = {
+            let mut guard = self.recorder.lock().map_err(|e| format!("Lock error: {}", e))?;
+            guard.take().ok_or("Not currently recording")?
+        };
+
+        // Send SIGINT (Ctrl+C) to ffmpeg to finalize the file gracefully
+        unsafe {
+            libc::kill(child.id() as i32, libc::SIGINT);
+        }
+
+        debug!(pid = child.id(), "waiting for ffmpeg to finish");
+        match child.wait() {
+            Ok(status) => debug!(%status, "ffmpeg exited"),
+            Err(e) => warn!(error = %e, "failed to wait for ffmpeg"),
+        }
+
+        // A split recording ends with the segment ffmpeg wrote last
+        let file_path = self
+            .segments
+            .lock()
+            .ok()
+            .and_then(|segments| segments.as_ref()?.read().pop())
+            .unwrap_or(file_path);
+
+        info!(path = ?file_path, "recording saved");
+        Ok((file_path, started.elapsed()))
+    }
+
+    fn take_finished_segments(&self) -> Vec<PathBuf> {
+        // Held so a stop can't finish between the check and the read
+        let Ok(recorder) = self.recorder.lock() else {
+            return Vec::new();
+        };
+        let Ok(mut guard) = self.segments.lock() else {
+            return Vec::new();
+        };
+        let Some(list) = guard.as_mut() else {
+            return Vec::new();
+        };
+        let mut closed = list.read();
+        if recorder.is_none() {
+            // Stopped: the last entry is what stop_recording returned
+            closed.pop();
+        }
+        let finished = closed.split_off(list.reported.min(closed.len()));
+        list.reported += finished.len();
+        if recorder.is_none() {
+            *guard = None;
+        }
+        finished
+    }
+}
+
+/// The bridge's error message, freed after reading
+unsafe fn take_recorder_error(error: *mut c_char, fallback: &str) -> String {
+    if error.is_null() {
+        return fallback.to_string();
+    }
+    let message = CStr::from_ptr(error).to_string_lossy().to_string();
+    screen_recorder_free_string(error);
+    message
+}
+
+/// The screen, or one window, recorded with ScreenCaptureKit on macOS 13+.
+/// Hardware H.264 encoding keeps long recordings cheap compared to ffmpeg.
+struct ScreenRecordingSource {
+    /// The file being recorded and when it started
+    recording: Mutex<Option<(PathBuf, Instant)>>,
+}
+
+impl CaptureSource for ScreenRecordingSource {
+    fn id(&self) -> &'static str {
+        "screen-recording"
+    }
+
+    fn label(&self) -> &'static str {
+        "Screen recording"
+    }
+
+    fn kind(&self) -> CaptureKind {
+        CaptureKind::Screencast
+    }
+
+    fn mode(&self) -> CaptureMode {
+        CaptureMode::Recording
+    }
+
+    fn media_subdir(&self) -> &'static str {
+        "videos"
+    }
+
+    fn available(&self) -> bool {
+        unsafe { screen_recorder_available() }
+    }
+
+    fn recording_elapsed(&self) -> Option<Duration> {
+        let recording = self.recording.lock().ok()?;
+        recording.as_ref().map(|(_, started)| started.elapsed())
+    }
+
+    fn start_recording(&self, dir: &Path, options: &CaptureOptions) -> Result<PathBuf, String> {
+        let mut guard = self.recording.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if guard.is_some() {
+            return Err("The screen is already being recorded".to_string());
+        }
+        // CGDirectDisplayID and CGWindowID are 32-bit
+        let id = |id: Option<u64>, what: &str| {
+            u32::try_from(id.unwrap_or(0)).map_err(|_| format!("Unknown {}", what))
+        };
+        let display = id(options.display, "display")?;
+        let window = id(options.window, "window")?;
+
+        let stem = capture_names::stem(dir, &[".mp4"], CaptureKind::Screencast, options, None)?;
+        let path = dir.join(format!("{}.mp4", stem));
+        let c_path = std::ffi::CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| format!("Invalid recording path: {}", path.display()))?;
+        debug!(path = ?path, display, window, "starting screen recording");
+        let mut error = std::ptr::null_mut();
+        if !unsafe { screen_recorder_start(display, window, c_path.as_ptr(), &mut error) } {
+            return Err(unsafe { take_recorder_error(error, "Screen recording did not start") });
+        }
+
+        info!(path = ?path, "screen recording started");
+        *guard = Some((path.clone(), Instant::now()));
+        Ok(path)
+    }
+
+    fn stop_recording(&self) -> Result<(PathBuf, Duration), String> {
+        let (path, started) = {
+            let mut guard = self.recording.lock().map_err(|e| format!("Lock error: {}", e))?;
+            guard.take().ok_or("Not currently recording")?
+        };
+        let mut error = std::ptr::null_mut();
+        if !unsafe { screen_recorder_stop(&mut error) } {
+            return Err(unsafe { take_recorder_error(error, "Failed to finish the screen recording") });
+        }
+        info!(path = ?path, "screen recording saved");
+        Ok((path, started.elapsed()))
+    }
+}
+
+/// Register the macOS capture sources
+pub fn register_sources(manager: &mut CaptureManager) {
+    manager.register(CameraSource);
+    manager.register(FfmpegSource {
+        id: "camera-video",
+        label: "Camera video",
+        kind: CaptureKind::Video,
+        media_subdir: "videos",
+        args: &[
             "-framerate", "30",
             "-video_size", "1280x720",
-            "-i", "0:0",  // First video device, first audio device
+            "-i", "0:0", // First video device, first audio device
             "-c:v", "libvpx-vp9",
             "-b:v", "1M",
             "-c:a", "libopus",
             "-b:a", "128k",
-            "-y",  // Overwrite output file if exists
-            file_path.to_str().ok_or("Invalid file path")?,
-        ])
-        .spawn()
-        .map_err(|e| {
-            let err_msg = format!("Failed to start ffmpeg: {}. Make sure ffmpeg is installed.", e);
-            println!("[Media] Error: {}", err_msg);
-            err_msg
-        })?;
-    
-    println!("[Media] ffmpeg process started with PID: {}", child.id());
-    
-    // Store the process handle and file path
-    {
-        let mut guard = VIDEO_RECORDER.lock().map_err(|e| format!("Lock error: {}", e))?;
-        *guard = Some((child, file_path.clone()));
-    }
-    
-    Ok(file_path.to_string_lossy().to_string())
+        ],
+        recorder: Mutex::new(None),
+        segments: Mutex::new(None),
+    });
+    manager.register(ScreenSource);
+    manager.register(ScreenRecordingSource {
+        recording: Mutex::new(None),
+    });
+    manager.register(FfmpegSource {
+        id: "microphone",
+        label: "Microphone",
+        kind: CaptureKind::Audio,
+        media_subdir: "audio",
+        args: &[
+            "-i", ":0", // No video, first audio device
+            "-c:a", "libopus",
+            "-b:a", "128k",
+        ],
+        recorder: Mutex::new(None),
+        segments: Mutex::new(None),
+    });
 }
 
 #[tauri::command]
-pub async fn stop_video_recording() -> Result<String, String> {
-    println!("[Media] stop_video_recording command called");
-    
-    let (mut child, file_path) = {
-        let mut guard = VIDEO_RECORDER.lock().map_err(|e| format!("Lock error: {}", e))?;
-        guard.take().ok_or("Not currently recording")?
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn capture_photo(app: AppHandle) -> Result<String, ShellError> {
+    let mut paths = capture::capture(&app, "camera", CaptureOptions::default()).await?;
+    paths.pop().ok_or_else(|| ShellError::unavailable("No photo was captured"))
+}
+
+/// Take `count` photos `interval_ms` apart (default 200) with one camera
+/// session, returning their paths in order
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn capture_photo_burst(app: AppHandle, count: u32, interval_ms: Option<u64>) -> Result<Vec<String>, ShellError> {
+    if count == 0 || count > MAX_BURST {
+        return Err(ShellError::invalid_input(format!("A burst is 1 to {} photos", MAX_BURST)));
+    }
+    let options = CaptureOptions {
+        count: Some(count),
+        interval_ms,
+        ..CaptureOptions::default()
     };
-    
-    // Send SIGINT (Ctrl+C) to ffmpeg to finalize the file gracefully
-    #[cfg(unix)]
-    {
-        unsafe {
-            libc::kill(child.id() as i32, libc::SIGINT);
+    capture::capture(&app, "camera", options).await
+}
+
+fn camera_names() -> Result<Vec<String>, String> {
+    use nokhwa::query;
+
+    let cameras = query(nokhwa::utils::ApiBackend::Auto)
+        .map_err(|e| format!("Failed to query cameras: {}", e))?;
+
+    let camera_names: Vec<String> = cameras
+        .iter()
+        .map(|info| info.human_name().to_string())
+        .collect();
+
+    Ok(camera_names)
+}
+
+fn microphone_names() -> Vec<String> {
+    unsafe {
+        let raw = devices_copy_microphones();
+        if raw.is_null() {
+            return Vec::new();
         }
+        let names = CStr::from_ptr(raw)
+            .to_string_lossy()
+            .lines()
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        devices_free_string(raw);
+        names
     }
-    
-    #[cfg(windows)]
-    {
-        // On Windows, we just kill the process
-        let _ = child.kill();
-    }
-    
-    // Wait for process to finish (with timeout)
-    println!("[Media] Waiting for ffmpeg to finish...");
-    match child.wait() {
-        Ok(status) => {
-            println!("[Media] ffmpeg exited with status: {}", status);
+}
+
+#[tauri::command]
+#[specta::specta]
+#[instrument(err)]
+pub async fn list_cameras() -> Result<Vec<String>, ShellError> {
+    camera_names().map_err(ShellError::unavailable)
+}
+
+#[tauri::command]
+#[specta::specta]
+#[instrument(err)]
+pub async fn list_microphones() -> Result<Vec<String>, ShellError> {
+    Ok(microphone_names())
+}
+
+extern "C" fn devices_changed() {
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    let generation = DEVICE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(DEVICE_SETTLE).await;
+        if DEVICE_GENERATION.load(Ordering::SeqCst) != generation {
+            return;
         }
-        Err(e) => {
-            println!("[Media] Error waiting for ffmpeg: {}", e);
+        let devices = tauri::async_runtime::spawn_blocking(|| {
+            let cameras = camera_names().unwrap_or_else(|e| {
+                warn!(error = %e, "failed to list cameras after hot-plug");
+                Vec::new()
+            });
+            MediaDevices { cameras, microphones: microphone_names() }
+        })
+        .await;
+        match devices {
+            Ok(devices) => {
+                info!(cameras = devices.cameras.len(), microphones = devices.microphones.len(), "media devices changed");
+                if let Err(e) = app.emit("media-devices-changed", devices) {
+                    warn!(error = %e, "failed to emit media-devices-changed");
+                }
+            }
+            Err(e) => warn!(error = %e, "device listing task failed"),
         }
+    });
+}
+
+/// Announce camera and microphone connects and disconnects as
+/// `media-devices-changed` with the updated device lists
+pub fn init_device_watch(app: &AppHandle) {
+    if APP_HANDLE.set(app.clone()).is_err() {
+        return;
     }
-    
-    println!("[Media] Video saved to: {:?}", file_path);
-    
+    unsafe { devices_watch(devices_changed) };
+}
+
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn start_video_recording(app: AppHandle) -> Result<String, ShellError> {
+    capture::start_recording(&app, "camera-video", CaptureOptions::default()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn stop_video_recording(app: AppHandle) -> Result<String, ShellError> {
     // Return the file path for uploading to backend
-    Ok(file_path.to_string_lossy().to_string())
+    capture::stop_recording(&app, "camera-video").await
 }
 
 #[tauri::command]
-pub async fn request_camera_permission() -> Result<bool, String> {
+#[specta::specta]
+#[instrument]
+pub async fn request_camera_permission() -> Result<bool, ShellError> {
     // On macOS, the system will automatically prompt for permission
     // when we try to access the camera. This command can be used to
     // pre-check or trigger the permission dialog.
-    println!("[Media] Camera permission requested");
     
     // Try to list cameras - this will trigger the permission dialog if needed
     match nokhwa::query(nokhwa::utils::ApiBackend::Auto) {
         Ok(_) => Ok(true),
         Err(e) => {
-            println!("[Media] Camera access error: {}", e);
+            warn!(error = %e, "camera access denied");
             Ok(false)
         }
     }
 }
 
 #[tauri::command]
-pub async fn request_microphone_permission() -> Result<bool, String> {
+#[specta::specta]
+#[instrument]
+pub async fn request_microphone_permission() -> Result<bool, ShellError> {
     // On macOS, microphone permission will be requested when accessing audio
     // This is a placeholder that returns true since AVFoundation handles it
-    println!("[Media] Microphone permission requested");
     Ok(true)
 }
 
@@ -0,0 +1,299 @@
+//! Manifest of captured media. Every photo, screenshot, recording (and
+//! recording segment) and import the capture sources write is recorded in
+//! `media_manifest.json` with its size, BLAKE3 hash and whether it was
+//! uploaded with `upload_media`, along with the transcript of recordings. `verify_media_integrity()` checks the files
+//! against the manifest, and the uploaded ones against the backend's list of
+//! uploads.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::{backend_client, DesktopConfig};
+
+const MANIFEST_FILE: &str = "media_manifest.json";
+
+/// Held while the manifest is read, changed and written back
+static MANIFEST: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadStatus {
+    #[default]
+    Local,
+    Uploaded,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    /// Relative to the app data dir
+    path: String,
+    size: u64,
+    blake3: String,
+    recorded_at: DateTime<Utc>,
+    #[serde(default)]
+    upload: UploadStatus,
+    /// Where the backend serves the upload, e.g. `/api/uploads/files/<name>`
+    #[serde(default)]
+    url: Option<String>,
+    /// The recording's WebVTT transcript, relative to the app data dir
+    #[serde(default)]
+    transcript: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegrityIssueKind {
+    /// In the manifest, but gone from disk
+    Missing,
+    /// Its size or hash changed since it was recorded
+    Corrupted,
+    /// Uploaded, but the backend no longer has it
+    MissingOnBackend,
+    /// The backend's copy has a different size
+    BackendMismatch,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    pub path: String,
+    pub kind: IntegrityIssueKind,
+    pub message: String,
+}
+
+/// Result of `verify_media_integrity`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Files in the manifest
+    pub checked: usize,
+    /// Whether uploaded files could be compared with the backend's list
+    pub backend_checked: bool,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+/// An entry of `GET /api/uploads/files`
+#[derive(Deserialize)]
+struct BackendFile {
+    filename: String,
+    size: u64,
+}
+
+fn manifest_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(MANIFEST_FILE))
+}
+
+fn load(app: &AppHandle) -> Vec<ManifestEntry> {
+    manifest_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| {
+            serde_json::from_str(&content)
+                .map_err(|e| warn!("Failed to parse {}: {}", MANIFEST_FILE, e))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, entries: &[ManifestEntry]) {
+    let Some(path) = manifest_path(app) else {
+        return;
+    };
+    let result = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Failed to serialize {}: {}", MANIFEST_FILE, e))
+        .and_then(|json| crate::prefs::write_atomic(&path, &json));
+    if let Err(e) = result {
+        warn!("Failed to save the media manifest: {}", e);
+    }
+}
+
+/// Change the manifest under its lock
+fn update(app: &AppHandle, change: impl FnOnce(&mut Vec<ManifestEntry>)) {
+    let _guard = MANIFEST.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = load(app);
+    change(&mut entries);
+    save(app, &entries);
+}
+
+/// `path` relative to the app data dir, as the manifest keeps it
+fn relative(app: &AppHandle, path: &Path) -> Option<String> {
+    let root = app.path().app_data_dir().ok()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(root).ok().map(|relative| relative.to_string_lossy().to_string())
+}
+
+/// Size and BLAKE3 hash of a file
+fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    Ok((size, hasher.finalize().to_hex().to_string()))
+}
+
+/// Hash new media files in the background and add them to the manifest,
+/// replacing earlier entries for the same paths
+pub(crate) fn track(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    async_runtime::spawn_blocking(move || {
+        for path in paths {
+            let Some(relative) = relative(&app, &path) else {
+                warn!("Not adding {} to the media manifest: outside the app data dir", path.display());
+                continue;
+            };
+            let (size, blake3) = match hash_file(&path) {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    warn!("Failed to hash {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            update(&app, |entries| {
+                entries.retain(|entry| entry.path != relative);
+                entries.push(ManifestEntry {
+                    path: relative,
+                    size,
+                    blake3,
+                    recorded_at: Utc::now(),
+                    upload: UploadStatus::Local,
+                    url: None,
+                    transcript: None,
+                });
+            });
+        }
+    });
+}
+
+/// Record that `path` was uploaded and is served at `url`. Files not in the
+/// manifest are left out.
+pub(crate) fn mark_uploaded(app: &AppHandle, path: &Path, url: &str) {
+    let Some(relative) = relative(app, path) else {
+        return;
+    };
+    update(app, |entries| {
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.path == relative) {
+            entry.upload = UploadStatus::Uploaded;
+            entry.url = Some(url.to_string());
+        }
+    });
+}
+
+/// Record `transcript` as the transcript of the recording at `path`. Files
+/// not in the manifest are left out.
+pub(crate) fn attach_transcript(app: &AppHandle, path: &Path, transcript: &Path) {
+    let (Some(relative), Some(transcript)) = (relative(app, path), relative(app, transcript)) else {
+        return;
+    };
+    update(app, |entries| {
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.path == relative) {
+            entry.transcript = Some(transcript);
+        }
+    });
+}
+
+fn verify(app: &AppHandle, forget_missing: bool) -> Result<IntegrityReport, String> {
+    let root = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let entries = {
+        let _guard = MANIFEST.lock().unwrap_or_else(|e| e.into_inner());
+        load(app)
+    };
+    let mut issues = Vec::new();
+    let mut missing = Vec::new();
+    let issue = |entry: &ManifestEntry, kind, message: String| IntegrityIssue {
+        path: root.join(&entry.path).to_string_lossy().to_string(),
+        kind,
+        message,
+    };
+
+    for entry in &entries {
+        let path = root.join(&entry.path);
+        if !path.exists() {
+            issues.push(issue(entry, IntegrityIssueKind::Missing, format!("{} is missing", entry.path)));
+            missing.push(entry.path.clone());
+            continue;
+        }
+        match hash_file(&path) {
+            Ok((size, _)) if size != entry.size => {
+                let message = format!("{} is {} bytes, {} when it was recorded", entry.path, size, entry.size);
+                issues.push(issue(entry, IntegrityIssueKind::Corrupted, message));
+            }
+            Ok((_, blake3)) if blake3 != entry.blake3 => {
+                let message = format!("{} changed since it was recorded", entry.path);
+                issues.push(issue(entry, IntegrityIssueKind::Corrupted, message));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let message = format!("{} can't be read: {}", entry.path, e);
+                issues.push(issue(entry, IntegrityIssueKind::Corrupted, message));
+            }
+        }
+    }
+
+    // Uploaded files are compared by name and size with what the backend stores
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let backend_files = backend_client::call(&config, "GET", "/api/uploads/files", None)
+        .map_err(|e| e.message)
+        .and_then(|response| {
+            serde_json::from_value::<Vec<BackendFile>>(response.body)
+                .map_err(|e| format!("Unexpected upload list: {}", e))
+        });
+    let backend_checked = match backend_files {
+        Ok(files) => {
+            for entry in entries.iter().filter(|entry| entry.upload == UploadStatus::Uploaded) {
+                let name = entry.url.as_deref().and_then(|url| url.rsplit('/').next()).unwrap_or_default();
+                match files.iter().find(|file| file.filename == name) {
+                    None => {
+                        let message = format!("{} was uploaded as {}, which the backend no longer has", entry.path, name);
+                        issues.push(issue(entry, IntegrityIssueKind::MissingOnBackend, message));
+                    }
+                    Some(file) if file.size != entry.size => {
+                        let message = format!(
+                            "The backend's copy of {} is {} bytes instead of {}",
+                            entry.path, file.size, entry.size
+                        );
+                        issues.push(issue(entry, IntegrityIssueKind::BackendMismatch, message));
+                    }
+                    Some(_) => {}
+                }
+            }
+            true
+        }
+        Err(e) => {
+            warn!("Can't compare uploaded media with the backend: {}", e);
+            false
+        }
+    };
+
+    if forget_missing && !missing.is_empty() {
+        update(app, |entries| entries.retain(|entry| !missing.contains(&entry.path)));
+    }
+    info!("Checked {} media file(s): {} issue(s)", entries.len(), issues.len());
+    Ok(IntegrityReport {
+        checked: entries.len(),
+        backend_checked,
+        issues,
+    })
+}
+
+/// Check every file in the media manifest for being missing or changed, and
+/// uploaded ones for being missing or different on the backend. With
+/// `forgetMissing`, missing files are reported once and then dropped from
+/// the manifest.
+#[tauri::command]
+pub async fn verify_media_integrity(app: AppHandle, forget_missing: Option<bool>) -> Result<IntegrityReport, String> {
+    async_runtime::spawn_blocking(move || verify(&app, forget_missing.unwrap_or(false)))
+        .await
+        .map_err(|e| format!("Integrity check task failed: {}", e))?
+}
@@ -0,0 +1,295 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::health::find_on_path;
+
+/// Get or create the media directory within app data
+pub fn get_media_dir(app: &AppHandle, subdir: &str) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    let media_dir = app_data_dir.join(subdir);
+    
+    fs::create_dir_all(&media_dir)
+        .map_err(|e| format!("Failed to create {} directory: {}", subdir, e))?;
+
+    Ok(media_dir)
+}
+
+/// Subfolder of a media directory holding the files of one entry
+fn entry_folder(entry_id: i64) -> String {
+    format!("entry_{}", entry_id)
+}
+
+/// `subdir` of the media directory, or its `entry_<id>` subfolder when the
+/// files are for entry `entry_id`
+pub fn get_capture_dir(app: &AppHandle, subdir: &str, entry_id: Option<i64>) -> Result<PathBuf, String> {
+    match entry_id {
+        Some(id) if id <= 0 => Err(format!("Invalid entry id {}", id)),
+        Some(id) => get_media_dir(app, &format!("{}/{}", subdir, entry_folder(id))),
+        None => get_media_dir(app, subdir),
+    }
+}
+
+/// The entry a media file was captured for, from its `entry_<id>` folder
+pub(crate) fn entry_of(path: &Path) -> Option<i64> {
+    let folder = path.parent()?.file_name()?.to_str()?;
+    folder.strip_prefix("entry_")?.parse().ok().filter(|id| *id > 0)
+}
+
+/// Files in the `entry_<id>` folder of each of `subdirs`, for `list_media`
+pub(crate) fn entry_files(app: &AppHandle, subdirs: &[&str], entry_id: i64) -> Result<Vec<(String, PathBuf)>, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let mut files = Vec::new();
+    for subdir in subdirs {
+        let dir = app_data_dir.join(subdir).join(entry_folder(entry_id));
+        let Ok(listing) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = listing
+            .filter_map(Result::ok)
+            .map(|item| item.path())
+            // ffmpeg's list of a split recording's segments isn't media
+            .filter(|path| path.is_file() && !path.to_string_lossy().ends_with(".segments.csv"))
+            .collect();
+        found.sort();
+        files.extend(found.into_iter().map(|path| (subdir.to_string(), path)));
+    }
+    Ok(files)
+}
+
+/// Directories whose files may be handed to the OS: app data (recordings,
+/// clipboard captures, shared files) and the backend's data dir (uploads)
+fn allowed_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(dir) = app.path().app_data_dir() {
+        roots.push(dir);
+    }
+    if let Ok(dir) = env::var("TAURI_DESKTOP_DATA_DIR") {
+        roots.push(PathBuf::from(dir));
+    }
+    roots
+        .into_iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+/// Resolve `path` (following symlinks and `..`) and reject anything outside
+/// the app's own data directories
+pub fn validate_app_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let resolved = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("File not found: {} ({})", path, e))?;
+
+    if allowed_roots(app).iter().any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        Err(format!("Access denied: {} is outside the app data directories", path))
+    }
+}
+
+/// App data subdirectories holding captured and imported media. The database,
+/// backups, settings and the rest of app data are not among them.
+const MEDIA_SUBDIRS: &[&str] = &[
+    "photos",
+    "screenshots",
+    "videos",
+    "imports",
+    "clips",
+    "captions",
+    "scans",
+    "transcripts",
+    "documents",
+    "clipboard",
+    "shared",
+    "email",
+    "calendar",
+];
+
+/// Directories the webview may read media from: the media subdirectories of
+/// app data and the backend's uploads
+fn media_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(dir) = app.path().app_data_dir() {
+        roots.extend(MEDIA_SUBDIRS.iter().map(|subdir| dir.join(subdir)));
+    }
+    if let Some(dir) = env::var_os("TAURI_UPLOADS_DIR") {
+        roots.push(PathBuf::from(dir));
+    }
+    roots
+        .into_iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+/// Like `validate_app_path`, but only for files in the media directories, for
+/// anything served to the webview
+pub fn validate_media_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let resolved = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("File not found: {} ({})", path, e))?;
+
+    if resolved.is_file() && media_roots(app).iter().any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        Err(format!("Access denied: {} is not a media file", path))
+    }
+}
+
+/// Show a media file selected in Finder / Explorer / the desktop file manager
+#[tauri::command]
+pub async fn reveal_in_file_manager(app: AppHandle, path: String) -> Result<(), String> {
+    let resolved = validate_app_path(&app, &path)?;
+    info!("Revealing {}", resolved.display());
+    app.opener()
+        .reveal_item_in_dir(&resolved)
+        .map_err(|e| format!("Failed to reveal file: {}", e))
+}
+
+/// Open a media file with the user's default application for its type
+#[tauri::command]
+pub async fn open_with_default_app(app: AppHandle, path: String) -> Result<(), String> {
+    let resolved = validate_app_path(&app, &path)?;
+    info!("Opening {} with the default app", resolved.display());
+    app.opener()
+        .open_path(resolved.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open file: {}", e))
+}
+
+/// What `inspect_media` found in a file. Fields ffprobe doesn't report for
+/// the container are `None`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    /// Container, e.g. `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`
+    pub format: String,
+    pub duration_secs: Option<f64>,
+    /// Overall bitrate in bits per second
+    pub bitrate: Option<u64>,
+    pub size_bytes: u64,
+    pub video: Option<VideoStream>,
+    pub audio: Option<AudioStream>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoStream {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStream {
+    pub codec: String,
+    pub channels: u32,
+    pub sample_rate: Option<u32>,
+}
+
+/// The parts of `ffprobe -print_format json -show_format -show_streams` used
+/// here. ffprobe prints most numbers as strings.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProbeOutput {
+    format: Option<ProbeFormat>,
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProbeFormat {
+    format_name: String,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    size: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProbeStream {
+    codec_type: String,
+    codec_name: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    channels: Option<u32>,
+    sample_rate: Option<String>,
+}
+
+pub(crate) fn probe(path: &Path) -> Result<MediaInfo, String> {
+    let ffprobe = find_on_path("ffprobe").ok_or("ffprobe was not found on the PATH")?;
+    let output = Command::new(ffprobe)
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Not a readable media file: {}", reason.trim()));
+    }
+    let parsed: ProbeOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+    let format = parsed.format.ok_or("ffprobe found no media container")?;
+
+    // Cover art shows up as a single-frame video stream
+    let video = parsed
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type == "video" && !matches!(stream.codec_name.as_str(), "mjpeg" | "png"))
+        .and_then(|stream| {
+            Some(VideoStream {
+                codec: stream.codec_name.clone(),
+                width: stream.width?,
+                height: stream.height?,
+            })
+        });
+    let audio = parsed.streams.iter().find(|stream| stream.codec_type == "audio").map(|stream| AudioStream {
+        codec: stream.codec_name.clone(),
+        channels: stream.channels.unwrap_or(0),
+        sample_rate: stream.sample_rate.as_deref().and_then(|rate| rate.parse().ok()),
+    });
+    if video.is_none() && audio.is_none() {
+        return Err("Not a readable media file: no audio or video stream".to_string());
+    }
+
+    Ok(MediaInfo {
+        format: format.format_name,
+        duration_secs: format.duration.as_deref().and_then(|secs| secs.parse().ok()),
+        bitrate: format.bit_rate.as_deref().and_then(|rate| rate.parse().ok()),
+        size_bytes: format
+            .size
+            .as_deref()
+            .and_then(|size| size.parse().ok())
+            .or_else(|| fs::metadata(path).ok().map(|meta| meta.len()))
+            .unwrap_or(0),
+        video,
+        audio,
+    })
+}
+
+/// Duration, codecs, resolution, bitrate and audio channels of a media file
+/// in the app's data directories. Fails for files ffprobe can't read, so a
+/// broken recording can be refused before upload.
+#[tauri::command]
+pub async fn inspect_media(app: AppHandle, path: String) -> Result<MediaInfo, String> {
+    let resolved = validate_app_path(&app, &path)?;
+    let result = async_runtime::spawn_blocking(move || probe(&resolved))
+        .await
+        .map_err(|e| format!("Media inspection task failed: {}", e))?;
+    if let Err(e) = &result {
+        warn!("Failed to inspect {}: {}", path, e);
+    }
+    result
+}
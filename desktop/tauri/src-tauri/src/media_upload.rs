@@ -0,0 +1,334 @@
+//! Uploads of recordings and other large files straight from disk to the
+//! backend, so multi-GB screen recordings don't pass through the webview.
+//! The file is sent to `/api/uploads/resumable` in chunks placed with
+//! `Content-Range`. When a chunk fails, the shell asks the backend how much
+//! arrived and continues from there. Unfinished uploads are kept in
+//! `media_uploads.json`, so uploading the same file again after a failure,
+//! a cancel or a quit picks up where it stopped instead of starting from
+//! zero. `media.uploadLimitKbps` caps the rate.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::backend_client::{self, BackendError, BackendErrorKind};
+use crate::jobs::{self, CancelToken, Cancellable, JobId, JobKind, Priority, Progress};
+use crate::settings::SettingsState;
+use crate::DesktopConfig;
+
+const STATE_FILE: &str = "media_uploads.json";
+
+const UPLOADS_PATH: &str = "/api/uploads/resumable";
+
+/// Largest chunk; a dropped connection loses at most this much
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Throttled chunks are cut to take at most this many seconds
+const THROTTLED_CHUNK_SECS: u64 = 30;
+
+/// Failed chunks in a row before the upload gives up until the next try
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Delay before resuming after a failed chunk, doubling up to `MAX_RETRY_DELAY`
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// An unfinished upload, as saved in `media_uploads.json`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    source: PathBuf,
+    /// Size and modification time the upload was started for
+    size: u64,
+    modified: Option<u64>,
+    id: String,
+}
+
+/// How far the backend got, from `GET`/`PUT` on an upload
+#[derive(Deserialize)]
+struct Status {
+    offset: u64,
+}
+
+/// Reads no faster than `bytes_per_sec`
+struct Throttled<R> {
+    inner: R,
+    bytes_per_sec: Option<u64>,
+    started: Instant,
+    read: u64,
+}
+
+impl<R> Throttled<R> {
+    fn new(inner: R, bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            inner,
+            bytes_per_sec,
+            started: Instant::now(),
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(rate) = self.bytes_per_sec else {
+            return self.inner.read(buf);
+        };
+        // Small reads keep the rate even instead of sending in bursts
+        let len = buf.len().min((rate / 10).max(1024) as usize);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.read += read as u64;
+        let due = Duration::from_secs_f64(self.read as f64 / rate as f64);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            thread::sleep(wait);
+        }
+        Ok(read)
+    }
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(STATE_FILE))
+}
+
+fn load_sessions(app: &AppHandle) -> Vec<Session> {
+    state_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| {
+            serde_json::from_str(&content)
+                .map_err(|e| warn!("Failed to parse {}: {}", STATE_FILE, e))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+fn save_sessions(app: &AppHandle, sessions: &[Session]) {
+    let Some(path) = state_path(app) else {
+        return;
+    };
+    let result = serde_json::to_string_pretty(sessions)
+        .map_err(|e| format!("Failed to serialize {}: {}", STATE_FILE, e))
+        .and_then(|json| crate::prefs::write_atomic(&path, &json));
+    if let Err(e) = result {
+        warn!("Failed to save the unfinished uploads: {}", e);
+    }
+}
+
+/// Replace the saved session of `source` with `session`, or drop it
+fn record(app: &AppHandle, source: &Path, session: Option<&Session>) {
+    let mut sessions = load_sessions(app);
+    sessions.retain(|saved| saved.source != source);
+    sessions.extend(session.cloned());
+    save_sessions(app, &sessions);
+}
+
+fn modified(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_secs())
+}
+
+fn offset_of(body: &Value) -> Option<u64> {
+    serde_json::from_value::<Status>(body.clone()).ok().map(|status| status.offset)
+}
+
+struct Upload<'a> {
+    app: &'a AppHandle,
+    config: DesktopConfig,
+    source: PathBuf,
+    cancel: CancelToken,
+    progress: Progress,
+}
+
+impl Upload<'_> {
+    /// The saved session for this file and how far it got, if the backend
+    /// still has it
+    fn resume(&self, size: u64, modified: Option<u64>) -> Option<(Session, u64)> {
+        let session = load_sessions(self.app).into_iter().find(|saved| saved.source == self.source)?;
+        let path = format!("{}/{}", UPLOADS_PATH, session.id);
+        if session.size != size || session.modified != modified {
+            // The file changed since; the backend's part is of no use
+            info!("{} changed since its upload started; starting over", self.source.display());
+            let _ = backend_client::call(&self.config, "DELETE", &path, None);
+            return None;
+        }
+        match backend_client::call(&self.config, "GET", &path, None) {
+            Ok(response) => offset_of(&response.body).map(|offset| (session, offset)),
+            Err(e) => {
+                warn!("Can't resume the upload of {}: {}", self.source.display(), e);
+                None
+            }
+        }
+    }
+
+    fn start(&self, size: u64, modified: Option<u64>) -> Result<Session, String> {
+        let filename = self.source.file_name().unwrap_or_default().to_string_lossy();
+        let body = json!({ "filename": filename, "size": size });
+        let response = backend_client::call(&self.config, "POST", UPLOADS_PATH, Some(&body)).map_err(|e| e.message)?;
+        let id = response
+            .body
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or("The backend didn't start the upload")?;
+        let session = Session {
+            source: self.source.clone(),
+            size,
+            modified,
+            id: id.to_string(),
+        };
+        record(self.app, &self.source, Some(&session));
+        Ok(session)
+    }
+
+    /// Send bytes from `offset` on, at most one chunk
+    fn send_chunk(&self, session: &Session, offset: u64) -> Result<Value, BackendError> {
+        let kbps = self.app.state::<SettingsState>().current().media.upload_limit_kbps;
+        let bytes_per_sec = kbps.map(|kbps| kbps * 1000 / 8);
+        let chunk = bytes_per_sec.map_or(CHUNK_SIZE, |rate| (rate * THROTTLED_CHUNK_SECS).min(CHUNK_SIZE));
+        let end = (offset + chunk).min(session.size) - 1;
+
+        let read_error = |e: io::Error| {
+            BackendError::new(
+                BackendErrorKind::InvalidRequest,
+                format!("Failed to read {}: {}", self.source.display(), e),
+            )
+        };
+        let mut file = File::open(&self.source).map_err(read_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+        let body = Cancellable::new(Throttled::new(file.take(end + 1 - offset), bytes_per_sec), &self.cancel);
+        let path = format!("{}/{}", UPLOADS_PATH, session.id);
+        backend_client::put_chunk(&self.config, &path, (offset, end, session.size), body).map(|response| response.body)
+    }
+
+    fn run(&self) -> Result<Value, String> {
+        let metadata = fs::metadata(&self.source).map_err(|e| format!("Cannot read {}: {}", self.source.display(), e))?;
+        let (size, modified) = (metadata.len(), modified(&metadata));
+        if size == 0 {
+            return Err(format!("{} is empty", self.source.display()));
+        }
+        let (mut session, mut offset) = match self.resume(size, modified) {
+            Some((session, offset)) => {
+                info!("Resuming the upload of {} at byte {}", self.source.display(), offset);
+                (session, offset)
+            }
+            None => (self.start(size, modified)?, 0),
+        };
+
+        let mut attempts = 0;
+        let mut delay = MIN_RETRY_DELAY;
+        loop {
+            // The backend keeps what arrived, so the next try resumes here
+            self.cancel.check()?;
+            self.progress.report(offset, size);
+            let error = match self.send_chunk(&session, offset) {
+                Ok(body) if body.get("url").is_some() => {
+                    record(self.app, &self.source, None);
+                    if let Some(url) = body.get("url").and_then(Value::as_str) {
+                        crate::media_manifest::mark_uploaded(self.app, &self.source, url);
+                    }
+                    self.progress.report(size, size);
+                    info!("Uploaded {} ({} bytes)", self.source.display(), size);
+                    return Ok(body);
+                }
+                Ok(body) => match offset_of(&body) {
+                    Some(next) => {
+                        offset = next;
+                        attempts = 0;
+                        delay = MIN_RETRY_DELAY;
+                        continue;
+                    }
+                    None => return Err("The backend didn't say how much of the upload arrived".to_string()),
+                },
+                // The chunk didn't start where the backend is; continue from there
+                Err(BackendError {
+                    status: Some(409),
+                    body: Some(body),
+                    ..
+                }) => match offset_of(&body) {
+                    Some(next) => {
+                        offset = next;
+                        continue;
+                    }
+                    None => return Err("The backend refused the upload's position".to_string()),
+                },
+                Err(BackendError { status: Some(404), .. }) => {
+                    warn!("The backend dropped the upload of {}; starting over", self.source.display());
+                    session = self.start(size, modified)?;
+                    offset = 0;
+                    continue;
+                }
+                Err(e) if e.kind == BackendErrorKind::Http || e.kind == BackendErrorKind::InvalidRequest => {
+                    return Err(e.message)
+                }
+                Err(e) => e,
+            };
+
+            self.cancel.check()?;
+            attempts += 1;
+            if attempts >= MAX_ATTEMPTS {
+                return Err(format!(
+                    "Upload of {} stopped at byte {} of {}: {}. Uploading it again resumes there.",
+                    self.source.display(),
+                    offset,
+                    size,
+                    error
+                ));
+            }
+            warn!("Upload chunk failed ({}), resuming in {:?}", error, delay);
+            thread::sleep(delay);
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            let path = format!("{}/{}", UPLOADS_PATH, session.id);
+            if let Some(next) = backend_client::call(&self.config, "GET", &path, None)
+                .ok()
+                .and_then(|response| offset_of(&response.body))
+            {
+                offset = next;
+            }
+        }
+    }
+}
+
+/// Upload `source` as a job. Returns what `/api/uploads/file` would have:
+/// `{ url, filename, content_type, size }`.
+pub(crate) async fn upload(
+    app: &AppHandle,
+    source: PathBuf,
+    cancel: CancelToken,
+    progress: Progress,
+) -> Result<Value, String> {
+    let app = app.clone();
+    async_runtime::spawn_blocking(move || {
+        let upload = Upload {
+            app: &app,
+            config: app.state::<DesktopConfig>().inner().clone(),
+            source,
+            cancel,
+            progress,
+        };
+        upload.run()
+    })
+    .await
+    .map_err(|e| format!("Upload task failed: {}", e))?
+}
+
+/// Upload a file from the app's data directories (e.g. a recording) to the
+/// backend in the background. Returns the job id; the job's result is what
+/// `/api/uploads/file` returns. An interrupted upload of the same file
+/// resumes from where it stopped.
+#[tauri::command]
+pub async fn upload_media(app: AppHandle, path: String) -> Result<JobId, String> {
+    crate::demo_mode::refuse("Uploading files")?;
+    let source = crate::media_store::validate_app_path(&app, &path)?;
+    if !source.is_file() {
+        return Err(format!("{} is not a file", path));
+    }
+    jobs::enqueue(&app, JobKind::MediaUpload { source }, Priority::High)
+}
@@ -0,0 +1,183 @@
+//! Captured media for the webview, served over the `ttt-media` protocol
+//! rather than handed out as filesystem paths the webview may not be allowed
+//! to load. `media_url` checks a file is in one of the media directories and
+//! issues it a random id; the handler serves only files with an issued id,
+//! so the rest of app data (database, backups, settings) can't be reached.
+//! Range requests are answered, so video and audio elements can seek.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
+use log::warn;
+use tauri::http::{header, HeaderValue, Request, Response, StatusCode};
+use tauri::AppHandle;
+
+use crate::media_store::validate_media_path;
+
+pub const SCHEME: &str = "ttt-media";
+
+/// Most bytes sent for one request; players ask for the rest as needed
+const MAX_RANGE: u64 = 4 * 1024 * 1024;
+
+/// Paths by the id `media_url` issued for them
+static ISSUED: Mutex<BTreeMap<String, PathBuf>> = Mutex::new(BTreeMap::new());
+
+/// The id of `path`, issuing one the first time it's asked for
+fn issue_id(path: PathBuf) -> String {
+    let mut issued = ISSUED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((id, _)) = issued.iter().find(|(_, issued_path)| **issued_path == path) {
+        return id.clone();
+    }
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let id = BASE64.encode(bytes);
+    issued.insert(id.clone(), path);
+    id
+}
+
+fn issued_path(id: &str) -> Option<PathBuf> {
+    ISSUED.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+}
+
+/// The app's own pages: the bundled frontend on each platform, and the dev
+/// server in development builds
+fn allowed_origin(app: &AppHandle, origin: &str) -> bool {
+    if matches!(origin, "tauri://localhost" | "http://tauri.localhost" | "https://tauri.localhost") {
+        return true;
+    }
+    app.config()
+        .build
+        .dev_url
+        .as_ref()
+        .is_some_and(|url| cfg!(debug_assertions) && url.origin().ascii_serialization() == origin)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "webm" => "video/webm",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "m4a" => "audio/mp4",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" | "opus" => "audio/ogg",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The byte range of a `Range: bytes=...` header, clamped to the file and to
+/// `MAX_RANGE`. `None` when it can't be satisfied.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    // Only the first range of a multi-range request is served
+    let (start, end) = spec.split(',').next()?.trim().split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.checked_sub(suffix.min(len))?, len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len.checked_sub(1)?)),
+    };
+    if start > end || start >= len {
+        return None;
+    }
+    Some((start, end.min(start + MAX_RANGE - 1)))
+}
+
+fn respond(status: StatusCode, body: Vec<u8>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "Content-Range, Accept-Ranges")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .body(Cow::Owned(body))
+        .unwrap_or_default()
+}
+
+fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Response<Cow<'static, [u8]>>, (StatusCode, String)> {
+    let id = request.uri().path().trim_start_matches('/');
+    let path = issued_path(id).ok_or((StatusCode::NOT_FOUND, "Unknown media id".to_string()))?;
+    let path = path.to_string_lossy();
+    // Checked again: the file may have been replaced since the id was issued
+    let resolved = validate_media_path(app, &path).map_err(|e| (StatusCode::NOT_FOUND, e))?;
+
+    let not_found = |e: std::io::Error| (StatusCode::NOT_FOUND, format!("Failed to read {}: {}", path, e));
+    let mut file = File::open(&resolved).map_err(not_found)?;
+    let len = file.metadata().map_err(not_found)?.len();
+
+    let range = request.headers().get(header::RANGE).and_then(|value| value.to_str().ok());
+    let mut response = if len == 0 && range.is_none() {
+        respond(StatusCode::OK, Vec::new())
+    } else {
+        // Without a Range header the file is sent as if `bytes=0-` was asked
+        // for, so a large recording is never read into memory whole
+        let Some((start, end)) = parse_range(range.unwrap_or("bytes=0-"), len) else {
+            let mut response = respond(StatusCode::RANGE_NOT_SATISFIABLE, Vec::new());
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            return Ok(response);
+        };
+        let mut body = Vec::with_capacity((end - start + 1) as usize);
+        file.seek(SeekFrom::Start(start)).map_err(not_found)?;
+        file.take(end - start + 1).read_to_end(&mut body).map_err(not_found)?;
+        if range.is_none() && end + 1 == len {
+            respond(StatusCode::OK, body)
+        } else {
+            let mut response = respond(StatusCode::PARTIAL_CONTENT, body);
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)) {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            response
+        }
+    };
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type(&resolved)));
+    Ok(response)
+}
+
+/// `ttt-media://localhost/<id>`: the file, or the requested byte range of it
+pub fn handle_request(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let mut response = serve(app, request).unwrap_or_else(|(status, message)| {
+        warn!("Media request failed: {}", message);
+        respond(status, message.into_bytes())
+    });
+    // `fetch` from the app's own pages only; `src` attributes don't need CORS
+    if let Some(origin) = request.headers().get(header::ORIGIN) {
+        if origin.to_str().is_ok_and(|origin| allowed_origin(app, origin)) {
+            response.headers_mut().insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        }
+    }
+    response
+}
+
+/// The `ttt-media` URL of a file in the media directories, for `src`
+/// attributes and `fetch`
+#[tauri::command]
+pub async fn media_url(app: AppHandle, path: String) -> Result<String, String> {
+    let resolved = validate_media_path(&app, &path)?;
+    let id = issue_id(resolved);
+    // WebView2 and Android's WebView serve custom protocols over http
+    Ok(if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", SCHEME, id)
+    } else {
+        format!("{}://localhost/{}", SCHEME, id)
+    })
+}
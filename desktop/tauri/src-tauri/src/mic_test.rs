@@ -0,0 +1,116 @@
+//! Microphone test: records a short sample with ffmpeg while reporting the
+//! input level, so the user can check a microphone before dictating or
+//! recording something that matters.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, info, instrument, warn};
+
+use crate::media_store::get_media_dir;
+use crate::permissions::{self, MICROPHONE};
+
+const SAMPLE_SECS: u32 = 3;
+
+/// Levels are sent at most this often; astats reports every audio frame
+const LEVEL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// dBFS shown as silence
+const FLOOR_DB: f32 = -60.0;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Payload of `mic-test-level`
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MicLevel {
+    /// Peak since the last event, in dBFS
+    pub db: f32,
+    /// `db` mapped onto 0 (-60 dBFS or quieter) to 1 (full scale), for a meter
+    pub level: f32,
+}
+
+impl MicLevel {
+    fn new(db: f32) -> Self {
+        let db = db.max(FLOOR_DB);
+        Self {
+            db,
+            level: (db - FLOOR_DB) / -FLOOR_DB,
+        }
+    }
+}
+
+fn record_sample(app: &AppHandle, device: &str, path: &std::path::Path) -> Result<(), String> {
+    // astats measures each frame and ametadata prints the value to stdout
+    let filter = "astats=metadata=1:reset=1,ametadata=print:key=lavfi.astats.Overall.Peak_level:file=-";
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-f", "avfoundation"])
+        .args(["-i", &format!(":{}", device)])
+        .args(["-t", &SAMPLE_SECS.to_string(), "-af", filter, "-c:a", "pcm_s16le", "-y"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}. Make sure ffmpeg is installed.", e))?;
+
+    let stdout = child.stdout.take().ok_or("ffmpeg has no output")?;
+    let mut peak = f32::NEG_INFINITY;
+    let mut last_emit = Instant::now();
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        let Some(value) = line.strip_prefix("lavfi.astats.Overall.Peak_level=") else {
+            continue;
+        };
+        // Silence is reported as -inf
+        let db = value.parse::<f32>().unwrap_or(f32::NEG_INFINITY);
+        peak = peak.max(db);
+        if last_emit.elapsed() >= LEVEL_INTERVAL {
+            if let Err(e) = app.emit("mic-test-level", MicLevel::new(peak)) {
+                warn!(error = %e, "failed to emit mic-test-level");
+            }
+            peak = f32::NEG_INFINITY;
+            last_emit = Instant::now();
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+    if !output.status.success() || !path.is_file() {
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("The microphone test failed: {}", reason.trim()));
+    }
+    Ok(())
+}
+
+/// Record a three-second sample from `device_id` (a name from
+/// `list_microphones`, or the first microphone), emitting `mic-test-level`
+/// about ten times a second. Returns the sample's path once it is recorded.
+#[tauri::command]
+#[specta::specta]
+#[instrument(skip(app), err)]
+pub async fn start_mic_test(app: AppHandle, device_id: Option<String>) -> Result<String, String> {
+    permissions::require(&app, MICROPHONE)?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A microphone test is already running".to_string());
+    }
+    let result = async {
+        let path = get_media_dir(&app, "mic-test")?.join("sample.wav");
+        let device = device_id.unwrap_or_else(|| "0".to_string());
+        debug!(device, "starting microphone test");
+        let recorder = app.clone();
+        let sample = path.clone();
+        tokio::task::spawn_blocking(move || record_sample(&recorder, &device, &sample))
+            .await
+            .map_err(|e| format!("Microphone test task panicked: {}", e))??;
+        info!(path = ?path, "microphone test recorded");
+        Ok(path.to_string_lossy().to_string())
+    }
+    .await;
+    RUNNING.store(false, Ordering::SeqCst);
+    result
+}
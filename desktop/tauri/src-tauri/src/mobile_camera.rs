@@ -0,0 +1,62 @@
+//! Photo capture on Android. nokhwa and the AVFoundation bridge are desktop
+//! only, so the system camera app takes the photo through `CameraPlugin`
+//! (gen/android/app/src/main/java/com/trackthething/desktop/CameraPlugin.kt).
+//! It writes to the app's cache dir, which the FileProvider in Tauri's
+//! Android template already exposes; the photo is then moved to `photos/`
+//! like on the desktop.
+
+use std::fs;
+use std::path::PathBuf;
+
+use log::info;
+use serde::Deserialize;
+use tauri::plugin::{Builder, PluginHandle, TauriPlugin};
+use tauri::{async_runtime, AppHandle, Manager, Wry};
+
+use crate::capture::{CaptureKind, CaptureOptions};
+use crate::capture_names;
+use crate::media_store::get_media_dir;
+
+const PLUGIN_PACKAGE: &str = "com.trackthething.desktop";
+
+struct MobileCamera(PluginHandle<Wry>);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptureResponse {
+    /// Where the camera app wrote the photo, in the cache dir
+    path: PathBuf,
+}
+
+pub fn init() -> TauriPlugin<Wry> {
+    Builder::new("camera")
+        .setup(|app, api| {
+            let handle = api.register_android_plugin(PLUGIN_PACKAGE, "CameraPlugin")?;
+            app.manage(MobileCamera(handle));
+            Ok(())
+        })
+        .build()
+}
+
+/// Take a photo with the system camera app and return its path under
+/// `photos/`. Fails when the user backs out of the camera.
+#[tauri::command]
+pub async fn capture_photo(app: AppHandle) -> Result<String, String> {
+    let photos_dir = get_media_dir(&app, "photos")?;
+    let handle = app.state::<MobileCamera>().0.clone();
+    let captured = async_runtime::spawn_blocking(move || handle.run_mobile_plugin::<CaptureResponse>("capturePhoto", ()))
+        .await
+        .map_err(|e| format!("Camera task panicked: {}", e))?
+        .map_err(|e| e.to_string())?;
+
+    let stem = capture_names::stem(&photos_dir, &[".jpg"], CaptureKind::Photo, &CaptureOptions::default(), None)?;
+    let file_path = photos_dir.join(format!("{}.jpg", stem));
+    // The cache dir may be on another volume than app data
+    if fs::rename(&captured.path, &file_path).is_err() {
+        fs::copy(&captured.path, &file_path).map_err(|e| format!("Failed to save photo: {}", e))?;
+        let _ = fs::remove_file(&captured.path);
+    }
+    info!("Photo saved to {}", file_path.display());
+    crate::telemetry::record(&app, "photo_capture", None);
+    Ok(file_path.to_string_lossy().to_string())
+}
@@ -0,0 +1,145 @@
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::time::sleep;
+
+// How often the local route is checked for interface changes
+const INTERFACE_INTERVAL: Duration = Duration::from_secs(5);
+
+// How often reachability is re-probed when no interface change was seen
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub online: bool,
+    /// Address of the interface that currently routes outbound traffic
+    pub local_address: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+pub struct NetworkMonitor {
+    online: AtomicBool,
+    status: Mutex<NetworkStatus>,
+    probes: Vec<String>,
+}
+
+fn resolve(probe: &str) -> Option<SocketAddr> {
+    probe.to_socket_addrs().ok()?.next()
+}
+
+/// Local address the OS would use to reach `target`. Connecting a UDP socket
+/// only selects a route, no packets are sent.
+fn local_route(target: SocketAddr) -> Option<IpAddr> {
+    let bind = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+fn current_local_address(probes: &[String]) -> Option<IpAddr> {
+    probes.iter().filter_map(|probe| resolve(probe)).find_map(local_route)
+}
+
+fn probe_reachable(probes: &[String]) -> bool {
+    probes.iter().any(|probe| {
+        resolve(probe)
+            .map(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+            .unwrap_or(false)
+    })
+}
+
+impl NetworkMonitor {
+    /// Result of the last probe
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+}
+
+/// Watch for interface changes and probe reachability, emitting
+/// `network-status` whenever the machine goes online or offline.
+pub fn init_network_monitor(app: &AppHandle, probes: Vec<String>) {
+    info!("Network probes: {}", probes.join(", "));
+    app.manage(NetworkMonitor {
+        // Assume online until the first probe says otherwise, so startup isn't gated on it
+        online: AtomicBool::new(true),
+        status: Mutex::new(NetworkStatus {
+            online: true,
+            local_address: None,
+            checked_at: Utc::now(),
+        }),
+        probes,
+    });
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let mut last_address: Option<IpAddr> = None;
+        let mut since_probe = PROBE_INTERVAL;
+        let mut first = true;
+
+        loop {
+            let monitor = app.state::<NetworkMonitor>();
+            let probes = monitor.probes.clone();
+
+            let address = {
+                let probes = probes.clone();
+                async_runtime::spawn_blocking(move || current_local_address(&probes))
+                    .await
+                    .ok()
+                    .flatten()
+            };
+            let interface_changed = address != last_address;
+            if interface_changed && !first {
+                info!(
+                    "Network interface changed: {}",
+                    address.map(|addr| addr.to_string()).unwrap_or_else(|| "none".into())
+                );
+            }
+            last_address = address;
+
+            if first || interface_changed || since_probe >= PROBE_INTERVAL {
+                since_probe = Duration::ZERO;
+                // No route at all means offline without waiting on probe timeouts
+                let online = address.is_some()
+                    && async_runtime::spawn_blocking(move || probe_reachable(&probes))
+                        .await
+                        .unwrap_or(false);
+
+                let status = NetworkStatus {
+                    online,
+                    local_address: address.map(|addr| addr.to_string()),
+                    checked_at: Utc::now(),
+                };
+                let was_online = monitor.online.swap(online, Ordering::Relaxed);
+                if let Ok(mut current) = monitor.status.lock() {
+                    *current = status.clone();
+                }
+
+                if first || was_online != online {
+                    info!("Network is {}", if online { "online" } else { "offline" });
+                    if let Err(e) = app.emit("network-status", &status) {
+                        warn!("Failed to emit network-status event: {}", e);
+                    }
+                }
+                first = false;
+            }
+
+            sleep(INTERFACE_INTERVAL).await;
+            since_probe += INTERFACE_INTERVAL;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_network_status(monitor: State<'_, NetworkMonitor>) -> Result<NetworkStatus, String> {
+    let status = monitor.status.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(status.clone())
+}
@@ -0,0 +1,455 @@
+use std::fs;
+use std::path::PathBuf;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::dnd::{dnd_active, suppress_notification};
+use crate::focus_main_window;
+
+const SCHEDULE_FILE: &str = "scheduled_notifications.json";
+
+// Upper bound on how long the scheduler sleeps, so wall-clock jumps (e.g. waking
+// from sleep) are noticed without waiting for the next due notification
+const MAX_SCHEDULER_SLEEP: Duration = Duration::from_secs(30);
+
+// Action id reported when the notification body itself is clicked
+const DEFAULT_ACTION: &str = "default";
+
+// Threads blocked waiting for a click on a notification the user may never
+// touch. Past MAX_LISTENERS, notifications are shown without actions and
+// nothing waits for them. Windows calls back instead of blocking.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const MAX_LISTENERS: usize = 8;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+static LISTENERS: AtomicUsize = AtomicUsize::new(0);
+
+/// A slot for one thread waiting on a notification, freed on drop
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+struct Listener;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Listener {
+    fn reserve() -> Option<Self> {
+        LISTENERS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < MAX_LISTENERS).then_some(count + 1))
+            .ok()
+            .map(|_| Listener)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        LISTENERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPayload {
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub actions: Vec<NotificationAction>,
+    /// Opaque data handed back with the `notification-action` event
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledNotification {
+    pub id: String,
+    pub at: DateTime<Utc>,
+    pub payload: NotificationPayload,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct NotificationActionEvent {
+    id: String,
+    action: String,
+    data: Option<serde_json::Value>,
+}
+
+/// Scheduled notifications, persisted to the app data dir so reminders survive
+/// restarts and fire from Rust even while the webview is hidden.
+pub struct NotificationScheduler {
+    pending: Mutex<Vec<ScheduledNotification>>,
+    wake: Notify,
+    store_path: Option<PathBuf>,
+}
+
+impl NotificationScheduler {
+    fn load(app: &AppHandle) -> Self {
+        let store_path = app.path().app_data_dir().ok().map(|dir| dir.join(SCHEDULE_FILE));
+
+        let pending = store_path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| match fs::read_to_string(path) {
+                Ok(content) => match serde_json::from_str::<Vec<ScheduledNotification>>(&content) {
+                    Ok(pending) => Some(pending),
+                    Err(e) => {
+                        warn!("Failed to parse scheduled notifications: {}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to read scheduled notifications: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        info!("Loaded {} scheduled notification(s)", pending.len());
+
+        Self {
+            pending: Mutex::new(pending),
+            wake: Notify::new(),
+            store_path,
+        }
+    }
+
+    fn persist(&self, pending: &[ScheduledNotification]) {
+        let Some(path) = &self.store_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                warn!("Failed to create notification store directory: {}", e);
+                return;
+            }
+        }
+
+        let json = match serde_json::to_string_pretty(pending) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize scheduled notifications: {}", e);
+                return;
+            }
+        };
+
+        // Write to a temp file and rename so a crash mid-write can't truncate the store
+        let tmp_path = path.with_extension("json.tmp");
+        if let Err(e) = fs::write(&tmp_path, json).and_then(|_| fs::rename(&tmp_path, path)) {
+            warn!("Failed to write scheduled notifications: {}", e);
+        }
+    }
+
+    /// Schedule `notification`, replacing any pending one with the same id
+    pub(crate) fn add(&self, notification: ScheduledNotification) -> Result<(), String> {
+        let mut pending = self.pending.lock().map_err(|e| format!("Lock error: {}", e))?;
+        pending.retain(|existing| existing.id != notification.id);
+        pending.push(notification);
+        self.persist(&pending);
+        drop(pending);
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    pub(crate) fn cancel(&self, id: &str) -> Result<bool, String> {
+        let mut pending = self.pending.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let before = pending.len();
+        pending.retain(|notification| notification.id != id);
+        let removed = pending.len() != before;
+        if removed {
+            self.persist(&pending);
+        }
+        Ok(removed)
+    }
+
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<ScheduledNotification> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let (due, remaining): (Vec<_>, Vec<_>) =
+            pending.drain(..).partition(|notification| notification.at <= now);
+        *pending = remaining;
+        if !due.is_empty() {
+            self.persist(&pending);
+        }
+        due
+    }
+
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.pending
+            .lock()
+            .ok()?
+            .iter()
+            .map(|notification| notification.at)
+            .min()
+    }
+}
+
+/// Load persisted notifications and start the scheduler loop.
+/// Notifications that came due while the app was closed fire immediately.
+pub fn init_notifications(app: &AppHandle) {
+    app.manage(NotificationScheduler::load(app));
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            let scheduler = app.state::<NotificationScheduler>();
+
+            // Reminders that come due during Do Not Disturb wait until it ends
+            let any_due = scheduler.next_due().is_some_and(|at| at <= Utc::now());
+            let deferring = any_due
+                && async_runtime::spawn_blocking(dnd_active).await.ok().flatten() == Some(true);
+
+            if !deferring {
+                for notification in scheduler.take_due(Utc::now()) {
+                    info!("Delivering scheduled notification {}", notification.id);
+                    if let Err(e) = show_native(&app, &notification.id, notification.payload) {
+                        warn!("Failed to deliver scheduled notification: {}", e);
+                    }
+                }
+            }
+
+            let wait = if deferring {
+                MAX_SCHEDULER_SLEEP
+            } else {
+                scheduler
+                    .next_due()
+                    .map(|at| (at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+                    .unwrap_or(MAX_SCHEDULER_SLEEP)
+                    .min(MAX_SCHEDULER_SLEEP)
+            };
+            let _ = tokio::time::timeout(wait, scheduler.wake.notified()).await;
+        }
+    });
+}
+
+fn next_notification_id() -> String {
+    format!(
+        "ntf-{}-{}",
+        Utc::now().timestamp_millis(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn emit_action(app: &AppHandle, id: &str, action: &str, data: Option<serde_json::Value>) {
+    info!("Notification {} activated with action '{}'", id, action);
+
+    // Clicking the notification itself should bring the app forward
+    if action == DEFAULT_ACTION {
+        focus_main_window(app);
+    }
+
+    if let Err(e) = app.emit(
+        "notification-action",
+        NotificationActionEvent {
+            id: id.to_string(),
+            action: action.to_string(),
+            data,
+        },
+    ) {
+        warn!("Failed to emit notification-action event: {}", e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn show_native(app: &AppHandle, id: &str, payload: NotificationPayload) -> Result<(), String> {
+    use crate::locale::{tr, Text};
+
+    let listener = Listener::reserve();
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname(&app.package_info().name)
+        .summary(&payload.title)
+        .body(&payload.body);
+    if listener.is_some() {
+        // XDG servers invoke the "default" action when the notification body is clicked
+        notification.action(DEFAULT_ACTION, tr(Text::Open));
+        for action in &payload.actions {
+            notification.action(&action.id, &action.label);
+        }
+    } else {
+        warn!("Too many notifications awaiting a click, showing {} without actions", id);
+    }
+
+    let handle = notification
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))?;
+
+    if let Some(listener) = listener {
+        let app = app.clone();
+        let id = id.to_string();
+        std::thread::spawn(move || {
+            let _listener = listener;
+            handle.wait_for_action(|action| {
+                if action != "__closed" {
+                    emit_action(&app, &id, action, payload.data);
+                }
+            });
+        });
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn show_native(app: &AppHandle, id: &str, payload: NotificationPayload) -> Result<(), String> {
+    use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+
+    use crate::locale::{tr, Text};
+
+    let bundle_id = app.config().identifier.clone();
+    let app = app.clone();
+    let id = id.to_string();
+    let listener = Listener::reserve();
+    if listener.is_none() {
+        warn!("Too many notifications awaiting a click, showing {} without actions", id);
+    }
+
+    // Delivery blocks until the user interacts with the notification, unless
+    // no listener slot was free
+    std::thread::spawn(move || {
+        // Only the first call takes effect; later calls report "already set"
+        let _ = mac_notification_sys::set_application(&bundle_id);
+
+        let labels: Vec<&str> = payload.actions.iter().map(|action| action.label.as_str()).collect();
+        let mut notification = Notification::new();
+        notification
+            .title(&payload.title)
+            .message(&payload.body)
+            .wait_for_click(listener.is_some());
+        match labels.as_slice() {
+            _ if listener.is_none() => {}
+            [] => {}
+            [single] => {
+                notification.main_button(MainButton::SingleAction(single));
+            }
+            many => {
+                notification.main_button(MainButton::DropdownActions(tr(Text::Actions), many));
+            }
+        }
+
+        match notification.send() {
+            Ok(NotificationResponse::Click) => emit_action(&app, &id, DEFAULT_ACTION, payload.data.clone()),
+            Ok(NotificationResponse::ActionButton(label)) => {
+                let action = payload
+                    .actions
+                    .iter()
+                    .find(|action| action.label == label)
+                    .map(|action| action.id.as_str())
+                    .unwrap_or(label.as_str());
+                emit_action(&app, &id, action, payload.data.clone());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to show notification: {}", e),
+        }
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn show_native(app: &AppHandle, id: &str, payload: NotificationPayload) -> Result<(), String> {
+    use tauri_winrt_notification::Toast;
+
+    // Unpackaged dev builds have no registered AppUserModelID, borrow PowerShell's
+    let app_id = if cfg!(debug_assertions) {
+        Toast::POWERSHELL_APP_ID.to_string()
+    } else {
+        app.config().identifier.clone()
+    };
+
+    let mut toast = Toast::new(&app_id).title(&payload.title).text1(&payload.body);
+    for action in &payload.actions {
+        toast = toast.add_button(&action.label, &action.id);
+    }
+
+    let app = app.clone();
+    let id = id.to_string();
+    let data = payload.data;
+    toast
+        .on_activated(move |action| {
+            emit_action(&app, &id, action.as_deref().unwrap_or(DEFAULT_ACTION), data.clone());
+            Ok(())
+        })
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn show_native(_app: &AppHandle, _id: &str, _payload: NotificationPayload) -> Result<(), String> {
+    Err("Notifications are not supported on this platform".to_string())
+}
+
+/// Show a notification immediately. Returns the notification id used in
+/// `notification-action` events. While Do Not Disturb is on the notification
+/// is downgraded to a badge update instead.
+#[tauri::command]
+pub async fn notify(
+    app: AppHandle,
+    title: String,
+    body: String,
+    actions: Option<Vec<NotificationAction>>,
+) -> Result<String, String> {
+    let id = next_notification_id();
+    if dnd_active() == Some(true) {
+        suppress_notification(&app, &id, &title);
+        return Ok(id);
+    }
+    show_native(
+        &app,
+        &id,
+        NotificationPayload {
+            title,
+            body,
+            actions: actions.unwrap_or_default(),
+            data: None,
+        },
+    )?;
+    Ok(id)
+}
+
+/// Schedule a notification for `at` (RFC 3339). Past times fire immediately;
+/// anything due during Do Not Disturb is delivered once it ends.
+#[tauri::command]
+pub async fn schedule_notification(
+    scheduler: State<'_, NotificationScheduler>,
+    at: DateTime<Utc>,
+    payload: NotificationPayload,
+) -> Result<String, String> {
+    let id = next_notification_id();
+    info!("Scheduling notification {} for {}", id, at.to_rfc3339());
+    scheduler.add(ScheduledNotification {
+        id: id.clone(),
+        at,
+        payload,
+    })?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn cancel_scheduled_notification(
+    scheduler: State<'_, NotificationScheduler>,
+    id: String,
+) -> Result<bool, String> {
+    scheduler.cancel(&id)
+}
+
+#[tauri::command]
+pub async fn list_scheduled_notifications(
+    scheduler: State<'_, NotificationScheduler>,
+) -> Result<Vec<ScheduledNotification>, String> {
+    let pending = scheduler.pending.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(pending.clone())
+}
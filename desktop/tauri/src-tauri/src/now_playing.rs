@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, PlatformConfig};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+use crate::locale::{tr, Text};
+
+// MPRIS bus name suffix on Linux (org.mpris.MediaPlayer2.<name>)
+const DBUS_NAME: &str = "trackthething";
+
+thread_local! {
+    // SMTC and MPNowPlayingInfoCenter handles must stay on the main thread
+    static CONTROLS: RefCell<Option<NowPlaying>> = const { RefCell::new(None) };
+}
+
+struct NowPlaying {
+    controls: MediaControls,
+    /// Media keys are only claimed while a recording or timer is active, so
+    /// they keep controlling the user's music player the rest of the time
+    attached: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum NowPlayingKind {
+    Recording,
+    Timer,
+}
+
+impl NowPlayingKind {
+    fn label(self) -> &'static str {
+        match self {
+            NowPlayingKind::Recording => tr(Text::Recording),
+            NowPlayingKind::Timer => tr(Text::Timer),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MediaKeyEvent {
+    action: &'static str,
+}
+
+fn handle_media_event(app: &AppHandle, event: MediaControlEvent) {
+    let action = match event {
+        MediaControlEvent::Play => "play",
+        MediaControlEvent::Pause => "pause",
+        MediaControlEvent::Toggle => "toggle",
+        MediaControlEvent::Stop => "stop",
+        MediaControlEvent::Raise => {
+            crate::focus_main_window(app);
+            return;
+        }
+        _ => return,
+    };
+    info!("Media key: {}", action);
+    if let Err(e) = app.emit("media-key", MediaKeyEvent { action }) {
+        warn!("Failed to emit media-key event: {}", e);
+    }
+}
+
+/// Run `f` against the media controls on the main thread and wait for its result
+async fn with_controls<T, F>(app: &AppHandle, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut NowPlaying) -> Result<T, String> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    app.run_on_main_thread(move || {
+        let result = CONTROLS.with(|controls| match controls.borrow_mut().as_mut() {
+            Some(now_playing) => f(now_playing),
+            None => Err("System media controls are not available".to_string()),
+        });
+        let _ = tx.send(result);
+    })
+    .map_err(|e| format!("Failed to reach the main thread: {}", e))?;
+    rx.await
+        .map_err(|e| format!("Media controls did not respond: {}", e))?
+}
+
+/// Create the platform media session. Must be called from `setup`, which runs
+/// on the main thread.
+pub fn init_now_playing(app: &AppHandle) {
+    #[cfg(target_os = "windows")]
+    let hwnd = {
+        use tauri::Manager;
+        match app.get_webview_window("main").and_then(|window| window.hwnd().ok()) {
+            Some(hwnd) => Some(hwnd.0 as *mut std::ffi::c_void),
+            None => {
+                warn!("Main window handle unavailable, media keys disabled");
+                return;
+            }
+        }
+    };
+    #[cfg(not(target_os = "windows"))]
+    let hwnd = None;
+
+    let display_name = app.package_info().name.clone();
+    let config = PlatformConfig {
+        display_name: &display_name,
+        dbus_name: DBUS_NAME,
+        hwnd,
+    };
+    match MediaControls::new(config) {
+        Ok(controls) => CONTROLS.with(|slot| {
+            *slot.borrow_mut() = Some(NowPlaying {
+                controls,
+                attached: false,
+            });
+        }),
+        Err(e) => warn!("System media controls unavailable: {}", e),
+    }
+}
+
+/// Show the active recording or timer in the OS media UI and route the
+/// play/pause keys to it as `media-key` events
+#[tauri::command]
+pub async fn set_now_playing(
+    app: AppHandle,
+    title: String,
+    kind: NowPlayingKind,
+    playing: bool,
+) -> Result<(), String> {
+    let handler_app = app.clone();
+    with_controls(&app, move |now_playing| {
+        if !now_playing.attached {
+            now_playing
+                .controls
+                .attach(move |event| handle_media_event(&handler_app, event))
+                .map_err(|e| format!("Failed to attach media controls: {}", e))?;
+            now_playing.attached = true;
+        }
+
+        now_playing
+            .controls
+            .set_metadata(MediaMetadata {
+                title: Some(&title),
+                album: Some(kind.label()),
+                artist: Some("Track the Thing"),
+                ..Default::default()
+            })
+            .map_err(|e| format!("Failed to set now playing info: {}", e))?;
+
+        let playback = if playing {
+            MediaPlayback::Playing { progress: None }
+        } else {
+            MediaPlayback::Paused { progress: None }
+        };
+        now_playing
+            .controls
+            .set_playback(playback)
+            .map_err(|e| format!("Failed to set playback state: {}", e))
+    })
+    .await
+}
+
+/// Remove the app from the OS media UI and release the media keys
+#[tauri::command]
+pub async fn clear_now_playing(app: AppHandle) -> Result<(), String> {
+    with_controls(&app, |now_playing| {
+        if !now_playing.attached {
+            return Ok(());
+        }
+        let _ = now_playing.controls.set_playback(MediaPlayback::Stopped);
+        now_playing
+            .controls
+            .detach()
+            .map_err(|e| format!("Failed to detach media controls: {}", e))?;
+        now_playing.attached = false;
+        Ok(())
+    })
+    .await
+}
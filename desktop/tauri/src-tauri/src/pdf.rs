@@ -0,0 +1,194 @@
+//! PDFs made of images: scans and photos of paperwork collected into one
+//! document. Each page is a single JPEG, sized so the image fills it at
+//! `DPI`. That is all these documents need, so they're written directly
+//! rather than through a PDF library.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ExtendedColorType, RgbImage};
+use log::info;
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle};
+
+use crate::media_manifest;
+use crate::media_store::{get_media_dir, validate_app_path};
+
+/// Where assembled PDFs are written, under the app data dir
+const DOCUMENTS_DIR: &str = "documents";
+
+/// Resolution pages are sized for
+const DPI: f64 = 150.0;
+
+const JPEG_QUALITY: u8 = 80;
+
+/// Longest side of a page in `assemble_pdf`; a phone photo is scaled down
+/// to about A4 at 200 dpi
+const MAX_PAGE_SIDE: u32 = 2400;
+
+const MAX_PAGES: usize = 200;
+
+/// A page ready to be written
+pub(crate) struct PdfPage {
+    jpeg: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl PdfPage {
+    pub(crate) fn new(image: &RgbImage) -> Result<Self, String> {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+            .encode(image, image.width(), image.height(), ExtendedColorType::Rgb8)
+            .map_err(|e| format!("Failed to encode a page: {}", e))?;
+        Ok(Self {
+            jpeg,
+            width: image.width(),
+            height: image.height(),
+        })
+    }
+}
+
+/// Write `pages` to `path` as one PDF
+pub(crate) fn write(path: &Path, pages: &[PdfPage]) -> Result<(), String> {
+    // Catalog and page tree, then a page, its image and its content per page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|n| 3 + n * 3).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes(),
+    ];
+    for (page, id) in pages.iter().zip(page_ids) {
+        let (width, height) = (page.width as f64 * 72.0 / DPI, page.height as f64 * 72.0 / DPI);
+        let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im Do Q", width, height);
+        let image_header = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 \
+             /Filter /DCTDecode /Length {} >>\nstream\n",
+            page.width,
+            page.height,
+            page.jpeg.len()
+        );
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im {} 0 R >> >> \
+                 /Contents {} 0 R >>",
+                width,
+                height,
+                id + 1,
+                id + 2
+            )
+            .into_bytes(),
+        );
+        objects.push([image_header.as_bytes(), &page.jpeg, b"\nendstream"].concat());
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content).into_bytes());
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (number, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", number + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    fs::write(path, pdf).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Result of `assemble_pdf`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssembledPdf {
+    pub path: String,
+    pub pages: usize,
+    pub size_bytes: u64,
+}
+
+/// `dest` as a plain `.pdf` file name
+fn file_name(dest: Option<String>) -> Result<String, String> {
+    let Some(dest) = dest.map(|dest| dest.trim().to_string()).filter(|dest| !dest.is_empty()) else {
+        return Ok(format!("document_{}.pdf", Utc::now().timestamp_millis()));
+    };
+    if dest.contains(['/', '\\']) || dest.starts_with('.') {
+        return Err("The PDF's name must be a plain file name".to_string());
+    }
+    if dest.to_ascii_lowercase().ends_with(".pdf") {
+        Ok(dest)
+    } else {
+        Ok(format!("{}.pdf", dest))
+    }
+}
+
+fn assemble(images: &[PathBuf], dest: &Path) -> Result<(), String> {
+    let mut pages = Vec::with_capacity(images.len());
+    for path in images {
+        let image = image::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let image = if image.width().max(image.height()) > MAX_PAGE_SIDE {
+            image.resize(MAX_PAGE_SIDE, MAX_PAGE_SIDE, FilterType::Triangle)
+        } else {
+            image
+        };
+        pages.push(PdfPage::new(&image.into_rgb8())?);
+    }
+    let partial = dest.with_extension("partial.pdf");
+    let result = write(&partial, &pages)
+        .and_then(|()| fs::rename(&partial, dest).map_err(|e| format!("Failed to save {}: {}", dest.display(), e)));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Collect photos and scans from the app's data directories into one PDF,
+/// a page per image in the order given. The PDF is saved under
+/// `documents/` as `dest` (a file name) or `document_<timestamp>.pdf`, and
+/// can be uploaded like any other media file.
+#[tauri::command]
+pub async fn assemble_pdf(
+    app: AppHandle,
+    image_paths: Vec<String>,
+    dest: Option<String>,
+) -> Result<AssembledPdf, String> {
+    if image_paths.is_empty() {
+        return Err("A PDF needs at least one image".to_string());
+    }
+    if image_paths.len() > MAX_PAGES {
+        return Err(format!("A PDF can have at most {} pages", MAX_PAGES));
+    }
+    let images = image_paths
+        .iter()
+        .map(|path| validate_app_path(&app, path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let dest = get_media_dir(&app, DOCUMENTS_DIR)?.join(file_name(dest)?);
+    if dest.exists() {
+        return Err(format!("{} already exists", dest.display()));
+    }
+
+    let written = dest.clone();
+    async_runtime::spawn_blocking(move || assemble(&images, &written))
+        .await
+        .map_err(|e| format!("PDF task failed: {}", e))??;
+    let size_bytes = fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0);
+    info!("Assembled {} page(s) into {} ({} bytes)", image_paths.len(), dest.display(), size_bytes);
+    media_manifest::track(&app, vec![dest.clone()]);
+    Ok(AssembledPdf {
+        path: dest.to_string_lossy().to_string(),
+        pages: image_paths.len(),
+        size_bytes,
+    })
+}
@@ -0,0 +1,144 @@
+//! Feature permissions: the shell's sensitive features stay off until the user
+//! enables each one, independently of what the OS has granted the app. A
+//! command that needs a disabled feature fails with `permission-denied:<feature>`
+//! and announces it as `feature-permission-denied`, so the webview can offer
+//! to enable the feature and retry.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const CAMERA: &str = "camera";
+pub const MICROPHONE: &str = "microphone";
+pub const SCREEN: &str = "screen";
+pub const CLIPBOARD: &str = "clipboard";
+pub const ACTIVITY: &str = "activity";
+
+/// Every feature that has to be enabled before use
+pub const FEATURES: &[&str] = &[CAMERA, MICROPHONE, SCREEN, CLIPBOARD, ACTIVITY];
+
+/// Start of the error a denied command fails with; the feature follows
+pub const DENIED_PREFIX: &str = "permission-denied:";
+
+const PREFS_FILE: &str = "feature_permissions.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct PermissionPrefs {
+    enabled: BTreeSet<String>,
+}
+
+/// Features the user enabled, mirrored in `feature_permissions.json`
+pub struct FeaturePermissions {
+    enabled: Mutex<BTreeSet<String>>,
+    prefs_path: Option<PathBuf>,
+}
+
+/// One entry of `get_feature_permissions()`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FeaturePermission {
+    feature: &'static str,
+    enabled: bool,
+}
+
+/// Payload of `feature-permission-denied`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PermissionDenied<'a> {
+    feature: &'a str,
+}
+
+impl FeaturePermissions {
+    fn load(app: &AppHandle) -> Self {
+        let prefs_path = app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE));
+        let prefs: PermissionPrefs = match prefs_path.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse feature permissions: {}", e);
+                PermissionPrefs::default()
+            }),
+            _ => PermissionPrefs::default(),
+        };
+        Self {
+            enabled: Mutex::new(prefs.enabled),
+            prefs_path,
+        }
+    }
+
+    fn is_enabled(&self, feature: &str) -> bool {
+        self.enabled
+            .lock()
+            .map(|enabled| enabled.contains(feature))
+            .unwrap_or(false)
+    }
+
+    fn set(&self, feature: &str, value: bool) -> Result<(), String> {
+        let path = self.prefs_path.as_ref().ok_or("Failed to resolve app config directory")?;
+        let mut enabled = self.enabled.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let mut updated = enabled.clone();
+        if value {
+            updated.insert(feature.to_string());
+        } else {
+            updated.remove(feature);
+        }
+        let json = serde_json::to_string_pretty(&PermissionPrefs { enabled: updated.clone() })
+            .map_err(|e| format!("Failed to serialize feature permissions: {}", e))?;
+        crate::prefs::write_atomic(path, &json)?;
+        *enabled = updated;
+        Ok(())
+    }
+}
+
+pub fn init_permissions(app: &AppHandle) {
+    app.manage(FeaturePermissions::load(app));
+}
+
+/// Whether the user enabled `feature`; checks that fail are reported by `require`
+pub(crate) fn is_enabled(app: &AppHandle, feature: &str) -> bool {
+    app.try_state::<FeaturePermissions>()
+        .map(|permissions| permissions.is_enabled(feature))
+        .unwrap_or(false)
+}
+
+/// Fail with `permission-denied:<feature>` unless the user enabled `feature`
+pub(crate) fn require(app: &AppHandle, feature: &str) -> Result<(), String> {
+    if is_enabled(app, feature) {
+        return Ok(());
+    }
+    info!("Denied use of {}, which is not enabled", feature);
+    if let Err(e) = app.emit("feature-permission-denied", PermissionDenied { feature }) {
+        warn!("Failed to emit feature-permission-denied event: {}", e);
+    }
+    Err(format!("{}{}", DENIED_PREFIX, feature))
+}
+
+/// Every feature and whether the user enabled it
+#[tauri::command]
+pub async fn get_feature_permissions(state: State<'_, FeaturePermissions>) -> Result<Vec<FeaturePermission>, String> {
+    Ok(FEATURES
+        .iter()
+        .map(|&feature| FeaturePermission {
+            feature,
+            enabled: state.is_enabled(feature),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn set_feature_permission(
+    state: State<'_, FeaturePermissions>,
+    feature: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if !FEATURES.contains(&feature.as_str()) {
+        return Err(format!("Unknown feature: {}", feature));
+    }
+    state.set(&feature, enabled)?;
+    info!("Feature {} {}", feature, if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! Battery saver. While the machine runs on battery at or below
+//! `power.lowBatteryPercent`, the shell checks the backend's health less
+//! often, stops the camera preview, pauses activity sampling and holds back
+//! background jobs that weren't started from the webview. `set_power_profile`
+//! forces the saver on or off instead. Every switch is announced as
+//! `power-profile-changed`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+
+use crate::settings::SettingsState;
+
+// How often the battery is read
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Health checks are this many times further apart while saving
+pub const HEALTH_INTERVAL_FACTOR: u64 = 4;
+
+static SAVING: AtomicBool = AtomicBool::new(false);
+
+static STATUS: Mutex<PowerStatus> = Mutex::new(PowerStatus {
+    profile: PowerProfile::Normal,
+    mode: PowerMode::Auto,
+    battery: None,
+});
+
+/// What `set_power_profile` asked for
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerMode {
+    /// Follow the battery
+    #[default]
+    Auto,
+    Saver,
+    Normal,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerProfile {
+    Normal,
+    Saver,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Battery {
+    pub on_battery: bool,
+    /// Charge left, when the OS reports it
+    pub percent: Option<u8>,
+}
+
+/// Payload of `power-profile-changed`
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    pub profile: PowerProfile,
+    pub mode: PowerMode,
+    /// `None` without a battery or where it can't be read
+    pub battery: Option<Battery>,
+}
+
+/// Parses `pmset -g batt`: "Now drawing from 'Battery Power'" followed by a
+/// line with "-InternalBattery-0 (id=…)\t85%; discharging; …"
+#[cfg(target_os = "macos")]
+fn battery() -> Option<Battery> {
+    let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let line = output.lines().find(|line| line.contains("InternalBattery"))?;
+    let percent = line
+        .split_once('%')
+        .and_then(|(before, _)| before.rsplit(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|digits| digits.parse().ok());
+    Some(Battery {
+        on_battery: output.contains("'Battery Power'"),
+        percent,
+    })
+}
+
+/// The first system battery under `/sys/class/power_supply`; peripherals
+/// such as mice report `scope` `Device` and are skipped
+#[cfg(target_os = "linux")]
+fn battery() -> Option<Battery> {
+    use std::fs;
+
+    let read = |path: &std::path::Path, name: &str| {
+        fs::read_to_string(path.join(name)).map(|value| value.trim().to_string()).ok()
+    };
+    let supplies: Vec<_> = fs::read_dir("/sys/class/power_supply").ok()?.flatten().map(|entry| entry.path()).collect();
+    let mains_online = supplies
+        .iter()
+        .any(|path| read(path, "type").as_deref() == Some("Mains") && read(path, "online").as_deref() == Some("1"));
+    let battery = supplies.iter().find(|path| {
+        read(path, "type").as_deref() == Some("Battery") && read(path, "scope").as_deref() != Some("Device")
+    })?;
+    Some(Battery {
+        on_battery: !mains_online && read(battery, "status").as_deref() == Some("Discharging"),
+        percent: read(battery, "capacity").and_then(|capacity| capacity.parse().ok()),
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn battery() -> Option<Battery> {
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    const AC_OFFLINE: u8 = 0;
+    const NO_SYSTEM_BATTERY: u8 = 128;
+    const UNKNOWN: u8 = 255;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    let mut status = SystemPowerStatus::default();
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 || status.battery_flag & NO_SYSTEM_BATTERY != 0 {
+        return None;
+    }
+    Some(Battery {
+        on_battery: status.ac_line_status == AC_OFFLINE,
+        percent: (status.battery_life_percent != UNKNOWN).then_some(status.battery_life_percent.min(100)),
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn battery() -> Option<Battery> {
+    None
+}
+
+/// Whether the battery saver is on
+pub fn saving() -> bool {
+    SAVING.load(Ordering::Relaxed)
+}
+
+fn profile_for(mode: PowerMode, battery: Option<Battery>, low_battery_percent: u8) -> PowerProfile {
+    // 0 turns the automatic switch off
+    let low = low_battery_percent > 0
+        && battery.is_some_and(|battery| {
+            battery.on_battery && battery.percent.is_some_and(|percent| percent <= low_battery_percent)
+        });
+    match mode {
+        PowerMode::Saver => PowerProfile::Saver,
+        PowerMode::Auto if low => PowerProfile::Saver,
+        PowerMode::Auto | PowerMode::Normal => PowerProfile::Normal,
+    }
+}
+
+/// Recompute the profile with `change` applied to the status, and act on a switch
+fn update(app: &AppHandle, change: impl FnOnce(&mut PowerStatus)) -> PowerStatus {
+    let low_battery_percent = app.state::<SettingsState>().current().power.low_battery_percent;
+    let (status, switched) = {
+        let mut status = STATUS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        change(&mut status);
+        let profile = profile_for(status.mode, status.battery, low_battery_percent);
+        let switched = profile != status.profile;
+        status.profile = profile;
+        (*status, switched)
+    };
+    if !switched {
+        return status;
+    }
+
+    let saving = status.profile == PowerProfile::Saver;
+    SAVING.store(saving, Ordering::Relaxed);
+    info!(
+        "Battery saver {} ({:?} mode, battery {:?})",
+        if saving { "on" } else { "off" },
+        status.mode,
+        status.battery
+    );
+    if saving {
+        #[cfg(target_os = "macos")]
+        async_runtime::spawn(async {
+            match crate::preview::stop_preview().await {
+                Ok(true) => info!("Stopped the camera preview to save battery"),
+                Ok(false) => {}
+                Err(e) => warn!("Failed to stop the camera preview: {}", e),
+            }
+        });
+    } else {
+        // Jobs held back while saving
+        crate::jobs::schedule(app);
+    }
+    if let Err(e) = app.emit("power-profile-changed", status) {
+        warn!("Failed to emit power-profile-changed event: {}", e);
+    }
+    status
+}
+
+/// Read the battery now and every `POLL_INTERVAL`
+pub fn init_power(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            match async_runtime::spawn_blocking(battery).await {
+                Ok(battery) => {
+                    update(&app, |status| status.battery = battery);
+                }
+                Err(e) => warn!("Battery task failed: {}", e),
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_power_status() -> Result<PowerStatus, String> {
+    STATUS.lock().map(|status| *status).map_err(|e| format!("Lock error: {}", e))
+}
+
+/// Force the battery saver on (`saver`) or off (`normal`), or let the
+/// battery decide again (`auto`). Lasts until the app quits.
+#[tauri::command]
+pub async fn set_power_profile(app: AppHandle, mode: PowerMode) -> Result<PowerStatus, String> {
+    info!("Power mode set to {:?}", mode);
+    Ok(update(&app, |status| status.mode = mode))
+}
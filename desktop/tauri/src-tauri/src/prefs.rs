@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::info;
+use serde_json::Value;
+
+/// Key holding the schema version in every versioned preference file
+pub const VERSION_KEY: &str = "schemaVersion";
+
+/// Upgrades a document by one version. A file's migration chain has one entry
+/// per version: index `n` turns version `n` into version `n + 1`.
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// The version a document was written with. Files from before versioning have
+/// none (version 0); `legacy_key` covers an earlier name for the field.
+fn document_version(value: &Value, legacy_key: Option<&str>) -> u64 {
+    value
+        .get(VERSION_KEY)
+        .or_else(|| legacy_key.and_then(|key| value.get(key)))
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+}
+
+fn backup_path(path: &Path, version: u64) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+/// Write `contents` next to `path` and rename it into place, so a crash never
+/// leaves a truncated file
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, contents).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Why the file at `path` can't be a preference document: it isn't UTF-8,
+/// isn't valid JSON (e.g. truncated by a crash) or isn't a JSON object.
+/// `None` when it's fine, missing or can't be read at all.
+pub fn corruption(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(_) => return Some("not UTF-8 text".to_string()),
+    };
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(_)) => None,
+        Ok(_) => Some("not a JSON object".to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+/// Move an unusable file aside as `<file>.corrupt-<unix seconds>.bak` and
+/// return where it went
+pub fn set_aside(path: &Path) -> Result<PathBuf, String> {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    let backup = path.with_file_name(format!("{}.corrupt-{}.bak", name, secs));
+    fs::rename(path, &backup).map_err(|e| format!("Failed to move {} aside: {}", path.display(), e))?;
+    Ok(backup)
+}
+
+/// Read a versioned JSON preference file, migrating it to `migrations.len()`
+/// if it is older. The original is kept as `<file>.v<old version>.bak` and the
+/// migrated document is written back. Returns `None` when the file doesn't exist.
+pub fn load_versioned(
+    path: &Path,
+    migrations: &[Migration],
+    legacy_key: Option<&str>,
+) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut value: Value =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let current = migrations.len() as u64;
+    let version = document_version(&value, legacy_key);
+    if version > current {
+        return Err(format!(
+            "{} was written by a newer version (schema {}, this build supports {})",
+            path.display(),
+            version,
+            current
+        ));
+    }
+    if version == current {
+        return Ok(Some(value));
+    }
+
+    let backup = backup_path(path, version);
+    fs::copy(path, &backup).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        value = migration(value).map_err(|e| format!("Migrating {} from schema {}: {}", path.display(), from, e))?;
+    }
+    if let Value::Object(map) = &mut value {
+        map.insert(VERSION_KEY.to_string(), Value::from(current));
+    }
+
+    let json = serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    write_atomic(path, &json)?;
+    info!(
+        "Migrated {} from schema {} to {} (backup at {})",
+        path.display(),
+        version,
+        current,
+        backup.display()
+    );
+    Ok(Some(value))
+}
@@ -0,0 +1,235 @@
+//! Live camera preview. A capture thread keeps the newest frame as a JPEG in
+//! a pooled buffer and the webview pulls frames from the `ttt-preview://`
+//! protocol as raw bytes, one request per frame it's ready to draw. Frames the
+//! camera delivers as MJPEG are passed through untouched; anything else is
+//! decoded and encoded into reused buffers.
+
+use std::borrow::Cow;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ExtendedColorType;
+use nokhwa::pixel_format::RgbFormat;
+use nokhwa::utils::{CameraFormat, CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType, Resolution};
+use nokhwa::Camera;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::AppHandle;
+use tracing::{debug, info, instrument, warn};
+
+use crate::camera_controls;
+use crate::permissions::{self, CAMERA};
+
+/// URI scheme the preview is served on
+pub const SCHEME: &str = "ttt-preview";
+
+const FRAME_WIDTH: u32 = 1280;
+const FRAME_HEIGHT: u32 = 720;
+const FRAME_RATE: u32 = 30;
+const JPEG_QUALITY: u8 = 75;
+
+/// Buffers kept for reuse: the frame being captured, the newest one, and one
+/// or two still being copied out to the webview
+const POOL_SIZE: usize = 4;
+
+static POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// Newest frame and its sequence number
+static LATEST: Mutex<Option<(u64, Arc<PooledBuffer>)>> = Mutex::new(None);
+
+static PREVIEW: tokio::sync::Mutex<Option<Preview>> = tokio::sync::Mutex::const_new(None);
+
+struct Preview {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// A buffer that goes back to the pool when the last reference is dropped
+struct PooledBuffer(Vec<u8>);
+
+impl PooledBuffer {
+    fn take() -> Self {
+        let mut buffer = POOL.lock().ok().and_then(|mut pool| pool.pop()).unwrap_or_default();
+        buffer.clear();
+        Self(buffer)
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Ok(mut pool) = POOL.lock() {
+            if pool.len() < POOL_SIZE {
+                pool.push(mem::take(&mut self.0));
+            }
+        }
+    }
+}
+
+fn publish(seq: u64, frame: PooledBuffer) {
+    if let Ok(mut latest) = LATEST.lock() {
+        *latest = Some((seq, Arc::new(frame)));
+    }
+}
+
+/// Capture from camera `index` until `stop` is set, applying camera control
+/// changes as they're made. RGB scratch space for cameras without MJPEG is
+/// allocated once.
+fn capture(mut camera: Camera, index: u32, stop: Arc<AtomicBool>) {
+    let mut rgb = Vec::new();
+    let mut seq = 0u64;
+    let mut controls = camera_controls::generation();
+    camera_controls::apply_live(&mut camera, index);
+    while !stop.load(Ordering::Relaxed) {
+        if camera_controls::generation() != controls {
+            controls = camera_controls::generation();
+            camera_controls::apply_live(&mut camera, index);
+        }
+        let buffer = match camera.frame() {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                warn!(error = %e, "preview frame failed");
+                break;
+            }
+        };
+        let mut frame = PooledBuffer::take();
+        if buffer.source_frame_format() == FrameFormat::MJPEG {
+            frame.0.extend_from_slice(buffer.buffer());
+        } else {
+            let resolution = buffer.resolution();
+            rgb.resize((resolution.width() * resolution.height() * 3) as usize, 0);
+            let encoded = buffer
+                .decode_image_to_buffer::<RgbFormat>(&mut rgb)
+                .map_err(|e| e.to_string())
+                .and_then(|()| {
+                    JpegEncoder::new_with_quality(&mut frame.0, JPEG_QUALITY)
+                        .encode(&rgb, resolution.width(), resolution.height(), ExtendedColorType::Rgb8)
+                        .map_err(|e| e.to_string())
+                });
+            if let Err(e) = encoded {
+                warn!(error = %e, "preview frame could not be encoded");
+                continue;
+            }
+        }
+        seq += 1;
+        publish(seq, frame);
+    }
+    camera_controls::end_live();
+    if let Err(e) = camera.stop_stream() {
+        warn!(error = %e, "failed to stop the preview stream");
+    }
+    if let Ok(mut latest) = LATEST.lock() {
+        *latest = None;
+    }
+    debug!(frames = seq, "preview stopped");
+}
+
+fn respond(status: StatusCode, body: Cow<'static, [u8]>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "X-Frame-Seq")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(body)
+        .unwrap_or_default()
+}
+
+/// `ttt-preview://localhost/frame?after=<seq>`: the newest frame as
+/// `image/jpeg` with its sequence number in `X-Frame-Seq`, or 204 when there's
+/// nothing newer than `after`
+pub fn handle_request(request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let after = request
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("after=")))
+        .and_then(|seq| seq.parse::<u64>().ok())
+        .unwrap_or(0);
+    let latest = LATEST.lock().ok().and_then(|latest| latest.clone());
+    match latest {
+        Some((seq, frame)) if seq > after => {
+            let mut response = respond(StatusCode::OK, Cow::Owned(frame.0.clone()));
+            let headers = response.headers_mut();
+            headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("image/jpeg"));
+            if let Ok(value) = header::HeaderValue::from_str(&seq.to_string()) {
+                headers.insert("X-Frame-Seq", value);
+            }
+            response
+        }
+        Some(_) => respond(StatusCode::NO_CONTENT, Cow::Borrowed(&[][..])),
+        None => respond(StatusCode::NOT_FOUND, Cow::Borrowed(&b"Preview is not running"[..])),
+    }
+}
+
+/// Start the live preview on `camera` (the first one by default). Returns the
+/// URL frames are fetched from.
+#[tauri::command]
+#[instrument(skip(app), err)]
+pub async fn start_camera_preview(app: AppHandle, camera: Option<u32>) -> Result<String, String> {
+    permissions::require(&app, CAMERA)?;
+    if crate::power::saving() {
+        return Err("The camera preview is paused by the battery saver".to_string());
+    }
+    let mut preview = PREVIEW.lock().await;
+    if preview.as_ref().is_some_and(|preview| !preview.thread.is_finished()) {
+        return Err("The camera preview is already running".to_string());
+    }
+
+    let camera = camera.unwrap_or(0);
+    let index = CameraIndex::Index(camera);
+    let format = CameraFormat::new(Resolution::new(FRAME_WIDTH, FRAME_HEIGHT), FrameFormat::MJPEG, FRAME_RATE);
+    let requested = RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(format));
+    // Camera is not Send, so it's opened on the thread that uses it
+    let (opened_tx, opened_rx) = tokio::sync::oneshot::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop = stop.clone();
+        thread::Builder::new()
+            .name("camera-preview".to_string())
+            .spawn(move || {
+                let opened = Camera::new(index, requested).and_then(|mut camera| {
+                    camera.open_stream()?;
+                    Ok(camera)
+                });
+                match opened {
+                    Ok(opened) => {
+                        info!(format = ?opened.camera_format(), "preview started");
+                        let _ = opened_tx.send(Ok(()));
+                        capture(opened, camera, stop);
+                    }
+                    Err(e) => {
+                        let _ = opened_tx.send(Err(format!("Failed to open the camera: {}", e)));
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to start the preview thread: {}", e))?
+    };
+    opened_rx
+        .await
+        .map_err(|_| "The preview thread exited".to_string())??;
+
+    *preview = Some(Preview { stop, thread });
+    Ok(format!("{}://localhost/frame", SCHEME))
+}
+
+/// Stop the preview if it's running; returns whether it was
+pub(crate) async fn stop_preview() -> Result<bool, String> {
+    let Some(preview) = PREVIEW.lock().await.take() else {
+        return Ok(false);
+    };
+    preview.stop.store(true, Ordering::Relaxed);
+    tokio::task::spawn_blocking(move || preview.thread.join())
+        .await
+        .map_err(|e| format!("Preview task failed: {}", e))?
+        .map_err(|_| "The preview thread panicked".to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+#[instrument(err)]
+pub async fn stop_camera_preview() -> Result<(), String> {
+    if !stop_preview().await? {
+        return Err("The camera preview is not running".to_string());
+    }
+    Ok(())
+}
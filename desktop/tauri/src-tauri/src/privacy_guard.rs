@@ -0,0 +1,151 @@
+//! Privacy guard: opt-in rules (`privacy.guard`) that suspend captures and
+//! activity tracking while the screen is shared or while an app the user
+//! listed (a password manager, a banking app) is frontmost. A capture the
+//! guard stops fails with the reason and is announced as `capture-skipped`,
+//! so the webview can tell the user why nothing was captured.
+
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+
+use crate::settings::{PrivacyGuardSettings, SettingsState};
+
+/// Helper processes that only run while a conferencing app shares the screen
+const SHARING_PROCESSES: &[&str] = &[
+    // Zoom
+    "CptHost",
+];
+
+/// Why the activity sampler was last suspended, so `capture-skipped` goes
+/// out once per suspension rather than every sample
+static ACTIVITY_SUSPENDED: Mutex<Option<GuardReason>> = Mutex::new(None);
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum GuardReason {
+    ScreenSharing,
+    BlockedApp { app: String },
+}
+
+impl GuardReason {
+    fn message(&self) -> String {
+        match self {
+            GuardReason::ScreenSharing => "the screen is being shared".to_string(),
+            GuardReason::BlockedApp { app } => format!("{} is in front", app),
+        }
+    }
+}
+
+/// Payload of `capture-skipped`: `{ capture, reason, app? }`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CaptureSkipped<'a> {
+    /// `activity`, `clipboard` or a capture kind such as `screenshot`
+    capture: &'a str,
+    #[serde(flatten)]
+    reason: &'a GuardReason,
+}
+
+#[cfg(target_os = "macos")]
+fn os_reports_sharing() -> bool {
+    extern "C" {
+        fn activity_screen_is_shared() -> bool;
+    }
+    unsafe { activity_screen_is_shared() }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn os_reports_sharing() -> bool {
+    false
+}
+
+fn process_running(name: &str) -> bool {
+    let output = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/FI", &format!("IMAGENAME eq {}.exe", name), "/NH"])
+            .stderr(Stdio::null())
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(name))
+    } else {
+        Command::new("pgrep")
+            .args(["-x", name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+    };
+    output.unwrap_or(false)
+}
+
+/// Best effort; see `SHARING_PROCESSES`
+fn screen_shared() -> bool {
+    os_reports_sharing() || SHARING_PROCESSES.iter().any(|name| process_running(name))
+}
+
+/// The rule that blocks captures right now, if any. `frontmost` is the
+/// frontmost app's name when the caller already knows it. Blocks on process
+/// lookups.
+pub(crate) fn blocking_reason(settings: &PrivacyGuardSettings, frontmost: Option<&str>) -> Option<GuardReason> {
+    if !settings.enabled {
+        return None;
+    }
+    if settings.screen_sharing && screen_shared() {
+        return Some(GuardReason::ScreenSharing);
+    }
+    if settings.apps.is_empty() {
+        return None;
+    }
+    let frontmost = match frontmost {
+        Some(name) => name.to_string(),
+        None => crate::activity::frontmost()?.0,
+    };
+    settings
+        .apps
+        .iter()
+        .any(|blocked| blocked.trim().eq_ignore_ascii_case(&frontmost))
+        .then_some(GuardReason::BlockedApp { app: frontmost })
+}
+
+fn emit_skipped(app: &AppHandle, capture: &str, reason: &GuardReason) {
+    info!("Privacy guard skipped {}: {}", capture, reason.message());
+    if let Err(e) = app.emit("capture-skipped", CaptureSkipped { capture, reason }) {
+        warn!("Failed to emit capture-skipped event: {}", e);
+    }
+}
+
+/// Fail when a rule blocks captures, announcing the skipped `capture`
+pub(crate) async fn check(app: &AppHandle, capture: &str) -> Result<(), String> {
+    let settings = app.state::<SettingsState>().current().privacy.guard;
+    if !settings.enabled {
+        return Ok(());
+    }
+    let reason = async_runtime::spawn_blocking(move || blocking_reason(&settings, None))
+        .await
+        .map_err(|e| format!("Privacy guard task failed: {}", e))?;
+    match reason {
+        Some(reason) => {
+            emit_skipped(app, capture, &reason);
+            Err(format!("Capture skipped by the privacy guard: {}", reason.message()))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Record whether the activity sampler is suspended, announcing each new
+/// suspension
+pub(crate) fn set_activity_suspended(app: &AppHandle, reason: Option<GuardReason>) {
+    let Ok(mut suspended) = ACTIVITY_SUSPENDED.lock() else {
+        return;
+    };
+    if *suspended == reason {
+        return;
+    }
+    match &reason {
+        Some(reason) => emit_skipped(app, "activity", reason),
+        None => info!("Privacy guard resumed activity tracking"),
+    }
+    *suspended = reason;
+}
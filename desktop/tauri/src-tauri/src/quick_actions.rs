@@ -0,0 +1,220 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter};
+
+use crate::focus_main_window;
+
+/// Deep link route used by jump list entries, which can only launch the exe
+pub const ROUTE: &str = "quick-action";
+
+const MAX_ITEMS: usize = 20;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QuickActionKind {
+    /// Fixed tasks such as "New entry" or "Start timer"
+    #[default]
+    Action,
+    /// Recently used items such as projects
+    Recent,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickActionItem {
+    /// Sent back in the `quick-action` event when the item is clicked
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub kind: QuickActionKind,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct QuickActionEvent {
+    id: String,
+}
+
+/// Tell the webview a quick action was picked from the jump list or dock menu
+pub fn dispatch(app: &AppHandle, id: &str) {
+    info!("Quick action: {}", id);
+    focus_main_window(app);
+    let event = QuickActionEvent { id: id.to_string() };
+    if let Err(e) = app.emit("quick-action", event) {
+        warn!("Failed to emit quick-action event: {}", e);
+    }
+}
+
+fn validate(items: &[QuickActionItem]) -> Result<(), String> {
+    if items.len() > MAX_ITEMS {
+        return Err(format!("At most {} quick actions are supported", MAX_ITEMS));
+    }
+    for item in items {
+        // Ids travel inside a deep link URL on Windows
+        let valid_id = !item.id.is_empty()
+            && item
+                .id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || "-_.:".contains(ch));
+        if !valid_id {
+            return Err(format!("Invalid quick action id: {:?}", item.id));
+        }
+        if item.title.trim().is_empty() {
+            return Err(format!("Quick action {} has no title", item.id));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+    use std::sync::OnceLock;
+
+    use tauri::AppHandle;
+
+    use super::QuickActionItem;
+
+    static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+    extern "C" {
+        fn quick_actions_install(callback: extern "C" fn(*const c_char));
+        fn quick_actions_set_items(items_json: *const c_char, error_out: *mut *mut c_char) -> bool;
+        fn quick_actions_free_string(value: *mut c_char);
+    }
+
+    extern "C" fn on_quick_action(id: *const c_char) {
+        if id.is_null() {
+            return;
+        }
+        let id = unsafe { CStr::from_ptr(id) }.to_string_lossy().to_string();
+        if let Some(app) = APP_HANDLE.get() {
+            super::dispatch(app, &id);
+        }
+    }
+
+    pub fn init(app: &AppHandle) {
+        let _ = APP_HANDLE.set(app.clone());
+        unsafe { quick_actions_install(on_quick_action) };
+    }
+
+    pub fn apply(items: &[QuickActionItem]) -> Result<(), String> {
+        let json = serde_json::to_string(items).map_err(|e| format!("Failed to serialize items: {}", e))?;
+        let json = CString::new(json).map_err(|e| format!("Invalid item text: {}", e))?;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        if unsafe { quick_actions_set_items(json.as_ptr(), &mut error) } {
+            return Ok(());
+        }
+        if error.is_null() {
+            return Err("Failed to update the dock menu".to_string());
+        }
+        let message = unsafe { CStr::from_ptr(error) }.to_string_lossy().to_string();
+        unsafe { quick_actions_free_string(error) };
+        Err(message)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use tauri::AppHandle;
+    use windows::core::{Interface, HSTRING, PROPVARIANT};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+    };
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+    };
+
+    use super::{QuickActionItem, QuickActionKind, ROUTE};
+    use crate::deep_link::SCHEME;
+    use crate::locale::{tr, Text};
+
+    pub fn init(_app: &AppHandle) {}
+
+    /// A jump list entry that relaunches the app with a quick action deep link.
+    /// The single-instance plugin forwards it to the running window.
+    unsafe fn shell_link(exe: &HSTRING, item: &QuickActionItem) -> windows::core::Result<IShellLinkW> {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(exe)?;
+        link.SetArguments(&HSTRING::from(format!("{}://{}/{}", SCHEME, ROUTE, item.id)))?;
+        link.SetIconLocation(exe, 0)?;
+        let store: IPropertyStore = link.cast()?;
+        store.SetValue(&PKEY_Title, &PROPVARIANT::from(item.title.as_str()))?;
+        store.Commit()?;
+        Ok(link)
+    }
+
+    unsafe fn collection<'a>(
+        exe: &HSTRING,
+        items: impl Iterator<Item = &'a QuickActionItem>,
+    ) -> windows::core::Result<IObjectArray> {
+        let collection: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        for item in items {
+            collection.AddObject(&shell_link(exe, item)?)?;
+        }
+        collection.cast()
+    }
+
+    unsafe fn build(exe: &HSTRING, items: &[QuickActionItem]) -> windows::core::Result<()> {
+        let list: ICustomDestinationList = CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut max_slots = 0u32;
+        // Items the user removed from the list; recents are replaced wholesale anyway
+        let _removed: IObjectArray = list.BeginList(&mut max_slots)?;
+
+        let of_kind = |kind: QuickActionKind| items.iter().filter(move |item| item.kind == kind);
+        if of_kind(QuickActionKind::Recent).next().is_some() {
+            let recent = of_kind(QuickActionKind::Recent).take(max_slots as usize);
+            list.AppendCategory(&HSTRING::from(tr(Text::Recent)), &collection(exe, recent)?)?;
+        }
+        if of_kind(QuickActionKind::Action).next().is_some() {
+            list.AddUserTasks(&collection(exe, of_kind(QuickActionKind::Action))?)?;
+        }
+        list.CommitList()
+    }
+
+    pub fn apply(items: &[QuickActionItem]) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to resolve executable: {}", e))?;
+        let exe = HSTRING::from(exe.as_os_str());
+        unsafe {
+            // Already-initialized threads report S_FALSE, which is fine
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            build(&exe, items).map_err(|e| format!("Failed to update the jump list: {}", e))
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use tauri::AppHandle;
+
+    use super::QuickActionItem;
+
+    pub fn init(_app: &AppHandle) {}
+
+    pub fn apply(_items: &[QuickActionItem]) -> Result<(), String> {
+        Err("Quick actions are not supported on this platform".to_string())
+    }
+}
+
+/// Hook up dock menu clicks. Jump list clicks arrive as deep links instead.
+pub fn init_quick_actions(app: &AppHandle) {
+    platform::init(app);
+}
+
+/// Replace the jump list (Windows) or dock menu (macOS) entries. Clicks are
+/// emitted as `quick-action` events carrying the item id.
+#[tauri::command]
+pub async fn set_quick_actions(items: Vec<QuickActionItem>) -> Result<(), String> {
+    validate(&items)?;
+    let count = items.len();
+    async_runtime::spawn_blocking(move || platform::apply(&items))
+        .await
+        .map_err(|e| format!("Quick action update panicked: {}", e))??;
+    info!("Updated {} quick action(s)", count);
+    Ok(())
+}
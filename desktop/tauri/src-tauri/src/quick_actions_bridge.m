@@ -0,0 +1,88 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+#import <objc/runtime.h>
+
+typedef void (*QuickActionCallback)(const char *identifier);
+static QuickActionCallback actionCallback = NULL;
+
+// Returned from applicationDockMenu:, rebuilt whenever the webview sends new items
+static NSMenu *dockMenu = nil;
+
+@interface TTTQuickActionTarget : NSObject
+- (void)performQuickAction:(NSMenuItem *)sender;
+@end
+
+@implementation TTTQuickActionTarget
+- (void)performQuickAction:(NSMenuItem *)sender {
+    NSString *identifier = sender.representedObject;
+    if (actionCallback != NULL && [identifier isKindOfClass:[NSString class]]) {
+        actionCallback(identifier.UTF8String);
+    }
+}
+@end
+
+static TTTQuickActionTarget *actionTarget = nil;
+
+static NSMenu *tttApplicationDockMenu(id self, SEL _cmd, NSApplication *sender) {
+    return dockMenu;
+}
+
+// The Tauri app delegate has no dock menu, so add one at runtime
+void quick_actions_install(QuickActionCallback callback) {
+    actionCallback = callback;
+    dispatch_async(dispatch_get_main_queue(), ^{
+        actionTarget = [[TTTQuickActionTarget alloc] init];
+        id delegate = [NSApp delegate];
+        if (delegate == nil) {
+            return;
+        }
+        class_addMethod([delegate class],
+                        @selector(applicationDockMenu:),
+                        (IMP)tttApplicationDockMenu,
+                        "@@:@");
+    });
+}
+
+// Replace the dock menu with items_json, an array of {"id", "title", "kind"}
+// objects. Recent items are listed above the actions, separated by a divider.
+bool quick_actions_set_items(const char *items_json, char **error_out) {
+    @autoreleasepool {
+        NSData *data = [NSData dataWithBytes:items_json length:strlen(items_json)];
+        NSError *error = nil;
+        NSArray *items = [NSJSONSerialization JSONObjectWithData:data options:0 error:&error];
+        if (![items isKindOfClass:[NSArray class]]) {
+            if (error_out != NULL) {
+                *error_out = strdup((error.localizedDescription ?: @"Invalid quick actions").UTF8String);
+            }
+            return false;
+        }
+
+        dispatch_sync(dispatch_get_main_queue(), ^{
+            NSMenu *menu = [[NSMenu alloc] initWithTitle:@""];
+            for (NSString *kind in @[@"recent", @"action"]) {
+                BOOL needsSeparator = menu.numberOfItems > 0;
+                for (NSDictionary *item in items) {
+                    if (![item[@"kind"] isEqual:kind]) {
+                        continue;
+                    }
+                    if (needsSeparator) {
+                        [menu addItem:[NSMenuItem separatorItem]];
+                        needsSeparator = NO;
+                    }
+                    NSMenuItem *menuItem = [[NSMenuItem alloc] initWithTitle:item[@"title"]
+                                                                      action:@selector(performQuickAction:)
+                                                               keyEquivalent:@""];
+                    menuItem.target = actionTarget;
+                    menuItem.representedObject = item[@"id"];
+                    [menu addItem:menuItem];
+                }
+            }
+            dockMenu = menu.numberOfItems > 0 ? menu : nil;
+        });
+        return true;
+    }
+}
+
+void quick_actions_free_string(char *value) {
+    free(value);
+}
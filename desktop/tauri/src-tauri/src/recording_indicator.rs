@@ -0,0 +1,160 @@
+//! A small always-on-top window shown while a capture source records: a red
+//! dot, the elapsed time and a stop button. It sits in the top-right corner
+//! of the monitor under the pointer, so a recording left running after the
+//! app was minimized is hard to miss. The page is served from the
+//! `ttt-indicator://` protocol and the stop button is a link the window's
+//! navigation handler catches, so the window gets no IPC access.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use log::{info, warn};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{async_runtime, AppHandle, Manager, PhysicalPosition, WebviewUrl, WebviewWindowBuilder};
+use tokio::time::sleep;
+
+use crate::capture::{self, CaptureManager};
+use crate::tray::format_duration;
+
+/// URI scheme the indicator page is served on
+pub const SCHEME: &str = "ttt-indicator";
+
+const LABEL: &str = "recording-indicator";
+const WIDTH: f64 = 148.0;
+const HEIGHT: f64 = 40.0;
+/// Gap to the monitor's edges, in logical pixels
+const MARGIN: f64 = 16.0;
+const INTERVAL: Duration = Duration::from_secs(1);
+
+const PAGE: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+  html, body { margin: 0; height: 100%; overflow: hidden; user-select: none; cursor: default; }
+  body { display: flex; align-items: center; gap: 8px; padding: 0 8px 0 12px; box-sizing: border-box;
+         background: #1f1f1f; color: #fff; font: 600 14px -apple-system, "Segoe UI", sans-serif; }
+  .dot { width: 10px; height: 10px; border-radius: 50%; background: #e5383b; animation: pulse 1.2s infinite; }
+  #elapsed { flex: 1; font-variant-numeric: tabular-nums; }
+  a { display: block; width: 22px; height: 22px; border-radius: 4px; background: #e5383b; }
+  a::after { content: ""; display: block; width: 8px; height: 8px; margin: 7px; background: #fff; }
+  @keyframes pulse { 50% { opacity: 0.3; } }
+</style>
+</head>
+<body>
+  <span class="dot"></span>
+  <span id="elapsed">00:00</span>
+  <a href="/stop" title="Stop recording"></a>
+</body>
+</html>
+"#;
+
+/// `ttt-indicator://localhost/`: the indicator page
+pub fn handle_request(_request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Cow::Borrowed(PAGE.as_bytes()))
+        .unwrap_or_default()
+}
+
+fn page_url() -> Result<tauri::Url, String> {
+    // WebView2 serves custom protocols over http
+    let url = if cfg!(windows) {
+        format!("http://{}.localhost/", SCHEME)
+    } else {
+        format!("{}://localhost/", SCHEME)
+    };
+    url.parse().map_err(|e| format!("Invalid indicator URL: {}", e))
+}
+
+/// Stop every running recording, for the stop button
+fn stop_all(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let ids = app.state::<CaptureManager>().recording_ids();
+        for id in ids {
+            match capture::stop_recording(&app, id).await {
+                Ok(path) => info!("Recording {} stopped from the indicator: {}", id, path),
+                Err(e) => warn!("Failed to stop recording {}: {}", id, e),
+            }
+        }
+    });
+}
+
+/// Top-right corner of the monitor the pointer is on
+fn position(app: &AppHandle) -> Option<PhysicalPosition<f64>> {
+    let monitor = app
+        .cursor_position()
+        .ok()
+        .and_then(|cursor| app.monitor_from_point(cursor.x, cursor.y).ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten())?;
+    let scale = monitor.scale_factor();
+    let origin = monitor.position();
+    let size = monitor.size();
+    Some(PhysicalPosition::new(
+        origin.x as f64 + size.width as f64 - (WIDTH + MARGIN) * scale,
+        origin.y as f64 + MARGIN * scale,
+    ))
+}
+
+fn show(app: &AppHandle) -> Result<(), String> {
+    let stop_handle = app.clone();
+    let window = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::CustomProtocol(page_url()?))
+        .title("Recording")
+        .inner_size(WIDTH, HEIGHT)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .visible_on_all_workspaces(true)
+        .skip_taskbar(true)
+        .focused(false)
+        .visible(false)
+        .on_navigation(move |url| {
+            if url.path() == "/stop" {
+                stop_all(&stop_handle);
+                return false;
+            }
+            true
+        })
+        .build()
+        .map_err(|e| format!("Failed to create recording indicator: {}", e))?;
+    if let Some(position) = position(app) {
+        let _ = window.set_position(position);
+    }
+    window.show().map_err(|e| e.to_string())
+}
+
+fn update(app: &AppHandle, elapsed: Option<Duration>) {
+    let window = app.get_webview_window(LABEL);
+    match (elapsed, window) {
+        (Some(elapsed), Some(window)) => {
+            let script = format!(
+                "document.getElementById('elapsed').textContent = {:?}",
+                format_duration(elapsed.as_secs())
+            );
+            let _ = window.eval(&script);
+        }
+        (Some(_), None) => {
+            if let Err(e) = show(app) {
+                warn!("{}", e);
+            }
+        }
+        (None, Some(window)) => {
+            let _ = window.destroy();
+        }
+        (None, None) => {}
+    }
+}
+
+/// Show the indicator whenever a recording runs
+pub fn init_recording_indicator(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(INTERVAL).await;
+            let elapsed = app.state::<CaptureManager>().recording();
+            update(&app, elapsed);
+        }
+    });
+}
@@ -0,0 +1,168 @@
+//! Reminders fired by the shell. The backend's reminders are mirrored into
+//! the notification scheduler, which fires them from Rust, so a reminder
+//! still shows up while the backend is stopped, restarting or still
+//! starting. One that came due while the machine slept or the app was
+//! closed is delivered when it wakes or starts.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use tauri::{async_runtime, AppHandle, Manager};
+use tokio::time::sleep;
+
+use crate::locale::{tr, Text};
+use crate::notifications::{NotificationPayload, NotificationScheduler, ScheduledNotification};
+use crate::{backend_client, DesktopConfig};
+
+const MIRROR_FILE: &str = "reminder_mirror.json";
+
+/// How often the mirror is refreshed from the backend
+const SYNC_INTERVAL: Duration = Duration::from_secs(120);
+
+/// Prefix of the scheduled notification ids of mirrored reminders
+const ID_PREFIX: &str = "reminder-";
+
+/// Serializes syncs and access to the mirror file
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// An item of `GET /api/reminders`
+#[derive(Deserialize, Debug)]
+struct BackendReminder {
+    id: i64,
+    entry_id: i64,
+    reminder_datetime: String,
+    entry: Option<ReminderEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReminderEntry {
+    title: String,
+}
+
+/// The backend stores UTC, usually without an offset
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|time| time.and_utc()))
+}
+
+fn mirror_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(MIRROR_FILE))
+}
+
+/// Reminder id to the time it was handed to the scheduler for. A reminder
+/// is scheduled once per time, so one already delivered isn't repeated.
+fn load_mirror(app: &AppHandle) -> BTreeMap<i64, DateTime<Utc>> {
+    let Some(path) = mirror_path(app) else {
+        return BTreeMap::new();
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", MIRROR_FILE, e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_mirror(app: &AppHandle, mirror: &BTreeMap<i64, DateTime<Utc>>) -> Result<(), String> {
+    let path = mirror_path(app).ok_or("Failed to resolve app data directory")?;
+    let json = serde_json::to_string_pretty(mirror).map_err(|e| format!("Failed to serialize {}: {}", MIRROR_FILE, e))?;
+    crate::prefs::write_atomic(&path, &json)
+}
+
+fn notification(reminder: &BackendReminder, at: DateTime<Utc>) -> ScheduledNotification {
+    let title = reminder
+        .entry
+        .as_ref()
+        .map(|entry| entry.title.trim())
+        .filter(|title| !title.is_empty())
+        .unwrap_or(tr(Text::Reminder))
+        .to_string();
+    ScheduledNotification {
+        id: format!("{}{}", ID_PREFIX, reminder.id),
+        at,
+        payload: NotificationPayload {
+            title,
+            body: at.with_timezone(&Local).format("%c").to_string(),
+            actions: Vec::new(),
+            data: Some(json!({ "reminderId": reminder.id, "entryId": reminder.entry_id })),
+        },
+    }
+}
+
+/// Bring the scheduled reminders in line with the backend's. Returns how
+/// many reminders are mirrored.
+fn sync(app: &AppHandle) -> Result<usize, String> {
+    let config = app.state::<DesktopConfig>().inner().clone();
+    let response = backend_client::call(&config, "GET", "/api/reminders", None).map_err(|e| e.message)?;
+    let reminders: Vec<BackendReminder> =
+        serde_json::from_value(response.body).map_err(|e| format!("Unexpected reminders response: {}", e))?;
+
+    let _guard = LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let scheduler = app.state::<NotificationScheduler>();
+    let mut mirror = load_mirror(app);
+    let mut current = BTreeMap::new();
+    for reminder in &reminders {
+        let Some(at) = parse_time(&reminder.reminder_datetime) else {
+            warn!("Skipping reminder {} with unreadable time {:?}", reminder.id, reminder.reminder_datetime);
+            continue;
+        };
+        current.insert(reminder.id, at);
+        if mirror.get(&reminder.id) == Some(&at) {
+            continue;
+        }
+        // New or rescheduled; a past time fires right away
+        scheduler.add(notification(reminder, at))?;
+        debug!("Mirrored reminder {} for {}", reminder.id, at.to_rfc3339());
+    }
+    // Dismissed or deleted in the meantime
+    for id in mirror.keys().filter(|id| !current.contains_key(id)) {
+        scheduler.cancel(&format!("{}{}", ID_PREFIX, id))?;
+    }
+    mirror = current;
+    save_mirror(app, &mirror)?;
+    Ok(mirror.len())
+}
+
+async fn sync_in_background(app: &AppHandle) -> Result<usize, String> {
+    let app = app.clone();
+    async_runtime::spawn_blocking(move || sync(&app))
+        .await
+        .map_err(|e| format!("Reminder sync task failed: {}", e))?
+}
+
+/// Refresh the mirror whenever the backend is up, every `SYNC_INTERVAL`.
+/// Needs the notification scheduler.
+pub fn init_reminders(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            let health_url = app.state::<DesktopConfig>().health_url.clone();
+            if crate::backend_is_ready(&health_url).await {
+                match sync_in_background(&app).await {
+                    Ok(count) => debug!("{} reminder(s) mirrored", count),
+                    Err(e) => warn!("Failed to mirror reminders: {}", e),
+                }
+            }
+            sleep(SYNC_INTERVAL).await;
+        }
+    });
+}
+
+/// Mirror the backend's reminders now, e.g. right after one was changed.
+/// Returns how many are mirrored.
+#[tauri::command]
+pub async fn sync_reminders(app: AppHandle) -> Result<usize, String> {
+    let count = sync_in_background(&app).await?;
+    info!("{} reminder(s) mirrored", count);
+    Ok(count)
+}
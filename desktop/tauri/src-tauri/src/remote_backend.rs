@@ -0,0 +1,91 @@
+//! Using a backend that runs elsewhere (`backend.remoteUrl`) instead of the
+//! bundled sidecar. The remote backend is started with `TAURI_BACKEND_TOKEN`
+//! set, and the same token is stored here so the shell's requests are
+//! accepted. Desktops keep it in the keychain; Android has no keychain the
+//! shell can use, so it goes in the app's private config dir instead.
+
+#[cfg(mobile)]
+use std::fs;
+
+use log::info;
+#[cfg(desktop)]
+use log::warn;
+use tauri::AppHandle;
+#[cfg(mobile)]
+use tauri::Manager;
+
+#[cfg(desktop)]
+const KEYCHAIN_SERVICE: &str = "com.trackthething.desktop";
+#[cfg(desktop)]
+const KEYCHAIN_ACCOUNT: &str = "remote-backend";
+
+#[cfg(mobile)]
+const TOKEN_FILE: &str = "remote_backend_token";
+
+#[cfg(desktop)]
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+/// The stored token, if any
+#[cfg(desktop)]
+pub(crate) fn load_token(_app: &AppHandle) -> Option<String> {
+    match keychain_entry().and_then(|entry| entry.get_password().map_err(|e| e.to_string())) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            warn!("No remote backend token: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(desktop)]
+fn store_token(_app: &AppHandle, token: Option<&str>) -> Result<(), String> {
+    let entry = keychain_entry()?;
+    match token {
+        Some(token) => entry
+            .set_password(token)
+            .map_err(|e| format!("Failed to save the remote backend token to the keychain: {}", e)),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove the remote backend token from the keychain: {}", e)),
+        },
+    }
+}
+
+#[cfg(mobile)]
+fn token_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(TOKEN_FILE))
+        .map_err(|e| format!("Failed to resolve app config directory: {}", e))
+}
+
+#[cfg(mobile)]
+pub(crate) fn load_token(app: &AppHandle) -> Option<String> {
+    let token = fs::read_to_string(token_path(app).ok()?).ok()?;
+    Some(token.trim().to_string()).filter(|token| !token.is_empty())
+}
+
+#[cfg(mobile)]
+fn store_token(app: &AppHandle, token: Option<&str>) -> Result<(), String> {
+    let path = token_path(app)?;
+    match token {
+        Some(token) => crate::prefs::write_atomic(&path, token),
+        None => match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+        },
+    }
+}
+
+/// Store the token the remote backend was started with, or forget it with
+/// `null`. Like `backend.remoteUrl`, it's used from the next launch on.
+#[tauri::command]
+pub async fn set_remote_backend_token(app: AppHandle, token: Option<String>) -> Result<(), String> {
+    let token = token.map(|token| token.trim().to_string()).filter(|token| !token.is_empty());
+    store_token(&app, token.as_deref())?;
+    info!("Remote backend token {}", if token.is_some() { "saved" } else { "cleared" });
+    Ok(())
+}
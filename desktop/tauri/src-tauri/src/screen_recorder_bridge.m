@@ -0,0 +1,265 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+#import <AVFoundation/AVFoundation.h>
+#import <CoreMedia/CoreMedia.h>
+#import <ScreenCaptureKit/ScreenCaptureKit.h>
+#import <unistd.h>
+
+// Screen recording with ScreenCaptureKit (macOS 13+). Frames are encoded by
+// the hardware H.264 encoder through AVAssetWriter, so recording costs far
+// less CPU than ffmpeg's avfoundation grabber. One recording at a time.
+
+static const int64_t FRAME_RATE = 30;
+
+// How long start and stop wait for ScreenCaptureKit and the writer
+static const int64_t TIMEOUT_SECS = 10;
+
+static void setError(char **error, NSString *message) {
+    if (error != NULL) {
+        *error = strdup(message.UTF8String);
+    }
+}
+
+static BOOL waitFor(dispatch_semaphore_t semaphore) {
+    return dispatch_semaphore_wait(semaphore, dispatch_time(DISPATCH_TIME_NOW, TIMEOUT_SECS * NSEC_PER_SEC)) == 0;
+}
+
+API_AVAILABLE(macos(13.0))
+@interface TTTScreenRecorder : NSObject <SCStreamOutput, SCStreamDelegate>
+@property (nonatomic, strong) SCStream *stream;
+@property (nonatomic, strong) AVAssetWriter *writer;
+@property (nonatomic, strong) AVAssetWriterInput *input;
+@property (nonatomic, strong) dispatch_queue_t queue;
+@property (atomic) BOOL sessionStarted;
+@end
+
+@implementation TTTScreenRecorder
+
+- (void)stream:(SCStream *)stream didOutputSampleBuffer:(CMSampleBufferRef)sampleBuffer ofType:(SCStreamOutputType)type {
+    if (type != SCStreamOutputTypeScreen || !CMSampleBufferIsValid(sampleBuffer)) {
+        return;
+    }
+    // Idle and blank frames carry no image
+    CFArrayRef attachments = CMSampleBufferGetSampleAttachmentsArray(sampleBuffer, false);
+    if (attachments == NULL || CFArrayGetCount(attachments) == 0) {
+        return;
+    }
+    NSDictionary *info = (__bridge NSDictionary *)CFArrayGetValueAtIndex(attachments, 0);
+    NSNumber *status = info[SCStreamFrameInfoStatus];
+    if (status == nil || status.integerValue != SCFrameStatusComplete) {
+        return;
+    }
+    if (!self.sessionStarted) {
+        [self.writer startWriting];
+        [self.writer startSessionAtSourceTime:CMSampleBufferGetPresentationTimeStamp(sampleBuffer)];
+        self.sessionStarted = YES;
+    }
+    if (self.input.readyForMoreMediaData) {
+        [self.input appendSampleBuffer:sampleBuffer];
+    }
+}
+
+- (void)stream:(SCStream *)stream didStopWithError:(NSError *)error {
+    NSLog(@"Screen recording stopped by the system: %@", error.localizedDescription);
+}
+
+@end
+
+static id currentRecorder = nil;
+
+bool screen_recorder_available(void) {
+    if (@available(macOS 13.0, *)) {
+        return true;
+    }
+    return false;
+}
+
+// Record `window_id`, or the display `display_id` (0: the main display)
+// without this app's windows, into the .mp4 at `path`. On failure `error` is
+// set; release it with screen_recorder_free_string.
+bool screen_recorder_start(uint32_t display_id, uint32_t window_id, const char *path, char **error) {
+    if (@available(macOS 13.0, *)) {
+        @autoreleasepool {
+            if (currentRecorder != nil) {
+                setError(error, @"The screen is already being recorded");
+                return false;
+            }
+
+            // Asking for shareable content is what triggers the Screen Recording prompt
+            __block SCShareableContent *content = nil;
+            __block NSError *contentError = nil;
+            dispatch_semaphore_t loaded = dispatch_semaphore_create(0);
+            [SCShareableContent getShareableContentExcludingDesktopWindows:YES
+                                                       onScreenWindowsOnly:YES
+                                                         completionHandler:^(SCShareableContent *result, NSError *err) {
+                content = result;
+                contentError = err;
+                dispatch_semaphore_signal(loaded);
+            }];
+            if (!waitFor(loaded) || content == nil) {
+                setError(error, contentError.localizedDescription ?: @"Screen Recording permission is needed to record the screen");
+                return false;
+            }
+
+            SCContentFilter *filter = nil;
+            CGSize size = CGSizeZero;
+            CGFloat scale = NSScreen.mainScreen.backingScaleFactor ?: 1.0;
+            if (window_id != 0) {
+                for (SCWindow *window in content.windows) {
+                    if (window.windowID == window_id) {
+                        filter = [[SCContentFilter alloc] initWithDesktopIndependentWindow:window];
+                        size = window.frame.size;
+                        break;
+                    }
+                }
+                if (filter == nil) {
+                    setError(error, @"The window is no longer on screen");
+                    return false;
+                }
+            } else {
+                CGDirectDisplayID target = display_id != 0 ? display_id : CGMainDisplayID();
+                SCDisplay *display = nil;
+                for (SCDisplay *candidate in content.displays) {
+                    if (candidate.displayID == target) {
+                        display = candidate;
+                        break;
+                    }
+                }
+                if (display == nil) {
+                    setError(error, @"The display is not connected");
+                    return false;
+                }
+                NSMutableArray<SCRunningApplication *> *excluded = [NSMutableArray array];
+                for (SCRunningApplication *app in content.applications) {
+                    if (app.processID == getpid()) {
+                        [excluded addObject:app];
+                    }
+                }
+                filter = [[SCContentFilter alloc] initWithDisplay:display
+                                            excludingApplications:excluded
+                                                 exceptingWindows:@[]];
+                size = CGSizeMake(display.width, display.height);
+            }
+
+            // H.264 needs even dimensions
+            size_t width = ((size_t)(size.width * scale)) & ~(size_t)1;
+            size_t height = ((size_t)(size.height * scale)) & ~(size_t)1;
+            if (width == 0 || height == 0) {
+                setError(error, @"Nothing to record in the selected area");
+                return false;
+            }
+
+            SCStreamConfiguration *config = [[SCStreamConfiguration alloc] init];
+            config.width = width;
+            config.height = height;
+            config.minimumFrameInterval = CMTimeMake(1, (int32_t)FRAME_RATE);
+            config.pixelFormat = kCVPixelFormatType_32BGRA;
+            config.showsCursor = YES;
+            config.queueDepth = 6;
+
+            NSURL *url = [NSURL fileURLWithPath:[NSString stringWithUTF8String:path]];
+            NSError *writerError = nil;
+            AVAssetWriter *writer = [AVAssetWriter assetWriterWithURL:url fileType:AVFileTypeMPEG4 error:&writerError];
+            if (writer == nil) {
+                setError(error, writerError.localizedDescription ?: @"Failed to create the recording file");
+                return false;
+            }
+            NSDictionary *settings = @{
+                AVVideoCodecKey: AVVideoCodecTypeH264,
+                AVVideoWidthKey: @(width),
+                AVVideoHeightKey: @(height),
+                AVVideoCompressionPropertiesKey: @{
+                    AVVideoExpectedSourceFrameRateKey: @(FRAME_RATE),
+                    AVVideoMaxKeyFrameIntervalKey: @(FRAME_RATE * 2),
+                },
+            };
+            AVAssetWriterInput *input = [AVAssetWriterInput assetWriterInputWithMediaType:AVMediaTypeVideo
+                                                                           outputSettings:settings];
+            input.expectsMediaDataInRealTime = YES;
+            if (![writer canAddInput:input]) {
+                setError(error, @"The recording format is not supported");
+                return false;
+            }
+            [writer addInput:input];
+
+            TTTScreenRecorder *recorder = [[TTTScreenRecorder alloc] init];
+            recorder.writer = writer;
+            recorder.input = input;
+            recorder.queue = dispatch_queue_create("com.trackthething.screen-recorder", DISPATCH_QUEUE_SERIAL);
+            recorder.stream = [[SCStream alloc] initWithFilter:filter configuration:config delegate:recorder];
+
+            NSError *outputError = nil;
+            if (![recorder.stream addStreamOutput:recorder
+                                             type:SCStreamOutputTypeScreen
+                               sampleHandlerQueue:recorder.queue
+                                            error:&outputError]) {
+                setError(error, outputError.localizedDescription ?: @"Failed to receive screen frames");
+                return false;
+            }
+
+            __block NSError *startError = nil;
+            dispatch_semaphore_t started = dispatch_semaphore_create(0);
+            [recorder.stream startCaptureWithCompletionHandler:^(NSError *err) {
+                startError = err;
+                dispatch_semaphore_signal(started);
+            }];
+            if (!waitFor(started) || startError != nil) {
+                setError(error, startError.localizedDescription ?: @"Screen recording did not start");
+                return false;
+            }
+
+            currentRecorder = recorder;
+            return true;
+        }
+    }
+    setError(error, @"Screen recording needs macOS 13 or later");
+    return false;
+}
+
+// Stop the recording and finish its file
+bool screen_recorder_stop(char **error) {
+    if (@available(macOS 13.0, *)) {
+        @autoreleasepool {
+            TTTScreenRecorder *recorder = currentRecorder;
+            currentRecorder = nil;
+            if (recorder == nil) {
+                setError(error, @"The screen is not being recorded");
+                return false;
+            }
+
+            dispatch_semaphore_t stopped = dispatch_semaphore_create(0);
+            [recorder.stream stopCaptureWithCompletionHandler:^(NSError *err) {
+                if (err != nil) {
+                    NSLog(@"Stopping screen capture failed: %@", err.localizedDescription);
+                }
+                dispatch_semaphore_signal(stopped);
+            }];
+            waitFor(stopped);
+
+            // Let frames already queued reach the writer
+            dispatch_sync(recorder.queue, ^{});
+            if (!recorder.sessionStarted) {
+                [recorder.writer cancelWriting];
+                setError(error, @"No frames were captured");
+                return false;
+            }
+
+            [recorder.input markAsFinished];
+            dispatch_semaphore_t finished = dispatch_semaphore_create(0);
+            [recorder.writer finishWritingWithCompletionHandler:^{
+                dispatch_semaphore_signal(finished);
+            }];
+            if (!waitFor(finished) || recorder.writer.status != AVAssetWriterStatusCompleted) {
+                setError(error, recorder.writer.error.localizedDescription ?: @"Failed to finish the recording");
+                return false;
+            }
+            return true;
+        }
+    }
+    setError(error, @"Screen recording needs macOS 13 or later");
+    return false;
+}
+
+void screen_recorder_free_string(char *value) {
+    free(value);
+}
@@ -0,0 +1,68 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+
+// Callback type for content received from the "Add to Track The Thing" service.
+// `text` may be NULL; `paths` holds `pathCount` UTF-8 file paths.
+typedef void (*ShareCallback)(const char *text, const char *const *paths, size_t pathCount);
+
+static ShareCallback shareCallback = NULL;
+
+@interface TTTServiceProvider : NSObject
+- (void)addToTrackTheThing:(NSPasteboard *)pboard userData:(NSString *)userData error:(NSString **)error;
+@end
+
+static TTTServiceProvider *serviceProvider = nil;
+
+@implementation TTTServiceProvider
+
+// Selector name must match NSMessage in Info.plist's NSServices entry
+- (void)addToTrackTheThing:(NSPasteboard *)pboard userData:(NSString *)userData error:(NSString **)error {
+    if (shareCallback == NULL) {
+        NSLog(@"[ServicesBridge] Service invoked before a callback was registered");
+        if (error != NULL) {
+            *error = @"Track the Thing is not ready yet";
+        }
+        return;
+    }
+    
+    NSArray<NSURL *> *urls = [pboard readObjectsForClasses:@[[NSURL class]]
+                                                   options:@{NSPasteboardURLReadingFileURLsOnlyKey: @YES}];
+    NSString *text = nil;
+    if (urls.count == 0) {
+        text = [pboard stringForType:NSPasteboardTypeString];
+    }
+    
+    if (urls.count == 0 && text.length == 0) {
+        NSLog(@"[ServicesBridge] Service invoked without text or files");
+        if (error != NULL) {
+            *error = @"Nothing to add";
+        }
+        return;
+    }
+    
+    NSUInteger count = urls.count;
+    const char **paths = count > 0 ? calloc(count, sizeof(char *)) : NULL;
+    for (NSUInteger i = 0; i < count; i++) {
+        paths[i] = [urls[i].path UTF8String];
+    }
+    
+    NSLog(@"[ServicesBridge] Received %lu file(s), text: %@", (unsigned long)count, text.length > 0 ? @"yes" : @"no");
+    shareCallback(text.length > 0 ? [text UTF8String] : NULL, paths, (size_t)count);
+    
+    free(paths);
+}
+
+@end
+
+// Register the app as the provider for its NSServices entries
+void services_register(ShareCallback callback) {
+    shareCallback = callback;
+    dispatch_async(dispatch_get_main_queue(), ^{
+        if (serviceProvider == nil) {
+            serviceProvider = [[TTTServiceProvider alloc] init];
+        }
+        [NSApp setServicesProvider:serviceProvider];
+        NSUpdateDynamicServices();
+        NSLog(@"[ServicesBridge] Services provider registered");
+    });
+}
@@ -0,0 +1,1220 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{async_runtime, AppHandle, Emitter, LogicalSize, Manager, State};
+use tokio::time::sleep;
+use tracing_subscriber::EnvFilter;
+
+use crate::backup_remote::RemoteBackupTarget;
+use crate::idle::{IdleMonitor, MIN_THRESHOLD_SECS};
+use crate::touri_env::TouriEnvIssue;
+use crate::updater::UpdateChannel;
+use crate::watch_folders::WatchFolder;
+use crate::{install_config, lan_sync, locale, logs, prefs, telemetry, tray, user_isolation};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+// How often the settings file (and .tourienv in dev) is checked for edits
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Schema of `settings.json`; older files are migrated on load
+pub const SETTINGS_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Index `n` upgrades a schema `n` settings document to `n + 1`
+const MIGRATIONS: &[prefs::Migration] = &[
+    // 0 -> 1: first persisted layout
+    Ok,
+    // 1 -> 2: `version` renamed to the `schemaVersion` shared by all preference files
+    |mut value| {
+        if let Value::Object(map) = &mut value {
+            map.remove("version");
+        }
+        Ok(value)
+    },
+    // 2 -> 3: speech follows the app locale unless set; drop the old hard-coded default
+    |mut value| {
+        if let Some(speech) = value.get_mut("speech").and_then(Value::as_object_mut) {
+            if speech.get("locale").and_then(Value::as_str) == Some("en-US") {
+                speech.remove("locale");
+            }
+        }
+        Ok(value)
+    },
+];
+
+/// Every setting, with the environment variable that overrides it in debug builds
+const KNOBS: &[(&str, Option<&str>)] = &[
+    ("general.locale", None),
+    ("general.trayStatus", None),
+    ("backend.host", Some("TAURI_BACKEND_HOST")),
+    ("backend.port", Some("TAURI_BACKEND_PORT")),
+    ("backend.launcherCommand", Some("PYINSTALLER_ENTRYPOINT")),
+    ("backend.healthIntervalSecs", Some("TAURI_HEALTH_INTERVAL_SECS")),
+    ("backend.remoteUrl", Some("TAURI_REMOTE_BACKEND_URL")),
+    ("window.heightRatio", Some("TAURI_WINDOW_HEIGHT_RATIO")),
+    ("window.width", Some("TAURI_WINDOW_WIDTH")),
+    ("window.maximized", Some("TAURI_WINDOW_MAXIMIZED")),
+    ("window.minWidth", Some("TAURI_WINDOW_MIN_WIDTH")),
+    ("window.minHeight", Some("TAURI_WINDOW_MIN_HEIGHT")),
+    ("window.splashMinMs", Some("TAURI_SPLASH_MIN_VISIBLE_MS")),
+    ("media.dataDir", Some("TAURI_DESKTOP_DATA_DIR")),
+    ("media.segmentMinutes", None),
+    ("media.watchFolders", None),
+    ("media.uploadLimitKbps", None),
+    ("media.cleanAudio", None),
+    ("media.fileNameTemplate", None),
+    ("speech.locale", None),
+    ("speech.transcribeRecordings", None),
+    ("speech.whisperModel", None),
+    ("privacy.idleThresholdSecs", Some("TAURI_IDLE_THRESHOLD_SECS")),
+    ("privacy.networkProbes", Some("TAURI_NETWORK_PROBES")),
+    ("privacy.telemetry", None),
+    ("privacy.guard.enabled", None),
+    ("privacy.guard.screenSharing", None),
+    ("privacy.guard.apps", None),
+    ("logging.level", Some("TAURI_LOG_LEVEL")),
+    ("logging.filter", Some("TAURI_LOG_FILTER")),
+    ("updates.channel", None),
+    ("backup.enabled", None),
+    ("backup.intervalHours", None),
+    ("backup.keep", None),
+    ("backup.destination", None),
+    ("backup.remote.target", None),
+    ("backup.remote.url", None),
+    ("backup.remote.bucket", None),
+    ("backup.remote.region", None),
+    ("backup.remote.prefix", None),
+    ("backup.remote.includeMedia", None),
+    ("sync.enabled", None),
+    ("sync.deviceName", None),
+    ("sync.port", None),
+    ("sync.intervalMinutes", None),
+    ("focus.workMinutes", None),
+    ("focus.shortBreakMinutes", None),
+    ("focus.longBreakMinutes", None),
+    ("focus.longBreakEvery", None),
+    ("focus.toggleDnd", None),
+    ("power.lowBatteryPercent", None),
+    ("hotkeys.toggleWindow", None),
+    ("hotkeys.quickCapture", None),
+    ("hotkeys.toggleRecording", None),
+    ("hotkeys.pushToTalk", None),
+    ("hotkeys.templateEntry", None),
+    ("hotkeys.templateId", None),
+    ("hotkeys.captureContext", None),
+];
+
+/// Paths handed to the backend, from `.tourienv` or derived from the data dir
+const BACKEND_PATHS: &[(&str, &str)] = &[
+    ("resolved.dataDir", "TAURI_DESKTOP_DATA_DIR"),
+    ("resolved.databasePath", "TAURI_DATABASE_PATH"),
+    ("resolved.uploadsDir", "TAURI_UPLOADS_DIR"),
+    ("resolved.staticDir", "TAURI_STATIC_DIR"),
+    ("resolved.backendLog", "TAURI_BACKEND_LOG"),
+];
+
+/// Desktop shell configuration persisted as `settings.json` in the config dir.
+/// Feature toggles with their own commands (autostart, app lock, activity
+/// tracking, search indexing) keep their own files.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub schema_version: u32,
+    pub general: GeneralSettings,
+    pub backend: BackendSettings,
+    pub window: WindowSettings,
+    pub media: MediaSettings,
+    pub speech: SpeechSettings,
+    pub privacy: PrivacySettings,
+    pub logging: LoggingSettings,
+    pub updates: UpdateSettings,
+    pub backup: BackupSettings,
+    pub sync: SyncSettings,
+    pub focus: FocusSettings,
+    pub power: PowerSettings,
+    pub hotkeys: HotkeySettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GeneralSettings {
+    /// BCP 47 app language; `None` follows the OS
+    pub locale: Option<String>,
+    /// Show the running timer, recording or focus session next to the tray icon
+    pub tray_status: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackendSettings {
+    /// Loopback host and port for the FastAPI sidecar. The default port avoids
+    /// the docker-compose ports so both stacks can run at once.
+    pub host: String,
+    pub port: u16,
+    /// Used when no packaged backend binary is bundled (development)
+    pub launcher_command: String,
+    /// Seconds between health checks once the backend is up
+    pub health_interval_secs: u64,
+    /// Backend running elsewhere (e.g. `http://192.168.1.20:18765`) to use
+    /// instead of starting one; the only option on Android
+    pub remote_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WindowSettings {
+    /// Share of the screen height used for the initial window size
+    pub height_ratio: f64,
+    /// Initial width in logical pixels; `None` sizes to 51% of the screen + 510px
+    pub width: Option<f64>,
+    pub maximized: bool,
+    pub splash_min_ms: u64,
+    /// Smallest size the main window can be resized to, in logical pixels
+    pub min_width: f64,
+    pub min_height: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MediaSettings {
+    /// Base directory for the desktop database, uploads and logs; `None` uses
+    /// the platform's application support directory
+    pub data_dir: Option<PathBuf>,
+    /// Split recordings into files of this many minutes; `None` records one file
+    pub segment_minutes: Option<u64>,
+    /// Folders whose new files are imported, e.g. a scanner's output
+    pub watch_folders: Vec<WatchFolder>,
+    /// Cap on `upload_media` in kilobits per second; `None` sends at full speed
+    pub upload_limit_kbps: Option<u64>,
+    /// Reduce noise and even out the loudness of finished recordings
+    pub clean_audio: bool,
+    /// Name of new captures without the extension, e.g. `{date}_{type}_{seq}`;
+    /// `None` keeps `{type}_{timestamp}`
+    pub file_name_template: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpeechSettings {
+    /// BCP 47 recognition and voice language, e.g. `en-US`; `None` uses the app locale
+    pub locale: Option<String>,
+    /// Transcribe finished audio, video and screen recordings in the background
+    pub transcribe_recordings: bool,
+    /// whisper.cpp model (`ggml-*.bin`) transcripts are made with
+    pub whisper_model: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrivacySettings {
+    /// Seconds without keyboard/mouse input before `user-idle` is emitted
+    pub idle_threshold_secs: u64,
+    /// `host:port` endpoints contacted to check connectivity
+    pub network_probes: Vec<String>,
+    /// Send anonymous usage statistics (opt-in)
+    pub telemetry: bool,
+    pub guard: PrivacyGuardSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrivacyGuardSettings {
+    /// Apply the rules below at all (opt-in)
+    pub enabled: bool,
+    /// Block captures while the screen is shared
+    pub screen_sharing: bool,
+    /// Block captures while one of these apps is frontmost, by name as
+    /// reported by activity tracking
+    pub apps: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LoggingSettings {
+    /// `error`, `warn`, `info`, `debug` or `trace`
+    pub level: String,
+    /// Per-module directives for traced code, in `RUST_LOG` syntax
+    /// (e.g. `info,app_lib::media=trace`). Falls back to `level`.
+    pub filter: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpdateSettings {
+    /// Release feed checked for new versions
+    pub channel: UpdateChannel,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupSettings {
+    /// Take scheduled backups
+    pub enabled: bool,
+    /// Hours between scheduled backups
+    pub interval_hours: u64,
+    /// Backups kept; older ones are deleted after each new backup
+    pub keep: usize,
+    /// Where archives are written; `None` uses `backups/` in the data dir
+    pub destination: Option<PathBuf>,
+    /// Off-site copies of each backup
+    pub remote: RemoteBackupSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemoteBackupSettings {
+    /// `webdav` or `s3`; `None` keeps backups local. Credentials are in the keychain.
+    pub target: Option<RemoteBackupTarget>,
+    /// WebDAV collection URL, or the S3 endpoint (e.g. `https://s3.eu-central-1.amazonaws.com`)
+    pub url: Option<String>,
+    /// S3 bucket
+    pub bucket: Option<String>,
+    /// S3 signing region; `None` uses `us-east-1`
+    pub region: Option<String>,
+    /// Prepended to remote file names, e.g. `laptop/`
+    pub prefix: Option<String>,
+    /// Also upload an archive of `uploads/` and `static/` with each backup
+    pub include_media: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncSettings {
+    /// Advertise this install on the local network and sync with paired peers
+    pub enabled: bool,
+    /// Name other installs see; `None` uses the computer's name
+    pub device_name: Option<String>,
+    /// Port peers connect to; 0 picks a free one
+    pub port: u16,
+    /// Minutes between automatic syncs with each paired peer
+    pub interval_minutes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FocusSettings {
+    /// Length of a focus phase
+    pub work_minutes: u64,
+    pub short_break_minutes: u64,
+    pub long_break_minutes: u64,
+    /// Focus phases before a long break instead of a short one
+    pub long_break_every: u32,
+    /// Turn on Do Not Disturb during focus phases
+    pub toggle_dnd: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PowerSettings {
+    /// Turn the battery saver on at or below this charge while on battery;
+    /// 0 leaves it to `set_power_profile`
+    pub low_battery_percent: u8,
+}
+
+/// Global shortcuts as accelerators, e.g. `CommandOrControl+Shift+Space`;
+/// `None` leaves the action unbound
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotkeySettings {
+    pub toggle_window: Option<String>,
+    pub quick_capture: Option<String>,
+    pub toggle_recording: Option<String>,
+    /// Held down while dictating
+    pub push_to_talk: Option<String>,
+    pub template_entry: Option<String>,
+    /// Entry template the `template_entry` shortcut adds
+    pub template_id: Option<String>,
+    /// Attach the frontmost app, window title and browser URL to
+    /// `quick-capture` (opt-in; also needs the `activity` permission)
+    pub capture_context: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            schema_version: SETTINGS_VERSION,
+            general: GeneralSettings::default(),
+            backend: BackendSettings::default(),
+            window: WindowSettings::default(),
+            media: MediaSettings::default(),
+            speech: SpeechSettings::default(),
+            privacy: PrivacySettings::default(),
+            logging: LoggingSettings::default(),
+            updates: UpdateSettings::default(),
+            backup: BackupSettings::default(),
+            sync: SyncSettings::default(),
+            focus: FocusSettings::default(),
+            power: PowerSettings::default(),
+            hotkeys: HotkeySettings::default(),
+        }
+    }
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            locale: None,
+            tray_status: true,
+        }
+    }
+}
+
+impl Default for BackendSettings {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".into(),
+            port: 18765,
+            launcher_command: "python3 backend/desktop_launcher.py".into(),
+            health_interval_secs: 30,
+            remote_url: None,
+        }
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            height_ratio: 0.95,
+            width: None,
+            maximized: false,
+            splash_min_ms: 1200,
+            min_width: 480.0,
+            min_height: 600.0,
+        }
+    }
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            idle_threshold_secs: 300,
+            network_probes: vec!["1.1.1.1:443".into(), "8.8.8.8:53".into()],
+            telemetry: false,
+            guard: PrivacyGuardSettings::default(),
+        }
+    }
+}
+
+impl Default for PrivacyGuardSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            screen_sharing: true,
+            apps: Vec::new(),
+        }
+    }
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep: 7,
+            destination: None,
+            remote: RemoteBackupSettings::default(),
+        }
+    }
+}
+
+impl RemoteBackupSettings {
+    fn validate(&self) -> Result<(), String> {
+        let Some(target) = self.target else {
+            return Ok(());
+        };
+        let url = self.url.as_deref().unwrap_or_default();
+        let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+            return Err("Remote backup URL must start with https:// or http://".to_string());
+        };
+        if rest.split('/').next().unwrap_or_default().is_empty() {
+            return Err("Remote backup URL has no host".to_string());
+        }
+        if target == RemoteBackupTarget::S3 {
+            if rest.trim_end_matches('/').contains('/') {
+                return Err("S3 endpoint must not have a path; set the bucket separately".to_string());
+            }
+            let bucket = self.bucket.as_deref().unwrap_or_default();
+            let valid = (3..=63).contains(&bucket.len())
+                && bucket.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '-'));
+            if !valid {
+                return Err(format!("Invalid S3 bucket name: {:?}", bucket));
+            }
+        }
+        if let Some(prefix) = &self.prefix {
+            let valid = !prefix.starts_with('/')
+                && prefix.split('/').all(|segment| segment != "." && segment != "..")
+                && prefix.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/'));
+            if !valid {
+                return Err(format!("Invalid remote backup prefix: {:?}", prefix));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device_name: None,
+            port: 0,
+            interval_minutes: 15,
+        }
+    }
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+            toggle_dnd: false,
+        }
+    }
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self { low_battery_percent: 20 }
+    }
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+            filter: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn backend_url(&self) -> String {
+        match &self.backend.remote_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("http://{}:{}", self.backend.host, self.backend.port),
+        }
+    }
+
+    pub fn app_locale(&self) -> String {
+        self.general.locale.clone().unwrap_or_else(locale::system_locale)
+    }
+
+    pub fn speech_locale(&self) -> String {
+        self.speech.locale.clone().unwrap_or_else(|| self.app_locale())
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.logging.level.parse().unwrap_or(LevelFilter::Info)
+    }
+
+    /// Directives for the tracing filter
+    pub fn trace_filter(&self) -> String {
+        self.logging.filter.clone().unwrap_or_else(|| self.logging.level.clone())
+    }
+
+    /// The configured data directory, or the platform default
+    pub fn data_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.media.data_dir {
+            return dir.clone();
+        }
+
+        #[cfg(target_os = "macos")]
+        let data_dir = dirs::home_dir()
+            .map(|h| h.join("Library/Application Support/TrackTheThingDesktop"))
+            .expect("FATAL: Cannot resolve home directory. Unable to determine data directory location.");
+
+        #[cfg(target_os = "linux")]
+        let data_dir = dirs::home_dir()
+            .map(|h| h.join(".local/share/track-the-thing-desktop"))
+            .expect("FATAL: Cannot resolve home directory. Unable to determine data directory location.");
+
+        #[cfg(target_os = "windows")]
+        let data_dir = dirs::data_local_dir()
+            .map(|d| d.join("TrackTheThingDesktop"))
+            .expect("FATAL: Cannot resolve local app data directory. Unable to determine data directory location.");
+
+        data_dir
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.schema_version != SETTINGS_VERSION {
+            return Err(format!("Unsupported settings schema {}", self.schema_version));
+        }
+        if self.backend.host.trim().is_empty() {
+            return Err("Backend host must not be empty".to_string());
+        }
+        if self.backend.port == 0 {
+            return Err("Backend port must not be 0".to_string());
+        }
+        if shell_words::split(&self.backend.launcher_command).map_or(true, |args| args.is_empty()) {
+            return Err("Backend launcher command is invalid".to_string());
+        }
+        if !(0.5..=0.98).contains(&self.window.height_ratio) {
+            return Err("Window height ratio must be between 0.5 and 0.98".to_string());
+        }
+        if self.window.width.is_some_and(|width| width <= 320.0) {
+            return Err("Window width must be greater than 320".to_string());
+        }
+        if let Some(url) = &self.backend.remote_url {
+            // Health checks and the event socket speak plain HTTP
+            let host = url.strip_prefix("http://").and_then(|rest| rest.split('/').next()).unwrap_or_default();
+            if host.is_empty() {
+                return Err("Remote backend URL must be http://<host>[:port]".to_string());
+            }
+        }
+        if !(5..=3600).contains(&self.backend.health_interval_secs) {
+            return Err("Health check interval must be between 5 and 3600 seconds".to_string());
+        }
+        if !(320.0..=4096.0).contains(&self.window.min_width) || !(320.0..=4096.0).contains(&self.window.min_height) {
+            return Err("Minimum window size must be between 320 and 4096".to_string());
+        }
+        if self.window.splash_min_ms > 10_000 {
+            return Err("Splash duration must be at most 10000 ms".to_string());
+        }
+        if self.media.data_dir.as_deref().is_some_and(|dir| !dir.is_absolute()) {
+            return Err("Data directory must be an absolute path".to_string());
+        }
+        if self.media.segment_minutes.is_some_and(|minutes| !(1..=240).contains(&minutes)) {
+            return Err("Recording segments must be between 1 and 240 minutes".to_string());
+        }
+        crate::watch_folders::validate(&self.media.watch_folders)?;
+        if self.media.upload_limit_kbps.is_some_and(|kbps| kbps < 64) {
+            return Err("Upload limit must be at least 64 kbit/s".to_string());
+        }
+        if let Some(template) = &self.media.file_name_template {
+            crate::capture_names::validate(template)?;
+        }
+        if self.speech.whisper_model.as_deref().is_some_and(|model| !model.is_absolute()) {
+            return Err("The whisper.cpp model must be an absolute path".to_string());
+        }
+        for tag in [&self.general.locale, &self.speech.locale].into_iter().flatten() {
+            locale::normalize(tag)?;
+        }
+        if self.privacy.idle_threshold_secs < MIN_THRESHOLD_SECS {
+            return Err(format!("Idle threshold must be at least {} seconds", MIN_THRESHOLD_SECS));
+        }
+        for probe in &self.privacy.network_probes {
+            let valid = probe
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port > 0));
+            if !valid {
+                return Err(format!("Network probe must be host:port, got {:?}", probe));
+            }
+        }
+        if self.logging.level.parse::<LevelFilter>().is_err() {
+            return Err(format!("Invalid log level: {:?}", self.logging.level));
+        }
+        if let Some(filter) = &self.logging.filter {
+            EnvFilter::try_new(filter).map_err(|e| format!("Invalid log filter {:?}: {}", filter, e))?;
+        }
+        if !(1..=720).contains(&self.backup.interval_hours) {
+            return Err("Backup interval must be between 1 and 720 hours".to_string());
+        }
+        if !(1..=365).contains(&self.backup.keep) {
+            return Err("Backups kept must be between 1 and 365".to_string());
+        }
+        if self.backup.destination.as_deref().is_some_and(|dir| !dir.is_absolute()) {
+            return Err("Backup destination must be an absolute path".to_string());
+        }
+        self.backup.remote.validate()?;
+        if let Some(name) = &self.sync.device_name {
+            if name.trim().is_empty() || name.len() > 63 {
+                return Err("Sync device name must be 1 to 63 characters".to_string());
+            }
+        }
+        if !(1..=1440).contains(&self.sync.interval_minutes) {
+            return Err("Sync interval must be between 1 and 1440 minutes".to_string());
+        }
+        if !(1..=240).contains(&self.focus.work_minutes) {
+            return Err("Focus phases must be between 1 and 240 minutes".to_string());
+        }
+        if !(1..=120).contains(&self.focus.short_break_minutes) || !(1..=120).contains(&self.focus.long_break_minutes) {
+            return Err("Breaks must be between 1 and 120 minutes".to_string());
+        }
+        if !(1..=12).contains(&self.focus.long_break_every) {
+            return Err("Long breaks must come every 1 to 12 focus phases".to_string());
+        }
+        if self.power.low_battery_percent > 95 {
+            return Err("Battery saver threshold must be at most 95%".to_string());
+        }
+        #[cfg(desktop)]
+        crate::hotkeys::validate(&self.hotkeys)?;
+        Ok(())
+    }
+
+    /// Overlay the `TAURI_*` variables from `.tourienv` (development builds).
+    /// Unparseable values are ignored, like before settings were persisted.
+    /// Returns the keys (as in `KNOBS`) that were overridden.
+    fn apply_env_overrides(&mut self) -> Vec<&'static str> {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.trim().parse().ok())
+        }
+
+        let mut applied = Vec::new();
+        if let Ok(host) = env::var("TAURI_BACKEND_HOST") {
+            self.backend.host = host;
+            applied.push("backend.host");
+        }
+        if let Some(port) = var("TAURI_BACKEND_PORT") {
+            self.backend.port = port;
+            applied.push("backend.port");
+        }
+        if let Ok(command) = env::var("PYINSTALLER_ENTRYPOINT") {
+            self.backend.launcher_command = command;
+            applied.push("backend.launcherCommand");
+        }
+        if let Ok(url) = env::var("TAURI_REMOTE_BACKEND_URL") {
+            self.backend.remote_url = Some(url.trim().to_string()).filter(|url| !url.is_empty());
+            applied.push("backend.remoteUrl");
+        }
+        if let Some(secs) = var::<u64>("TAURI_HEALTH_INTERVAL_SECS") {
+            self.backend.health_interval_secs = secs.clamp(5, 3600);
+            applied.push("backend.healthIntervalSecs");
+        }
+        if let Some(ratio) = var::<f64>("TAURI_WINDOW_HEIGHT_RATIO") {
+            self.window.height_ratio = ratio.clamp(0.5, 0.98);
+            applied.push("window.heightRatio");
+        }
+        if let Some(width) = var::<f64>("TAURI_WINDOW_WIDTH").filter(|width| *width > 320.0) {
+            self.window.width = Some(width);
+            applied.push("window.width");
+        }
+        if let Ok(value) = env::var("TAURI_WINDOW_MAXIMIZED") {
+            self.window.maximized = matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes");
+            applied.push("window.maximized");
+        }
+        if let Some(width) = var::<f64>("TAURI_WINDOW_MIN_WIDTH") {
+            self.window.min_width = width.clamp(320.0, 4096.0);
+            applied.push("window.minWidth");
+        }
+        if let Some(height) = var::<f64>("TAURI_WINDOW_MIN_HEIGHT") {
+            self.window.min_height = height.clamp(320.0, 4096.0);
+            applied.push("window.minHeight");
+        }
+        if let Some(ms) = var::<u64>("TAURI_SPLASH_MIN_VISIBLE_MS") {
+            self.window.splash_min_ms = ms.min(10_000);
+            applied.push("window.splashMinMs");
+        }
+        if let Some(dir) = env::var_os("TAURI_DESKTOP_DATA_DIR").map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+            self.media.data_dir = Some(dir);
+            applied.push("media.dataDir");
+        }
+        if let Some(secs) = var::<u64>("TAURI_IDLE_THRESHOLD_SECS") {
+            self.privacy.idle_threshold_secs = secs.max(MIN_THRESHOLD_SECS);
+            applied.push("privacy.idleThresholdSecs");
+        }
+        if let Ok(probes) = env::var("TAURI_NETWORK_PROBES") {
+            self.privacy.network_probes = probes
+                .split(',')
+                .map(|probe| probe.trim().to_string())
+                .filter(|probe| !probe.is_empty())
+                .collect();
+            applied.push("privacy.networkProbes");
+        }
+        if let Some(level) = var::<LevelFilter>("TAURI_LOG_LEVEL") {
+            self.logging.level = level.to_string().to_ascii_lowercase();
+            applied.push("logging.level");
+        }
+        if let Ok(filter) = env::var("TAURI_LOG_FILTER") {
+            if EnvFilter::try_new(filter.trim()).is_ok() {
+                self.logging.filter = Some(filter.trim().to_string());
+                applied.push("logging.filter");
+            }
+        }
+        applied
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigSource {
+    Default,
+    SettingsFile,
+    Environment,
+    /// Computed from other values (e.g. the backend URL from host and port)
+    Derived,
+    /// `config.toml`; see `install_config`
+    ConfigFile,
+    LaunchArgs,
+    /// Another user's instance holds the configured value; see `user_isolation`
+    PerUser,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveValue {
+    pub key: String,
+    pub value: Value,
+    pub source: ConfigSource,
+    pub env_var: Option<&'static str>,
+    /// Why a value that was provided (env var or settings file) isn't the one in use
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    pub build: &'static str,
+    pub settings_file: Option<String>,
+    pub settings_file_exists: bool,
+    pub touri_env: Option<String>,
+    /// Unknown keys, bad values and unparseable lines in `.tourienv`
+    pub touri_env_issues: Vec<TouriEnvIssue>,
+    pub values: Vec<EffectiveValue>,
+}
+
+fn load_file(path: &Path) -> Settings {
+    let value = match prefs::load_versioned(path, MIGRATIONS, Some("version")) {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            info!("No settings file at {:?}, using defaults", path);
+            return Settings::default();
+        }
+        Err(e) => {
+            warn!("Failed to load settings, using defaults: {}", e);
+            return Settings::default();
+        }
+    };
+
+    match serde_json::from_value::<Settings>(value) {
+        Ok(settings) => match settings.validate() {
+            Ok(()) => settings,
+            Err(e) => {
+                warn!("Invalid settings, using defaults: {}", e);
+                Settings::default()
+            }
+        },
+        Err(e) => {
+            warn!("Failed to parse settings, using defaults: {}", e);
+            Settings::default()
+        }
+    }
+}
+
+/// Back up a settings file that can't be used and write the defaults in its
+/// place. A missing or unreadable file, or one from a newer version, is left
+/// alone.
+fn repair_file(path: &Path) {
+    let reason = match prefs::corruption(path) {
+        Some(reason) => reason,
+        None => match prefs::load_versioned(path, MIGRATIONS, Some("version")) {
+            Ok(Some(value)) => match serde_json::from_value::<Settings>(value) {
+                Ok(settings) => match settings.validate() {
+                    Ok(()) => return,
+                    Err(e) => e,
+                },
+                Err(e) => e.to_string(),
+            },
+            _ => return,
+        },
+    };
+    if crate::startup_repairs::set_aside_corrupted(path, &reason) {
+        if let Err(e) = save_file(path, &Settings::default()) {
+            warn!("Failed to regenerate settings: {}", e);
+        }
+    }
+}
+
+fn save_file(path: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    prefs::write_atomic(path, &json)
+}
+
+/// Recursively apply a JSON merge patch: objects merge, anything else replaces
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) if value.is_object() => merge(existing, value),
+                    _ => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// The settings file with the environment (`.tourienv` in debug builds),
+/// `config.toml` and launch flags applied on top, and the keys each of them
+/// replaced. See `install_config` for the order. A data dir or port taken by
+/// another user is then swapped for a per-user one (`user_isolation`).
+fn resolve(path: Option<&Path>) -> (Settings, Vec<(&'static str, ConfigSource)>) {
+    let mut settings = path.map(load_file).unwrap_or_default();
+    let environment = if cfg!(debug_assertions) {
+        settings.apply_env_overrides()
+    } else {
+        install_config::environment().apply(&mut settings)
+    };
+    let config_file = path
+        .and_then(Path::parent)
+        .map(install_config::config_file)
+        .unwrap_or_default()
+        .apply(&mut settings);
+    let launch_args = install_config::launch_args().apply(&mut settings);
+    let per_user = user_isolation::apply(&mut settings);
+
+    let mut overridden: Vec<(&'static str, ConfigSource)> = Vec::new();
+    for (keys, source) in [
+        (environment, ConfigSource::Environment),
+        (config_file, ConfigSource::ConfigFile),
+        (launch_args, ConfigSource::LaunchArgs),
+        (per_user, ConfigSource::PerUser),
+    ] {
+        for key in keys {
+            overridden.retain(|(replaced, _)| *replaced != key);
+            overridden.push((key, source));
+        }
+    }
+    (settings, overridden)
+}
+
+pub struct SettingsState {
+    settings: Mutex<Settings>,
+    /// Held from reading `settings.json` until the edited file is written, so
+    /// concurrent updates don't drop each other's changes
+    writing: Mutex<()>,
+    /// Keys currently taken from the environment, `config.toml` or launch flags
+    overrides: Mutex<Vec<(&'static str, ConfigSource)>>,
+    path: Option<PathBuf>,
+    /// The `.tourienv` that was loaded, in debug builds
+    touri_env: Option<PathBuf>,
+}
+
+impl SettingsState {
+    pub fn current(&self) -> Settings {
+        self.settings.lock().map(|settings| settings.clone()).unwrap_or_default()
+    }
+
+    /// Where `key` comes from when something above `settings.json` sets it
+    pub fn override_source(&self, key: &str) -> Option<ConfigSource> {
+        let overrides = self.overrides.lock().ok()?;
+        overrides.iter().find(|(replaced, _)| *replaced == key).map(|&(_, source)| source)
+    }
+}
+
+/// Load settings from the config dir (with overrides from `touri_env`, the
+/// `.tourienv` loaded in debug builds) and manage them as app state
+pub fn init_settings(app: &AppHandle, touri_env: Option<PathBuf>) -> Settings {
+    let path = app
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(SETTINGS_FILE));
+
+    if let Some(path) = &path {
+        repair_file(path);
+    }
+    install_config::init();
+    let (settings, overrides) = resolve(path.as_deref());
+    info!(
+        "Settings: backend {}, data dir {}",
+        settings.backend_url(),
+        settings.data_dir().display()
+    );
+
+    app.manage(SettingsState {
+        settings: Mutex::new(settings.clone()),
+        writing: Mutex::new(()),
+        overrides: Mutex::new(overrides),
+        path,
+        touri_env,
+    });
+    settings
+}
+
+/// Apply the settings that don't need a restart: log level, idle threshold,
+/// telemetry opt-in, tray status, LAN sync, window constraints, global shortcuts and the app and speech locales. The
+/// backend health interval is read on every check.
+pub fn apply_live(app: &AppHandle, settings: &Settings) {
+    log::set_max_level(settings.log_level());
+    logs::set_trace_filter(&settings.trace_filter());
+
+    if let Some(monitor) = app.try_state::<IdleMonitor>() {
+        monitor.set_threshold_secs(settings.privacy.idle_threshold_secs);
+    }
+    telemetry::set_enabled(app, settings.privacy.telemetry);
+    tray::set_status_enabled(settings.general.tray_status);
+    lan_sync::apply_settings(app, &settings.sync);
+
+    if let Some(window) = app.get_webview_window("main") {
+        let min_size = LogicalSize::new(settings.window.min_width, settings.window.min_height);
+        if let Err(e) = window.set_min_size(Some(min_size)) {
+            warn!("Failed to set minimum window size: {}", e);
+        }
+    }
+
+    locale::apply(app, &settings.app_locale());
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    crate::speech::set_locale(&settings.speech_locale());
+
+    crate::capture_names::set_template(settings.media.file_name_template.clone());
+
+    #[cfg(target_os = "macos")]
+    crate::media::set_segment_minutes(settings.media.segment_minutes);
+
+    #[cfg(desktop)]
+    crate::hotkeys::apply(app, &settings.hotkeys);
+}
+
+/// Store new settings, apply them and emit `settings-changed`
+fn replace(app: &AppHandle, state: &SettingsState, settings: Settings) -> Result<(), String> {
+    {
+        let mut current = state.settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if *current == settings {
+            return Ok(());
+        }
+        *current = settings.clone();
+    }
+    apply_live(app, &settings);
+    if let Err(e) = app.emit("settings-changed", &settings) {
+        warn!("Failed to emit settings-changed event: {}", e);
+    }
+    Ok(())
+}
+
+/// Validate and write `file` as `settings.json`, then resolve it again and
+/// apply the result. Returns the live settings.
+fn persist(app: &AppHandle, state: &SettingsState, path: &Path, file: Settings) -> Result<Settings, String> {
+    file.validate()?;
+    save_file(path, &file)?;
+    let (settings, overrides) = resolve(Some(path));
+    if let Ok(mut current) = state.overrides.lock() {
+        *current = overrides;
+    }
+    replace(app, state, settings.clone())?;
+    Ok(settings)
+}
+
+/// Change what `settings.json` holds with `edit`, then persist and apply it.
+/// Only the file is edited, so values from the environment, `config.toml`,
+/// launch flags or a per-user swap never end up saved as the user's own.
+/// Returns the live settings.
+pub fn update(app: &AppHandle, state: &SettingsState, edit: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    let path = state
+        .path
+        .as_deref()
+        .ok_or_else(|| "Config directory unavailable".to_string())?;
+    let _writing = state.writing.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut file = load_file(path);
+    edit(&mut file);
+    persist(app, state, path, file)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Reload when `settings.json`, `config.toml` or the `.tourienv` loaded in
+/// dev builds is edited outside the app
+pub fn watch_settings(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let state = app.state::<SettingsState>();
+        let config_file = state
+            .path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(|dir| dir.join(install_config::CONFIG_FILE));
+        let watched: Vec<PathBuf> = state.path.iter().chain(&state.touri_env).cloned().chain(config_file).collect();
+        let mut stamps: Vec<Option<SystemTime>> = watched.iter().map(|path| modified(path)).collect();
+
+        loop {
+            sleep(WATCH_INTERVAL).await;
+
+            let current: Vec<Option<SystemTime>> = watched.iter().map(|path| modified(path)).collect();
+            if current == stamps {
+                continue;
+            }
+            stamps = current;
+
+            if let Some(env_path) = &state.touri_env {
+                crate::touri_env::check(env_path);
+                if let Err(e) = dotenvy::from_path_override(env_path) {
+                    warn!("Failed to reload .tourienv: {}", e);
+                }
+            }
+            let (settings, overrides) = resolve(state.path.as_deref());
+            if let Ok(mut current) = state.overrides.lock() {
+                *current = overrides;
+            }
+            info!("Settings changed on disk, reloading");
+            if let Err(e) = replace(&app, &state, settings) {
+                warn!("Failed to apply reloaded settings: {}", e);
+            }
+        }
+    });
+}
+
+pub(crate) fn effective_config(state: &SettingsState) -> EffectiveConfig {
+    let settings = serde_json::to_value(state.current()).unwrap_or_default();
+    let overrides = state.overrides.lock().map(|keys| keys.clone()).unwrap_or_default();
+    let touri_env_issues = crate::touri_env::issues();
+    let file: Option<Value> = state
+        .path
+        .as_deref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let mut values: Vec<EffectiveValue> = KNOBS
+        .iter()
+        .map(|&(key, env_var)| {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            let value = settings.pointer(&pointer).cloned().unwrap_or(Value::Null);
+            let file_value = file.as_ref().and_then(|file| file.pointer(&pointer));
+            let env_set = env_var.is_some_and(|name| env::var_os(name).is_some());
+            // Clamped or ignored `.tourienv` values
+            let env_issue = env_var.and_then(|name| {
+                touri_env_issues
+                    .iter()
+                    .find(|issue| issue.key.as_deref() == Some(name))
+                    .map(|issue| issue.message.clone())
+            });
+
+            let overridden = overrides.iter().find(|(replaced, _)| *replaced == key);
+            let (source, note) = if let Some(&(_, source)) = overridden {
+                (source, user_isolation::reason(key).filter(|_| source == ConfigSource::PerUser))
+            } else if file_value == Some(&value) {
+                (ConfigSource::SettingsFile, None)
+            } else if file_value.is_some() {
+                (ConfigSource::Default, Some("settings.json value was rejected; see the log".to_string()))
+            } else {
+                (ConfigSource::Default, None)
+            };
+            let note = match (env_set && source != ConfigSource::Environment, env_var) {
+                (true, Some(name)) if source == ConfigSource::ConfigFile => {
+                    Some(format!("{} is overridden by {}", name, install_config::CONFIG_FILE))
+                }
+                (true, Some(name)) if source == ConfigSource::LaunchArgs => {
+                    Some(format!("{} is overridden by a launch flag", name))
+                }
+                (true, Some(name)) if !cfg!(debug_assertions) && !install_config::KEYS.contains(&key) => {
+                    Some(format!("{} is ignored in release builds; use settings.json", name))
+                }
+                (true, Some(name)) => {
+                    Some(env_issue.unwrap_or_else(|| format!("{} is set but could not be used", name)))
+                }
+                _ => note.or(env_issue),
+            };
+
+            EffectiveValue {
+                key: key.to_string(),
+                value,
+                source,
+                env_var,
+                note,
+            }
+        })
+        .collect();
+
+    let current = state.current();
+    for (key, value) in [
+        ("resolved.appLocale", current.app_locale()),
+        ("resolved.speechLocale", current.speech_locale()),
+        ("resolved.backendUrl", current.backend_url()),
+        ("resolved.healthUrl", format!("{}/health", current.backend_url())),
+    ] {
+        values.push(EffectiveValue {
+            key: key.to_string(),
+            value: Value::String(value),
+            source: ConfigSource::Derived,
+            env_var: None,
+            note: None,
+        });
+    }
+    for &(key, env_var) in BACKEND_PATHS {
+        let source = if state.touri_env.is_some() {
+            ConfigSource::Environment
+        } else {
+            ConfigSource::Derived
+        };
+        values.push(EffectiveValue {
+            key: key.to_string(),
+            value: env::var(env_var).map(Value::String).unwrap_or(Value::Null),
+            source,
+            env_var: Some(env_var),
+            note: None,
+        });
+    }
+    let start_hidden = crate::autostart::launched_hidden();
+    values.push(EffectiveValue {
+        key: "resolved.startHidden".to_string(),
+        value: Value::Bool(start_hidden),
+        source: if start_hidden {
+            ConfigSource::LaunchArgs
+        } else {
+            ConfigSource::Default
+        },
+        env_var: None,
+        note: None,
+    });
+
+    EffectiveConfig {
+        build: if cfg!(debug_assertions) { "debug" } else { "release" },
+        settings_file: state.path.as_ref().map(|path| path.to_string_lossy().to_string()),
+        settings_file_exists: state.path.as_deref().is_some_and(Path::exists),
+        touri_env: state.touri_env.as_ref().map(|path| path.to_string_lossy().to_string()),
+        touri_env_issues,
+        values,
+    }
+}
+
+/// Every value the shell resolved, with where it came from, for support
+/// ("it ignores my TAURI_WINDOW_WIDTH")
+#[tauri::command]
+pub async fn get_effective_config(state: State<'_, SettingsState>) -> Result<EffectiveConfig, String> {
+    Ok(effective_config(&state))
+}
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, SettingsState>) -> Result<Settings, String> {
+    Ok(state.current())
+}
+
+/// Merge `patch` (a partial settings object) into `settings.json`, validate
+/// and persist the result, and emit `settings-changed`. Overrides from the
+/// environment, `config.toml` and launch flags still apply on top. Backend and
+/// network probe changes take effect on the next launch. The data directory
+/// and the backend launcher command can't be changed here: they decide where
+/// data goes and what runs, so use `set_data_dir` to move the data directory.
+#[tauri::command]
+pub async fn update_settings(
+    app: AppHandle,
+    state: State<'_, SettingsState>,
+    patch: Value,
+) -> Result<Settings, String> {
+    if !patch.is_object() {
+        return Err("Settings patch must be an object".to_string());
+    }
+
+    let path = state
+        .path
+        .as_deref()
+        .ok_or_else(|| "Config directory unavailable".to_string())?;
+    let _writing = state.writing.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let current = load_file(path);
+    let mut value = serde_json::to_value(&current).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    merge(&mut value, patch);
+    let file: Settings = serde_json::from_value(value).map_err(|e| format!("Invalid settings: {}", e))?;
+    if file.media.data_dir != current.media.data_dir {
+        return Err("media.dataDir can't be changed here; use set_data_dir".to_string());
+    }
+    if file.backend.launcher_command != current.backend.launcher_command {
+        return Err("backend.launcherCommand can only be changed in settings.json or config.toml".to_string());
+    }
+    let updated = persist(&app, &state, path, file)?;
+    info!("Settings updated");
+    Ok(updated)
+}
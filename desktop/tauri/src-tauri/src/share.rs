@@ -0,0 +1,104 @@
+use std::ffi::CStr;
+use std::fs;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::media_store::get_media_dir;
+
+// FFI declarations for the Objective-C services provider
+extern "C" {
+    fn services_register(
+        callback: extern "C" fn(*const c_char, *const *const c_char, usize),
+    );
+}
+
+// App handle used from the services callback, which arrives on the main thread
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Content sent from another app through "Add to Track The Thing"
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalShare {
+    pub text: Option<String>,
+    /// Copies of the shared files inside the media `shared` directory
+    pub files: Vec<String>,
+}
+
+/// Register the macOS Services provider declared in Info.plist
+pub fn init_share_services(app: AppHandle) {
+    let _ = APP_HANDLE.set(app);
+    unsafe {
+        services_register(share_callback);
+    }
+}
+
+extern "C" fn share_callback(text_ptr: *const c_char, paths_ptr: *const *const c_char, path_count: usize) {
+    let text = if text_ptr.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(text_ptr) }.to_str().ok().map(str::to_string)
+    };
+
+    let mut paths = Vec::with_capacity(path_count);
+    if !paths_ptr.is_null() {
+        for i in 0..path_count {
+            let path_ptr = unsafe { *paths_ptr.add(i) };
+            if path_ptr.is_null() {
+                continue;
+            }
+            if let Ok(path) = unsafe { CStr::from_ptr(path_ptr) }.to_str() {
+                paths.push(PathBuf::from(path));
+            }
+        }
+    }
+
+    let Some(app) = APP_HANDLE.get().cloned() else {
+        warn!("Share received before the services provider was initialized");
+        return;
+    };
+
+    // Copying files can take a while; don't block the main thread
+    std::thread::spawn(move || {
+        if let Err(e) = ingest_share(&app, text, &paths) {
+            warn!("Failed to ingest shared content: {}", e);
+        }
+    });
+}
+
+fn unique_destination(dir: &Path, source: &Path) -> PathBuf {
+    let file_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "shared_file".to_string());
+    let candidate = dir.join(&file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    dir.join(format!("{}_{}", chrono::Utc::now().timestamp_millis(), file_name))
+}
+
+fn ingest_share(app: &AppHandle, text: Option<String>, paths: &[PathBuf]) -> Result<(), String> {
+    let mut files = Vec::new();
+    if !paths.is_empty() {
+        let shared_dir = get_media_dir(app, "shared")?;
+        for source in paths {
+            if !source.is_file() {
+                warn!("Skipping shared item that is not a file: {}", source.display());
+                continue;
+            }
+            let destination = unique_destination(&shared_dir, source);
+            fs::copy(source, &destination)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            files.push(destination.to_string_lossy().to_string());
+        }
+    }
+
+    info!("Ingested external share: {} file(s), text: {}", files.len(), text.is_some());
+    app.emit("external-share", ExternalShare { text, files })
+        .map_err(|e| format!("Failed to emit external-share event: {}", e))
+}
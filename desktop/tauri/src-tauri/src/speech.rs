@@ -1,16 +1,28 @@
 use std::ffi::CStr;
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
-use tauri::{AppHandle, Emitter};
+use std::time::Instant;
+use serde::Serialize;
+use tauri::AppHandle;
 use tokio::sync::oneshot;
+use tracing::{debug, error, info, info_span, instrument, trace, warn, Span};
+
+use crate::error::ShellError;
+use crate::event_batch;
 
 // FFI declarations for Objective-C functions
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" {
     fn speech_request_authorization(callback: extern "C" fn(bool));
     fn speech_start_recording(callback: extern "C" fn(*const c_char, bool)) -> bool;
+    fn speech_start_external_recording(callback: extern "C" fn(*const c_char, bool)) -> bool;
+    fn speech_append_audio(samples: *const f32, frame_count: u32, sample_rate: f64) -> bool;
     fn speech_stop_recording();
     fn speech_is_available() -> bool;
+    fn speech_set_locale(identifier: *const c_char);
+    fn speech_authorization_state() -> i32;
+    fn capture_authorization_state(video: bool) -> i32;
 }
 
 // Global state to hold the app handle for callbacks (using OnceLock for thread safety)
@@ -21,17 +33,76 @@ static APP_HANDLE: OnceLock<Arc<Mutex<AppHandle>>> = OnceLock::new();
 // pending requests with the same result.
 static AUTH_SENDERS: OnceLock<Arc<Mutex<Vec<oneshot::Sender<bool>>>>> = OnceLock::new();
 
+// The running recognition session's span, entered by the transcription
+// callback so its events are attributed to the session
+static SESSION_SPAN: Mutex<Option<Span>> = Mutex::new(None);
+static SESSION_COUNT: AtomicU64 = AtomicU64::new(0);
+static SESSION_STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+// Whether the running session takes its audio from `feed_speech_audio`
+// rather than the microphone
+static EXTERNAL_AUDIO: AtomicBool = AtomicBool::new(false);
+
+// Final transcriptions of the running push-to-talk session, `None` when
+// none runs
+#[cfg(target_os = "macos")]
+static PUSH_TO_TALK: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Payload of `speech-transcription`
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechTranscription {
+    pub text: String,
+    /// The recognizer won't revise this text any more
+    pub is_final: bool,
+}
+
+/// Payload of `dictation`: everything said during one push-to-talk
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Dictation {
+    pub text: String,
+}
+
+fn session_span() -> Span {
+    SESSION_SPAN
+        .lock()
+        .ok()
+        .and_then(|span| span.clone())
+        .unwrap_or_else(Span::none)
+}
+
 // Initialize the speech recognition system
 pub fn init_speech_system(app: AppHandle) {
     let _ = APP_HANDLE.set(Arc::new(Mutex::new(app)));
     let _ = AUTH_SENDERS.set(Arc::new(Mutex::new(Vec::new())));
 }
 
+// Apply the recognition language from settings (takes effect on the next session)
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn set_locale(locale: &str) {
+    if let Ok(locale) = std::ffi::CString::new(locale) {
+        unsafe { speech_set_locale(locale.as_ptr()) };
+    }
+}
+
+/// Camera, microphone and speech recognition permission states (in that
+/// order) as reported by the system, without prompting
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn permission_states() -> [i32; 3] {
+    unsafe {
+        [
+            capture_authorization_state(true),
+            capture_authorization_state(false),
+            speech_authorization_state(),
+        ]
+    }
+}
+
 // Callback for authorization - completes all pending authorization requests
 // since they all ask about the same system-level permission
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" fn authorization_callback(authorized: bool) {
-    println!("[Speech] Authorization callback: {}", authorized);
+    debug!(authorized, "authorization callback");
     if let Some(senders_arc) = AUTH_SENDERS.get() {
         if let Ok(mut guard) = senders_arc.lock() {
             // Drain all pending senders and complete them with the result
@@ -40,55 +111,172 @@ extern "C" fn authorization_callback(authorized: bool) {
             for sender in senders {
                 let _ = sender.send(authorized);
             }
-            println!("[Speech] Completed {} pending authorization request(s)", count);
+            debug!(count, "completed pending authorization requests");
         }
     }
 }
 
 // Callback for transcription results
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 extern "C" fn transcription_callback(text_ptr: *const c_char, is_final: bool) {
+    let _session = session_span().entered();
     unsafe {
-        println!("[Speech] transcription_callback called, is_final: {}", is_final);
-        
         if text_ptr.is_null() {
-            println!("[Speech] Error: text_ptr is null");
+            error!("transcription callback got a null text pointer");
             return;
         }
         
         let c_str = CStr::from_ptr(text_ptr);
         if let Ok(text) = c_str.to_str() {
-            println!("[Speech] Transcription text: '{}', is_final: {}", text, is_final);
+            // Only the length: transcripts end up in diagnostics bundles otherwise
+            trace!(chars = text.chars().count(), is_final, "transcription received");
             
+            #[cfg(target_os = "macos")]
+            if is_final {
+                if let Ok(mut segments) = PUSH_TO_TALK.lock() {
+                    // Stopping repeats a result the recognizer already finalized
+                    if let Some(segments) = segments.as_mut() {
+                        if segments.last().map(String::as_str) != Some(text) {
+                            segments.push(text.to_string());
+                        }
+                    }
+                }
+            }
+
             // Emit event to frontend
             if let Some(app_handle_arc) = APP_HANDLE.get() {
                 if let Ok(guard) = app_handle_arc.lock() {
-                    println!("[Speech] Emitting speech-transcription event...");
-                    match guard.emit("speech-transcription", serde_json::json!({
-                        "text": text,
-                        "isFinal": is_final
-                    })) {
-                        Ok(_) => println!("[Speech] Event emitted successfully"),
-                        Err(e) => println!("[Speech] Failed to emit event: {:?}", e),
+                    // Partials are coalesced; a final result always goes out
+                    let payload = SpeechTranscription {
+                        text: text.to_string(),
+                        is_final,
+                    };
+                    if is_final {
+                        event_batch::emit_now(&guard, "speech-transcription", payload);
+                    } else {
+                        event_batch::emit(&guard, "speech-transcription", payload);
                     }
                 } else {
-                    println!("[Speech] Error: Failed to lock APP_HANDLE");
+                    error!("failed to lock APP_HANDLE");
                 }
             } else {
-                println!("[Speech] Error: APP_HANDLE not initialized");
+                error!("APP_HANDLE not initialized");
             }
         } else {
-            println!("[Speech] Error: Failed to convert C string to Rust string");
+            error!("transcription is not valid UTF-8");
+        }
+    }
+}
+
+// Start a recognition session in its own span
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn start_session(external_audio: bool) -> Result<(), String> {
+    let id = SESSION_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    let span = info_span!("speech_session", id, external_audio);
+    let _entered = span.enter();
+    if let Ok(mut session) = SESSION_SPAN.lock() {
+        *session = Some(span.clone());
+    }
+
+    let success = unsafe {
+        if external_audio {
+            speech_start_external_recording(transcription_callback)
+        } else {
+            speech_start_recording(transcription_callback)
+        }
+    };
+    if success {
+        info!("speech recognition started");
+        EXTERNAL_AUDIO.store(external_audio, Ordering::SeqCst);
+        if let Ok(mut started) = SESSION_STARTED.lock() {
+            *started = Some(Instant::now());
+        }
+        Ok(())
+    } else {
+        error!("failed to start speech recognition");
+        if let Ok(mut session) = SESSION_SPAN.lock() {
+            *session = None;
         }
+        Err("Failed to start speech recognition".to_string())
+    }
+}
+
+// Stop the running session and record how long it ran
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn stop_session() {
+    // Entered while stopping: the bridge delivers the final transcription
+    // from inside speech_stop_recording
+    let _session = session_span().entered();
+    unsafe {
+        speech_stop_recording();
+    }
+    info!("speech recognition stopped");
+    EXTERNAL_AUDIO.store(false, Ordering::SeqCst);
+    if let Ok(mut session) = SESSION_SPAN.lock() {
+        *session = None;
+    }
+    let started = SESSION_STARTED.lock().ok().and_then(|mut started| started.take());
+    if let (Some(started), Some(app)) = (started, APP_HANDLE.get()) {
+        if let Ok(app) = app.lock() {
+            crate::telemetry::record(&app, "speech_session", Some(started.elapsed()));
+        }
+    }
+}
+
+/// Start dictating while the push-to-talk shortcut is held. Does nothing
+/// while another recognition session runs.
+#[cfg(target_os = "macos")]
+pub fn push_to_talk_pressed(app: &AppHandle) {
+    if let Err(e) = crate::permissions::require(app, crate::permissions::MICROPHONE) {
+        warn!(error = %e, "push-to-talk not started");
+        return;
+    }
+    let busy = SESSION_SPAN.lock().map(|session| session.is_some()).unwrap_or(true);
+    {
+        let Ok(mut segments) = PUSH_TO_TALK.lock() else {
+            return;
+        };
+        if busy || segments.is_some() {
+            debug!("push-to-talk ignored, recognition already running");
+            return;
+        }
+        *segments = Some(Vec::new());
+    }
+    if let Err(e) = start_session(false) {
+        warn!(error = %e, "push-to-talk failed to start");
+        if let Ok(mut segments) = PUSH_TO_TALK.lock() {
+            *segments = None;
+        }
+    }
+}
+
+/// Finish the push-to-talk session and emit its text as `dictation`
+#[cfg(target_os = "macos")]
+pub fn push_to_talk_released(app: &AppHandle) {
+    use tauri::Emitter;
+
+    let active = PUSH_TO_TALK.lock().map(|segments| segments.is_some()).unwrap_or(false);
+    if !active {
+        return;
+    }
+    stop_session();
+    let segments = PUSH_TO_TALK.lock().ok().and_then(|mut segments| segments.take()).unwrap_or_default();
+    let text = segments.join(" ");
+    info!(chars = text.chars().count(), "push-to-talk finished");
+    if text.is_empty() {
+        return;
+    }
+    if let Err(e) = app.emit("dictation", Dictation { text }) {
+        warn!(error = %e, "failed to emit dictation event");
     }
 }
 
 #[tauri::command]
-pub async fn request_speech_authorization() -> Result<bool, String> {
-    #[cfg(target_os = "macos")]
+#[specta::specta]
+#[instrument(err)]
+pub async fn request_speech_authorization() -> Result<bool, ShellError> {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
-        println!("[Speech] Requesting authorization...");
-        
         // Create a oneshot channel for the callback
         let (tx, rx) = oneshot::channel();
         
@@ -99,10 +287,10 @@ pub async fn request_speech_authorization() -> Result<bool, String> {
                 guard.push(tx);
                 was_empty
             } else {
-                return Err("Failed to acquire lock on AUTH_SENDERS".to_string());
+                return Err(ShellError::internal("Failed to acquire lock on AUTH_SENDERS"));
             }
         } else {
-            return Err("Speech system not initialized".to_string());
+            return Err(ShellError::unavailable("Speech system not initialized"));
         };
         
         // Only request authorization if we're the first request
@@ -116,77 +304,109 @@ pub async fn request_speech_authorization() -> Result<bool, String> {
         // Wait for the callback with a timeout
         match tokio::time::timeout(std::time::Duration::from_secs(30), rx).await {
             Ok(Ok(authorized)) => {
-                println!("[Speech] Authorization result: {}", authorized);
+                info!(authorized, "speech authorization result");
                 Ok(authorized)
             }
             Ok(Err(_)) => {
-                Err("Authorization callback failed".to_string())
+                Err(ShellError::internal("Authorization callback failed"))
             }
             Err(_) => {
-                Err("Authorization request timed out".to_string())
+                Err(ShellError::timeout("Authorization request timed out"))
             }
         }
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
     {
-        Err("Speech recognition is only available on macOS".to_string())
+        Err(ShellError::unsupported("Speech recognition is only available on macOS and iOS"))
     }
 }
 
+/// Start speech recognition. By default the bridge captures the microphone itself;
+/// with `external_audio` set, audio is instead supplied via `feed_speech_audio`
+/// (e.g. WebRTC call audio captured by the webview).
 #[tauri::command]
-pub async fn start_speech_recognition() -> Result<(), String> {
-    #[cfg(target_os = "macos")]
+#[specta::specta]
+pub async fn start_speech_recognition(app: AppHandle, external_audio: Option<bool>) -> Result<(), ShellError> {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
-        println!("[Speech] start_speech_recognition command called");
-        unsafe {
-            let success = speech_start_recording(transcription_callback);
-            println!("[Speech] speech_start_recording returned: {}", success);
-            if success {
-                println!("[Speech] Speech recognition started successfully");
-                Ok(())
-            } else {
-                let err_msg = "Failed to start speech recognition".to_string();
-                println!("[Speech] Error: {}", err_msg);
-                Err(err_msg)
-            }
+        let external_audio = external_audio.unwrap_or(false);
+        if !external_audio {
+            crate::permissions::require(&app, crate::permissions::MICROPHONE)?;
         }
+        start_session(external_audio).map_err(ShellError::unavailable)
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
     {
-        Err("Speech recognition is only available on macOS".to_string())
+        let _ = (app, external_audio);
+        Err(ShellError::unsupported("Speech recognition is only available on macOS and iOS"))
     }
 }
 
+/// Push a chunk of mono 32-bit float PCM into an external-audio recognition session.
 #[tauri::command]
-pub async fn stop_speech_recognition() -> Result<(), String> {
-    #[cfg(target_os = "macos")]
+#[specta::specta]
+pub async fn feed_speech_audio(chunk: Vec<f32>, sample_rate: f64) -> Result<(), ShellError> {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
-        println!("[Speech] stop_speech_recognition command called");
-        unsafe {
-            speech_stop_recording();
+        if !EXTERNAL_AUDIO.load(Ordering::SeqCst) {
+            let running = SESSION_SPAN.lock().map(|session| session.is_some()).unwrap_or(false);
+            return Err(ShellError::unavailable(if running {
+                "The running speech recognition session listens to the microphone"
+            } else {
+                "No external-audio speech recognition session is active"
+            }));
+        }
+        if chunk.is_empty() {
+            return Ok(());
         }
-        println!("[Speech] Speech recognition stopped");
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(ShellError::invalid_input(format!("Invalid sample rate: {}", sample_rate)));
+        }
+        let frame_count = u32::try_from(chunk.len())
+            .map_err(|_| ShellError::invalid_input("Audio chunk is too large"))?;
+        let accepted = unsafe { speech_append_audio(chunk.as_ptr(), frame_count, sample_rate) };
+        if accepted {
+            Ok(())
+        } else {
+            Err(ShellError::unavailable("No external-audio speech recognition session is active"))
+        }
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    {
+        let _ = (chunk, sample_rate);
+        Err(ShellError::unsupported("Speech recognition is only available on macOS and iOS"))
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn stop_speech_recognition() -> Result<(), ShellError> {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    {
+        stop_session();
         Ok(())
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
     {
-        Err("Speech recognition is only available on macOS".to_string())
+        Err(ShellError::unsupported("Speech recognition is only available on macOS and iOS"))
     }
 }
 
 #[tauri::command]
-pub async fn is_speech_available() -> Result<bool, String> {
-    #[cfg(target_os = "macos")]
+#[specta::specta]
+pub async fn is_speech_available() -> Result<bool, ShellError> {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
         unsafe {
             Ok(speech_is_available())
         }
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
     {
         Ok(false)
     }
@@ -13,6 +13,54 @@ static AVAudioEngine *audioEngine = nil;
 static TranscriptionCallback transcriptionCallback = NULL;
 static BOOL tapInstalled = NO;  // Track if audio tap is installed
 static NSString *lastTranscription = nil;  // Store last transcription for final emit
+static NSString *recognizerLocale = @"en-US";  // From the speech settings
+static BOOL externalAudioSession = NO;  // Whether the session takes audio from speech_append_audio
+#if TARGET_OS_IOS
+static BOOL audioSessionActive = NO;  // Whether we activated the shared audio session
+static id interruptionObserver = nil;
+#endif
+
+// iOS routes all audio through the shared AVAudioSession: switch it to
+// recording (ducking other audio) before the microphone is used. macOS has
+// no session, the input node works as is.
+static BOOL activateAudioSession(void) {
+#if TARGET_OS_IOS
+    AVAudioSession *session = [AVAudioSession sharedInstance];
+    NSError *error = nil;
+    if (![session setCategory:AVAudioSessionCategoryRecord
+                         mode:AVAudioSessionModeMeasurement
+                      options:AVAudioSessionCategoryOptionDuckOthers
+                        error:&error]) {
+        NSLog(@"[SpeechBridge] Failed to set audio session category: %@", error);
+        return NO;
+    }
+    if (![session setActive:YES withOptions:AVAudioSessionSetActiveOptionNotifyOthersOnDeactivation error:&error]) {
+        NSLog(@"[SpeechBridge] Failed to activate audio session: %@", error);
+        return NO;
+    }
+    audioSessionActive = YES;
+    NSLog(@"[SpeechBridge] Audio session activated for recording");
+#endif
+    return YES;
+}
+
+// Hand the audio session back so ducked audio resumes
+static void deactivateAudioSession(void) {
+#if TARGET_OS_IOS
+    if (!audioSessionActive) {
+        return;
+    }
+    NSError *error = nil;
+    if (![[AVAudioSession sharedInstance] setActive:NO
+                                        withOptions:AVAudioSessionSetActiveOptionNotifyOthersOnDeactivation
+                                              error:&error]) {
+        NSLog(@"[SpeechBridge] Failed to deactivate audio session: %@", error);
+    }
+    audioSessionActive = NO;
+#endif
+}
+
+void speech_stop_recording(void);
 
 // Helper function to safely remove audio tap
 static void safelyRemoveTap(void) {
@@ -62,10 +110,12 @@ static void cleanupRecognition(void) {
     
     // Safely remove the audio tap
     safelyRemoveTap();
+    deactivateAudioSession();
     
     // Clear references
     recognitionRequest = nil;
     recognitionTask = nil;
+    externalAudioSession = NO;
     
     // Recreate audio engine fresh for next recording
     // This prevents state corruption and delays on subsequent recordings
@@ -74,14 +124,45 @@ static void cleanupRecognition(void) {
     transcriptionCallback = NULL;
 }
 
+// Change the recognition language. The recognizer is recreated for the next
+// session; one that is already running keeps its language.
+void speech_set_locale(const char *identifier) {
+    NSString *locale = [NSString stringWithUTF8String:identifier];
+    if (locale.length == 0 || [locale isEqualToString:recognizerLocale]) {
+        return;
+    }
+    NSLog(@"[SpeechBridge] Recognition locale set to %@", locale);
+    recognizerLocale = locale;
+    if (recognitionTask == nil) {
+        speechRecognizer = nil;
+    }
+}
+
 // Initialize the speech recognition system
 void speech_initialize(void) {
     if (speechRecognizer == nil) {
-        speechRecognizer = [[SFSpeechRecognizer alloc] initWithLocale:[NSLocale localeWithLocaleIdentifier:@"en-US"]];
+        speechRecognizer = [[SFSpeechRecognizer alloc] initWithLocale:[NSLocale localeWithLocaleIdentifier:recognizerLocale]];
     }
     if (audioEngine == nil) {
         audioEngine = [[AVAudioEngine alloc] init];
     }
+#if TARGET_OS_IOS
+    // A phone call or Siri takes the microphone away; end the session like a
+    // manual stop so the text so far is kept
+    if (interruptionObserver == nil) {
+        interruptionObserver = [[NSNotificationCenter defaultCenter]
+            addObserverForName:AVAudioSessionInterruptionNotification
+                        object:[AVAudioSession sharedInstance]
+                         queue:[NSOperationQueue mainQueue]
+                    usingBlock:^(NSNotification *note) {
+            NSUInteger type = [note.userInfo[AVAudioSessionInterruptionTypeKey] unsignedIntegerValue];
+            if (type == AVAudioSessionInterruptionTypeBegan && audioSessionActive) {
+                NSLog(@"[SpeechBridge] Audio session interrupted, stopping recording");
+                speech_stop_recording();
+            }
+        }];
+    }
+#endif
 }
 
 // Request authorization for speech recognition
@@ -99,6 +180,7 @@ bool speech_start_recording(TranscriptionCallback callback) {
     
     // Clear any previous transcription
     lastTranscription = nil;
+    externalAudioSession = NO;
     
     // Check authorization first
     SFSpeechRecognizerAuthorizationStatus authStatus = [SFSpeechRecognizer authorizationStatus];
@@ -138,10 +220,16 @@ bool speech_start_recording(TranscriptionCallback callback) {
     
     recognitionRequest.shouldReportPartialResults = YES;
     
+    // The input node's format depends on the audio session, so configure it first
+    if (!activateAudioSession()) {
+        return false;
+    }
+    
     // Get audio input node
     AVAudioInputNode *inputNode = audioEngine.inputNode;
     if (inputNode == nil) {
         NSLog(@"[SpeechBridge] Failed to get audio input node");
+        deactivateAudioSession();
         return false;
     }
     
@@ -149,6 +237,7 @@ bool speech_start_recording(TranscriptionCallback callback) {
     AVAudioFormat *recordingFormat = [inputNode outputFormatForBus:0];
     if (recordingFormat == nil) {
         NSLog(@"[SpeechBridge] Failed to get recording format");
+        deactivateAudioSession();
         return false;
     }
     
@@ -166,6 +255,7 @@ bool speech_start_recording(TranscriptionCallback callback) {
         NSLog(@"[SpeechBridge] Audio tap installed successfully");
     } @catch (NSException *exception) {
         NSLog(@"[SpeechBridge] Failed to install audio tap: %@", exception.reason);
+        deactivateAudioSession();
         return false;
     }
     
@@ -177,6 +267,7 @@ bool speech_start_recording(TranscriptionCallback callback) {
     if (!audioStarted || audioError != nil) {
         NSLog(@"[SpeechBridge] Audio engine failed to start: %@", audioError);
         safelyRemoveTap();
+        deactivateAudioSession();
         return false;
     }
     
@@ -219,6 +310,7 @@ bool speech_start_recording(TranscriptionCallback callback) {
         NSLog(@"[SpeechBridge] Failed to create recognition task");
         safelyRemoveTap();
         [audioEngine stop];
+        deactivateAudioSession();
         return false;
     }
     
@@ -226,6 +318,118 @@ bool speech_start_recording(TranscriptionCallback callback) {
     return true;
 }
 
+// Start recognizing speech from audio supplied by the caller instead of the microphone.
+// Audio is pushed in with speech_append_audio (e.g. PCM captured by the webview).
+bool speech_start_external_recording(TranscriptionCallback callback) {
+    speech_initialize();
+    
+    lastTranscription = nil;
+    externalAudioSession = NO;
+    
+    SFSpeechRecognizerAuthorizationStatus authStatus = [SFSpeechRecognizer authorizationStatus];
+    if (authStatus != SFSpeechRecognizerAuthorizationStatusAuthorized) {
+        NSLog(@"Speech recognition not authorized. Status: %ld", (long)authStatus);
+        return false;
+    }
+    
+    if (speechRecognizer == nil || !speechRecognizer.isAvailable) {
+        NSLog(@"Speech recognizer not available");
+        return false;
+    }
+    
+    transcriptionCallback = callback;
+    
+    if (recognitionTask != nil) {
+        [recognitionTask cancel];
+        recognitionTask = nil;
+    }
+    
+    // The microphone is not used in this mode, make sure a previous session released it
+    safelyRemoveTap();
+    if (audioEngine != nil && audioEngine.isRunning) {
+        [audioEngine stop];
+    }
+    deactivateAudioSession();
+    
+    recognitionRequest = [[SFSpeechAudioBufferRecognitionRequest alloc] init];
+    if (recognitionRequest == nil) {
+        NSLog(@"Failed to create recognition request");
+        return false;
+    }
+    
+    recognitionRequest.shouldReportPartialResults = YES;
+    
+    NSLog(@"[SpeechBridge] Starting external-audio recognition task...");
+    recognitionTask = [speechRecognizer recognitionTaskWithRequest:recognitionRequest
+                                                     resultHandler:^(SFSpeechRecognitionResult *result, NSError *error) {
+        if (error != nil) {
+            NSLog(@"[SpeechBridge] Recognition error: %@ (domain: %@, code: %ld)", 
+                  error.localizedDescription, error.domain, (long)error.code);
+        }
+        
+        if (result != nil) {
+            NSString *transcription = result.bestTranscription.formattedString;
+            bool isFinal = result.isFinal;
+            
+            lastTranscription = [transcription copy];
+            
+            if (transcriptionCallback != NULL) {
+                const char *cString = [transcription UTF8String];
+                transcriptionCallback(cString, isFinal);
+            }
+        }
+        
+        if (error != nil) {
+            NSLog(@"[SpeechBridge] Error occurred, cleaning up");
+            cleanupRecognition();
+        }
+    }];
+    
+    if (recognitionTask == nil) {
+        NSLog(@"[SpeechBridge] Failed to create recognition task");
+        recognitionRequest = nil;
+        return false;
+    }
+    
+    externalAudioSession = YES;
+    NSLog(@"[SpeechBridge] External-audio recognition task started successfully");
+    return true;
+}
+
+// Append a chunk of mono 32-bit float PCM to the active recognition request.
+// Returns false if no external-audio session is active (a microphone session
+// takes its audio from the tap only) or the buffer can't be created.
+bool speech_append_audio(const float *samples, uint32_t frameCount, double sampleRate) {
+    if (recognitionRequest == nil || !externalAudioSession) {
+        return false;
+    }
+    
+    if (samples == NULL || frameCount == 0 || sampleRate <= 0) {
+        return false;
+    }
+    
+    AVAudioFormat *format = [[AVAudioFormat alloc] initWithCommonFormat:AVAudioPCMFormatFloat32
+                                                             sampleRate:sampleRate
+                                                               channels:1
+                                                            interleaved:NO];
+    if (format == nil) {
+        NSLog(@"[SpeechBridge] Failed to create audio format for %f Hz", sampleRate);
+        return false;
+    }
+    
+    AVAudioPCMBuffer *buffer = [[AVAudioPCMBuffer alloc] initWithPCMFormat:format frameCapacity:frameCount];
+    if (buffer == nil) {
+        NSLog(@"[SpeechBridge] Failed to allocate PCM buffer");
+        return false;
+    }
+    
+    memcpy(buffer.floatChannelData[0], samples, frameCount * sizeof(float));
+    buffer.frameLength = frameCount;
+    
+    [recognitionRequest appendAudioPCMBuffer:buffer];
+    return true;
+}
+
 // Stop recording
 void speech_stop_recording(void) {
     NSLog(@"[SpeechBridge] speech_stop_recording called");
@@ -252,8 +456,29 @@ bool speech_is_available(void) {
     }
     
     if (speechRecognizer == nil) {
-        speechRecognizer = [[SFSpeechRecognizer alloc] initWithLocale:[NSLocale localeWithLocaleIdentifier:@"en-US"]];
+        speechRecognizer = [[SFSpeechRecognizer alloc] initWithLocale:[NSLocale localeWithLocaleIdentifier:recognizerLocale]];
     }
     
     return speechRecognizer != nil && speechRecognizer.isAvailable;
 }
+
+// Permission states for the health dashboard, all mapped to one encoding:
+// 0 not determined, 1 denied, 2 restricted, 3 authorized
+int speech_authorization_state(void) {
+    switch ([SFSpeechRecognizer authorizationStatus]) {
+        case SFSpeechRecognizerAuthorizationStatusAuthorized: return 3;
+        case SFSpeechRecognizerAuthorizationStatusRestricted: return 2;
+        case SFSpeechRecognizerAuthorizationStatusDenied: return 1;
+        default: return 0;
+    }
+}
+
+int capture_authorization_state(bool video) {
+    AVMediaType type = video ? AVMediaTypeVideo : AVMediaTypeAudio;
+    switch ([AVCaptureDevice authorizationStatusForMediaType:type]) {
+        case AVAuthorizationStatusAuthorized: return 3;
+        case AVAuthorizationStatusRestricted: return 2;
+        case AVAuthorizationStatusDenied: return 1;
+        default: return 0;
+    }
+}
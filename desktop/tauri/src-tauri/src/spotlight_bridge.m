@@ -0,0 +1,123 @@
+#import <Foundation/Foundation.h>
+#import <AppKit/AppKit.h>
+#import <CoreSpotlight/CoreSpotlight.h>
+#import <UniformTypeIdentifiers/UniformTypeIdentifiers.h>
+#import <objc/runtime.h>
+
+// All indexed entries share one domain so they can be replaced in a single call
+static NSString *const kEntriesDomain = @"trackthething.entries";
+
+typedef void (*SpotlightOpenCallback)(const char *url);
+static SpotlightOpenCallback openCallback = NULL;
+
+static void setError(char **error_out, NSError *error, NSString *fallback) {
+    if (error_out != NULL) {
+        *error_out = strdup((error.localizedDescription ?: fallback).UTF8String);
+    }
+}
+
+static NSError *deleteEntries(void) {
+    __block NSError *failure = nil;
+    dispatch_semaphore_t done = dispatch_semaphore_create(0);
+    [[CSSearchableIndex defaultSearchableIndex] deleteSearchableItemsWithDomainIdentifiers:@[kEntriesDomain]
+                                                                        completionHandler:^(NSError *error) {
+        failure = error;
+        dispatch_semaphore_signal(done);
+    }];
+    dispatch_semaphore_wait(done, DISPATCH_TIME_FOREVER);
+    return failure;
+}
+
+// Replace the indexed entries with items_json, an array of
+// {"url", "title", "snippet"} objects. The deep link URL is the item identifier.
+bool spotlight_replace_items(const char *items_json, char **error_out) {
+    @autoreleasepool {
+        NSData *data = [NSData dataWithBytes:items_json length:strlen(items_json)];
+        NSError *error = nil;
+        NSArray *items = [NSJSONSerialization JSONObjectWithData:data options:0 error:&error];
+        if (![items isKindOfClass:[NSArray class]]) {
+            setError(error_out, error, @"Invalid Spotlight items");
+            return false;
+        }
+        
+        NSMutableArray<CSSearchableItem *> *searchable = [NSMutableArray arrayWithCapacity:items.count];
+        for (NSDictionary *item in items) {
+            NSString *url = item[@"url"];
+            if (![url isKindOfClass:[NSString class]]) {
+                continue;
+            }
+            CSSearchableItemAttributeSet *attributes =
+                [[CSSearchableItemAttributeSet alloc] initWithContentType:UTTypeText];
+            attributes.title = item[@"title"];
+            attributes.contentDescription = item[@"snippet"];
+            [searchable addObject:[[CSSearchableItem alloc] initWithUniqueIdentifier:url
+                                                                    domainIdentifier:kEntriesDomain
+                                                                        attributeSet:attributes]];
+        }
+        
+        error = deleteEntries();
+        if (error != nil) {
+            setError(error_out, error, @"Failed to clear Spotlight items");
+            return false;
+        }
+        
+        __block NSError *failure = nil;
+        dispatch_semaphore_t done = dispatch_semaphore_create(0);
+        [[CSSearchableIndex defaultSearchableIndex] indexSearchableItems:searchable
+                                                       completionHandler:^(NSError *indexError) {
+            failure = indexError;
+            dispatch_semaphore_signal(done);
+        }];
+        dispatch_semaphore_wait(done, DISPATCH_TIME_FOREVER);
+        
+        if (failure != nil) {
+            setError(error_out, failure, @"Failed to index Spotlight items");
+            return false;
+        }
+        return true;
+    }
+}
+
+bool spotlight_clear_items(char **error_out) {
+    @autoreleasepool {
+        NSError *error = deleteEntries();
+        if (error != nil) {
+            setError(error_out, error, @"Failed to clear Spotlight items");
+            return false;
+        }
+        return true;
+    }
+}
+
+void spotlight_free_string(char *value) {
+    free(value);
+}
+
+// Added to the app delegate: Spotlight opens a result by continuing a user activity
+static BOOL tttContinueUserActivity(id self, SEL _cmd, NSApplication *application,
+                                    NSUserActivity *activity, id restorationHandler) {
+    if (![activity.activityType isEqualToString:CSSearchableItemActionType] || openCallback == NULL) {
+        return NO;
+    }
+    NSString *identifier = activity.userInfo[CSSearchableItemActivityIdentifier];
+    if (identifier == nil) {
+        return NO;
+    }
+    openCallback(identifier.UTF8String);
+    return YES;
+}
+
+// The Tauri app delegate doesn't handle user activities, so add the handler at runtime
+void spotlight_install_handler(SpotlightOpenCallback callback) {
+    openCallback = callback;
+    dispatch_async(dispatch_get_main_queue(), ^{
+        id delegate = [NSApp delegate];
+        if (delegate == nil) {
+            return;
+        }
+        class_addMethod([delegate class],
+                        @selector(application:continueUserActivity:restorationHandler:),
+                        (IMP)tttContinueUserActivity,
+                        "c@:@@@?");
+    });
+}
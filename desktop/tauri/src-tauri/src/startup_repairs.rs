@@ -0,0 +1,171 @@
+//! Repairs made at startup instead of crashing or quietly falling back to
+//! defaults: corrupted preference files are backed up and regenerated,
+//! SQLite side files left behind without their database are moved aside,
+//! and partially written uploads are set aside. Everything repaired is
+//! announced as `startup-repairs` once the window is shown, and kept for
+//! `get_startup_repairs`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Where moved-aside files go, under the data dir
+pub const RECOVERED_DIR: &str = "recovered";
+
+/// SQLite's side files; a journal or WAL without its database would be
+/// applied to the next, unrelated one
+const DATABASE_SIDE_FILES: &[&str] = &["-wal", "-shm", "-journal"];
+
+/// Suffixes of uploads whose write never finished
+const PARTIAL_SUFFIXES: &[&str] = &[".tmp", ".part", ".partial"];
+
+static REPAIRS: Mutex<Vec<Repair>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RepairKind {
+    /// A settings or window preferences file that couldn't be parsed
+    CorruptedPrefs,
+    /// A database journal, WAL or shared-memory file without its database
+    StaleDatabaseFile,
+    /// An empty or unfinished file in the uploads directory
+    PartialUpload,
+}
+
+/// One entry of `startup-repairs`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Repair {
+    pub kind: RepairKind,
+    /// The file that was repaired
+    pub path: String,
+    /// Where the original was kept
+    pub backup: Option<String>,
+    pub reason: String,
+}
+
+fn record(kind: RepairKind, path: &Path, backup: Option<&Path>, reason: String) {
+    warn!("Startup repair: {} ({})", path.display(), reason);
+    let repair = Repair {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        backup: backup.map(|backup| backup.to_string_lossy().to_string()),
+        reason,
+    };
+    if let Ok(mut repairs) = REPAIRS.lock() {
+        repairs.push(repair);
+    }
+}
+
+/// Back up a corrupted preference file so the caller can regenerate it.
+/// Returns false if it couldn't be moved, in which case it is left alone.
+pub fn set_aside_corrupted(path: &Path, reason: &str) -> bool {
+    match crate::prefs::set_aside(path) {
+        Ok(backup) => {
+            record(RepairKind::CorruptedPrefs, path, Some(&backup), reason.to_string());
+            true
+        }
+        Err(e) => {
+            warn!("{}", e);
+            false
+        }
+    }
+}
+
+/// Move `path` into `dir`, keeping its name
+fn move_into(path: &Path, dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let target = dir.join(path.file_name().unwrap_or_default());
+    fs::rename(path, &target)
+        .or_else(|_| fs::copy(path, &target).and_then(|_| fs::remove_file(path)))
+        .map_err(|e| format!("Failed to move {} aside: {}", path.display(), e))?;
+    Ok(target)
+}
+
+fn repair_database_files(database: &Path, recovered: &Path) {
+    if database.exists() {
+        return;
+    }
+    let name = database.file_name().unwrap_or_default().to_string_lossy();
+    for suffix in DATABASE_SIDE_FILES {
+        let side = database.with_file_name(format!("{}{}", name, suffix));
+        if !side.exists() {
+            continue;
+        }
+        match move_into(&side, recovered) {
+            Ok(backup) => record(
+                RepairKind::StaleDatabaseFile,
+                &side,
+                Some(&backup),
+                format!("left behind without {}", name),
+            ),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+fn repair_uploads(uploads: &Path, recovered: &Path) {
+    let Ok(entries) = fs::read_dir(uploads) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Some(metadata) = entry.metadata().ok().filter(|metadata| metadata.is_file()) else {
+            continue;
+        };
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let reason = if PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+            "the upload never finished"
+        } else if metadata.len() == 0 {
+            "the upload is empty"
+        } else {
+            continue;
+        };
+        match move_into(&path, &recovered.join("uploads")) {
+            Ok(backup) => record(RepairKind::PartialUpload, &path, Some(&backup), reason.to_string()),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// Check the database and uploads before the backend starts. Settings and
+/// window preferences are repaired where they are loaded.
+pub fn run(app: &AppHandle) {
+    let data_dir = crate::data_dir::current_data_dir(app);
+    let recovered = data_dir
+        .join(RECOVERED_DIR)
+        .join(Local::now().format("%Y%m%d-%H%M%S").to_string());
+    repair_database_files(&crate::db_encryption::database_path(app), &recovered);
+    let uploads = env::var_os("TAURI_UPLOADS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data_dir.join("uploads"));
+    repair_uploads(&uploads, &recovered);
+}
+
+/// Emit `startup-repairs` if anything was repaired
+pub fn report(app: &AppHandle) {
+    let repairs = REPAIRS.lock().map(|repairs| repairs.clone()).unwrap_or_default();
+    if repairs.is_empty() {
+        return;
+    }
+    info!("{} startup repair(s) made", repairs.len());
+    if let Err(e) = app.emit("startup-repairs", &repairs) {
+        warn!("Failed to emit startup-repairs: {}", e);
+    }
+}
+
+/// What was repaired at startup, for a webview that loaded after
+/// `startup-repairs`
+#[tauri::command]
+pub async fn get_startup_repairs() -> Result<Vec<Repair>, String> {
+    REPAIRS
+        .lock()
+        .map(|repairs| repairs.clone())
+        .map_err(|e| format!("Lock error: {}", e))
+}
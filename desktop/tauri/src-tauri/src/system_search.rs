@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Manager, State};
+use tokio::sync::Notify;
+
+use crate::deep_link::SCHEME;
+use crate::{backend_client, DesktopConfig};
+
+const PREFS_FILE: &str = "search_index_prefs.json";
+
+// Entries change throughout the day; a periodic refresh keeps the index close enough
+const REINDEX_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+const SNIPPET_CHARS: usize = 300;
+
+/// A recent entry as exposed to Spotlight / Windows Search
+#[derive(Serialize, Clone, Debug)]
+struct IndexedItem {
+    url: String,
+    title: String,
+    snippet: String,
+}
+
+// Subset of the backend's SearchResult schema
+#[derive(Deserialize)]
+struct BackendEntry {
+    id: i64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    date: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SearchIndexPrefs {
+    enabled: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndexStatus {
+    pub enabled: bool,
+    /// Whether this platform has a system search index to write to
+    pub supported: bool,
+    pub indexed_count: usize,
+    pub last_indexed_at: Option<DateTime<Utc>>,
+}
+
+/// Opt-in mirror of recent entries into the OS search index
+pub struct SystemSearchIndexer {
+    enabled: AtomicBool,
+    indexed_count: AtomicUsize,
+    last_indexed_at: Mutex<Option<DateTime<Utc>>>,
+    wake: Notify,
+    config: DesktopConfig,
+    prefs_path: Option<PathBuf>,
+}
+
+/// Rich-text entries are stored as HTML; search snippets want plain text
+fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn fetch_recent_entries(config: &DesktopConfig) -> Result<Vec<IndexedItem>, String> {
+    let response = backend_client::call(config, "GET", "/api/search/", None)
+        .map_err(|e| format!("Failed to fetch entries: {}", e))?;
+    let entries: Vec<BackendEntry> =
+        serde_json::from_value(response.body).map_err(|e| format!("Failed to parse entries: {}", e))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let body = plain_text(&entry.content);
+            let title = if entry.title.trim().is_empty() {
+                body.chars().take(60).collect::<String>()
+            } else {
+                entry.title.trim().to_string()
+            };
+            let snippet: String = body.chars().take(SNIPPET_CHARS).collect();
+            IndexedItem {
+                url: format!("{}://entry/{}", SCHEME, entry.id),
+                title: if title.is_empty() { format!("Entry {}", entry.date) } else { title },
+                snippet,
+            }
+        })
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+    use std::sync::OnceLock;
+
+    use tauri::{AppHandle, Url};
+
+    use super::IndexedItem;
+
+    static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+    extern "C" {
+        fn spotlight_replace_items(items_json: *const c_char, error_out: *mut *mut c_char) -> bool;
+        fn spotlight_clear_items(error_out: *mut *mut c_char) -> bool;
+        fn spotlight_free_string(value: *mut c_char);
+        fn spotlight_install_handler(callback: extern "C" fn(*const c_char));
+    }
+
+    pub const SUPPORTED: bool = true;
+
+    extern "C" fn on_spotlight_open(url: *const c_char) {
+        if url.is_null() {
+            return;
+        }
+        let url = unsafe { CStr::from_ptr(url) }.to_string_lossy().to_string();
+        let (Some(app), Ok(url)) = (APP_HANDLE.get(), Url::parse(&url)) else {
+            return;
+        };
+        log::info!("Opening Spotlight result {}", url);
+        crate::deep_link::handle_urls(app, vec![url]);
+    }
+
+    unsafe fn take_error(error: *mut c_char, fallback: &str) -> String {
+        if error.is_null() {
+            return fallback.to_string();
+        }
+        let message = CStr::from_ptr(error).to_string_lossy().to_string();
+        spotlight_free_string(error);
+        message
+    }
+
+    pub fn init(app: &AppHandle) {
+        let _ = APP_HANDLE.set(app.clone());
+        unsafe { spotlight_install_handler(on_spotlight_open) };
+    }
+
+    pub fn replace(items: &[IndexedItem]) -> Result<(), String> {
+        let json = serde_json::to_string(items).map_err(|e| format!("Failed to serialize items: {}", e))?;
+        let json = CString::new(json).map_err(|e| format!("Invalid item text: {}", e))?;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        if unsafe { spotlight_replace_items(json.as_ptr(), &mut error) } {
+            Ok(())
+        } else {
+            Err(unsafe { take_error(error, "Failed to update Spotlight index") })
+        }
+    }
+
+    pub fn clear() -> Result<(), String> {
+        let mut error: *mut c_char = std::ptr::null_mut();
+        if unsafe { spotlight_clear_items(&mut error) } {
+            Ok(())
+        } else {
+            Err(unsafe { take_error(error, "Failed to clear Spotlight index") })
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::AppHandle;
+
+    use super::IndexedItem;
+
+    pub const SUPPORTED: bool = true;
+
+    /// Start menu folder for entry shortcuts. Windows Search indexes the Start
+    /// menu, and opening an Internet Shortcut launches its `trackthething://` URL.
+    fn shortcuts_dir() -> Result<PathBuf, String> {
+        dirs::data_dir()
+            .map(|dir| dir.join("Microsoft/Windows/Start Menu/Programs/Track the Thing/Entries"))
+            .ok_or_else(|| "Failed to resolve the Start menu folder".to_string())
+    }
+
+    fn file_name(title: &str) -> String {
+        let name: String = title
+            .chars()
+            .map(|ch| if "\\/:*?\"<>|".contains(ch) || ch.is_control() { '_' } else { ch })
+            .take(80)
+            .collect();
+        name.trim().trim_end_matches('.').to_string()
+    }
+
+    pub fn init(_app: &AppHandle) {}
+
+    pub fn replace(items: &[IndexedItem]) -> Result<(), String> {
+        clear()?;
+        let dir = shortcuts_dir()?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create shortcuts folder: {}", e))?;
+        for item in items {
+            // The entry id keeps names unique when titles repeat
+            let id = item.url.rsplit('/').next().unwrap_or_default();
+            let path = dir.join(format!("{} ({}).url", file_name(&item.title), id));
+            let content = format!("[InternetShortcut]\r\nURL={}\r\n", item.url);
+            fs::write(&path, content).map_err(|e| format!("Failed to write shortcut: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn clear() -> Result<(), String> {
+        let dir = shortcuts_dir()?;
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove shortcuts: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use tauri::AppHandle;
+
+    use super::IndexedItem;
+
+    pub const SUPPORTED: bool = false;
+
+    pub fn init(_app: &AppHandle) {}
+
+    pub fn replace(_items: &[IndexedItem]) -> Result<(), String> {
+        Err("System search indexing is not supported on this platform".to_string())
+    }
+
+    pub fn clear() -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl SystemSearchIndexer {
+    fn load(app: &AppHandle, config: DesktopConfig) -> Self {
+        let prefs_path = app.path().app_config_dir().ok().map(|dir| dir.join(PREFS_FILE));
+        let prefs: SearchIndexPrefs = prefs_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            enabled: AtomicBool::new(prefs.enabled && platform::SUPPORTED),
+            indexed_count: AtomicUsize::new(0),
+            last_indexed_at: Mutex::new(None),
+            wake: Notify::new(),
+            config,
+            prefs_path,
+        }
+    }
+
+    fn save_prefs(&self, enabled: bool) -> Result<(), String> {
+        let path = self.prefs_path.as_ref().ok_or("Failed to resolve app config directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&SearchIndexPrefs { enabled })
+            .map_err(|e| format!("Failed to serialize search index preferences: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write search index preferences: {}", e))
+    }
+
+    fn status(&self) -> SearchIndexStatus {
+        SearchIndexStatus {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            supported: platform::SUPPORTED,
+            indexed_count: self.indexed_count.load(Ordering::Relaxed),
+            last_indexed_at: self.last_indexed_at.lock().ok().and_then(|at| *at),
+        }
+    }
+
+    async fn reindex(&self) -> Result<usize, String> {
+        let config = self.config.clone();
+        let count = async_runtime::spawn_blocking(move || {
+            let items = fetch_recent_entries(&config)?;
+            platform::replace(&items)?;
+            Ok::<_, String>(items.len())
+        })
+        .await
+        .map_err(|e| format!("Indexing task panicked: {}", e))??;
+
+        self.indexed_count.store(count, Ordering::Relaxed);
+        if let Ok(mut at) = self.last_indexed_at.lock() {
+            *at = Some(Utc::now());
+        }
+        info!("Indexed {} entries for system search", count);
+        Ok(count)
+    }
+}
+
+/// Start the periodic indexer. Nothing is written until indexing is enabled.
+pub fn init_system_search(app: &AppHandle, config: DesktopConfig) {
+    platform::init(app);
+    app.manage(SystemSearchIndexer::load(app, config));
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            let indexer = app.state::<SystemSearchIndexer>();
+            if indexer.enabled.load(Ordering::Relaxed) {
+                // The backend may still be starting, the next pass retries
+                if let Err(e) = indexer.reindex().await {
+                    warn!("System search indexing failed: {}", e);
+                }
+            }
+            let _ = tokio::time::timeout(REINDEX_INTERVAL, indexer.wake.notified()).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_system_search_indexing(
+    indexer: State<'_, SystemSearchIndexer>,
+) -> Result<SearchIndexStatus, String> {
+    Ok(indexer.status())
+}
+
+/// Turn indexing on (indexes right away) or off (removes indexed entries)
+#[tauri::command]
+pub async fn set_system_search_indexing(
+    indexer: State<'_, SystemSearchIndexer>,
+    enabled: bool,
+) -> Result<SearchIndexStatus, String> {
+    if enabled && !platform::SUPPORTED {
+        return Err("System search indexing is not supported on this platform".to_string());
+    }
+    indexer.save_prefs(enabled)?;
+    indexer.enabled.store(enabled, Ordering::Relaxed);
+
+    if enabled {
+        indexer.wake.notify_one();
+    } else {
+        async_runtime::spawn_blocking(platform::clear)
+            .await
+            .map_err(|e| format!("Indexing task panicked: {}", e))??;
+        indexer.indexed_count.store(0, Ordering::Relaxed);
+        info!("System search index cleared");
+    }
+    Ok(indexer.status())
+}
+
+/// Refresh the index now, e.g. after a batch of edits. Returns the number of entries indexed.
+#[tauri::command]
+pub async fn reindex_system_search(indexer: State<'_, SystemSearchIndexer>) -> Result<usize, String> {
+    if !indexer.enabled.load(Ordering::Relaxed) {
+        return Err("System search indexing is not enabled".to_string());
+    }
+    indexer.reindex().await
+}
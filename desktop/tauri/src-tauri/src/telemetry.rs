@@ -0,0 +1,319 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{async_runtime, AppHandle, Manager, State};
+use tokio::time::sleep;
+
+use crate::network::NetworkMonitor;
+use crate::prefs;
+use crate::settings::SettingsState;
+
+/// Collector the release pipeline points telemetry at. Builds made without it
+/// (local and CI builds) still queue events for the preview but never send them.
+const ENDPOINT: Option<&str> = option_env!("TAURI_TELEMETRY_ENDPOINT");
+
+const QUEUE_FILE: &str = "telemetry.json";
+
+/// Index `n` upgrades a schema `n` telemetry.json to `n + 1`
+const QUEUE_MIGRATIONS: &[prefs::Migration] = &[
+    // 0 -> 1: first versioned layout
+    Ok,
+];
+
+/// Oldest events are dropped beyond this while uploads keep failing
+const MAX_EVENTS: usize = 500;
+
+/// A batch is sent at most this often
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Retry delay after a failed upload, doubling up to `MAX_BACKOFF`
+const MIN_BACKOFF: Duration = Duration::from_secs(5 * 60);
+const MAX_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Longest feature name accepted from the webview
+const MAX_FEATURE_LEN: usize = 40;
+
+/// One use of a feature. Deliberately coarse: no content, no identifiers and
+/// only the day it happened.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryEvent {
+    /// e.g. `photo_capture`
+    pub feature: String,
+    /// Bucket such as `1-5m` for timed features (recordings, speech sessions)
+    pub duration: Option<String>,
+    pub day: NaiveDate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct TelemetryQueue {
+    schema_version: u32,
+    events: Vec<TelemetryEvent>,
+}
+
+/// The request body, exactly as uploaded
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Batch<'a> {
+    app_version: String,
+    os: &'static str,
+    arch: &'static str,
+    events: &'a [TelemetryEvent],
+}
+
+struct Schedule {
+    next_upload: DateTime<Utc>,
+    backoff: Duration,
+    last_upload: Option<DateTime<Utc>>,
+}
+
+pub struct TelemetryState {
+    path: PathBuf,
+    queue: Mutex<TelemetryQueue>,
+    schedule: Mutex<Schedule>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryPreview {
+    pub enabled: bool,
+    /// Where batches go; `None` in builds that never upload
+    pub endpoint: Option<String>,
+    pub pending_events: usize,
+    /// The JSON body the next upload would send
+    pub payload: Value,
+    pub next_upload: Option<DateTime<Utc>>,
+    pub last_upload: Option<DateTime<Utc>>,
+}
+
+fn endpoint() -> Option<&'static str> {
+    ENDPOINT.filter(|url| !url.trim().is_empty())
+}
+
+fn duration_bucket(duration: Duration) -> &'static str {
+    match duration.as_secs() {
+        0..=9 => "<10s",
+        10..=59 => "10-60s",
+        60..=299 => "1-5m",
+        300..=899 => "5-15m",
+        900..=3599 => "15-60m",
+        _ => ">60m",
+    }
+}
+
+fn batch<'a>(app: &AppHandle, events: &'a [TelemetryEvent]) -> Batch<'a> {
+    Batch {
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        events,
+    }
+}
+
+impl TelemetryState {
+    fn save(&self, queue: &TelemetryQueue) {
+        let result = serde_json::to_string_pretty(queue)
+            .map_err(|e| format!("Failed to serialize {}: {}", QUEUE_FILE, e))
+            .and_then(|json| prefs::write_atomic(&self.path, &json));
+        if let Err(e) = result {
+            warn!("Failed to save telemetry queue: {}", e);
+        }
+    }
+
+    fn push(&self, event: TelemetryEvent) {
+        let Ok(mut queue) = self.queue.lock() else {
+            return;
+        };
+        queue.events.push(event);
+        let excess = queue.events.len().saturating_sub(MAX_EVENTS);
+        queue.events.drain(..excess);
+        self.save(&queue);
+    }
+
+    fn clear(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            if queue.events.is_empty() {
+                return;
+            }
+            queue.events.clear();
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn load_queue(path: &Path) -> TelemetryQueue {
+    let mut queue = match prefs::load_versioned(path, QUEUE_MIGRATIONS, None) {
+        Ok(Some(value)) => serde_json::from_value(value).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", QUEUE_FILE, e);
+            TelemetryQueue::default()
+        }),
+        Ok(None) => TelemetryQueue::default(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", QUEUE_FILE, e);
+            TelemetryQueue::default()
+        }
+    };
+    queue.schema_version = QUEUE_MIGRATIONS.len() as u32;
+    queue
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<SettingsState>()
+        .is_some_and(|settings| settings.current().privacy.telemetry)
+}
+
+/// Note that `feature` was used, if the user opted in. `duration` is only kept
+/// as a bucket.
+pub fn record(app: &AppHandle, feature: &str, duration: Option<Duration>) {
+    if !enabled(app) {
+        return;
+    }
+    let Some(state) = app.try_state::<TelemetryState>() else {
+        return;
+    };
+    debug!("Telemetry: {}", feature);
+    state.push(TelemetryEvent {
+        feature: feature.to_string(),
+        duration: duration.map(|duration| duration_bucket(duration).to_string()),
+        day: Utc::now().date_naive(),
+    });
+}
+
+/// Follow the `privacy.telemetry` setting. Opting out discards whatever was
+/// queued.
+pub fn set_enabled(app: &AppHandle, enabled: bool) {
+    if !enabled {
+        if let Some(state) = app.try_state::<TelemetryState>() {
+            state.clear();
+        }
+    }
+}
+
+/// Send the queued events. Events recorded meanwhile stay queued.
+async fn upload(app: &AppHandle, state: &TelemetryState, url: &'static str) -> Result<usize, String> {
+    let events = state.queue.lock().map_err(|e| format!("Lock error: {}", e))?.events.clone();
+    let body = serde_json::to_value(batch(app, &events)).map_err(|e| format!("Failed to serialize batch: {}", e))?;
+    async_runtime::spawn_blocking(move || {
+        ureq::post(url)
+            .timeout(Duration::from_secs(30))
+            .send_json(body)
+            .map(|_| ())
+            .map_err(|e| format!("Telemetry upload failed: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Telemetry upload task failed: {}", e))??;
+
+    let mut queue = state.queue.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let sent = events.len().min(queue.events.len());
+    queue.events.drain(..sent);
+    state.save(&queue);
+    Ok(sent)
+}
+
+pub fn init_telemetry(app: &AppHandle) {
+    let path = match app.path().app_local_data_dir() {
+        Ok(dir) => dir.join(QUEUE_FILE),
+        Err(e) => {
+            warn!("Telemetry disabled, app data directory unavailable: {}", e);
+            return;
+        }
+    };
+    app.manage(TelemetryState {
+        queue: Mutex::new(load_queue(&path)),
+        path,
+        schedule: Mutex::new(Schedule {
+            next_upload: Utc::now() + UPLOAD_INTERVAL,
+            backoff: MIN_BACKOFF,
+            last_upload: None,
+        }),
+    });
+    if !enabled(app) {
+        app.state::<TelemetryState>().clear();
+    }
+
+    let Some(url) = endpoint() else {
+        info!("Telemetry uploads are not configured in this build");
+        return;
+    };
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(TICK).await;
+            let state = app.state::<TelemetryState>();
+            let due = state
+                .schedule
+                .lock()
+                .map(|schedule| Utc::now() >= schedule.next_upload)
+                .unwrap_or(false);
+            let pending = state.queue.lock().map(|queue| !queue.events.is_empty()).unwrap_or(false);
+            let online = app.try_state::<NetworkMonitor>().map_or(true, |monitor| monitor.is_online());
+            if !due || !pending || !online || !enabled(&app) {
+                continue;
+            }
+
+            let result = upload(&app, &state, url).await;
+            let Ok(mut schedule) = state.schedule.lock() else {
+                continue;
+            };
+            match result {
+                Ok(sent) => {
+                    debug!("Uploaded {} telemetry event(s)", sent);
+                    schedule.backoff = MIN_BACKOFF;
+                    schedule.last_upload = Some(Utc::now());
+                    schedule.next_upload = Utc::now() + UPLOAD_INTERVAL;
+                }
+                Err(e) => {
+                    warn!("{}; retrying in {} min", e, schedule.backoff.as_secs() / 60);
+                    schedule.next_upload = Utc::now() + schedule.backoff;
+                    schedule.backoff = (schedule.backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    });
+}
+
+/// Exactly what the next upload would send, whether or not telemetry is on
+#[tauri::command]
+pub async fn get_telemetry_preview(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    state: State<'_, TelemetryState>,
+) -> Result<TelemetryPreview, String> {
+    let enabled = settings.current().privacy.telemetry;
+    let events = state.queue.lock().map_err(|e| format!("Lock error: {}", e))?.events.clone();
+    let payload = serde_json::to_value(batch(&app, &events)).map_err(|e| format!("Failed to serialize batch: {}", e))?;
+    let schedule = state.schedule.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(TelemetryPreview {
+        enabled,
+        endpoint: endpoint().map(str::to_string),
+        pending_events: events.len(),
+        payload,
+        next_upload: (enabled && endpoint().is_some() && !events.is_empty()).then_some(schedule.next_upload),
+        last_upload: schedule.last_upload,
+    })
+}
+
+/// Record use of a webview feature. Names are short `snake_case` identifiers;
+/// anything else is rejected so free text can't end up in a batch.
+#[tauri::command]
+pub async fn record_feature_use(app: AppHandle, feature: String, duration_ms: Option<u64>) -> Result<(), String> {
+    let valid = !feature.is_empty()
+        && feature.len() <= MAX_FEATURE_LEN
+        && feature.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(format!("Invalid feature name: {:?}", feature));
+    }
+    record(&app, &feature, duration_ms.map(Duration::from_millis));
+    Ok(())
+}
@@ -0,0 +1,288 @@
+//! Timekeeping for the running timer. The shell keeps the time rather than
+//! the webview, so a webview reload or a backend restart doesn't lose a
+//! running timer. The timer is kept in the data dir as start and pause
+//! timestamps; the elapsed time is always derived from them, so a crash or
+//! quit loses nothing either. Idle detection pauses a running timer as of
+//! the last input and resumes it when the user is back.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter};
+use tokio::time::sleep;
+
+use crate::locale::{tr, Text};
+use crate::{data_dir, prefs, tray};
+
+/// Hidden, so it isn't mistaken for user data
+pub(crate) const STATE_FILE: &str = ".timer.json";
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The active timer, mirrored to `STATE_FILE` on every change
+static TIMER: Mutex<Option<ActiveTimer>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PauseReason {
+    /// `pause_timer`
+    User,
+    /// No input for the idle threshold
+    Idle,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ActiveTimer {
+    entry_id: i64,
+    started_at: DateTime<Utc>,
+    /// Time counted in earlier runs, before the last pause
+    accumulated_ms: u64,
+    /// Start of the current run; `None` while paused
+    running_since: Option<DateTime<Utc>>,
+    paused_reason: Option<PauseReason>,
+}
+
+impl ActiveTimer {
+    fn elapsed_ms(&self, now: DateTime<Utc>) -> u64 {
+        let current = self
+            .running_since
+            .map(|since| (now - since).num_milliseconds().max(0) as u64)
+            .unwrap_or(0);
+        self.accumulated_ms + current
+    }
+
+    /// Stop counting as of `at`
+    fn pause(&mut self, at: DateTime<Utc>, reason: PauseReason) {
+        if let Some(since) = self.running_since.take() {
+            self.accumulated_ms += (at.max(since) - since).num_milliseconds() as u64;
+        }
+        self.paused_reason = Some(reason);
+    }
+
+    fn resume(&mut self, now: DateTime<Utc>) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+            self.paused_reason = None;
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimerStatus {
+    Running,
+    Paused,
+    Stopped,
+}
+
+/// Returned by the timer commands and the payload of `timer-changed` and
+/// `timer-tick`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerSnapshot {
+    pub entry_id: i64,
+    pub status: TimerStatus,
+    pub started_at: DateTime<Utc>,
+    pub elapsed_ms: u64,
+    pub paused_reason: Option<PauseReason>,
+}
+
+fn snapshot(timer: &ActiveTimer, status: TimerStatus) -> TimerSnapshot {
+    TimerSnapshot {
+        entry_id: timer.entry_id,
+        status,
+        started_at: timer.started_at,
+        elapsed_ms: timer.elapsed_ms(Utc::now()),
+        paused_reason: timer.paused_reason,
+    }
+}
+
+fn current_status(timer: &ActiveTimer) -> TimerStatus {
+    if timer.running_since.is_some() {
+        TimerStatus::Running
+    } else {
+        TimerStatus::Paused
+    }
+}
+
+fn state_path(app: &AppHandle) -> PathBuf {
+    data_dir::current_data_dir(app).join(STATE_FILE)
+}
+
+fn save(app: &AppHandle, timer: Option<&ActiveTimer>) -> Result<(), String> {
+    let path = state_path(app);
+    match timer {
+        Some(timer) => {
+            let json = serde_json::to_string_pretty(timer)
+                .map_err(|e| format!("Failed to serialize {}: {}", STATE_FILE, e))?;
+            prefs::write_atomic(&path, &json)
+        }
+        None => match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+        },
+    }
+}
+
+fn emit_changed(app: &AppHandle, snapshot: &TimerSnapshot) {
+    if let Err(e) = app.emit("timer-changed", snapshot) {
+        warn!("Failed to emit timer-changed event: {}", e);
+    }
+}
+
+/// Apply `change` to the active timer, save it and announce the result
+fn update<F>(app: &AppHandle, change: F) -> Result<TimerSnapshot, String>
+where
+    F: FnOnce(&mut ActiveTimer) -> Result<(), String>,
+{
+    let mut guard = TIMER.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let timer = guard.as_mut().ok_or("No timer is running")?;
+    change(timer)?;
+    save(app, Some(timer))?;
+    let snapshot = snapshot(timer, current_status(timer));
+    emit_changed(app, &snapshot);
+    Ok(snapshot)
+}
+
+/// Pause a running timer as of the user's last input. Called by the idle
+/// monitor when the user goes idle.
+pub(crate) fn on_idle(app: &AppHandle, idle_seconds: u64) {
+    let last_input = Utc::now() - chrono::Duration::seconds(idle_seconds as i64);
+    let result = update(app, |timer| {
+        if timer.running_since.is_none() {
+            return Err("Timer already paused".to_string());
+        }
+        timer.pause(last_input, PauseReason::Idle);
+        Ok(())
+    });
+    if let Ok(snapshot) = result {
+        info!("Timer of entry {} paused while idle", snapshot.entry_id);
+    }
+}
+
+/// Resume a timer the idle monitor paused. Called when input resumes.
+pub(crate) fn on_active(app: &AppHandle) {
+    let result = update(app, |timer| {
+        if timer.paused_reason != Some(PauseReason::Idle) {
+            return Err("Timer not paused for idleness".to_string());
+        }
+        timer.resume(Utc::now());
+        Ok(())
+    });
+    if let Ok(snapshot) = result {
+        info!("Timer of entry {} resumed", snapshot.entry_id);
+    }
+}
+
+/// The running timer's time, for the tray
+pub(crate) fn tray_status() -> Option<String> {
+    let guard = TIMER.lock().ok()?;
+    let timer = guard.as_ref().filter(|timer| timer.running_since.is_some())?;
+    let secs = timer.elapsed_ms(Utc::now()) / 1000;
+    Some(format!("{} {}", tr(Text::Timer), tray::format_duration(secs)))
+}
+
+/// Restore the timer saved in the data dir and emit `timer-tick` every
+/// second while it runs
+pub fn init_timer(app: &AppHandle) {
+    let path = state_path(app);
+    match fs::read_to_string(&path) {
+        Ok(text) => match serde_json::from_str::<ActiveTimer>(&text) {
+            Ok(timer) => {
+                info!("Restored timer of entry {}", timer.entry_id);
+                if let Ok(mut guard) = TIMER.lock() {
+                    *guard = Some(timer);
+                }
+            }
+            Err(e) => warn!("Failed to parse {}: {}", STATE_FILE, e),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to read {}: {}", path.display(), e),
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        loop {
+            sleep(TICK_INTERVAL).await;
+            let tick = TIMER.lock().ok().and_then(|guard| {
+                guard
+                    .as_ref()
+                    .filter(|timer| timer.running_since.is_some())
+                    .map(|timer| snapshot(timer, TimerStatus::Running))
+            });
+            if let Some(tick) = tick {
+                let _ = app.emit("timer-tick", tick);
+            }
+        }
+    });
+}
+
+/// Start timing `entry_id`, or resume its paused timer. Fails while another
+/// entry's timer is active.
+#[tauri::command]
+pub async fn start_timer(app: AppHandle, entry_id: i64) -> Result<TimerSnapshot, String> {
+    {
+        let mut guard = TIMER.lock().map_err(|e| format!("Lock error: {}", e))?;
+        match guard.as_ref() {
+            Some(timer) if timer.entry_id != entry_id => {
+                return Err(format!("Stop the timer of entry {} first", timer.entry_id));
+            }
+            Some(_) => {}
+            None => {
+                let now = Utc::now();
+                let timer = ActiveTimer {
+                    entry_id,
+                    started_at: now,
+                    accumulated_ms: 0,
+                    running_since: Some(now),
+                    paused_reason: None,
+                };
+                save(&app, Some(&timer))?;
+                let snapshot = snapshot(&timer, TimerStatus::Running);
+                *guard = Some(timer);
+                info!("Timer started for entry {}", entry_id);
+                emit_changed(&app, &snapshot);
+                return Ok(snapshot);
+            }
+        }
+    }
+    update(&app, |timer| {
+        timer.resume(Utc::now());
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn pause_timer(app: AppHandle) -> Result<TimerSnapshot, String> {
+    update(&app, |timer| {
+        timer.pause(Utc::now(), PauseReason::User);
+        Ok(())
+    })
+}
+
+/// Stop the timer, returning the time it counted so it can be saved to the entry
+#[tauri::command]
+pub async fn stop_timer(app: AppHandle) -> Result<TimerSnapshot, String> {
+    let mut guard = TIMER.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let timer = guard.as_ref().ok_or("No timer is running")?;
+    let snapshot = snapshot(timer, TimerStatus::Stopped);
+    save(&app, None)?;
+    *guard = None;
+    info!("Timer of entry {} stopped after {}s", snapshot.entry_id, snapshot.elapsed_ms / 1000);
+    emit_changed(&app, &snapshot);
+    Ok(snapshot)
+}
+
+/// The active timer, if any
+#[tauri::command]
+pub async fn get_timer() -> Result<Option<TimerSnapshot>, String> {
+    let guard = TIMER.lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(guard.as_ref().map(|timer| snapshot(timer, current_status(timer))))
+}
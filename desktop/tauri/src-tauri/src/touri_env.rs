@@ -0,0 +1,286 @@
+//! Checks `.tourienv` when it is loaded in debug builds. Overrides that
+//! `Settings::apply_env_overrides` ignores or clamps, misspelled keys and lines
+//! dotenv can't parse are logged with their line number and kept for
+//! `get_effective_config()`, instead of quietly leaving the default in use.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::{warn, LevelFilter};
+use serde::Serialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::idle::MIN_THRESHOLD_SECS;
+
+/// What a known key must hold. Empty values are always allowed; they leave
+/// the setting at its default.
+enum Rule {
+    Text,
+    Port,
+    /// Clamped into the range
+    Whole(u64, u64),
+    /// Clamped into the range
+    Number(f64, f64),
+    /// Ignored unless greater than the minimum
+    Above(f64),
+    Bool,
+    AbsolutePath,
+    HttpUrl,
+    Probes,
+    LogLevel,
+    LogFilter,
+}
+
+/// Every variable the shell or the backend launcher reads from `.tourienv`
+const KEYS: &[(&str, Rule)] = &[
+    ("TAURI_BACKEND_HOST", Rule::Text),
+    ("TAURI_BACKEND_PORT", Rule::Port),
+    ("PYINSTALLER_ENTRYPOINT", Rule::Text),
+    ("TAURI_REMOTE_BACKEND_URL", Rule::HttpUrl),
+    ("TAURI_HEALTH_INTERVAL_SECS", Rule::Whole(5, 3600)),
+    ("TAURI_WINDOW_HEIGHT_RATIO", Rule::Number(0.5, 0.98)),
+    ("TAURI_WINDOW_WIDTH", Rule::Above(320.0)),
+    ("TAURI_WINDOW_MAXIMIZED", Rule::Bool),
+    ("TAURI_WINDOW_MIN_WIDTH", Rule::Number(320.0, 4096.0)),
+    ("TAURI_WINDOW_MIN_HEIGHT", Rule::Number(320.0, 4096.0)),
+    ("TAURI_SPLASH_MIN_VISIBLE_MS", Rule::Whole(0, 10_000)),
+    ("TAURI_DESKTOP_DATA_DIR", Rule::AbsolutePath),
+    ("TAURI_DATABASE_PATH", Rule::Text),
+    ("TAURI_UPLOADS_DIR", Rule::Text),
+    ("TAURI_STATIC_DIR", Rule::Text),
+    ("TAURI_BACKEND_LOG", Rule::Text),
+    ("TAURI_IDLE_THRESHOLD_SECS", Rule::Whole(MIN_THRESHOLD_SECS, u64::MAX)),
+    ("TAURI_NETWORK_PROBES", Rule::Probes),
+    ("TAURI_LOG_LEVEL", Rule::LogLevel),
+    ("TAURI_LOG_FILTER", Rule::LogFilter),
+    ("DOCKER_HOST", Rule::Text),
+];
+
+/// Unknown keys this close to a known one are reported as likely typos
+const MAX_TYPO_DISTANCE: usize = 3;
+
+static ISSUES: Mutex<Vec<TouriEnvIssue>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The line or value is not used at all
+    Error,
+    /// Used, but probably not as intended (clamped, unknown or repeated)
+    Warning,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TouriEnvIssue {
+    /// 1-based; `None` when the file couldn't be read
+    pub line: Option<usize>,
+    pub key: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn issue(line: Option<usize>, key: Option<&str>, severity: Severity, message: String) -> TouriEnvIssue {
+    TouriEnvIssue {
+        line,
+        key: key.map(str::to_string),
+        severity,
+        message,
+    }
+}
+
+/// The key assigned on `line`, if it is a `KEY=value` line
+fn line_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, _) = line.split_once('=')?;
+    let key = key.trim_end();
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    valid.then_some(key)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn suggestion(key: &str) -> Option<&'static str> {
+    let upper = key.to_ascii_uppercase();
+    KEYS.iter()
+        .map(|(known, _)| (edit_distance(&upper, known), *known))
+        .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Why `value` won't be used as written, if it won't
+fn check_value(rule: &Rule, value: &str) -> Option<(Severity, String)> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let ignored = |expected: &str| {
+        Some((Severity::Error, format!("{:?} is not {}, so it is ignored", value, expected)))
+    };
+    match rule {
+        Rule::Text => None,
+        Rule::Port => match value.parse::<u16>() {
+            Ok(0) | Err(_) => ignored("a port between 1 and 65535"),
+            Ok(_) => None,
+        },
+        Rule::Whole(min, max) => match value.parse::<u64>() {
+            Err(_) => ignored("a whole number"),
+            Ok(number) if number < *min || number > *max => {
+                let clamped = number.clamp(*min, *max);
+                Some((Severity::Warning, format!("{} is out of range, {} is used instead", number, clamped)))
+            }
+            Ok(_) => None,
+        },
+        Rule::Number(min, max) => match value.parse::<f64>() {
+            Err(_) => ignored("a number"),
+            Ok(number) if number < *min || number > *max => {
+                let clamped = number.clamp(*min, *max);
+                Some((Severity::Warning, format!("{} is out of range, {} is used instead", number, clamped)))
+            }
+            Ok(_) => None,
+        },
+        Rule::Above(min) => match value.parse::<f64>() {
+            Ok(number) if number > *min => None,
+            _ => ignored(&format!("a number greater than {}", min)),
+        },
+        Rule::Bool => match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "0" | "false" | "no" => None,
+            _ => Some((Severity::Warning, format!("{:?} is not true or false, so false is used", value))),
+        },
+        Rule::AbsolutePath if !Path::new(value).is_absolute() => {
+            let tilde = if value.starts_with('~') { " (`~` is not expanded)" } else { "" };
+            let message = format!("{:?} is not an absolute path{}, so the default data dir is used", value, tilde);
+            Some((Severity::Error, message))
+        }
+        Rule::AbsolutePath => None,
+        Rule::HttpUrl => match value.strip_prefix("http://").and_then(|rest| rest.split('/').next()) {
+            Some(host) if !host.is_empty() => None,
+            _ => {
+                let message = format!("{:?} is not an http://<host>[:port] URL, so the backend can't be reached", value);
+                Some((Severity::Error, message))
+            }
+        },
+        Rule::Probes => value
+            .split(',')
+            .map(str::trim)
+            .filter(|probe| !probe.is_empty())
+            .find(|probe| {
+                !probe
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port > 0))
+            })
+            .map(|probe| (Severity::Warning, format!("{:?} is not host:port; the probe will always fail", probe))),
+        Rule::LogLevel => match value.parse::<LevelFilter>() {
+            Ok(_) => None,
+            Err(_) => ignored("a log level"),
+        },
+        Rule::LogFilter => EnvFilter::try_new(value)
+            .err()
+            .map(|e| (Severity::Error, format!("{:?} is not a valid log filter ({}), so it is ignored", value, e))),
+    }
+}
+
+fn validate(path: &Path) -> Vec<TouriEnvIssue> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return vec![issue(None, None, Severity::Error, format!("Failed to read {}: {}", path.display(), e))],
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut issues = Vec::new();
+
+    // Lines of each key, in file order, to place the parsed values
+    let mut key_lines: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(key) = line_key(line) {
+            key_lines.entry(key).or_default().push(index + 1);
+        }
+    }
+    for (key, numbers) in &key_lines {
+        if let [first, .., last] = numbers.as_slice() {
+            let message = format!("{} is also set on line {}; only one of them is used", key, first);
+            issues.push(issue(Some(*last), Some(key), Severity::Warning, message));
+        }
+    }
+
+    let parsed = match dotenvy::from_path_iter(path) {
+        Ok(parsed) => parsed,
+        Err(e) => return vec![issue(None, None, Severity::Error, format!("Failed to read {}: {}", path.display(), e))],
+    };
+    let mut next_line: HashMap<String, usize> = HashMap::new();
+    for item in parsed {
+        match item {
+            Ok((key, value)) => {
+                let seen = next_line.entry(key.clone()).or_default();
+                let line = key_lines.get(key.as_str()).and_then(|numbers| numbers.get(*seen)).copied();
+                *seen += 1;
+                match KEYS.iter().find(|(known, _)| *known == key) {
+                    Some((_, rule)) => {
+                        if let Some((severity, message)) = check_value(rule, &value) {
+                            issues.push(issue(line, Some(&key), severity, format!("{}: {}", key, message)));
+                        }
+                    }
+                    None => {
+                        let message = match suggestion(&key) {
+                            Some(known) => format!("Unknown key {}; did you mean {}?", key, known),
+                            None => format!("Unknown key {}; nothing reads it", key),
+                        };
+                        issues.push(issue(line, Some(&key), Severity::Warning, message));
+                    }
+                }
+            }
+            Err(dotenvy::Error::LineParse(text, column)) => {
+                let first = text.lines().next().unwrap_or_default().trim();
+                let line = lines
+                    .iter()
+                    .position(|line| !first.is_empty() && line.trim() == first)
+                    .map(|index| index + 1);
+                let message = format!(
+                    "Can't parse {:?} at column {}; this line and the ones after it aren't loaded",
+                    first,
+                    column + 1
+                );
+                issues.push(issue(line, line_key(first), Severity::Error, message));
+            }
+            Err(e) => issues.push(issue(None, None, Severity::Error, e.to_string())),
+        }
+    }
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
+
+/// Check `.tourienv` before it is loaded (or reloaded), log what's wrong and
+/// keep it for `issues()`
+pub fn check(path: &Path) {
+    let found = validate(path);
+    for issue in &found {
+        match issue.line {
+            Some(line) => warn!(".tourienv line {}: {}", line, issue.message),
+            None => warn!(".tourienv: {}", issue.message),
+        }
+    }
+    if let Ok(mut issues) = ISSUES.lock() {
+        *issues = found;
+    }
+}
+
+/// Problems found the last time `.tourienv` was loaded
+pub fn issues() -> Vec<TouriEnvIssue> {
+    ISSUES.lock().map(|issues| issues.clone()).unwrap_or_default()
+}
@@ -0,0 +1,224 @@
+//! Transcripts of recordings, made in the background with whisper.cpp so
+//! recorded notes become searchable text. With `speech.transcribeRecordings`
+//! on, every finished audio, video and screen recording (and each segment
+//! of a split one) is queued as a low-priority job; `transcribe_media`
+//! queues any file at once. The sound is converted to the 16 kHz mono WAV
+//! whisper.cpp reads, and the transcript is written under `transcripts/` as
+//! WebVTT and plain text, recorded in the media manifest and announced with
+//! `transcript-ready`.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::capture::CaptureKind;
+use crate::capture_names;
+use crate::health::find_on_path;
+use crate::jobs::{self, CancelToken, JobId, JobKind, Priority, Progress};
+use crate::media_manifest;
+use crate::media_store::{entry_of, get_capture_dir, probe, validate_app_path};
+use crate::settings::SettingsState;
+
+/// Where transcripts are written, under the app data dir
+pub(crate) const TRANSCRIPTS_DIR: &str = "transcripts";
+
+/// Names the whisper.cpp command line tool is installed under
+const WHISPER_COMMANDS: &[&str] = &["whisper-cli", "whisper-cpp"];
+
+/// Result of a transcription job, and payload of `transcript-ready`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    /// The recording
+    pub source: String,
+    /// The WebVTT transcript; `None` when the file has no sound
+    pub vtt: Option<String>,
+    pub text: String,
+}
+
+/// Queue transcripts of finished recordings when
+/// `speech.transcribeRecordings` is on. Other kinds of capture are skipped.
+pub(crate) fn queue_recordings(app: &AppHandle, kind: CaptureKind, paths: &[PathBuf]) {
+    if !matches!(kind, CaptureKind::Audio | CaptureKind::Video | CaptureKind::Screencast) {
+        return;
+    }
+    if !app.state::<SettingsState>().current().speech.transcribe_recordings {
+        return;
+    }
+    for path in paths {
+        let source = path.clone();
+        if let Err(e) = jobs::enqueue(app, JobKind::Transcription { source }, Priority::Low) {
+            warn!(path = ?path, error = %e, "failed to queue the transcript");
+        }
+    }
+}
+
+/// whisper.cpp's language code for a BCP 47 tag, e.g. `en` for `en-US`
+fn language(locale: &str) -> String {
+    locale.split(['-', '_']).next().unwrap_or("auto").to_ascii_lowercase()
+}
+
+/// Percentage from whisper.cpp's `-pp` output, e.g.
+/// `whisper_print_progress_callback: progress =  40%`
+fn parse_progress(line: &str) -> Option<u64> {
+    let (_, rest) = line.split_once("progress =")?;
+    rest.trim().trim_end_matches('%').trim().parse().ok()
+}
+
+/// The sound of `source` as 16 kHz mono 16-bit WAV
+fn extract_audio(source: &Path, wav: &Path) -> Result<(), String> {
+    let ffmpeg = find_on_path("ffmpeg").ok_or("ffmpeg was not found on the PATH")?;
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(source)
+        .args(["-vn", "-ac", "1", "-ar", "16000", "-c:a", "pcm_s16le"])
+        .arg(wav)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Extracting the sound failed: {}", reason.trim()));
+    }
+    Ok(())
+}
+
+/// Run whisper.cpp over `wav`, writing `<base>.vtt` and `<base>.txt`
+fn run_whisper(
+    model: &Path,
+    language: &str,
+    wav: &Path,
+    base: &Path,
+    cancel: &CancelToken,
+    progress: &Progress,
+) -> Result<(), String> {
+    let whisper = WHISPER_COMMANDS
+        .iter()
+        .find_map(|name| find_on_path(name))
+        .ok_or("whisper.cpp (whisper-cli) was not found on the PATH")?;
+    let mut child = Command::new(whisper)
+        .arg("-m")
+        .arg(model)
+        .args(["-l", language, "-pp", "-ovtt", "-otxt", "-f"])
+        .arg(wav)
+        .arg("-of")
+        .arg(base)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start whisper.cpp: {}", e))?;
+
+    // Progress goes to stderr, with the rest of whisper.cpp's log
+    let mut log = Vec::new();
+    let stderr = child.stderr.take().ok_or("whisper.cpp has no output")?;
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(jobs::CANCELLED.to_string());
+        }
+        match parse_progress(&line) {
+            Some(percent) => progress.report(percent.min(100), 100),
+            None => log.push(line),
+        }
+    }
+    let status = child.wait().map_err(|e| format!("Failed to wait for whisper.cpp: {}", e))?;
+    if !status.success() {
+        let reason = log.iter().rev().take(5).rev().cloned().collect::<Vec<_>>().join("\n");
+        return Err(format!("whisper.cpp failed: {}", reason.trim()));
+    }
+    Ok(())
+}
+
+fn transcribe_file(
+    app: &AppHandle,
+    source: &Path,
+    cancel: &CancelToken,
+    progress: &Progress,
+) -> Result<Transcript, String> {
+    let settings = app.state::<SettingsState>().current();
+    let model = settings
+        .speech
+        .whisper_model
+        .clone()
+        .ok_or("Set speech.whisperModel to a whisper.cpp model to make transcripts")?;
+    if !model.is_file() {
+        return Err(format!("The whisper.cpp model {} doesn't exist", model.display()));
+    }
+    let source_name = source.to_string_lossy().to_string();
+    if probe(source)?.audio.is_none() {
+        info!(path = ?source, "no sound to transcribe");
+        return Ok(Transcript {
+            source: source_name,
+            vtt: None,
+            text: String::new(),
+        });
+    }
+
+    // Next to the transcripts of the recording's entry, like the recording.
+    // Recordings from different folders can share a stem, so a clash gets a
+    // suffix rather than overwriting the other transcript.
+    let dir = get_capture_dir(app, TRANSCRIPTS_DIR, entry_of(source))?;
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let stem = capture_names::unique_stem(&dir, &stem, &[".vtt", ".txt", ".16k.wav"])?;
+    let (base, wav) = (dir.join(&stem), dir.join(format!("{}.16k.wav", stem)));
+    let result = extract_audio(source, &wav).and_then(|()| {
+        cancel.check()?;
+        run_whisper(&model, &language(&settings.speech_locale()), &wav, &base, cancel, progress)
+    });
+    let _ = fs::remove_file(&wav);
+    result?;
+
+    // whisper.cpp appends the extensions to the base it was given
+    let vtt = dir.join(format!("{}.vtt", stem));
+    let text = fs::read_to_string(dir.join(format!("{}.txt", stem)))
+        .map_err(|e| format!("Failed to read the transcript: {}", e))?
+        .trim()
+        .to_string();
+    progress.report(100, 100);
+    info!(path = ?source, transcript = ?vtt, "recording transcribed");
+    media_manifest::attach_transcript(app, source, &vtt);
+    Ok(Transcript {
+        source: source_name,
+        vtt: Some(vtt.to_string_lossy().to_string()),
+        text,
+    })
+}
+
+/// Transcribe `source` as a job and announce the transcript with
+/// `transcript-ready`
+pub(crate) async fn transcribe(
+    app: &AppHandle,
+    source: PathBuf,
+    cancel: CancelToken,
+    progress: Progress,
+) -> Result<Transcript, String> {
+    let task_app = app.clone();
+    let transcript = async_runtime::spawn_blocking(move || transcribe_file(&task_app, &source, &cancel, &progress))
+        .await
+        .map_err(|e| format!("Transcription task failed: {}", e))??;
+    if transcript.vtt.is_some() {
+        if let Err(e) = app.emit("transcript-ready", transcript.clone()) {
+            warn!(error = %e, "failed to emit transcript-ready");
+        }
+    }
+    Ok(transcript)
+}
+
+/// Transcribe an audio or video file from the app's data directories now,
+/// whatever `speech.transcribeRecordings` says. Queues a high-priority job
+/// and returns its id; the job's result is `{ source, vtt, text }`.
+#[tauri::command]
+pub async fn transcribe_media(app: AppHandle, path: String) -> Result<JobId, String> {
+    let source = validate_app_path(&app, &path)?;
+    if !source.is_file() {
+        return Err(format!("{} is not a file", path));
+    }
+    jobs::enqueue(&app, JobKind::Transcription { source }, Priority::High)
+}
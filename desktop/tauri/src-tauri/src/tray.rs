@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::warn;
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{async_runtime, AppHandle, Manager, Wry};
+use tokio::time::sleep;
+
+use crate::capture::CaptureManager;
+use crate::locale::{tr, Text};
+use crate::{focus, focus_main_window, timer, BackendProcess, DesktopConfig};
+
+pub const TRAY_ID: &str = "main";
+
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Follows `general.trayStatus`
+static STATUS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let quit = MenuItem::with_id(app, "quit", tr(Text::Quit), true, None::<&str>)?;
+    // A headless app has no window to show
+    if app.state::<DesktopConfig>().headless {
+        return Menu::with_items(app, &[&quit]);
+    }
+    let show = MenuItem::with_id(app, "show", tr(Text::ShowApp), true, None::<&str>)?;
+    Menu::with_items(app, &[&show, &quit])
+}
+
+/// Rebuild the tray menu, e.g. after the app locale changed
+pub fn refresh_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = build_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        warn!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+/// `1:02:03`, or `02:03` under an hour
+pub(crate) fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Show `status` (e.g. a countdown) next to the tray icon and in its
+/// tooltip, or go back to just the app name. Only macOS and Linux show text
+/// next to the icon.
+fn set_status(app: &AppHandle, status: Option<&str>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let name = app.package_info().name.clone();
+    let tooltip = match status {
+        Some(status) => format!("{} – {}", name, status),
+        None => name,
+    };
+    if let Err(e) = tray.set_title(status).and_then(|()| tray.set_tooltip(Some(tooltip))) {
+        warn!("Failed to update tray status: {}", e);
+    }
+}
+
+/// Follow the `general.trayStatus` setting
+pub fn set_status_enabled(enabled: bool) {
+    STATUS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// What is running right now: the focus session, the timer and any
+/// recording, e.g. `Focus time 24:59 · Timer 1:02:03`
+fn current_status(app: &AppHandle) -> Option<String> {
+    let recording = app
+        .try_state::<CaptureManager>()
+        .and_then(|manager| manager.recording())
+        .map(|elapsed| format!("{} {}", tr(Text::Recording), format_duration(elapsed.as_secs())));
+    let parts: Vec<String> = [focus::tray_status(), timer::tray_status(), recording]
+        .into_iter()
+        .flatten()
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Refresh the status every second, touching the tray only when it changes
+fn run_status(app: AppHandle) {
+    async_runtime::spawn(async move {
+        let mut shown: Option<String> = None;
+        loop {
+            sleep(STATUS_INTERVAL).await;
+            let status = if STATUS_ENABLED.load(Ordering::Relaxed) {
+                current_status(&app)
+            } else {
+                None
+            };
+            if status != shown {
+                set_status(&app, status.as_deref());
+                shown = status;
+            }
+        }
+    });
+}
+
+/// Create the tray icon used to reopen the window when the app runs hidden
+pub fn init_tray(app: &AppHandle) -> tauri::Result<()> {
+    let menu = build_menu(app)?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(app.package_info().name.clone())
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show" => focus_main_window(app),
+            "quit" => {
+                app.state::<BackendProcess>().terminate();
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                focus_main_window(tray.app_handle());
+            }
+        });
+
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder.build(app)?;
+    run_status(app.clone());
+    Ok(())
+}
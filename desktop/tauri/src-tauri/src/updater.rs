@@ -0,0 +1,209 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::settings::{self, SettingsState};
+use crate::telemetry;
+use crate::BackendProcess;
+
+/// Minisign public key the release pipeline signs update bundles with. Builds
+/// made without it (local and CI builds) can't install updates.
+const PUBKEY: Option<&str> = option_env!("TAURI_UPDATER_PUBKEY");
+
+/// Release assets per channel. Beta builds are published to a rolling `beta`
+/// release so GitHub's "latest" keeps pointing at stable.
+const STABLE_RELEASE: &str = "https://github.com/ad-repo/track-the-thing/releases/latest/download";
+const BETA_RELEASE: &str = "https://github.com/ad-repo/track-the-thing/releases/download/beta";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    /// URL of the release asset `name` on this channel
+    pub fn asset_url(self, name: &str) -> String {
+        let release = match self {
+            UpdateChannel::Stable => STABLE_RELEASE,
+            UpdateChannel::Beta => BETA_RELEASE,
+        };
+        format!("{}/{}", release, name)
+    }
+}
+
+/// The update found by the last check, kept so installing doesn't fetch the
+/// manifest again
+#[derive(Default)]
+pub struct UpdaterState {
+    pending: Mutex<Option<Update>>,
+    installing: AtomicBool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub available: bool,
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    /// Version offered by the channel, if newer than the running one
+    pub version: Option<String>,
+    /// Release notes (markdown) from the manifest
+    pub changelog: Option<String>,
+    /// RFC 3339 publish date
+    pub date: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    bytes_done: u64,
+    bytes_total: Option<u64>,
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        warn!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+async fn check(app: &AppHandle, channel: UpdateChannel) -> Result<Option<Update>, String> {
+    let pubkey = PUBKEY
+        .filter(|key| !key.trim().is_empty())
+        .ok_or_else(|| "Updates are not available in this build".to_string())?;
+    let endpoint = channel
+        .asset_url("latest.json")
+        .parse()
+        .map_err(|e| format!("Invalid update endpoint: {}", e))?;
+
+    let app_for_exit = app.clone();
+    let updater = app
+        .updater_builder()
+        .pubkey(pubkey)
+        .endpoints(vec![endpoint])
+        .map_err(|e| format!("Invalid update endpoint: {}", e))?
+        // Windows installers quit the app themselves; don't leave the backend behind
+        .on_before_exit(move || app_for_exit.state::<BackendProcess>().terminate())
+        .build()
+        .map_err(|e| format!("Failed to set up the updater: {}", e))?;
+    updater
+        .check()
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))
+}
+
+/// Whether this build was signed for self-updates
+pub(crate) fn updates_available() -> bool {
+    PUBKEY.is_some_and(|key| !key.trim().is_empty())
+}
+
+pub fn init_updater(app: &AppHandle) {
+    app.manage(UpdaterState::default());
+}
+
+/// Ask the configured channel for a newer version. The result is remembered
+/// for `install_update`.
+#[tauri::command]
+pub async fn check_for_updates(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    updater: State<'_, UpdaterState>,
+) -> Result<UpdateInfo, String> {
+    let channel = settings.current().updates.channel;
+    let update = check(&app, channel).await?;
+
+    let info = UpdateInfo {
+        available: update.is_some(),
+        channel,
+        current_version: app.package_info().version.to_string(),
+        version: update.as_ref().map(|update| update.version.clone()),
+        changelog: update.as_ref().and_then(|update| update.body.clone()),
+        date: update
+            .as_ref()
+            .and_then(|update| update.raw_json.get("pub_date"))
+            .and_then(|date| date.as_str())
+            .map(str::to_string),
+    };
+    match &info.version {
+        Some(version) => info!("Update {} available on the {:?} channel", version, channel),
+        None => info!("No update available on the {:?} channel", channel),
+    }
+
+    if let Ok(mut pending) = updater.pending.lock() {
+        *pending = update;
+    }
+    Ok(info)
+}
+
+/// Download, verify and install the update found by `check_for_updates`.
+/// Emits `update-download-progress` while downloading and `update-ready` once
+/// the new version takes effect on the next start (see `restart_to_update`).
+#[tauri::command]
+pub async fn install_update(app: AppHandle, updater: State<'_, UpdaterState>) -> Result<String, String> {
+    let update = updater
+        .pending
+        .lock()
+        .map_err(|e| format!("Lock error: {}", e))?
+        .clone()
+        .ok_or_else(|| "No update to install; check for updates first".to_string())?;
+
+    if updater.installing.swap(true, Ordering::SeqCst) {
+        return Err("An update is already being installed".to_string());
+    }
+    info!("Downloading update {}", update.version);
+
+    let mut bytes_done = 0u64;
+    let result = update
+        .download_and_install(
+            |chunk, bytes_total| {
+                bytes_done += chunk as u64;
+                emit(&app, "update-download-progress", DownloadProgress { bytes_done, bytes_total });
+            },
+            || info!("Update {} downloaded, installing", update.version),
+        )
+        .await
+        .map_err(|e| format!("Failed to install update {}: {}", update.version, e));
+    updater.installing.store(false, Ordering::SeqCst);
+    result?;
+
+    if let Ok(mut pending) = updater.pending.lock() {
+        *pending = None;
+    }
+    info!("Update {} installed, ready to restart", update.version);
+    telemetry::record(&app, "app_update", None);
+    emit(&app, "update-ready", &update.version);
+    Ok(update.version)
+}
+
+/// Relaunch into the installed update
+#[tauri::command]
+pub async fn restart_to_update(app: AppHandle) -> Result<(), String> {
+    info!("Restarting to apply update");
+    app.state::<BackendProcess>().terminate();
+    app.restart()
+}
+
+/// Switch between the stable and beta release feeds. Moving back to stable
+/// doesn't downgrade; the next stable release newer than the running one is offered.
+#[tauri::command]
+pub async fn set_update_channel(
+    app: AppHandle,
+    settings: State<'_, SettingsState>,
+    updater: State<'_, UpdaterState>,
+    channel: UpdateChannel,
+) -> Result<UpdateChannel, String> {
+    settings::update(&app, &settings, |file| file.updates.channel = channel)?;
+
+    // A pending update came from the old channel
+    if let Ok(mut pending) = updater.pending.lock() {
+        *pending = None;
+    }
+    info!("Update channel set to {:?}", channel);
+    Ok(channel)
+}
@@ -0,0 +1,221 @@
+//! Keeps the instances of different OS users apart on a shared machine (fast
+//! user switching, a data dir under `/Users/Shared`). The data dir is locked
+//! for as long as the app runs with `.instance.lock`, which names the user
+//! holding it. When the configured data dir is locked by or only writable for
+//! another user, or the backend port is already taken, this launch uses a
+//! per-user data dir or port instead of sharing their database or talking to
+//! their backend. The switch is decided once per launch, is never saved to
+//! `settings.json` and shows up in `get_effective_config()` with the source
+//! `perUser`.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use fs2::FileExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+const LOCK_FILE: &str = ".instance.lock";
+
+/// Per-user ports start this far above the configured one at most
+const PORT_SPREAD: u16 = 500;
+/// Consecutive ports tried from there
+const PORT_ATTEMPTS: u16 = 20;
+
+static DECISION: OnceLock<Isolation> = OnceLock::new();
+
+/// Released when the process exits
+static LOCK: Mutex<Option<File>> = Mutex::new(None);
+
+/// What this launch switched to, with why
+#[derive(Default, Debug)]
+struct Isolation {
+    data_dir: Option<(PathBuf, String)>,
+    port: Option<(u16, String)>,
+}
+
+/// Contents of `.instance.lock`
+#[derive(Serialize, Deserialize, Debug)]
+struct Owner {
+    user: String,
+    pid: u32,
+    port: u16,
+}
+
+enum Claim {
+    /// Locked by this process
+    Claimed(File),
+    /// Locked by another instance of this user, or locking isn't possible
+    /// there; used as before
+    Shared,
+    /// Locked by or only writable for another user
+    Foreign(Option<Owner>),
+}
+
+fn current_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.trim().is_empty()))
+        .unwrap_or_else(|| "user".to_string())
+}
+
+fn owner_of(path: &Path) -> Option<Owner> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn claim(dir: &Path, user: &str) -> Claim {
+    if let Err(e) = fs::create_dir_all(dir) {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            return Claim::Foreign(None);
+        }
+        warn!("Failed to create {}: {}", dir.display(), e);
+        return Claim::Shared;
+    }
+    let path = dir.join(LOCK_FILE);
+    let file = match OpenOptions::new().read(true).write(true).create(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return Claim::Foreign(owner_of(&path)),
+        Err(e) => {
+            warn!("Failed to open {}: {}", path.display(), e);
+            return Claim::Shared;
+        }
+    };
+    match file.try_lock_exclusive() {
+        Ok(()) => Claim::Claimed(file),
+        Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => match owner_of(&path) {
+            Some(owner) if owner.user == user => Claim::Shared,
+            // Windows keeps others from reading a locked file, so no owner
+            // usually means another user
+            owner => Claim::Foreign(owner),
+        },
+        Err(e) => {
+            warn!("Failed to lock {}: {}", path.display(), e);
+            Claim::Shared
+        }
+    }
+}
+
+fn port_in_use(host: &str, port: u16) -> bool {
+    matches!(TcpListener::bind((host, port)), Err(e) if e.kind() == io::ErrorKind::AddrInUse)
+}
+
+/// A free port at an offset derived from the user name, so a user keeps
+/// getting the same one
+fn per_user_port(host: &str, configured: u16, user: &str) -> Option<u16> {
+    // FNV-1a
+    let hash = user
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3));
+    let offset = 1 + (hash % u64::from(PORT_SPREAD)) as u16;
+    (0..PORT_ATTEMPTS)
+        .filter_map(|attempt| configured.checked_add(offset + attempt))
+        .find(|&port| !port_in_use(host, port))
+}
+
+fn decide(settings: &Settings) -> Isolation {
+    let user = current_user();
+    let mut isolation = Isolation::default();
+    let configured = settings.data_dir();
+    let mut foreign_port = None;
+    let mut lock = None;
+
+    match claim(&configured, &user) {
+        Claim::Claimed(file) => lock = Some(file),
+        Claim::Shared => {}
+        Claim::Foreign(owner) => {
+            let reason = match &owner {
+                Some(owner) => format!("{} is in use by {}'s instance", configured.display(), owner.user),
+                None => format!("{} belongs to another user", configured.display()),
+            };
+            foreign_port = owner.map(|owner| owner.port);
+            let candidates = [
+                Settings::default().data_dir(),
+                PathBuf::from(format!("{}-{}", configured.display(), user)),
+            ];
+            for dir in candidates.into_iter().filter(|dir| *dir != configured) {
+                if let Claim::Claimed(file) = claim(&dir, &user) {
+                    warn!("{}; using {} for this launch", reason, dir.display());
+                    lock = Some(file);
+                    isolation.data_dir = Some((dir, reason.clone()));
+                    break;
+                }
+            }
+            if isolation.data_dir.is_none() {
+                warn!("{}, and no per-user data dir could be locked; sharing it", reason);
+            }
+        }
+    }
+
+    let (host, configured_port) = (settings.backend.host.as_str(), settings.backend.port);
+    let local = settings.backend.remote_url.is_none();
+    if local && port_in_use(host, configured_port) {
+        let reason = if foreign_port == Some(configured_port) {
+            format!("port {} is used by another user's instance", configured_port)
+        } else {
+            format!("port {} is already in use", configured_port)
+        };
+        match per_user_port(host, configured_port, &user) {
+            Some(port) => {
+                warn!("Backend {}; using port {} for this launch", reason, port);
+                isolation.port = Some((port, reason));
+            }
+            None => warn!("Backend {}, and no free per-user port was found", reason),
+        }
+    }
+
+    if let Some(mut file) = lock {
+        let owner = Owner {
+            user,
+            pid: std::process::id(),
+            port: isolation.port.as_ref().map_or(configured_port, |(port, _)| *port),
+        };
+        let written = serde_json::to_vec(&owner)
+            .map_err(io::Error::from)
+            .and_then(|json| file.set_len(0).and_then(|()| file.write_all(&json)));
+        if let Err(e) = written {
+            warn!("Failed to record the data dir owner: {}", e);
+        }
+        if let Ok(mut held) = LOCK.lock() {
+            *held = Some(file);
+        }
+    }
+    if isolation.data_dir.is_none() && isolation.port.is_none() {
+        info!("Data dir and backend port are free for this user");
+    }
+    isolation
+}
+
+/// Switch `settings` to the per-user data dir and port if the configured
+/// ones belong to another user. Decided on the first call; returns the keys
+/// (as in `settings::KNOBS`) replaced.
+pub fn apply(settings: &mut Settings) -> Vec<&'static str> {
+    let isolation = DECISION.get_or_init(|| decide(settings));
+    let mut applied = Vec::new();
+    if let Some((dir, _)) = &isolation.data_dir {
+        settings.media.data_dir = Some(dir.clone());
+        applied.push("media.dataDir");
+    }
+    if let Some((port, _)) = isolation.port {
+        settings.backend.port = port;
+        applied.push("backend.port");
+    }
+    applied
+}
+
+/// Why `key` was switched, for `get_effective_config()`
+pub fn reason(key: &str) -> Option<String> {
+    let isolation = DECISION.get()?;
+    match key {
+        "media.dataDir" => isolation.data_dir.as_ref().map(|(_, reason)| reason.clone()),
+        "backend.port" => isolation.port.as_ref().map(|(_, reason)| reason.clone()),
+        _ => None,
+    }
+}
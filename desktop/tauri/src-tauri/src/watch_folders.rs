@@ -0,0 +1,288 @@
+//! Watched folders, such as a scanner's output or the screenshots
+//! directory. New files that match a folder's rules are queued as `import`
+//! jobs, imported like files picked by the user (the `file` capture source)
+//! and announced as `watch-folder-ingested`, so the webview can attach or
+//! upload them. The
+//! rules are `media.watchFolders` in the settings; folders are polled, which
+//! works the same on every platform and for network shares.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tokio::time::sleep;
+
+use crate::capture::{self, CaptureOptions};
+use crate::jobs::{self, JobKind, Priority};
+use crate::settings::SettingsState;
+
+const STATE_FILE: &str = "watch_folders_state.json";
+
+/// Time between scans of the watched folders
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How often the scan times are saved when nothing was ingested
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Suffixes of files that are still being downloaded or written
+const PARTIAL_SUFFIXES: &[&str] = &[".part", ".partial", ".crdownload", ".download", ".tmp"];
+
+/// A folder to ingest new files from, an entry of `media.watchFolders`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchFolder {
+    pub path: PathBuf,
+    /// Extensions to ingest without the dot, e.g. `pdf`; empty takes every file
+    pub extensions: Vec<String>,
+    /// Only files whose name contains this, ignoring case
+    pub name_contains: Option<String>,
+    /// Delete the original once it has been imported
+    pub delete_after_import: bool,
+}
+
+impl WatchFolder {
+    fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy().to_lowercase()) else {
+            return false;
+        };
+        if name.starts_with('.') || PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+            return false;
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        (self.extensions.is_empty() || self.extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(&extension)))
+            && self
+                .name_contains
+                .as_ref()
+                .map_or(true, |part| name.contains(&part.to_lowercase()))
+    }
+}
+
+/// Check the rules of `media.watchFolders`
+pub(crate) fn validate(folders: &[WatchFolder]) -> Result<(), String> {
+    for (i, folder) in folders.iter().enumerate() {
+        if !folder.path.is_absolute() {
+            return Err(format!("Watched folder must be an absolute path: {}", folder.path.display()));
+        }
+        if folders[..i].iter().any(|other| other.path == folder.path) {
+            return Err(format!("Folder is watched twice: {}", folder.path.display()));
+        }
+        if let Some(extension) = folder
+            .extensions
+            .iter()
+            .find(|extension| extension.is_empty() || extension.contains(['.', '/', '\\']))
+        {
+            return Err(format!("Invalid extension {:?}; leave out the dot", extension));
+        }
+    }
+    Ok(())
+}
+
+/// Payload of `watch-folder-ingested`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FolderIngest {
+    /// The watched folder, as in the settings
+    folder: String,
+    /// The file that appeared in it
+    source: String,
+    /// The imported copy in the media directory
+    path: String,
+}
+
+/// What a folder looked like at the last scan
+#[derive(Default)]
+struct FolderScan {
+    /// Files already handled or there before the folder was watched, by size
+    seen: HashMap<PathBuf, u64>,
+    /// New files and their size at the last scan; taken once the size holds
+    pending: HashMap<PathBuf, u64>,
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(STATE_FILE))
+}
+
+/// When each folder was last scanned, in Unix milliseconds, so files added
+/// while the app was closed are still ingested
+fn load_last_scans(app: &AppHandle) -> BTreeMap<PathBuf, i64> {
+    state_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| {
+            serde_json::from_str(&content)
+                .map_err(|e| warn!("Failed to parse {}: {}", STATE_FILE, e))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+fn save_last_scans(app: &AppHandle, scans: &BTreeMap<PathBuf, i64>) {
+    let Some(path) = state_path(app) else {
+        return;
+    };
+    let result = serde_json::to_string_pretty(scans)
+        .map_err(|e| format!("Failed to serialize {}: {}", STATE_FILE, e))
+        .and_then(|json| crate::prefs::write_atomic(&path, &json));
+    if let Err(e) = result {
+        warn!("Failed to save watched folder state: {}", e);
+    }
+}
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+/// Matching files in `folder` (not its subfolders) with their size and
+/// modification time
+fn list(folder: &WatchFolder) -> Result<Vec<(PathBuf, u64, i64)>, String> {
+    let entries = fs::read_dir(&folder.path).map_err(|e| format!("Failed to read {}: {}", folder.path.display(), e))?;
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            let path = entry.path();
+            let modified = metadata.modified().map(millis).unwrap_or(0);
+            folder.matches(&path).then_some((path, metadata.len(), modified))
+        })
+        .collect())
+}
+
+/// Files of a newly watched folder that are already handled: everything when
+/// it was never scanned, otherwise what was there by the last scan
+fn baseline(folder: &WatchFolder, last_scan: Option<i64>) -> Result<FolderScan, String> {
+    let seen = list(folder)?
+        .into_iter()
+        .filter(|(_, _, modified)| last_scan.map_or(true, |scanned| *modified <= scanned))
+        .map(|(path, size, _)| (path, size))
+        .collect();
+    Ok(FolderScan {
+        seen,
+        pending: HashMap::new(),
+    })
+}
+
+/// New files of `folder` that kept their size since the previous scan, so
+/// a scanner or download is done writing them
+fn finished_files(folder: &WatchFolder, scan: &mut FolderScan) -> Result<Vec<PathBuf>, String> {
+    let files = list(folder)?;
+    let mut pending = HashMap::new();
+    let mut finished = Vec::new();
+    for (path, size, _) in &files {
+        if scan.seen.get(path) == Some(size) {
+            continue;
+        }
+        if *size > 0 && scan.pending.get(path) == Some(size) {
+            finished.push(path.clone());
+        } else {
+            pending.insert(path.clone(), *size);
+        }
+    }
+    // Forget deleted files, so one saved again under the same name counts as new
+    let present: HashSet<&PathBuf> = files.iter().map(|(path, _, _)| path).collect();
+    scan.seen.retain(|path, _| present.contains(path));
+    scan.pending = pending;
+    Ok(finished)
+}
+
+/// Import `source` from `folder`; run as an `import` job
+pub(crate) async fn ingest(app: &AppHandle, folder: &WatchFolder, source: PathBuf) -> Result<(), String> {
+    let options = CaptureOptions {
+        path: Some(source.clone()),
+        ..CaptureOptions::default()
+    };
+    let path = capture::capture(app, "file", options)
+        .await?
+        .pop()
+        .ok_or("Nothing was imported")?;
+    info!("Ingested {} from watched folder {}", source.display(), folder.path.display());
+    if folder.delete_after_import {
+        if let Err(e) = fs::remove_file(&source) {
+            warn!("Failed to delete {} after importing it: {}", source.display(), e);
+        }
+    }
+
+    let event = FolderIngest {
+        folder: folder.path.to_string_lossy().to_string(),
+        source: source.to_string_lossy().to_string(),
+        path,
+    };
+    if let Err(e) = app.emit("watch-folder-ingested", event) {
+        warn!("Failed to emit watch-folder-ingested: {}", e);
+    }
+    Ok(())
+}
+
+/// Scan the folders of the current settings every `POLL_INTERVAL`. Folders
+/// added to the settings are picked up on the next scan.
+pub fn init_watch_folders(app: &AppHandle) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let mut last_scans = load_last_scans(&app);
+        let mut last_saved = Instant::now();
+        let mut scans: HashMap<PathBuf, FolderScan> = HashMap::new();
+        loop {
+            let folders = app.state::<SettingsState>().current().media.watch_folders;
+            scans.retain(|path, _| folders.iter().any(|folder| &folder.path == path));
+            last_scans.retain(|path, _| folders.iter().any(|folder| &folder.path == path));
+
+            let mut ingested = false;
+            for folder in folders {
+                let scan_started = millis(SystemTime::now());
+                let previous = scans.remove(&folder.path);
+                let last_scan = last_scans.get(&folder.path).copied();
+                let scanned = {
+                    let folder = folder.clone();
+                    async_runtime::spawn_blocking(move || {
+                        // A folder that can't be read (e.g. an unmounted share) gets a new baseline once it can
+                        let mut scan = match previous {
+                            Some(scan) => scan,
+                            None => baseline(&folder, last_scan)?,
+                        };
+                        finished_files(&folder, &mut scan).map(|finished| (scan, finished))
+                    })
+                    .await
+                };
+                let (mut scan, finished) = match scanned {
+                    Ok(Ok(scanned)) => scanned,
+                    Ok(Err(e)) => {
+                        debug!("Skipping watched folder: {}", e);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Watched folder task failed: {}", e);
+                        continue;
+                    }
+                };
+                for source in finished {
+                    let size = fs::metadata(&source).map(|metadata| metadata.len()).unwrap_or(0);
+                    let job = JobKind::Import {
+                        folder: folder.clone(),
+                        source: source.clone(),
+                    };
+                    if let Err(e) = jobs::enqueue(&app, job, Priority::Normal) {
+                        warn!("Failed to queue {} for import: {}", source.display(), e);
+                    }
+                    // Not retried, so a file that can't be imported doesn't fail every scan
+                    scan.seen.insert(source, size);
+                    ingested = true;
+                }
+                // Files still being written must not count as seen after a restart
+                if scan.pending.is_empty() {
+                    last_scans.insert(folder.path.clone(), scan_started);
+                }
+                scans.insert(folder.path, scan);
+            }
+            if ingested || last_saved.elapsed() >= SAVE_INTERVAL {
+                save_last_scans(&app, &last_scans);
+                last_saved = Instant::now();
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    });
+}
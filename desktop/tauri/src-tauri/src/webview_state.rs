@@ -0,0 +1,125 @@
+//! A copy of the webview's localStorage, where the frontend caches drafts,
+//! kept in the data dir. It's backed up and restored with the database, and
+//! its dataset ID changes whenever the data under it is replaced, which tells
+//! the frontend to drop what it cached for the old data.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::{data_dir, prefs};
+
+/// Hidden, so it isn't mistaken for user data
+pub(crate) const STATE_FILE: &str = ".webview-state.json";
+
+/// localStorage itself is capped at a few MB
+pub(crate) const MAX_STATE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Serializes reads and writes of the state file
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebviewState {
+    /// Changes when the data dir's contents are replaced, e.g. by a restore
+    pub dataset_id: String,
+    /// localStorage keys and values
+    pub items: BTreeMap<String, String>,
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+pub(crate) fn state_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE)
+}
+
+fn new_dataset_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A missing or unreadable file is an empty state
+fn read(path: &Path) -> WebviewState {
+    let Ok(text) = fs::read_to_string(path) else {
+        return WebviewState::default();
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        warn!("Failed to parse {}: {}", STATE_FILE, e);
+        WebviewState::default()
+    })
+}
+
+fn write(path: &Path, state: &WebviewState) -> Result<(), String> {
+    let json = serde_json::to_string(state).map_err(|e| format!("Failed to serialize {}: {}", STATE_FILE, e))?;
+    if json.len() as u64 > MAX_STATE_BYTES {
+        return Err("The webview state is too large to save".to_string());
+    }
+    prefs::write_atomic(path, &json)
+}
+
+/// Parse a state file taken from a backup archive
+pub(crate) fn parse(text: &str) -> Result<WebviewState, String> {
+    serde_json::from_str(text).map_err(|e| format!("Corrupt {}: {}", STATE_FILE, e))
+}
+
+/// Put `items` (nothing when `None`) in place under a new dataset ID and
+/// tell the webview to replace its localStorage with them. Called once the
+/// database has been replaced.
+pub(crate) fn replace(app: &AppHandle, items: Option<BTreeMap<String, String>>) {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let state = WebviewState {
+        dataset_id: new_dataset_id(),
+        items: items.unwrap_or_default(),
+        saved_at: Some(Utc::now()),
+    };
+    if let Err(e) = write(&state_path(&data_dir::current_data_dir(app)), &state) {
+        warn!("Failed to write {}: {}", STATE_FILE, e);
+    }
+    info!("Webview state replaced ({} items)", state.items.len());
+    if let Err(e) = app.emit("webview-state-reset", &state) {
+        warn!("Failed to emit webview-state-reset event: {}", e);
+    }
+}
+
+/// The saved copy of localStorage. The frontend compares `datasetId` with the
+/// one it last saw and, if they differ, replaces its localStorage with `items`.
+#[tauri::command]
+pub async fn get_webview_state(app: AppHandle) -> Result<WebviewState, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = state_path(&data_dir::current_data_dir(&app));
+    let mut state = read(&path);
+    if state.dataset_id.is_empty() {
+        state.dataset_id = new_dataset_id();
+        write(&path, &state)?;
+    }
+    Ok(state)
+}
+
+/// Save the webview's localStorage. Refused when `datasetId` is stale, so a
+/// webview that hasn't seen a restore yet can't write the old drafts back.
+#[tauri::command]
+pub async fn save_webview_state(
+    app: AppHandle,
+    dataset_id: String,
+    items: BTreeMap<String, String>,
+) -> Result<(), String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = state_path(&data_dir::current_data_dir(&app));
+    let current = read(&path);
+    if !current.dataset_id.is_empty() && current.dataset_id != dataset_id {
+        return Err("The data was replaced; reload the webview state first".to_string());
+    }
+    let state = WebviewState {
+        dataset_id,
+        items,
+        saved_at: Some(Utc::now()),
+    };
+    write(&path, &state)
+}
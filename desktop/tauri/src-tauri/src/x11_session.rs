@@ -0,0 +1,109 @@
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_uchar, c_ulong};
+use std::sync::OnceLock;
+
+use log::warn;
+use x11_dl::xlib::{Atom, Display, Window, Xlib, XA_STRING};
+use x11_dl::xss::Xss;
+
+// libX11/libXss are loaded at runtime so Wayland-only systems without them still start
+static XLIB: OnceLock<Option<Xlib>> = OnceLock::new();
+static XSS: OnceLock<Option<Xss>> = OnceLock::new();
+
+pub fn xlib() -> Option<&'static Xlib> {
+    XLIB.get_or_init(|| {
+        Xlib::open()
+            .map_err(|e| warn!("libX11 not available: {}", e))
+            .ok()
+    })
+    .as_ref()
+}
+
+pub fn xss() -> Option<&'static Xss> {
+    XSS.get_or_init(|| {
+        Xss::open()
+            .map_err(|e| warn!("libXss not available: {}", e))
+            .ok()
+    })
+    .as_ref()
+}
+
+/// Run `f` with a short-lived connection to the X server, if one is reachable
+pub fn with_display<T>(f: impl FnOnce(&Xlib, *mut x11_dl::xlib::Display) -> Option<T>) -> Option<T> {
+    let xlib = xlib()?;
+    unsafe {
+        let display = (xlib.XOpenDisplay)(std::ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let result = f(xlib, display);
+        (xlib.XCloseDisplay)(display);
+        result
+    }
+}
+
+/// Raw value of a window property, with its format (8, 16 or 32) and item count
+pub unsafe fn property(
+    xlib: &Xlib,
+    display: *mut Display,
+    window: Window,
+    name: &CStr,
+    req_type: Atom,
+) -> Option<(Vec<u8>, c_int, c_ulong)> {
+    let atom = (xlib.XInternAtom)(display, name.as_ptr(), 1);
+    if atom == 0 {
+        return None;
+    }
+    let mut actual_type: Atom = 0;
+    let mut actual_format: c_int = 0;
+    let mut item_count: c_ulong = 0;
+    let mut bytes_after: c_ulong = 0;
+    let mut data: *mut c_uchar = std::ptr::null_mut();
+    let status = (xlib.XGetWindowProperty)(
+        display,
+        window,
+        atom,
+        0,
+        1024,
+        0,
+        req_type,
+        &mut actual_type,
+        &mut actual_format,
+        &mut item_count,
+        &mut bytes_after,
+        &mut data,
+    );
+    if status != 0 || data.is_null() {
+        return None;
+    }
+    // Format-32 properties are returned as arrays of C longs
+    let item_size = match actual_format {
+        8 => 1,
+        16 => 2,
+        32 => std::mem::size_of::<c_ulong>(),
+        _ => 0,
+    };
+    let bytes = std::slice::from_raw_parts(data, item_count as usize * item_size).to_vec();
+    (xlib.XFree)(data.cast());
+    Some((bytes, actual_format, item_count))
+}
+
+/// The window's title, preferring the UTF-8 EWMH name
+pub unsafe fn window_title(xlib: &Xlib, display: *mut Display, window: Window) -> Option<String> {
+    let utf8_string = (xlib.XInternAtom)(display, c"UTF8_STRING".as_ptr(), 0);
+    property(xlib, display, window, c"_NET_WM_NAME", utf8_string)
+        .or_else(|| property(xlib, display, window, c"WM_NAME", XA_STRING))
+        .map(|(bytes, _, _)| String::from_utf8_lossy(&bytes).to_string())
+        .filter(|title| !title.is_empty())
+}
+
+/// The app a window belongs to. WM_CLASS is "instance\0class\0"; the class is
+/// the human-facing app name.
+pub unsafe fn window_class(xlib: &Xlib, display: *mut Display, window: Window) -> Option<String> {
+    property(xlib, display, window, c"WM_CLASS", XA_STRING).and_then(|(bytes, _, _)| {
+        bytes
+            .split(|byte| *byte == 0)
+            .rfind(|part| !part.is_empty())
+            .map(|part| String::from_utf8_lossy(part).to_string())
+    })
+}
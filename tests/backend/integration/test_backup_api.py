@@ -587,3 +587,49 @@ class TestBackupFullRestore:
 
         # Production returns 422 when missing required file parameter
         assert response.status_code == 422
+
+
+@pytest.mark.integration
+class TestBackupSnapshot:
+    """Test POST /api/backup/snapshot, used by the desktop shell's scheduled backups."""
+
+    def test_snapshot_requires_desktop_token(self, client: TestClient):
+        """Requests without the shell's token are refused."""
+        response = client.post('/api/backup/snapshot', json={'name': 'snapshot.db'})
+
+        assert response.status_code == 403
+
+    def test_snapshot_refuses_proxied_requests(self, client: TestClient, monkeypatch, tmp_path):
+        """Requests the shell proxies for the webview carry the token but aren't the shell's own."""
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        monkeypatch.setenv('BACKUPS_DIR', str(tmp_path))
+        response = client.post(
+            '/api/backup/snapshot',
+            json={'name': 'snapshot.db'},
+            headers={'X-Desktop-Token': 'secret', 'X-Desktop-Proxied': '1'},
+        )
+
+        assert response.status_code == 403
+        assert not (tmp_path / 'snapshot.db').exists()
+
+    @pytest.mark.parametrize('name', ['/tmp/snapshot.db', '../snapshot.db', 'nested/snapshot.db', 'snapshot.txt', '..'])
+    def test_snapshot_rejects_anything_but_a_file_name(self, client: TestClient, monkeypatch, tmp_path, name):
+        """Snapshots only go in the backups directory."""
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        monkeypatch.setenv('BACKUPS_DIR', str(tmp_path))
+        response = client.post('/api/backup/snapshot', json={'name': name}, headers={'X-Desktop-Token': 'secret'})
+
+        assert response.status_code == 400
+
+    def test_snapshot_refuses_to_overwrite(self, client: TestClient, monkeypatch, tmp_path):
+        """An existing file is never replaced."""
+        existing = tmp_path / 'snapshot.db'
+        existing.write_text('keep me')
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        monkeypatch.setenv('BACKUPS_DIR', str(tmp_path))
+        response = client.post(
+            '/api/backup/snapshot', json={'name': 'snapshot.db'}, headers={'X-Desktop-Token': 'secret'}
+        )
+
+        assert response.status_code == 400
+        assert existing.read_text() == 'keep me'
@@ -0,0 +1,49 @@
+"""
+Integration tests for the desktop shell token check.
+"""
+
+import pytest
+from fastapi.testclient import TestClient
+
+
+@pytest.mark.integration
+class TestDesktopToken:
+    """Requests proxied by the desktop shell carry X-Desktop-Token."""
+
+    def test_matching_token_is_accepted(self, client: TestClient, monkeypatch):
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        response = client.get('/api/labels/', headers={'X-Desktop-Token': 'secret'})
+
+        assert response.status_code == 200
+
+    def test_wrong_token_is_rejected(self, client: TestClient, monkeypatch):
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        response = client.get('/api/labels/', headers={'X-Desktop-Token': 'guess'})
+
+        assert response.status_code == 401
+        assert response.json()['detail'] == 'Invalid desktop token'
+
+    def test_token_without_configured_secret_is_rejected(self, client: TestClient, monkeypatch):
+        monkeypatch.delenv('DESKTOP_SHELL_TOKEN', raising=False)
+        response = client.get('/api/labels/', headers={'X-Desktop-Token': 'anything'})
+
+        assert response.status_code == 401
+
+    def test_missing_token_is_rejected(self, client: TestClient, monkeypatch):
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        response = client.get('/api/labels/')
+
+        assert response.status_code == 401
+        assert response.json()['detail'] == 'Missing desktop token'
+
+    def test_requests_without_token_are_served_without_the_shell(self, client: TestClient, monkeypatch):
+        monkeypatch.delenv('DESKTOP_SHELL_TOKEN', raising=False)
+        response = client.get('/api/labels/')
+
+        assert response.status_code == 200
+
+    def test_health_needs_no_token(self, client: TestClient, monkeypatch):
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        response = client.get('/health')
+
+        assert response.status_code == 200
@@ -0,0 +1,50 @@
+"""
+Integration tests for the desktop event WebSocket.
+"""
+
+import pytest
+from fastapi.testclient import TestClient
+
+
+ENTRY = {'content': 'Hello', 'content_type': 'rich_text'}
+
+
+@pytest.mark.integration
+class TestEventSocket:
+    """Entry changes are published on /api/events."""
+
+    def test_entry_created_is_published(self, client: TestClient):
+        with client.websocket_connect('/api/events') as websocket:
+            response = client.post('/api/entries/note/2024-01-15', json=ENTRY)
+            assert response.status_code == 201
+
+            message = websocket.receive_json()
+
+        assert message == {'type': 'entry-updated', 'data': {'id': response.json()['id'], 'action': 'created'}}
+
+    def test_entry_deleted_is_published(self, client: TestClient):
+        response = client.post('/api/entries/note/2024-01-15', json=ENTRY)
+        entry_id = response.json()['id']
+
+        with client.websocket_connect('/api/events') as websocket:
+            assert client.delete(f'/api/entries/{entry_id}').status_code == 204
+
+            message = websocket.receive_json()
+
+        assert message == {'type': 'entry-updated', 'data': {'id': entry_id, 'action': 'deleted'}}
+
+    def test_wrong_token_is_refused(self, client: TestClient, monkeypatch):
+        from starlette.websockets import WebSocketDisconnect
+
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        with pytest.raises(WebSocketDisconnect):
+            with client.websocket_connect('/api/events', headers={'X-Desktop-Token': 'guess'}) as websocket:
+                websocket.receive_json()
+
+    def test_missing_token_is_refused(self, client: TestClient, monkeypatch):
+        from starlette.websockets import WebSocketDisconnect
+
+        monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+        with pytest.raises(WebSocketDisconnect):
+            with client.websocket_connect('/api/events') as websocket:
+                websocket.receive_json()
@@ -93,3 +93,21 @@ class TestNotesAPI:
         assert response.status_code == 200
         data = response.json()
         assert [note['date'] for note in data] == ['2025-11-01', '2025-11-15']
+
+    def test_get_note_span(self, client: TestClient, db_session: Session):
+        """GET /api/notes/span should return the first and last note dates."""
+        response = client.get('/api/notes/span')
+        assert response.status_code == 200
+        assert response.json() == {'first': None, 'last': None}
+
+        db_session.add_all(
+            [
+                DailyNote(date='2025-11-15'),
+                DailyNote(date='2024-02-01'),
+                DailyNote(date='2025-12-01'),
+            ]
+        )
+        db_session.commit()
+
+        response = client.get('/api/notes/span')
+        assert response.json() == {'first': '2024-02-01', 'last': '2025-12-01'}
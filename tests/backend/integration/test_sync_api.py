@@ -0,0 +1,132 @@
+"""
+Integration tests for /api/sync, the change feed used by LAN sync.
+"""
+
+from datetime import datetime, timedelta
+
+import pytest
+from fastapi.testclient import TestClient
+from sqlalchemy.orm import Session
+
+from app.models import DailyNote, NoteEntry, SyncTombstone
+from app.sync import pinned_copy_id
+
+SHELL = {'X-Desktop-Token': 'secret'}
+
+
+def remote_entry(sync_id: str, updated_at: datetime, **fields) -> dict:
+    change = {
+        'sync_id': sync_id,
+        'date': '2025-11-15',
+        'title': 'From the laptop',
+        'content': '<p>Hello</p>',
+        'labels': [{'name': 'work', 'color': '#ff0000'}],
+        'created_at': updated_at.isoformat(),
+        'updated_at': updated_at.isoformat(),
+    }
+    change.update(fields)
+    return change
+
+
+@pytest.fixture
+def shell_token(monkeypatch):
+    monkeypatch.setenv('DESKTOP_SHELL_TOKEN', 'secret')
+
+
+@pytest.mark.integration
+class TestSyncAPI:
+    """Test GET /api/sync/changes and POST /api/sync/apply."""
+
+    def test_sync_requires_desktop_token(self, client: TestClient):
+        """Only the desktop shell can read or apply changes."""
+        assert client.get('/api/sync/changes').status_code == 403
+        assert client.post('/api/sync/apply', json={}).status_code == 403
+
+    def test_changes_include_entries_and_tombstones(self, client: TestClient, db_session: Session, shell_token):
+        """Entries carry their date and labels; deleted entries leave a tombstone."""
+        note = DailyNote(date='2025-11-15')
+        db_session.add(note)
+        db_session.flush()
+        kept = NoteEntry(daily_note_id=note.id, title='Kept', content='<p>kept</p>')
+        gone = NoteEntry(daily_note_id=note.id, title='Gone', content='<p>gone</p>')
+        db_session.add_all([kept, gone])
+        db_session.commit()
+        gone_sync_id = gone.sync_id
+
+        assert client.delete(f'/api/entries/{gone.id}', headers=SHELL).status_code == 204
+
+        data = client.get('/api/sync/changes', headers=SHELL).json()
+        assert [entry['sync_id'] for entry in data['entries']] == [kept.sync_id]
+        assert data['entries'][0]['date'] == '2025-11-15'
+        assert [tombstone['sync_id'] for tombstone in data['tombstones']] == [gone_sync_id]
+
+    def test_changes_since_cursor(self, client: TestClient, db_session: Session, shell_token):
+        """Passing the previous `now` returns only later changes."""
+        note = DailyNote(date='2025-11-15')
+        db_session.add(note)
+        db_session.flush()
+        db_session.add(NoteEntry(daily_note_id=note.id, content='<p>old</p>'))
+        db_session.commit()
+
+        cursor = client.get('/api/sync/changes', headers=SHELL).json()['now']
+        assert client.get('/api/sync/changes', params={'since': cursor}, headers=SHELL).json()['entries'] == []
+
+    def test_apply_creates_entry_with_note_and_labels(self, client: TestClient, db_session: Session, shell_token):
+        """A new remote entry is created on its date with its labels, keeping its timestamp."""
+        updated_at = datetime(2025, 11, 15, 9, 30)
+        response = client.post('/api/sync/apply', json={'entries': [remote_entry('abc', updated_at)]}, headers=SHELL)
+
+        assert response.status_code == 200
+        assert response.json() == {'created': 1, 'updated': 0, 'deleted': 0, 'skipped': 0}
+        entry = db_session.query(NoteEntry).filter(NoteEntry.sync_id == 'abc').one()
+        assert entry.daily_note.date == '2025-11-15'
+        assert [label.name for label in entry.labels] == ['work']
+        assert entry.updated_at == updated_at
+
+    def test_apply_keeps_newer_local_edit(self, client: TestClient, db_session: Session, shell_token):
+        """An older remote change doesn't overwrite a newer local one."""
+        note = DailyNote(date='2025-11-15')
+        db_session.add(note)
+        db_session.flush()
+        local = NoteEntry(daily_note_id=note.id, content='<p>local</p>', sync_id='abc')
+        db_session.add(local)
+        db_session.commit()
+
+        stale = local.updated_at - timedelta(hours=1)
+        response = client.post('/api/sync/apply', json={'entries': [remote_entry('abc', stale)]}, headers=SHELL)
+
+        assert response.json()['skipped'] == 1
+        db_session.refresh(local)
+        assert local.content == '<p>local</p>'
+
+    def test_apply_tombstone_deletes_entry(self, client: TestClient, db_session: Session, shell_token):
+        """A remote deletion removes the entry when it wasn't edited here since."""
+        note = DailyNote(date='2025-11-15')
+        db_session.add(note)
+        db_session.flush()
+        db_session.add(NoteEntry(daily_note_id=note.id, content='<p>doomed</p>', sync_id='abc'))
+        db_session.commit()
+
+        deleted_at = datetime.utcnow() + timedelta(minutes=1)
+        response = client.post(
+            '/api/sync/apply',
+            json={'tombstones': [{'sync_id': 'abc', 'deleted_at': deleted_at.isoformat()}]},
+            headers=SHELL,
+        )
+
+        assert response.json()['deleted'] == 1
+        assert db_session.query(NoteEntry).filter(NoteEntry.sync_id == 'abc').count() == 0
+        assert db_session.get(SyncTombstone, 'abc') is not None
+
+    def test_pinned_copies_share_sync_id(self, client: TestClient, db_session: Session):
+        """Copies of a pinned entry get an ID derived from date and content, the same on every install."""
+        note = DailyNote(date='2025-11-14')
+        db_session.add(note)
+        db_session.flush()
+        db_session.add(NoteEntry(daily_note_id=note.id, title='Daily', content='<p>standup</p>', is_pinned=1))
+        db_session.commit()
+
+        first = client.get('/api/entries/note/2025-11-15').json()
+        copy = db_session.query(NoteEntry).filter(NoteEntry.id == first[0]['id']).one()
+
+        assert copy.sync_id == pinned_copy_id('2025-11-15', 'Daily', '<p>standup</p>')
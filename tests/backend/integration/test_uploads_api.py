@@ -57,6 +57,14 @@ class TestUploadsAPI:
         response = client.post('/api/uploads/image', files=payload)
         assert response.status_code == 400
 
+    def test_list_files_reports_names_and_sizes(self, client: TestClient):
+        """The file list should name each stored upload with its size."""
+        stored = client.post('/api/uploads/file', files={'file': ('a.txt', b'abc', 'text/plain')}).json()
+
+        response = client.get('/api/uploads/files')
+        assert response.status_code == 200
+        assert response.json() == [{'filename': stored['url'].split('/')[-1], 'size': 3}]
+
     def test_download_all_files_returns_zip(self, client: TestClient):
         """download-all should stream a zip containing uploaded files."""
         files = {'file': ('doc.txt', b'hello', 'text/plain')}
@@ -92,3 +100,47 @@ class TestUploadsAPI:
         skip_response = client.post('/api/uploads/restore-files', files=files)
         assert skip_response.status_code == 200
         assert skip_response.json()['stats']['skipped'] == 2
+
+    def test_resumable_upload_in_chunks(self, client: TestClient, temp_upload_dir: Path):
+        """Chunks appended in order should complete into a regular upload."""
+        payload = b'0123456789'
+        created = client.post('/api/uploads/resumable', json={'filename': 'screen.mov', 'size': len(payload)})
+        assert created.status_code == 201
+        upload_id = created.json()['id']
+
+        first = client.put(
+            f'/api/uploads/resumable/{upload_id}', content=payload[:6], headers={'Content-Range': 'bytes 0-5/10'}
+        )
+        assert first.status_code == 200
+        assert first.json()['offset'] == 6
+        assert client.get(f'/api/uploads/resumable/{upload_id}').json()['offset'] == 6
+
+        last = client.put(
+            f'/api/uploads/resumable/{upload_id}', content=payload[6:], headers={'Content-Range': 'bytes 6-9/10'}
+        )
+        assert last.status_code == 200
+        data = last.json()
+        assert data['filename'] == 'screen.mov'
+        assert data['size'] == len(payload)
+        stored_name = data['url'].split('/')[-1]
+        assert (temp_upload_dir / stored_name).read_bytes() == payload
+        assert client.get(f'/api/uploads/resumable/{upload_id}').status_code == 404
+
+    def test_resumable_upload_refuses_chunk_at_wrong_offset(self, client: TestClient):
+        """A chunk that skips ahead should be refused with the offset to resume from."""
+        created = client.post('/api/uploads/resumable', json={'filename': 'clip.mp4', 'size': 8})
+        upload_id = created.json()['id']
+        client.put(f'/api/uploads/resumable/{upload_id}', content=b'abcd', headers={'Content-Range': 'bytes 0-3/8'})
+
+        response = client.put(
+            f'/api/uploads/resumable/{upload_id}', content=b'gh', headers={'Content-Range': 'bytes 6-7/8'}
+        )
+        assert response.status_code == 409
+        assert response.json()['offset'] == 4
+
+    def test_download_all_skips_unfinished_uploads(self, client: TestClient):
+        """Partial resumable uploads should not end up in the zip."""
+        client.post('/api/uploads/resumable', json={'filename': 'big.mov', 'size': 100})
+
+        response = client.get('/api/uploads/download-all')
+        assert response.status_code == 404